@@ -0,0 +1,61 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use std::str::FromStr;
+use ubi::{ForgeType, UbiBuilder};
+
+pub(crate) fn ensure_cmd() -> Command {
+    Command::new("ensure")
+        .about(concat!(
+            "Install a project's executable unless it is already at the release's tag. The tag that",
+            " was installed is recorded in a .ubi-meta file in the install directory, so running",
+            " this again is a no-op until the tag changes.",
+        ))
+        .arg(Arg::new("project").required(true).help(concat!(
+            "The project to install, like houseabsolute/precious. Add a tag to install a specific",
+            " release, like houseabsolute/precious@v0.7.0. Otherwise, the latest release is used.",
+        )))
+        .arg(Arg::new("exe").long("exe").short('e').help(concat!(
+            "The name of the file to look for in an archive file, or the name of the downloadable",
+            " file excluding its extension. This has the same default as the top-level --exe",
+            " option.",
+        )))
+        .arg(Arg::new("in").long("in").short('i').help(concat!(
+            "The directory in which the binary should be placed. This has the same default as",
+            " the top-level --in option.",
+        )))
+        .arg(crate::forge_arg())
+        .arg(crate::use_keyring_arg())
+        .arg(crate::api_base_url_arg())
+        .arg(crate::gitlab_url_arg())
+}
+
+pub(crate) async fn ensure(matches: &ArgMatches) -> Result<()> {
+    let mut builder = UbiBuilder::new()
+        .project(
+            matches
+                .get_one::<String>("project")
+                .expect("clap requires a project"),
+        )
+        .ensure();
+    if let Some(e) = matches.get_one::<String>("exe") {
+        builder = builder.exe(e);
+    }
+    if let Some(dir) = matches.get_one::<String>("in") {
+        builder = builder.install_dir(dir);
+    }
+    if let Some(ft) = matches.get_one::<String>("forge") {
+        builder = builder.forge(ForgeType::from_str(ft)?);
+    }
+    if let Some(url) = matches.get_one::<String>("api-base-url") {
+        builder = builder.api_base_url(url);
+    }
+    if let Some(url) = matches.get_one::<String>("gitlab-url") {
+        builder = builder.gitlab_url(url);
+    }
+    if matches.get_flag("use-keyring") {
+        builder = builder.use_keyring();
+    }
+
+    builder.build()?.install_binary().await?;
+    Ok(())
+}
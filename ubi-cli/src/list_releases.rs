@@ -0,0 +1,140 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use std::str::FromStr;
+use ubi::{ForgeType, Release, UbiBuilder};
+
+pub(crate) fn list_releases_cmd() -> Command {
+    Command::new("list-releases")
+        .about("List the releases for a project, newest first.")
+        .arg(Arg::new("project").required(true).help(concat!(
+            "The project to list releases for, like houseabsolute/precious or",
+            " https://github.com/houseabsolute/precious.",
+        )))
+        .arg(crate::forge_arg())
+        .arg(crate::use_keyring_arg())
+        .arg(crate::api_base_url_arg())
+        .arg(crate::gitlab_url_arg())
+}
+
+pub(crate) async fn list_releases(matches: &ArgMatches) -> Result<()> {
+    let mut builder = UbiBuilder::new().project(
+        matches
+            .get_one::<String>("project")
+            .expect("clap requires a project"),
+    );
+    if let Some(ft) = matches.get_one::<String>("forge") {
+        builder = builder.forge(ForgeType::from_str(ft)?);
+    }
+    if let Some(url) = matches.get_one::<String>("api-base-url") {
+        builder = builder.api_base_url(url);
+    }
+    if let Some(url) = matches.get_one::<String>("gitlab-url") {
+        builder = builder.gitlab_url(url);
+    }
+    if matches.get_flag("use-keyring") {
+        builder = builder.use_keyring();
+    }
+
+    let releases = builder.build()?.list_releases().await?;
+    for line in format_releases(&releases) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+// Each line has the tag, the publish date, and whether it's a prerelease or draft, lined up in
+// columns.
+fn format_releases(releases: &[Release]) -> Vec<String> {
+    let tag_width = releases.iter().map(|r| r.tag.len()).max().unwrap_or(0);
+    let date_width = releases
+        .iter()
+        .map(|r| published_at(r).len())
+        .max()
+        .unwrap_or(0);
+    releases
+        .iter()
+        .map(|r| {
+            let mut flags = vec![];
+            if r.prerelease {
+                flags.push("prerelease");
+            }
+            if r.draft {
+                flags.push("draft");
+            }
+            let line = format!(
+                "{:tag_width$}  {:date_width$}  {}",
+                r.tag,
+                published_at(r),
+                flags.join(", "),
+            );
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+fn published_at(release: &Release) -> &str {
+    release.published_at.as_deref().unwrap_or("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn release(tag: &str, prerelease: bool, draft: bool, published_at: Option<&str>) -> Release {
+        Release {
+            tag: tag.to_string(),
+            prerelease,
+            draft,
+            published_at: published_at.map(String::from),
+        }
+    }
+
+    #[test]
+    fn format_releases() {
+        let releases = [
+            release("v2.0.0", false, true, None),
+            release("v2.0.0-rc.1", true, false, Some("2025-04-02T12:00:00Z")),
+            release("v1.0.0", false, false, Some("2025-03-18T21:03:02Z")),
+        ];
+        assert_eq!(
+            super::format_releases(&releases),
+            [
+                "v2.0.0       -                     draft",
+                "v2.0.0-rc.1  2025-04-02T12:00:00Z  prerelease",
+                "v1.0.0       2025-03-18T21:03:02Z",
+            ],
+        );
+    }
+
+    #[test]
+    fn format_releases_with_no_releases() {
+        assert!(super::format_releases(&[]).is_empty());
+    }
+
+    #[test_case(&["ubi", "list-releases", "houseabsolute/precious"], None ; "project only")]
+    #[test_case(
+        &["ubi", "list-releases", "houseabsolute/precious", "--forge", "gitlab"],
+        Some("gitlab") ;
+        "with forge"
+    )]
+    fn list_releases_cmd_parses(args: &[&str], expect_forge: Option<&str>) {
+        let matches = crate::cmd().try_get_matches_from(args).unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "list-releases");
+        assert_eq!(
+            matches.get_one::<String>("project").map(String::as_str),
+            Some("houseabsolute/precious"),
+        );
+        assert_eq!(
+            matches.get_one::<String>("forge").map(String::as_str),
+            expect_forge,
+        );
+    }
+
+    #[test_case(&["ubi", "list-releases"] ; "missing project")]
+    #[test_case(&["ubi", "list-releases", "houseabsolute/precious", "--tag", "v1.0.0"] ; "with install args")]
+    fn list_releases_cmd_errors(args: &[&str]) {
+        assert!(crate::cmd().try_get_matches_from(args).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use clap::ArgMatches;
+use fern::Dispatch;
+use log::LevelFilter;
+use serde_json::json;
+use std::str::FromStr;
+use strum::{AsRefStr, EnumString, VariantNames};
+use ubi::ColorChoice;
+
+#[derive(AsRefStr, Clone, Copy, Debug, Default, EnumString, PartialEq, Eq, VariantNames)]
+pub(crate) enum LogFormat {
+    #[strum(serialize = "text")]
+    #[default]
+    Text,
+    #[strum(serialize = "json")]
+    Json,
+}
+
+pub(crate) fn init_logger_from_matches(matches: &ArgMatches) -> Result<()> {
+    let level = level_from_matches(matches);
+    let format = matches
+        .get_one::<String>("log-format")
+        .map(|f| LogFormat::from_str(f))
+        .transpose()?
+        .unwrap_or_default();
+
+    match format {
+        LogFormat::Text => ubi::init_logger(level, color_from_matches(matches))?,
+        LogFormat::Json => init_json_logger(level)?,
+    }
+    Ok(())
+}
+
+// The possible values are checked by clap, so parsing can't fail.
+pub(crate) fn color_from_matches(matches: &ArgMatches) -> ColorChoice {
+    matches
+        .get_one::<String>("color")
+        .and_then(|c| ColorChoice::from_str(c).ok())
+        .unwrap_or_default()
+}
+
+fn level_from_matches(matches: &ArgMatches) -> LevelFilter {
+    level_from_flags(
+        matches.get_flag("quiet"),
+        matches.get_count("verbose"),
+        matches.get_flag("debug"),
+    )
+}
+
+// The `--debug` flag predates `-v` being repeatable, and it is the same as passing `-v` once.
+fn level_from_flags(quiet: bool, verbose: u8, debug: bool) -> LevelFilter {
+    if quiet {
+        return LevelFilter::Error;
+    }
+    match (verbose, debug) {
+        (0, false) => LevelFilter::Warn,
+        (0 | 1, _) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+fn init_json_logger(level: LevelFilter) -> Result<(), log::SetLoggerError> {
+    Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}",
+                json_record(record.level(), record.target(), &message.to_string()),
+            ));
+        })
+        .level(level)
+        // This is very noisy.
+        .level_for("hyper", LevelFilter::Error)
+        .chain(std::io::stderr())
+        .apply()
+}
+
+fn json_record(level: log::Level, target: &str, message: &str) -> serde_json::Value {
+    json!({
+        "level": level.as_str(),
+        "target": target,
+        "message": message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(false, 0, false, LevelFilter::Warn ; "no flags")]
+    #[test_case(true, 0, false, LevelFilter::Error ; "quiet")]
+    #[test_case(false, 1, false, LevelFilter::Debug ; "one verbose")]
+    #[test_case(false, 2, false, LevelFilter::Trace ; "two verbose")]
+    #[test_case(false, 3, false, LevelFilter::Trace ; "three verbose")]
+    #[test_case(false, 0, true, LevelFilter::Debug ; "debug")]
+    #[test_case(false, 1, true, LevelFilter::Debug ; "debug and one verbose")]
+    #[test_case(false, 2, true, LevelFilter::Trace ; "debug and two verbose")]
+    fn level_from_flags(quiet: bool, verbose: u8, debug: bool, expect: LevelFilter) {
+        assert_eq!(super::level_from_flags(quiet, verbose, debug), expect);
+    }
+
+    #[test_case(&[], LevelFilter::Warn ; "no flags")]
+    #[test_case(&["-q"], LevelFilter::Error ; "short quiet")]
+    #[test_case(&["--quiet"], LevelFilter::Error ; "long quiet")]
+    #[test_case(&["-v"], LevelFilter::Debug ; "short verbose")]
+    #[test_case(&["-vv"], LevelFilter::Trace ; "short verbose twice")]
+    #[test_case(&["--verbose", "--verbose"], LevelFilter::Trace ; "long verbose twice")]
+    #[test_case(&["-d"], LevelFilter::Debug ; "short debug")]
+    fn level_from_cli_flags(flags: &[&str], expect: LevelFilter) {
+        let matches = crate::cmd()
+            .try_get_matches_from(
+                ["ubi", "--project", "houseabsolute/precious"]
+                    .iter()
+                    .chain(flags),
+            )
+            .unwrap();
+        assert_eq!(level_from_matches(&matches), expect);
+    }
+
+    #[test_case(&[], ColorChoice::Auto ; "no flag")]
+    #[test_case(&["--color", "auto"], ColorChoice::Auto ; "auto")]
+    #[test_case(&["--color", "always"], ColorChoice::Always ; "always")]
+    #[test_case(&["--color", "never"], ColorChoice::Never ; "never")]
+    fn color_from_cli_flags(flags: &[&str], expect: ColorChoice) {
+        let matches = crate::cmd()
+            .try_get_matches_from(
+                ["ubi", "--project", "houseabsolute/precious"]
+                    .iter()
+                    .chain(flags),
+            )
+            .unwrap();
+        assert_eq!(color_from_matches(&matches), expect);
+    }
+
+    #[test]
+    fn invalid_color_flag() {
+        let res = crate::cmd().try_get_matches_from([
+            "ubi",
+            "--project",
+            "houseabsolute/precious",
+            "--color",
+            "sometimes",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn quiet_conflicts_with_verbose() {
+        let res = crate::cmd().try_get_matches_from([
+            "ubi",
+            "--project",
+            "houseabsolute/precious",
+            "-q",
+            "-v",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn json_record() {
+        assert_eq!(
+            super::json_record(log::Level::Info, "ubi::installer", "Installed \"project\""),
+            json!({
+                "level": "INFO",
+                "target": "ubi::installer",
+                "message": "Installed \"project\"",
+            }),
+        );
+    }
+}
@@ -1,10 +1,24 @@
-use anyhow::{anyhow, Error, Result};
-use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
-use log::{debug, error};
-use std::{env, path::Path, str::FromStr};
+mod ensure;
+mod list_releases;
+mod logging;
+mod manifest;
+mod output;
+mod path_hint;
+mod self_test;
+mod token;
+
+use anyhow::{Error, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use log::{error, warn};
+use logging::{init_logger_from_matches, LogFormat};
+use output::{output_format_from_matches, print_json_err, print_json_result, OutputFormat};
+use std::{env, path::Path, str::FromStr, time::Duration};
 use strum::VariantNames;
 use thiserror::Error;
-use ubi::{ForgeType, Ubi, UbiBuilder};
+use ubi::{
+    ColorChoice, ForgeType, InstallPlan, InstallResult, Libc, SelfUpgrade, TlsVersion, Ubi,
+    UbiBuilder,
+};
 
 #[derive(Debug, Error)]
 enum UbiError {
@@ -22,23 +36,95 @@ async fn main() {
         std::process::exit(126);
     }
 
-    // We use this when `--self-upgrade` is passed. We need to create this String here so that we
-    // can make a Ubi<'_> instance that borrows this value. It needs to have the same lifetime as
-    // `matches`. If we try to make it in `self_upgrade_ubi` we end up trying to return a reference
-    // data owned by that fn.
-    let ubi_exe_path = match env::current_exe() {
-        Ok(p) => p,
-        Err(e) => {
-            let e = anyhow!("could not find path for current executable: {e}");
-            print_err(&e);
-            std::process::exit(127);
+    let status = if let Some(("token", token_matches)) = matches.subcommand() {
+        match token::token(token_matches) {
+            Ok(()) => 0,
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        }
+    } else if let Some(("install", install_matches)) = matches.subcommand() {
+        match manifest::install(install_matches).await {
+            Ok(()) => 0,
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        }
+    } else if let Some(("ensure", ensure_matches)) = matches.subcommand() {
+        match ensure::ensure(ensure_matches).await {
+            Ok(()) => 0,
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        }
+    } else if let Some(("list-releases", list_matches)) = matches.subcommand() {
+        match list_releases::list_releases(list_matches).await {
+            Ok(()) => 0,
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        }
+    } else if let Some(("selftest", self_test_matches)) = matches.subcommand() {
+        match self_test::self_test(self_test_matches) {
+            Ok(()) => 0,
+            Err(e) => {
+                print_err(&e);
+                1
+            }
         }
+    } else if matches.get_flag("self-upgrade") {
+        self_upgrade(&matches).await
+    } else {
+        install(&matches).await
     };
-    let status = match make_ubi(&matches, &ubi_exe_path) {
-        Ok((mut u, post_run)) => match u.install_binary().await {
-            Ok(()) => {
-                if let Some(post_run) = post_run {
-                    post_run();
+    std::process::exit(status);
+}
+
+async fn install(matches: &ArgMatches) -> i32 {
+    if output_format_from_matches(matches) == OutputFormat::Json {
+        return install_with_json_output(matches).await;
+    }
+
+    match make_ubi(matches) {
+        Ok(mut u) if matches.get_flag("dry-run") => match u.install_plan().await {
+            Ok(plan) => {
+                print_plan(&plan);
+                0
+            }
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        },
+        Ok(mut u) if matches.get_flag("print-url") => match u.asset_download_url().await {
+            Ok(url) => {
+                println!("{url}");
+                0
+            }
+            Err(e) => {
+                print_err(&e);
+                1
+            }
+        },
+        Ok(mut u) => match u.install_binary().await {
+            Ok(InstallResult {
+                install_path,
+                staged_path: Some(staged_path),
+                ..
+            }) => {
+                print_move_plan(&staged_path, &install_path);
+                0
+            }
+            // The install dir is a temp dir when packaging, so there's nothing to hint about.
+            Ok(_) if matches.contains_id("package-output") => 0,
+            Ok(_) => {
+                let use_color = logging::color_from_matches(matches).use_color_on_stderr();
+                if let Some(hint) = path_hint::path_hint_for(u.install_dir(), use_color) {
+                    warn!("{hint}");
                 }
                 0
             }
@@ -51,8 +137,47 @@ async fn main() {
             print_err(&e);
             127
         }
+    }
+}
+
+// With JSON output, the only thing printed to stdout is the JSON for the result or the error. A dry
+// run uses the same result, without a checksum, since nothing is downloaded.
+async fn install_with_json_output(matches: &ArgMatches) -> i32 {
+    let mut u = match make_ubi(matches) {
+        Ok(u) => u,
+        Err(e) => {
+            print_json_err(&e);
+            return 127;
+        }
     };
-    std::process::exit(status);
+    match u.install_binary().await {
+        Ok(result) => {
+            print_json_result(&result);
+            0
+        }
+        Err(e) => {
+            print_json_err(&e);
+            1
+        }
+    }
+}
+
+async fn self_upgrade(matches: &ArgMatches) -> i32 {
+    if let Err(e) = validate_args(matches) {
+        print_err(&e);
+        return 127;
+    }
+
+    match ubi::self_upgrade(UbiBuilder::new()).await {
+        Ok(upgrade) => {
+            print_self_upgrade(&upgrade);
+            0
+        }
+        Err(e) => {
+            print_err(&e);
+            1
+        }
+    }
 }
 
 const MAX_TERM_WIDTH: usize = 100;
@@ -65,7 +190,12 @@ fn cmd() -> Command {
         .about("The universal binary release installer")
         .arg(Arg::new("project").long("project").short('p').help(concat!(
             "The project you want to install, like houseabsolute/precious",
-            " or https://github.com/houseabsolute/precious.",
+            " or https://github.com/houseabsolute/precious. Add `@<tag>` to the end to install",
+            " that release, like houseabsolute/precious@v0.7.0. The URL of a release page picks that",
+            " release, and the URL of a release asset is downloaded directly, as with --url. For an",
+            " OCI registry, this is an",
+            " image reference like ghcr.io/owner/image:tag. For Azure DevOps, this is",
+            " org/project/repo.",
         )))
         .arg(
             Arg::new("tag")
@@ -73,42 +203,98 @@ fn cmd() -> Command {
                 .short('t')
                 .help("The tag to download. Defaults to the latest release."),
         )
+        .arg(
+            Arg::new("version-req")
+                .long("version-req")
+                .value_name("req")
+                .help(concat!(
+                    "A semver version requirement, like `^1.2` or `>=0.4, <0.6`. The newest",
+                    " release whose tag matches this is downloaded. Tags with a leading `v` are",
+                    " accepted. You cannot combine this with --tag.",
+                )),
+        )
+        .arg(
+            Arg::new("tag-pattern")
+                .long("tag-pattern")
+                .value_name("regex")
+                .help(concat!(
+                    "A regex to match against release tags, for projects that don't use semver",
+                    " tags, like `^release-\\d{4}-\\d{2}$`. The most recently published release",
+                    " whose tag matches this is downloaded. You cannot combine this with --tag or",
+                    " --version-req.",
+                )),
+        )
+        .arg(
+            Arg::new("include-prereleases")
+                .long("include-prereleases")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Consider prereleases when picking the release to download. Without",
+                    " --version-req, this downloads the newest release, even if it is a",
+                    " prerelease. You cannot combine this with --tag.",
+                )),
+        )
         .arg(Arg::new("url").long("url").short('u').help(concat!(
             "The url of the file to download. This can be provided instead of a project or",
             " tag. This will not use the forge site's API, so you will never hit its API",
             " limits. With this parameter, you do not need to set a token env var except for",
-            " private repos."
+            " private repos. This can also be a file:// URL, which is the same as passing",
+            " --local-asset."
         )))
+        .arg(
+            Arg::new("local-asset")
+                .long("local-asset")
+                .value_name("path")
+                .help(concat!(
+                    "The path to a release asset that you have already downloaded. This is",
+                    " installed without making any network requests. Unless you pass --exe, the",
+                    " executable name is taken from the start of the file name, up to the first",
+                    " `-`, `_`, or `.`.",
+                )),
+        )
         .arg(
             Arg::new("self-upgrade")
                 .long("self-upgrade")
                 .action(ArgAction::SetTrue)
                 .help(concat!(
-                    "Use ubi to upgrade to the latest version of ubi. The",
-                    " --exe, --in, --project, --tag, and --url args will be",
-                    " ignored."
+                    "Use ubi to upgrade to the latest version of ubi. The new executable replaces",
+                    " the one that is running, and the old and new versions are printed. You cannot",
+                    " pass --asset-name, --exe, --in, --project, --tag, --tag-pattern, --url, or",
+                    " --version-req when this is set.",
                 )),
         )
         .arg(
             Arg::new("in")
                 .long("in")
+                .visible_alias("install-dir")
                 .short('i')
-                .help("The directory in which the binary should be placed. Defaults to ./bin."),
+                .help(concat!(
+                    "The directory in which the binary should be placed. Defaults to $XDG_BIN_HOME",
+                    " or ~/.local/bin on Unix systems and %LOCALAPPDATA%\\ubi\\bin on Windows. The",
+                    " directory will be created if it does not exist. If it is not in your PATH, ubi",
+                    " prints a hint explaining how to add it.",
+                )),
         )
         .arg(Arg::new("exe").long("exe").short('e').help(concat!(
             "The name of the file to look for in an archive file, or the name of the downloadable",
             " file excluding its extension, e.g. `ubi.gz`. By default this is the same as the",
             " project name, so for houseabsolute/precious we look for precious or",
-            " precious.exe. When running on Windows the `.exe` suffix will be added, as needed. You",
-            " cannot pass `--extract-all` when this is set.",
-        )))
-        .arg(Arg::new("rename-exe-to").long("rename-exe").help(concat!(
-            "The name to use for the executable after it is unpacked. By default this is the same",
-            " as the name of the file passed for the `--exe` flag. If that flag isn't passed, this",
-            " is the same as the name of the project. Note that when set, this name is used as-is,",
-            " so on Windows, `.exe` will not be appended to the name given. You cannot pass",
-            " `--extract-all` when this is set.",
+            " precious.exe. When running on Windows the `.exe` suffix will be added, as needed. This",
+            " can include the directories the file is in, like `bin/precious`, to pick one of",
+            " several files with the same name. You cannot pass `--extract-all` when this is set.",
         )))
+        .arg(
+            Arg::new("rename-exe-to")
+                .long("rename-exe")
+                .visible_alias("rename")
+                .help(concat!(
+                    "The name to use for the executable after it is unpacked. By default this is",
+                    " the same as the name of the file passed for the `--exe` flag. If that flag",
+                    " isn't passed, this is the same as the name of the project. When running on",
+                    " Windows the `.exe` suffix will be added, as needed. You cannot pass",
+                    " `--extract-all` when this is set.",
+                )),
+        )
         .arg(
             Arg::new("extract-all")
                 .long("extract-all")
@@ -124,6 +310,84 @@ fn cmd() -> Command {
                     " when this is set.",
                 )),
         )
+        .arg(
+            Arg::new("strip-components")
+                .long("strip-components")
+                .value_name("count")
+                .value_parser(clap::value_parser!(usize))
+                .requires("extract-all")
+                .help(concat!(
+                    "Remove this many leading path components from each file in the archive when",
+                    " --extract-all is set, like tar's `--strip-components`. Files with no more",
+                    " than this many components are skipped. When this is set, a top-level",
+                    " directory shared by all the files is not otherwise removed.",
+                )),
+        )
+        .arg(
+            Arg::new("all-exes")
+                .long("all-exes")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Install every executable in the archive instead of just one. On Windows,",
+                    " this means files ending in `.bat`, `.cmd`, or `.exe`. Elsewhere, it means",
+                    " files with an executable bit set. The executables are all installed directly",
+                    " in the --in directory. You cannot pass `--exe`, `--rename-exe-to`, or",
+                    " `--extract-all` when this is set.",
+                )),
+        )
+        .arg(
+            Arg::new("exe-glob")
+                .long("exe-glob")
+                .value_name("glob")
+                .requires("all-exes")
+                .help(concat!(
+                    "A glob pattern matched against file names to pick which files are installed",
+                    " when --all-exes is set, like `project-*`. This replaces the default check",
+                    " for executables.",
+                )),
+        )
+        .arg(
+            Arg::new("install-as-is")
+                .long("install-as-is")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["exe", "extract-all", "all-exes"])
+                .help(concat!(
+                    "Install the release asset exactly as it was downloaded, for files that aren't",
+                    " executables, like a `.wasm` module. The file is not extracted or made",
+                    " executable. It's installed with the asset's name, or the --rename-exe-to name.",
+                    " You must also pass --asset-name, --url, or --local-asset.",
+                )),
+        )
+        .arg(
+            Arg::new("versioned-install")
+                .long("versioned-install")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "extract-all",
+                    "all-exes",
+                    "install-as-is",
+                    "url",
+                    "local-asset",
+                    "stage-dir",
+                ])
+                .help(concat!(
+                    "Install the executable with the release's version in its name, like",
+                    " `foo-1.2.3`, and point a `foo` symlink at it. On Windows, this creates a",
+                    " `foo.cmd` shim instead. Older versions are kept, so you can roll back by",
+                    " repointing the link.",
+                )),
+        )
+        .arg(
+            Arg::new("completions-dir")
+                .long("completions-dir")
+                .value_name("dir")
+                .conflicts_with_all(["extract-all", "all-exes", "stage-dir"])
+                .help(concat!(
+                    "Install any shell completion files in the archive into this directory, in a",
+                    " subdirectory for each shell. These are files named like `*.bash`, `*.fish`,",
+                    " `*.zsh`, or `_*` with no extension.",
+                )),
+        )
         .arg(
             Arg::new("matching")
                 .long("matching")
@@ -137,69 +401,551 @@ fn cmd() -> Command {
                 )),
         )
         .arg(
-            Arg::new("forge")
-                .long("forge")
+            Arg::new("matching-regex")
+                .long("matching-regex")
+                .value_name("regex")
+                .help(concat!(
+                    "A regex that release filenames must match, like",
+                    r" `project-.*-linux-(amd64|x86_64)\.tar\.gz`. Unlike --matching, files that",
+                    " don't match this are never picked. If several files match, the usual OS/arch",
+                    " checks pick between them. This cannot be combined with --url.",
+                )),
+        )
+        .arg(
+            Arg::new("libc")
+                .long("libc")
+                .value_parser(clap::builder::PossibleValuesParser::new(Libc::VARIANTS))
+                .help(concat!(
+                    "The libc to pick a release asset for on Linux. By default this is detected",
+                    " from the current platform. Assets built for this libc are preferred, and",
+                    " glibc assets are never picked for musl.",
+                )),
+        )
+        .arg(
+            Arg::new("prefer-static")
+                .long("prefer-static")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Prefer release assets with `static` in their name, and avoid those with",
+                    " `dynamic`, when there are multiple matching files for your OS/arch.",
+                )),
+        )
+        .arg(
+            Arg::new("prefer-gnu-on-windows")
+                .long("prefer-gnu-on-windows")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "On Windows, prefer release assets built with the GNU (MinGW) toolchain over",
+                    " those built with MSVC when there are multiple matching files for your OS/arch.",
+                )),
+        )
+        .arg(
+            Arg::new("extension-preference")
+                .long("extension-preference")
+                .value_name("extensions")
+                .value_delimiter(',')
+                .help(concat!(
+                    "A comma-separated list of extensions to prefer, best first, like `zip,tar.gz`.",
+                    " This breaks ties between release assets for your OS/arch that differ only by",
+                    " extension. The default is `tar.zst,tar.xz,tar.gz,tgz,zip`.",
+                )),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "When `ubi` cannot pick a single release asset for your OS/arch, list the",
+                    " candidates and ask you to choose one. This is ignored when stdin is not a",
+                    " terminal.",
+                )),
+        )
+        .arg(Arg::new("asset-name").long("asset-name").help(concat!(
+            "The exact name of the release asset to download. When this is set, `ubi` will not",
+            " try to pick an asset based on your OS/arch, and the --matching option is ignored.",
+            " This cannot be combined with --url.",
+        )))
+        .arg(
+            Arg::new("verify-signature")
+                .long("verify-signature")
+                .value_name("key")
+                .help(concat!(
+                    "A minisign public key, or the path to a file containing one, used to verify",
+                    " the downloaded file before installing it. The signature is looked for in a",
+                    " release asset with the same name as the downloaded file plus `.minisig`. If",
+                    " the signature is missing or invalid, nothing will be installed.",
+                )),
+        )
+        .arg(
+            Arg::new("verify-gpg")
+                .long("verify-gpg")
+                .value_name("key")
+                .action(ArgAction::Append)
+                .help(concat!(
+                    "An armored GPG public key, or the path to a file containing one, used to",
+                    " verify the downloaded file before installing it. Pass this more than once to",
+                    " accept a signature from any of the keys. The detached signature is looked",
+                    " for in a release asset with the same name as the downloaded file plus `.asc`",
+                    " or `.sig`. If the signature is missing or invalid, nothing will be installed.",
+                )),
+        )
+        .arg(
+            Arg::new("verify-checksums")
+                .long("verify-checksums")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Verify the SHA-256 checksum of the downloaded file before installing it. The",
+                    " checksum is looked for in a release asset named `<file>.sha256`,",
+                    " `SHA256SUMS`, or `checksums.txt`. If no checksum is found or it does not",
+                    " match, nothing will be installed.",
+                )),
+        )
+        .arg(
+            Arg::new("verify-binary-arch")
+                .long("verify-binary-arch")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["extract-all", "all-exes"])
+                .help(concat!(
+                    "Check that the executable was built for the target platform's architecture,",
+                    " by looking at its ELF, Mach-O, or PE header. If it was built for a different",
+                    " architecture, nothing will be installed. Files in other formats, like",
+                    " scripts, are not checked.",
+                )),
+        )
+        .arg(forge_arg())
+        .arg(use_keyring_arg())
+        .arg(api_base_url_arg())
+        .arg(gitlab_url_arg())
+        .arg(
+            Arg::new("cache-downloads")
+                .long("cache-downloads")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Remember the ETag and Last-Modified headers of the downloaded file, and skip",
+                    " the download and install if it hasn't changed the next time. These are",
+                    " stored in `.ubi-cache.json` in the --in directory by default.",
+                )),
+        )
+        .arg(
+            Arg::new("cache-file")
+                .long("cache-file")
+                .value_name("file")
+                .help("The file to use for --cache-downloads. Setting this implies --cache-downloads."),
+        )
+        .arg(
+            Arg::new("cache-release-info")
+                .long("cache-release-info")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Cache the release information from the forge's API, and reuse it instead of",
+                    " making any API requests if the same release is installed again within the",
+                    " --release-info-cache-ttl. This is stored under the platform's cache directory",
+                    " by default.",
+                )),
+        )
+        .arg(
+            Arg::new("release-info-cache-dir")
+                .long("release-info-cache-dir")
+                .value_name("dir")
+                .help(concat!(
+                    "The directory to use for --cache-release-info. Setting this implies",
+                    " --cache-release-info.",
+                )),
+        )
+        .arg(
+            Arg::new("release-info-cache-ttl")
+                .long("release-info-cache-ttl")
+                .value_name("seconds")
+                .value_parser(clap::value_parser!(u64))
+                .help(concat!(
+                    "How long to use cached release information for, in seconds. The default is",
+                    " 300. Setting this implies --cache-release-info.",
+                )),
+        )
+        .arg(
+            Arg::new("refresh")
+                .long("refresh")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Ignore any cached release information and get it from the forge's API. The",
+                    " new information is still cached.",
+                )),
+        )
+        .arg(
+            Arg::new("keep-archive")
+                .long("keep-archive")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Keep a copy of the downloaded file, with its original name, after installing",
+                    " from it. This is written to the --in directory by default.",
+                )),
+        )
+        .arg(
+            Arg::new("keep-archive-dir")
+                .long("keep-archive-dir")
+                .value_name("dir")
+                .help(concat!(
+                    "The directory to write the copy of the downloaded file to. Setting this",
+                    " implies --keep-archive.",
+                )),
+        )
+        .arg(
+            Arg::new("max-retries")
+                .long("max-retries")
+                .value_name("n")
+                .value_parser(clap::value_parser!(u32))
+                .help(concat!(
+                    "The number of times to retry a request that fails with a 429, 502, 503, or 504",
+                    " response, or with a connection error. Defaults to 3.",
+                )),
+        )
+        .arg(
+            Arg::new("connect-timeout")
+                .long("connect-timeout")
+                .value_name("seconds")
+                .value_parser(clap::value_parser!(u64))
+                .help(concat!(
+                    "The number of seconds to wait for a connection to the server, including the",
+                    " TLS handshake. Defaults to 10.",
+                )),
+        )
+        .arg(
+            Arg::new("request-timeout")
+                .long("request-timeout")
+                .value_name("seconds")
+                .value_parser(clap::value_parser!(u64))
+                .help(concat!(
+                    "The number of seconds to wait for the server to respond to a request. This does",
+                    " not include the time spent downloading the response body. By default, there is",
+                    " no request timeout.",
+                )),
+        )
+        .arg(
+            Arg::new("max-download-rate")
+                .long("max-download-rate")
+                .value_name("bytes-per-second")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help(concat!(
+                    "The maximum rate at which to download release assets, in bytes per second. By",
+                    " default, there is no limit.",
+                )),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .value_name("url")
+                .help(concat!(
+                    "A proxy URL to use for all requests. If this isn't set, then the proxy is taken",
+                    " from the HTTPS_PROXY, HTTP_PROXY, or ALL_PROXY env vars, if one of these is",
+                    " set. Hosts listed in the NO_PROXY env var will never be proxied.",
+                )),
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .long("ca-cert")
+                .value_name("file")
+                .help(concat!(
+                    "A PEM file with one or more CA certificates to trust, in addition to the",
+                    " system's root certificates. Use this for forges or mirrors with a certificate",
+                    " signed by a private CA.",
+                )),
+        )
+        .arg(
+            Arg::new("no-verify-tls")
+                .long("no-verify-tls")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Do not verify TLS certificates. This is dangerous, since anyone who can",
+                    " intercept the connection can change what is downloaded. Prefer --ca-cert",
+                    " when the server's certificate is signed by a private CA.",
+                )),
+        )
+        .arg(
+            Arg::new("min-tls-version")
+                .long("min-tls-version")
+                .value_name("version")
+                .value_parser(clap::builder::PossibleValuesParser::new(TlsVersion::VARIANTS))
+                .help(concat!(
+                    "The minimum TLS version to allow for connections, either `1.2` or `1.3`.",
+                    " Defaults to `1.2`.",
+                )),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .value_name("name:value")
+                .action(ArgAction::Append)
+                .value_parser(parse_header)
+                .help(concat!(
+                    "A header to send with every request, like `X-Internal-Auth: secret`. This can",
+                    " be passed more than once. Reserved headers like Authorization can only be set",
+                    " with --allow-reserved-headers.",
+                )),
+        )
+        .arg(
+            Arg::new("allow-reserved-headers")
+                .long("allow-reserved-headers")
+                .action(ArgAction::SetTrue)
+                .requires("header")
+                .help(concat!(
+                    "Allow setting reserved headers like Authorization with --header. A forge",
+                    " token's header still takes priority over a --header with the same name.",
+                )),
+        )
+        .arg(
+            Arg::new("basic-auth")
+                .long("basic-auth")
+                .value_name("username:password")
+                .value_parser(parse_basic_auth)
+                .help(concat!(
+                    "A username and password to send with HTTP Basic auth to the forge's API, for a",
+                    " gateway in front of it. These are never sent to other hosts. This cannot be",
+                    " used with a forge token, except for a GitLab CI job token.",
+                )),
+        )
+        .arg(
+            Arg::new("user-agent")
+                .long("user-agent")
+                .value_name("user-agent")
+                .help(concat!(
+                    "The User-Agent header to send with every request. This defaults to",
+                    " `ubi/<version> (+https://github.com/houseabsolute/ubi)`.",
+                )),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Show a progress bar while downloading the release asset. This is not shown",
+                    " when stderr is not a terminal.",
+                )),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("format")
                 .value_parser(clap::builder::PossibleValuesParser::new(
-                    ForgeType::VARIANTS,
+                    OutputFormat::VARIANTS,
                 ))
+                .conflicts_with_all(["progress", "self-upgrade"])
                 .help(concat!(
-                    "The forge to use. If this isn't set, then the value of --project or --url",
-                    " will be checked for gitlab.com. If this contains any other domain _or_ if it",
-                    " does not have a domain at all, then the default is GitHub.",
+                    "The format for the result of an install. With `json`, a JSON object with the",
+                    " `tag`, `asset_name`, `asset_url`, `asset_size`, `install_path`, `staged_path`,",
+                    " and `sha256` keys is printed to stdout on success, and an object with an",
+                    " `error` key on failure. Defaults to `text`.",
+                )),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Print the release asset that would be downloaded and where it would be",
+                    " installed, without downloading or installing anything.",
+                )),
+        )
+        .arg(
+            Arg::new("print-url")
+                .long("print-url")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["dry-run", "output", "stage-dir", "self-upgrade"])
+                .help(concat!(
+                    "Print only the download URL of the release asset that would be downloaded,",
+                    " without downloading or installing anything.",
+                )),
+        )
+        .arg(
+            Arg::new("stage-dir")
+                .long("stage-dir")
+                .value_name("dir")
+                .conflicts_with_all(["dry-run", "skip-if-exists", "cache-downloads", "cache-file"])
+                .help(concat!(
+                    "Download and unpack the release asset into this directory instead of the",
+                    " install directory, then print where the staged files should be moved to.",
+                    " Moving them is left to you.",
+                )),
+        )
+        .arg(
+            Arg::new("package-output")
+                .long("package-output")
+                .value_name("file")
+                .conflicts_with_all([
+                    "in",
+                    "stage-dir",
+                    "dry-run",
+                    "print-url",
+                    "skip-if-exists",
+                    "cache-downloads",
+                    "cache-file",
+                    "completions-dir",
+                    "versioned-install",
+                    "self-upgrade",
+                ])
+                .help(concat!(
+                    "Write the installed files into a new `.tar.gz` file at this path instead of",
+                    " installing them, which is useful for building packages. The files are put",
+                    " under the --package-prefix in the tarball.",
+                )),
+        )
+        .arg(
+            Arg::new("package-prefix")
+                .long("package-prefix")
+                .value_name("path")
+                .requires("package-output")
+                .help(concat!(
+                    "The directory to put the files in inside the --package-output tarball, like",
+                    " `/opt/foo/bin`. Defaults to `usr/local/bin`.",
+                )),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("skip-if-exists")
+                .help(concat!(
+                    "Always download and install the release asset, overwriting any existing",
+                    " executable. This ignores the download cache.",
+                )),
+        )
+        .arg(
+            Arg::new("skip-if-exists")
+                .long("skip-if-exists")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Do not download anything if the executable already exists in the install",
+                    " directory. With --extract-all or --all-exes, this only works with",
+                    " --cache-downloads.",
+                )),
+        )
+        .arg(
+            Arg::new("install-lock-timeout")
+                .long("install-lock-timeout")
+                .value_name("seconds")
+                .value_parser(clap::value_parser!(u64))
+                .help(concat!(
+                    "How long to wait, in seconds, for another `ubi` process that is installing into",
+                    " the same directory to finish. The default is 300.",
                 )),
         )
-        .arg(Arg::new("api-base-url").long("api-base-url").help(concat!(
-            "The the base URL for the forge site's API. This is useful for testing or if you want",
-            " to operate against an Enterprise version of GitHub or GitLab. This should be",
-            " something like `https://github.my-corp.example.com/api/v4`.",
-        )))
         .arg(
             Arg::new("verbose")
                 .short('v')
                 .long("verbose")
-                .action(ArgAction::SetTrue)
-                .help("Enable verbose output."),
+                .action(ArgAction::Count)
+                .help("Enable debugging output. Pass this twice to enable tracing output."),
         )
         .arg(
             Arg::new("debug")
                 .short('d')
                 .long("debug")
                 .action(ArgAction::SetTrue)
-                .help("Enable debugging output."),
+                .help("Enable debugging output. This is the same as passing -v once."),
         )
         .arg(
             Arg::new("quiet")
                 .short('q')
                 .long("quiet")
                 .action(ArgAction::SetTrue)
-                .help("Suppresses most output."),
+                .conflicts_with_all(["verbose", "debug"])
+                .help("Suppresses all output except errors."),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    LogFormat::VARIANTS,
+                ))
+                .help(concat!(
+                    "The format for log output. The `json` format prints one JSON object per line",
+                    " with `level`, `target`, and `message` keys.",
+                )),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("when")
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    ColorChoice::VARIANTS,
+                ))
+                .help(concat!(
+                    "Whether to use color in log output and the progress bar. With `auto`, color",
+                    " is used when stderr is a terminal and the NO_COLOR env var is not set.",
+                    " Defaults to `auto`.",
+                )),
         )
-        .group(ArgGroup::new("log-level").args(["verbose", "debug", "quiet"]))
+        .subcommand(manifest::install_cmd())
+        .subcommand(ensure::ensure_cmd())
+        .subcommand(list_releases::list_releases_cmd())
+        .subcommand(self_test::self_test_cmd())
+        .subcommand(token::token_cmd())
+        .args_conflicts_with_subcommands(true)
         .max_term_width(MAX_TERM_WIDTH)
 }
 
-pub(crate) fn init_logger_from_matches(matches: &ArgMatches) -> Result<(), log::SetLoggerError> {
-    let level = if matches.get_flag("debug") {
-        log::LevelFilter::Debug
-    } else if matches.get_flag("verbose") {
-        log::LevelFilter::Info
-    } else if matches.get_flag("quiet") {
-        log::LevelFilter::Error
-    } else {
-        log::LevelFilter::Warn
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let Some((name, value)) = header.split_once(':') else {
+        return Err(format!(
+            "the header `{header}` must be in the form `name:value`"
+        ));
+    };
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+// Unlike a header, the password is not trimmed, since whitespace may be part of it.
+fn parse_basic_auth(credentials: &str) -> Result<(String, String), String> {
+    let Some((username, password)) = credentials.split_once(':') else {
+        return Err("the basic auth credentials must be in the form `username:password`".into());
     };
+    Ok((username.to_string(), password.to_string()))
+}
 
-    ubi::init_logger(level)
+// These args are shared with the `list-releases` command.
+fn forge_arg() -> Arg {
+    Arg::new("forge")
+        .long("forge")
+        .value_parser(clap::builder::PossibleValuesParser::new(
+            ForgeType::VARIANTS,
+        ))
+        .help(concat!(
+            "The forge to use. If this isn't set, then the value of --project or --url",
+            " will be checked for bitbucket.org, codeberg.org, dev.azure.com, gitea.com, ghcr.io,",
+            " git.sr.ht, or gitlab.com. If this contains any other domain _or_ if it does not have",
+            " a domain at all, then the default is GitHub. For a Gitea or Forgejo project URL, the",
+            " API on the same host is used.",
+        ))
 }
 
-fn make_ubi<'a>(
-    matches: &'a ArgMatches,
-    ubi_exe_path: &'a Path,
-) -> Result<(Ubi<'a>, Option<impl FnOnce()>)> {
+fn use_keyring_arg() -> Arg {
+    Arg::new("use-keyring")
+        .long("use-keyring")
+        .action(ArgAction::SetTrue)
+        .help(concat!(
+            "Look for a forge token in the OS keyring if there is none in the forge's env",
+            " vars. Use `ubi token set <forge>` to store a token.",
+        ))
+}
+
+fn api_base_url_arg() -> Arg {
+    Arg::new("api-base-url").long("api-base-url").help(concat!(
+        "The the base URL for the forge site's API. This is useful for testing or if you want",
+        " to operate against a self-hosted Gitea or an Enterprise version of GitHub or GitLab.",
+        " This should be something like `https://github.my-corp.example.com/api/v3`. This",
+        " overrides the UBI_GITHUB_API_URL, UBI_GITLAB_URL, UBI_GITEA_URL, and UBI_FORGEJO_URL",
+        " env vars.",
+    ))
+}
+
+fn gitlab_url_arg() -> Arg {
+    Arg::new("gitlab-url").long("gitlab-url").help(concat!(
+        "The URL of a self-hosted GitLab instance, like `https://gitlab.example.com`. This implies",
+        " `--forge gitlab`, and the API base URL will be this URL plus `/api/v4` unless you also",
+        " pass `--api-base-url`.",
+    ))
+}
+
+fn make_ubi(matches: &ArgMatches) -> Result<Ubi<'_>> {
     validate_args(matches)?;
-    if matches.get_flag("self-upgrade") {
-        return self_upgrade_ubi(ubi_exe_path);
-    }
 
     let mut builder = UbiBuilder::new();
     if let Some(p) = matches.get_one::<String>("project") {
@@ -208,15 +954,51 @@ fn make_ubi<'a>(
     if let Some(t) = matches.get_one::<String>("tag") {
         builder = builder.tag(t);
     }
+    if let Some(r) = matches.get_one::<String>("version-req") {
+        builder = builder.version_req(r);
+    }
+    if let Some(p) = matches.get_one::<String>("tag-pattern") {
+        builder = builder.tag_pattern(p);
+    }
+    if matches.get_flag("include-prereleases") {
+        builder = builder.include_prereleases();
+    }
     if let Some(u) = matches.get_one::<String>("url") {
         builder = builder.url(u);
     }
+    if let Some(p) = matches.get_one::<String>("local-asset") {
+        builder = builder.local_asset(p);
+    }
     if let Some(dir) = matches.get_one::<String>("in") {
         builder = builder.install_dir(dir);
     }
     if let Some(m) = matches.get_one::<String>("matching") {
         builder = builder.matching(m);
     }
+    if let Some(l) = matches.get_one::<String>("libc") {
+        builder = builder.libc(Libc::from_str(l)?);
+    }
+    if matches.get_flag("use-keyring") {
+        builder = builder.use_keyring();
+    }
+    if matches.get_flag("prefer-static") {
+        builder = builder.prefer_static();
+    }
+    if matches.get_flag("prefer-gnu-on-windows") {
+        builder = builder.prefer_gnu_on_windows();
+    }
+    if let Some(extensions) = matches.get_many::<String>("extension-preference") {
+        builder = builder.extension_preference(&extensions.map(String::as_str).collect::<Vec<_>>());
+    }
+    if matches.get_flag("interactive") {
+        builder = builder.interactive();
+    }
+    if let Some(r) = matches.get_one::<String>("matching-regex") {
+        builder = builder.matching_regex(r);
+    }
+    if let Some(n) = matches.get_one::<String>("asset-name") {
+        builder = builder.exact_asset_name(n);
+    }
     if let Some(e) = matches.get_one::<String>("exe") {
         builder = builder.exe(e);
     }
@@ -226,19 +1008,150 @@ fn make_ubi<'a>(
     if matches.get_flag("extract-all") {
         builder = builder.extract_all();
     }
+    if let Some(n) = matches.get_one::<usize>("strip-components") {
+        builder = builder.strip_components(*n);
+    }
+    if matches.get_flag("all-exes") {
+        builder = builder.all_exes();
+    }
+    if let Some(g) = matches.get_one::<String>("exe-glob") {
+        builder = builder.exe_glob(g);
+    }
+    if matches.get_flag("install-as-is") {
+        builder = builder.install_as_is();
+    }
+    if matches.get_flag("versioned-install") {
+        builder = builder.versioned_install();
+    }
+    if let Some(d) = matches.get_one::<String>("completions-dir") {
+        builder = builder.completions_dir(d);
+    }
+    if let Some(k) = matches.get_one::<String>("verify-signature") {
+        builder = builder.verify_signature(k);
+    }
+    if let Some(keys) = matches.get_many::<String>("verify-gpg") {
+        for k in keys {
+            builder = builder.verify_gpg(k);
+        }
+    }
+    if matches.get_flag("verify-checksums") {
+        builder = builder.verify_checksum();
+    }
+    if matches.get_flag("verify-binary-arch") {
+        builder = builder.verify_binary_arch();
+    }
+    if matches.get_flag("cache-downloads") {
+        builder = builder.cache_downloads();
+    }
+    if let Some(f) = matches.get_one::<String>("cache-file") {
+        builder = builder.cache_file(f);
+    }
+    if matches.get_flag("cache-release-info") {
+        builder = builder.cache_release_info();
+    }
+    if let Some(d) = matches.get_one::<String>("release-info-cache-dir") {
+        builder = builder.release_info_cache_dir(d);
+    }
+    if let Some(t) = matches.get_one::<u64>("release-info-cache-ttl") {
+        builder = builder.release_info_cache_ttl(Duration::from_secs(*t));
+    }
+    if matches.get_flag("refresh") {
+        builder = builder.refresh_release_info();
+    }
+    if let Some(d) = matches.get_one::<String>("stage-dir") {
+        builder = builder.stage_dir(d);
+    }
+    if let Some(o) = matches.get_one::<String>("package-output") {
+        builder = builder.package_output(o);
+    }
+    if let Some(p) = matches.get_one::<String>("package-prefix") {
+        builder = builder.package_prefix(p);
+    }
+    if matches.get_flag("force") {
+        builder = builder.force();
+    }
+    if matches.get_flag("skip-if-exists") {
+        builder = builder.skip_if_exists();
+    }
+    if let Some(t) = matches.get_one::<u64>("install-lock-timeout") {
+        builder = builder.install_lock_timeout(Duration::from_secs(*t));
+    }
+    if matches.get_flag("keep-archive") {
+        builder = builder.keep_archive();
+    }
+    if let Some(d) = matches.get_one::<String>("keep-archive-dir") {
+        builder = builder.keep_archive_dir(d);
+    }
+    if matches.get_flag("progress") {
+        builder = builder.progress();
+    }
+    // The text output uses `Ubi::install_plan` for a dry run, but the JSON output needs the result
+    // from `Ubi::install_binary`, which is what the builder's dry run returns.
+    if matches.get_flag("dry-run") {
+        builder = builder.dry_run();
+    }
+    builder = builder.color(logging::color_from_matches(matches));
+    if let Some(r) = matches.get_one::<u32>("max-retries") {
+        builder = builder.max_retries(*r);
+    }
+    if let Some(t) = matches.get_one::<u64>("connect-timeout") {
+        builder = builder.connect_timeout(Duration::from_secs(*t));
+    }
+    if let Some(t) = matches.get_one::<u64>("request-timeout") {
+        builder = builder.request_timeout(Duration::from_secs(*t));
+    }
+    if let Some(r) = matches.get_one::<u64>("max-download-rate") {
+        builder = builder.max_download_rate(*r);
+    }
+    if let Some(p) = matches.get_one::<String>("proxy") {
+        builder = builder.proxy(p);
+    }
+    if let Some(c) = matches.get_one::<String>("ca-cert") {
+        builder = builder.ca_cert(c);
+    }
+    if matches.get_flag("no-verify-tls") {
+        builder = builder.danger_accept_invalid_certs();
+    }
+    if let Some(v) = matches.get_one::<String>("min-tls-version") {
+        builder = builder.min_tls_version(TlsVersion::from_str(v)?);
+    }
+    if let Some(headers) = matches.get_many::<(String, String)>("header") {
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+    }
+    if matches.get_flag("allow-reserved-headers") {
+        builder = builder.allow_reserved_headers();
+    }
+    if let Some((username, password)) = matches.get_one::<(String, String)>("basic-auth") {
+        builder = builder.basic_auth(username, password);
+    }
+    if let Some(ua) = matches.get_one::<String>("user-agent") {
+        builder = builder.user_agent(ua);
+    }
     if let Some(ft) = matches.get_one::<String>("forge") {
         builder = builder.forge(ForgeType::from_str(ft)?);
     }
     if let Some(url) = matches.get_one::<String>("api-base-url") {
         builder = builder.api_base_url(url);
     }
+    if let Some(url) = matches.get_one::<String>("gitlab-url") {
+        builder = builder.gitlab_url(url);
+    }
 
-    Ok((builder.build()?, None))
+    builder.build()
 }
 
 fn validate_args(matches: &ArgMatches) -> Result<()> {
     if matches.contains_id("url") {
-        for a in &["project", "tag"] {
+        for a in &[
+            "project",
+            "tag",
+            "version-req",
+            "tag-pattern",
+            "asset-name",
+            "matching-regex",
+        ] {
             if matches.contains_id(a) {
                 return Err(UbiError::InvalidArgsError(format!(
                     "You cannot combine the --url and --{a} options"
@@ -248,8 +1161,65 @@ fn validate_args(matches: &ArgMatches) -> Result<()> {
         }
     }
 
+    if matches.contains_id("local-asset") {
+        for a in &[
+            "project",
+            "tag",
+            "version-req",
+            "tag-pattern",
+            "url",
+            "asset-name",
+            "matching-regex",
+        ] {
+            if matches.contains_id(a) {
+                return Err(UbiError::InvalidArgsError(format!(
+                    "You cannot combine the --local-asset and --{a} options"
+                ))
+                .into());
+            }
+        }
+    }
+
+    if matches.contains_id("tag") {
+        if matches.contains_id("version-req") {
+            return Err(UbiError::InvalidArgsError(
+                "You cannot combine the --tag and --version-req options".to_string(),
+            )
+            .into());
+        }
+        if matches.contains_id("tag-pattern") {
+            return Err(UbiError::InvalidArgsError(
+                "You cannot combine the --tag and --tag-pattern options".to_string(),
+            )
+            .into());
+        }
+        if matches.get_flag("include-prereleases") {
+            return Err(UbiError::InvalidArgsError(
+                "You cannot combine the --tag and --include-prereleases options".to_string(),
+            )
+            .into());
+        }
+    }
+
+    if matches.contains_id("tag-pattern") && matches.contains_id("version-req") {
+        return Err(UbiError::InvalidArgsError(
+            "You cannot combine the --tag-pattern and --version-req options".to_string(),
+        )
+        .into());
+    }
+
     if matches.get_flag("self-upgrade") {
-        for a in &["exe", "in", "project", "tag"] {
+        for a in &[
+            "asset-name",
+            "exe",
+            "in",
+            "local-asset",
+            "project",
+            "tag",
+            "tag-pattern",
+            "url",
+            "version-req",
+        ] {
             if matches.contains_id(a) {
                 return Err(UbiError::InvalidArgsError(format!(
                     "You cannot combine the --self-upgrade and --{a} options"
@@ -257,49 +1227,59 @@ fn validate_args(matches: &ArgMatches) -> Result<()> {
                 .into());
             }
         }
+        // There's no way to see what a self-upgrade would do without doing it.
+        if matches.get_flag("dry-run") {
+            return Err(UbiError::InvalidArgsError(
+                "You cannot combine the --self-upgrade and --dry-run options".to_string(),
+            )
+            .into());
+        }
     }
 
     if !(matches.contains_id("project")
         || matches.contains_id("url")
+        || matches.contains_id("local-asset")
         || matches.get_flag("self-upgrade"))
     {
-        return Err(
-            UbiError::InvalidArgsError("You must pass a --project or --url.".to_string()).into(),
-        );
+        return Err(UbiError::InvalidArgsError(
+            "You must pass a --project, --url, or --local-asset.".to_string(),
+        )
+        .into());
     }
 
     Ok(())
 }
 
-fn self_upgrade_ubi(ubi_exe_path: &Path) -> Result<(Ubi<'_>, Option<impl FnOnce()>)> {
-    let ubi =
-        UbiBuilder::new()
-            .project("houseabsolute/ubi")
-            .install_dir(ubi_exe_path.parent().ok_or_else(|| {
-                anyhow!("executable path `{}` has no parent", ubi_exe_path.display())
-            })?)
-            .build()?;
+fn print_plan(plan: &InstallPlan) {
+    let size = plan
+        .asset_size
+        .map(|s| format!(" ({})", output::human_size(s)))
+        .unwrap_or_default();
+    println!(
+        "Would download {}{size} from {}",
+        plan.asset_name, plan.asset_url
+    );
+    println!("Would install into {}", plan.install_path.display());
+}
+
+fn print_move_plan(staged_path: &Path, install_path: &Path) {
+    println!("Staged {}", staged_path.display());
+    println!("Move it to {} to install it", install_path.display());
+}
 
-    let post_run = if cfg!(target_os = "windows") {
-        let mut old_exe = ubi_exe_path.to_path_buf();
-        old_exe.set_file_name("ubi-old.exe");
-        debug!(
-            "renaming {} to {}",
-            ubi_exe_path.display(),
-            old_exe.display()
+fn print_self_upgrade(upgrade: &SelfUpgrade) {
+    println!(
+        "Upgraded ubi at {} from {} to {}",
+        upgrade.path.display(),
+        upgrade.old_version,
+        upgrade.new_version,
+    );
+    if let Some(old_exe) = &upgrade.old_exe {
+        println!(
+            "The self-upgrade operation left an old binary behind that must be deleted manually: {}",
+            old_exe.display(),
         );
-        std::fs::rename(ubi_exe_path, &old_exe)?;
-        Some(move || {
-            println!(
-                "The self-upgrade operation left an old binary behind that must be deleted manually: {}",
-                old_exe.display(),
-            );
-        })
-    } else {
-        None
-    };
-
-    Ok((ubi, post_run))
+    }
 }
 
 fn print_err(e: &Error) {
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::path::{Path, PathBuf};
+use ubi::{Lockfile, Manifest, DEFAULT_LOCKFILE_NAME, DEFAULT_MANIFEST_FILE_NAME};
+
+pub(crate) fn install_cmd() -> Command {
+    Command::new("install")
+        .about(concat!(
+            "Install every tool listed in a ubi.toml manifest and record the release asset that was",
+            " installed for each one in a ubi.lock lockfile.",
+        ))
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .short('m')
+                .default_value(DEFAULT_MANIFEST_FILE_NAME)
+                .help("The manifest file listing the tools to install."),
+        )
+        .arg(Arg::new("lockfile").long("lockfile").help(concat!(
+            "The lockfile to write, or to read with --locked. Defaults to ubi.lock in the same",
+            " directory as the manifest.",
+        )))
+        .arg(
+            Arg::new("locked")
+                .long("locked")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Install the exact release assets recorded in the lockfile instead of updating it.",
+                    " This fails if an asset's SHA-256 digest does not match the lockfile.",
+                )),
+        )
+        .arg(Arg::new("in").long("in").short('i').help(concat!(
+            "The directory in which the binaries should be placed. This has the same default as",
+            " the top-level --in option.",
+        )))
+}
+
+pub(crate) async fn install(matches: &ArgMatches) -> Result<()> {
+    let manifest_path = Path::new(
+        matches
+            .get_one::<String>("manifest")
+            .expect("the manifest has a default"),
+    );
+    let lockfile_path = lockfile_path(
+        manifest_path,
+        matches.get_one::<String>("lockfile").map(String::as_str),
+    );
+    let install_dir = matches.get_one::<String>("in").map(Path::new);
+
+    let manifest = Manifest::from_file(manifest_path)?;
+    if matches.get_flag("locked") {
+        if !lockfile_path.exists() {
+            return Err(anyhow!(
+                "There is no lockfile at {}, so you need to install without --locked to create it",
+                lockfile_path.display(),
+            ));
+        }
+        let lockfile = Lockfile::from_file(&lockfile_path)?;
+        return ubi::install_from_lockfile(&manifest, &lockfile, install_dir, 0).await;
+    }
+
+    let existing = if lockfile_path.exists() {
+        Some(Lockfile::from_file(&lockfile_path)?)
+    } else {
+        None
+    };
+    let lockfile = ubi::install_from_manifest(&manifest, existing.as_ref(), install_dir, 0).await?;
+    lockfile.write(&lockfile_path)?;
+    println!("Wrote the lockfile to {}.", lockfile_path.display());
+
+    Ok(())
+}
+
+fn lockfile_path(manifest_path: &Path, lockfile: Option<&str>) -> PathBuf {
+    if let Some(lockfile) = lockfile {
+        return PathBuf::from(lockfile);
+    }
+    manifest_path.with_file_name(DEFAULT_LOCKFILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("ubi.toml", None, "ubi.lock" ; "default")]
+    #[test_case("tools/ubi.toml", None, "tools/ubi.lock" ; "manifest in a subdirectory")]
+    #[test_case("tools/ubi.toml", Some("other.lock"), "other.lock" ; "explicit lockfile")]
+    fn lockfile_path(manifest: &str, lockfile: Option<&str>, expect: &str) {
+        assert_eq!(
+            super::lockfile_path(Path::new(manifest), lockfile),
+            PathBuf::from(expect),
+        );
+    }
+
+    #[test_case(&["ubi", "install"], "ubi.toml", None, false ; "defaults")]
+    #[test_case(
+        &["ubi", "install", "--manifest", "tools.toml", "--lockfile", "tools.lock", "--locked"],
+        "tools.toml",
+        Some("tools.lock"),
+        true ;
+        "all options"
+    )]
+    fn install_cmd_parses(
+        args: &[&str],
+        expect_manifest: &str,
+        expect_lockfile: Option<&str>,
+        expect_locked: bool,
+    ) {
+        let matches = crate::cmd().try_get_matches_from(args).unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "install");
+        assert_eq!(
+            matches.get_one::<String>("manifest").map(String::as_str),
+            Some(expect_manifest),
+        );
+        assert_eq!(
+            matches.get_one::<String>("lockfile").map(String::as_str),
+            expect_lockfile,
+        );
+        assert_eq!(matches.get_flag("locked"), expect_locked);
+    }
+
+    #[test]
+    fn install_cmd_conflicts_with_install_args() {
+        assert!(crate::cmd()
+            .try_get_matches_from(["ubi", "--project", "houseabsolute/precious", "install"])
+            .is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Error;
+use clap::ArgMatches;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use strum::{AsRefStr, EnumString, VariantNames};
+use ubi::InstallResult;
+
+#[derive(AsRefStr, Clone, Copy, Debug, Default, EnumString, PartialEq, Eq, VariantNames)]
+pub(crate) enum OutputFormat {
+    #[strum(serialize = "text")]
+    #[default]
+    Text,
+    #[strum(serialize = "json")]
+    Json,
+}
+
+// The possible values are checked by clap, so parsing can't fail.
+pub(crate) fn output_format_from_matches(matches: &ArgMatches) -> OutputFormat {
+    matches
+        .get_one::<String>("output")
+        .and_then(|o| OutputFormat::from_str(o).ok())
+        .unwrap_or_default()
+}
+
+// The JSON is printed on a single line so that it's easy to read from a script, even if some other
+// output ends up on stdout.
+pub(crate) fn print_json_result(result: &InstallResult) {
+    println!("{}", result_json(result));
+}
+
+pub(crate) fn print_json_err(e: &Error) {
+    println!("{}", err_json(e));
+}
+
+fn result_json(result: &InstallResult) -> Value {
+    serde_json::to_value(result).expect("an InstallResult can always be serialized")
+}
+
+fn err_json(e: &Error) -> Value {
+    json!({ "error": e.to_string() })
+}
+
+// Sizes are shown in binary units with one decimal place, like `2.8 MiB`, except for sizes under
+// 1 KiB, which are shown in bytes.
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::path::PathBuf;
+    use test_case::test_case;
+    use url::Url;
+
+    #[test_case(
+        Some("v1.2.3"), Some("0123abcd"), None,
+        json!({
+            "tag": "v1.2.3",
+            "asset_name": "precious-Linux-x86_64-musl.tar.gz",
+            "asset_url": "https://github.com/houseabsolute/precious/releases/download/v1.2.3/precious-Linux-x86_64-musl.tar.gz",
+            "asset_size": 2906752,
+            "install_path": "/usr/local/bin/precious",
+            "staged_path": null,
+            "sha256": "0123abcd",
+        }) ;
+        "install"
+    )]
+    #[test_case(
+        Some("v1.2.3"), None, None,
+        json!({
+            "tag": "v1.2.3",
+            "asset_name": "precious-Linux-x86_64-musl.tar.gz",
+            "asset_url": "https://github.com/houseabsolute/precious/releases/download/v1.2.3/precious-Linux-x86_64-musl.tar.gz",
+            "asset_size": 2906752,
+            "install_path": "/usr/local/bin/precious",
+            "staged_path": null,
+            "sha256": null,
+        }) ;
+        "dry run"
+    )]
+    #[test_case(
+        None, Some("0123abcd"), Some("/tmp/stage/precious"),
+        json!({
+            "tag": null,
+            "asset_name": "precious-Linux-x86_64-musl.tar.gz",
+            "asset_url": "https://github.com/houseabsolute/precious/releases/download/v1.2.3/precious-Linux-x86_64-musl.tar.gz",
+            "asset_size": 2906752,
+            "install_path": "/usr/local/bin/precious",
+            "staged_path": "/tmp/stage/precious",
+            "sha256": "0123abcd",
+        }) ;
+        "staged without a tag"
+    )]
+    fn result_json(
+        tag: Option<&str>,
+        sha256: Option<&str>,
+        staged_path: Option<&str>,
+        expect: Value,
+    ) -> anyhow::Result<()> {
+        let result = InstallResult {
+            tag: tag.map(String::from),
+            asset_name: "precious-Linux-x86_64-musl.tar.gz".to_string(),
+            asset_url: Url::parse(
+                "https://github.com/houseabsolute/precious/releases/download/v1.2.3/precious-Linux-x86_64-musl.tar.gz",
+            )?,
+            asset_size: Some(2_906_752),
+            install_path: PathBuf::from("/usr/local/bin/precious"),
+            staged_path: staged_path.map(PathBuf::from),
+            sha256: sha256.map(String::from),
+        };
+        assert_eq!(super::result_json(&result), expect);
+        Ok(())
+    }
+
+    #[test_case(0, "0 B")]
+    #[test_case(1023, "1023 B")]
+    #[test_case(1024, "1.0 KiB")]
+    #[test_case(1536, "1.5 KiB")]
+    #[test_case(2_906_752, "2.8 MiB")]
+    #[test_case(5 * 1024 * 1024 * 1024, "5.0 GiB")]
+    #[test_case(u64::MAX, "16777216.0 TiB")]
+    fn human_size(bytes: u64, expect: &str) {
+        assert_eq!(super::human_size(bytes), expect);
+    }
+
+    #[test]
+    fn err_json() {
+        let e = anyhow!("could not find a release for this OS and architecture");
+        assert_eq!(
+            super::err_json(&e),
+            json!({ "error": "could not find a release for this OS and architecture" }),
+        );
+    }
+
+    #[test_case(&[], OutputFormat::Text ; "default")]
+    #[test_case(&["--output", "text"], OutputFormat::Text ; "text")]
+    #[test_case(&["--output", "json"], OutputFormat::Json ; "json")]
+    fn output_format_from_matches(flags: &[&str], expect: OutputFormat) {
+        let matches = crate::cmd()
+            .try_get_matches_from(
+                ["ubi", "--project", "houseabsolute/precious"]
+                    .iter()
+                    .chain(flags),
+            )
+            .unwrap();
+        assert_eq!(super::output_format_from_matches(&matches), expect);
+    }
+
+    #[test]
+    fn output_json_conflicts_with_progress() {
+        let err = crate::cmd()
+            .try_get_matches_from([
+                "ubi",
+                "--project",
+                "houseabsolute/precious",
+                "--output",
+                "json",
+                "--progress",
+            ])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+}
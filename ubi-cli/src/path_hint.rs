@@ -0,0 +1,168 @@
+use std::{
+    env,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+// Returns a one-line hint explaining how to add the install directory to the user's `PATH`, or
+// `None` if it's already there. With color, the command to run is shown in bold.
+pub(crate) fn path_hint_for(install_dir: &Path, use_color: bool) -> Option<String> {
+    if dir_is_in_path(install_dir, env::var_os("PATH").as_deref()) {
+        return None;
+    }
+    Some(path_hint(
+        install_dir,
+        env::var("SHELL").ok().as_deref(),
+        cfg!(target_os = "windows"),
+        use_color,
+    ))
+}
+
+pub(crate) fn dir_is_in_path(dir: &Path, path: Option<&OsStr>) -> bool {
+    let Some(path) = path else {
+        return false;
+    };
+    // If the directory exists we compare canonicalized paths, so that a `PATH` entry that is a
+    // symlink to the directory still counts.
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let dir = canonical(dir);
+    env::split_paths(path)
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p: PathBuf| canonical(&p) == dir)
+}
+
+fn path_hint(dir: &Path, shell: Option<&str>, is_windows: bool, use_color: bool) -> String {
+    let bold = |command: String| {
+        if use_color {
+            format!("\x1B[1m{command}\x1B[22m")
+        } else {
+            command
+        }
+    };
+
+    let dir = dir.display();
+    if is_windows {
+        return format!(
+            "{dir} is not in your PATH. To add it, run this in PowerShell: {}",
+            bold(format!(
+                r#"[Environment]::SetEnvironmentVariable("Path", [Environment]::GetEnvironmentVariable("Path", "User") + ";{dir}", "User")"#,
+            )),
+        );
+    }
+
+    let shell = shell
+        .map(Path::new)
+        .and_then(Path::file_name)
+        .and_then(OsStr::to_str);
+    let command = match shell {
+        Some("fish") => format!("fish_add_path {dir}"),
+        Some(shell) if shell == "zsh" || shell == "bash" => {
+            format!(r#"echo 'export PATH="{dir}:$PATH"' >> ~/.{shell}rc"#)
+        }
+        _ => format!(r#"echo 'export PATH="{dir}:$PATH"' >> ~/.profile"#),
+    };
+    format!(
+        "{dir} is not in your PATH. To add it, run: {}",
+        bold(command)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+    use test_case::test_case;
+
+    #[test_case(&["/usr/bin", "/home/autarch/.local/bin"], true ; "in PATH")]
+    #[test_case(&["/usr/bin", "/home/autarch/.local/bin/"], true ; "in PATH with trailing slash")]
+    #[test_case(&["/usr/bin", "/home/autarch/bin"], false ; "not in PATH")]
+    #[test_case(&["/usr/bin", "/home/autarch/.local"], false ; "parent in PATH")]
+    #[test_case(&[], false ; "empty PATH")]
+    fn dir_is_in_path(entries: &[&str], expect: bool) {
+        let path = env::join_paths(entries).unwrap();
+        assert_eq!(
+            super::dir_is_in_path(Path::new("/home/autarch/.local/bin"), Some(&path)),
+            expect,
+        );
+    }
+
+    #[test]
+    fn dir_is_in_path_without_path() {
+        assert!(!super::dir_is_in_path(
+            Path::new("/home/autarch/.local/bin"),
+            None
+        ));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn dir_is_in_path_via_symlink() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let dir = td.path().join("bin");
+        std::fs::create_dir(&dir)?;
+        let link = td.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link)?;
+
+        let path: OsString = env::join_paths([&link])?;
+        assert!(super::dir_is_in_path(&dir, Some(&path)));
+        Ok(())
+    }
+
+    #[test_case(
+        Some("/usr/bin/fish"),
+        "/home/autarch/.local/bin is not in your PATH. To add it, run: fish_add_path /home/autarch/.local/bin" ;
+        "fish"
+    )]
+    #[test_case(
+        Some("/bin/zsh"),
+        r#"/home/autarch/.local/bin is not in your PATH. To add it, run: echo 'export PATH="/home/autarch/.local/bin:$PATH"' >> ~/.zshrc"# ;
+        "zsh"
+    )]
+    #[test_case(
+        Some("/bin/bash"),
+        r#"/home/autarch/.local/bin is not in your PATH. To add it, run: echo 'export PATH="/home/autarch/.local/bin:$PATH"' >> ~/.bashrc"# ;
+        "bash"
+    )]
+    #[test_case(
+        Some("/bin/dash"),
+        r#"/home/autarch/.local/bin is not in your PATH. To add it, run: echo 'export PATH="/home/autarch/.local/bin:$PATH"' >> ~/.profile"# ;
+        "other shell"
+    )]
+    #[test_case(
+        None,
+        r#"/home/autarch/.local/bin is not in your PATH. To add it, run: echo 'export PATH="/home/autarch/.local/bin:$PATH"' >> ~/.profile"# ;
+        "no shell"
+    )]
+    fn path_hint_unix(shell: Option<&str>, expect: &str) {
+        assert_eq!(
+            super::path_hint(Path::new("/home/autarch/.local/bin"), shell, false, false),
+            expect,
+        );
+    }
+
+    #[test]
+    fn path_hint_with_color() {
+        assert_eq!(
+            super::path_hint(
+                Path::new("/home/autarch/.local/bin"),
+                Some("/usr/bin/fish"),
+                false,
+                true
+            ),
+            "/home/autarch/.local/bin is not in your PATH. To add it, run: \x1B[1mfish_add_path /home/autarch/.local/bin\x1B[22m",
+        );
+    }
+
+    #[test]
+    fn path_hint_windows() {
+        assert_eq!(
+            super::path_hint(
+                Path::new(r"C:\Users\autarch\AppData\Local\ubi\bin"),
+                None,
+                true,
+                false
+            ),
+            r#"C:\Users\autarch\AppData\Local\ubi\bin is not in your PATH. To add it, run this in PowerShell: [Environment]::SetEnvironmentVariable("Path", [Environment]::GetEnvironmentVariable("Path", "User") + ";C:\Users\autarch\AppData\Local\ubi\bin", "User")"#,
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    output::{output_format_from_matches, OutputFormat},
+    path_hint,
+};
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use serde_json::Value;
+use std::{env, ffi::OsStr};
+use strum::VariantNames;
+use ubi::SelfTestReport;
+
+pub(crate) fn self_test_cmd() -> Command {
+    Command::new("selftest")
+        .about(concat!(
+            "Report what ubi detects about this environment, including the OS, architecture, and",
+            " libc it picks release assets for, which forge token env vars are set, and the default",
+            " install directory. The tokens are masked.",
+        ))
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("format")
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    OutputFormat::VARIANTS,
+                ))
+                .help("The format for the report, either `text` or `json`. Defaults to `text`."),
+        )
+}
+
+pub(crate) fn self_test(matches: &ArgMatches) -> Result<()> {
+    let report = ubi::self_test()?;
+    let in_path = install_dir_in_path(&report, env::var_os("PATH").as_deref());
+    match output_format_from_matches(matches) {
+        OutputFormat::Text => {
+            for line in format_report(&report, in_path) {
+                println!("{line}");
+            }
+        }
+        OutputFormat::Json => println!("{}", report_json(&report, in_path)),
+    }
+    Ok(())
+}
+
+fn install_dir_in_path(report: &SelfTestReport, path: Option<&OsStr>) -> Option<bool> {
+    report
+        .install_dir
+        .as_deref()
+        .map(|dir| path_hint::dir_is_in_path(dir, path))
+}
+
+fn format_report(report: &SelfTestReport, in_path: Option<bool>) -> Vec<String> {
+    let mut lines = vec![
+        format!("ubi version: {}", report.version),
+        format!("target:      {}", report.target),
+        format!("OS:          {}", report.os),
+        format!("arch:        {}", report.arch),
+    ];
+    if let Some(libc) = &report.libc {
+        lines.push(format!("libc:        {libc}"));
+    }
+    if report.tokens.is_empty() {
+        lines.push("tokens:      none".to_string());
+    } else {
+        lines.push("tokens:".to_string());
+        lines.extend(report.tokens.iter().map(|t| {
+            format!(
+                "  {} = {} (used for {})",
+                t.env_var,
+                t.masked_token,
+                t.forges.join(", "),
+            )
+        }));
+    }
+    match (&report.install_dir, &report.install_dir_error) {
+        (Some(dir), _) => lines.push(format!(
+            "install dir: {} ({})",
+            dir.display(),
+            if in_path == Some(true) {
+                "in PATH"
+            } else {
+                "not in PATH"
+            },
+        )),
+        (None, Some(e)) => lines.push(format!("install dir: {e}")),
+        (None, None) => lines.push("install dir: unknown".to_string()),
+    }
+    lines
+}
+
+fn report_json(report: &SelfTestReport, in_path: Option<bool>) -> Value {
+    let mut json = serde_json::to_value(report).expect("a SelfTestReport can always be serialized");
+    json["install_dir_in_path"] = Value::from(in_path);
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use ubi::TokenEnvVar;
+
+    fn report() -> SelfTestReport {
+        SelfTestReport {
+            version: "1.2.3".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            libc: Some("gnu".to_string()),
+            tokens: vec![TokenEnvVar {
+                env_var: "GITHUB_TOKEN".to_string(),
+                forges: vec!["github".to_string(), "oci".to_string()],
+                masked_token: "****cdef".to_string(),
+            }],
+            install_dir: Some(PathBuf::from("/home/autarch/.local/bin")),
+            install_dir_error: None,
+        }
+    }
+
+    #[test]
+    fn install_dir_in_path() -> Result<()> {
+        let path = env::join_paths(["/usr/bin", "/home/autarch/.local/bin"])?;
+        assert_eq!(
+            super::install_dir_in_path(&report(), Some(&path)),
+            Some(true)
+        );
+        assert_eq!(super::install_dir_in_path(&report(), None), Some(false));
+        let no_dir = SelfTestReport {
+            install_dir: None,
+            ..report()
+        };
+        assert_eq!(super::install_dir_in_path(&no_dir, Some(&path)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn format_report() {
+        assert_eq!(
+            super::format_report(&report(), Some(false)),
+            [
+                "ubi version: 1.2.3",
+                "target:      x86_64-unknown-linux-gnu",
+                "OS:          linux",
+                "arch:        x86_64",
+                "libc:        gnu",
+                "tokens:",
+                "  GITHUB_TOKEN = ****cdef (used for github, oci)",
+                "install dir: /home/autarch/.local/bin (not in PATH)",
+            ],
+        );
+    }
+
+    #[test]
+    fn format_report_without_tokens_or_install_dir() {
+        let report = SelfTestReport {
+            os: "windows".to_string(),
+            target: "x86_64-pc-windows-msvc".to_string(),
+            libc: None,
+            tokens: vec![],
+            install_dir: None,
+            install_dir_error: Some("the LOCALAPPDATA env var is not set".to_string()),
+            ..report()
+        };
+        assert_eq!(
+            super::format_report(&report, None),
+            [
+                "ubi version: 1.2.3",
+                "target:      x86_64-pc-windows-msvc",
+                "OS:          windows",
+                "arch:        x86_64",
+                "tokens:      none",
+                "install dir: the LOCALAPPDATA env var is not set",
+            ],
+        );
+    }
+
+    #[test]
+    fn report_json() {
+        assert_eq!(
+            super::report_json(&report(), Some(true)),
+            json!({
+                "version": "1.2.3",
+                "target": "x86_64-unknown-linux-gnu",
+                "os": "linux",
+                "arch": "x86_64",
+                "libc": "gnu",
+                "tokens": [{
+                    "env_var": "GITHUB_TOKEN",
+                    "forges": ["github", "oci"],
+                    "masked_token": "****cdef",
+                }],
+                "install_dir": "/home/autarch/.local/bin",
+                "install_dir_error": null,
+                "install_dir_in_path": true,
+            }),
+        );
+    }
+
+    #[test]
+    fn self_test_cmd_parses() {
+        let matches = crate::cmd()
+            .try_get_matches_from(["ubi", "selftest", "--output", "json"])
+            .unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "selftest");
+        assert_eq!(output_format_from_matches(matches), OutputFormat::Json);
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    str::FromStr,
+};
+use strum::VariantNames;
+use ubi::ForgeType;
+
+pub(crate) fn token_cmd() -> Command {
+    let forge_arg = || {
+        Arg::new("forge")
+            .required(true)
+            .value_parser(clap::builder::PossibleValuesParser::new(
+                ForgeType::VARIANTS,
+            ))
+            .help("The forge that the token is for. Use `gitea` for a Forgejo token.")
+    };
+
+    Command::new("token")
+        .about("Manage forge tokens stored in the OS keyring. These are used with --use-keyring.")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about(concat!(
+                    "Store a token for a forge in the OS keyring. The token is read from stdin,",
+                    " so it never ends up in your shell history.",
+                ))
+                .arg(forge_arg()),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Delete the token for a forge from the OS keyring.")
+                .arg(forge_arg()),
+        )
+}
+
+pub(crate) fn token(matches: &ArgMatches) -> Result<()> {
+    let (action, matches) = matches
+        .subcommand()
+        .expect("clap requires a token subcommand");
+    let forge = ForgeType::from_str(
+        matches
+            .get_one::<String>("forge")
+            .expect("clap requires a forge"),
+    )?;
+
+    match action {
+        "set" => {
+            let token = read_token(&forge, io::stdin().lock())?;
+            ubi::set_keyring_token(&forge, &token)?;
+            println!("Stored the {} token in the OS keyring.", forge.as_ref());
+        }
+        "delete" => {
+            if ubi::delete_keyring_token(&forge)? {
+                println!("Deleted the {} token from the OS keyring.", forge.as_ref());
+            } else {
+                println!("There was no {} token in the OS keyring.", forge.as_ref());
+            }
+        }
+        _ => unreachable!("clap only accepts the set and delete subcommands"),
+    }
+    Ok(())
+}
+
+fn read_token(forge: &ForgeType, mut input: impl BufRead) -> Result<String> {
+    if io::stdin().is_terminal() {
+        eprint!("Enter the {} token: ", forge.as_ref());
+        io::stderr().flush()?;
+    }
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let token = line.trim();
+    if token.is_empty() {
+        return Err(anyhow!("No token was given on stdin"));
+    }
+    Ok(token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("token\n", Some("token") ; "with newline")]
+    #[test_case("  token  \r\n", Some("token") ; "with whitespace")]
+    #[test_case("token", Some("token") ; "without newline")]
+    #[test_case("\n", None ; "empty line")]
+    #[test_case("", None ; "no input")]
+    fn read_token(input: &str, expect: Option<&str>) {
+        let res = super::read_token(&ForgeType::GitHub, input.as_bytes());
+        assert_eq!(res.ok().as_deref(), expect);
+    }
+
+    #[test_case(&["ubi", "token", "set", "github"], "set", "github" ; "set")]
+    #[test_case(&["ubi", "token", "delete", "gitlab"], "delete", "gitlab" ; "delete")]
+    fn token_cmd_parses(args: &[&str], expect_action: &str, expect_forge: &str) {
+        let matches = crate::cmd().try_get_matches_from(args).unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "token");
+        let (action, matches) = matches.subcommand().unwrap();
+        assert_eq!(action, expect_action);
+        assert_eq!(
+            matches.get_one::<String>("forge").map(String::as_str),
+            Some(expect_forge),
+        );
+    }
+
+    #[test_case(&["ubi", "token", "set"] ; "missing forge")]
+    #[test_case(&["ubi", "token", "set", "forgejo"] ; "unknown forge")]
+    #[test_case(&["ubi", "token"] ; "missing action")]
+    #[test_case(&["ubi", "--project", "houseabsolute/precious", "token", "set", "github"] ; "with install args")]
+    fn token_cmd_errors(args: &[&str]) {
+        assert!(crate::cmd().try_get_matches_from(args).is_err());
+    }
+}
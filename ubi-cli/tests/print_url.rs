@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::{fs, process::Command};
+use tempfile::tempdir;
+
+const RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz",
+      "name": "project-Linux-x86_64.tar.gz"
+    },
+    {
+      "url": "{url}/download/project-Darwin-x86_64.tar.gz",
+      "name": "project-Darwin-x86_64.tar.gz"
+    }
+  ]
+}"#;
+
+#[test]
+fn print_url() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(200)
+        .with_body(RELEASE_RESPONSE.replace("{url}", &url))
+        .create();
+    let download_mock = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+
+    let td = tempdir()?;
+    let output = Command::new(env!("CARGO_BIN_EXE_ubi"))
+        .args([
+            "--project",
+            "test/project",
+            "--api-base-url",
+            &url,
+            "--in",
+            &td.path().to_string_lossy(),
+            "--asset-name",
+            "project-Linux-x86_64.tar.gz",
+            "--print-url",
+        ])
+        .env_remove("GITHUB_TOKEN")
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "ubi failed: {}",
+        String::from_utf8_lossy(&output.stderr),
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("{url}/download/project-Linux-x86_64.tar.gz\n"),
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+    release_mock.assert();
+    download_mock.assert();
+
+    Ok(())
+}
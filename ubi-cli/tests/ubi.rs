@@ -4,7 +4,9 @@ use std::os::unix::fs::PermissionsExt;
 #[cfg(target_family = "unix")]
 use std::os::unix::prelude::*;
 use std::{
-    env, fs,
+    env,
+    ffi::OsStr,
+    fs, iter,
     path::{Path, PathBuf},
     process,
 };
@@ -571,8 +573,22 @@ fn run_test(td: &Path, cmd: &Path, args: &[&str], expect: PathBuf) -> Result<boo
     if debug {
         args.push("--debug");
     }
+    // The default install dir depends on the user's env, so we install into `./bin` unless the
+    // test picks a dir itself.
+    if !args.iter().any(|a| *a == "--in" || *a == "--self-upgrade") {
+        args.extend(["--in", "bin"]);
+    }
 
-    check_command_result(cmd, &args, debug)?;
+    // We add the install dir to the `PATH` so that ubi doesn't print a hint about adding it.
+    let install_dir = td.join(
+        expect
+            .parent()
+            .ok_or_else(|| anyhow!("expected path has no parent"))?,
+    );
+    let path = env::join_paths(
+        iter::once(install_dir).chain(env::split_paths(&env::var_os("PATH").unwrap_or_default())),
+    )?;
+    check_command_result(cmd, &args, debug, &path)?;
     if let Err(e) = check_installed_binary(expect) {
         dump_tree(td)?;
         return Err(e);
@@ -594,13 +610,17 @@ fn clean_tempdir(td: &Path) -> Result<()> {
     Ok(())
 }
 
-fn check_command_result(cmd: &Path, args: &[&str], debug: bool) -> Result<()> {
-    let (stdout, stderr) = run_command(cmd, args)?;
+fn check_command_result(cmd: &Path, args: &[&str], debug: bool, path: &OsStr) -> Result<()> {
+    let (stdout, stderr) = run_command_with_path(cmd, args, Some(path))?;
 
-    if cfg!(windows) && args.contains(&"--self-upgrade") {
-        assert!(stdout.unwrap_or_default().contains(
-            "The self-upgrade operation left an old binary behind that must be deleted manually"
-        ));
+    if args.contains(&"--self-upgrade") {
+        let stdout = stdout.unwrap_or_default();
+        assert!(stdout.contains("Upgraded ubi at"));
+        if cfg!(windows) {
+            assert!(stdout.contains(
+                "The self-upgrade operation left an old binary behind that must be deleted manually"
+            ));
+        }
     } else {
         assert_eq!(
             stdout.unwrap_or_default(),
@@ -622,10 +642,21 @@ fn check_command_result(cmd: &Path, args: &[&str], debug: bool) -> Result<()> {
 }
 
 fn run_command(cmd: &Path, args: &[&str]) -> Result<(Option<String>, Option<String>)> {
+    run_command_with_path(cmd, args, None)
+}
+
+fn run_command_with_path(
+    cmd: &Path,
+    args: &[&str],
+    path: Option<&OsStr>,
+) -> Result<(Option<String>, Option<String>)> {
     let mut c = process::Command::new(cmd);
     for a in args {
         c.arg(a);
     }
+    if let Some(path) = path {
+        c.env("PATH", path);
+    }
     c.env(
         "GITHUB_TOKEN",
         env::var("GITHUB_TOKEN").as_deref().unwrap_or(""),
@@ -4,7 +4,8 @@ use regex::Regex;
 use std::sync::LazyLock;
 
 // This is a special case to account for the fact that MacOS ARM systems can
-// also run x86-64 binaries.
+// also run x86-64 binaries. Universal binaries are matched separately with
+// `macos_universal_re`.
 pub(crate) fn macos_aarch64_re() -> &'static Lazy<Regex> {
     regex!(
         r"(?ix)
@@ -23,6 +24,30 @@ pub(crate) fn macos_aarch64_re() -> &'static Lazy<Regex> {
             x64
             |
             amd64
+        )
+        (?:
+            \b
+            |
+            _
+        )
+        "
+    )
+}
+
+// These are macOS binaries that run on every macOS arch. We only look for this on macOS, since these
+// words could mean anything elsewhere.
+pub(crate) fn macos_universal_re() -> &'static Lazy<Regex> {
+    regex!(
+        r"(?ix)
+        (?:
+            \b
+            |
+            _
+        )
+        (?:
+            universal2?
+            |
+            fat
             |
             all
         )
@@ -65,7 +90,71 @@ pub(crate) fn arm_re() -> &'static Lazy<Regex> {
             |
             _
         )
-        arm(?:v[0-7])?
+        (?:
+            arm(?:v[0-7][alk]?)?(?:hf|el)?
+            |
+            # Some projects only put the ABI in the name, like
+            # project-linux-gnueabihf.
+            (?:gnu|musl)?eabi(?:hf)?
+        )
+        (?:
+            \b
+            |
+            _
+        )
+        "
+    )
+}
+
+// These are used to pick between hard-float and soft-float assets for 32-bit
+// ARM. They only need to match assets that already matched `arm_re`.
+pub(crate) fn arm_hard_float_re() -> &'static Lazy<Regex> {
+    regex!(
+        r"(?ix)
+        (?:
+            eabihf
+            |
+            arm(?:v[0-7][alk]?)?hf
+        )
+        (?:
+            \b
+            |
+            _
+        )
+        "
+    )
+}
+
+pub(crate) fn arm_soft_float_re() -> &'static Lazy<Regex> {
+    regex!(
+        r"(?ix)
+        (?:
+            eabi
+            |
+            armel
+        )
+        (?:
+            \b
+            |
+            _
+        )
+        "
+    )
+}
+
+pub(crate) fn loongarch64_re() -> &'static Lazy<Regex> {
+    regex!(
+        r"(?ix)
+        (?:
+            \b
+            |
+            _
+        )
+        (?:
+            loong(?:arch)?_?64
+            |
+            la64
+        )
         (?:
             \b
             |
@@ -243,7 +332,11 @@ pub(crate) fn riscv64_re() -> &'static Lazy<Regex> {
             |
             _
         )
-        riscv(_?64)?
+        (?:
+            riscv(?:_?64(?:gc)?)?
+            |
+            rv64(?:gc)?
+        )
         (?:
             \b
             |
@@ -298,7 +391,7 @@ pub(crate) fn x86_32_re() -> &'static Lazy<Regex> {
             _
         )
         (?:
-            386 | i586 | i686
+            i?386 | i586 | i686
             |
             x86[_-]32
             |
@@ -311,6 +404,22 @@ pub(crate) fn x86_32_re() -> &'static Lazy<Regex> {
             |
             _
         )
+        |
+        # A bare `x86`, as in `windows-x86.zip`, is 32-bit, but we can't let
+        # this match the start of `x86_64` or `x86-64`.
+        (?:
+            \b
+            |
+            _
+        )
+        x86
+        (?:
+            $
+            |
+            [^\w-]
+            |
+            -(?:[^6]|6[^4])
+        )
         "
     )
 }
@@ -324,11 +433,9 @@ pub(crate) fn x86_64_re() -> &'static Lazy<Regex> {
             _
         )
         (?:
-            386 | i586 | i686
+            i?386 | i586 | i686
             |
-            x86[_-]32
-            |
-            x86[_-]64
+            x86(?:[_-](?:32|64))?
             |
             x64
             |
@@ -354,6 +461,7 @@ pub(crate) static ALL_ARCHES_RE: LazyLock<Regex> = LazyLock::new(|| {
         &[
             aarch64_re(),
             arm_re(),
+            loongarch64_re(),
             mipsle_re(),
             mips_re(),
             mips64le_re(),
@@ -0,0 +1,486 @@
+use crate::{
+    forge::{Forge, ReleaseAssets},
+    project,
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::debug;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const API_VERSION: &str = "7.1";
+
+#[derive(Debug)]
+pub(crate) struct AzureDevOps {
+    project_name: String,
+    tag: Option<String>,
+    api_base_url: Url,
+    token: Option<String>,
+    retry_policy: RetryPolicy,
+}
+
+unsafe impl Send for AzureDevOps {}
+unsafe impl Sync for AzureDevOps {}
+
+// Azure DevOps repos don't have releases. Instead, we use the artifacts published by the most recent
+// successful pipeline build of the repo. Builds can only be filtered by the repo's id, not its name,
+// so we have to look up the repo first.
+#[derive(Debug, Deserialize, Serialize)]
+struct Repository {
+    id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct List<T> {
+    value: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Build {
+    id: u64,
+    source_branch: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Artifact {
+    name: String,
+    resource: ArtifactResource,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactResource {
+    download_url: Url,
+}
+
+impl From<Artifact> for Asset {
+    // An artifact is always downloaded as a zip file containing the artifact's files.
+    fn from(artifact: Artifact) -> Self {
+        Asset {
+            name: format!("{}.zip", artifact.name),
+            url: artifact.resource.download_url,
+            size: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for AzureDevOps {
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let repo = self
+            .make_release_info_request(client)
+            .await?
+            .json::<Repository>()
+            .await?;
+
+        let url = self.builds_url(&repo.id);
+        debug!("Getting the latest successful build from {url}");
+        let Some(build) = self
+            .make_api_request(client, url)
+            .await?
+            .json::<List<Build>>()
+            .await?
+            .value
+            .into_iter()
+            .next()
+        else {
+            return Err(match &self.tag {
+                Some(tag) => anyhow!(
+                    "could not find a successful build of {} for the tag {tag}",
+                    self.project_name,
+                ),
+                None => anyhow!(
+                    "could not find any successful builds of {}",
+                    self.project_name
+                ),
+            });
+        };
+
+        let url = self.artifacts_url(build.id);
+        debug!("Getting the artifacts for build {} from {url}", build.id);
+        let artifacts = self
+            .make_api_request(client, url)
+            .await?
+            .json::<List<Artifact>>()
+            .await?
+            .value;
+
+        Ok(ReleaseAssets {
+            // The latest build may not be for a tag, in which case we don't know what release it is.
+            tag: build
+                .source_branch
+                .strip_prefix("refs/tags/")
+                .map(String::from),
+            assets: artifacts.into_iter().map(Asset::from).collect(),
+        })
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        let (_, _, repo) = project::split_azure_devops_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        let mut url = self.project_api_url(&["git", "repositories", repo]);
+        url.query_pairs_mut()
+            .append_pair("api-version", API_VERSION);
+
+        url
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
+        if let Some(token) = self.token.as_deref() {
+            debug!("Adding Azure DevOps token to Azure DevOps request.");
+            // A personal access token is sent as the password with an empty username.
+            req_builder = req_builder.basic_auth("", Some(token));
+        } else {
+            debug!("No Azure DevOps token found.");
+        }
+        Ok(req_builder)
+    }
+}
+
+impl AzureDevOps {
+    pub(crate) fn new(
+        project_name: String,
+        tag: Option<String>,
+        api_base_url: Url,
+        token: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            project_name,
+            tag,
+            api_base_url,
+            token,
+            retry_policy,
+        }
+    }
+
+    // Every API URL we use is under `<org>/<project>/_apis`.
+    fn project_api_url(&self, path: &[&str]) -> Url {
+        let (org, project, _) = project::split_azure_devops_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            .pop_if_empty()
+            .push(org)
+            .push(project)
+            .push("_apis")
+            .extend(path);
+
+        url
+    }
+
+    fn builds_url(&self, repo_id: &str) -> Url {
+        let mut url = self.project_api_url(&["build", "builds"]);
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("repositoryId", repo_id)
+                .append_pair("repositoryType", "TfsGit")
+                .append_pair("statusFilter", "completed")
+                .append_pair("resultFilter", "succeeded")
+                .append_pair("queryOrder", "finishTimeDescending")
+                .append_pair("$top", "1");
+            if let Some(tag) = &self.tag {
+                query.append_pair("branchName", &format!("refs/tags/{tag}"));
+            }
+            query.append_pair("api-version", API_VERSION);
+        }
+
+        url
+    }
+
+    fn artifacts_url(&self, build_id: u64) -> Url {
+        let mut url =
+            self.project_api_url(&["build", "builds", &build_id.to_string(), "artifacts"]);
+        url.query_pairs_mut()
+            .append_pair("api-version", API_VERSION);
+
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Mock, Server};
+    use reqwest::Client;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn fetch_assets_without_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mocks = mock_artifacts(&mut server, Matcher::Missing, None).await;
+
+        let azure = AzureDevOps::new(
+            "some-org/some-project/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got = azure.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            got.assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ubi-Linux-x86_64-musl.zip", "ubi-Windows-msvc-x86_64.zip"],
+        );
+        assert_eq!(
+            got.assets[0].url,
+            Url::parse(
+                "https://dev.azure.com/some-org/some-project/_apis/build/builds/42/artifacts?artifactName=ubi-Linux-x86_64-musl&api-version=7.1&%24format=zip"
+            )?,
+        );
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        // This is ":some-pat" base64 encoded.
+        let mocks = mock_artifacts(
+            &mut server,
+            Matcher::Exact("Basic OnNvbWUtcGF0".to_string()),
+            None,
+        )
+        .await;
+
+        let azure = AzureDevOps::new(
+            "some-org/some-project/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            Some("some-pat".to_string()),
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got = azure.fetch_assets(&client).await?;
+        assert_eq!(got.assets.len(), 2);
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_tag() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mocks = mock_artifacts(&mut server, Matcher::Missing, Some("v1.0.0")).await;
+
+        let azure = AzureDevOps::new(
+            "some-org/some-project/ubi".to_string(),
+            Some("v1.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got = azure.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(got.assets.len(), 2);
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_no_builds() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let repo_mock = mock_repo(&mut server, Matcher::Missing).await;
+        let builds_mock = server
+            .mock("GET", "/some-org/some-project/_apis/build/builds")
+            .match_query(Matcher::UrlEncoded(
+                "branchName".to_string(),
+                "refs/tags/v2.0.0".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"count": 0, "value": []}"#)
+            .create_async()
+            .await;
+
+        let azure = AzureDevOps::new(
+            "some-org/some-project/ubi".to_string(),
+            Some("v2.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let err = azure.fetch_assets(&client).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "could not find a successful build of some-org/some-project/ubi for the tag v2.0.0",
+        );
+
+        repo_mock.assert_async().await;
+        builds_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    async fn mock_repo(server: &mut Server, authorization_header_matcher: Matcher) -> Mock {
+        server
+            .mock("GET", "/some-org/some-project/_apis/git/repositories/ubi")
+            .match_query(Matcher::UrlEncoded(
+                "api-version".to_string(),
+                "7.1".to_string(),
+            ))
+            .match_header("Authorization", authorization_header_matcher)
+            .with_status(200)
+            .with_body(REPOSITORY_RESPONSE)
+            .create_async()
+            .await
+    }
+
+    async fn mock_artifacts(
+        server: &mut Server,
+        authorization_header_matcher: Matcher,
+        tag: Option<&str>,
+    ) -> Vec<Mock> {
+        let repo_mock = mock_repo(server, authorization_header_matcher.clone()).await;
+
+        let mut query = vec![
+            Matcher::UrlEncoded(
+                "repositoryId".to_string(),
+                "3c9a8f4e-1b2d-4e5f-9a6b-7c8d9e0f1a2b".to_string(),
+            ),
+            Matcher::UrlEncoded("resultFilter".to_string(), "succeeded".to_string()),
+            Matcher::UrlEncoded("$top".to_string(), "1".to_string()),
+        ];
+        if let Some(tag) = tag {
+            query.push(Matcher::UrlEncoded(
+                "branchName".to_string(),
+                format!("refs/tags/{tag}"),
+            ));
+        }
+        let builds_mock = server
+            .mock("GET", "/some-org/some-project/_apis/build/builds")
+            .match_query(Matcher::AllOf(query))
+            .match_header("Authorization", authorization_header_matcher.clone())
+            .with_status(200)
+            .with_body(BUILDS_RESPONSE)
+            .create_async()
+            .await;
+        let artifacts_mock = server
+            .mock(
+                "GET",
+                "/some-org/some-project/_apis/build/builds/42/artifacts",
+            )
+            .match_query(Matcher::UrlEncoded(
+                "api-version".to_string(),
+                "7.1".to_string(),
+            ))
+            .match_header("Authorization", authorization_header_matcher)
+            .with_status(200)
+            .with_body(ARTIFACTS_RESPONSE)
+            .create_async()
+            .await;
+
+        vec![repo_mock, builds_mock, artifacts_mock]
+    }
+
+    const REPOSITORY_RESPONSE: &str = r#"
+{
+  "id": "3c9a8f4e-1b2d-4e5f-9a6b-7c8d9e0f1a2b",
+  "name": "ubi",
+  "defaultBranch": "refs/heads/main"
+}
+"#;
+
+    const BUILDS_RESPONSE: &str = r#"
+{
+  "count": 1,
+  "value": [
+    {
+      "id": 42,
+      "buildNumber": "20250101.1",
+      "status": "completed",
+      "result": "succeeded",
+      "sourceBranch": "refs/tags/v1.0.0"
+    }
+  ]
+}
+"#;
+
+    const ARTIFACTS_RESPONSE: &str = r#"
+{
+  "count": 2,
+  "value": [
+    {
+      "id": 7,
+      "name": "ubi-Linux-x86_64-musl",
+      "resource": {
+        "type": "Container",
+        "downloadUrl": "https://dev.azure.com/some-org/some-project/_apis/build/builds/42/artifacts?artifactName=ubi-Linux-x86_64-musl&api-version=7.1&%24format=zip"
+      }
+    },
+    {
+      "id": 8,
+      "name": "ubi-Windows-msvc-x86_64",
+      "resource": {
+        "type": "Container",
+        "downloadUrl": "https://dev.azure.com/some-org/some-project/_apis/build/builds/42/artifacts?artifactName=ubi-Windows-msvc-x86_64&api-version=7.1&%24format=zip"
+      }
+    }
+  ]
+}
+"#;
+
+    #[test]
+    fn api_base_url() {
+        let azure = AzureDevOps::new(
+            "some-org/some-project/ubi".to_string(),
+            Some("v1.0.0".to_string()),
+            Url::parse("https://azure.example.com/tfs/").unwrap(),
+            None,
+            RetryPolicy::default(),
+        );
+        assert_eq!(
+            azure.release_info_url().as_str(),
+            "https://azure.example.com/tfs/some-org/some-project/_apis/git/repositories/ubi?api-version=7.1",
+        );
+        assert_eq!(
+            azure.builds_url("some-id").as_str(),
+            "https://azure.example.com/tfs/some-org/some-project/_apis/build/builds?repositoryId=some-id&repositoryType=TfsGit&statusFilter=completed&resultFilter=succeeded&queryOrder=finishTimeDescending&%24top=1&branchName=refs%2Ftags%2Fv1.0.0&api-version=7.1",
+        );
+        assert_eq!(
+            azure.artifacts_url(42).as_str(),
+            "https://azure.example.com/tfs/some-org/some-project/_apis/build/builds/42/artifacts?api-version=7.1",
+        );
+    }
+}
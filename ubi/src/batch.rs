@@ -0,0 +1,146 @@
+use crate::{Ubi, UbiBuilder};
+use anyhow::{Context, Result};
+use futures::{stream, StreamExt};
+use log::debug;
+use std::path::{Path, PathBuf};
+
+/// The default number of release info requests that [`install_many`] makes at once.
+pub const DEFAULT_INSTALL_MANY_CONCURRENCY: usize = 8;
+
+/// `InstallSpec` describes one project to install with [`install_many`].
+#[derive(Debug)]
+pub struct InstallSpec<'a> {
+    project: &'a str,
+    tag: Option<&'a str>,
+    install_dir: Option<PathBuf>,
+    builder: Option<UbiBuilder<'a>>,
+}
+
+/// `InstalledBinary` describes a project installed by [`install_many`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstalledBinary {
+    /// The project that was installed, as passed to [`InstallSpec::new`].
+    pub project: String,
+    /// The name of the release asset that was installed.
+    pub asset_name: String,
+    /// The path that was installed. See [`InstallPlan::install_path`](crate::InstallPlan::install_path) for details.
+    pub install_path: PathBuf,
+    /// The tag of the release that was installed. See
+    /// [`InstallResult::tag`](crate::InstallResult::tag) for when this is `None`.
+    pub tag: Option<String>,
+    /// The SHA-256 digest of the downloaded release asset, as a lowercase hex string. This is
+    /// `None` if nothing was downloaded, because this was a dry run or the asset was up to date.
+    pub sha256: Option<String>,
+}
+
+impl<'a> InstallSpec<'a> {
+    /// Returns a new spec for the given project. See [`UbiBuilder::project`] for the accepted
+    /// formats.
+    #[must_use]
+    pub fn new(project: &'a str) -> Self {
+        Self {
+            project,
+            tag: None,
+            install_dir: None,
+            builder: None,
+        }
+    }
+
+    /// Set the tag to install. See [`UbiBuilder::tag`] for details.
+    #[must_use]
+    pub fn tag(mut self, tag: &'a str) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Set the directory to install into. See [`UbiBuilder::install_dir`] for details.
+    #[must_use]
+    pub fn install_dir<P: AsRef<Path>>(mut self, install_dir: P) -> Self {
+        self.install_dir = Some(install_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a builder to use for any other options, like a token or forge. The project, tag, and
+    /// install directory from this spec override anything set on the builder.
+    #[must_use]
+    pub fn builder(mut self, builder: UbiBuilder<'a>) -> Self {
+        self.builder = Some(builder);
+        self
+    }
+
+    // Returns the `Ubi` instance for this spec with its release info already fetched.
+    async fn prepare(self) -> Result<Ubi<'a>> {
+        let mut builder = self.builder.unwrap_or_default().project(self.project);
+        if let Some(tag) = self.tag {
+            builder = builder.tag(tag);
+        }
+        if let Some(install_dir) = self.install_dir {
+            builder = builder.install_dir(install_dir);
+        }
+
+        let mut ubi = builder.build()?;
+        ubi.prefetch_release_assets().await?;
+        Ok(ubi)
+    }
+}
+
+/// Installs each of the given projects. The release info for every project is fetched first, with
+/// up to `concurrency` requests in flight at once. Then each project is downloaded and installed in
+/// turn. If `concurrency` is 0, then [`DEFAULT_INSTALL_MANY_CONCURRENCY`] is used.
+///
+/// This returns one result per spec, in the same order as `specs`. A failure for one project does
+/// not stop the others from being installed.
+///
+/// # Errors
+///
+/// Each result can contain any of the errors returned by [`UbiBuilder::build`] and
+/// [`Ubi::install_binary`].
+pub async fn install_many(
+    specs: Vec<InstallSpec<'_>>,
+    concurrency: usize,
+) -> Vec<Result<InstalledBinary>> {
+    let concurrency = if concurrency == 0 {
+        DEFAULT_INSTALL_MANY_CONCURRENCY
+    } else {
+        concurrency
+    };
+    debug!(
+        "fetching release info for {} projects, {concurrency} at a time",
+        specs.len()
+    );
+
+    let mut prepared = stream::iter(specs.into_iter().enumerate())
+        .map(|(i, spec)| async move {
+            let project = spec.project;
+            let res = spec
+                .prepare()
+                .await
+                .with_context(|| format!("could not install {project}"));
+            (i, project, res)
+        })
+        .buffer_unordered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+    prepared.sort_by_key(|(i, _, _)| *i);
+
+    let mut results = vec![];
+    for (_, project, res) in prepared {
+        results.push(install_prepared(project, res).await);
+    }
+    results
+}
+
+async fn install_prepared(project: &str, prepared: Result<Ubi<'_>>) -> Result<InstalledBinary> {
+    let installed = prepared?
+        .install_binary()
+        .await
+        .with_context(|| format!("could not install {project}"))?;
+
+    Ok(InstalledBinary {
+        project: project.to_string(),
+        asset_name: installed.asset_name,
+        install_path: installed.install_path,
+        tag: installed.tag,
+        sha256: installed.sha256,
+    })
+}
@@ -0,0 +1,204 @@
+// This looks at the header of an executable to see which architecture it was built for, so that an
+// asset for the wrong platform is caught before it is installed. Only ELF, Mach-O, and PE files are
+// checked. Anything else, like a script or a jar file, can't be checked, so it is always accepted.
+use anyhow::{anyhow, Context, Result};
+use goblin::{elf, mach, pe};
+use itertools::Itertools;
+use log::debug;
+use platforms::{Arch, Platform, OS};
+use scroll::Pread;
+use std::{fs::File, io::Read, path::Path};
+
+// All of the headers that we look at are near the start of the file.
+const HEADER_LEN: u64 = 64 * 1024;
+
+// goblin doesn't have a constant for this yet.
+const EM_LOONGARCH: u16 = 258;
+
+// The file is usually a temp file when this is called, so `name` is the name of the executable that
+// is used in the error.
+pub(crate) fn check_binary_arch(exe: &Path, name: &str, platform: &Platform) -> Result<()> {
+    let mut head = vec![];
+    File::open(exe)
+        .with_context(|| format!("could not open {} to check its architecture", exe.display()))?
+        .take(HEADER_LEN)
+        .read_to_end(&mut head)?;
+
+    let Some(arches) = binary_arches(&head)
+        .with_context(|| format!("could not read the executable header of {}", exe.display()))?
+    else {
+        debug!(
+            "could not tell which architecture {} is for, so it was not checked",
+            exe.display(),
+        );
+        return Ok(());
+    };
+
+    let accepted = accepted_arches(platform);
+    if arches.iter().any(|a| accepted.contains(a)) {
+        debug!(
+            "{} is an executable for {}, which matches the target platform",
+            exe.display(),
+            arches.iter().map(|a| a.as_str()).join(", "),
+        );
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "the executable {name} is built for {}, but the target platform is {}",
+        arches.iter().map(|a| a.as_str()).join(" and "),
+        platform.target_arch.as_str(),
+    ))
+}
+
+// A universal Mach-O binary contains executables for more than one architecture, so this returns
+// all of them. This returns `None` if the file isn't an executable we can parse, or if it's for an
+// architecture that we don't know about.
+fn binary_arches(head: &[u8]) -> Result<Option<Vec<Arch>>> {
+    if head.starts_with(elf::header::ELFMAG) {
+        let header = head.pread::<elf::header::Header>(0)?;
+        let is_64 = header.e_ident[elf::header::EI_CLASS] == elf::header::ELFCLASS64;
+        return Ok(elf_arch(header.e_machine, is_64).map(|a| vec![a]));
+    }
+
+    if head.starts_with(b"MZ") {
+        let header = pe::header::Header::parse(head)?;
+        return Ok(pe_arch(header.coff_header.machine).map(|a| vec![a]));
+    }
+
+    if head.len() < 4 {
+        return Ok(None);
+    }
+    let (magic, ctx) = mach::parse_magic_and_ctx(head, 0)?;
+    if magic == mach::fat::FAT_MAGIC {
+        let cputypes = mach::MultiArch::new(head)?
+            .iter_arches()
+            .map(|a| a.map(|a| a.cputype()))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(cputypes.into_iter().map(mach_arch).collect());
+    }
+    if let Some(ctx) = ctx {
+        let header = head.pread_with::<mach::header::Header>(0, ctx)?;
+        return Ok(mach_arch(header.cputype()).map(|a| vec![a]));
+    }
+
+    Ok(None)
+}
+
+fn elf_arch(machine: u16, is_64: bool) -> Option<Arch> {
+    Some(match machine {
+        elf::header::EM_386 => Arch::X86,
+        elf::header::EM_X86_64 => Arch::X86_64,
+        elf::header::EM_ARM => Arch::Arm,
+        elf::header::EM_AARCH64 => Arch::AArch64,
+        elf::header::EM_MIPS if is_64 => Arch::Mips64,
+        elf::header::EM_MIPS => Arch::Mips,
+        elf::header::EM_PPC => Arch::PowerPc,
+        elf::header::EM_PPC64 => Arch::PowerPc64,
+        elf::header::EM_RISCV if is_64 => Arch::Riscv64,
+        elf::header::EM_RISCV => Arch::Riscv32,
+        elf::header::EM_S390 => Arch::S390X,
+        elf::header::EM_SPARCV9 => Arch::Sparc64,
+        EM_LOONGARCH => Arch::Loongarch64,
+        _ => return None,
+    })
+}
+
+fn mach_arch(cputype: mach::cputype::CpuType) -> Option<Arch> {
+    Some(match cputype {
+        mach::cputype::CPU_TYPE_X86 => Arch::X86,
+        mach::cputype::CPU_TYPE_X86_64 => Arch::X86_64,
+        mach::cputype::CPU_TYPE_ARM => Arch::Arm,
+        mach::cputype::CPU_TYPE_ARM64 => Arch::AArch64,
+        mach::cputype::CPU_TYPE_POWERPC => Arch::PowerPc,
+        mach::cputype::CPU_TYPE_POWERPC64 => Arch::PowerPc64,
+        _ => return None,
+    })
+}
+
+fn pe_arch(machine: u16) -> Option<Arch> {
+    Some(match machine {
+        pe::header::COFF_MACHINE_X86 => Arch::X86,
+        pe::header::COFF_MACHINE_X86_64 => Arch::X86_64,
+        pe::header::COFF_MACHINE_ARMNT => Arch::Arm,
+        pe::header::COFF_MACHINE_ARM64 => Arch::AArch64,
+        _ => return None,
+    })
+}
+
+// Some platforms can run executables for another architecture. The asset picker will pick an
+// x86-64 asset on macOS ARM, which runs under Rosetta, and Windows can run x86 executables on
+// x86-64, and both x86 and x86-64 executables on ARM.
+fn accepted_arches(platform: &Platform) -> Vec<Arch> {
+    let arch = match platform.target_arch {
+        Arch::Mips32r6 => Arch::Mips,
+        Arch::Mips64r6 => Arch::Mips64,
+        Arch::Arm64ec => Arch::AArch64,
+        a => a,
+    };
+    match (platform.target_os, arch) {
+        (OS::MacOS, Arch::AArch64) => vec![arch, Arch::X86_64],
+        (OS::Windows, Arch::AArch64) => vec![arch, Arch::X86_64, Arch::X86],
+        (OS::Windows, Arch::X86_64) => vec![arch, Arch::X86],
+        _ => vec![arch],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("elf-x86_64", "x86_64-unknown-linux-gnu", Ok(()) ; "ELF x86-64 on Linux x86-64")]
+    #[test_case("elf-aarch64", "aarch64-unknown-linux-musl", Ok(()) ; "ELF AArch64 on Linux AArch64")]
+    #[test_case("elf-arm", "arm-unknown-linux-gnueabihf", Ok(()) ; "32-bit ELF ARM on Linux ARM")]
+    #[test_case(
+        "elf-aarch64", "x86_64-unknown-linux-gnu",
+        Err("the executable elf-aarch64 is built for aarch64, but the target platform is x86_64") ;
+        "ELF AArch64 on Linux x86-64"
+    )]
+    #[test_case("macho-arm64", "aarch64-apple-darwin", Ok(()) ; "Mach-O ARM64 on macOS ARM")]
+    #[test_case("macho-x86_64", "aarch64-apple-darwin", Ok(()) ; "Mach-O x86-64 on macOS ARM under Rosetta")]
+    #[test_case(
+        "macho-arm64", "x86_64-apple-darwin",
+        Err("the executable macho-arm64 is built for aarch64, but the target platform is x86_64") ;
+        "Mach-O ARM64 on macOS x86-64"
+    )]
+    #[test_case("macho-universal", "x86_64-apple-darwin", Ok(()) ; "universal Mach-O on macOS x86-64")]
+    #[test_case("macho-universal", "aarch64-apple-darwin", Ok(()) ; "universal Mach-O on macOS ARM")]
+    #[test_case(
+        "macho-universal", "powerpc-unknown-linux-gnu",
+        Err("the executable macho-universal is built for x86_64 and aarch64, but the target platform is powerpc") ;
+        "universal Mach-O on PowerPC"
+    )]
+    #[test_case("pe-x86_64.exe", "x86_64-pc-windows-msvc", Ok(()) ; "PE x86-64 on Windows x86-64")]
+    #[test_case("pe-x86_64.exe", "aarch64-pc-windows-msvc", Ok(()) ; "PE x86-64 on Windows ARM")]
+    #[test_case("pe-arm64.exe", "aarch64-pc-windows-msvc", Ok(()) ; "PE ARM64 on Windows ARM")]
+    #[test_case(
+        "pe-arm64.exe", "x86_64-pc-windows-msvc",
+        Err("the executable pe-arm64.exe is built for aarch64, but the target platform is x86_64") ;
+        "PE ARM64 on Windows x86-64"
+    )]
+    fn check_binary_arch(fixture: &str, target: &str, expect: Result<(), &str>) -> Result<()> {
+        let platform = Platform::find(target).unwrap();
+        let path = Path::new("test-data/binary-arch").join(fixture);
+        assert_eq!(
+            super::check_binary_arch(&path, fixture, platform).map_err(|e| e.to_string()),
+            expect.map_err(String::from),
+        );
+        Ok(())
+    }
+
+    #[test_case(b"#!/bin/sh\necho project\n" ; "shell script")]
+    #[test_case(b"PK\x03\x04" ; "zip file")]
+    #[test_case(b"" ; "empty file")]
+    fn binary_arches_for_other_files(head: &[u8]) -> Result<()> {
+        assert_eq!(binary_arches(head)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn binary_arches_for_truncated_elf() {
+        assert!(binary_arches(&b"\x7fELF\x02\x01\x01"[..]).is_err());
+    }
+}
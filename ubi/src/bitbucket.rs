@@ -0,0 +1,405 @@
+use crate::{
+    forge::{Forge, ReleaseAssets},
+    project,
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use itertools::Itertools;
+use log::debug;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug)]
+pub(crate) struct Bitbucket {
+    project_name: String,
+    tag: Option<String>,
+    api_base_url: Url,
+    username: Option<String>,
+    app_password: Option<String>,
+    retry_policy: RetryPolicy,
+}
+
+unsafe impl Send for Bitbucket {}
+unsafe impl Sync for Bitbucket {}
+
+// Bitbucket doesn't have releases. Instead, each repo has a list of downloads. This list is
+// paginated, with each page containing a link to the `next` page, if there is one.
+#[derive(Debug, Deserialize, Serialize)]
+struct DownloadsPage {
+    values: Vec<Download>,
+    #[serde(default)]
+    next: Option<Url>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Download {
+    name: String,
+    #[serde(default)]
+    size: Option<u64>,
+    links: DownloadLinks,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DownloadLinks {
+    #[serde(rename = "self")]
+    self_link: Link,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Link {
+    href: Url,
+}
+
+impl From<Download> for Asset {
+    fn from(download: Download) -> Self {
+        Asset {
+            name: download.name,
+            url: download.links.self_link.href,
+            size: download.size,
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for Bitbucket {
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let mut assets: Vec<Asset> = vec![];
+
+        let mut page = self
+            .make_release_info_request(client)
+            .await?
+            .json::<DownloadsPage>()
+            .await?;
+        loop {
+            assets.extend(page.values.into_iter().map(Asset::from));
+            let Some(next) = page.next else {
+                break;
+            };
+            debug!("Getting next page of downloads from {next}");
+            page = self
+                .make_api_request(client, next)
+                .await?
+                .json::<DownloadsPage>()
+                .await?;
+        }
+
+        // Downloads don't belong to a release, so we can't tell which tag the latest one is for.
+        let Some(tag) = &self.tag else {
+            return Ok(ReleaseAssets { tag: None, assets });
+        };
+
+        // Since there are no releases, the best we can do with a tag is to look for downloads that
+        // include the tag in their name.
+        debug!("filtering downloads for names containing the tag {tag}");
+        let all_names = assets.iter().map(|a| &a.name).join(", ");
+        let assets = assets
+            .into_iter()
+            .filter(|a| a.name.contains(tag.as_str()))
+            .collect::<Vec<_>>();
+        if assets.is_empty() {
+            return Err(anyhow!(
+                "could not find any downloads with names containing the tag {tag} from {all_names}",
+            ));
+        }
+
+        Ok(ReleaseAssets {
+            tag: Some(tag.clone()),
+            assets,
+        })
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        let (workspace, repo) = project::split_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            .push("repositories")
+            .push(workspace)
+            .push(repo)
+            .push("downloads");
+
+        url
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
+        if let (Some(username), Some(app_password)) = (&self.username, &self.app_password) {
+            debug!("Adding Bitbucket app password to Bitbucket request.");
+            req_builder = req_builder.basic_auth(username, Some(app_password));
+        } else {
+            debug!("No Bitbucket app password found.");
+        }
+        Ok(req_builder)
+    }
+}
+
+impl Bitbucket {
+    pub(crate) fn new(
+        project_name: String,
+        tag: Option<String>,
+        api_base_url: Url,
+        username: Option<String>,
+        app_password: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            project_name,
+            tag,
+            api_base_url,
+            username,
+            app_password,
+            retry_policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use reqwest::Client;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn fetch_assets_without_app_password() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let (m1, m2) = mock_downloads(&mut server, Matcher::Missing).await;
+
+        let bitbucket = Bitbucket::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got_assets = bitbucket.fetch_assets(&client).await?.assets;
+        assert_eq!(
+            got_assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "ubi-v1.0.0-Linux-x86_64-musl.tar.gz",
+                "ubi-v1.0.0-Windows-msvc-x86_64.zip",
+                "ubi-v0.9.0-Linux-x86_64-musl.tar.gz",
+            ],
+        );
+        assert_eq!(
+            got_assets[0].url,
+            Url::parse(
+                "https://api.bitbucket.org/2.0/repositories/houseabsolute/ubi/downloads/ubi-v1.0.0-Linux-x86_64-musl.tar.gz"
+            )?,
+        );
+        assert_eq!(got_assets[0].size, Some(2_906_752));
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_app_password() -> Result<()> {
+        let mut server = Server::new_async().await;
+        // This is "autarch:app-password" base64 encoded.
+        let (m1, m2) = mock_downloads(
+            &mut server,
+            Matcher::Exact("Basic YXV0YXJjaDphcHAtcGFzc3dvcmQ=".to_string()),
+        )
+        .await;
+
+        let bitbucket = Bitbucket::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            Some("autarch".to_string()),
+            Some("app-password".to_string()),
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got_assets = bitbucket.fetch_assets(&client).await?.assets;
+        assert_eq!(got_assets.len(), 3);
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_tag() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let (m1, m2) = mock_downloads(&mut server, Matcher::Missing).await;
+
+        let bitbucket = Bitbucket::new(
+            "houseabsolute/ubi".to_string(),
+            Some("v0.9.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got_assets = bitbucket.fetch_assets(&client).await?.assets;
+        assert_eq!(
+            got_assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ubi-v0.9.0-Linux-x86_64-musl.tar.gz"],
+        );
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_tag_that_does_not_match() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let (m1, m2) = mock_downloads(&mut server, Matcher::Missing).await;
+
+        let bitbucket = Bitbucket::new(
+            "houseabsolute/ubi".to_string(),
+            Some("v2.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let res = bitbucket.fetch_assets(&client).await;
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .starts_with("could not find any downloads with names containing the tag v2.0.0"));
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        Ok(())
+    }
+
+    async fn mock_downloads(
+        server: &mut Server,
+        authorization_header_matcher: Matcher,
+    ) -> (mockito::Mock, mockito::Mock) {
+        let first_page = BITBUCKET_FIRST_PAGE_RESPONSE.replace(
+            "{next}",
+            &format!(
+                "{}/repositories/houseabsolute/ubi/downloads?page=2",
+                server.url()
+            ),
+        );
+        let m1 = server
+            .mock("GET", "/repositories/houseabsolute/ubi/downloads")
+            .match_query(Matcher::Missing)
+            .match_header("Authorization", authorization_header_matcher.clone())
+            .with_status(200)
+            .with_body(first_page)
+            .create_async()
+            .await;
+        let m2 = server
+            .mock("GET", "/repositories/houseabsolute/ubi/downloads")
+            .match_query(Matcher::UrlEncoded("page".to_string(), "2".to_string()))
+            .match_header("Authorization", authorization_header_matcher)
+            .with_status(200)
+            .with_body(BITBUCKET_SECOND_PAGE_RESPONSE)
+            .create_async()
+            .await;
+
+        (m1, m2)
+    }
+
+    const BITBUCKET_FIRST_PAGE_RESPONSE: &str = r#"
+{
+  "pagelen": 2,
+  "size": 3,
+  "page": 1,
+  "next": "{next}",
+  "values": [
+    {
+      "name": "ubi-v1.0.0-Linux-x86_64-musl.tar.gz",
+      "size": 2906752,
+      "downloads": 3,
+      "links": {
+        "self": {
+          "href": "https://api.bitbucket.org/2.0/repositories/houseabsolute/ubi/downloads/ubi-v1.0.0-Linux-x86_64-musl.tar.gz"
+        }
+      }
+    },
+    {
+      "name": "ubi-v1.0.0-Windows-msvc-x86_64.zip",
+      "size": 2506752,
+      "downloads": 0,
+      "links": {
+        "self": {
+          "href": "https://api.bitbucket.org/2.0/repositories/houseabsolute/ubi/downloads/ubi-v1.0.0-Windows-msvc-x86_64.zip"
+        }
+      }
+    }
+  ]
+}
+"#;
+
+    const BITBUCKET_SECOND_PAGE_RESPONSE: &str = r#"
+{
+  "pagelen": 2,
+  "size": 3,
+  "page": 2,
+  "values": [
+    {
+      "name": "ubi-v0.9.0-Linux-x86_64-musl.tar.gz",
+      "size": 2806752,
+      "downloads": 12,
+      "links": {
+        "self": {
+          "href": "https://api.bitbucket.org/2.0/repositories/houseabsolute/ubi/downloads/ubi-v0.9.0-Linux-x86_64-musl.tar.gz"
+        }
+      }
+    }
+  ]
+}
+"#;
+
+    #[test]
+    fn api_base_url() {
+        let bitbucket = Bitbucket::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse("https://bitbucket.example.com/2.0").unwrap(),
+            None,
+            None,
+            RetryPolicy::default(),
+        );
+        let url = bitbucket.release_info_url();
+        assert_eq!(
+            url.as_str(),
+            "https://bitbucket.example.com/2.0/repositories/houseabsolute/ubi/downloads"
+        );
+    }
+}
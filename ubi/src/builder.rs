@@ -1,21 +1,49 @@
+#[cfg(feature = "progress")]
+use crate::style::ColorChoice;
 /// The `builder` module contains the `UbiBuilder` struct which is used to create a `Ubi` instance.
 use crate::{
-    forge::{Forge, ForgeType},
-    installer::{ArchiveInstaller, ExeInstaller, Installer},
-    picker::AssetPicker,
-    ubi::Ubi,
+    cache::{DownloadCache, DEFAULT_CACHE_FILE_NAME},
+    checksum,
+    extension::Extension,
+    forge::{self, Forge, ForgeOptions, ForgeType},
+    gitlab,
+    gpg::GpgVerifier,
+    install_lock::DEFAULT_INSTALL_LOCK_TIMEOUT,
+    install_meta::InstallMeta,
+    installer::{AllExesInstaller, ArchiveInstaller, AsIsInstaller, ExeInstaller, Installer},
+    interactive,
+    keyring::{Keyring, OsKeyring},
+    netrc,
+    oci::{OciReference, GHCR_DOMAIN},
+    package::{Package, DEFAULT_PACKAGE_PREFIX},
+    picker::{AssetPicker, AssetSelector, Libc, ScoringWeights, DEFAULT_EXTENSION_PREFERENCE},
+    post_install::PostInstallHook,
+    progress::DownloadProgress,
+    project::{self, ProjectRef},
+    release_cache::{ReleaseInfoCache, DEFAULT_RELEASE_INFO_CACHE_TTL},
+    retry::{self, RetryPolicy, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BASE_DELAY},
+    signature::SignatureVerifier,
+    tls::TlsVersion,
+    ubi::{Asset, Ubi},
+    version::VersionReq,
 };
-use anyhow::{anyhow, Result};
-use log::debug;
+use anyhow::{anyhow, Context, Result};
+use glob::Pattern;
+use log::{debug, warn};
 use platforms::{Platform, PlatformReq, OS};
+use regex::Regex;
 use reqwest::{
-    header::{HeaderMap, HeaderValue, USER_AGENT},
-    Client,
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    Certificate, Client, NoProxy, Proxy,
 };
 use std::{
     env,
+    ffi::OsString,
+    fs,
+    num::NonZeroU64,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 use url::Url;
 use which::which;
@@ -26,17 +54,79 @@ use which::which;
 pub struct UbiBuilder<'a> {
     project: Option<&'a str>,
     tag: Option<&'a str>,
+    version_req: Option<&'a str>,
+    tag_pattern: Option<&'a str>,
+    include_prereleases: bool,
     url: Option<&'a str>,
+    local_asset: Option<PathBuf>,
     install_dir: Option<PathBuf>,
+    stage_dir: Option<PathBuf>,
+    package_output: Option<PathBuf>,
+    package_prefix: Option<&'a str>,
     matching: Option<&'a str>,
+    matching_regex: Option<&'a str>,
+    exact_asset_name: Option<&'a str>,
     exe: Option<&'a str>,
     rename_exe_to: Option<&'a str>,
     extract_all: bool,
+    strip_components: Option<usize>,
+    all_exes: bool,
+    exe_glob: Option<&'a str>,
+    install_as_is: bool,
+    versioned_install: bool,
+    completions_dir: Option<PathBuf>,
     token: Option<&'a str>,
+    use_keyring: bool,
     platform: Option<&'a Platform>,
     is_musl: Option<bool>,
+    libc: Option<Libc>,
+    prefer_static: bool,
+    prefer_gnu_on_windows: bool,
+    extension_preference: Option<Vec<&'a str>>,
+    scoring_weights: ScoringWeights,
+    interactive: bool,
     api_base_url: Option<&'a str>,
+    gitlab_url: Option<&'a str>,
     forge: Option<ForgeType>,
+    verify_signature: Option<&'a str>,
+    verify_gpg: Vec<&'a str>,
+    verify_checksum: bool,
+    expect_sha256: Option<&'a str>,
+    verify_binary_arch: bool,
+    dry_run: bool,
+    force: bool,
+    skip_if_exists: bool,
+    ensure: bool,
+    install_lock_timeout: Option<Duration>,
+    cache_downloads: bool,
+    cache_file: Option<PathBuf>,
+    cache_release_info: bool,
+    release_info_cache_dir: Option<PathBuf>,
+    release_info_cache_ttl: Option<Duration>,
+    refresh_release_info: bool,
+    keep_archive: bool,
+    keep_archive_dir: Option<PathBuf>,
+    max_retries: Option<u32>,
+    retry_base_delay: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    max_download_rate: Option<u64>,
+    proxy: Option<&'a str>,
+    ca_cert: Option<PathBuf>,
+    danger_accept_invalid_certs: bool,
+    min_tls_version: TlsVersion,
+    headers: Vec<(&'a str, &'a str)>,
+    allow_reserved_headers: bool,
+    basic_auth: Option<(&'a str, &'a str)>,
+    user_agent: Option<&'a str>,
+    #[cfg(feature = "progress")]
+    progress: bool,
+    #[cfg(feature = "progress")]
+    color: ColorChoice,
+    download_progress: Option<Box<dyn DownloadProgress>>,
+    asset_selector: Option<AssetSelector>,
+    post_install: Option<PostInstallHook>,
+    keep_install_on_post_install_failure: bool,
 }
 
 impl<'a> UbiBuilder<'a> {
@@ -52,6 +142,26 @@ impl<'a> UbiBuilder<'a> {
     /// accepts a URL to any page in the project, like
     /// `https://github.com/houseabsolute/precious/releases`.
     ///
+    /// The URL of a release page, like `https://github.com/houseabsolute/precious/releases/tag/v0.7.0`,
+    /// picks the release with that tag. The URL of a release asset, like
+    /// `https://github.com/houseabsolute/precious/releases/download/v0.7.0/precious-Linux-x86_64-musl.tar.gz`,
+    /// is downloaded directly, just as if it had been passed to [`UbiBuilder::url`].
+    ///
+    /// An Azure DevOps repo belongs to a project in an organization, so its name is
+    /// `org/project/repo`, like `some-org/some-project/some-repo`, or a URL like
+    /// `https://dev.azure.com/some-org/some-project/_git/some-repo`. A bare name like this is only
+    /// accepted when the forge is set to [`ForgeType::AzureDevOps`].
+    ///
+    /// For an image in an OCI registry, this is a reference like `ghcr.io/owner/image:tag` or
+    /// `ghcr.io/owner/image@sha256:...`. A reference on `ghcr.io` is recognized automatically. For
+    /// other registries, you must also set the forge to [`ForgeType::Oci`]. If the reference has no
+    /// tag or digest, the `latest` tag is used, unless you call [`UbiBuilder::tag`].
+    ///
+    /// The project or URL can be followed by `@<tag>`, like `houseabsolute/precious@v0.7.0` or
+    /// `https://github.com/houseabsolute/precious@v0.7.0`, to download the release with that tag. You
+    /// cannot call [`UbiBuilder::tag`], `version_req`, `tag_pattern`, or `include_prereleases` when
+    /// the project includes a tag.
+    ///
     /// You must set this or set `url`, but not both.
     #[must_use]
     pub fn project(mut self, project: &'a str) -> Self {
@@ -61,17 +171,73 @@ impl<'a> UbiBuilder<'a> {
 
     /// Set the tag to download. By default the most recent release is downloaded. You cannot set
     /// this with the `url` option.
+    ///
+    /// Bitbucket does not have releases, so for Bitbucket this is used to filter the repo's
+    /// downloads to those with names containing the tag.
+    ///
+    /// Azure DevOps also does not have releases. Instead, `ubi` uses the artifacts from the most
+    /// recent successful pipeline build of the repo. If a tag is set, this is the most recent
+    /// successful build of that tag.
     #[must_use]
     pub fn tag(mut self, tag: &'a str) -> Self {
         self.tag = Some(tag);
         self
     }
 
+    /// Set a semver version requirement for the release to download, like `^1.2` or
+    /// `>=0.4, <0.6`. The syntax is the same as Cargo's, so a bare version like `1.2` means `^1.2`.
+    /// When this is set, `ubi` lists all of the project's releases, parses each release's tag as a
+    /// version, ignoring a leading `v`, and downloads the release with the highest matching
+    /// version. Tags that are not valid versions are ignored. Prereleases are only matched if the
+    /// requirement includes a prerelease of the same version, like `>=1.2.0-beta`.
+    ///
+    /// This is supported for GitHub, GitLab, and Gitea (including Forgejo) projects. You cannot set
+    /// this with the `tag`, `url`, or `local_asset` options.
+    #[must_use]
+    pub fn version_req(mut self, version_req: &'a str) -> Self {
+        self.version_req = Some(version_req);
+        self
+    }
+
+    /// Set a regex to pick the release to download by its tag, like `^release-\d{4}-\d{2}$`. This
+    /// is for projects whose tags are not semver versions, like `release-2024-06`. When this is set,
+    /// `ubi` lists all of the project's releases and downloads the most recently published release
+    /// whose tag matches the regex. The regex is not anchored, so it can match any part of the tag.
+    ///
+    /// This is supported for the same forges as `version_req`. You cannot set this with the `tag`,
+    /// `version_req`, `url`, or `local_asset` options.
+    #[must_use]
+    pub fn tag_pattern(mut self, tag_pattern: &'a str) -> Self {
+        self.tag_pattern = Some(tag_pattern);
+        self
+    }
+
+    /// Call this to tell `ubi` to consider prereleases when picking the release to install. By
+    /// default, `ubi` installs the latest release, which is never one that the forge site marks as a
+    /// prerelease. When this is set, `ubi` lists the project's releases and installs the newest one,
+    /// even if it is a prerelease.
+    ///
+    /// When this is combined with `version_req`, releases marked as prereleases are considered,
+    /// and so are tags with a prerelease version, like `v1.3.0-beta.1`, if they otherwise match
+    /// the requirement. Draft releases are never installed.
+    ///
+    /// This is supported for GitHub, GitLab, and Gitea (including Forgejo) projects. For GitLab,
+    /// upcoming releases are treated as prereleases. You cannot set this with the `tag`, `url`, or
+    /// `local_asset` options.
+    #[must_use]
+    pub fn include_prereleases(mut self) -> Self {
+        self.include_prereleases = true;
+        self
+    }
+
     /// Set the URL to download from. This can be provided instead of a project or tag. This will not
     /// use the forge site API, so you will never hit API limits. That in turn means you won't have
     /// to set a token env var except when downloading a release from a private repo when the URL is
     /// set.
     ///
+    /// This can also be a `file://` URL, which is treated the same as passing the path to
+    /// `local_asset`.
+    ///
     /// You must set this or set `project`, but not both.
     #[must_use]
     pub fn url(mut self, url: &'a str) -> Self {
@@ -79,13 +245,68 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
-    /// Set the directory to install the binary in. If not set, it will default to `./bin`.
+    /// Set the path to a release asset that has already been downloaded. This skips the forge
+    /// site entirely, so no network requests are made unless checksum or signature verification
+    /// is enabled. The file is unpacked and installed exactly as it would be if it had been
+    /// downloaded, including using its name and magic bytes to figure out what kind of file it is.
+    ///
+    /// If `exe` is not set, then the executable name is taken from the start of the file name, up
+    /// to the first `-`, `_`, or `.`, so `precious-Linux-x86_64.tar.gz` installs `precious`.
+    ///
+    /// You cannot set this with `project`, `tag`, `url`, or `exact_asset_name`.
+    #[must_use]
+    pub fn local_asset<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.local_asset = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory to install the binary in. If not set, it defaults to `$XDG_BIN_HOME` or
+    /// `~/.local/bin` on Unix systems, and `%LOCALAPPDATA%\ubi\bin` on Windows. The directory is
+    /// created if it does not exist. Use [`Ubi::install_dir`] to get the directory that was used.
     #[must_use]
     pub fn install_dir<P: AsRef<Path>>(mut self, install_dir: P) -> Self {
         self.install_dir = Some(install_dir.as_ref().to_path_buf());
         self
     }
 
+    /// Set a directory to stage the binary in instead of installing it. When this is set,
+    /// [`Ubi::install_binary`] downloads and unpacks the release asset as usual, but the files are
+    /// written to this directory. The returned [`InstallResult`](crate::InstallResult) has the
+    /// staged path in its `staged_path` field, and its `install_path` is the path that the files
+    /// would have been installed to, based on [`install_dir`](Self::install_dir). Moving the files
+    /// from one to the other is up to you.
+    ///
+    /// You cannot set this with `dry_run`, `skip_if_exists`, `cache_downloads`, or `cache_file`.
+    #[must_use]
+    pub fn stage_dir<P: AsRef<Path>>(mut self, stage_dir: P) -> Self {
+        self.stage_dir = Some(stage_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the path of a `.tar.gz` file to write instead of installing, which is useful for building
+    /// distro packages. When this is set, [`Ubi::install_binary`] downloads and unpacks the release
+    /// asset as usual, but the files are written into a new tarball at this path, under the
+    /// [`package_prefix`](Self::package_prefix), like `usr/local/bin/foo`. Nothing is written to
+    /// the install directory, and the returned [`InstallResult`](crate::InstallResult) has the
+    /// tarball's path in its `install_path` field.
+    ///
+    /// You cannot set this with `install_dir`, `stage_dir`, `dry_run`, `skip_if_exists`,
+    /// `cache_downloads`, `cache_file`, `completions_dir`, or `versioned_install`.
+    #[must_use]
+    pub fn package_output<P: AsRef<Path>>(mut self, package_output: P) -> Self {
+        self.package_output = Some(package_output.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory that files are put in inside the tarball written for
+    /// [`package_output`](Self::package_output). This is a path relative to the root of the
+    /// filesystem, and a leading `/` is ignored. It defaults to `usr/local/bin`.
+    #[must_use]
+    pub fn package_prefix(mut self, package_prefix: &'a str) -> Self {
+        self.package_prefix = Some(package_prefix);
+        self
+    }
+
     /// Set a string to match against the release filename when there are multiple files for your
     /// OS/arch, i.e. "gnu" or "musl". Note that this is only used when there is more than one
     /// matching release filename for your OS/arch. If only one release asset matches your OS/arch,
@@ -96,9 +317,41 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Set a regex to match against release filenames, like
+    /// `project-.*-linux-(amd64|x86_64)\.tar\.gz`. Unlike `matching`, this is applied before any of
+    /// `ubi`'s OS/arch heuristics, and assets whose names don't match are never picked. If more
+    /// than one asset matches, the heuristics are used to pick between them. If no assets match,
+    /// then installation fails with an error listing the available asset names.
+    ///
+    /// The regex can match any part of the name, so use `^` and `$` to match the whole name. The
+    /// `exact_asset_name` option takes precedence over this. You cannot set this with the `url` or
+    /// `local_asset` options.
+    #[must_use]
+    pub fn matching_regex(mut self, matching_regex: &'a str) -> Self {
+        self.matching_regex = Some(matching_regex);
+        self
+    }
+
+    /// Set the exact name of the release asset to download. When this is set, `ubi` skips all of its
+    /// OS/arch heuristics and picks the asset with this name. If there is no such asset, then
+    /// installation fails with an error listing the available asset names. This takes precedence
+    /// over `matching`.
+    ///
+    /// You cannot set this with the `url` option.
+    #[must_use]
+    pub fn exact_asset_name(mut self, name: &'a str) -> Self {
+        self.exact_asset_name = Some(name);
+        self
+    }
+
     /// Set the name of the executable to look for in archive files. By default this is the same as
     /// the project name, so for `houseabsolute/precious` we look for `precious` or
-    /// `precious.exe`. When running on Windows the ".exe" suffix will be added as needed.
+    /// `precious.exe`. When running on Windows the ".exe" suffix will be added as needed, and a name
+    /// that already ends with `.exe` will match with or without it.
+    ///
+    /// This can include directories separated by `/`, like `bin/precious`, for archives that
+    /// contain more than one file with the same name. In that case, only a file whose path in the
+    /// archive ends with `bin/precious` matches. The executable is still installed as `precious`.
     ///
     /// You cannot call `extract_all` if you set this.
     #[must_use]
@@ -110,8 +363,9 @@ impl<'a> UbiBuilder<'a> {
     /// The name to use when installing the executable. This is useful if the executable in the
     /// archive file has a name that includes a version number or platform information. If this is
     /// not set, then the executable will be installed with the name it has in the archive
-    /// file. Note that this name is used as-is, so on Windows, `.exe` will not be appended to the
-    /// name given.
+    /// file. The executable bit is set on the installed file. On Windows, if the name given does
+    /// not already end with the executable's extension, that extension is appended, using `.exe`
+    /// when the executable in the archive has no extension.
     ///
     /// You cannot call `extract_all` if you set this.
     #[must_use]
@@ -131,17 +385,138 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Set the number of leading path components to remove from each file in the archive when
+    /// `extract_all` is set, like tar's `--strip-components` option. For example, with a value of
+    /// `2`, an archive entry named `project-1.2.3/bin/project` is extracted as `project`. Entries
+    /// that have no more than this many path components are skipped. When this is set, `ubi` does
+    /// not also remove a top-level directory shared by all the files in the archive.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn strip_components(mut self, strip_components: usize) -> Self {
+        self.strip_components = Some(strip_components);
+        self
+    }
+
+    /// Call this to tell `ubi` to install every executable it finds in the archive, rather than
+    /// just the one matching the project or `exe` name. All of the executables are installed
+    /// directly in the install directory, regardless of where they were in the archive. On Windows,
+    /// a file is considered executable if it has a `.bat`, `.cmd`, or `.exe` extension. Elsewhere,
+    /// any file with an executable bit set is used. You can use `exe_glob` to change this.
+    ///
+    /// You cannot set `exe` or `rename_exe_to`, or call `extract_all`, when this is set.
+    #[must_use]
+    pub fn all_exes(mut self) -> Self {
+        self.all_exes = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to install the release asset exactly as it was downloaded, for assets
+    /// that aren't executables, like a `.wasm` module or a JSON schema. The file isn't extracted or
+    /// decompressed, it isn't made executable, and it doesn't need to look like a binary or archive.
+    /// It's installed with the asset's name, or with the name set by `rename_exe_to`.
+    ///
+    /// You must also set `exact_asset_name`, `url`, or `local_asset`, since the asset can't be
+    /// picked by platform. You cannot set `exe`, `completions_dir`, or `verify_binary_arch`, or
+    /// call `extract_all` or `all_exes`, when this is set.
+    #[must_use]
+    pub fn install_as_is(mut self) -> Self {
+        self.install_as_is = true;
+        self
+    }
+
+    /// Call this to install the executable with the release's version in its name, like
+    /// `foo-1.2.3`, and to point a link named `foo` at it. Installing another version later only
+    /// repoints the link, so the older versions are kept and you can roll back by changing the link.
+    /// The version is the release's tag, without a leading `v`. On Windows, the link is a
+    /// `foo.cmd` shim that runs the versioned executable.
+    ///
+    /// You cannot set a `url`, `local_asset`, or `stage_dir`, or call `extract_all`, `all_exes`,
+    /// or `install_as_is`, when this is set.
+    #[must_use]
+    pub fn versioned_install(mut self) -> Self {
+        self.versioned_install = true;
+        self
+    }
+
+    /// Set a glob pattern to select which files are installed when `all_exes` is set. The pattern
+    /// is matched against each file's name, not its full path in the archive, so `project-*` would
+    /// match `bin/project-helper`. When this is set, the files' permissions and extensions are
+    /// ignored.
+    ///
+    /// You must call `all_exes` if you set this.
+    #[must_use]
+    pub fn exe_glob(mut self, glob: &'a str) -> Self {
+        self.exe_glob = Some(glob);
+        self
+    }
+
+    /// Set a directory to install shell completion files into. When this is set and the release
+    /// asset is an archive, any files in it named like `*.bash`, `*.fish`, `*.zsh`, or `_*` (with
+    /// no extension, which is how zsh completion files are usually named) are installed alongside
+    /// the executable. Each file is copied into a subdirectory of this directory named after its
+    /// shell, so `completions/project.bash` is installed as `<dir>/bash/project.bash`. The
+    /// directories are created if they do not exist.
+    ///
+    /// You cannot set this with `extract_all`, `all_exes`, or `stage_dir`.
+    #[must_use]
+    pub fn completions_dir<P: AsRef<Path>>(mut self, completions_dir: P) -> Self {
+        self.completions_dir = Some(completions_dir.as_ref().to_path_buf());
+        self
+    }
+
     /// Set a token to use for API requests. If this is not set, then `ubi` will look for a token in
     /// the appropriate env var:
     ///
+    /// * Azure DevOps - `AZURE_DEVOPS_TOKEN`
+    /// * Bitbucket - `BITBUCKET_APP_PASSWORD`
+    /// * Gitea - `CODEBERG_TOKEN` for `codeberg.org` or `FORGEJO_TOKEN` for any other host, then
+    ///   `GITEA_TOKEN`.
     /// * GitHub - `GITHUB_TOKEN`
-    /// * GitLab - `CI_TOKEN`, then `GITLAB_TOKEN`.
+    /// * GitLab - `CI_JOB_TOKEN`, then `GITLAB_TOKEN`.
+    /// * OCI registry - `GITHUB_TOKEN`
+    /// * SourceHut - `SRHT_TOKEN`
+    ///
+    /// If there is no token in the env vars, or in the OS keyring when
+    /// [`use_keyring`](Self::use_keyring) is set, then `ubi` looks in your netrc file for an entry
+    /// for the forge's API host, like `machine api.github.com login x password <token>`, and uses
+    /// its password as the token. The netrc file is the one named by the `NETRC` env var, or else
+    /// `~/.netrc` (or `~/_netrc` on Windows). The `default` entry is never used.
+    ///
+    /// For Bitbucket, the token is an app password, and the `BITBUCKET_USERNAME` env var must also
+    /// be set. These are sent using HTTP Basic auth. For a token from a netrc file, the entry's
+    /// login is used if `BITBUCKET_USERNAME` is not set.
+    ///
+    /// For Azure DevOps, the token is a personal access token. This is sent using HTTP Basic auth
+    /// with an empty username.
+    ///
+    /// For GitLab, a token from `CI_JOB_TOKEN` is sent in the `JOB-TOKEN` header, which is what
+    /// GitLab CI requires. Any other GitLab token, including one set with this method, is treated as
+    /// a personal or project access token and sent as a bearer token.
+    ///
+    /// For an OCI registry, the token is base64-encoded and sent as a bearer token, which is what
+    /// `ghcr.io` expects for a GitHub token. Without a token, `ubi` gets an anonymous token from the
+    /// registry's token service, which works for public images.
     #[must_use]
     pub fn token(mut self, token: &'a str) -> Self {
         self.token = Some(token);
         self
     }
 
+    /// Call this to tell `ubi` to look for a forge token in the OS keyring when none was set with
+    /// `token` and none was found in the forge's env vars. The token is looked up with the service
+    /// name `ubi:<forge>`, like `ubi:github` or `ubi:gitlab`. Gitea and Forgejo tokens both use
+    /// `ubi:gitea`. Use [`set_keyring_token`](crate::set_keyring_token) to store a token.
+    ///
+    /// On macOS, this uses the `security` tool to read the login keychain. On other Unix-like
+    /// systems, it uses `secret-tool` from libsecret, which must be installed. This is not
+    /// supported on Windows.
+    #[must_use]
+    pub fn use_keyring(mut self) -> Self {
+        self.use_keyring = true;
+        self
+    }
+
     /// Set a GitHub token to use for API requests. If this is not set then this will be taken from
     /// the `GITHUB_TOKEN` env var if it is set.
     #[deprecated(since = "0.6.0", note = "please use `token` instead")]
@@ -178,10 +553,97 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Set the libc to pick release assets for. This is only relevant for Linux platforms. If this
+    /// isn't set then it will be determined based on the current platform's libc.
+    ///
+    /// `ubi` prefers assets with names that indicate they were built for this libc, like
+    /// `project-x86_64-unknown-linux-musl.tar.gz`. If there are none, it falls back to assets that
+    /// don't indicate a libc. For `Libc::Gnu`, it will then fall back to musl assets, since these
+    /// are usually statically linked. It never picks a glibc asset for `Libc::Musl`.
+    /// An asset that names your CPU architecture still comes first, unless you change the
+    /// [`scoring_weights`](Self::scoring_weights).
+    ///
+    /// You cannot set this with `is_musl`, and you cannot set this to `Libc::Musl` on a non-Linux
+    /// platform.
+    #[must_use]
+    pub fn libc(mut self, libc: Libc) -> Self {
+        self.libc = Some(libc);
+        self
+    }
+
+    /// Call this to tell `ubi` to prefer statically linked release assets. When there are multiple
+    /// assets for your OS and architecture, `ubi` picks one with `static` in its name if there is
+    /// one. Otherwise it avoids assets with `dynamic` in their name if there are any others.
+    ///
+    /// This only breaks ties between assets that already match your platform, so a static asset
+    /// for a different architecture is never picked over a dynamic one for yours.
+    #[must_use]
+    pub fn prefer_static(mut self) -> Self {
+        self.prefer_static = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to prefer release assets built with the GNU (MinGW) toolchain on
+    /// Windows. When there are multiple assets for your OS and architecture, `ubi` normally picks
+    /// one with `msvc` in its name if there is one. With this set, it picks one with `gnu`,
+    /// `gnullvm`, or `mingw` in its name instead. This has no effect on other platforms.
+    #[must_use]
+    pub fn prefer_gnu_on_windows(mut self) -> Self {
+        self.prefer_gnu_on_windows = true;
+        self
+    }
+
+    /// Set the order in which to prefer asset extensions, best first, like `&["zip", "tar.gz"]`.
+    /// When `ubi` is left with multiple assets for your OS and architecture that differ only by
+    /// their extension, it picks the one whose extension comes first in this list. Assets with an
+    /// extension that is not in the list come after all of the ones that are. The default is
+    /// `tar.zst`, `tar.xz`, `tar.gz`, `tgz`, then `zip`. A leading `.` on an extension is ignored.
+    #[must_use]
+    pub fn extension_preference(mut self, extensions: &[&'a str]) -> Self {
+        self.extension_preference = Some(extensions.to_vec());
+        self
+    }
+
+    /// Set the points that `ubi` gives a release asset for each way that its name matches your
+    /// platform, which is useful for projects with unusual asset names. Of the assets that can run on
+    /// your platform, `ubi` keeps the ones with the highest score, then breaks any ties with its other
+    /// preferences, like [`prefer_static`](Self::prefer_static) and the
+    /// [`extension_preference`](Self::extension_preference). See [`ScoringWeights`] for the
+    /// defaults.
+    ///
+    /// For example, giving the `extension` a weight of 100 means that a `.tar.gz` asset that doesn't
+    /// name any CPU architecture is picked over a `.zip` asset that names yours.
+    #[must_use]
+    pub fn scoring_weights(mut self, weights: ScoringWeights) -> Self {
+        self.scoring_weights = weights;
+        self
+    }
+
+    /// Call this to let the user choose a release asset when `ubi` cannot pick one on its own. If
+    /// there are multiple assets left after applying all of the heuristics for picking an asset,
+    /// or if none of the assets match your OS, architecture, and libc, `ubi` prints a numbered list
+    /// of the candidates to stderr and reads the selection from stdin.
+    ///
+    /// This only has an effect when stdin is a terminal. Otherwise `ubi` behaves as if this was
+    /// not called, so it never blocks waiting for input in CI.
+    #[must_use]
+    pub fn interactive(mut self) -> Self {
+        self.interactive = true;
+        self
+    }
+
     /// Set the forge type to use for fetching assets and release information. This determines which
     /// REST API is used to get information about releases and to download the release. If this isn't
     /// set, then this will be determined from the hostname in the url, if that is set.  Otherwise,
     /// the default is GitHub.
+    ///
+    /// For [`ForgeType::Gitea`], which also covers Forgejo, the API is found on the same host as the
+    /// project's URL, so a project on a self-hosted instance, like
+    /// `https://codeberg.org/owner/repo`, does not need an [`api_base_url`](Self::api_base_url).
+    ///
+    /// With [`ForgeType::Oci`], `ubi` treats an image in an OCI registry as a release. Each layer
+    /// with an `org.opencontainers.image.title` annotation, as pushed by tools like
+    /// [ORAS](https://oras.land/), is treated as a release asset with that name.
     #[must_use]
     pub fn forge(mut self, forge: ForgeType) -> Self {
         self.forge = Some(forge);
@@ -190,13 +652,456 @@ impl<'a> UbiBuilder<'a> {
 
     /// Set the base URL for the forge site's API. This is useful for testing or if you want to
     /// operate against an Enterprise version of GitHub or GitLab. This should be something like
-    /// `https://github.my-corp.example.com/api/v4`.
+    /// `https://github.my-corp.example.com/api/v3` for GitHub Enterprise Server or
+    /// `https://gitlab.my-corp.example.com/api/v4` for GitLab. If this isn't set, then the URL from
+    /// the forge's env var is used, if it's set and the project is not a URL. That's
+    /// `UBI_GITHUB_API_URL` for GitHub, `UBI_GITLAB_URL` for GitLab, and `UBI_GITEA_URL` or
+    /// `UBI_FORGEJO_URL` for Gitea. The GitLab and Gitea env vars are the instance's URL, not its API
+    /// URL. Otherwise the default for the forge is used, like `https://api.github.com` for GitHub.
     #[must_use]
     pub fn api_base_url(mut self, api_base_url: &'a str) -> Self {
         self.api_base_url = Some(api_base_url);
         self
     }
 
+    /// Set the URL of a self-hosted GitLab instance, like `https://gitlab.example.com`. Setting this
+    /// implies that the forge is GitLab, so you cannot combine it with a different
+    /// [`forge`](Self::forge). The API base URL is this URL plus `/api/v4`, unless you also call
+    /// [`api_base_url`](Self::api_base_url). If this isn't set, then `https://gitlab.com` is used.
+    #[must_use]
+    pub fn gitlab_url(mut self, gitlab_url: &'a str) -> Self {
+        self.gitlab_url = Some(gitlab_url);
+        self
+    }
+
+    /// Set a minisign public key to verify the downloaded release asset with. This can either be the
+    /// base64-encoded key or the path to a `.pub` file containing the key. When this is set, `ubi`
+    /// looks for an asset with the same name as the selected asset plus a `.minisig` extension in
+    /// the same release, and verifies the downloaded file against it before installing anything.
+    /// If you set a `url`, then the signature is downloaded from that URL plus `.minisig`.
+    ///
+    /// If the signature cannot be found or verification fails, then installation fails.
+    #[must_use]
+    pub fn verify_signature(mut self, public_key: &'a str) -> Self {
+        self.verify_signature = Some(public_key);
+        self
+    }
+
+    /// Add a GPG public key to verify the downloaded release asset with. This can either be an
+    /// armored key block, like the output of `gpg --armor --export`, or the path to a file
+    /// containing an armored or binary key block. Call this more than once to accept a signature
+    /// from any of several keys. RSA and Ed25519 keys are supported.
+    ///
+    /// When this is set, `ubi` looks for a detached signature asset with the same name as the
+    /// selected asset plus a `.asc` or `.sig` extension in the same release, and verifies the
+    /// downloaded file against it before installing anything. If you set a `url`, then the
+    /// signature is downloaded from that URL plus `.asc`.
+    ///
+    /// If the signature cannot be found or verification fails, then installation fails.
+    #[must_use]
+    pub fn verify_gpg(mut self, public_key: &'a str) -> Self {
+        self.verify_gpg.push(public_key);
+        self
+    }
+
+    /// Call this to tell `ubi` to verify the SHA-256 checksum of the downloaded release asset
+    /// before installing it. The checksum is taken from a release asset named `<asset>.sha256`,
+    /// `SHA256SUMS`, or `checksums.txt`, checked in that order. If you set a `url`, then the
+    /// checksum is downloaded from that URL plus `.sha256`.
+    ///
+    /// If no checksum file can be found or the checksum does not match, then installation fails.
+    #[must_use]
+    pub fn verify_checksum(mut self) -> Self {
+        self.verify_checksum = true;
+        self
+    }
+
+    /// Set the SHA-256 digest that the downloaded release asset must have, as a hex string. This
+    /// is checked before anything is installed, and installation fails if the digest does not
+    /// match. Unlike [`verify_checksum`](Self::verify_checksum), this does not look for a checksum
+    /// file in the release, so you can use it to pin an asset to a digest you recorded earlier.
+    #[must_use]
+    pub fn expect_sha256(mut self, sha256: &'a str) -> Self {
+        self.expect_sha256 = Some(sha256);
+        self
+    }
+
+    /// Call this to tell `ubi` to check that the executable it is about to install was built for
+    /// the target platform's architecture. This looks at the ELF, Mach-O, or PE header of the
+    /// executable, so it catches a release asset that was picked for the wrong platform. On macOS
+    /// ARM, an x86-64 executable is accepted, since it can run under Rosetta, and on Windows, an x86
+    /// executable is accepted on x86-64 and ARM. Files that are not in one of these formats, like
+    /// scripts, are not checked.
+    ///
+    /// If the executable is for a different architecture, then installation fails.
+    ///
+    /// You cannot call `extract_all` or `all_exes` if you set this.
+    #[must_use]
+    pub fn verify_binary_arch(mut self) -> Self {
+        self.verify_binary_arch = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to stop before downloading anything. When this is set,
+    /// [`Ubi::install_binary`] will still fetch the release information from the forge site and
+    /// pick a release asset, but it will only log what it would have installed. You can use
+    /// [`Ubi::install_plan`] to get this information directly.
+    #[must_use]
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Call this to always download and install the release asset, overwriting anything that is
+    /// already installed. If [`cache_downloads`](Self::cache_downloads) was also called, then `ubi`
+    /// does not send the cached headers, so the server always sends the asset. The new headers are
+    /// still cached.
+    ///
+    /// You cannot call both this and [`skip_if_exists`](Self::skip_if_exists).
+    #[must_use]
+    pub fn force(mut self) -> Self {
+        self.force = true;
+        self
+    }
+
+    /// Call this to skip the install if the executable already exists in the install directory.
+    /// `ubi` still fetches the release information and picks a release asset, so
+    /// [`Ubi::install_binary`] returns the tag it resolved, but it does not download anything.
+    ///
+    /// With [`extract_all`](Self::extract_all) or [`all_exes`](Self::all_exes), `ubi` cannot know
+    /// which files an archive would install without downloading it. In that case, the install is
+    /// only skipped if [`cache_downloads`](Self::cache_downloads) was called and the files
+    /// installed from the same asset last time still exist.
+    ///
+    /// You cannot call both this and [`force`](Self::force).
+    #[must_use]
+    pub fn skip_if_exists(mut self) -> Self {
+        self.skip_if_exists = true;
+        self
+    }
+
+    /// Call this to skip the install if the executable is already at the tag of the release that
+    /// `ubi` picks. This is useful for tools that converge a machine on a set of versions, since
+    /// running the same install again is a no-op. `ubi` still fetches the release information to
+    /// resolve the tag, but it does not download anything if the executable is up to date.
+    ///
+    /// To do this, `ubi` records the tag that each executable was installed from in a file named
+    /// `.ubi-meta` in the install directory. This file is written by every install that calls this.
+    /// Once it exists, other installs into the same directory keep it up to date too.
+    ///
+    /// You cannot set this with `url`, `local_asset`, `extract_all`, `all_exes`, `stage_dir`,
+    /// `package_output`, `force`, or `skip_if_exists`.
+    #[must_use]
+    pub fn ensure(mut self) -> Self {
+        self.ensure = true;
+        self
+    }
+
+    /// Set how long to wait for another `ubi` process that is installing into the same directory to
+    /// finish. While installing, `ubi` holds a lock for the install directory, so that two
+    /// processes don't write the same files at the same time. The lock file is in the system's
+    /// temp directory. The default is five minutes.
+    #[must_use]
+    pub fn install_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.install_lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Call this to tell `ubi` to remember the `ETag` and `Last-Modified` headers sent with the
+    /// release asset it downloads. The next time you install the same asset from the same project,
+    /// `ubi` sends these back to the server. If the server responds with `304 Not Modified`, then
+    /// nothing is downloaded and the existing installation is left as-is. This is only done if the
+    /// files installed last time still exist.
+    ///
+    /// By default, the cache is stored in a file named `.ubi-cache.json` in the install directory.
+    /// You can use `cache_file` to change this.
+    #[must_use]
+    pub fn cache_downloads(mut self) -> Self {
+        self.cache_downloads = true;
+        self
+    }
+
+    /// Set the path of the file used to cache `ETag` and `Last-Modified` headers. Setting this
+    /// implies `cache_downloads`.
+    #[must_use]
+    pub fn cache_file<P: AsRef<Path>>(mut self, cache_file: P) -> Self {
+        self.cache_file = Some(cache_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Call this to cache the release information that `ubi` gets from the forge's API. As long as
+    /// the cached information is newer than the TTL, installing the same release of the same
+    /// project again does not make any requests to the forge's API. When installing the latest
+    /// release, this means that a release made within the TTL may not be seen. The TTL defaults to
+    /// five minutes, and you can use `release_info_cache_ttl` to change it.
+    ///
+    /// By default, the cache is stored in a `ubi/releases` directory under the platform's cache
+    /// directory, like `~/.cache` on Linux. You can use `release_info_cache_dir` to change this.
+    #[must_use]
+    pub fn cache_release_info(mut self) -> Self {
+        self.cache_release_info = true;
+        self
+    }
+
+    /// Set the directory used to cache release information. Setting this implies
+    /// `cache_release_info`. The directory is created if it does not exist.
+    #[must_use]
+    pub fn release_info_cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.release_info_cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set how long cached release information is used for. The default is five minutes. Setting
+    /// this implies `cache_release_info`.
+    #[must_use]
+    pub fn release_info_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.release_info_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Call this to ignore any cached release information and always get it from the forge's API.
+    /// The information that is fetched is still cached for later runs. This does nothing unless
+    /// the release information cache is enabled.
+    #[must_use]
+    pub fn refresh_release_info(mut self) -> Self {
+        self.refresh_release_info = true;
+        self
+    }
+
+    /// Call this to keep a copy of the downloaded release asset after it is installed. The copy has
+    /// the same name as the asset, like `precious-Linux-x86_64-musl.tar.gz`. By default, it is
+    /// written to the install directory. You can use `keep_archive_dir` to change this.
+    ///
+    /// The copy is written after the executable is installed, so it does not affect that. If the
+    /// asset is a bare executable that was installed as-is, no copy is made.
+    #[must_use]
+    pub fn keep_archive(mut self) -> Self {
+        self.keep_archive = true;
+        self
+    }
+
+    /// Set the directory to write the copy of the downloaded release asset to. Setting this implies
+    /// `keep_archive`. The directory is created if it does not exist.
+    #[must_use]
+    pub fn keep_archive_dir<P: AsRef<Path>>(mut self, keep_archive_dir: P) -> Self {
+        self.keep_archive_dir = Some(keep_archive_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the maximum number of times to retry a request that fails in a way that is likely to be
+    /// transient. This applies to requests to the forge site's API and to asset downloads. A
+    /// request is retried if it gets a `429`, `502`, `503`, or `504` response, or if it fails with
+    /// a connection or timeout error. The default is 3. Set this to 0 to disable retries.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Set the base delay between retries. The delay doubles after each retry, with some random
+    /// jitter added. If a `429` response has a `Retry-After` header with a number of seconds, that
+    /// is used as the delay instead. The default is 1 second.
+    #[must_use]
+    pub fn retry_base_delay(mut self, delay: Duration) -> Self {
+        self.retry_base_delay = Some(delay);
+        self
+    }
+
+    /// Set how long to wait for a connection to the server to be established, including the TLS
+    /// handshake. This applies to each attempt when a request is retried. The default is 10
+    /// seconds.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long to wait for the server to respond to a request, starting from when the request
+    /// is sent. This does not include the time spent reading the response body, so downloading a
+    /// large release asset over a slow connection will not time out as long as the server starts
+    /// responding in time. This applies to each attempt when a request is retried. By default,
+    /// there is no request timeout.
+    #[must_use]
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum rate, in bytes per second, at which release assets are downloaded. This
+    /// applies to each download made by the [`Ubi`] this builds, including any retries. It does
+    /// not apply to the API requests used to find the release. By default, there is no limit.
+    #[must_use]
+    pub fn max_download_rate(mut self, bytes_per_second: u64) -> Self {
+        self.max_download_rate = Some(bytes_per_second);
+        self
+    }
+
+    /// Set a proxy URL to use for all requests, like `http://proxy.example.com:8080`. If this is
+    /// not set, then `ubi` uses the proxy from the `HTTPS_PROXY`, `HTTP_PROXY`, or `ALL_PROXY` env
+    /// vars, if one of these is set. Either way, hosts listed in the `NO_PROXY` env var bypass the
+    /// proxy.
+    #[must_use]
+    pub fn proxy(mut self, proxy: &'a str) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the path to a PEM file with one or more root certificates to trust, in addition to the
+    /// usual roots. This is the safe way to use a forge site or proxy with a certificate signed by a
+    /// private CA, since certificates are still verified.
+    #[must_use]
+    pub fn ca_cert<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.ca_cert = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Call this to turn off TLS certificate verification for every request. **This is
+    /// dangerous**, since anyone who can intercept your traffic can then make `ubi` download and
+    /// install anything they want. Only use this as a last resort, and prefer
+    /// [`ca_cert`](Self::ca_cert) for a forge site with a certificate signed by a private CA.
+    #[must_use]
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.danger_accept_invalid_certs = true;
+        self
+    }
+
+    /// Set the minimum TLS version to allow for every connection. By default, this is TLS 1.2.
+    /// Setting this to TLS 1.3 makes `ubi` refuse to talk to a forge site, proxy, or download host
+    /// that only supports older versions.
+    #[must_use]
+    pub fn min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = version;
+        self
+    }
+
+    /// Add a header to send with every request, including requests to the forge site's API and
+    /// release asset downloads. This can be called more than once to add multiple headers. If the
+    /// same header is added more than once, the last value is used. Like the forge token, these
+    /// headers are not sent when a request is redirected to a different host, such as the storage
+    /// host that GitHub redirects asset downloads to.
+    ///
+    /// Headers that `ubi` sets on a request itself, like `Accept` or the header for a forge token,
+    /// take priority over these. By default, you cannot set the `Authorization`,
+    /// `Proxy-Authorization`, `JOB-TOKEN`, `PRIVATE-TOKEN`, `Host`, `Content-Length`, or
+    /// `Transfer-Encoding` headers. Call `allow_reserved_headers` to allow these.
+    #[must_use]
+    pub fn header(mut self, name: &'a str, value: &'a str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Call this to allow setting reserved headers like `Authorization` with `header`. If a forge
+    /// token is also set, then the forge token's header is still used instead of your header
+    /// with the same name.
+    #[must_use]
+    pub fn allow_reserved_headers(mut self) -> Self {
+        self.allow_reserved_headers = true;
+        self
+    }
+
+    /// Set a username and password to send with HTTP Basic auth to the forge site's API. This is
+    /// for a gateway in front of the forge's API that requires its own credentials.
+    ///
+    /// The credentials are sent in the `Authorization` header. They're only sent to requests for the
+    /// API's host, including release asset downloads from that host, and never to another host that
+    /// an asset is downloaded from or redirected to.
+    ///
+    /// Forge tokens are also sent in the `Authorization` header, so `build` returns an error if
+    /// there is one. The exception is a GitLab CI job token, which is sent in its own `JOB-TOKEN`
+    /// header, so it can be used along with these credentials.
+    #[must_use]
+    pub fn basic_auth(mut self, username: &'a str, password: &'a str) -> Self {
+        self.basic_auth = Some((username, password));
+        self
+    }
+
+    /// Set the `User-Agent` header to send with every request. If this is not set, `ubi` sends
+    /// `ubi/<version> (+https://github.com/houseabsolute/ubi)`. A `User-Agent` header added with
+    /// [`header`](Self::header) takes priority over this.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &'a str) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Call this to show a progress bar on stderr while downloading the release asset. If the server
+    /// doesn't send a `Content-Length` header, then a spinner with the number of bytes downloaded is
+    /// shown instead. Nothing is shown when stderr is not a terminal.
+    ///
+    /// This is only available when the `progress` feature is enabled. If you set
+    /// `download_progress`, then that takes precedence over this.
+    #[cfg(feature = "progress")]
+    #[must_use]
+    pub fn progress(mut self) -> Self {
+        self.progress = true;
+        self
+    }
+
+    /// Set whether the progress bar shown by `progress` uses color. By default, color is used when
+    /// stderr is a terminal and the `NO_COLOR` env var is not set.
+    ///
+    /// This is only available when the `progress` feature is enabled.
+    #[cfg(feature = "progress")]
+    #[must_use]
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set a [`DownloadProgress`] implementation to be notified of the release asset download's
+    /// progress. Use this if you want to report progress in your own way.
+    #[must_use]
+    pub fn download_progress(mut self, progress: Box<dyn DownloadProgress>) -> Self {
+        self.download_progress = Some(progress);
+        self
+    }
+
+    /// Set a closure to pick the release asset to install. This is called with every asset in the
+    /// release, the same list that `ubi`'s own asset picking would look at. If it returns an
+    /// asset, then that asset is installed. If it returns `None`, then `ubi` falls back to picking
+    /// an asset as it normally would.
+    ///
+    /// This is not used when a `url` is set, since there is no list of assets to pick from in that
+    /// case.
+    #[must_use]
+    pub fn asset_selector<F>(mut self, selector: F) -> Self
+    where
+        F: Fn(&[Asset]) -> Option<Asset> + Send + Sync + 'static,
+    {
+        self.asset_selector = Some(AssetSelector::new(selector));
+        self
+    }
+
+    /// Set a closure to run after a successful install, like one that runs `foo --version` to
+    /// check that the installed executable works. It's called with the path of the installed
+    /// executable, or of the link for a versioned install. It is not called for a dry run, or when
+    /// nothing is installed because the executable is already installed or up to date.
+    ///
+    /// If the closure returns an error, then the install is rolled back, which puts back the
+    /// executable that was there before, if any, and [`Ubi::install_binary`] returns the error.
+    /// Other files installed along with the executable, like shell completions, are not rolled
+    /// back. Call
+    /// [`keep_install_on_post_install_failure`](Self::keep_install_on_post_install_failure) to keep
+    /// the install instead.
+    ///
+    /// You cannot call `extract_all` or `all_exes` when this is set.
+    #[must_use]
+    pub fn post_install<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
+    {
+        self.post_install = Some(PostInstallHook::new(hook));
+        self
+    }
+
+    /// Call this to keep the installed files when the [`post_install`](Self::post_install) closure
+    /// returns an error. The error is still returned by [`Ubi::install_binary`].
+    #[must_use]
+    pub fn keep_install_on_post_install_failure(mut self) -> Self {
+        self.keep_install_on_post_install_failure = true;
+        self
+    }
+
     const TARGET: &'static str = env!("TARGET");
 
     /// Builds a new [`Ubi`] instance and returns it.
@@ -204,15 +1109,126 @@ impl<'a> UbiBuilder<'a> {
     /// # Errors
     ///
     /// If you have tried to set incompatible options (setting a `project` or `tag` with a `url`) or
-    /// you have not set required options (one of `project` or `url`), then this method will return
-    /// an error.
-    pub fn build(self) -> Result<Ubi<'a>> {
-        if self.project.is_none() && self.url.is_none() {
-            return Err(anyhow!("You must set a project or url"));
+    /// you have not set required options (one of `project`, `url`, or `local_asset`), then this
+    /// method will return an error. It will also return an error if the `local_asset` does not
+    /// exist. It will also return an error if the public key passed to `verify_signature` or
+    /// `verify_gpg` cannot be read or parsed, if the pattern passed to `exe_glob` is not a valid
+    /// glob, if the regex passed to `matching_regex` is not valid, or if a header passed to
+    /// `header` is not valid or is reserved. It will also return an error if `basic_auth` is set
+    /// along with a forge token that is sent in the `Authorization` header.
+    pub fn build(mut self) -> Result<Ubi<'a>> {
+        if let Some(project) = self.project {
+            if self.url.is_none() && project_is_release_asset_url(project, self.forge.as_ref()) {
+                debug!(
+                    "the project {project} is a release asset URL, so it is downloaded directly"
+                );
+                self.url = self.project.take();
+            }
+        }
+        if self.project.is_none() && self.url.is_none() && self.local_asset.is_none() {
+            return Err(anyhow!("You must set a project, url, or local_asset"));
+        }
+        if self.local_asset.is_some()
+            && (self.project.is_some()
+                || self.tag.is_some()
+                || self.url.is_some()
+                || self.exact_asset_name.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set a local_asset with a project, tag, url, or exact_asset_name"
+            ));
         }
         if self.url.is_some() && (self.project.is_some() || self.tag.is_some()) {
             return Err(anyhow!("You cannot set a url with a project or tag"));
         }
+        if self.version_req.is_some()
+            && (self.tag.is_some() || self.url.is_some() || self.local_asset.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set a version_req with a tag, url, or local_asset"
+            ));
+        }
+        if self.tag_pattern.is_some()
+            && (self.tag.is_some()
+                || self.version_req.is_some()
+                || self.url.is_some()
+                || self.local_asset.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set a tag_pattern with a tag, version_req, url, or local_asset"
+            ));
+        }
+        if self.include_prereleases
+            && (self.tag.is_some() || self.url.is_some() || self.local_asset.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set include_prereleases with a tag, url, or local_asset"
+            ));
+        }
+        if let Some(sha256) = self.expect_sha256 {
+            if !checksum::is_sha256_hash(sha256) {
+                return Err(anyhow!(
+                    "The expected SHA-256 checksum `{sha256}` is not a 64 character hex string"
+                ));
+            }
+        }
+        if self.force && self.skip_if_exists {
+            return Err(anyhow!("You cannot set both force and skip_if_exists"));
+        }
+        if self.ensure
+            && (self.url.is_some()
+                || self.local_asset.is_some()
+                || self.extract_all
+                || self.all_exes
+                || self.stage_dir.is_some()
+                || self.package_output.is_some()
+                || self.force
+                || self.skip_if_exists)
+        {
+            return Err(anyhow!(
+                "You cannot set ensure with url, local_asset, extract_all, all_exes, stage_dir, package_output, force, or skip_if_exists"
+            ));
+        }
+        if self.stage_dir.is_some()
+            && (self.dry_run
+                || self.skip_if_exists
+                || self.cache_downloads
+                || self.cache_file.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set a stage_dir with dry_run, skip_if_exists, cache_downloads, or cache_file"
+            ));
+        }
+        if self.package_output.is_some() {
+            if self.install_dir.is_some()
+                || self.stage_dir.is_some()
+                || self.dry_run
+                || self.skip_if_exists
+                || self.cache_downloads
+                || self.cache_file.is_some()
+                || self.completions_dir.is_some()
+                || self.versioned_install
+            {
+                return Err(anyhow!(
+                    "You cannot set a package_output with install_dir, stage_dir, dry_run, skip_if_exists, cache_downloads, cache_file, completions_dir, or versioned_install"
+                ));
+            }
+        } else if self.package_prefix.is_some() {
+            return Err(anyhow!(
+                "You cannot set a package_prefix without a package_output"
+            ));
+        }
+        if self.max_download_rate == Some(0) {
+            return Err(anyhow!("The max_download_rate must be greater than zero"));
+        }
+        if self.matching_regex.is_some() && (self.url.is_some() || self.local_asset.is_some()) {
+            return Err(anyhow!(
+                "You cannot set a matching_regex with a url or local_asset"
+            ));
+        }
+        if self.url.is_some() && self.exact_asset_name.is_some() {
+            return Err(anyhow!("You cannot set a url with an exact_asset_name"));
+        }
         if self.exe.is_some() && self.extract_all {
             return Err(anyhow!("You cannot set exe and enable extract_all"));
         }
@@ -221,59 +1237,496 @@ impl<'a> UbiBuilder<'a> {
                 "You cannot set rename_exe_to and enable extract_all"
             ));
         }
+        if self.strip_components.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set strip_components without enabling extract_all"
+            ));
+        }
+        if self.all_exes {
+            if self.exe.is_some() {
+                return Err(anyhow!("You cannot set exe and enable all_exes"));
+            }
+            if self.rename_exe_to.is_some() {
+                return Err(anyhow!("You cannot set rename_exe_to and enable all_exes"));
+            }
+            if self.extract_all {
+                return Err(anyhow!("You cannot enable both extract_all and all_exes"));
+            }
+        } else if self.exe_glob.is_some() {
+            return Err(anyhow!("You cannot set exe_glob without enabling all_exes"));
+        }
+        if self.install_as_is {
+            if self.exact_asset_name.is_none() && self.url.is_none() && self.local_asset.is_none() {
+                return Err(anyhow!(
+                    "You must set an exact_asset_name, url, or local_asset when install_as_is is enabled"
+                ));
+            }
+            if self.exe.is_some() {
+                return Err(anyhow!("You cannot set exe and enable install_as_is"));
+            }
+            if self.extract_all || self.all_exes {
+                return Err(anyhow!(
+                    "You cannot enable install_as_is with extract_all or all_exes"
+                ));
+            }
+            if self.completions_dir.is_some() || self.verify_binary_arch {
+                return Err(anyhow!(
+                    "You cannot set a completions_dir or verify_binary_arch with install_as_is"
+                ));
+            }
+        }
+        if self.versioned_install {
+            if self.extract_all || self.all_exes || self.install_as_is {
+                return Err(anyhow!(
+                    "You cannot enable versioned_install with extract_all, all_exes, or install_as_is"
+                ));
+            }
+            if self.url.is_some() || self.local_asset.is_some() || self.stage_dir.is_some() {
+                return Err(anyhow!(
+                    "You cannot set a url, local_asset, or stage_dir with versioned_install"
+                ));
+            }
+        }
+        if self.post_install.is_some() && (self.extract_all || self.all_exes) {
+            return Err(anyhow!(
+                "You cannot set a post_install hook with extract_all or all_exes"
+            ));
+        }
+
+        if self.completions_dir.is_some()
+            && (self.extract_all || self.all_exes || self.stage_dir.is_some())
+        {
+            return Err(anyhow!(
+                "You cannot set a completions_dir with extract_all, all_exes, or stage_dir"
+            ));
+        }
+        if self.verify_binary_arch && (self.extract_all || self.all_exes) {
+            return Err(anyhow!(
+                "You cannot set verify_binary_arch with extract_all or all_exes"
+            ));
+        }
+
+        if self.is_musl.is_some() && self.libc.is_some() {
+            return Err(anyhow!("You cannot set both is_musl and libc"));
+        }
+
+        let extension_preference = self.validated_extension_preference()?;
 
         let platform = self.determine_platform()?;
 
         self.check_musl_setting(&platform)?;
 
-        let asset_url = self.url.map(Url::parse).transpose()?;
-        let (project_name, forge_type) =
-            parse_project_name(self.project, asset_url.as_ref(), self.forge.clone())?;
-        let installer = self.new_installer(&project_name, &platform)?;
-        let forge = self.new_forge(project_name, &forge_type)?;
-        let is_musl = self.is_musl.unwrap_or_else(|| platform_is_musl(&platform));
+        let asset_url = if let Some(path) = &self.local_asset {
+            Some(local_asset_url(path)?)
+        } else {
+            self.url.map(Url::parse).transpose()?
+        };
+        let gitlab_url = self.gitlab_url.map(Url::parse).transpose()?;
+        let forge = match (&self.forge, &gitlab_url) {
+            (Some(forge), Some(_)) if *forge != ForgeType::GitLab => {
+                return Err(anyhow!(
+                    "You cannot set gitlab_url when the forge is {}",
+                    forge.forge_name(),
+                ));
+            }
+            (None, Some(_)) => Some(ForgeType::GitLab),
+            (forge, _) => forge.clone(),
+        };
+        let oci_reference = self.oci_reference(forge.as_ref())?;
+        // Only a forge project is a `ProjectRef`. A local asset has no project, and an OCI
+        // repository can have any number of `/`s.
+        let (project_name, forge_type, project_ref) = match (&asset_url, &oci_reference) {
+            (Some(url), _) if url.scheme() == "file" => {
+                (local_asset_project_name(url)?, ForgeType::default(), None)
+            }
+            (_, Some(reference)) => (reference.repository.clone(), ForgeType::Oci, None),
+            _ => {
+                let project_ref = parse_project_name(self.project, asset_url.as_ref(), forge)?;
+                if project_ref.tag.is_some() {
+                    self.check_project_tag_conflicts()?;
+                }
+                (
+                    project_ref.project_name(),
+                    project_ref.forge.clone(),
+                    Some(project_ref),
+                )
+            }
+        };
+        let package = self
+            .package_output
+            .as_deref()
+            .map(|o| Package::new(o, self.package_prefix.unwrap_or(DEFAULT_PACKAGE_PREFIX)))
+            .transpose()?;
+        // When staging, the installer writes to the stage dir, and we build a second installer for
+        // the install dir just to get the path that the files would be installed to. When
+        // packaging, it writes to the dir that the package is built in.
+        let package_install_dir = package.as_ref().map(Package::install_dir);
+        let installer = self.new_installer(
+            package_install_dir
+                .as_deref()
+                .or(self.stage_dir.as_deref())
+                .or(self.install_dir.as_deref()),
+            &project_name,
+            &platform,
+        )?;
+        let final_install_path = self
+            .stage_dir
+            .is_some()
+            .then(|| {
+                self.new_installer(self.install_dir.as_deref(), &project_name, &platform)
+                    .map(|i| i.install_path().to_path_buf())
+            })
+            .transpose()?;
+        let download_cache = self.new_download_cache(&project_name)?;
+        // The install metadata describes the executable in the install dir, so there's none when
+        // we're installing anywhere else or installing more than one file.
+        let install_meta =
+            (!self.extract_all && !self.all_exes && self.stage_dir.is_none() && package.is_none())
+                .then(|| InstallMeta::new(installer.install_dir()));
+        let release_info_cache = self.new_release_info_cache()?;
+        // When packaging, the archive is kept next to the package rather than being put in it.
+        let keep_archive_dir = (self.keep_archive || self.keep_archive_dir.is_some()).then(|| {
+            self.keep_archive_dir.clone().unwrap_or_else(|| {
+                match self.package_output.as_deref().and_then(Path::parent) {
+                    Some(dir) => dir.to_path_buf(),
+                    None => installer.install_dir().to_path_buf(),
+                }
+            })
+        });
+        let retry_policy = RetryPolicy::new(
+            self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            self.retry_base_delay.unwrap_or(DEFAULT_RETRY_BASE_DELAY),
+            self.request_timeout,
+            self.basic_auth.map(|(u, p)| (u.to_string(), p.to_string())),
+        )
+        .with_extra_headers(extra_headers(&self.headers, self.allow_reserved_headers)?);
+        let forge = self.new_forge(
+            project_name,
+            &forge_type,
+            project_ref.as_ref(),
+            gitlab_url.as_ref(),
+            oci_reference.as_ref(),
+            retry_policy,
+        )?;
+        // The forge's copy of the policy knows the forge's API host, which the HTTP Basic auth
+        // credentials are sent to.
+        let retry_policy = forge.retry_policy().clone();
+        let client = reqwest_client(
+            self.proxy,
+            self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+            self.user_agent.unwrap_or(DEFAULT_USER_AGENT),
+            self.ca_cert.as_deref(),
+            self.danger_accept_invalid_certs,
+            self.min_tls_version,
+        )?;
+        if self.basic_auth.is_some() && forge.sends_token_in_authorization_header(&client)? {
+            return Err(anyhow!(
+                "You cannot set basic_auth with a forge token that is sent in the Authorization header"
+            ));
+        }
+        let is_musl = self
+            .is_musl
+            .or(self.libc.map(|l| l == Libc::Musl))
+            .unwrap_or_else(|| platform_is_musl(&platform));
+        let signature_verifier = self
+            .verify_signature
+            .map(SignatureVerifier::new)
+            .transpose()?;
+        let gpg_verifier = (!self.verify_gpg.is_empty())
+            .then(|| GpgVerifier::new(&self.verify_gpg))
+            .transpose()?;
+        let version_req = self.version_req.map(VersionReq::from_str).transpose()?;
+        let tag_pattern = self
+            .tag_pattern
+            .map(|p| {
+                Regex::new(p).with_context(|| format!("could not parse the tag pattern `{p}`"))
+            })
+            .transpose()?;
+        let matching_regex = self
+            .matching_regex
+            .map(|r| {
+                Regex::new(r).with_context(|| format!("could not parse the matching regex `{r}`"))
+            })
+            .transpose()?;
 
         Ok(Ubi::new(
             forge,
             asset_url,
-            AssetPicker::new(self.matching, platform, is_musl, self.extract_all),
+            version_req,
+            tag_pattern,
+            self.include_prereleases,
+            AssetPicker::new(
+                self.matching,
+                matching_regex,
+                self.exact_asset_name,
+                platform,
+                is_musl,
+                self.extract_all || self.all_exes,
+                self.prefer_static,
+                self.prefer_gnu_on_windows,
+                extension_preference,
+                self.scoring_weights,
+                self.interactive && interactive::stdin_is_terminal(),
+            ),
+            self.asset_selector.take(),
             installer,
-            reqwest_client()?,
+            final_install_path,
+            package,
+            signature_verifier,
+            gpg_verifier,
+            self.verify_checksum,
+            self.expect_sha256.map(str::to_ascii_lowercase),
+            self.dry_run,
+            self.force,
+            self.skip_if_exists,
+            self.ensure,
+            download_cache,
+            install_meta,
+            release_info_cache,
+            keep_archive_dir,
+            self.versioned_install,
+            self.post_install.take(),
+            self.keep_install_on_post_install_failure,
+            self.install_lock_timeout
+                .unwrap_or(DEFAULT_INSTALL_LOCK_TIMEOUT),
+            retry_policy,
+            self.max_download_rate.and_then(NonZeroU64::new),
+            self.take_download_progress(),
+            client,
         ))
     }
 
-    fn new_installer(&self, project_name: &str, platform: &Platform) -> Result<Box<dyn Installer>> {
+    fn new_installer(
+        &self,
+        install_dir: Option<&Path>,
+        project_name: &str,
+        platform: &Platform,
+    ) -> Result<Box<dyn Installer>> {
         if self.extract_all {
-            let install_path = install_path(self.install_dir.as_deref(), None)?;
-            Ok(Box::new(ArchiveInstaller::new(install_path)))
+            let install_path = install_path(install_dir, None)?;
+            Ok(Box::new(ArchiveInstaller::new(
+                install_path,
+                self.strip_components,
+            )))
+        } else if self.install_as_is {
+            let file_name = self.as_is_file_name()?;
+            let install_path = install_path(install_dir, Some(&file_name))?;
+            Ok(Box::new(AsIsInstaller::new(install_path)))
+        } else if self.all_exes {
+            let install_path = install_path(install_dir, None)?;
+            let glob = self
+                .exe_glob
+                .map(|g| {
+                    Pattern::new(g).with_context(|| format!("could not parse the exe glob `{g}`"))
+                })
+                .transpose()?;
+            Ok(Box::new(AllExesInstaller::new(
+                install_path,
+                glob,
+                platform.target_os == OS::Windows,
+            )))
         } else {
             let expect_exe_stem_name = expect_exe_stem_name(self.exe, project_name);
-            let install_path = install_path(
-                self.install_dir.as_deref(),
-                self.rename_exe_to.or(Some(expect_exe_stem_name)),
-            )?;
+            // The exe may include the directories it's in, but we only install the file itself.
+            let exe_file_name = expect_exe_stem_name
+                .rsplit('/')
+                .next()
+                .unwrap_or(expect_exe_stem_name);
+            let install_path =
+                install_path(install_dir, self.rename_exe_to.or(Some(exe_file_name)))?;
             Ok(Box::new(ExeInstaller::new(
                 install_path,
                 expect_exe_stem_name.to_string(),
                 platform.target_os == OS::Windows,
+                self.completions_dir.clone(),
+                self.verify_binary_arch.then(|| platform.clone()),
             )))
         }
     }
 
-    fn new_forge(
-        &self,
-        project_name: String,
-        forge_type: &ForgeType,
-    ) -> Result<Box<dyn Forge + Send + Sync>> {
-        forge_type.make_forge_impl(
-            project_name,
-            self.tag.map(String::from),
-            self.api_base_url.map(String::from),
-            self.token.map(String::from),
-        )
+    // The file is installed with the name of the asset, which we know before we pick it, since the
+    // asset must be named exactly or given as a URL or path.
+    fn as_is_file_name(&self) -> Result<String> {
+        if let Some(name) = self.rename_exe_to.or(self.exact_asset_name) {
+            return Ok(name.to_string());
+        }
+        if let Some(path) = &self.local_asset {
+            if let Some(name) = path.file_name() {
+                return Ok(name.to_string_lossy().into_owned());
+            }
+        }
+        if let Some(url) = self.url {
+            let url = Url::parse(url)?;
+            if let Some(name) = url
+                .path_segments()
+                .and_then(|mut s| s.next_back())
+                .filter(|n| !n.is_empty())
+            {
+                return Ok(name.to_string());
+            }
+        }
+        Err(anyhow!(
+            "could not determine the name of the file to install, so you must set rename_exe_to"
+        ))
+    }
+
+    #[cfg(feature = "progress")]
+    fn take_download_progress(&mut self) -> Option<Box<dyn DownloadProgress>> {
+        use crate::progress::ProgressBarReporter;
+        use std::io::IsTerminal;
+
+        if self.download_progress.is_none() && self.progress && std::io::stderr().is_terminal() {
+            return Some(Box::new(ProgressBarReporter::new(
+                self.color.use_color_on_stderr(),
+            )));
+        }
+        self.download_progress.take()
+    }
+
+    #[cfg(not(feature = "progress"))]
+    fn take_download_progress(&mut self) -> Option<Box<dyn DownloadProgress>> {
+        self.download_progress.take()
+    }
+
+    fn new_download_cache(&self, project_name: &str) -> Result<Option<DownloadCache>> {
+        if !self.cache_downloads && self.cache_file.is_none() {
+            return Ok(None);
+        }
+
+        let path = if let Some(cache_file) = &self.cache_file {
+            cache_file.clone()
+        } else {
+            install_path(self.install_dir.as_deref(), Some(DEFAULT_CACHE_FILE_NAME))?
+        };
+        debug!("download cache path = {}", path.display());
+        Ok(Some(DownloadCache::new(path, project_name.to_string())))
+    }
+
+    fn new_release_info_cache(&self) -> Result<Option<ReleaseInfoCache>> {
+        if !self.cache_release_info
+            && self.release_info_cache_dir.is_none()
+            && self.release_info_cache_ttl.is_none()
+        {
+            return Ok(None);
+        }
+
+        let dir = if let Some(dir) = &self.release_info_cache_dir {
+            dir.clone()
+        } else {
+            ReleaseInfoCache::default_dir()?
+        };
+        debug!("release info cache dir = {}", dir.display());
+        Ok(Some(ReleaseInfoCache::new(
+            dir,
+            self.release_info_cache_ttl
+                .unwrap_or(DEFAULT_RELEASE_INFO_CACHE_TTL),
+            self.refresh_release_info,
+        )))
+    }
+
+    fn new_forge(
+        &self,
+        project_name: String,
+        forge_type: &ForgeType,
+        project_ref: Option<&ProjectRef>,
+        gitlab_url: Option<&Url>,
+        oci_reference: Option<&OciReference>,
+        retry_policy: RetryPolicy,
+    ) -> Result<Box<dyn Forge + Send + Sync>> {
+        let netrc_file = netrc::default_netrc_file();
+        let opts = ForgeOptions {
+            tag: self
+                .tag
+                .map(String::from)
+                .or_else(|| oci_reference.and_then(|r| r.tag.clone())),
+            api_base: self.api_base(
+                forge_type,
+                project_ref,
+                gitlab_url,
+                oci_reference,
+                |name| env::var(name).ok(),
+            )?,
+            token: self.token.map(String::from),
+            keyring: self.use_keyring.then_some(&OsKeyring as &dyn Keyring),
+            netrc_file: netrc_file.as_deref(),
+            retry_policy,
+        };
+        match project_ref {
+            Some(project_ref) => forge::forge_for(project_ref, &opts),
+            None => forge_type.make_forge_impl(project_name, &opts),
+        }
+    }
+
+    // An explicit option always wins. After that, a project URL's host tells us which instance to
+    // use, so the URL from the env is only used when the project isn't a URL.
+    fn api_base(
+        &self,
+        forge_type: &ForgeType,
+        project_ref: Option<&ProjectRef>,
+        gitlab_url: Option<&Url>,
+        oci_reference: Option<&OciReference>,
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<String>> {
+        if let Some(api_base) = self
+            .api_base_url
+            .map(String::from)
+            .or_else(|| gitlab_url.map(|u| gitlab::api_base_url_for_instance(u).to_string()))
+            .or_else(|| oci_reference.map(OciReference::registry_url))
+        {
+            return Ok(Some(api_base));
+        }
+        if project_ref.is_some_and(|p| p.host.is_some()) {
+            return Ok(None);
+        }
+
+        let forge_type = project_ref.map_or(forge_type, |p| &p.forge);
+        Ok(forge_type
+            .api_base_url_from_env(get_env)?
+            .map(|u| u.to_string()))
+    }
+
+    // A project in an OCI registry is a reference like `ghcr.io/owner/image:tag`, which we can't
+    // parse like a project URL for a forge. We treat the project as a reference if the forge is
+    // OCI, or if no forge was given and the project starts with `ghcr.io/`.
+    fn oci_reference(&self, forge: Option<&ForgeType>) -> Result<Option<OciReference>> {
+        let Some(project) = self.project else {
+            return Ok(None);
+        };
+        if project.starts_with("http") {
+            return Ok(None);
+        }
+        let is_oci = match forge {
+            Some(forge) => *forge == ForgeType::Oci,
+            None => project.starts_with(&format!("{GHCR_DOMAIN}/")),
+        };
+        if !is_oci {
+            return Ok(None);
+        }
+
+        let reference = OciReference::from_str(project)?;
+        if reference.tag.is_some() && self.tag.is_some() {
+            return Err(anyhow!(
+                "You cannot set a tag when the OCI project `{project}` includes a tag or digest"
+            ));
+        }
+        Ok(Some(reference))
+    }
+
+    fn check_project_tag_conflicts(&self) -> Result<()> {
+        if self.tag.is_some()
+            || self.version_req.is_some()
+            || self.tag_pattern.is_some()
+            || self.include_prereleases
+        {
+            return Err(anyhow!(
+                "You cannot set a tag, version_req, tag_pattern, or include_prereleases when the project `{}` includes a tag",
+                self.project.unwrap_or_default(),
+            ));
+        }
+        Ok(())
     }
 
-    fn determine_platform(&self) -> Result<Platform> {
+    pub(crate) fn determine_platform(&self) -> Result<Platform> {
         if let Some(p) = self.platform {
             Ok(p.clone())
         } else {
@@ -289,61 +1742,190 @@ impl<'a> UbiBuilder<'a> {
         }
     }
 
+    fn validated_extension_preference(&self) -> Result<Vec<&'a str>> {
+        let Some(extensions) = &self.extension_preference else {
+            return Ok(DEFAULT_EXTENSION_PREFERENCE.to_vec());
+        };
+
+        extensions
+            .iter()
+            .map(|e| {
+                let ext = e.strip_prefix('.').unwrap_or(e);
+                let path = format!("asset.{}", ext.to_ascii_lowercase());
+                match Extension::from_path(Path::new(&path)) {
+                    Ok(Some(known)) if known.extension_without_dot().eq_ignore_ascii_case(ext) => {
+                        Ok(ext)
+                    }
+                    _ => Err(anyhow!(
+                        "`{e}` in the extension_preference is not an extension that ubi knows about"
+                    )),
+                }
+            })
+            .collect()
+    }
+
     fn check_musl_setting(&self, platform: &Platform) -> Result<()> {
-        if self.is_musl.unwrap_or_default() && platform.target_os != OS::Linux {
+        if platform.target_os == OS::Linux {
+            return Ok(());
+        }
+        if self.is_musl.unwrap_or_default() {
             return Err(anyhow!(
                 "You cannot set is_musl to true on a non-Linux platform - the current platform is {}",
                 platform.target_os,
             ));
         }
+        if self.libc == Some(Libc::Musl) {
+            return Err(anyhow!(
+                "You cannot set libc to musl on a non-Linux platform - the current platform is {}",
+                platform.target_os,
+            ));
+        }
         Ok(())
     }
 }
 
+// The returned project only has a host if it came from a URL, since a bare name's host is just the
+// default for its forge. Its tag is set if the project ended with `@<tag>`.
 fn parse_project_name(
     project: Option<&str>,
     url: Option<&Url>,
     forge: Option<ForgeType>,
-) -> Result<(String, ForgeType)> {
-    let (parsed, from) = if let Some(project) = project {
+) -> Result<ProjectRef> {
+    let (parsed, from, host, tag) = if let Some(project) = project {
         if project.starts_with("http") {
-            (Url::parse(project)?, format!("--project {project}"))
+            let parsed = Url::parse(project)?;
+            // An OCI reference can include a digest after an `@`, so we don't look for a tag in it.
+            let (parsed, tag) = if forge
+                .clone()
+                .unwrap_or_else(|| ForgeType::from_url(&parsed))
+                == ForgeType::Oci
+            {
+                (parsed, None)
+            } else {
+                project::split_url_tag(project, &parsed)?
+            };
+            let host = project::host_with_port(&parsed);
+            (parsed, format!("--project {project}"), Some(host), tag)
         } else {
-            let base = forge.unwrap_or_default().url_base();
-            (base.join(project)?, format!("--project {project}"))
+            let (project, tag) = project::split_tag(project, project)?;
+            let tag = tag.map(String::from);
+            if forge == Some(ForgeType::AzureDevOps) {
+                let (org, proj, repo) = project::split_azure_devops_project_name(project)?;
+                return Ok(ProjectRef {
+                    forge: ForgeType::AzureDevOps,
+                    owner: format!("{org}/{proj}"),
+                    repo: repo.to_string(),
+                    tag,
+                    host: None,
+                });
+            }
+            // A bare project name must be exactly `owner/repo`. Otherwise, joining it to the base
+            // URL below would quietly ignore anything after the repo.
+            project::split_project_name(project)?;
+            let base = forge.clone().unwrap_or_default().url_base();
+            (
+                base.join(project)?,
+                format!("--project {project}"),
+                None,
+                tag,
+            )
         }
     } else if let Some(u) = url {
-        (u.clone(), format!("--url {u}"))
+        (
+            u.clone(),
+            format!("--url {u}"),
+            Some(project::host_with_port(u)),
+            None,
+        )
     } else {
         unreachable!(
             "Did not get a --project or --url argument but that should be checked in main.rs"
         );
     };
 
-    let parts = parsed.path().split('/').collect::<Vec<_>>();
-    if parts.len() < 3 || parts[1].is_empty() || parts[2].is_empty() {
-        return Err(anyhow!("could not parse org and repo name from {from}"));
+    // A self-hosted forge can be on any domain, so an explicit forge always wins over the one we
+    // guess from the URL.
+    let forge = forge.unwrap_or_else(|| ForgeType::from_url(&parsed));
+
+    // The URL of a release page picks that release. We don't look at the path of a `--url`, since
+    // that asset is downloaded directly.
+    let tag = match project {
+        Some(p) if p.starts_with("http") => {
+            project::combine_url_tags(p, tag, project::tag_from_url(&forge, &parsed))?
+        }
+        _ => tag,
+    };
+
+    if forge == ForgeType::AzureDevOps {
+        let Some((org, proj, repo)) = project::azure_devops_project_from_path(parsed.path()) else {
+            return Err(anyhow!(
+                "could not parse organization and project name from {from}"
+            ));
+        };
+        debug!("Parsed {from} = {org} / {proj} / {repo}");
+        return Ok(ProjectRef {
+            forge,
+            owner: format!("{org}/{proj}"),
+            repo: repo.to_string(),
+            tag,
+            host,
+        });
     }
 
-    // The first part is an empty string for the leading '/' in the path.
-    let (org, proj) = (parts[1], parts[2]);
+    let Some((org, proj)) = project::owner_and_repo_from_path(parsed.path()) else {
+        return Err(anyhow!("could not parse org and repo name from {from}"));
+    };
     debug!("Parsed {from} = {org} / {proj}");
 
-    Ok((
-        format!("{org}/{proj}"),
-        // If the forge argument was not `None` this is kind of pointless, but it should never
-        // be _wrong_ in that case.
-        ForgeType::from_url(&parsed),
-    ))
+    Ok(ProjectRef {
+        forge,
+        owner: org.to_string(),
+        repo: proj.to_string(),
+        tag,
+        host,
+    })
+}
+
+fn project_is_release_asset_url(project: &str, forge: Option<&ForgeType>) -> bool {
+    if !project.starts_with("http") {
+        return false;
+    }
+    let Ok(url) = Url::parse(project) else {
+        return false;
+    };
+    let forge = forge.cloned().unwrap_or_else(|| ForgeType::from_url(&url));
+    project::is_release_asset_url(&forge, &url)
+}
+
+fn local_asset_url(path: &Path) -> Result<Url> {
+    let path = fs::canonicalize(path)
+        .with_context(|| format!("could not find the local asset at {}", path.display()))?;
+    Url::from_file_path(&path)
+        .map_err(|()| anyhow!("could not make a file URL from {}", path.display()))
+}
+
+// There's no project for a local file, but we use the project name to find the executable in an
+// archive, so we guess it from the file name.
+fn local_asset_project_name(url: &Url) -> Result<String> {
+    let file_name = url
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .unwrap_or_default();
+    let name = file_name.split(['-', '_', '.']).next().unwrap_or_default();
+    if name.is_empty() {
+        return Err(anyhow!(
+            "could not determine the executable name from the local asset at {url}"
+        ));
+    }
+    debug!("using {name} as the executable name for the local asset at {url}");
+    Ok(name.to_string())
 }
 
 fn install_path(install_dir: Option<&Path>, exe: Option<&str>) -> Result<PathBuf> {
     let mut install_dir = if let Some(install_dir) = install_dir {
         install_dir.to_path_buf()
     } else {
-        let mut install_dir = env::current_dir()?;
-        install_dir.push("bin");
-        install_dir
+        default_install_dir(cfg!(target_os = "windows"), |name| env::var_os(name))?
     };
     if let Some(exe) = exe {
         install_dir.push(exe);
@@ -352,6 +1934,39 @@ fn install_path(install_dir: Option<&Path>, exe: Option<&str>) -> Result<PathBuf
     Ok(install_dir)
 }
 
+// The XDG spec says that relative paths in its env vars should be ignored, so we do the same for
+// `XDG_BIN_HOME`, even though that one isn't in the spec yet.
+pub(crate) fn default_install_dir(
+    is_windows: bool,
+    var: impl Fn(&str) -> Option<OsString>,
+) -> Result<PathBuf> {
+    let non_empty_var = |name| var(name).filter(|v| !v.is_empty());
+    if is_windows {
+        let Some(local_app_data) = non_empty_var("LOCALAPPDATA") else {
+            return Err(anyhow!(
+                "could not find the default install directory because the LOCALAPPDATA env var is not set"
+            ));
+        };
+        return Ok(PathBuf::from(local_app_data).join("ubi").join("bin"));
+    }
+
+    if let Some(xdg_bin_home) = non_empty_var("XDG_BIN_HOME").map(PathBuf::from) {
+        if xdg_bin_home.is_absolute() {
+            return Ok(xdg_bin_home);
+        }
+        debug!(
+            "ignoring XDG_BIN_HOME because it is not an absolute path: {}",
+            xdg_bin_home.display()
+        );
+    }
+    let Some(home) = non_empty_var("HOME") else {
+        return Err(anyhow!(
+            "could not find the default install directory because the HOME env var is not set"
+        ));
+    };
+    Ok(PathBuf::from(home).join(".local").join("bin"))
+}
+
 fn expect_exe_stem_name<'a>(exe: Option<&'a str>, project_name: &'a str) -> &'a str {
     let name = if let Some(exe) = exe {
         exe
@@ -365,7 +1980,7 @@ fn expect_exe_stem_name<'a>(exe: Option<&'a str>, project_name: &'a str) -> &'a
     name
 }
 
-fn platform_is_musl(platform: &Platform) -> bool {
+pub(crate) fn platform_is_musl(platform: &Platform) -> bool {
     if platform.target_os != OS::Linux {
         return false;
     }
@@ -383,13 +1998,111 @@ fn platform_is_musl(platform: &Platform) -> bool {
     output.status.success() && String::from_utf8_lossy(&output.stdout).contains("musl")
 }
 
-fn reqwest_client() -> Result<Client> {
-    let builder = Client::builder().gzip(true);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_USER_AGENT: &str = concat!(
+    "ubi/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/houseabsolute/ubi)",
+);
+
+// These are either set by `ubi` for a forge token, or would break the request if they were wrong.
+const RESERVED_HEADERS: &[&str] = &[
+    "authorization",
+    "content-length",
+    "host",
+    "job-token",
+    "private-token",
+    "proxy-authorization",
+    "transfer-encoding",
+];
+
+fn extra_headers(headers: &[(&str, &str)], allow_reserved: bool) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name =
+            HeaderName::from_str(name).with_context(|| format!("invalid header name `{name}`"))?;
+        if !allow_reserved && RESERVED_HEADERS.contains(&header_name.as_str()) {
+            return Err(anyhow!(
+                "You cannot set the reserved `{name}` header unless you call allow_reserved_headers"
+            ));
+        }
+        // These are often used for authentication, so we don't want them showing up in debug
+        // output.
+        let mut header_value = HeaderValue::from_str(value)
+            .with_context(|| format!("invalid value for the `{name}` header"))?;
+        header_value.set_sensitive(true);
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+// If no proxy is given, reqwest will look at the standard proxy env vars, including `NO_PROXY`, on
+// its own. Setting a proxy turns that off, so we need to look at `NO_PROXY` ourselves.
+//
+// We don't set reqwest's overall timeout here, since that would include reading the response body.
+// The request timeout is applied by the `RetryPolicy` instead.
+//
+// The certificates in `ca_cert` are added to the default roots rather than replacing them, so a
+// private CA for one forge doesn't break requests to other sites.
+//
+// GitHub redirects asset downloads to a storage host, which rejects requests that include the forge
+// token. reqwest only removes a few headers, like `Authorization`, when a redirect goes to a
+// different host, so it would still send a GitLab `JOB-TOKEN` header or the extra headers there.
+// Instead, the redirect policy stops at a redirect to a different host, and the `RetryPolicy`
+// follows it without the token or the extra headers.
+fn reqwest_client(
+    proxy: Option<&str>,
+    connect_timeout: Duration,
+    user_agent: &str,
+    ca_cert: Option<&Path>,
+    danger_accept_invalid_certs: bool,
+    min_tls_version: TlsVersion,
+) -> Result<Client> {
+    debug!("requiring TLS {} or later", min_tls_version.as_ref());
+    let mut builder = Client::builder()
+        .gzip(true)
+        .redirect(retry::redirect_policy())
+        .connect_timeout(connect_timeout)
+        .min_tls_version(min_tls_version.reqwest_version());
+    if let Some(proxy) = proxy {
+        debug!("using proxy {proxy}");
+        builder = builder.proxy(
+            Proxy::all(proxy)
+                .with_context(|| format!("invalid proxy URL: {proxy}"))?
+                .no_proxy(NoProxy::from_env()),
+        );
+    }
+    if let Some(path) = ca_cert {
+        let pem = fs::read(path)
+            .with_context(|| format!("could not read the CA certificate at {}", path.display()))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("could not parse the CA certificate at {}", path.display()))?;
+        if certs.is_empty() {
+            return Err(anyhow!(
+                "the CA certificate file at {} does not contain any certificates",
+                path.display(),
+            ));
+        }
+        debug!(
+            "trusting {} certificate(s) from {}",
+            certs.len(),
+            path.display()
+        );
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if danger_accept_invalid_certs {
+        warn!("TLS certificate verification is turned off, so downloads cannot be trusted");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
 
     let mut headers = HeaderMap::new();
     headers.insert(
         USER_AGENT,
-        HeaderValue::from_str(&format!("ubi version {}", super::VERSION))?,
+        HeaderValue::from_str(user_agent)
+            .with_context(|| format!("invalid user agent `{user_agent}`"))?,
     );
     Ok(builder.default_headers(headers).build()?)
 }
@@ -397,8 +2110,20 @@ fn reqwest_client() -> Result<Client> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use mockito::{Matcher, Server};
+    use serial_test::serial;
     use test_case::test_case;
 
+    // Most of these tests only check the project name and forge.
+    fn project_name_and_forge(
+        project: Option<&str>,
+        url: Option<&Url>,
+        forge: Option<ForgeType>,
+    ) -> Result<(String, ForgeType)> {
+        let project = super::parse_project_name(project, url, forge)?;
+        Ok((project.project_name(), project.forge))
+    }
+
     #[test]
     fn parse_project_name() -> Result<()> {
         let org_and_repo = "some-owner/some-repo";
@@ -410,7 +2135,7 @@ mod test {
             format!("https://github.com/{org_and_repo}/actions/runs/4275745616"),
         ];
         for p in projects {
-            let (project_name, forge_type) = super::parse_project_name(Some(p), None, None)?;
+            let (project_name, forge_type) = project_name_and_forge(Some(p), None, None)?;
             assert_eq!(
                 project_name, org_and_repo,
                 "got the right project from --project {p}",
@@ -418,7 +2143,7 @@ mod test {
             assert_eq!(forge_type, ForgeType::GitHub);
 
             let (project_name, forge_type) =
-                super::parse_project_name(Some(p), None, Some(ForgeType::GitHub))?;
+                project_name_and_forge(Some(p), None, Some(ForgeType::GitHub))?;
             assert_eq!(
                 project_name, org_and_repo,
                 "got the right project from --project {p}",
@@ -428,7 +2153,7 @@ mod test {
 
         {
             let url = Url::parse("https://github.com/houseabsolute/precious/releases/download/v0.1.7/precious-Linux-x86_64-musl.tar.gz")?;
-            let (project_name, forge_type) = super::parse_project_name(None, Some(&url), None)?;
+            let (project_name, forge_type) = project_name_and_forge(None, Some(&url), None)?;
             assert_eq!(
                 project_name, "houseabsolute/precious",
                 "got the right project from the --url",
@@ -436,7 +2161,7 @@ mod test {
             assert_eq!(forge_type, ForgeType::GitHub);
 
             let (project_name, forge_type) =
-                super::parse_project_name(None, Some(&url), Some(ForgeType::GitHub))?;
+                project_name_and_forge(None, Some(&url), Some(ForgeType::GitHub))?;
             assert_eq!(
                 project_name, "houseabsolute/precious",
                 "got the right project from the --url",
@@ -444,9 +2169,390 @@ mod test {
             assert_eq!(forge_type, ForgeType::GitHub);
         }
 
+        {
+            let (project_name, forge_type) = project_name_and_forge(
+                Some("https://gitea.com/some-owner/some-repo/releases"),
+                None,
+                None,
+            )?;
+            assert_eq!(
+                project_name, org_and_repo,
+                "got the right project from gitea.com"
+            );
+            assert_eq!(forge_type, ForgeType::Gitea);
+
+            let (project_name, forge_type) =
+                project_name_and_forge(Some(org_and_repo), None, Some(ForgeType::Gitea))?;
+            assert_eq!(project_name, org_and_repo);
+            assert_eq!(forge_type, ForgeType::Gitea);
+        }
+
+        {
+            let (project_name, forge_type) = project_name_and_forge(
+                Some("https://gitlab.example.com/some-owner/some-repo/-/releases"),
+                None,
+                Some(ForgeType::GitLab),
+            )?;
+            assert_eq!(
+                project_name, org_and_repo,
+                "got the right project from a self-hosted GitLab"
+            );
+            assert_eq!(forge_type, ForgeType::GitLab);
+        }
+
+        {
+            let sourcehut_project = "~some-owner/some-repo";
+            let (project_name, forge_type) = project_name_and_forge(
+                Some("https://git.sr.ht/~some-owner/some-repo/refs/v1.0.0"),
+                None,
+                None,
+            )?;
+            assert_eq!(
+                project_name, sourcehut_project,
+                "got the right project from git.sr.ht"
+            );
+            assert_eq!(forge_type, ForgeType::SourceHut);
+
+            let (project_name, forge_type) =
+                project_name_and_forge(Some(sourcehut_project), None, Some(ForgeType::SourceHut))?;
+            assert_eq!(project_name, sourcehut_project);
+            assert_eq!(forge_type, ForgeType::SourceHut);
+        }
+
+        {
+            let azure_project = "some-org/some-project/some-repo";
+            let (project_name, forge_type) = project_name_and_forge(
+                Some("https://dev.azure.com/some-org/some-project/_git/some-repo"),
+                None,
+                None,
+            )?;
+            assert_eq!(
+                project_name, azure_project,
+                "got the right project from dev.azure.com"
+            );
+            assert_eq!(forge_type, ForgeType::AzureDevOps);
+
+            let (project_name, forge_type) =
+                project_name_and_forge(Some(azure_project), None, Some(ForgeType::AzureDevOps))?;
+            assert_eq!(project_name, azure_project);
+            assert_eq!(forge_type, ForgeType::AzureDevOps);
+
+            let err =
+                project_name_and_forge(Some(org_and_repo), None, Some(ForgeType::AzureDevOps))
+                    .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "`some-owner/some-repo` is not a valid project name, it must be in the form `org/project/repo`",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test_case(Some("some-owner/some-repo"), None, None ; "bare project name")]
+    #[test_case(Some("https://codeberg.org/some-owner/some-repo"), None, Some("codeberg.org") ; "project URL")]
+    #[test_case(Some("https://git.example.com:3000/some-owner/some-repo"), None, Some("git.example.com:3000") ; "project URL with a port")]
+    #[test_case(None, Some("https://codeberg.org/some-owner/some-repo/releases/download/v1.0.0/some-repo.tar.gz"), Some("codeberg.org") ; "asset URL")]
+    fn parse_project_name_host(
+        project: Option<&str>,
+        url: Option<&str>,
+        expect: Option<&str>,
+    ) -> Result<()> {
+        let url = url.map(Url::parse).transpose()?;
+        let project = super::parse_project_name(project, url.as_ref(), None)?;
+        assert_eq!(project.host.as_deref(), expect);
+        Ok(())
+    }
+
+    #[test_case(Some("houseabsolute/ubi@v0.2.0"), None, "houseabsolute/ubi", Some("v0.2.0") ; "bare project name with tag")]
+    #[test_case(Some("https://github.com/houseabsolute/ubi@v1"), None, "houseabsolute/ubi", Some("v1") ; "project URL with tag")]
+    #[test_case(Some("houseabsolute/ubi"), None, "houseabsolute/ubi", None ; "bare project name without tag")]
+    #[test_case(Some("https://github.com/houseabsolute/ubi"), None, "houseabsolute/ubi", None ; "project URL without tag")]
+    #[test_case(None, Some("https://github.com/houseabsolute/ubi/releases/download/v1/ubi@x.tar.gz"), "houseabsolute/ubi", None ; "asset URL with an @ is not split")]
+    #[test_case(Some("https://github.com/houseabsolute/ubi/releases/tag/v1.2.3"), None, "houseabsolute/ubi", Some("v1.2.3") ; "release URL")]
+    #[test_case(Some("https://gitlab.com/gitlab-org/cli/-/releases/v1.36.0"), None, "gitlab-org/cli", Some("v1.36.0") ; "GitLab release URL")]
+    fn parse_project_name_tag(
+        project: Option<&str>,
+        url: Option<&str>,
+        expect_name: &str,
+        expect_tag: Option<&str>,
+    ) -> Result<()> {
+        let url = url.map(Url::parse).transpose()?;
+        let project = super::parse_project_name(project, url.as_ref(), None)?;
+        assert_eq!(project.project_name(), expect_name);
+        assert_eq!(project.tag.as_deref(), expect_tag);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_project_name_with_tag_after_an_at_and_in_the_path() {
+        let project = "https://github.com/houseabsolute/ubi/releases/tag/v1@v2";
+        let err = super::parse_project_name(Some(project), None, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("could not parse `{project}` as a project: it has a tag after an `@` and a tag in the URL path"),
+        );
+    }
+
+    #[test_case("https://github.com/houseabsolute/ubi/releases/download/v1/ubi-Linux-x86_64.tar.gz", None, true ; "GitHub asset URL")]
+    #[test_case("https://git.example.com/owner/repo/releases/download/v1/repo.tar.gz", Some(ForgeType::Gitea), true ; "self-hosted Gitea asset URL")]
+    #[test_case("https://github.com/houseabsolute/ubi/releases/tag/v1", None, false ; "release URL")]
+    #[test_case("houseabsolute/ubi", None, false ; "bare project name")]
+    fn project_is_release_asset_url(project: &str, forge: Option<ForgeType>, expect: bool) {
+        assert_eq!(
+            super::project_is_release_asset_url(project, forge.as_ref()),
+            expect
+        );
+    }
+
+    #[test_case(|b| b.tag("v2") ; "tag")]
+    #[test_case(|b| b.version_req("^1") ; "version_req")]
+    #[test_case(|b| b.tag_pattern("^v") ; "tag_pattern")]
+    #[test_case(|b| b.include_prereleases() ; "include_prereleases")]
+    fn build_with_tagged_project_and_tag_option(f: fn(UbiBuilder) -> UbiBuilder) {
+        let err = f(UbiBuilder::new().project("houseabsolute/ubi@v1"))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set a tag, version_req, tag_pattern, or include_prereleases when the project `houseabsolute/ubi@v1` includes a tag",
+        );
+    }
+
+    #[test_case("justrepo", "`justrepo` is not a valid project name, it must be in the form `owner/repo`" ; "no slash")]
+    #[test_case("a/b/c", "`a/b/c` is not a valid project name, it must be in the form `owner/repo`" ; "too many slashes")]
+    #[test_case("owner/", "`owner/` is not a valid project name, it must be in the form `owner/repo`" ; "empty repo")]
+    #[test_case("", "the project name is empty, but it must be in the form `owner/repo`" ; "empty")]
+    fn build_with_invalid_project_name(project: &str, expect: &str) {
+        for forge in [ForgeType::Gitea, ForgeType::GitHub, ForgeType::GitLab] {
+            let err = UbiBuilder::new()
+                .project(project)
+                .forge(forge.clone())
+                .build()
+                .unwrap_err();
+            assert_eq!(err.to_string(), expect, "error for {}", forge.forge_name());
+        }
+    }
+
+    #[test_case(|b| b, Some("https://ghe.example.com/api/v3") ; "env var")]
+    #[test_case(|b| b.api_base_url("https://api.example.com"), Some("https://api.example.com") ; "api_base_url wins")]
+    #[test_case(|b| b.forge(ForgeType::GitLab), Some("https://gitlab.example.com/api/v4") ; "GitLab env var")]
+    #[test_case(
+        |b| b.gitlab_url("https://other-gitlab.example.com"),
+        Some("https://other-gitlab.example.com/api/v4") ;
+        "gitlab_url wins"
+    )]
+    #[test_case(|b| b.project("https://github.com/test/project"), None ; "project URL")]
+    fn api_base_from_env(f: fn(UbiBuilder) -> UbiBuilder, expect: Option<&str>) -> Result<()> {
+        let env = [
+            ("UBI_GITHUB_API_URL", "https://ghe.example.com/api/v3"),
+            ("UBI_GITLAB_URL", "https://gitlab.example.com"),
+        ];
+        let get_env = |name: &str| {
+            env.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| (*v).to_string())
+        };
+
+        let builder = f(UbiBuilder::new().project("test/project"));
+        let gitlab_url = builder.gitlab_url.map(Url::parse).transpose()?;
+        let forge = builder
+            .forge
+            .clone()
+            .or(gitlab_url.as_ref().map(|_| ForgeType::GitLab));
+        let project_ref = super::parse_project_name(builder.project, None, forge)?;
+        assert_eq!(
+            builder.api_base(
+                &project_ref.forge,
+                Some(&project_ref),
+                gitlab_url.as_ref(),
+                None,
+                get_env,
+            )?,
+            expect.map(String::from),
+        );
+
+        Ok(())
+    }
+
+    #[test_case(
+        |b| b.project("test/project"),
+        "You must set an exact_asset_name, url, or local_asset when install_as_is is enabled" ;
+        "no exact asset"
+    )]
+    #[test_case(
+        |b| b.local_asset("module.wasm").exe("module"),
+        "You cannot set exe and enable install_as_is" ;
+        "exe"
+    )]
+    #[test_case(
+        |b| b.local_asset("module.wasm").all_exes(),
+        "You cannot enable install_as_is with extract_all or all_exes" ;
+        "all_exes"
+    )]
+    #[test_case(
+        |b| b.local_asset("module.wasm").verify_binary_arch(),
+        "You cannot set a completions_dir or verify_binary_arch with install_as_is" ;
+        "verify_binary_arch"
+    )]
+    fn build_with_install_as_is_conflict(f: fn(UbiBuilder) -> UbiBuilder, expect: &str) {
+        let err = f(UbiBuilder::new().install_as_is()).build().unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[test_case(
+        |b| b.project("test/project").extract_all(),
+        "You cannot enable versioned_install with extract_all, all_exes, or install_as_is" ;
+        "extract_all"
+    )]
+    #[test_case(
+        |b| b.project("test/project").exact_asset_name("module.wasm").install_as_is(),
+        "You cannot enable versioned_install with extract_all, all_exes, or install_as_is" ;
+        "install_as_is"
+    )]
+    #[test_case(
+        |b| b.url("https://example.com/project-Linux-x86_64.tar.gz"),
+        "You cannot set a url, local_asset, or stage_dir with versioned_install" ;
+        "url"
+    )]
+    #[test_case(
+        |b| b.project("test/project").stage_dir("stage"),
+        "You cannot set a url, local_asset, or stage_dir with versioned_install" ;
+        "stage_dir"
+    )]
+    fn build_with_versioned_install_conflict(f: fn(UbiBuilder) -> UbiBuilder, expect: &str) {
+        let err = f(UbiBuilder::new().versioned_install())
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[test_case(|b| b.extract_all() ; "extract_all")]
+    #[test_case(|b| b.all_exes() ; "all_exes")]
+    fn build_with_post_install_conflict(f: fn(UbiBuilder) -> UbiBuilder) {
+        let err = f(UbiBuilder::new()
+            .project("test/project")
+            .post_install(|_| Ok(())))
+        .build()
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set a post_install hook with extract_all or all_exes",
+        );
+    }
+
+    #[test_case(|b| b.install_dir("/usr/local/bin") ; "install_dir")]
+    #[test_case(|b| b.stage_dir("/tmp/stage") ; "stage_dir")]
+    #[test_case(|b| b.dry_run() ; "dry_run")]
+    #[test_case(|b| b.skip_if_exists() ; "skip_if_exists")]
+    #[test_case(|b| b.cache_downloads() ; "cache_downloads")]
+    #[test_case(|b| b.completions_dir("/tmp/completions") ; "completions_dir")]
+    #[test_case(|b| b.versioned_install() ; "versioned_install")]
+    fn build_with_package_output_conflict(f: fn(UbiBuilder) -> UbiBuilder) {
+        let err = f(UbiBuilder::new()
+            .project("test/project")
+            .package_output("/tmp/project.tar.gz"))
+        .build()
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set a package_output with install_dir, stage_dir, dry_run, skip_if_exists, cache_downloads, cache_file, completions_dir, or versioned_install",
+        );
+    }
+
+    #[test_case(|b| b.url("https://example.com/project.tar.gz") ; "url")]
+    #[test_case(|b| b.local_asset("test-data/project.tar.gz") ; "local_asset")]
+    #[test_case(|b| b.project("test/project").extract_all() ; "extract_all")]
+    #[test_case(|b| b.project("test/project").all_exes() ; "all_exes")]
+    #[test_case(|b| b.project("test/project").stage_dir("/tmp/stage") ; "stage_dir")]
+    #[test_case(|b| b.project("test/project").package_output("/tmp/project.tar.gz") ; "package_output")]
+    #[test_case(|b| b.project("test/project").force() ; "force")]
+    #[test_case(|b| b.project("test/project").skip_if_exists() ; "skip_if_exists")]
+    fn build_with_ensure_conflict(f: fn(UbiBuilder) -> UbiBuilder) {
+        let err = f(UbiBuilder::new().ensure()).build().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set ensure with url, local_asset, extract_all, all_exes, stage_dir, package_output, force, or skip_if_exists",
+        );
+    }
+
+    #[test]
+    fn build_with_package_prefix_without_package_output() {
+        let err = UbiBuilder::new()
+            .project("test/project")
+            .package_prefix("opt/bin")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set a package_prefix without a package_output",
+        );
+    }
+
+    #[test_case(None, Ok(&["tar.zst", "tar.xz", "tar.gz", "tgz", "zip"]) ; "default")]
+    #[test_case(Some(&["zip", ".tar.gz"]), Ok(&["zip", "tar.gz"]) ; "custom with leading dot")]
+    #[test_case(Some(&["TAR.XZ"]), Ok(&["TAR.XZ"]) ; "uppercase")]
+    #[test_case(Some(&["zip", "rar"]), Err("`rar` in the extension_preference is not an extension that ubi knows about") ; "unknown extension")]
+    #[test_case(Some(&[""]), Err("`` in the extension_preference is not an extension that ubi knows about") ; "empty")]
+    fn extension_preference(extensions: Option<&[&str]>, expect: Result<&[&str], &str>) {
+        let mut builder = UbiBuilder::new();
+        if let Some(extensions) = extensions {
+            builder = builder.extension_preference(extensions);
+        }
+        assert_eq!(
+            builder
+                .validated_extension_preference()
+                .map_err(|e| e.to_string()),
+            expect.map(<[&str]>::to_vec).map_err(String::from),
+        );
+    }
+
+    #[test_case(
+        "ghcr.io/owner/image:v1", None, None,
+        Some(("ghcr.io", "owner/image", Some("v1"))) ;
+        "ghcr without forge"
+    )]
+    #[test_case(
+        "registry.example.com/owner/image", Some(ForgeType::Oci), Some("v2"),
+        Some(("registry.example.com", "owner/image", None)) ;
+        "other registry with forge and tag"
+    )]
+    #[test_case("ghcr.io/owner/image", Some(ForgeType::GitHub), None, None ; "explicit non-OCI forge")]
+    #[test_case("https://ghcr.io/owner/image", None, None, None ; "URL")]
+    #[test_case("houseabsolute/precious", None, None, None ; "GitHub project")]
+    fn oci_reference(
+        project: &str,
+        forge: Option<ForgeType>,
+        tag: Option<&str>,
+        expect: Option<(&str, &str, Option<&str>)>,
+    ) -> Result<()> {
+        let mut builder = UbiBuilder::new().project(project);
+        if let Some(tag) = tag {
+            builder = builder.tag(tag);
+        }
+        assert_eq!(
+            builder.oci_reference(forge.as_ref())?,
+            expect.map(|(registry, repository, tag)| OciReference {
+                registry: Some(registry.to_string()),
+                repository: repository.to_string(),
+                tag: tag.map(String::from),
+            }),
+        );
         Ok(())
     }
 
+    #[test]
+    fn oci_reference_with_tag_and_tagged_project() {
+        let builder = UbiBuilder::new()
+            .project("ghcr.io/owner/image:v1")
+            .tag("v2");
+        let err = builder.oci_reference(None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "You cannot set a tag when the OCI project `ghcr.io/owner/image:v1` includes a tag or digest",
+        );
+    }
+
     #[test_case(
         None,
         "houseabsolute/precious",
@@ -466,4 +2572,302 @@ mod test {
     ) {
         assert_eq!(super::expect_exe_stem_name(exe, project_name), expect);
     }
+
+    #[tokio::test]
+    async fn reqwest_client_uses_proxy() -> Result<()> {
+        let mut proxy = Server::new_async().await;
+        let m = proxy
+            .mock("GET", "/repos/test/project/releases/latest")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = reqwest_client(
+            Some(&proxy.url()),
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            None,
+            false,
+            TlsVersion::default(),
+        )?;
+        // This host doesn't exist, so this request only succeeds if it goes through the proxy.
+        let resp = client
+            .get("http://forge.invalid/repos/test/project/releases/latest")
+            .send()
+            .await?;
+        assert_eq!(resp.status(), 200);
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    // We don't test setting the proxy via `HTTP_PROXY` and friends, since that's handled entirely by
+    // reqwest, and setting these env vars could send other tests' requests through our proxy.
+    #[serial]
+    #[tokio::test]
+    async fn reqwest_client_respects_no_proxy() -> Result<()> {
+        let mut proxy = Server::new_async().await;
+        let mut forge = Server::new_async().await;
+        let proxied = proxy
+            .mock("GET", Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+        let direct = forge
+            .mock("GET", "/repos/test/project/releases/latest")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let vars = env::vars();
+        env::remove_var("no_proxy");
+        env::set_var("NO_PROXY", "example.com,127.0.0.1");
+        let client = reqwest_client(
+            Some(&proxy.url()),
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            None,
+            false,
+            TlsVersion::default(),
+        );
+        env::remove_var("NO_PROXY");
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        let resp = client?
+            .get(format!(
+                "{}/repos/test/project/releases/latest",
+                forge.url()
+            ))
+            .send()
+            .await?;
+        assert_eq!(resp.status(), 200);
+
+        proxied.assert_async().await;
+        direct.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test]
+    fn reqwest_client_with_invalid_proxy() {
+        let err = reqwest_client(
+            Some("not a url"),
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            None,
+            false,
+            TlsVersion::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "invalid proxy URL: not a url");
+    }
+    // The server accepts the TCP connection but never responds, so the TLS handshake stalls. That
+    // counts as part of connecting.
+    #[tokio::test]
+    async fn reqwest_client_connect_timeout() -> Result<()> {
+        let addr = crate::test_case::stalled_server()?;
+
+        let client = reqwest_client(
+            None,
+            Duration::from_millis(100),
+            DEFAULT_USER_AGENT,
+            None,
+            false,
+            TlsVersion::default(),
+        )?;
+        let res = tokio::time::timeout(
+            Duration::from_secs(10),
+            client.get(format!("https://{addr}/")).send(),
+        )
+        .await?;
+        let err = res.unwrap_err();
+        assert!(err.is_connect(), "{err:?}");
+        assert!(err.is_timeout(), "{err:?}");
+
+        Ok(())
+    }
+    // The server's certificate is signed by a CA that isn't one of the default roots, so requests
+    // to it only succeed when that CA is trusted or certificates aren't verified.
+    #[test_case(None, false, true ; "default roots")]
+    #[test_case(Some("test-data/tls/ca.pem"), false, false ; "custom CA")]
+    #[test_case(None, true, false ; "accept invalid certs")]
+    #[test_case(Some("test-data/tls/server.pem"), false, true ; "CA that did not sign the server cert")]
+    #[tokio::test]
+    async fn reqwest_client_tls(
+        ca_cert: Option<&str>,
+        danger_accept_invalid_certs: bool,
+        expect_err: bool,
+    ) -> Result<()> {
+        let addr = crate::test_case::tls_server()?;
+
+        let client = reqwest_client(
+            None,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            ca_cert.map(Path::new),
+            danger_accept_invalid_certs,
+            TlsVersion::default(),
+        )?;
+        let res = client
+            .get(format!("https://localhost:{}/", addr.port()))
+            .send()
+            .await;
+        if expect_err {
+            let err = res.unwrap_err();
+            assert!(err.is_connect(), "{err:?}");
+        } else {
+            let resp = res?;
+            assert_eq!(resp.status(), 200);
+            assert_eq!(resp.text().await?, "ok");
+        }
+
+        Ok(())
+    }
+
+    // The server only speaks TLS 1.2, so requests to it fail when we require TLS 1.3.
+    #[test_case(TlsVersion::Tls1_2, false ; "TLS 1.2")]
+    #[test_case(TlsVersion::Tls1_3, true ; "TLS 1.3")]
+    #[tokio::test]
+    async fn reqwest_client_min_tls_version(
+        min_tls_version: TlsVersion,
+        expect_err: bool,
+    ) -> Result<()> {
+        let addr = crate::test_case::tls_server_with_versions(&[&rustls::version::TLS12])?;
+
+        let client = reqwest_client(
+            None,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            Some(Path::new("test-data/tls/ca.pem")),
+            false,
+            min_tls_version,
+        )?;
+        let res = client
+            .get(format!("https://localhost:{}/", addr.port()))
+            .send()
+            .await;
+        if expect_err {
+            let err = res.unwrap_err();
+            assert!(err.is_connect(), "{err:?}");
+        } else {
+            assert_eq!(res?.text().await?, "ok");
+        }
+
+        Ok(())
+    }
+
+    #[test_case(None, TlsVersion::Tls1_2 ; "default")]
+    #[test_case(Some(TlsVersion::Tls1_2), TlsVersion::Tls1_2 ; "TLS 1.2")]
+    #[test_case(Some(TlsVersion::Tls1_3), TlsVersion::Tls1_3 ; "TLS 1.3")]
+    fn min_tls_version(set: Option<TlsVersion>, expect: TlsVersion) -> Result<()> {
+        let mut builder = UbiBuilder::new()
+            .project("houseabsolute/precious")
+            .install_dir("/tmp");
+        if let Some(v) = set {
+            builder = builder.min_tls_version(v);
+        }
+        assert_eq!(builder.min_tls_version, expect);
+        builder.build()?;
+        Ok(())
+    }
+
+    #[test_case("test-data/tls/missing.pem", "could not read the CA certificate at test-data/tls/missing.pem" ; "missing file")]
+    #[test_case("test-data/tls/server.key", "the CA certificate file at test-data/tls/server.key does not contain any certificates" ; "no certificates")]
+    fn reqwest_client_with_invalid_ca_cert(ca_cert: &str, expect: &str) {
+        let err = reqwest_client(
+            None,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_USER_AGENT,
+            Some(Path::new(ca_cert)),
+            false,
+            TlsVersion::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[test]
+    fn extra_headers() -> Result<()> {
+        let headers = super::extra_headers(
+            &[
+                ("X-Internal-Auth", "secret"),
+                ("x-other", "first"),
+                ("X-Other", "second"),
+            ],
+            false,
+        )?;
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-internal-auth"], "secret");
+        assert!(headers["x-internal-auth"].is_sensitive());
+        assert_eq!(headers["x-other"], "second", "the last value is used");
+
+        let headers = super::extra_headers(&[("Authorization", "Basic Zm9vOmJhcg==")], true)?;
+        assert_eq!(headers["authorization"], "Basic Zm9vOmJhcg==");
+
+        Ok(())
+    }
+
+    #[test_case(
+        "Authorization",
+        "Bearer secret",
+        "You cannot set the reserved `Authorization` header unless you call allow_reserved_headers" ;
+        "reserved header"
+    )]
+    #[test_case(
+        "JOB-TOKEN",
+        "secret",
+        "You cannot set the reserved `JOB-TOKEN` header unless you call allow_reserved_headers" ;
+        "reserved GitLab header"
+    )]
+    #[test_case("Not A Header", "value", "invalid header name `Not A Header`" ; "invalid name")]
+    #[test_case("X-Internal-Auth", "bad\nvalue", "invalid value for the `X-Internal-Auth` header" ; "invalid value")]
+    fn extra_headers_errors(name: &str, value: &str, expect: &str) {
+        let err = super::extra_headers(&[(name, value)], false).unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+    #[test_case(
+        false,
+        &[("HOME", "/home/autarch")],
+        Some(&["/home/autarch", ".local", "bin"]) ;
+        "Unix with HOME"
+    )]
+    #[test_case(
+        false,
+        &[("HOME", "/home/autarch"), ("XDG_BIN_HOME", "/opt/autarch/bin")],
+        Some(&["/opt/autarch/bin"]) ;
+        "Unix with XDG_BIN_HOME"
+    )]
+    #[test_case(
+        false,
+        &[("HOME", "/home/autarch"), ("XDG_BIN_HOME", "")],
+        Some(&["/home/autarch", ".local", "bin"]) ;
+        "Unix with empty XDG_BIN_HOME"
+    )]
+    #[test_case(
+        false,
+        &[("HOME", "/home/autarch"), ("XDG_BIN_HOME", "bin")],
+        Some(&["/home/autarch", ".local", "bin"]) ;
+        "Unix with relative XDG_BIN_HOME"
+    )]
+    #[test_case(false, &[], None ; "Unix without HOME")]
+    #[test_case(
+        true,
+        &[("LOCALAPPDATA", r"C:\Users\autarch\AppData\Local"), ("HOME", "/home/autarch")],
+        Some(&[r"C:\Users\autarch\AppData\Local", "ubi", "bin"]) ;
+        "Windows with LOCALAPPDATA"
+    )]
+    #[test_case(true, &[("XDG_BIN_HOME", "/opt/autarch/bin")], None ; "Windows without LOCALAPPDATA")]
+    fn default_install_dir(is_windows: bool, vars: &[(&str, &str)], expect: Option<&[&str]>) {
+        let res = super::default_install_dir(is_windows, |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| OsString::from(v))
+        });
+        assert_eq!(res.ok(), expect.map(|e| e.iter().collect::<PathBuf>()));
+    }
 }
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use log::debug;
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, create_dir_all},
+    path::PathBuf,
+};
+
+pub(crate) const DEFAULT_CACHE_FILE_NAME: &str = ".ubi-cache.json";
+
+// The cache file maps a project and asset name to the validators the server sent for that asset
+// the last time we downloaded it.
+#[derive(Debug)]
+pub(crate) struct DownloadCache {
+    path: PathBuf,
+    project_name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub(crate) struct CacheEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    // We record what we installed so that we don't skip a download when the installed files are
+    // gone.
+    #[serde(default)]
+    pub(crate) installed: Vec<PathBuf>,
+}
+
+impl CacheEntry {
+    // Returns `None` if the response has neither an `ETag` nor a `Last-Modified` header, since
+    // there is nothing to cache in that case.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            etag,
+            last_modified,
+            installed: vec![],
+        })
+    }
+
+    pub(crate) fn is_installed(&self) -> bool {
+        !self.installed.is_empty() && self.installed.iter().all(|p| p.exists())
+    }
+
+    pub(crate) fn add_conditional_headers(
+        &self,
+        mut req_builder: RequestBuilder,
+    ) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            debug!("adding If-None-Match header with {etag}");
+            req_builder = req_builder.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            debug!("adding If-Modified-Since header with {last_modified}");
+            req_builder = req_builder.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        req_builder
+    }
+}
+
+impl DownloadCache {
+    pub(crate) fn new(path: PathBuf, project_name: String) -> Self {
+        Self { path, project_name }
+    }
+
+    pub(crate) fn get(&self, asset_name: &str) -> Result<Option<CacheEntry>> {
+        let key = self.key(asset_name);
+        let entry = self.read_entries()?.remove(&key);
+        debug!(
+            "{} a cache entry for {key} in {}",
+            if entry.is_some() {
+                "found"
+            } else {
+                "did not find"
+            },
+            self.path.display(),
+        );
+        Ok(entry)
+    }
+
+    pub(crate) fn store(&self, asset_name: &str, entry: CacheEntry) -> Result<()> {
+        let mut entries = self.read_entries()?;
+        entries.insert(self.key(asset_name), entry);
+
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)
+                .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+        }
+        debug!("writing download cache to {}", self.path.display());
+        fs::write(&self.path, serde_json::to_string_pretty(&entries)?).with_context(|| {
+            format!(
+                "could not write the download cache to {}",
+                self.path.display()
+            )
+        })
+    }
+
+    fn key(&self, asset_name: &str) -> String {
+        format!("{}/{asset_name}", self.project_name)
+    }
+
+    fn read_entries(&self) -> Result<BTreeMap<String, CacheEntry>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let contents = fs::read_to_string(&self.path).with_context(|| {
+            format!(
+                "could not read the download cache from {}",
+                self.path.display()
+            )
+        })?;
+        // A cache file we can't parse is not worth failing over. We'll just overwrite it.
+        Ok(serde_json::from_str(&contents).unwrap_or_else(|e| {
+            debug!(
+                "could not parse the download cache in {}: {e}",
+                self.path.display()
+            );
+            BTreeMap::new()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use tempfile::tempdir;
+    use test_log::test;
+
+    #[test]
+    fn store_and_get() -> Result<()> {
+        let td = tempdir()?;
+        let path = td.path().join("subdir").join(DEFAULT_CACHE_FILE_NAME);
+        let cache = DownloadCache::new(path.clone(), "test/project".to_string());
+        assert_eq!(cache.get("project.tar.gz")?, None);
+
+        let entry = CacheEntry {
+            etag: Some(r#""abc""#.to_string()),
+            last_modified: None,
+            installed: vec![td.path().join("project")],
+        };
+        cache.store("project.tar.gz", entry.clone())?;
+        cache.store("other.tar.gz", CacheEntry::default())?;
+
+        assert_eq!(cache.get("project.tar.gz")?, Some(entry));
+        assert_eq!(
+            DownloadCache::new(path, "test/other".to_string()).get("project.tar.gz")?,
+            None,
+            "entries are keyed by project",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unparseable_cache_file() -> Result<()> {
+        let td = tempdir()?;
+        let path = td.path().join(DEFAULT_CACHE_FILE_NAME);
+        fs::write(&path, "not json")?;
+
+        let cache = DownloadCache::new(path, "test/project".to_string());
+        assert_eq!(cache.get("project.tar.gz")?, None);
+        cache.store("project.tar.gz", CacheEntry::default())?;
+        assert_eq!(cache.get("project.tar.gz")?, Some(CacheEntry::default()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(CacheEntry::from_headers(&headers), None);
+
+        headers.insert(ETAG, HeaderValue::from_static(r#""abc""#));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(
+            CacheEntry::from_headers(&headers),
+            Some(CacheEntry {
+                etag: Some(r#""abc""#.to_string()),
+                last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+                installed: vec![],
+            }),
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ubi::Asset;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::{fs::File, io, path::Path};
+
+const SHA256_EXTENSION: &str = ".sha256";
+
+pub(crate) fn checksum_asset_for(asset: &Asset, assets: &[Asset]) -> Result<Asset> {
+    debug!("looking for a checksum file for {}", asset.name);
+
+    // We check for the most specific name first. The last check also matches names like
+    // `project_1.2.3_checksums.txt`, which is what goreleaser creates by default.
+    let sidecar_name = format!("{}{SHA256_EXTENSION}", asset.name);
+    let found = assets
+        .iter()
+        .find(|a| a.name == sidecar_name)
+        .or_else(|| assets.iter().find(|a| a.name == "SHA256SUMS"))
+        .or_else(|| assets.iter().find(|a| a.name.ends_with("checksums.txt")));
+    if let Some(a) = found {
+        debug!("found checksum file named {}", a.name);
+        return Ok(a.clone());
+    }
+
+    Err(anyhow!(
+        "could not find a checksum file for {} (looked for {sidecar_name}, SHA256SUMS, or checksums.txt)",
+        asset.name,
+    ))
+}
+
+// When we're given a URL instead of a project, there's no list of release assets to look in, so we
+// assume the checksum file is next to the asset.
+pub(crate) fn checksum_asset_for_url(asset: &Asset) -> Asset {
+    let mut url = asset.url.clone();
+    url.set_path(&format!("{}{SHA256_EXTENSION}", asset.url.path()));
+    Asset {
+        name: format!("{}{SHA256_EXTENSION}", asset.name),
+        url,
+        size: None,
+    }
+}
+
+// Checksum files contain lines like `<hash>  <filename>`, as created by `sha256sum`. In binary mode,
+// the filename is prefixed with a `*`. A `<asset>.sha256` file may also contain just the hash.
+pub(crate) fn expected_checksum(
+    contents: &str,
+    asset_name: &str,
+    checksum_file_name: &str,
+) -> Result<String> {
+    for line in contents.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(hash) = parts.next() else {
+            continue;
+        };
+        let name = parts.next();
+        if !is_sha256_hash(hash) {
+            continue;
+        }
+
+        match name {
+            Some(name) => {
+                let name = name.trim_start_matches('*').trim_start_matches("./");
+                if name == asset_name {
+                    return Ok(hash.to_lowercase());
+                }
+            }
+            None if checksum_file_name.ends_with(SHA256_EXTENSION) => {
+                return Ok(hash.to_lowercase());
+            }
+            None => (),
+        }
+    }
+
+    Err(anyhow!(
+        "could not find a SHA-256 checksum for {asset_name} in {checksum_file_name}",
+    ))
+}
+
+pub(crate) fn is_sha256_hash(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Returns the SHA-256 digest of the file as a lowercase hex string.
+pub(crate) fn sha256_hex(file: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut reader = File::open(file)
+        .with_context(|| format!("could not open {} to get its checksum", file.display()))?;
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn sha256_hex_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub(crate) fn verify(file: &Path, expect: &str, checksum_file_name: &str) -> Result<()> {
+    check(
+        &file.display().to_string(),
+        &sha256_hex(file)?,
+        expect,
+        checksum_file_name,
+    )
+}
+
+pub(crate) fn verify_bytes(
+    name: &str,
+    data: &[u8],
+    expect: &str,
+    checksum_file_name: &str,
+) -> Result<()> {
+    check(name, &sha256_hex_bytes(data), expect, checksum_file_name)
+}
+
+fn check(name: &str, got: &str, expect: &str, checksum_file_name: &str) -> Result<()> {
+    debug!("verifying SHA-256 checksum for {name}");
+
+    if got != expect {
+        return Err(anyhow!(
+            "the SHA-256 checksum for {name} is {got}, but {checksum_file_name} says it should be {expect}"
+        ));
+    }
+    debug!("SHA-256 checksum matches");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+    use url::Url;
+
+    // This is the SHA-256 digest of "exe".
+    const HASH: &str = "9095bdb859308b62acf04036ffd4adfe366d7f737d276eb6c46ae434f3816c9b";
+
+    #[test_case(
+        &["project.tar.gz", "project.tar.gz.sha256", "SHA256SUMS"],
+        Some("project.tar.gz.sha256") ;
+        "prefers sidecar file"
+    )]
+    #[test_case(&["project.tar.gz", "SHA256SUMS", "checksums.txt"], Some("SHA256SUMS") ; "SHA256SUMS")]
+    #[test_case(&["project.tar.gz", "checksums.txt"], Some("checksums.txt") ; "checksums.txt")]
+    #[test_case(
+        &["project.tar.gz", "project_1.2.3_checksums.txt"],
+        Some("project_1.2.3_checksums.txt") ;
+        "goreleaser checksums file"
+    )]
+    #[test_case(&["project.tar.gz", "project.zip.sha256"], None ; "no checksum file")]
+    fn checksum_asset_for(asset_names: &[&str], expect: Option<&str>) -> Result<()> {
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let res = super::checksum_asset_for(&assets[0], &assets);
+        match expect {
+            Some(expect) => assert_eq!(res?.name, expect),
+            None => assert_eq!(
+                res.unwrap_err().to_string(),
+                "could not find a checksum file for project.tar.gz (looked for project.tar.gz.sha256, SHA256SUMS, or checksums.txt)",
+            ),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_asset_for_url() -> Result<()> {
+        let asset = Asset {
+            name: "project.tar.gz".to_string(),
+            url: Url::parse("https://example.com/download/project.tar.gz")?,
+            size: None,
+        };
+        let checksum = super::checksum_asset_for_url(&asset);
+        assert_eq!(checksum.name, "project.tar.gz.sha256");
+        assert_eq!(
+            checksum.url.as_str(),
+            "https://example.com/download/project.tar.gz.sha256",
+        );
+
+        Ok(())
+    }
+
+    #[test_case(&format!("{HASH}  project.tar.gz\n"), "SHA256SUMS" ; "single line")]
+    #[test_case(
+        &format!("{}  project.zip\n{HASH}  project.tar.gz\n", "a".repeat(64)),
+        "SHA256SUMS" ;
+        "multiple lines"
+    )]
+    #[test_case(&format!("{HASH} *project.tar.gz\n"), "SHA256SUMS" ; "binary mode")]
+    #[test_case(&format!("{HASH}  ./project.tar.gz\n"), "checksums.txt" ; "leading dot slash")]
+    #[test_case(&format!("{}\n", HASH.to_uppercase()), "project.tar.gz.sha256" ; "sidecar with only a hash")]
+    fn expected_checksum(contents: &str, checksum_file_name: &str) -> Result<()> {
+        assert_eq!(
+            super::expected_checksum(contents, "project.tar.gz", checksum_file_name)?,
+            HASH,
+        );
+        Ok(())
+    }
+
+    #[test_case(&format!("{HASH}  project.zip\n"), "SHA256SUMS" ; "no matching line")]
+    #[test_case(&format!("{HASH}\n"), "SHA256SUMS" ; "hash without name in a sums file")]
+    #[test_case("not-a-hash  project.tar.gz\n", "SHA256SUMS" ; "invalid hash")]
+    fn expected_checksum_errors(contents: &str, checksum_file_name: &str) {
+        let res = super::expected_checksum(contents, "project.tar.gz", checksum_file_name);
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            format!("could not find a SHA-256 checksum for project.tar.gz in {checksum_file_name}"),
+        );
+    }
+
+    #[test]
+    fn verify_correct_checksum() -> Result<()> {
+        let td = tempdir()?;
+        let file = td.path().join("project.tar.gz");
+        fs::write(&file, "exe")?;
+
+        super::verify(&file, HASH, "SHA256SUMS")
+    }
+
+    #[test]
+    fn verify_corrupted_file() -> Result<()> {
+        let td = tempdir()?;
+        let file = td.path().join("project.tar.gz");
+        fs::write(&file, "exf")?;
+
+        let res = super::verify(&file, HASH, "SHA256SUMS");
+        assert!(res.is_err());
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.ends_with(&format!("but SHA256SUMS says it should be {HASH}")),
+            "{err}",
+        );
+
+        Ok(())
+    }
+}
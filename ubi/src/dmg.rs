@@ -0,0 +1,199 @@
+// A macOS disk image can't be read without mounting it, so we use `hdiutil`, which is part of every
+// macOS install. The image is mounted on a temp dir and detached when the `MountedDmg` is dropped,
+// which also happens when a panic unwinds past it.
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::TempDir;
+
+#[derive(Debug)]
+pub(crate) struct MountedDmg {
+    // This must be dropped after the image is detached, since a mounted volume can't be removed.
+    mount_point: TempDir,
+}
+
+impl MountedDmg {
+    pub(crate) fn attach(dmg: &Path) -> Result<Self> {
+        let mount_point = tempfile::Builder::new()
+            .prefix("ubi-dmg-")
+            .tempdir()
+            .context("could not create a temp dir to mount the dmg file on")?;
+        debug!(
+            "mounting dmg file at {} on {}",
+            dmg.display(),
+            mount_point.path().display(),
+        );
+
+        let output = Command::new("hdiutil")
+            .arg("attach")
+            .args(["-nobrowse", "-readonly", "-noautoopen", "-mountpoint"])
+            .arg(mount_point.path())
+            .arg(dmg)
+            .output()
+            .context("could not run `hdiutil attach`")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "could not mount the dmg file at {}: `hdiutil attach` failed: {}",
+                dmg.display(),
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+
+        Ok(MountedDmg { mount_point })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.mount_point.path()
+    }
+
+    // Returns every regular file in the image, shallowest first. Symlinks are skipped, since most
+    // images that contain an app have a link to `/Applications`.
+    pub(crate) fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        find_files(self.path(), &mut files)?;
+        files.sort_by(|a, b| {
+            a.components()
+                .count()
+                .cmp(&b.components().count())
+                .then_with(|| a.cmp(b))
+        });
+        Ok(files)
+    }
+
+    fn detach(&self, force: bool) -> Result<()> {
+        let mut cmd = Command::new("hdiutil");
+        cmd.arg("detach").arg(self.path());
+        if force {
+            cmd.arg("-force");
+        }
+        let output = cmd.output().context("could not run `hdiutil detach`")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "`hdiutil detach` failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MountedDmg {
+    fn drop(&mut self) {
+        debug!("detaching dmg file mounted on {}", self.path().display());
+        // A detach can fail if something still has a file open in the image, so we try once more
+        // with `-force`.
+        if let Err(e) = self.detach(false).or_else(|_| self.detach(true)) {
+            warn!(
+                "could not detach the dmg file mounted on {}: {e}",
+                self.path().display(),
+            );
+        }
+    }
+}
+
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("could not read {} in the mounted dmg file", dir.display()))?
+    {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            find_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+    use tempfile::tempdir;
+
+    // We can't make a dmg file without `hdiutil`, so the fixture is built from a directory when the
+    // test runs.
+    fn make_dmg(dir: &Path) -> Result<PathBuf> {
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("bin"))?;
+        fs::write(src.join("bin").join("project"), "#!/bin/sh\necho project\n")?;
+        fs::write(src.join("README.md"), "# project\n")?;
+
+        let dmg = dir.join("project.dmg");
+        let status = Command::new("hdiutil")
+            .args([
+                "create",
+                "-quiet",
+                "-fs",
+                "HFS+",
+                "-volname",
+                "project",
+                "-srcfolder",
+            ])
+            .arg(&src)
+            .arg(&dmg)
+            .status()?;
+        assert!(status.success(), "hdiutil create succeeded");
+        Ok(dmg)
+    }
+
+    #[test]
+    fn attach_and_detach() -> Result<()> {
+        let td = tempdir()?;
+        let dmg = make_dmg(td.path())?;
+
+        let mounted = MountedDmg::attach(&dmg)?;
+        let mount_point = mounted.path().to_path_buf();
+        let files = mounted
+            .files()?
+            .into_iter()
+            .map(|f| f.strip_prefix(&mount_point).map(Path::to_path_buf))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(files.contains(&PathBuf::from("README.md")));
+        assert!(files.contains(&PathBuf::from("bin/project")));
+
+        drop(mounted);
+        assert!(!mount_point.exists(), "the dmg file was detached");
+
+        Ok(())
+    }
+
+    #[test]
+    fn detach_after_panic() -> Result<()> {
+        let td = tempdir()?;
+        let dmg = make_dmg(td.path())?;
+
+        let mut mount_point = None;
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let mounted = MountedDmg::attach(&dmg).unwrap();
+            mount_point = Some(mounted.path().to_path_buf());
+            panic!("a panic while the dmg file is mounted");
+        }));
+        assert!(res.is_err());
+
+        let mount_point = mount_point.expect("the dmg file was mounted");
+        assert!(!mount_point.exists(), "the dmg file was detached");
+
+        Ok(())
+    }
+
+    #[test]
+    fn attach_invalid_file() -> Result<()> {
+        let td = tempdir()?;
+        let dmg = td.path().join("bogus.dmg");
+        fs::write(&dmg, "not a disk image")?;
+
+        let err = MountedDmg::attach(&dmg).unwrap_err();
+        assert!(err.to_string().starts_with(&format!(
+            "could not mount the dmg file at {}",
+            dmg.display()
+        )));
+
+        Ok(())
+    }
+}
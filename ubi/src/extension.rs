@@ -1,6 +1,6 @@
 use crate::arch::ALL_ARCHES_RE;
 use crate::os::ALL_OSES_RE;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use lazy_regex::regex;
 use log::debug;
@@ -8,6 +8,8 @@ use platforms::{Platform, OS};
 use regex::Regex;
 use std::{
     ffi::OsStr,
+    fs::File,
+    io::Read,
     path::{Path, PathBuf},
     sync::LazyLock,
 };
@@ -24,22 +26,32 @@ pub(crate) enum ExtensionError {
 pub(crate) enum Extension {
     AppImage,
     Bat,
+    Br,
     Bz,
     Bz2,
+    Dmg,
     Exe,
     Gz,
     Jar,
+    Lzma,
     Pyz,
+    SevenZ,
     Tar,
+    TarBr,
     TarBz,
     TarBz2,
     TarGz,
+    TarLzma,
     TarXz,
+    TarZst,
     Tbz,
+    Tbz2,
     Tgz,
     Txz,
+    Tzst,
     Xz,
     Zip,
+    Zst,
 }
 
 impl Extension {
@@ -47,22 +59,32 @@ impl Extension {
         match self {
             Extension::AppImage => ".AppImage",
             Extension::Bat => ".bat",
+            Extension::Br => ".br",
             Extension::Bz => ".bz",
             Extension::Bz2 => ".bz2",
+            Extension::Dmg => ".dmg",
             Extension::Exe => ".exe",
             Extension::Gz => ".gz",
             Extension::Jar => ".jar",
+            Extension::Lzma => ".lzma",
             Extension::Pyz => ".pyz",
+            Extension::SevenZ => ".7z",
             Extension::Tar => ".tar",
+            Extension::TarBr => ".tar.br",
             Extension::TarBz => ".tar.bz",
             Extension::TarBz2 => ".tar.bz2",
             Extension::TarGz => ".tar.gz",
+            Extension::TarLzma => ".tar.lzma",
             Extension::TarXz => ".tar.xz",
+            Extension::TarZst => ".tar.zst",
             Extension::Tbz => ".tbz",
+            Extension::Tbz2 => ".tbz2",
             Extension::Tgz => ".tgz",
             Extension::Txz => ".txz",
+            Extension::Tzst => ".tzst",
             Extension::Xz => ".xz",
             Extension::Zip => ".zip",
+            Extension::Zst => ".zst",
         }
     }
 
@@ -74,21 +96,31 @@ impl Extension {
         match self {
             Extension::AppImage
             | Extension::Bat
+            | Extension::Br
             | Extension::Bz
             | Extension::Bz2
             | Extension::Exe
             | Extension::Gz
             | Extension::Jar
+            | Extension::Lzma
             | Extension::Pyz
-            | Extension::Xz => false,
-            Extension::Tar
+            | Extension::Xz
+            | Extension::Zst => false,
+            Extension::Dmg
+            | Extension::SevenZ
+            | Extension::Tar
+            | Extension::TarBr
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLzma
             | Extension::TarXz
+            | Extension::TarZst
             | Extension::Tbz
+            | Extension::Tbz2
             | Extension::Tgz
             | Extension::Txz
+            | Extension::Tzst
             | Extension::Zip => true,
         }
     }
@@ -100,19 +132,29 @@ impl Extension {
             | Extension::Exe
             | Extension::Jar
             | Extension::Pyz => true,
-            Extension::Bz
+            Extension::Br
+            | Extension::Bz
             | Extension::Gz
             | Extension::Bz2
+            | Extension::Dmg
+            | Extension::Lzma
+            | Extension::SevenZ
             | Extension::Tar
+            | Extension::TarBr
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLzma
             | Extension::TarXz
+            | Extension::TarZst
             | Extension::Tbz
+            | Extension::Tbz2
             | Extension::Tgz
             | Extension::Txz
+            | Extension::Tzst
             | Extension::Xz
-            | Extension::Zip => false,
+            | Extension::Zip
+            | Extension::Zst => false,
         }
     }
 
@@ -120,6 +162,7 @@ impl Extension {
         match self {
             Extension::AppImage => platform.target_os == OS::Linux,
             Extension::Bat | Extension::Exe => platform.target_os == OS::Windows,
+            Extension::Dmg => platform.target_os == OS::MacOS,
             _ => true,
         }
     }
@@ -163,8 +206,47 @@ impl Extension {
         }
         .into())
     }
+
+    // This is used as a fallback for files without an extension. We only look for archive and
+    // compression formats here, since a file with no extension that isn't one of those is treated
+    // as an executable. Brotli has no magic bytes, so a brotli-compressed file is only recognized
+    // by its extension.
+    pub(crate) fn from_magic_bytes(path: &Path) -> Result<Option<Extension>> {
+        let mut buf = [0u8; 6];
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open file at {}", path.display()))?;
+        let n = file.read(&mut buf)?;
+
+        if n >= SEVEN_Z_MAGIC.len() && buf[..SEVEN_Z_MAGIC.len()] == SEVEN_Z_MAGIC {
+            debug!("found 7z magic bytes in {}", path.display());
+            return Ok(Some(Extension::SevenZ));
+        }
+        if n >= ZSTD_MAGIC.len() && buf[..ZSTD_MAGIC.len()] == ZSTD_MAGIC {
+            debug!("found zstd magic bytes in {}", path.display());
+            return Ok(Some(Extension::Zst));
+        }
+        if n >= BZIP2_MAGIC.len() && buf[..BZIP2_MAGIC.len()] == BZIP2_MAGIC {
+            debug!("found bzip2 magic bytes in {}", path.display());
+            return Ok(Some(Extension::Bz2));
+        }
+        if n >= LZMA_MAGIC.len() && buf[..LZMA_MAGIC.len()] == LZMA_MAGIC {
+            debug!("found lzma magic bytes in {}", path.display());
+            return Ok(Some(Extension::Lzma));
+        }
+
+        Ok(None)
+    }
 }
 
+const SEVEN_Z_MAGIC: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const BZIP2_MAGIC: [u8; 3] = [b'B', b'Z', b'h'];
+// The legacy `.lzma` format has no real magic bytes. It starts with a properties byte, which is
+// `0x5D` for the default settings that every common tool uses, followed by the dictionary size as a
+// little-endian `u32`. The default dictionary sizes are all multiples of 64 KiB, so the low two
+// bytes of the size are zero.
+const LZMA_MAGIC: [u8; 3] = [0x5D, 0x00, 0x00];
+
 fn extension_is_part_of_version(path: &Path, ext_str: &OsStr) -> bool {
     let ext_str = ext_str.to_string_lossy().to_string();
 
@@ -210,19 +292,29 @@ mod test {
     use test_log::test;
 
     #[test_case("foo.AppImage", Ok(Some(Extension::AppImage)))]
+    #[test_case("foo.br", Ok(Some(Extension::Br)))]
     #[test_case("foo.bz", Ok(Some(Extension::Bz)))]
     #[test_case("foo.bz2", Ok(Some(Extension::Bz2)))]
     #[test_case("foo.exe", Ok(Some(Extension::Exe)))]
     #[test_case("foo.gz", Ok(Some(Extension::Gz)))]
     #[test_case("foo.jar", Ok(Some(Extension::Jar)))]
+    #[test_case("foo.lzma", Ok(Some(Extension::Lzma)))]
     #[test_case("foo.pyz", Ok(Some(Extension::Pyz)))]
+    #[test_case("foo.7z", Ok(Some(Extension::SevenZ)))]
     #[test_case("foo.tar", Ok(Some(Extension::Tar)))]
+    #[test_case("foo.tar.br", Ok(Some(Extension::TarBr)))]
     #[test_case("foo.tar.bz", Ok(Some(Extension::TarBz)))]
     #[test_case("foo.tar.bz2", Ok(Some(Extension::TarBz2)))]
     #[test_case("foo.tar.gz", Ok(Some(Extension::TarGz)))]
+    #[test_case("foo.tar.lzma", Ok(Some(Extension::TarLzma)))]
     #[test_case("foo.tar.xz", Ok(Some(Extension::TarXz)))]
+    #[test_case("foo.tar.zst", Ok(Some(Extension::TarZst)))]
+    #[test_case("foo.tbz", Ok(Some(Extension::Tbz)))]
+    #[test_case("foo.tbz2", Ok(Some(Extension::Tbz2)))]
+    #[test_case("foo.tzst", Ok(Some(Extension::Tzst)))]
     #[test_case("foo.xz", Ok(Some(Extension::Xz)))]
     #[test_case("foo.zip", Ok(Some(Extension::Zip)))]
+    #[test_case("foo.zst", Ok(Some(Extension::Zst)))]
     #[test_case("foo", Ok(None))]
     #[test_case("foo_3.2.1_linux_amd64", Ok(None))]
     #[test_case("foo_3.9.1.linux.amd64", Ok(None))]
@@ -233,17 +325,40 @@ mod test {
         crate::test_case::init_logging();
 
         let ext = Extension::from_path(Path::new(path));
-        if expect.is_ok() {
-            assert!(ext.is_ok());
-            assert_eq!(ext.unwrap(), expect.unwrap());
-        } else {
-            assert_eq!(
-                ext.unwrap_err().to_string(),
-                expect.unwrap_err().to_string()
-            );
+        match expect {
+            Ok(expect) => {
+                assert!(ext.is_ok());
+                assert_eq!(ext.unwrap(), expect);
+            }
+            Err(expect) => {
+                assert_eq!(ext.unwrap_err().to_string(), expect.to_string());
+            }
         }
     }
 
+    #[test_case("test-data/project", None)]
+    #[test_case("test-data/project.tar", None)]
+    #[test_case("test-data/project.7z", Some(Extension::SevenZ))]
+    #[test_case("test-data/project-zst-without-extension", Some(Extension::Zst))]
+    #[test_case("test-data/project.tar.bz2", Some(Extension::Bz2))]
+    #[test_case(
+        "test-data/project-tarball-bz2-without-extension",
+        Some(Extension::Bz2)
+    )]
+    #[test_case("test-data/project.tar.lzma", Some(Extension::Lzma))]
+    #[test_case(
+        "test-data/project-tarball-lzma-without-extension",
+        Some(Extension::Lzma)
+    )]
+    #[test_case("test-data/project.tar.xz", None)]
+    fn from_magic_bytes(path: &str, expect: Option<Extension>) -> Result<()> {
+        crate::test_case::init_logging();
+
+        assert_eq!(Extension::from_magic_bytes(Path::new(path))?, expect);
+
+        Ok(())
+    }
+
     #[test]
     fn matches_platform() -> Result<()> {
         let freebsd = Platform::find("x86_64-unknown-freebsd").unwrap().clone();
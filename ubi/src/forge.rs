@@ -1,45 +1,131 @@
-use std::env;
+use std::{env, path::Path};
 
-use crate::{github::GitHub, gitlab::GitLab, ubi::Asset};
-use anyhow::Result;
+use crate::{
+    azure_devops::AzureDevOps,
+    bitbucket::Bitbucket,
+    gitea::Gitea,
+    github::GitHub,
+    gitlab::{self, GitLab, GitLabToken, CI_JOB_TOKEN_ENV_VAR},
+    keyring::{self, Keyring},
+    netrc,
+    oci::{Oci, GHCR_DOMAIN},
+    project::ProjectRef,
+    retry::RetryPolicy,
+    sourcehut::SourceHut,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use log::debug;
 use reqwest::{
-    header::{HeaderValue, ACCEPT},
+    header::{HeaderValue, ACCEPT, AUTHORIZATION},
     Client, RequestBuilder, Response,
 };
 // It'd be nice to use clap::ValueEnum here, but then we'd need to add clap as a dependency for the
 // library code, which would be annoying for downstream users who just want to use the library.
-use strum::{AsRefStr, EnumString, VariantNames};
+use strum::{AsRefStr, EnumIter, EnumString, VariantNames};
 use url::Url;
 
-#[derive(AsRefStr, Clone, Debug, Default, EnumString, PartialEq, Eq, VariantNames)]
+#[derive(AsRefStr, Clone, Debug, Default, EnumIter, EnumString, PartialEq, Eq, VariantNames)]
 #[allow(clippy::module_name_repetitions)]
 pub enum ForgeType {
+    #[strum(serialize = "azure-devops")]
+    AzureDevOps,
+    #[strum(serialize = "bitbucket")]
+    Bitbucket,
+    #[strum(serialize = "gitea")]
+    Gitea,
     #[strum(serialize = "github")]
     #[default]
     GitHub,
     #[strum(serialize = "gitlab")]
     GitLab,
+    #[strum(serialize = "oci")]
+    Oci,
+    #[strum(serialize = "sourcehut")]
+    SourceHut,
+}
+
+// These are the assets for one release, along with the release's tag. If no tag was set on the
+// forge, this tells us which tag the latest release has, but it is `None` if the forge's release
+// info doesn't include it.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ReleaseAssets {
+    pub(crate) tag: Option<String>,
+    pub(crate) assets: Vec<Asset>,
 }
 
+/// `Release` is one of a project's releases. These are returned by
+/// [`Ubi::list_releases`](crate::Ubi::list_releases).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    /// The release's tag, like `v1.2.3`.
+    pub tag: String,
+    /// Whether this is a prerelease. GitLab has no prerelease flag, so for GitLab this is set for
+    /// releases with a release date in the future.
+    pub prerelease: bool,
+    /// Whether this is a draft release. GitLab does not have draft releases.
+    pub draft: bool,
+    /// When the release was published, as a timestamp like `2025-03-18T21:03:02Z`. This is `None`
+    /// for a draft release that has not been published.
+    pub published_at: Option<String>,
+}
+
+// Forges are always used as a `Box<dyn Forge>`, so this trait must stay object safe. `async_trait`
+// makes that possible for the async methods.
 #[async_trait]
 pub(crate) trait Forge: std::fmt::Debug {
-    async fn fetch_assets(&self, client: &Client) -> Result<Vec<Asset>>;
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets>;
+
+    // Returns all of the project's releases, newest first. This is also used to find a release
+    // matching a version requirement, or the newest prerelease.
+    async fn list_releases(&self, _client: &Client) -> Result<Vec<Release>> {
+        Err(anyhow!("listing releases is not supported for this forge"))
+    }
+
+    // Sets the tag used by later calls to `fetch_assets`.
+    fn set_tag(&mut self, tag: String);
+    // Returns the tag used by `fetch_assets`, if one was set. If this is `None`, then the latest
+    // release is used.
+    fn tag(&self) -> Option<&str>;
 
     fn release_info_url(&self) -> Url;
+    fn retry_policy(&self) -> &RetryPolicy;
     fn maybe_add_token_header(&self, req_builder: RequestBuilder) -> Result<RequestBuilder>;
+    // Returns true if the forge has a token that it sends in the `Authorization` header, which is
+    // where the HTTP Basic auth credentials for `UbiBuilder::basic_auth` go too.
+    fn sends_token_in_authorization_header(&self, client: &Client) -> Result<bool> {
+        let req = self
+            .maybe_add_token_header(client.get(self.release_info_url()))?
+            .build()?;
+        Ok(req.headers().contains_key(AUTHORIZATION))
+    }
+    // Returns an error explaining that the forge's API rate limit was hit, if `resp` says so. This
+    // is checked before any other error status, so the user gets a more helpful message than just
+    // the status code.
+    fn rate_limit_error(&self, _resp: &Response) -> Option<anyhow::Error> {
+        None
+    }
 
     async fn make_release_info_request(&self, client: &Client) -> Result<Response> {
         let url = self.release_info_url();
         debug!("Getting release info from {url}");
+        self.make_api_request(client, url).await
+    }
 
+    async fn make_api_request(&self, client: &Client, url: Url) -> Result<Response> {
         let mut req_builder = client
             .get(url)
             .header(ACCEPT, HeaderValue::from_str("application/json")?);
         req_builder = self.maybe_add_token_header(req_builder)?;
-        let resp = client.execute(req_builder.build()?).await?;
+        let resp = self
+            .retry_policy()
+            .execute(client, req_builder.build()?)
+            .await?;
 
+        if let Some(e) = self.rate_limit_error(&resp) {
+            return Err(e);
+        }
         if let Err(e) = resp.error_for_status_ref() {
             return Err(anyhow::Error::new(e));
         }
@@ -48,38 +134,76 @@ pub(crate) trait Forge: std::fmt::Debug {
     }
 }
 
+const AZURE_DEVOPS_DOMAIN: &str = "dev.azure.com";
+const BITBUCKET_DOMAIN: &str = "bitbucket.org";
+const GITEA_DOMAIN: &str = "gitea.com";
 const GITHUB_DOMAIN: &str = "github.com";
 const GITLAB_DOMAIN: &str = "gitlab.com";
+const SOURCEHUT_DOMAIN: &str = "git.sr.ht";
+
+const AZURE_DEVOPS_API_BASE: &str = "https://dev.azure.com";
+// Codeberg runs Forgejo, which has the same API as Gitea.
+const CODEBERG_DOMAIN: &str = "codeberg.org";
 
+// These are checked before `GITEA_TOKEN`, since someone using Codeberg or another Forgejo instance
+// expects a token env var named for it to work.
+const CODEBERG_TOKEN_ENV_VAR: &str = "CODEBERG_TOKEN";
+const FORGEJO_TOKEN_ENV_VAR: &str = "FORGEJO_TOKEN";
+
+const BITBUCKET_API_BASE: &str = "https://api.bitbucket.org/2.0";
+const GITEA_API_BASE: &str = "https://gitea.com/api/v1";
 const GITHUB_API_BASE: &str = "https://api.github.com";
 const GITLAB_API_BASE: &str = "https://gitlab.com/api/v4";
+const SOURCEHUT_API_BASE: &str = "https://git.sr.ht/api";
 
 impl ForgeType {
+    // A URL on a host we don't recognize is treated as GitHub, since that's the default forge.
     pub(crate) fn from_url(url: &Url) -> ForgeType {
-        if url.domain().unwrap().contains(GITLAB_DOMAIN) {
-            ForgeType::GitLab
+        url.host_str()
+            .and_then(Self::from_known_host)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn from_known_host(host: &str) -> Option<ForgeType> {
+        if host.contains(AZURE_DEVOPS_DOMAIN) {
+            Some(ForgeType::AzureDevOps)
+        } else if host.contains(BITBUCKET_DOMAIN) {
+            Some(ForgeType::Bitbucket)
+        } else if host.contains(GITEA_DOMAIN) || host.contains(CODEBERG_DOMAIN) {
+            Some(ForgeType::Gitea)
+        } else if host.contains(GITHUB_DOMAIN) {
+            Some(ForgeType::GitHub)
+        } else if host.contains(GITLAB_DOMAIN) {
+            Some(ForgeType::GitLab)
+        } else if host.contains(GHCR_DOMAIN) {
+            Some(ForgeType::Oci)
+        } else if host.contains(SOURCEHUT_DOMAIN) {
+            Some(ForgeType::SourceHut)
         } else {
-            ForgeType::default()
+            None
         }
     }
 
     pub(crate) fn make_forge_impl(
         &self,
         project_name: String,
-        tag: Option<String>,
-        api_base: Option<String>,
-        mut token: Option<String>,
+        opts: &ForgeOptions<'_>,
     ) -> Result<Box<dyn Forge + Send + Sync>> {
-        let api_base_url = if let Some(api_base) = api_base {
-            Url::parse(&api_base)?
+        let api_base_url = if let Some(api_base) = &opts.api_base {
+            Url::parse(api_base)?
         } else {
             self.api_base_url()
         };
+        let tag = opts.tag.clone();
+        let mut token = opts.token.clone();
+        let retry_policy = opts.retry_policy.clone().for_api_base_url(&api_base_url);
 
+        let mut token_env_var = None;
         if token.is_none() {
-            for name in self.env_var_names() {
+            for name in self.token_env_var_names(&api_base_url) {
                 token = env::var(name).ok();
                 if token.is_some() {
+                    token_env_var = Some(name);
                     debug!(
                         "Using {} token from the {name} environment variable.",
                         self.forge_name()
@@ -88,38 +212,598 @@ impl ForgeType {
                 }
             }
         }
+        // The env vars take priority, so a token can always be overridden for one run.
+        if let (None, Some(keyring)) = (&token, opts.keyring) {
+            token = keyring.get(&keyring::service_name(self))?;
+            if token.is_some() {
+                debug!("Using {} token from the OS keyring.", self.forge_name());
+            }
+        }
+        let mut netrc_login = None;
+        if let (None, Some(netrc_file)) = (&token, opts.netrc_file) {
+            if let Some(creds) = netrc::credentials_for(netrc_file, &api_base_url)? {
+                debug!(
+                    "Using {} token from the netrc file at {}.",
+                    self.forge_name(),
+                    netrc_file.display(),
+                );
+                token = Some(creds.password);
+                netrc_login = Some(creds.login);
+            }
+        }
 
         Ok(match self {
-            ForgeType::GitHub => Box::new(GitHub::new(project_name, tag, api_base_url, token)),
-            ForgeType::GitLab => Box::new(GitLab::new(project_name, tag, api_base_url, token)),
+            ForgeType::AzureDevOps => Box::new(AzureDevOps::new(
+                project_name,
+                tag,
+                api_base_url,
+                token,
+                retry_policy,
+            )),
+            ForgeType::Bitbucket => {
+                // The login in a netrc entry is the username for its app password.
+                let username = env::var("BITBUCKET_USERNAME").ok().or(netrc_login);
+                if token.is_some() && username.is_none() {
+                    return Err(anyhow!(
+                        "You must set the BITBUCKET_USERNAME env var when using a Bitbucket app password"
+                    ));
+                }
+                Box::new(Bitbucket::new(
+                    project_name,
+                    tag,
+                    api_base_url,
+                    username,
+                    token,
+                    retry_policy,
+                ))
+            }
+            ForgeType::Gitea => Box::new(Gitea::new(
+                project_name,
+                tag,
+                api_base_url,
+                token,
+                retry_policy,
+            )),
+            ForgeType::GitHub => Box::new(GitHub::new(
+                project_name,
+                tag,
+                api_base_url,
+                token,
+                retry_policy,
+            )),
+            ForgeType::GitLab => Box::new(GitLab::new(
+                project_name,
+                tag,
+                api_base_url,
+                token.map(|t| {
+                    if token_env_var == Some(CI_JOB_TOKEN_ENV_VAR) {
+                        GitLabToken::Job(t)
+                    } else {
+                        GitLabToken::Access(t)
+                    }
+                }),
+                retry_policy,
+            )),
+            ForgeType::Oci => Box::new(Oci::new(
+                project_name,
+                tag,
+                api_base_url,
+                token,
+                retry_policy,
+            )),
+            ForgeType::SourceHut => Box::new(SourceHut::new(
+                project_name,
+                tag,
+                api_base_url,
+                token,
+                retry_policy,
+            )),
         })
     }
 
     pub(crate) fn url_base(&self) -> Url {
         match self {
+            ForgeType::AzureDevOps => {
+                Url::parse(&format!("https://{AZURE_DEVOPS_DOMAIN}")).unwrap()
+            }
+            ForgeType::Bitbucket => Url::parse(&format!("https://{BITBUCKET_DOMAIN}")).unwrap(),
+            ForgeType::Gitea => Url::parse(&format!("https://{GITEA_DOMAIN}")).unwrap(),
             ForgeType::GitHub => Url::parse(&format!("https://{GITHUB_DOMAIN}")).unwrap(),
             ForgeType::GitLab => Url::parse(&format!("https://{GITLAB_DOMAIN}")).unwrap(),
+            ForgeType::Oci => Url::parse(&format!("https://{GHCR_DOMAIN}")).unwrap(),
+            ForgeType::SourceHut => Url::parse(&format!("https://{SOURCEHUT_DOMAIN}")).unwrap(),
+        }
+    }
+
+    // Every Gitea or Forgejo instance serves its API from the same host as its web pages, so we can
+    // find the API for a project URL on any host. For the other forges, the API is on a different
+    // host, or its location depends on how the instance is set up, so we use the default.
+    pub(crate) fn api_base_url_for_host(&self, host: &str) -> Url {
+        match self {
+            ForgeType::Gitea => Url::parse(&format!("https://{host}/api/v1")).unwrap(),
+            _ => self.api_base_url(),
         }
     }
 
     pub(crate) fn api_base_url(&self) -> Url {
         match self {
+            ForgeType::AzureDevOps => Url::parse(AZURE_DEVOPS_API_BASE).unwrap(),
+            ForgeType::Bitbucket => Url::parse(BITBUCKET_API_BASE).unwrap(),
+            ForgeType::Gitea => Url::parse(GITEA_API_BASE).unwrap(),
             ForgeType::GitHub => Url::parse(GITHUB_API_BASE).unwrap(),
             ForgeType::GitLab => Url::parse(GITLAB_API_BASE).unwrap(),
+            ForgeType::Oci => Url::parse(&format!("https://{GHCR_DOMAIN}")).unwrap(),
+            ForgeType::SourceHut => Url::parse(SOURCEHUT_API_BASE).unwrap(),
         }
     }
 
     pub(crate) fn env_var_names(&self) -> &'static [&'static str] {
         match self {
+            ForgeType::AzureDevOps => &["AZURE_DEVOPS_TOKEN"],
+            ForgeType::Bitbucket => &["BITBUCKET_APP_PASSWORD"],
+            ForgeType::Gitea => &[CODEBERG_TOKEN_ENV_VAR, FORGEJO_TOKEN_ENV_VAR, "GITEA_TOKEN"],
             ForgeType::GitHub => &["GITHUB_TOKEN"],
-            ForgeType::GitLab => &["CI_TOKEN", "GITLAB_TOKEN"],
+            ForgeType::GitLab => &[CI_JOB_TOKEN_ENV_VAR, "GITLAB_TOKEN"],
+            ForgeType::Oci => &["GITHUB_TOKEN"],
+            ForgeType::SourceHut => &["SRHT_TOKEN"],
+        }
+    }
+
+    // Returns the env vars to look for a token in, in order, for the forge instance at
+    // `api_base_url`. For Gitea, a host-specific env var is checked before `GITEA_TOKEN`. This is
+    // `CODEBERG_TOKEN` for Codeberg and `FORGEJO_TOKEN` for any other host.
+    pub(crate) fn token_env_var_names(&self, api_base_url: &Url) -> Vec<&'static str> {
+        let is_codeberg = api_base_url.host_str() == Some(CODEBERG_DOMAIN);
+        self.env_var_names()
+            .iter()
+            .copied()
+            .filter(|name| match *name {
+                CODEBERG_TOKEN_ENV_VAR => is_codeberg,
+                FORGEJO_TOKEN_ENV_VAR => !is_codeberg,
+                _ => true,
+            })
+            .collect()
+    }
+
+    // These env vars set the URL of a self-hosted instance of the forge, for teams that always use
+    // the same one. For GitHub, this is the API URL, since a GitHub Enterprise Server instance's API
+    // URL isn't always its URL plus a path. Forgejo is listed for Gitea, since the Gitea forge
+    // supports Forgejo instances too.
+    pub(crate) fn url_env_var_names(&self) -> &'static [&'static str] {
+        match self {
+            ForgeType::Gitea => &["UBI_GITEA_URL", "UBI_FORGEJO_URL"],
+            ForgeType::GitHub => &["UBI_GITHUB_API_URL"],
+            ForgeType::GitLab => &["UBI_GITLAB_URL"],
+            _ => &[],
         }
     }
 
+    // Returns the API base URL for the instance set in the first of `url_env_var_names` that is
+    // set, if any.
+    pub(crate) fn api_base_url_from_env(
+        &self,
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Url>> {
+        let Some((name, value)) = self
+            .url_env_var_names()
+            .iter()
+            .find_map(|name| get_env(name).filter(|v| !v.is_empty()).map(|v| (name, v)))
+        else {
+            return Ok(None);
+        };
+
+        debug!(
+            "using the {} URL from the {name} env var",
+            self.forge_name()
+        );
+        let url = Url::parse(&value)
+            .with_context(|| format!("the {name} env var, `{value}`, is not a valid URL"))?;
+        let api_base = match self {
+            ForgeType::Gitea => {
+                let mut url = url;
+                url.path_segments_mut()
+                    .map_err(|()| anyhow!("the {name} env var, `{value}`, is not a valid URL"))?
+                    .pop_if_empty()
+                    .extend(["api", "v1"]);
+                url
+            }
+            ForgeType::GitLab => gitlab::api_base_url_for_instance(&url),
+            _ => url,
+        };
+        Ok(Some(api_base))
+    }
+
     pub(crate) fn forge_name(&self) -> &'static str {
         match self {
+            ForgeType::AzureDevOps => "Azure DevOps",
+            ForgeType::Bitbucket => "Bitbucket",
+            ForgeType::Gitea => "Gitea",
             ForgeType::GitHub => "GitHub",
             ForgeType::GitLab => "GitLab",
+            ForgeType::Oci => "OCI registry",
+            ForgeType::SourceHut => "SourceHut",
         }
     }
 }
+
+// These are all of the settings for a forge except the project. A setting that is `None` is looked up
+// from the environment, or from the project, by `forge_for` and `ForgeType::make_forge_impl`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ForgeOptions<'a> {
+    pub(crate) tag: Option<String>,
+    pub(crate) api_base: Option<String>,
+    pub(crate) token: Option<String>,
+    pub(crate) keyring: Option<&'a dyn Keyring>,
+    pub(crate) netrc_file: Option<&'a Path>,
+    pub(crate) retry_policy: RetryPolicy,
+}
+
+// Makes the forge for a project. The project's forge type was picked from its URL's host by
+// `parse_project`, and that host also tells us where a self-hosted Gitea instance's API is. A tag or
+// API base URL in the options takes priority over the project's.
+pub(crate) fn forge_for(
+    project: &ProjectRef,
+    opts: &ForgeOptions<'_>,
+) -> Result<Box<dyn Forge + Send + Sync>> {
+    let mut opts = opts.clone();
+    if opts.tag.is_none() {
+        opts.tag.clone_from(&project.tag);
+    }
+    if let (None, Some(host)) = (&opts.api_base, &project.host) {
+        opts.api_base = Some(project.forge.api_base_url_for_host(host).to_string());
+    }
+    project.forge.make_forge_impl(project.project_name(), &opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::tests::MockKeyring;
+    use mockito::{Matcher, Server};
+    use serial_test::serial;
+    use std::fs;
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case(ForgeType::GitHub, &[("UBI_GITHUB_API_URL", "https://ghe.example.com/api/v3")], Some("https://ghe.example.com/api/v3") ; "GitHub")]
+    #[test_case(ForgeType::GitLab, &[("UBI_GITLAB_URL", "https://gitlab.example.com/")], Some("https://gitlab.example.com/api/v4") ; "GitLab")]
+    #[test_case(ForgeType::Gitea, &[("UBI_FORGEJO_URL", "https://forgejo.example.com")], Some("https://forgejo.example.com/api/v1") ; "Forgejo")]
+    #[test_case(
+        ForgeType::Gitea,
+        &[("UBI_GITEA_URL", "https://gitea.example.com"), ("UBI_FORGEJO_URL", "https://forgejo.example.com")],
+        Some("https://gitea.example.com/api/v1") ;
+        "Gitea before Forgejo"
+    )]
+    #[test_case(ForgeType::GitHub, &[("UBI_GITLAB_URL", "https://gitlab.example.com")], None ; "other forge's env var")]
+    #[test_case(ForgeType::GitHub, &[("UBI_GITHUB_API_URL", "")], None ; "empty env var")]
+    #[test_case(ForgeType::SourceHut, &[], None ; "forge without an env var")]
+    fn api_base_url_from_env(
+        forge: ForgeType,
+        env: &[(&str, &str)],
+        expect: Option<&str>,
+    ) -> Result<()> {
+        let get_env = |name: &str| {
+            env.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| (*v).to_string())
+        };
+        assert_eq!(
+            forge.api_base_url_from_env(get_env)?,
+            expect.map(Url::parse).transpose()?,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn api_base_url_from_env_with_invalid_url() {
+        let err = ForgeType::GitHub
+            .api_base_url_from_env(|_| Some("not a url".to_string()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the UBI_GITHUB_API_URL env var, `not a url`, is not a valid URL",
+        );
+    }
+
+    #[test_case("https://github.com/owner/repo", ForgeType::GitHub ; "GitHub")]
+    #[test_case("https://gitlab.com/owner/repo", ForgeType::GitLab ; "GitLab")]
+    #[test_case("https://gitea.com/owner/repo", ForgeType::Gitea ; "Gitea")]
+    #[test_case("https://codeberg.org/owner/repo", ForgeType::Gitea ; "Codeberg")]
+    #[test_case("https://dev.azure.com/org/project/_git/repo", ForgeType::AzureDevOps ; "Azure DevOps")]
+    #[test_case("https://ghe.example.com/owner/repo", ForgeType::GitHub ; "unknown host")]
+    #[test_case("http://127.0.0.1:1234/owner/repo", ForgeType::GitHub ; "IP address")]
+    fn from_url(url: &str, expect: ForgeType) -> Result<()> {
+        assert_eq!(ForgeType::from_url(&Url::parse(url)?), expect);
+        Ok(())
+    }
+
+    #[test_case(
+        "https://github.com/houseabsolute/ubi",
+        "GitHub",
+        "https://api.github.com/repos/houseabsolute/ubi/releases/latest" ;
+        "GitHub"
+    )]
+    #[test_case(
+        "https://gitlab.com/houseabsolute/ubi",
+        "GitLab",
+        "https://gitlab.com/api/v4/projects/houseabsolute%2Fubi/releases/permalink/latest" ;
+        "GitLab"
+    )]
+    #[test_case(
+        "https://codeberg.org/houseabsolute/ubi",
+        "Gitea",
+        "https://codeberg.org/api/v1/repos/houseabsolute/ubi/releases/latest" ;
+        "Forgejo on Codeberg"
+    )]
+    #[test_case(
+        "https://codeberg.org/houseabsolute/ubi/releases/tag/v1.0.0",
+        "Gitea",
+        "https://codeberg.org/api/v1/repos/houseabsolute/ubi/releases/tags/v1.0.0" ;
+        "Forgejo release URL"
+    )]
+    fn forge_for(project: &str, expect_forge: &str, expect_url: &str) -> Result<()> {
+        let project = crate::parse_project(project)?;
+        let forge = super::forge_for(&project, &ForgeOptions::default())?;
+        // The forge structs have no common way to say what they are, but their debug output
+        // starts with the struct name.
+        assert!(
+            format!("{forge:?}").starts_with(&format!("{expect_forge} {{")),
+            "{forge:?}",
+        );
+        assert_eq!(forge.release_info_url().as_str(), expect_url);
+        Ok(())
+    }
+
+    #[test]
+    fn forge_for_with_options() -> Result<()> {
+        let project =
+            crate::parse_project("https://codeberg.org/houseabsolute/ubi/releases/tag/v1.0.0")?;
+        let forge = super::forge_for(
+            &project,
+            &ForgeOptions {
+                tag: Some("v2.0.0".to_string()),
+                api_base: Some("https://forgejo.example.com/api/v1".to_string()),
+                ..ForgeOptions::default()
+            },
+        )?;
+        assert_eq!(forge.tag(), Some("v2.0.0"));
+        assert_eq!(
+            forge.release_info_url().as_str(),
+            "https://forgejo.example.com/api/v1/repos/houseabsolute/ubi/releases/tags/v2.0.0",
+        );
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn token_from_keyring() -> Result<()> {
+        github_token_source(
+            None,
+            None,
+            Some("keyring-token"),
+            None,
+            Some("keyring-token"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn env_var_token_takes_priority_over_keyring() -> Result<()> {
+        github_token_source(
+            None,
+            Some("env-token"),
+            Some("keyring-token"),
+            None,
+            Some("env-token"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn explicit_token_takes_priority_over_keyring() -> Result<()> {
+        github_token_source(
+            Some("explicit-token"),
+            None,
+            Some("keyring-token"),
+            None,
+            Some("explicit-token"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn no_token_in_keyring() -> Result<()> {
+        github_token_source(None, None, None, None, None).await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn token_from_netrc() -> Result<()> {
+        github_token_source(None, None, None, Some("netrc-token"), Some("netrc-token")).await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn env_var_token_takes_priority_over_netrc() -> Result<()> {
+        github_token_source(
+            None,
+            Some("env-token"),
+            None,
+            Some("netrc-token"),
+            Some("env-token"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn explicit_token_takes_priority_over_netrc() -> Result<()> {
+        github_token_source(
+            Some("explicit-token"),
+            Some("env-token"),
+            None,
+            Some("netrc-token"),
+            Some("explicit-token"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn keyring_token_takes_priority_over_netrc() -> Result<()> {
+        github_token_source(
+            None,
+            None,
+            Some("keyring-token"),
+            Some("netrc-token"),
+            Some("keyring-token"),
+        )
+        .await
+    }
+
+    #[test_case("https://codeberg.org/api/v1", &["CODEBERG_TOKEN", "GITEA_TOKEN"] ; "Codeberg")]
+    #[test_case("https://gitea.com/api/v1", &["FORGEJO_TOKEN", "GITEA_TOKEN"] ; "Gitea")]
+    #[test_case("https://forgejo.example.com/api/v1", &["FORGEJO_TOKEN", "GITEA_TOKEN"] ; "self-hosted Forgejo")]
+    fn gitea_token_env_var_names(api_base_url: &str, expect: &[&str]) -> Result<()> {
+        assert_eq!(
+            ForgeType::Gitea.token_env_var_names(&Url::parse(api_base_url)?),
+            expect,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn token_env_var_names_for_other_forges() -> Result<()> {
+        let url = Url::parse("https://codeberg.org/api/v1")?;
+        assert_eq!(
+            ForgeType::GitLab.token_env_var_names(&url),
+            ForgeType::GitLab.env_var_names(),
+        );
+        Ok(())
+    }
+
+    // The client resolves `codeberg.org` to the mock server, so we can check that a request to
+    // Codeberg uses the `CODEBERG_TOKEN`.
+    #[test_case("codeberg.org", &[("CODEBERG_TOKEN", "codeberg"), ("FORGEJO_TOKEN", "forgejo"), ("GITEA_TOKEN", "gitea")], Some("codeberg") ; "Codeberg token for Codeberg")]
+    #[test_case("codeberg.org", &[("FORGEJO_TOKEN", "forgejo"), ("GITEA_TOKEN", "gitea")], Some("gitea") ; "Forgejo token is not used for Codeberg")]
+    #[test_case("127.0.0.1", &[("CODEBERG_TOKEN", "codeberg"), ("FORGEJO_TOKEN", "forgejo"), ("GITEA_TOKEN", "gitea")], Some("forgejo") ; "Forgejo token for other hosts")]
+    #[test_case("127.0.0.1", &[("CODEBERG_TOKEN", "codeberg")], None ; "Codeberg token is not used for other hosts")]
+    #[test_case("127.0.0.1", &[("GITEA_TOKEN", "gitea")], Some("gitea") ; "generic token")]
+    #[tokio::test]
+    #[serial]
+    async fn gitea_token_from_host_env_var(
+        host: &str,
+        set_vars: &[(&str, &str)],
+        expect_token: Option<&str>,
+    ) -> Result<()> {
+        let vars = env::vars().collect::<Vec<_>>();
+        for name in ForgeType::Gitea.env_var_names() {
+            env::remove_var(name);
+        }
+        for (k, v) in set_vars {
+            env::set_var(k, v);
+        }
+
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .match_header(
+                "Authorization",
+                expect_token.map_or(Matcher::Missing, |t| Matcher::Exact(format!("token {t}"))),
+            )
+            .with_status(200)
+            .with_body(r#"{"tag_name": "v1.0.0", "assets": []}"#)
+            .create_async()
+            .await;
+        let addr = server.socket_address();
+
+        let res = ForgeType::Gitea.make_forge_impl(
+            "houseabsolute/ubi".to_string(),
+            &ForgeOptions {
+                api_base: Some(format!("http://{host}:{}", addr.port())),
+                ..ForgeOptions::default()
+            },
+        );
+
+        for name in ForgeType::Gitea.env_var_names() {
+            env::remove_var(name);
+        }
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        let client = Client::builder().resolve(host, addr).build()?;
+        res?.fetch_assets(&client).await?;
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    async fn github_token_source(
+        token: Option<&str>,
+        env_token: Option<&str>,
+        keyring_token: Option<&str>,
+        netrc_token: Option<&str>,
+        expect_token: Option<&str>,
+    ) -> Result<()> {
+        let vars = env::vars().collect::<Vec<_>>();
+        env::remove_var("GITHUB_TOKEN");
+        if let Some(t) = env_token {
+            env::set_var("GITHUB_TOKEN", t);
+        }
+
+        let keyring = MockKeyring::default();
+        if let Some(t) = keyring_token {
+            keyring.set("ubi:github", t)?;
+        }
+
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .match_header(
+                "Authorization",
+                expect_token.map_or(Matcher::Missing, |t| Matcher::Exact(format!("Bearer {t}"))),
+            )
+            .with_status(200)
+            .with_body(r#"{"assets": []}"#)
+            .create_async()
+            .await;
+
+        // The netrc entry is keyed by the mock server's host.
+        let td = tempdir()?;
+        let netrc_file = td.path().join(".netrc");
+        if let Some(t) = netrc_token {
+            let url = Url::parse(&server.url())?;
+            let host = url.host_str().unwrap();
+            fs::write(
+                &netrc_file,
+                format!("machine {host} login github-user password {t}\n"),
+            )?;
+        }
+
+        let res = ForgeType::GitHub.make_forge_impl(
+            "houseabsolute/ubi".to_string(),
+            &ForgeOptions {
+                api_base: Some(server.url()),
+                token: token.map(String::from),
+                keyring: Some(&keyring),
+                netrc_file: netrc_token.map(|_| netrc_file.as_path()),
+                ..ForgeOptions::default()
+            },
+        );
+
+        env::remove_var("GITHUB_TOKEN");
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        res?.fetch_assets(&Client::new()).await?;
+        m.assert_async().await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+use crate::{
+    forge::{self, Forge, ReleaseAssets},
+    project,
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use log::debug;
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    Client, RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug)]
+pub(crate) struct Gitea {
+    project_name: String,
+    tag: Option<String>,
+    api_base_url: Url,
+    token: Option<String>,
+    retry_policy: RetryPolicy,
+}
+
+unsafe impl Send for Gitea {}
+unsafe impl Sync for Gitea {}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Release {
+    #[serde(default)]
+    tag_name: Option<String>,
+    assets: Vec<GiteaAsset>,
+}
+
+// Gitea's asset JSON uses `browser_download_url` for the download link, and it includes a number of
+// fields we don't care about. Older releases of Gitea do not always include the `uploader` or
+// `size` fields, so these are optional.
+#[derive(Debug, Deserialize, Serialize)]
+struct GiteaAsset {
+    name: String,
+    browser_download_url: Url,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    uploader: Option<GiteaUser>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GiteaUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReleaseSummary {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    // This is null for drafts.
+    #[serde(default)]
+    published_at: Option<String>,
+}
+
+// Gitea's default maximum page size is 50.
+const RELEASES_PER_PAGE: usize = 50;
+
+impl From<GiteaAsset> for Asset {
+    fn from(asset: GiteaAsset) -> Self {
+        Asset {
+            name: asset.name,
+            url: asset.browser_download_url,
+            size: asset.size,
+        }
+    }
+}
+
+impl From<ReleaseSummary> for forge::Release {
+    fn from(release: ReleaseSummary) -> Self {
+        forge::Release {
+            tag: release.tag_name,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            published_at: release.published_at,
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for Gitea {
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let release = self
+            .make_release_info_request(client)
+            .await?
+            .json::<Release>()
+            .await?;
+        Ok(ReleaseAssets {
+            tag: release.tag_name,
+            assets: release.assets.into_iter().map(Asset::from).collect(),
+        })
+    }
+
+    async fn list_releases(&self, client: &Client) -> Result<Vec<forge::Release>> {
+        let mut all_releases = vec![];
+        for page in 1.. {
+            let mut url = self.releases_url();
+            url.query_pairs_mut()
+                .append_pair("limit", &RELEASES_PER_PAGE.to_string())
+                .append_pair("page", &page.to_string());
+            debug!("Getting releases from {url}");
+            let releases = self
+                .make_api_request(client, url)
+                .await?
+                .json::<Vec<ReleaseSummary>>()
+                .await?;
+            let count = releases.len();
+            all_releases.extend(releases.into_iter().map(forge::Release::from));
+            if count < RELEASES_PER_PAGE {
+                break;
+            }
+        }
+
+        Ok(all_releases)
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        let mut url = self.releases_url();
+        if let Some(tag) = &self.tag {
+            url.path_segments_mut()
+                .expect("could not get path segments for url")
+                .push("tags")
+                .push(tag);
+        } else {
+            url.path_segments_mut()
+                .expect("could not get path segments for url")
+                .push("latest");
+        }
+
+        url
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
+        if let Some(token) = self.token.as_deref() {
+            debug!("Adding Gitea token to Gitea request.");
+            let mut auth_val = HeaderValue::from_str(&format!("token {token}"))?;
+            auth_val.set_sensitive(true);
+            req_builder = req_builder.header(AUTHORIZATION, auth_val);
+        } else {
+            debug!("No Gitea token found.");
+        }
+        Ok(req_builder)
+    }
+}
+
+impl Gitea {
+    fn releases_url(&self) -> Url {
+        let (owner, repo) = project::split_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            .push("repos")
+            .push(owner)
+            .push(repo)
+            .push("releases");
+        url
+    }
+
+    pub(crate) fn new(
+        project_name: String,
+        tag: Option<String>,
+        api_base_url: Url,
+        token: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            project_name,
+            tag,
+            api_base_url,
+            token,
+            retry_policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+    use reqwest::Client;
+    use serial_test::serial;
+    use std::env;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_without_token() -> Result<()> {
+        fetch_assets(None, None).await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_with_token() -> Result<()> {
+        fetch_assets(None, Some("fakeToken")).await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_with_tag() -> Result<()> {
+        fetch_assets(Some("v1.0.0"), None).await
+    }
+
+    async fn fetch_assets(tag: Option<&str>, token: Option<&str>) -> Result<()> {
+        let vars = env::vars();
+        env::remove_var("GITEA_TOKEN");
+
+        let expect_path = if let Some(tag) = tag {
+            format!("/repos/houseabsolute/ubi/releases/tags/{tag}")
+        } else {
+            "/repos/houseabsolute/ubi/releases/latest".to_string()
+        };
+        let authorization_header_matcher = if let Some(token) = token {
+            mockito::Matcher::Exact(format!("token {token}"))
+        } else {
+            mockito::Matcher::Missing
+        };
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", expect_path.as_str())
+            .match_header("Authorization", authorization_header_matcher)
+            .with_status(200)
+            .with_body(GITEA_RELEASE_RESPONSE)
+            .create_async()
+            .await;
+
+        let gitea = Gitea::new(
+            "houseabsolute/ubi".to_string(),
+            tag.map(String::from),
+            Url::parse(&server.url())?,
+            token.map(String::from),
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got = gitea.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            got.assets,
+            vec![
+                Asset {
+                    name: "ubi-Linux-x86_64-musl.tar.gz".to_string(),
+                    url: Url::parse(
+                        "https://gitea.com/houseabsolute/ubi/releases/download/v1.0.0/ubi-Linux-x86_64-musl.tar.gz"
+                    )?,
+                    size: Some(2_906_752),
+                },
+                Asset {
+                    name: "ubi-Windows-msvc-x86_64.zip".to_string(),
+                    url: Url::parse(
+                        "https://gitea.com/houseabsolute/ubi/releases/download/v1.0.0/ubi-Windows-msvc-x86_64.zip"
+                    )?,
+                    size: None,
+                },
+            ],
+        );
+
+        m.assert_async().await;
+
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        Ok(())
+    }
+
+    // This is trimmed down from a real Gitea response. The second asset is missing the `uploader`
+    // and `size` fields, as happens with older Gitea releases.
+    const GITEA_RELEASE_RESPONSE: &str = r#"
+{
+  "id": 1,
+  "tag_name": "v1.0.0",
+  "assets": [
+    {
+      "id": 101,
+      "name": "ubi-Linux-x86_64-musl.tar.gz",
+      "size": 2906752,
+      "download_count": 3,
+      "created_at": "2025-03-01T00:00:00Z",
+      "uuid": "5b2e8f2a-0000-0000-0000-000000000000",
+      "browser_download_url": "https://gitea.com/houseabsolute/ubi/releases/download/v1.0.0/ubi-Linux-x86_64-musl.tar.gz",
+      "uploader": { "id": 1, "login": "autarch" }
+    },
+    {
+      "id": 102,
+      "name": "ubi-Windows-msvc-x86_64.zip",
+      "download_count": 0,
+      "created_at": "2025-03-01T00:00:00Z",
+      "uuid": "5b2e8f2a-0000-0000-0000-000000000001",
+      "browser_download_url": "https://gitea.com/houseabsolute/ubi/releases/download/v1.0.0/ubi-Windows-msvc-x86_64.zip"
+    }
+  ]
+}
+"#;
+
+    // This uses a response in the same format as the real API, rather than one we serialized
+    // ourselves, to make sure we deserialize the fields we care about.
+    #[test(tokio::test)]
+    async fn list_releases_from_api_response() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(LIST_RELEASES_RESPONSE)
+            .create_async()
+            .await;
+
+        let gitea = Gitea::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let releases = gitea.list_releases(&Client::new()).await?;
+        assert_eq!(
+            releases,
+            [
+                forge::Release {
+                    tag: "v0.7.0".to_string(),
+                    prerelease: false,
+                    draft: true,
+                    published_at: Some("0001-01-01T00:00:00Z".to_string()),
+                },
+                forge::Release {
+                    tag: "v0.7.0-rc.1".to_string(),
+                    prerelease: true,
+                    draft: false,
+                    published_at: Some("2025-04-02T12:00:00Z".to_string()),
+                },
+                forge::Release {
+                    tag: "v0.6.1".to_string(),
+                    prerelease: false,
+                    draft: false,
+                    published_at: Some("2025-03-18T21:03:02Z".to_string()),
+                },
+            ],
+        );
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    const LIST_RELEASES_RESPONSE: &str = r#"[
+  {"id": 3, "tag_name": "v0.7.0", "draft": true, "prerelease": false, "published_at": "0001-01-01T00:00:00Z", "assets": []},
+  {"id": 2, "tag_name": "v0.7.0-rc.1", "draft": false, "prerelease": true, "published_at": "2025-04-02T12:00:00Z", "assets": []},
+  {"id": 1, "tag_name": "v0.6.1", "draft": false, "prerelease": false, "published_at": "2025-03-18T21:03:02Z", "assets": []}
+]"#;
+
+    #[test(tokio::test)]
+    async fn list_releases() -> Result<()> {
+        // The first page is full, so we have to ask for a second page.
+        let first_page = (0..RELEASES_PER_PAGE)
+            .map(|i| ReleaseSummary {
+                tag_name: format!("v1.0.{i}"),
+                prerelease: i == 1,
+                draft: i == 0,
+                published_at: (i != 0).then(|| format!("2025-03-18T21:{i:02}:00Z")),
+            })
+            .collect::<Vec<_>>();
+        let second_page = vec![ReleaseSummary {
+            tag_name: "v0.9.0".to_string(),
+            prerelease: false,
+            draft: false,
+            published_at: Some("2024-12-01T10:00:00Z".to_string()),
+        }];
+
+        let mut server = Server::new_async().await;
+        let mut mocks = vec![];
+        for (page, releases) in [(1, &first_page), (2, &second_page)] {
+            mocks.push(
+                server
+                    .mock("GET", "/repos/houseabsolute/ubi/releases")
+                    .match_query(mockito::Matcher::AllOf(vec![
+                        mockito::Matcher::UrlEncoded(
+                            "limit".to_string(),
+                            RELEASES_PER_PAGE.to_string(),
+                        ),
+                        mockito::Matcher::UrlEncoded("page".to_string(), page.to_string()),
+                    ]))
+                    .with_status(200)
+                    .with_body(serde_json::to_string(releases)?)
+                    .create_async()
+                    .await,
+            );
+        }
+
+        let gitea = Gitea::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let tags = gitea.list_releases(&Client::new()).await?;
+        assert_eq!(tags.len(), RELEASES_PER_PAGE + 1);
+        assert_eq!(
+            tags[..2],
+            [
+                forge::Release {
+                    tag: "v1.0.0".to_string(),
+                    prerelease: false,
+                    draft: true,
+                    published_at: None,
+                },
+                forge::Release {
+                    tag: "v1.0.1".to_string(),
+                    prerelease: true,
+                    draft: false,
+                    published_at: Some("2025-03-18T21:01:00Z".to_string()),
+                },
+            ],
+        );
+        assert_eq!(
+            tags.last(),
+            Some(&forge::Release {
+                tag: "v0.9.0".to_string(),
+                prerelease: false,
+                draft: false,
+                published_at: Some("2024-12-01T10:00:00Z".to_string()),
+            }),
+        );
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn api_base_url() {
+        let gitea = Gitea::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse("https://gitea.example.com/api/v1").unwrap(),
+            None,
+            RetryPolicy::default(),
+        );
+        let url = gitea.release_info_url();
+        assert_eq!(
+            url.as_str(),
+            "https://gitea.example.com/api/v1/repos/houseabsolute/ubi/releases/latest"
+        );
+    }
+}
@@ -1,12 +1,18 @@
-use crate::{forge::Forge, ubi::Asset};
-use anyhow::Result;
+use crate::{
+    forge::{self, Forge, ReleaseAssets},
+    project,
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::debug;
 use reqwest::{
     header::{HeaderValue, AUTHORIZATION},
-    Client, RequestBuilder,
+    Client, RequestBuilder, Response, StatusCode,
 };
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
 
 #[derive(Debug)]
@@ -15,6 +21,7 @@ pub(crate) struct GitHub {
     tag: Option<String>,
     api_base_url: Url,
     token: Option<String>,
+    retry_policy: RetryPolicy,
 }
 
 unsafe impl Send for GitHub {}
@@ -22,32 +29,83 @@ unsafe impl Sync for GitHub {}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct Release {
+    #[serde(default)]
+    pub(crate) tag_name: Option<String>,
     pub(crate) assets: Vec<Asset>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct ReleaseSummary {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    // This is null for drafts.
+    #[serde(default)]
+    published_at: Option<String>,
+}
+
+impl From<ReleaseSummary> for forge::Release {
+    fn from(release: ReleaseSummary) -> Self {
+        forge::Release {
+            tag: release.tag_name,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            published_at: release.published_at,
+        }
+    }
+}
+
+const RELEASES_PER_PAGE: usize = 100;
+
 #[async_trait]
 impl Forge for GitHub {
-    async fn fetch_assets(&self, client: &Client) -> Result<Vec<Asset>> {
-        Ok(self
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let release = self
             .make_release_info_request(client)
             .await?
             .json::<Release>()
-            .await?
-            .assets)
+            .await?;
+        Ok(ReleaseAssets {
+            tag: release.tag_name,
+            assets: release.assets,
+        })
     }
 
-    fn release_info_url(&self) -> Url {
-        let mut parts = self.project_name.split('/');
-        let owner = parts.next().unwrap();
-        let repo = parts.next().unwrap();
+    async fn list_releases(&self, client: &Client) -> Result<Vec<forge::Release>> {
+        let mut all_releases = vec![];
+        for page in 1.. {
+            let mut url = self.releases_url();
+            url.query_pairs_mut()
+                .append_pair("per_page", &RELEASES_PER_PAGE.to_string())
+                .append_pair("page", &page.to_string());
+            debug!("Getting releases from {url}");
+            let releases = self
+                .make_api_request(client, url)
+                .await?
+                .json::<Vec<ReleaseSummary>>()
+                .await?;
+            let count = releases.len();
+            all_releases.extend(releases.into_iter().map(forge::Release::from));
+            if count < RELEASES_PER_PAGE {
+                break;
+            }
+        }
 
-        let mut url = self.api_base_url.clone();
-        url.path_segments_mut()
-            .expect("could not get path segments for url")
-            .push("repos")
-            .push(owner)
-            .push(repo)
-            .push("releases");
+        Ok(all_releases)
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        let mut url = self.releases_url();
         if let Some(tag) = &self.tag {
             url.path_segments_mut()
                 .expect("could not get path segments for url")
@@ -62,6 +120,10 @@ impl Forge for GitHub {
         url
     }
 
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
     fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
         if let Some(token) = self.token.as_deref() {
             debug!("Adding GitHub token to GitHub request.");
@@ -72,24 +134,92 @@ impl Forge for GitHub {
         }
         Ok(req_builder)
     }
+
+    fn rate_limit_error(&self, resp: &Response) -> Option<anyhow::Error> {
+        rate_limit_error(resp, self.token.is_some(), SystemTime::now())
+    }
 }
 
 impl GitHub {
+    fn releases_url(&self) -> Url {
+        let (owner, repo) = project::split_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            // This avoids an empty segment if the base URL has a trailing slash, like
+            // `https://ghe.example.com/api/v3/`.
+            .pop_if_empty()
+            .push("repos")
+            .push(owner)
+            .push(repo)
+            .push("releases");
+        url
+    }
+
     pub(crate) fn new(
         project_name: String,
         tag: Option<String>,
         api_base_url: Url,
         token: Option<String>,
+        retry_policy: RetryPolicy,
     ) -> Self {
         Self {
             project_name,
             tag,
             api_base_url,
             token,
+            retry_policy,
         }
     }
 }
 
+// When the rate limit is hit, GitHub responds with a 403 or 429 and sets `X-RateLimit-Remaining` to
+// 0. The `X-RateLimit-Reset` header is when the limit resets, in seconds since the epoch.
+fn rate_limit_error(resp: &Response, has_token: bool, now: SystemTime) -> Option<anyhow::Error> {
+    if !matches!(
+        resp.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+    let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok());
+    if header("x-ratelimit-remaining") != Some("0") {
+        return None;
+    }
+
+    let resets = header("x-ratelimit-reset")
+        .and_then(|r| r.parse::<u64>().ok())
+        .map(|r| {
+            let reset = UNIX_EPOCH + Duration::from_secs(r);
+            let at = httpdate::fmt_http_date(reset);
+            match reset.duration_since(now) {
+                Ok(wait) => format!(", it resets at {at} ({})", describe_wait(wait)),
+                Err(_) => format!(", it reset at {at}"),
+            }
+        })
+        .unwrap_or_default();
+    let hint = if has_token {
+        "this limit applies to the GitHub token you are using, so wait until it resets to try again"
+    } else {
+        "set the GITHUB_TOKEN env var to make authenticated requests, which have a much higher rate limit"
+    };
+    Some(anyhow!(
+        "the GitHub API rate limit was exceeded{resets}; {hint}"
+    ))
+}
+
+fn describe_wait(wait: Duration) -> String {
+    if wait.as_secs() < 60 {
+        return String::from("in less than a minute");
+    }
+    match wait.as_secs().div_ceil(60) {
+        1 => String::from("in 1 minute"),
+        minutes => format!("in {minutes} minutes"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +227,7 @@ mod tests {
     use reqwest::Client;
     use serial_test::serial;
     use std::env;
+    use test_case::test_case;
     use test_log::test;
 
     #[test(tokio::test)]
@@ -124,6 +255,7 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://api.github.com/repos/houseabsolute/ubi/releases/assets/1")?,
+            size: Some(2_906_752),
         }];
 
         let expect_path = if let Some(tag) = tag {
@@ -131,8 +263,8 @@ mod tests {
         } else {
             "/repos/houseabsolute/ubi/releases/latest".to_string()
         };
-        let authorization_header_matcher = if token.is_some() {
-            mockito::Matcher::Exact(format!("Bearer {}", token.unwrap()))
+        let authorization_header_matcher = if let Some(token) = token {
+            mockito::Matcher::Exact(format!("Bearer {token}"))
         } else {
             mockito::Matcher::Missing
         };
@@ -142,6 +274,7 @@ mod tests {
             .match_header("Authorization", authorization_header_matcher)
             .with_status(200)
             .with_body(serde_json::to_string(&Release {
+                tag_name: Some(tag.unwrap_or("v1.0.0").to_string()),
                 assets: assets.clone(),
             })?)
             .create_async()
@@ -152,11 +285,13 @@ mod tests {
             tag.map(String::from),
             Url::parse(&server.url())?,
             token.map(String::from),
+            RetryPolicy::default(),
         );
 
         let client = Client::new();
-        let got_assets = github.fetch_assets(&client).await?;
-        assert_eq!(got_assets, assets);
+        let got = github.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some(tag.unwrap_or("v1.0.0")));
+        assert_eq!(got.assets, assets);
 
         m.assert_async().await;
 
@@ -167,18 +302,241 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn api_base_url() {
+    // This uses a response in the same format as the real API, rather than one we serialized
+    // ourselves, to make sure we deserialize the fields we care about.
+    #[test(tokio::test)]
+    async fn list_releases_from_api_response() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(LIST_RELEASES_RESPONSE)
+            .create_async()
+            .await;
+
+        let github = GitHub::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let releases = github.list_releases(&Client::new()).await?;
+        assert_eq!(
+            releases,
+            [
+                forge::Release {
+                    tag: "v0.7.0".to_string(),
+                    prerelease: false,
+                    draft: true,
+                    published_at: None,
+                },
+                forge::Release {
+                    tag: "v0.7.0-rc.1".to_string(),
+                    prerelease: true,
+                    draft: false,
+                    published_at: Some("2025-04-02T12:00:00Z".to_string()),
+                },
+                forge::Release {
+                    tag: "v0.6.1".to_string(),
+                    prerelease: false,
+                    draft: false,
+                    published_at: Some("2025-03-18T21:03:02Z".to_string()),
+                },
+            ],
+        );
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    const LIST_RELEASES_RESPONSE: &str = r#"[
+  {"tag_name": "v0.7.0", "name": "v0.7.0", "draft": true, "prerelease": false, "published_at": null, "assets": []},
+  {"tag_name": "v0.7.0-rc.1", "name": "v0.7.0-rc.1", "draft": false, "prerelease": true, "published_at": "2025-04-02T12:00:00Z", "assets": []},
+  {"tag_name": "v0.6.1", "name": "v0.6.1", "draft": false, "prerelease": false, "published_at": "2025-03-18T21:03:02Z", "assets": []}
+]"#;
+
+    #[test(tokio::test)]
+    async fn list_releases() -> Result<()> {
+        // The first page is full, so we have to ask for a second page.
+        let first_page = (0..RELEASES_PER_PAGE)
+            .map(|i| ReleaseSummary {
+                tag_name: format!("v1.0.{i}"),
+                prerelease: i == 1,
+                draft: i == 0,
+                published_at: (i != 0).then(|| format!("2025-03-18T21:{i:02}:00Z")),
+            })
+            .collect::<Vec<_>>();
+        let second_page = vec![ReleaseSummary {
+            tag_name: "v0.9.0".to_string(),
+            prerelease: false,
+            draft: false,
+            published_at: Some("2024-12-01T10:00:00Z".to_string()),
+        }];
+
+        let mut server = Server::new_async().await;
+        let mut mocks = vec![];
+        for (page, releases) in [(1, &first_page), (2, &second_page)] {
+            mocks.push(
+                server
+                    .mock("GET", "/repos/houseabsolute/ubi/releases")
+                    .match_query(mockito::Matcher::AllOf(vec![
+                        mockito::Matcher::UrlEncoded(
+                            "per_page".to_string(),
+                            RELEASES_PER_PAGE.to_string(),
+                        ),
+                        mockito::Matcher::UrlEncoded("page".to_string(), page.to_string()),
+                    ]))
+                    .with_status(200)
+                    .with_body(serde_json::to_string(releases)?)
+                    .create_async()
+                    .await,
+            );
+        }
+
         let github = GitHub::new(
             "houseabsolute/ubi".to_string(),
             None,
-            Url::parse("https://github.example.com/api/v4").unwrap(),
+            Url::parse(&server.url())?,
             None,
+            RetryPolicy::default(),
         );
-        let url = github.release_info_url();
+        let tags = github.list_releases(&Client::new()).await?;
+        assert_eq!(tags.len(), RELEASES_PER_PAGE + 1);
         assert_eq!(
-            url.as_str(),
-            "https://github.example.com/api/v4/repos/houseabsolute/ubi/releases/latest"
+            tags[..2],
+            [
+                forge::Release {
+                    tag: "v1.0.0".to_string(),
+                    prerelease: false,
+                    draft: true,
+                    published_at: None,
+                },
+                forge::Release {
+                    tag: "v1.0.1".to_string(),
+                    prerelease: true,
+                    draft: false,
+                    published_at: Some("2025-03-18T21:01:00Z".to_string()),
+                },
+            ],
+        );
+        assert_eq!(
+            tags.last(),
+            Some(&forge::Release {
+                tag: "v0.9.0".to_string(),
+                prerelease: false,
+                draft: false,
+                published_at: Some("2024-12-01T10:00:00Z".to_string()),
+            }),
+        );
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test_case(None, Some("0"), 1_800, true, "in 30 minutes", "set the GITHUB_TOKEN env var" ; "without token")]
+    #[test_case(Some("ghp_fakeToken"), Some("0"), 1_800, true, "in 30 minutes", "this limit applies to the GitHub token" ; "with token")]
+    #[test_case(None, Some("0"), 30, true, "in less than a minute", "set the GITHUB_TOKEN env var" ; "resets soon")]
+    #[test_case(None, Some("12"), 1_800, false, "", "" ; "requests remaining")]
+    #[test_case(None, None, 1_800, false, "", "" ; "no rate limit headers")]
+    #[tokio::test]
+    async fn fetch_assets_when_rate_limited(
+        token: Option<&str>,
+        remaining: Option<&str>,
+        reset_in_secs: u64,
+        expect_rate_limit_error: bool,
+        expect_wait: &str,
+        expect_hint: &str,
+    ) -> Result<()> {
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + reset_in_secs;
+
+        let mut server = Server::new_async().await;
+        let mut mock = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .with_status(403)
+            .with_header("x-ratelimit-limit", "60")
+            .with_header("x-ratelimit-reset", &reset.to_string())
+            .with_body(r#"{"message": "API rate limit exceeded"}"#);
+        if let Some(remaining) = remaining {
+            mock = mock.with_header("x-ratelimit-remaining", remaining);
+        }
+        let m = mock.create_async().await;
+
+        let github = GitHub::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            token.map(String::from),
+            RetryPolicy::default(),
         );
+        let err = github.fetch_assets(&Client::new()).await.unwrap_err();
+        let msg = err.to_string();
+        if expect_rate_limit_error {
+            let at = httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(reset));
+            assert!(
+                msg.starts_with(&format!(
+                    "the GitHub API rate limit was exceeded, it resets at {at} ({expect_wait}); {expect_hint}"
+                )),
+                "{msg}",
+            );
+        } else {
+            assert!(msg.contains("403 Forbidden"), "{msg}");
+        }
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test_case(Duration::from_secs(0), "in less than a minute")]
+    #[test_case(Duration::from_secs(59), "in less than a minute")]
+    #[test_case(Duration::from_secs(60), "in 1 minute")]
+    #[test_case(Duration::from_secs(61), "in 2 minutes")]
+    #[test_case(Duration::from_secs(3_600), "in 60 minutes")]
+    fn describe_wait(wait: Duration, expect: &str) {
+        assert_eq!(super::describe_wait(wait), expect);
+    }
+
+    #[test_case(
+        "https://api.github.com",
+        None,
+        "https://api.github.com/repos/houseabsolute/ubi/releases/latest" ;
+        "default base"
+    )]
+    #[test_case(
+        "https://ghe.example.com/api/v3",
+        None,
+        "https://ghe.example.com/api/v3/repos/houseabsolute/ubi/releases/latest" ;
+        "enterprise base"
+    )]
+    #[test_case(
+        "https://ghe.example.com/api/v3/",
+        None,
+        "https://ghe.example.com/api/v3/repos/houseabsolute/ubi/releases/latest" ;
+        "enterprise base with trailing slash"
+    )]
+    #[test_case(
+        "https://ghe.example.com/api/v3",
+        Some("v1.0.0"),
+        "https://ghe.example.com/api/v3/repos/houseabsolute/ubi/releases/tags/v1.0.0" ;
+        "enterprise base with tag"
+    )]
+    fn api_base_url(api_base_url: &str, tag: Option<&str>, expect: &str) -> Result<()> {
+        let github = GitHub::new(
+            "houseabsolute/ubi".to_string(),
+            tag.map(String::from),
+            Url::parse(api_base_url)?,
+            None,
+            RetryPolicy::default(),
+        );
+        let url = github.release_info_url();
+        assert_eq!(url.as_str(), expect);
+
+        Ok(())
     }
 }
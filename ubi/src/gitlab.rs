@@ -1,4 +1,8 @@
-use crate::{forge::Forge, ubi::Asset};
+use crate::{
+    forge::{self, Forge, ReleaseAssets},
+    retry::RetryPolicy,
+    ubi::Asset,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use log::debug;
@@ -6,12 +10,26 @@ use reqwest::{header::HeaderValue, header::AUTHORIZATION, Client, RequestBuilder
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+pub(crate) const CI_JOB_TOKEN_ENV_VAR: &str = "CI_JOB_TOKEN";
+const JOB_TOKEN_HEADER: &str = "JOB-TOKEN";
+
 #[derive(Debug)]
 pub(crate) struct GitLab {
     project_name: String,
     tag: Option<String>,
     api_base_url: Url,
-    token: Option<String>,
+    token: Option<GitLabToken>,
+    retry_policy: RetryPolicy,
+}
+
+// GitLab CI job tokens are only accepted in the `JOB-TOKEN` header. Sending one as a bearer token
+// gets a 401 response.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum GitLabToken {
+    // A token taken from the `CI_JOB_TOKEN` env var.
+    Job(String),
+    // A personal, group, or project access token.
+    Access(String),
 }
 
 unsafe impl Send for GitLab {}
@@ -19,33 +37,105 @@ unsafe impl Sync for GitLab {}
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Release {
+    #[serde(default)]
+    tag_name: Option<String>,
     assets: GitLabAssets,
 }
 
+// GitLab puts the files that were attached to a release in `links`, and the source archives that it
+// generates for every release in `sources`. The source archives are not binaries, so we only return
+// the links.
 #[derive(Debug, Deserialize, Serialize)]
 struct GitLabAssets {
+    #[serde(default)]
     links: Vec<Asset>,
+    #[serde(default)]
+    sources: Vec<Source>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Source {
+    format: String,
+    url: Url,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReleaseSummary {
+    tag_name: String,
+    // This is set for releases with a release date in the future.
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(default)]
+    released_at: Option<String>,
+}
+
+// GitLab has no prerelease flag or drafts, so we treat an upcoming release as a prerelease.
+impl From<ReleaseSummary> for forge::Release {
+    fn from(release: ReleaseSummary) -> Self {
+        forge::Release {
+            tag: release.tag_name,
+            prerelease: release.upcoming_release,
+            draft: false,
+            published_at: release.released_at,
+        }
+    }
 }
 
+const RELEASES_PER_PAGE: usize = 100;
+
 #[async_trait]
 impl Forge for GitLab {
-    async fn fetch_assets(&self, client: &Client) -> Result<Vec<Asset>> {
-        Ok(self
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let release = self
             .make_release_info_request(client)
             .await?
             .json::<Release>()
-            .await?
-            .assets
-            .links)
+            .await?;
+        if !release.assets.sources.is_empty() {
+            debug!(
+                "ignoring the {} source archives for the release, since they are not binaries",
+                release.assets.sources.len(),
+            );
+        }
+        Ok(ReleaseAssets {
+            tag: release.tag_name,
+            assets: release.assets.links,
+        })
+    }
+
+    async fn list_releases(&self, client: &Client) -> Result<Vec<forge::Release>> {
+        let mut all_releases = vec![];
+        for page in 1.. {
+            let mut url = self.releases_url();
+            url.query_pairs_mut()
+                .append_pair("per_page", &RELEASES_PER_PAGE.to_string())
+                .append_pair("page", &page.to_string());
+            debug!("Getting releases from {url}");
+            let releases = self
+                .make_api_request(client, url)
+                .await?
+                .json::<Vec<ReleaseSummary>>()
+                .await?;
+            let count = releases.len();
+            all_releases.extend(releases.into_iter().map(forge::Release::from));
+            if count < RELEASES_PER_PAGE {
+                break;
+            }
+        }
+
+        Ok(all_releases)
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
     }
 
     fn release_info_url(&self) -> Url {
-        let mut url = self.api_base_url.clone();
-        url.path_segments_mut()
-            .expect("could not get path segments for url")
-            .push("projects")
-            .push(&self.project_name)
-            .push("releases");
+        let mut url = self.releases_url();
         if let Some(tag) = &self.tag {
             url.path_segments_mut()
                 .expect("could not get path segments for url")
@@ -59,32 +149,66 @@ impl Forge for GitLab {
         url
     }
 
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
     fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
-        if let Some(token) = self.token.as_deref() {
-            debug!("Adding GitLab token to GitLab request.");
-            let bearer = format!("Bearer {token}");
-            let mut auth_val = HeaderValue::from_str(&bearer)?;
-            auth_val.set_sensitive(true);
-            req_builder = req_builder.header(AUTHORIZATION, auth_val);
-        } else {
-            debug!("No GitLab token found.");
+        match &self.token {
+            Some(GitLabToken::Job(token)) => {
+                debug!("Adding GitLab CI job token to GitLab request.");
+                let mut job_token_val = HeaderValue::from_str(token)?;
+                job_token_val.set_sensitive(true);
+                req_builder = req_builder.header(JOB_TOKEN_HEADER, job_token_val);
+            }
+            Some(GitLabToken::Access(token)) => {
+                debug!("Adding GitLab token to GitLab request.");
+                let bearer = format!("Bearer {token}");
+                let mut auth_val = HeaderValue::from_str(&bearer)?;
+                auth_val.set_sensitive(true);
+                req_builder = req_builder.header(AUTHORIZATION, auth_val);
+            }
+            None => debug!("No GitLab token found."),
         }
         Ok(req_builder)
     }
 }
 
+// This works with an instance hosted under a path, like `https://example.com/gitlab/`.
+pub(crate) fn api_base_url_for_instance(instance_url: &Url) -> Url {
+    let mut url = instance_url.clone();
+    url.path_segments_mut()
+        .expect("could not get path segments for url")
+        .pop_if_empty()
+        .extend(["api", "v4"]);
+    url
+}
+
 impl GitLab {
+    fn releases_url(&self) -> Url {
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            .pop_if_empty()
+            .push("projects")
+            .push(&self.project_name)
+            .push("releases");
+        url
+    }
+
     pub(crate) fn new(
         project_name: String,
         tag: Option<String>,
         api_base_url: Url,
-        token: Option<String>,
+        token: Option<GitLabToken>,
+        retry_policy: RetryPolicy,
     ) -> Self {
         Self {
             project_name,
             tag,
             api_base_url,
             token,
+            retry_policy,
         }
     }
 }
@@ -92,10 +216,13 @@ impl GitLab {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::forge::{ForgeOptions, ForgeType};
     use mockito::Server;
     use reqwest::Client;
     use serial_test::serial;
-    use std::env;
+    use std::{env, fs};
+    use tempfile::tempdir;
+    use test_case::test_case;
     use test_log::test;
 
     #[test(tokio::test)]
@@ -124,6 +251,7 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://gitlab.com/api/v4/projects/owner%2Frepo/releases/assets/1")?,
+            size: None,
         }];
 
         let expect_path = if let Some(tag) = tag {
@@ -131,8 +259,8 @@ mod tests {
         } else {
             "/projects/houseabsolute%2Fubi/releases/permalink/latest".to_string()
         };
-        let authorization_header_matcher = if token.is_some() {
-            mockito::Matcher::Exact(format!("Bearer {}", token.unwrap()))
+        let authorization_header_matcher = if let Some(token) = token {
+            mockito::Matcher::Exact(format!("Bearer {token}"))
         } else {
             mockito::Matcher::Missing
         };
@@ -142,8 +270,10 @@ mod tests {
             .match_header("Authorization", authorization_header_matcher)
             .with_status(200)
             .with_body(serde_json::to_string(&Release {
+                tag_name: Some(tag.unwrap_or("v1.0.0").to_string()),
                 assets: GitLabAssets {
                     links: assets.clone(),
+                    sources: vec![],
                 },
             })?)
             .create_async()
@@ -153,12 +283,14 @@ mod tests {
             "houseabsolute/ubi".to_string(),
             tag.map(String::from),
             Url::parse(&server.url())?,
-            token.map(String::from),
+            token.map(|t| GitLabToken::Access(t.to_string())),
+            RetryPolicy::default(),
         );
 
         let client = Client::new();
-        let got_assets = github.fetch_assets(&client).await?;
-        assert_eq!(got_assets, assets);
+        let got = github.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some(tag.unwrap_or("v1.0.0")));
+        assert_eq!(got.assets, assets);
 
         m.assert_async().await;
 
@@ -169,18 +301,446 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn api_base_url() {
+    const RELEASE_WITH_LINKS_AND_SOURCES: &str = r#"
+{
+  "tag_name": "v1.0.0",
+  "name": "v1.0.0",
+  "assets": {
+    "count": 4,
+    "sources": [
+      {
+        "format": "zip",
+        "url": "https://gitlab.com/houseabsolute/ubi/-/archive/v1.0.0/ubi-v1.0.0.zip"
+      },
+      {
+        "format": "tar.gz",
+        "url": "https://gitlab.com/houseabsolute/ubi/-/archive/v1.0.0/ubi-v1.0.0.tar.gz"
+      }
+    ],
+    "links": [
+      {
+        "id": 1,
+        "name": "ubi-Linux-x86_64.tar.gz",
+        "url": "https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Linux-x86_64.tar.gz",
+        "direct_asset_url": "https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Linux-x86_64.tar.gz",
+        "link_type": "package"
+      },
+      {
+        "id": 2,
+        "name": "ubi-Darwin-arm64.tar.gz",
+        "url": "https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Darwin-arm64.tar.gz",
+        "direct_asset_url": "https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Darwin-arm64.tar.gz",
+        "link_type": "package"
+      }
+    ]
+  }
+}
+"#;
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_links_and_sources() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock(
+                "GET",
+                "/projects/houseabsolute%2Fubi/releases/permalink/latest",
+            )
+            .with_status(200)
+            .with_body(RELEASE_WITH_LINKS_AND_SOURCES)
+            .create_async()
+            .await;
+
         let gitlab = GitLab::new(
             "houseabsolute/ubi".to_string(),
             None,
-            Url::parse("https://gitlab.example.com/api/v4").unwrap(),
+            Url::parse(&server.url())?,
             None,
+            RetryPolicy::default(),
         );
-        let url = gitlab.release_info_url();
+        let got = gitlab.fetch_assets(&Client::new()).await?;
+        assert_eq!(got.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            got.assets,
+            vec![
+                Asset {
+                    name: "ubi-Linux-x86_64.tar.gz".to_string(),
+                    url: Url::parse("https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Linux-x86_64.tar.gz")?,
+                    size: None,
+                },
+                Asset {
+                    name: "ubi-Darwin-arm64.tar.gz".to_string(),
+                    url: Url::parse("https://gitlab.com/houseabsolute/ubi/-/releases/v1.0.0/downloads/ubi-Darwin-arm64.tar.gz")?,
+                    size: None,
+                },
+            ],
+        );
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn token_from_ci_job_token_uses_job_token_header() -> Result<()> {
+        token_header(
+            &[("CI_JOB_TOKEN", "job-token")],
+            None,
+            Some("job-token"),
+            None,
+            None,
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn token_from_gitlab_token_uses_bearer() -> Result<()> {
+        token_header(
+            &[("GITLAB_TOKEN", "glpat-fakeToken")],
+            None,
+            None,
+            Some("glpat-fakeToken"),
+            None,
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn ci_job_token_is_preferred_to_gitlab_token() -> Result<()> {
+        token_header(
+            &[
+                ("CI_JOB_TOKEN", "job-token"),
+                ("GITLAB_TOKEN", "glpat-fakeToken"),
+            ],
+            None,
+            Some("job-token"),
+            None,
+            None,
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn explicit_token_uses_bearer() -> Result<()> {
+        token_header(
+            &[("CI_JOB_TOKEN", "job-token")],
+            Some("glpat-explicitToken"),
+            None,
+            Some("glpat-explicitToken"),
+            None,
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn no_token_sends_no_header() -> Result<()> {
+        token_header(&[], None, None, None, None).await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn token_from_netrc_uses_bearer() -> Result<()> {
+        token_header(
+            &[],
+            None,
+            None,
+            Some("glpat-netrcToken"),
+            Some("glpat-netrcToken"),
+        )
+        .await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn gitlab_token_is_preferred_to_netrc() -> Result<()> {
+        token_header(
+            &[("GITLAB_TOKEN", "glpat-fakeToken")],
+            None,
+            None,
+            Some("glpat-fakeToken"),
+            Some("glpat-netrcToken"),
+        )
+        .await
+    }
+
+    async fn token_header(
+        env_vars: &[(&str, &str)],
+        token: Option<&str>,
+        expect_job_token: Option<&str>,
+        expect_bearer: Option<&str>,
+        netrc_token: Option<&str>,
+    ) -> Result<()> {
+        let vars = env::vars();
+        env::remove_var("GITLAB_TOKEN");
+        env::remove_var("CI_JOB_TOKEN");
+        for (k, v) in env_vars {
+            env::set_var(k, v);
+        }
+
+        let header_matcher = |expect: Option<String>| match expect {
+            Some(v) => mockito::Matcher::Exact(v),
+            None => mockito::Matcher::Missing,
+        };
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock(
+                "GET",
+                "/projects/houseabsolute%2Fubi/releases/permalink/latest",
+            )
+            .match_header(
+                JOB_TOKEN_HEADER,
+                header_matcher(expect_job_token.map(String::from)),
+            )
+            .match_header(
+                "Authorization",
+                header_matcher(expect_bearer.map(|t| format!("Bearer {t}"))),
+            )
+            .with_status(200)
+            .with_body(serde_json::to_string(&Release {
+                tag_name: None,
+                assets: GitLabAssets {
+                    links: vec![],
+                    sources: vec![],
+                },
+            })?)
+            .create_async()
+            .await;
+
+        // The netrc entry is keyed by the mock server's host.
+        let td = tempdir()?;
+        let netrc_file = td.path().join(".netrc");
+        if let Some(t) = netrc_token {
+            let url = Url::parse(&server.url())?;
+            let host = url.host_str().unwrap();
+            fs::write(
+                &netrc_file,
+                format!("machine {host} login gitlab-user password {t}\n"),
+            )?;
+        }
+
+        let gitlab = ForgeType::GitLab.make_forge_impl(
+            "houseabsolute/ubi".to_string(),
+            &ForgeOptions {
+                api_base: Some(server.url()),
+                token: token.map(String::from),
+                netrc_file: netrc_token.map(|_| netrc_file.as_path()),
+                ..ForgeOptions::default()
+            },
+        )?;
+        let res = gitlab.fetch_assets(&Client::new()).await;
+
+        for (k, _) in env_vars {
+            env::remove_var(k);
+        }
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        res?;
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    // This uses a response in the same format as the real API, rather than one we serialized
+    // ourselves, to make sure we deserialize the fields we care about.
+    #[test(tokio::test)]
+    async fn list_releases_from_api_response() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/projects/houseabsolute%2Fubi/releases")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(LIST_RELEASES_RESPONSE)
+            .create_async()
+            .await;
+
+        let gitlab = GitLab::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let releases = gitlab.list_releases(&Client::new()).await?;
+        assert_eq!(
+            releases,
+            [
+                forge::Release {
+                    tag: "v0.7.0".to_string(),
+                    prerelease: true,
+                    draft: false,
+                    published_at: Some("2030-01-01T00:00:00.000Z".to_string()),
+                },
+                forge::Release {
+                    tag: "v0.6.1".to_string(),
+                    prerelease: false,
+                    draft: false,
+                    published_at: Some("2025-03-18T21:03:02.000Z".to_string()),
+                },
+                forge::Release {
+                    tag: "v0.6.0".to_string(),
+                    prerelease: false,
+                    draft: false,
+                    published_at: Some("2025-02-01T08:30:00.000Z".to_string()),
+                },
+            ],
+        );
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    const LIST_RELEASES_RESPONSE: &str = r#"[
+  {"tag_name": "v0.7.0", "name": "v0.7.0", "upcoming_release": true, "released_at": "2030-01-01T00:00:00.000Z", "assets": {"links": []}},
+  {"tag_name": "v0.6.1", "name": "v0.6.1", "upcoming_release": false, "released_at": "2025-03-18T21:03:02.000Z", "assets": {"links": []}},
+  {"tag_name": "v0.6.0", "name": "v0.6.0", "upcoming_release": false, "released_at": "2025-02-01T08:30:00.000Z", "assets": {"links": []}}
+]"#;
+
+    #[test(tokio::test)]
+    async fn list_releases() -> Result<()> {
+        // The first page is full, so we have to ask for a second page.
+        let first_page = (0..RELEASES_PER_PAGE)
+            .map(|i| ReleaseSummary {
+                tag_name: format!("v1.0.{i}"),
+                upcoming_release: i == 0,
+                released_at: Some(format!("2025-03-18T21:{i:02}:00Z")),
+            })
+            .collect::<Vec<_>>();
+        let second_page = vec![ReleaseSummary {
+            tag_name: "v0.9.0".to_string(),
+            upcoming_release: false,
+            released_at: Some("2024-12-01T10:00:00Z".to_string()),
+        }];
+
+        let mut server = Server::new_async().await;
+        let mut mocks = vec![];
+        for (page, releases) in [(1, &first_page), (2, &second_page)] {
+            mocks.push(
+                server
+                    .mock("GET", "/projects/houseabsolute%2Fubi/releases")
+                    .match_query(mockito::Matcher::AllOf(vec![
+                        mockito::Matcher::UrlEncoded(
+                            "per_page".to_string(),
+                            RELEASES_PER_PAGE.to_string(),
+                        ),
+                        mockito::Matcher::UrlEncoded("page".to_string(), page.to_string()),
+                    ]))
+                    .with_status(200)
+                    .with_body(serde_json::to_string(releases)?)
+                    .create_async()
+                    .await,
+            );
+        }
+
+        let gitlab = GitLab::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let tags = gitlab.list_releases(&Client::new()).await?;
+        assert_eq!(tags.len(), RELEASES_PER_PAGE + 1);
         assert_eq!(
-            url.as_str(),
-            "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases/permalink/latest"
+            tags[0],
+            forge::Release {
+                tag: "v1.0.0".to_string(),
+                prerelease: true,
+                draft: false,
+                published_at: Some("2025-03-18T21:00:00Z".to_string()),
+            },
+            "upcoming release is a prerelease",
         );
+        assert_eq!(
+            tags.last(),
+            Some(&forge::Release {
+                tag: "v0.9.0".to_string(),
+                prerelease: false,
+                draft: false,
+                published_at: Some("2024-12-01T10:00:00Z".to_string()),
+            }),
+        );
+
+        for m in mocks {
+            m.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test_case(
+        "https://gitlab.com/api/v4",
+        None,
+        "https://gitlab.com/api/v4/projects/houseabsolute%2Fubi/releases/permalink/latest" ;
+        "default base"
+    )]
+    #[test_case(
+        "https://gitlab.example.com/api/v4",
+        None,
+        "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases/permalink/latest" ;
+        "custom base"
+    )]
+    #[test_case(
+        "https://gitlab.example.com/api/v4/",
+        None,
+        "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases/permalink/latest" ;
+        "custom base with trailing slash"
+    )]
+    #[test_case(
+        "https://gitlab.example.com/api/v4",
+        Some("v1.0.0"),
+        "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases/v1.0.0" ;
+        "custom base with tag"
+    )]
+    fn api_base_url(api_base_url: &str, tag: Option<&str>, expect: &str) -> Result<()> {
+        let gitlab = GitLab::new(
+            "houseabsolute/ubi".to_string(),
+            tag.map(String::from),
+            Url::parse(api_base_url)?,
+            None,
+            RetryPolicy::default(),
+        );
+        let url = gitlab.release_info_url();
+        assert_eq!(url.as_str(), expect);
+
+        Ok(())
+    }
+
+    #[test_case(
+        "https://gitlab.com",
+        "https://gitlab.com/api/v4/projects/houseabsolute%2Fubi/releases" ;
+        "gitlab.com"
+    )]
+    #[test_case(
+        "https://gitlab.example.com",
+        "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases" ;
+        "custom host"
+    )]
+    #[test_case(
+        "https://gitlab.example.com/",
+        "https://gitlab.example.com/api/v4/projects/houseabsolute%2Fubi/releases" ;
+        "custom host with trailing slash"
+    )]
+    #[test_case(
+        "https://example.com/gitlab",
+        "https://example.com/gitlab/api/v4/projects/houseabsolute%2Fubi/releases" ;
+        "custom host with path"
+    )]
+    fn api_base_url_for_instance(instance_url: &str, expect: &str) -> Result<()> {
+        let gitlab = GitLab::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            super::api_base_url_for_instance(&Url::parse(instance_url)?),
+            None,
+            RetryPolicy::default(),
+        );
+        assert_eq!(gitlab.releases_url().as_str(), expect);
+
+        Ok(())
     }
 }
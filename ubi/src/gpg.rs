@@ -0,0 +1,722 @@
+use crate::ubi::Asset;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use itertools::Itertools;
+use log::debug;
+use ring::{
+    digest,
+    signature::{self, RsaPublicKeyComponents, UnparsedPublicKey},
+};
+use std::{fs, path::Path};
+
+// `gpg --detach-sign --armor` creates `<file>.asc`, while `gpg --detach-sign` creates a binary
+// `<file>.sig`. We look for them in that order.
+const SIGNATURE_EXTENSIONS: &[&str] = &[".asc", ".sig"];
+
+const SIGNATURE_TAG: u8 = 2;
+const PUBLIC_KEY_TAG: u8 = 6;
+const PUBLIC_SUBKEY_TAG: u8 = 14;
+
+const BINARY_DOCUMENT_SIGNATURE: u8 = 0x00;
+
+const RSA_ALGO: u8 = 1;
+const RSA_SIGN_ONLY_ALGO: u8 = 3;
+const EDDSA_LEGACY_ALGO: u8 = 22;
+const ED25519_ALGO: u8 = 27;
+
+const ISSUER_SUBPACKET: u8 = 16;
+const ISSUER_FINGERPRINT_SUBPACKET: u8 = 33;
+
+// This is the OID for Ed25519 used by `EdDSA` keys in the format that GnuPG creates.
+const ED25519_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+#[derive(Debug)]
+pub(crate) struct GpgVerifier {
+    keys: Vec<PublicKey>,
+}
+
+#[derive(Debug)]
+struct PublicKey {
+    fingerprint: Vec<u8>,
+    material: KeyMaterial,
+}
+
+#[derive(Debug)]
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct Signature<'a> {
+    public_key_algo: u8,
+    hash_algo: u8,
+    // This is everything from the version up to the end of the hashed subpackets, which is what
+    // gets hashed along with the signed data.
+    hashed: &'a [u8],
+    hash_prefix: &'a [u8],
+    issuer_fingerprint: Option<&'a [u8]>,
+    issuer_key_id: Option<&'a [u8]>,
+    values: SignatureValues<'a>,
+}
+
+#[derive(Debug)]
+enum SignatureValues<'a> {
+    Rsa(&'a [u8]),
+    EdDsa { r: &'a [u8], s: &'a [u8] },
+}
+
+impl GpgVerifier {
+    // Each public key can either be given as an armored key block, like the output of `gpg
+    // --armor --export`, or as a path to a file containing an armored or binary key block. A single
+    // key block may contain more than one key, and we use subkeys as well as primary keys.
+    pub(crate) fn new(public_keys: &[&str]) -> Result<Self> {
+        let mut keys = vec![];
+        for public_key in public_keys {
+            let path = Path::new(public_key);
+            let (data, source) = if path.is_file() {
+                debug!("reading GPG public key from {}", path.display());
+                let data = fs::read(path).with_context(|| {
+                    format!("could not read a GPG public key from {}", path.display())
+                })?;
+                (data, path.display().to_string())
+            } else {
+                (public_key.as_bytes().to_vec(), "given inline".to_string())
+            };
+
+            let mut found = parse_public_keys(&dearmor(&data)?)
+                .with_context(|| format!("could not parse the GPG public key {source}"))?;
+            if found.is_empty() {
+                return Err(anyhow!(
+                    "the GPG public key {source} does not contain any RSA or Ed25519 keys",
+                ));
+            }
+            for key in &found {
+                debug!("loaded GPG public key {}", hex(&key.fingerprint));
+            }
+            keys.append(&mut found);
+        }
+
+        Ok(Self { keys })
+    }
+
+    pub(crate) fn signature_asset_for(asset: &Asset, assets: &[Asset]) -> Result<Asset> {
+        for ext in SIGNATURE_EXTENSIONS {
+            let name = format!("{}{ext}", asset.name);
+            debug!("looking for a GPG signature asset named {name}");
+            if let Some(a) = assets.iter().find(|a| a.name == name) {
+                return Ok(a.clone());
+            }
+        }
+
+        Err(anyhow!(
+            "could not find a GPG signature for {} (expected an asset named {})",
+            asset.name,
+            SIGNATURE_EXTENSIONS
+                .iter()
+                .map(|ext| format!("{}{ext}", asset.name))
+                .join(" or "),
+        ))
+    }
+
+    // When we're given a URL instead of a project, there's no list of release assets to look in,
+    // so we assume an armored signature is next to the asset.
+    pub(crate) fn signature_asset_for_url(asset: &Asset) -> Asset {
+        let ext = SIGNATURE_EXTENSIONS[0];
+        let mut url = asset.url.clone();
+        url.set_path(&format!("{}{ext}", asset.url.path()));
+        Asset {
+            name: format!("{}{ext}", asset.name),
+            url,
+            size: None,
+        }
+    }
+
+    pub(crate) fn verify(&self, file: &Path, signature: &[u8]) -> Result<()> {
+        let data = fs::read(file)
+            .with_context(|| format!("could not read {} to verify it", file.display()))?;
+        self.verify_bytes(&file.display().to_string(), &data, signature)
+    }
+
+    // The name is only used in messages, so it can be a path or an asset name.
+    pub(crate) fn verify_bytes(&self, name: &str, data: &[u8], signature: &[u8]) -> Result<()> {
+        debug!("verifying GPG signature for {name}");
+
+        let signature_data = dearmor(signature).context("could not parse the GPG signature")?;
+        let signatures =
+            parse_signatures(&signature_data).context("could not parse the GPG signature")?;
+
+        let mut found_key = false;
+        for sig in &signatures {
+            for key in self.keys.iter().filter(|k| sig.may_be_from(k)) {
+                found_key = true;
+                match key.verify(data, sig) {
+                    Ok(()) => {
+                        debug!(
+                            "GPG signature is valid, made by key {}",
+                            hex(&key.fingerprint),
+                        );
+                        return Ok(());
+                    }
+                    Err(e) => debug!(
+                        "GPG signature is not valid for key {}: {e}",
+                        hex(&key.fingerprint),
+                    ),
+                }
+            }
+        }
+
+        let reason = if found_key {
+            "the signature does not match the file"
+        } else {
+            "the signature was not made by any of the given public keys"
+        };
+        Err(anyhow!(
+            "GPG signature verification failed for {name}: {reason}"
+        ))
+    }
+}
+
+impl PublicKey {
+    fn key_id(&self) -> &[u8] {
+        &self.fingerprint[self.fingerprint.len() - 8..]
+    }
+
+    fn verify(&self, data: &[u8], sig: &Signature<'_>) -> Result<()> {
+        let (digest_algo, rsa_params) = match sig.hash_algo {
+            8 => (&digest::SHA256, &signature::RSA_PKCS1_2048_8192_SHA256),
+            9 => (&digest::SHA384, &signature::RSA_PKCS1_2048_8192_SHA384),
+            10 => (&digest::SHA512, &signature::RSA_PKCS1_2048_8192_SHA512),
+            n => {
+                return Err(anyhow!(
+                    "the signature uses an unsupported hash algorithm ({n})"
+                ))
+            }
+        };
+
+        // The signed message is the data followed by the hashed part of the signature packet and
+        // a trailer with the length of that part.
+        let hashed_len = u32::try_from(sig.hashed.len())?;
+        let mut message = Vec::with_capacity(data.len() + sig.hashed.len() + 6);
+        message.extend_from_slice(data);
+        message.extend_from_slice(sig.hashed);
+        message.extend_from_slice(&[0x04, 0xff]);
+        message.extend_from_slice(&hashed_len.to_be_bytes());
+
+        let digest = digest::digest(digest_algo, &message);
+        if digest.as_ref()[..2] != *sig.hash_prefix {
+            return Err(anyhow!("the hash of the file does not match the signature"));
+        }
+
+        match (&self.material, &sig.values) {
+            (KeyMaterial::Rsa { n, e }, SignatureValues::Rsa(s)) => {
+                // MPIs have their leading zeroes stripped, but the signature must be the same
+                // length as the modulus.
+                RsaPublicKeyComponents { n, e }
+                    .verify(rsa_params, &message, &left_pad(s, n.len())?)
+                    .map_err(|_| anyhow!("the RSA signature is not valid"))
+            }
+            (KeyMaterial::Ed25519(q), SignatureValues::EdDsa { r, s }) => {
+                // Unlike RSA, EdDSA signs the digest rather than the message itself.
+                let mut rs = left_pad(r, 32)?;
+                rs.append(&mut left_pad(s, 32)?);
+                UnparsedPublicKey::new(&signature::ED25519, q)
+                    .verify(digest.as_ref(), &rs)
+                    .map_err(|_| anyhow!("the Ed25519 signature is not valid"))
+            }
+            _ => Err(anyhow!(
+                "the signature algorithm ({}) does not match the public key",
+                sig.public_key_algo,
+            )),
+        }
+    }
+}
+
+impl Signature<'_> {
+    // If the signature says which key made it, we only try that key. Otherwise we try all of them.
+    fn may_be_from(&self, key: &PublicKey) -> bool {
+        if let Some(fingerprint) = self.issuer_fingerprint {
+            return fingerprint == key.fingerprint;
+        }
+        if let Some(key_id) = self.issuer_key_id {
+            return key_id == key.key_id();
+        }
+        true
+    }
+}
+
+fn parse_public_keys(data: &[u8]) -> Result<Vec<PublicKey>> {
+    let mut keys = vec![];
+    for (tag, body) in packets(data)? {
+        if tag != PUBLIC_KEY_TAG && tag != PUBLIC_SUBKEY_TAG {
+            continue;
+        }
+        if let Some(key) = parse_public_key(body)? {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+// Keys with a version or algorithm that we don't support are skipped rather than treated as an
+// error, since a key block often has subkeys, like encryption keys, that we don't need.
+fn parse_public_key(body: &[u8]) -> Result<Option<PublicKey>> {
+    let mut r = Reader(body);
+    let version = r.u8()?;
+    if version != 4 {
+        debug!("skipping version {version} GPG public key");
+        return Ok(None);
+    }
+    // This is the creation time.
+    r.take(4)?;
+    let algo = r.u8()?;
+    let material = match algo {
+        RSA_ALGO | RSA_SIGN_ONLY_ALGO => {
+            let n = r.mpi()?.to_vec();
+            let e = r.mpi()?.to_vec();
+            KeyMaterial::Rsa { n, e }
+        }
+        EDDSA_LEGACY_ALGO => {
+            let oid_len = usize::from(r.u8()?);
+            let oid = r.take(oid_len)?;
+            if oid != ED25519_OID {
+                debug!("skipping EdDSA GPG public key with an unsupported curve");
+                return Ok(None);
+            }
+            // The point is prefixed with 0x40 to mark it as a native point encoding.
+            match r.mpi()? {
+                [0x40, q @ ..] if q.len() == 32 => KeyMaterial::Ed25519(q.to_vec()),
+                _ => return Err(anyhow!("the Ed25519 public key is not valid")),
+            }
+        }
+        ED25519_ALGO => KeyMaterial::Ed25519(r.take(32)?.to_vec()),
+        _ => {
+            debug!("skipping GPG public key with unsupported algorithm {algo}");
+            return Ok(None);
+        }
+    };
+
+    let body_len = u16::try_from(body.len())?;
+    let mut ctx = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+    ctx.update(&[0x99]);
+    ctx.update(&body_len.to_be_bytes());
+    ctx.update(body);
+
+    Ok(Some(PublicKey {
+        fingerprint: ctx.finish().as_ref().to_vec(),
+        material,
+    }))
+}
+
+fn parse_signatures(data: &[u8]) -> Result<Vec<Signature<'_>>> {
+    let signatures = packets(data)?
+        .into_iter()
+        .filter(|(tag, _)| *tag == SIGNATURE_TAG)
+        .map(|(_, body)| parse_signature(body))
+        .collect::<Result<Vec<_>>>()?;
+    if signatures.is_empty() {
+        return Err(anyhow!("it does not contain a signature packet"));
+    }
+    Ok(signatures)
+}
+
+fn parse_signature(body: &[u8]) -> Result<Signature<'_>> {
+    let mut r = Reader(body);
+    let version = r.u8()?;
+    if version != 4 {
+        return Err(anyhow!(
+            "only version 4 signatures are supported, but this is version {version}",
+        ));
+    }
+    let signature_type = r.u8()?;
+    if signature_type != BINARY_DOCUMENT_SIGNATURE {
+        return Err(anyhow!(
+            "only binary document signatures are supported, but this signature has type {signature_type:#04x}",
+        ));
+    }
+    let public_key_algo = r.u8()?;
+    let hash_algo = r.u8()?;
+    let hashed_subpackets_len = usize::from(r.u16()?);
+    let hashed_subpackets = r.take(hashed_subpackets_len)?;
+    let hashed = &body[..6 + hashed_subpackets_len];
+    let unhashed_subpackets_len = usize::from(r.u16()?);
+    let unhashed_subpackets = r.take(unhashed_subpackets_len)?;
+    let hash_prefix = r.take(2)?;
+
+    let mut issuer_fingerprint = None;
+    let mut issuer_key_id = None;
+    for (subpacket_type, data) in subpackets(hashed_subpackets)?
+        .into_iter()
+        .chain(subpackets(unhashed_subpackets)?)
+    {
+        match (subpacket_type, data) {
+            (ISSUER_FINGERPRINT_SUBPACKET, [4, fingerprint @ ..]) => {
+                issuer_fingerprint = Some(fingerprint);
+            }
+            (ISSUER_SUBPACKET, key_id) => issuer_key_id = Some(key_id),
+            _ => (),
+        }
+    }
+
+    let values = match public_key_algo {
+        RSA_ALGO | RSA_SIGN_ONLY_ALGO => SignatureValues::Rsa(r.mpi()?),
+        EDDSA_LEGACY_ALGO => SignatureValues::EdDsa {
+            r: r.mpi()?,
+            s: r.mpi()?,
+        },
+        ED25519_ALGO => {
+            let rs = r.take(64)?;
+            SignatureValues::EdDsa {
+                r: &rs[..32],
+                s: &rs[32..],
+            }
+        }
+        n => {
+            return Err(anyhow!(
+                "the signature uses an unsupported public key algorithm ({n})",
+            ))
+        }
+    };
+
+    Ok(Signature {
+        public_key_algo,
+        hash_algo,
+        hashed,
+        hash_prefix,
+        issuer_fingerprint,
+        issuer_key_id,
+        values,
+    })
+}
+
+// Returns the tag and body of each packet. This handles both the old and new packet header
+// formats, but not partial body lengths, which are only used for streamed data.
+fn packets(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut r = Reader(data);
+    let mut packets = vec![];
+    while !r.is_empty() {
+        let ctb = r.u8()?;
+        if ctb & 0x80 == 0 {
+            return Err(anyhow!("this is not OpenPGP data"));
+        }
+        let (tag, len) = if ctb & 0x40 == 0 {
+            let tag = (ctb >> 2) & 0x0f;
+            let len = match ctb & 0x03 {
+                0 => usize::from(r.u8()?),
+                1 => usize::from(r.u16()?),
+                2 => usize::try_from(r.u32()?)?,
+                _ => r.0.len(),
+            };
+            (tag, len)
+        } else {
+            let tag = ctb & 0x3f;
+            let len = match r.u8()? {
+                o @ 0..=191 => usize::from(o),
+                o @ 192..=223 => (usize::from(o - 192) << 8) + usize::from(r.u8()?) + 192,
+                255 => usize::try_from(r.u32()?)?,
+                _ => return Err(anyhow!("partial body lengths are not supported")),
+            };
+            (tag, len)
+        };
+        packets.push((tag, r.take(len)?));
+    }
+    Ok(packets)
+}
+
+fn subpackets(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut r = Reader(data);
+    let mut subpackets = vec![];
+    while !r.is_empty() {
+        let len = match r.u8()? {
+            o @ 0..=191 => usize::from(o),
+            o @ 192..=254 => (usize::from(o - 192) << 8) + usize::from(r.u8()?) + 192,
+            255 => usize::try_from(r.u32()?)?,
+        };
+        let body = r.take(len)?;
+        let Some((subpacket_type, rest)) = body.split_first() else {
+            return Err(anyhow!("the signature contains an empty subpacket"));
+        };
+        // The high bit marks the subpacket as critical.
+        subpackets.push((subpacket_type & 0x7f, rest));
+    }
+    Ok(subpackets)
+}
+
+// Armored data is base64 between the `BEGIN` and `END` lines, optionally preceded by headers like
+// `Comment: ...` and followed by a CRC-24 checksum line starting with `=`. Data that isn't armored
+// is returned as-is, so binary keys and `.sig` files work too.
+fn dearmor(data: &[u8]) -> Result<Vec<u8>> {
+    let Some(text) = std::str::from_utf8(data)
+        .ok()
+        .filter(|t| t.trim_start().starts_with("-----BEGIN PGP "))
+    else {
+        return Ok(data.to_vec());
+    };
+
+    let mut lines = text.trim_start().lines().map(str::trim).skip(1);
+    let mut armored = vec![];
+    let mut found_end = false;
+    for line in lines.by_ref() {
+        if line.starts_with("-----END PGP ") {
+            found_end = true;
+            break;
+        }
+        armored.push(line);
+    }
+    if !found_end {
+        return Err(anyhow!("the armored data has no END line"));
+    }
+
+    // Headers, if there are any, end at the first blank line.
+    let body_start = armored
+        .iter()
+        .position(|l| l.is_empty())
+        .filter(|i| armored[..*i].iter().all(|l| l.contains(": ")))
+        .map_or(0, |i| i + 1);
+    let mut base64 = String::new();
+    let mut checksum = None;
+    for line in &armored[body_start..] {
+        if let Some(c) = line.strip_prefix('=') {
+            checksum = Some(c);
+        } else {
+            base64.push_str(line);
+        }
+    }
+
+    let decoded = STANDARD
+        .decode(&base64)
+        .context("the armored data is not valid base64")?;
+    if let Some(checksum) = checksum {
+        let expect = STANDARD
+            .decode(checksum)
+            .context("the armored data checksum is not valid base64")?;
+        if crc24(&decoded).to_be_bytes()[1..] != expect[..] {
+            return Err(anyhow!("the armored data checksum does not match"));
+        }
+    }
+
+    Ok(decoded)
+}
+
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = 0x00b7_04ce_u32;
+    for b in data {
+        crc ^= u32::from(*b) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= 0x0186_4cfb;
+            }
+        }
+    }
+    crc & 0x00ff_ffff
+}
+
+fn left_pad(value: &[u8], len: usize) -> Result<Vec<u8>> {
+    if value.len() > len {
+        return Err(anyhow!("the signature is longer than expected"));
+    }
+    let mut padded = vec![0; len - value.len()];
+    padded.extend_from_slice(value);
+    Ok(padded)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).join("")
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(anyhow!("the OpenPGP data is truncated"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    // An MPI is a two byte count of bits followed by the big-endian bytes of the number.
+    fn mpi(&mut self) -> Result<&'a [u8]> {
+        let bits = usize::from(self.u16()?);
+        self.take(bits.div_ceil(8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+    use url::Url;
+
+    const PROJECT: &str = "test-data/project.tar.gz";
+
+    #[test_case("ed25519.asc", "project.tar.gz.ed25519.asc" ; "Ed25519 with armored signature")]
+    #[test_case("rsa.asc", "project.tar.gz.rsa.sig" ; "RSA with binary signature")]
+    #[test_case("subkey.asc", "project.tar.gz.subkey.asc" ; "Ed25519 signing subkey")]
+    fn verify_valid_signature(key: &str, signature: &str) -> Result<()> {
+        let verifier = GpgVerifier::new(&[&format!("test-data/gpg/{key}")])?;
+        verifier.verify(
+            Path::new(PROJECT),
+            &fs::read(format!("test-data/gpg/{signature}"))?,
+        )
+    }
+
+    #[test]
+    fn verify_with_inline_key() -> Result<()> {
+        let key = fs::read_to_string("test-data/gpg/ed25519.asc")?;
+        let verifier = GpgVerifier::new(&[&key])?;
+        verifier.verify(
+            Path::new(PROJECT),
+            &fs::read("test-data/gpg/project.tar.gz.ed25519.asc")?,
+        )
+    }
+
+    #[test]
+    fn verify_with_multiple_keys() -> Result<()> {
+        let verifier = GpgVerifier::new(&["test-data/gpg/other.asc", "test-data/gpg/rsa.asc"])?;
+        verifier.verify(
+            Path::new(PROJECT),
+            &fs::read("test-data/gpg/project.tar.gz.rsa.sig")?,
+        )
+    }
+
+    #[test_case("project.tar.gz.ed25519.asc" ; "Ed25519")]
+    #[test_case("project.tar.gz.rsa.sig" ; "RSA")]
+    fn verify_tampered_file(signature: &str) -> Result<()> {
+        let mut data = fs::read(PROJECT)?;
+        data.push(0);
+        let td = tempdir()?;
+        let tampered = td.path().join("project.tar.gz");
+        fs::write(&tampered, data)?;
+
+        let verifier = GpgVerifier::new(&["test-data/gpg/ed25519.asc", "test-data/gpg/rsa.asc"])?;
+        let res = verifier.verify(&tampered, &fs::read(format!("test-data/gpg/{signature}"))?);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            format!(
+                "GPG signature verification failed for {}: the signature does not match the file",
+                tampered.display(),
+            ),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_signature_from_other_key() -> Result<()> {
+        let verifier = GpgVerifier::new(&["test-data/gpg/other.asc"])?;
+        let res = verifier.verify(
+            Path::new(PROJECT),
+            &fs::read("test-data/gpg/project.tar.gz.ed25519.asc")?,
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            format!(
+                "GPG signature verification failed for {PROJECT}: the signature was not made by any of the given public keys",
+            ),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_invalid_signature() -> Result<()> {
+        let verifier = GpgVerifier::new(&["test-data/gpg/ed25519.asc"])?;
+        let res = verifier.verify(Path::new(PROJECT), b"not a signature");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not parse the GPG signature"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_public_key() {
+        let res = GpgVerifier::new(&["not a key"]);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not parse the GPG public key given inline",
+        );
+    }
+
+    #[test]
+    fn dearmor_with_bad_checksum() -> Result<()> {
+        let key = fs::read_to_string("test-data/gpg/ed25519.asc")?;
+        let (before, after) = key.split_once("\n=").unwrap();
+        let bad = format!("{before}\n=AAAA{}", &after[4..]);
+        assert_eq!(
+            dearmor(bad.as_bytes()).unwrap_err().to_string(),
+            "the armored data checksum does not match",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_ids() -> Result<()> {
+        let verifier = GpgVerifier::new(&["test-data/gpg/subkey.asc"])?;
+        assert_eq!(verifier.keys.len(), 2, "primary key and signing subkey");
+
+        let verifier = GpgVerifier::new(&["test-data/gpg/ed25519.asc"])?;
+        assert_eq!(
+            hex(&verifier.keys[0].fingerprint),
+            "BD33C617244F21FA822BCE1C99C7361DD3BDEEDB",
+        );
+        assert_eq!(hex(verifier.keys[0].key_id()), "99C7361DD3BDEEDB");
+
+        Ok(())
+    }
+
+    fn assets(names: &[&str]) -> Vec<Asset> {
+        let url = Url::parse("https://example.com").unwrap();
+        names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect()
+    }
+
+    #[test_case(&["project.tar.gz", "project.tar.gz.asc"], Some("project.tar.gz.asc") ; "armored")]
+    #[test_case(&["project.tar.gz", "project.tar.gz.sig"], Some("project.tar.gz.sig") ; "binary")]
+    #[test_case(
+        &["project.tar.gz", "project.tar.gz.sig", "project.tar.gz.asc"],
+        Some("project.tar.gz.asc") ;
+        "prefers armored"
+    )]
+    #[test_case(&["project.tar.gz", "project.tar.gz.minisig"], None ; "missing")]
+    fn signature_asset_for(names: &[&str], expect: Option<&str>) {
+        let assets = assets(names);
+        let res = GpgVerifier::signature_asset_for(&assets[0], &assets);
+        match expect {
+            Some(name) => assert_eq!(res.unwrap().name, name),
+            None => assert_eq!(
+                res.unwrap_err().to_string(),
+                "could not find a GPG signature for project.tar.gz (expected an asset named project.tar.gz.asc or project.tar.gz.sig)",
+            ),
+        }
+    }
+}
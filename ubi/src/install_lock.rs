@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    fs::{self, create_dir_all, File, OpenOptions, TryLockError},
+    path::{self, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+pub(crate) const DEFAULT_INSTALL_LOCK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// How long we wait between attempts to take the lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// This is an advisory lock for an install directory, which is held while we install into that
+// directory. This keeps two `ubi` processes from writing the same files, including the download
+// cache file, at the same time. The lock is released when this is dropped, which closes the file.
+//
+// The lock file is in the temp dir, not the install dir, so that we don't leave a file in a
+// directory like `~/bin`, or in the directory that an archive is unpacked into. It's named for a
+// hash of the install dir's canonical path. The lock file is never deleted, since deleting it while
+// another process is waiting to lock it would let a third process take a lock on a new file at the
+// same path.
+#[derive(Debug)]
+pub(crate) struct InstallLock {
+    _file: File,
+}
+
+impl InstallLock {
+    pub(crate) async fn acquire(dir: &Path, timeout: Duration) -> Result<Self> {
+        let path = lock_file_path(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("could not open the lock file at {}", path.display()))?;
+
+        let start = Instant::now();
+        let mut logged_wait = false;
+        while let Err(e) = file.try_lock() {
+            if let TryLockError::Error(e) = e {
+                return Err(anyhow::Error::new(e).context(format!(
+                    "could not lock the lock file at {}",
+                    path.display()
+                )));
+            }
+            if start.elapsed() >= timeout {
+                return Err(anyhow!(
+                    "timed out after {}s waiting for another ubi process to finish installing into {}",
+                    timeout.as_secs(),
+                    dir.display(),
+                ));
+            }
+            if !logged_wait {
+                debug!(
+                    "waiting for another process to release the lock on {} for {}",
+                    path.display(),
+                    dir.display(),
+                );
+                logged_wait = true;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        debug!("locked {} for {}", path.display(), dir.display());
+        Ok(Self { _file: file })
+    }
+}
+
+fn lock_file_path(dir: &Path) -> Result<PathBuf> {
+    let lock_dir = env::temp_dir().join("ubi-locks");
+    create_dir_all(&lock_dir)
+        .with_context(|| format!("could not create a directory at {}", lock_dir.display()))?;
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(canonical_path(dir)?.to_string_lossy().as_bytes())
+    );
+    Ok(lock_dir.join(format!("{hash}.lock")))
+}
+
+// The install dir may not exist yet, and we don't want to create it until something is installed
+// into it. So we canonicalize the part of the path that does exist and add the rest to that.
+fn canonical_path(dir: &Path) -> Result<PathBuf> {
+    let dir = path::absolute(dir)
+        .with_context(|| format!("could not make {} an absolute path", dir.display()))?;
+    let mut existing = dir.as_path();
+    let mut rest = vec![];
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return Ok(dir.clone()),
+        }
+    }
+
+    let mut canonical = fs::canonicalize(existing)
+        .with_context(|| format!("could not canonicalize {}", existing.display()))?;
+    canonical.extend(rest.into_iter().rev());
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn acquire_waits_for_the_lock() -> Result<()> {
+        let td = tempdir()?;
+
+        let lock = InstallLock::acquire(td.path(), Duration::from_secs(1)).await?;
+        let err = InstallLock::acquire(td.path(), Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "timed out after 1s waiting for another ubi process to finish installing into {}",
+                td.path().display(),
+            ),
+        );
+
+        drop(lock);
+        InstallLock::acquire(td.path(), Duration::from_secs(1)).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn lock_file_path() -> Result<()> {
+        let td = tempdir()?;
+        let dir = td.path().join("bin");
+
+        let path = super::lock_file_path(&dir)?;
+        assert!(!dir.exists(), "the install dir is not created");
+        assert_eq!(
+            path.parent(),
+            Some(env::temp_dir().join("ubi-locks").as_path())
+        );
+
+        fs::create_dir(&dir)?;
+        assert_eq!(
+            super::lock_file_path(&dir)?,
+            path,
+            "the lock file is the same once the dir exists",
+        );
+        assert_eq!(
+            super::lock_file_path(&dir.join("..").join("bin"))?,
+            path,
+            "the lock file is the same for a different path to the same dir",
+        );
+        assert_ne!(super::lock_file_path(td.path())?, path);
+
+        Ok(())
+    }
+}
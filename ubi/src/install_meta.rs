@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub(crate) const INSTALL_META_FILE_NAME: &str = ".ubi-meta";
+
+// The `.ubi-meta` file sits next to the installed executables and maps each executable's file name
+// to the release it was installed from. We use this for `UbiBuilder::ensure`, so that we can tell
+// whether an executable is already at the release's tag without downloading anything.
+#[derive(Debug)]
+pub(crate) struct InstallMeta {
+    path: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub(crate) struct InstalledRelease {
+    pub(crate) tag: String,
+    pub(crate) asset_name: String,
+}
+
+impl InstallMeta {
+    pub(crate) fn new(install_dir: &Path) -> Self {
+        Self {
+            path: install_dir.join(INSTALL_META_FILE_NAME),
+        }
+    }
+
+    pub(crate) fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub(crate) fn get(&self, exe: &Path) -> Result<Option<InstalledRelease>> {
+        let key = Self::key(exe);
+        let release = self.read_entries()?.remove(&key);
+        debug!(
+            "{} the installed release for {key} in {}",
+            if release.is_some() {
+                "found"
+            } else {
+                "did not find"
+            },
+            self.path.display(),
+        );
+        Ok(release)
+    }
+
+    // If we don't know the release's tag, we remove any existing entry for the executable, since
+    // it no longer describes what's installed.
+    pub(crate) fn store(&self, exe: &Path, release: Option<InstalledRelease>) -> Result<()> {
+        let mut entries = self.read_entries()?;
+        let key = Self::key(exe);
+        match release {
+            Some(release) => {
+                entries.insert(key, release);
+            }
+            None => {
+                entries.remove(&key);
+            }
+        }
+
+        debug!("writing install metadata to {}", self.path.display());
+        fs::write(&self.path, serde_json::to_string_pretty(&entries)?).with_context(|| {
+            format!(
+                "could not write the install metadata to {}",
+                self.path.display()
+            )
+        })
+    }
+
+    fn key(exe: &Path) -> String {
+        exe.file_name()
+            .map(OsStr::to_string_lossy)
+            .unwrap_or_default()
+            .into_owned()
+    }
+
+    fn read_entries(&self) -> Result<BTreeMap<String, InstalledRelease>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let contents = fs::read_to_string(&self.path).with_context(|| {
+            format!(
+                "could not read the install metadata from {}",
+                self.path.display()
+            )
+        })?;
+        // If we can't parse the file, then we don't know what's installed, so we act as if nothing
+        // is, and overwrite it after the next install.
+        Ok(serde_json::from_str(&contents).unwrap_or_else(|e| {
+            debug!(
+                "could not parse the install metadata in {}: {e}",
+                self.path.display()
+            );
+            BTreeMap::new()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use test_log::test;
+
+    fn release(tag: &str) -> InstalledRelease {
+        InstalledRelease {
+            tag: tag.to_string(),
+            asset_name: "project-Linux-x86_64.tar.gz".to_string(),
+        }
+    }
+
+    #[test]
+    fn store_and_get() -> Result<()> {
+        let td = tempdir()?;
+        let meta = InstallMeta::new(td.path());
+        let exe = td.path().join("project");
+        assert!(!meta.exists());
+        assert_eq!(meta.get(&exe)?, None);
+
+        meta.store(&exe, Some(release("v1.0.0")))?;
+        meta.store(&td.path().join("other"), Some(release("v2.0.0")))?;
+        assert!(meta.exists());
+        assert_eq!(meta.get(&exe)?, Some(release("v1.0.0")));
+
+        meta.store(&exe, Some(release("v1.1.0")))?;
+        assert_eq!(meta.get(&exe)?, Some(release("v1.1.0")));
+
+        meta.store(&exe, None)?;
+        assert_eq!(meta.get(&exe)?, None);
+        assert_eq!(
+            meta.get(&td.path().join("other"))?,
+            Some(release("v2.0.0")),
+            "other entries are kept",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unparseable_meta_file() -> Result<()> {
+        let td = tempdir()?;
+        fs::write(td.path().join(INSTALL_META_FILE_NAME), "not json")?;
+        let meta = InstallMeta::new(td.path());
+        let exe = td.path().join("project");
+        assert_eq!(meta.get(&exe)?, None);
+
+        meta.store(&exe, Some(release("v1.0.0")))?;
+        assert_eq!(meta.get(&exe)?, Some(release("v1.0.0")));
+
+        Ok(())
+    }
+}
@@ -1,45 +1,120 @@
-use crate::{extension::Extension, ubi::Download};
+use crate::{binary_arch::check_binary_arch, extension::Extension, ubi::Download};
 use anyhow::{anyhow, Context, Result};
 use binstall_tar::Archive;
+use brotli::Decompressor as BrotliDecompressor;
 use bzip2::read::BzDecoder;
 use flate2::read::GzDecoder;
+use glob::Pattern;
 use log::{debug, info};
+use platforms::Platform;
+use sevenz_rust::{Archive as SevenZArchive, Password, SevenZReader};
 use std::{
     collections::HashSet,
-    ffi::OsString,
+    ffi::{OsStr, OsString},
     fmt::Debug,
     fs::{self, create_dir_all, File},
     io::{Read, Write},
     path::{Path, PathBuf},
 };
 use strum::IntoEnumIterator;
-use xz2::read::XzDecoder;
+use tempfile::{tempdir, NamedTempFile};
+use xz2::{read::XzDecoder, stream::Stream};
 use zip::{read::ZipFile, ZipArchive};
+use zstd::stream::read::Decoder as ZstdDecoder;
 
+#[cfg(target_os = "macos")]
+use crate::dmg::MountedDmg;
 #[cfg(target_family = "unix")]
 use std::fs::{set_permissions, Permissions};
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::PermissionsExt;
 
 pub(crate) trait Installer: Debug {
-    fn install(&self, download: &Download) -> Result<()>;
+    // Returns the paths that were installed. For an `ArchiveInstaller`, this is the directory into
+    // which the archive was unpacked.
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>>;
+    // This is the path that will be installed to, which is used for dry runs. For an
+    // `ExeInstaller`, the real path may also have an extension from the downloaded file.
+    fn install_path(&self) -> &Path;
+    // This is the directory that files are installed into.
+    fn install_dir(&self) -> &Path;
+    // Returns the path of the executable if it's already installed. We can't tell whether an
+    // archive was already unpacked without looking at what's in it, so this is `None` for the other
+    // installers.
+    fn existing_install(&self) -> Option<PathBuf> {
+        None
+    }
+    // We check whether the download looks like an error page, which would be an HTML, XML, or JSON
+    // document, unless the installer copies the file as is, since that file could be any of those.
+    fn expects_binary_or_archive(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct ExeInstaller {
     install_path: PathBuf,
     exe_file_stem: String,
+    // The directories that the executable must be in, from the end of the archive entry's path. This
+    // is empty unless the exe name includes a path, like `bin/foo`.
+    exe_dir: PathBuf,
     is_windows: bool,
     extensions: Vec<&'static str>,
+    completions_dir: Option<PathBuf>,
+    verify_arch: Option<Platform>,
 }
 
 #[derive(Debug)]
 pub(crate) struct ArchiveInstaller {
     install_root: PathBuf,
+    strip_components: Option<usize>,
+}
+
+#[derive(Debug)]
+pub(crate) struct AllExesInstaller {
+    install_dir: PathBuf,
+    glob: Option<Pattern>,
+    is_windows: bool,
+}
+
+// This installs the downloaded file without extracting it or making it executable, for assets like
+// a `.wasm` module or a JSON schema.
+#[derive(Debug)]
+pub(crate) struct AsIsInstaller {
+    install_path: PathBuf,
+}
+
+// An executable that has been written to a temp file in the directory it will be installed into.
+// Once it is complete, it is renamed into place.
+#[derive(Debug)]
+struct StagedExe {
+    file: NamedTempFile,
+    install_path: PathBuf,
 }
 
+const WINDOWS_EXE_EXTENSIONS: &[&str] = &["bat", "cmd", "exe"];
+
+// This is the buffer size that the `brotli` crate's own examples use.
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+// The most archive entries to list when we can't find the executable in an archive.
+const MAX_LISTED_ARCHIVE_FILES: usize = 20;
+
 impl ExeInstaller {
-    pub(crate) fn new(install_path: PathBuf, exe: String, is_windows: bool) -> Self {
+    // The `exe` may include directories separated by `/`, like `bin/foo`, in which case only an
+    // archive entry whose path ends with those directories and file name matches. On Windows, it
+    // may also include an extension like `.exe`, which is ignored when matching.
+    //
+    // If `completions_dir` is set, shell completion files in the archive are installed under it, in
+    // a subdirectory for each shell. If `verify_arch` is set, the executable must be built for that
+    // platform's architecture.
+    pub(crate) fn new(
+        install_path: PathBuf,
+        exe: String,
+        is_windows: bool,
+        completions_dir: Option<PathBuf>,
+        verify_arch: Option<Platform>,
+    ) -> Self {
         let extensions = if is_windows {
             Extension::iter()
                 .filter(super::extension::Extension::is_windows_only)
@@ -49,51 +124,89 @@ impl ExeInstaller {
             vec![]
         };
 
+        let (exe_dir, exe_file_name) = match exe.rsplit_once('/') {
+            Some((dir, name)) => (PathBuf::from(dir), name),
+            None => (PathBuf::new(), exe.as_str()),
+        };
+        let exe_file_stem = extensions
+            .iter()
+            .find_map(|ext| {
+                let stem_len = exe_file_name.len().checked_sub(ext.len())?;
+                exe_file_name
+                    .get(stem_len..)
+                    .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+                    .then(|| &exe_file_name[..stem_len])
+            })
+            .unwrap_or(exe_file_name)
+            .to_string();
+
         ExeInstaller {
             install_path,
-            exe_file_stem: exe,
+            exe_file_stem,
+            exe_dir,
             is_windows,
             extensions,
+            completions_dir,
+            verify_arch,
         }
     }
 
-    fn extract_executable(&self, downloaded_file: &Path) -> Result<Option<PathBuf>> {
+    fn extract_executable(&self, downloaded_file: &Path) -> Result<StagedExe> {
         match Extension::from_path(downloaded_file)? {
+            Some(Extension::Tar) => self.extract_executable_from_tarball(downloaded_file),
+            // We can't trust the extension to tell us whether a compressed file contains a
+            // tarball. Some projects compress a bare executable as `.tar.gz`, and others compress
+            // a tarball as `.xz`. So we look at the decompressed contents instead.
             Some(
-                Extension::Tar
+                Extension::TarBr
                 | Extension::TarBz
                 | Extension::TarBz2
                 | Extension::TarGz
+                | Extension::TarLzma
                 | Extension::TarXz
+                | Extension::TarZst
                 | Extension::Tbz
+                | Extension::Tbz2
                 | Extension::Tgz
-                | Extension::Txz,
-            ) => Ok(Some(self.extract_executable_from_tarball(downloaded_file)?)),
-            Some(Extension::Bz | Extension::Bz2) => {
-                self.unbzip(downloaded_file)?;
-                Ok(None)
-            }
-            Some(Extension::Gz) => {
-                self.ungzip(downloaded_file)?;
-                Ok(None)
-            }
-            Some(Extension::Xz) => {
-                self.unxz(downloaded_file)?;
-                Ok(None)
-            }
-            Some(Extension::Zip) => Ok(Some(self.extract_executable_from_zip(downloaded_file)?)),
+                | Extension::Txz
+                | Extension::Tzst
+                | Extension::Br
+                | Extension::Bz
+                | Extension::Bz2
+                | Extension::Gz
+                | Extension::Lzma
+                | Extension::Xz
+                | Extension::Zst,
+            ) => self.extract_executable_from_compressed_file(downloaded_file),
+            Some(Extension::SevenZ) => self.extract_executable_from_7z(downloaded_file),
+            Some(Extension::Zip) => self.extract_executable_from_zip(downloaded_file),
+            Some(Extension::Dmg) => self.extract_executable_from_dmg(downloaded_file),
             Some(
                 Extension::AppImage
                 | Extension::Bat
                 | Extension::Exe
                 | Extension::Jar
                 | Extension::Pyz,
-            )
-            | None => Ok(Some(self.copy_executable(downloaded_file)?)),
+            ) => self.copy_executable(downloaded_file),
+            None => match Extension::from_magic_bytes(downloaded_file)? {
+                Some(Extension::SevenZ) => self.extract_executable_from_7z(downloaded_file),
+                Some(Extension::Bz2 | Extension::Lzma | Extension::Zst) => {
+                    self.extract_executable_from_compressed_file(downloaded_file)
+                }
+                _ => self.copy_executable(downloaded_file),
+            },
+        }
+    }
+
+    fn extract_executable_from_compressed_file(&self, downloaded_file: &Path) -> Result<StagedExe> {
+        if is_tarball(downloaded_file)? {
+            self.extract_executable_from_tarball(downloaded_file)
+        } else {
+            self.uncompress(downloaded_file)
         }
     }
 
-    fn extract_executable_from_tarball(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_tarball(&self, downloaded_file: &Path) -> Result<StagedExe> {
         debug!(
             "extracting executable from tarball at {}",
             downloaded_file.display(),
@@ -108,7 +221,8 @@ impl ExeInstaller {
         //
         // So the only viable solution is find the entry, then _re-open_ the file and go through the
         // entries again until we find the one we want.
-        if let Some(idx) = self.best_match_from_tarball(downloaded_file)? {
+        let mut files = vec![];
+        if let Some(idx) = self.best_match_from_tarball(downloaded_file, &mut files)? {
             let mut arch = tar_reader_for(downloaded_file)?;
             for (i, entry) in arch.entries()?.enumerate() {
                 let mut entry = entry?;
@@ -121,7 +235,7 @@ impl ExeInstaller {
                 if let Some(ext) = Extension::from_path(entry_path.as_ref())? {
                     if ext.should_preserve_extension_on_install() {
                         debug!("preserving the {} extension on install", ext.extension());
-                        install_path.set_extension(ext.extension_without_dot());
+                        install_path = add_extension(&install_path, ext.extension_without_dot());
                     }
                 }
 
@@ -130,17 +244,24 @@ impl ExeInstaller {
                     entry_path.display(),
                     install_path.display(),
                 );
-                self.create_install_dir()?;
-                entry.unpack(&install_path).unwrap();
+                let mut staged = self.stage_exe(install_path)?;
+                std::io::copy(&mut entry, &mut staged.file)?;
+                Self::apply_entry_mode(staged.file.path(), Some(entry.header().mode()?))?;
 
-                return Ok(install_path);
+                return Ok(staged);
             }
         }
 
-        self.could_not_find_archive_matches_error()
+        self.could_not_find_archive_matches_error(&files)
     }
 
-    fn best_match_from_tarball(&self, downloaded_file: &Path) -> Result<Option<usize>> {
+    // The path of every file in the archive is added to `files`, so that we can list them if there
+    // is no match.
+    fn best_match_from_tarball(
+        &self,
+        downloaded_file: &Path,
+        files: &mut Vec<String>,
+    ) -> Result<Option<usize>> {
         let mut arch = tar_reader_for(downloaded_file)?;
         let mut possible_matches: Vec<usize> = vec![];
         for (i, entry) in arch.entries()?.enumerate() {
@@ -151,6 +272,10 @@ impl ExeInstaller {
 
             let path = entry.path()?;
             debug!("found tarball entry with path {}", path.display());
+            files.push(path.to_string_lossy().to_string());
+            if !self.archive_member_is_in_exe_dir(&path) {
+                continue;
+            }
             if let Some(file_name) = path.file_name() {
                 if let Some(file_name) = file_name.to_str() {
                     if self.archive_member_is_exact_match(file_name) {
@@ -171,20 +296,21 @@ impl ExeInstaller {
         Ok(possible_matches.into_iter().next())
     }
 
-    fn extract_executable_from_zip(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_zip(&self, downloaded_file: &Path) -> Result<StagedExe> {
         debug!(
             "extracting executable from zip file at {}",
             downloaded_file.display()
         );
 
         let mut zip = ZipArchive::new(open_file(downloaded_file)?)?;
-        if let Some(mut zf) = self.best_match_from_zip_archive(&mut zip)? {
+        let mut files = vec![];
+        if let Some(mut zf) = self.best_match_from_zip_archive(&mut zip, &mut files)? {
             let zf_path = Path::new(zf.name());
             let mut install_path = self.install_path.clone();
             if let Some(ext) = Extension::from_path(zf_path)? {
                 if ext.should_preserve_extension_on_install() {
                     debug!("preserving the {} extension on install", ext.extension());
-                    install_path.set_extension(ext.extension_without_dot());
+                    install_path = add_extension(&install_path, ext.extension_without_dot());
                 }
             }
 
@@ -195,25 +321,31 @@ impl ExeInstaller {
             );
             let mut buffer: Vec<u8> = Vec::with_capacity(usize::try_from(zf.size())?);
             zf.read_to_end(&mut buffer)?;
-            self.create_install_dir()?;
 
-            File::create(&install_path)?.write_all(&buffer)?;
+            let mut staged = self.stage_exe(install_path)?;
+            staged.file.write_all(&buffer)?;
+            Self::apply_entry_mode(staged.file.path(), zf.unix_mode())?;
 
-            return Ok(install_path);
+            return Ok(staged);
         }
 
-        self.could_not_find_archive_matches_error()
+        self.could_not_find_archive_matches_error(&files)
     }
 
     fn best_match_from_zip_archive<'a>(
         &self,
         zip: &'a mut ZipArchive<File>,
+        files: &mut Vec<String>,
     ) -> Result<Option<ZipFile<'a>>> {
         let mut possible_matches: Vec<usize> = vec![];
         for i in 0..zip.len() {
             let zf = zip.by_index(i)?;
             if zf.is_file() {
                 let path = PathBuf::from(zf.name());
+                files.push(zf.name().to_string());
+                if !self.archive_member_is_in_exe_dir(&path) {
+                    continue;
+                }
                 if let Some(file_name) = path.file_name() {
                     if let Some(file_name) = file_name.to_str() {
                         if self.archive_member_is_exact_match(file_name) {
@@ -226,7 +358,7 @@ impl ExeInstaller {
                         } else if self.archive_member_is_partial_match(file_name) {
                             debug!("found zip file entry with partial match: {file_name}");
                             // Note that we don't test if the file is executable on Unix systems
-                            // because preserving the mode is not a standard Zip behavior, AFAICT.
+                            // because many zip files don't record a Unix mode for their entries.
                             possible_matches.push(i);
                         }
                     }
@@ -241,6 +373,155 @@ impl ExeInstaller {
         Ok(None)
     }
 
+    fn extract_executable_from_7z(&self, downloaded_file: &Path) -> Result<StagedExe> {
+        debug!(
+            "extracting executable from 7z file at {}",
+            downloaded_file.display()
+        );
+
+        let mut reader = SevenZReader::open(downloaded_file, Password::empty())?;
+        let mut files = vec![];
+        if let Some(name) = self.best_match_from_7z_archive(reader.archive(), &mut files) {
+            let mut install_path = self.install_path.clone();
+            if let Some(ext) = Extension::from_path(Path::new(&name))? {
+                if ext.should_preserve_extension_on_install() {
+                    debug!("preserving the {} extension on install", ext.extension());
+                    install_path = add_extension(&install_path, ext.extension_without_dot());
+                }
+            }
+
+            debug!(
+                "extracting 7z file entry named {name} to {}",
+                install_path.display(),
+            );
+            let mut staged = self.stage_exe(install_path)?;
+
+            // Entries in a 7z archive are usually compressed together in a single "solid" block,
+            // so we have to read through every entry before the one we want. Each entry's reader
+            // must be drained, otherwise the next entry will be read from the wrong position.
+            reader.for_each_entries(|entry, entry_reader| {
+                if entry.name() == name {
+                    std::io::copy(entry_reader, &mut staged.file)?;
+                    return Ok(false);
+                }
+                std::io::copy(entry_reader, &mut std::io::sink())?;
+                Ok(true)
+            })?;
+
+            return Ok(staged);
+        }
+
+        self.could_not_find_archive_matches_error(&files)
+    }
+
+    fn best_match_from_7z_archive(
+        &self,
+        arch: &SevenZArchive,
+        files: &mut Vec<String>,
+    ) -> Option<String> {
+        let mut possible_matches: Vec<&str> = vec![];
+        for entry in &arch.files {
+            if entry.is_directory() || !entry.has_stream() {
+                continue;
+            }
+
+            debug!("found 7z file entry with path {}", entry.name());
+            files.push(entry.name().to_string());
+            if !self.archive_member_is_in_exe_dir(Path::new(entry.name())) {
+                continue;
+            }
+            if let Some(file_name) = Path::new(entry.name()).file_name() {
+                if let Some(file_name) = file_name.to_str() {
+                    if self.archive_member_is_exact_match(file_name) {
+                        debug!("found 7z file entry with exact match: {file_name}");
+                        return Some(entry.name().to_string());
+                    } else if self.archive_member_is_partial_match(file_name) {
+                        // Like zip files, 7z archives don't reliably record Unix file modes, so we
+                        // don't check if the entry is executable.
+                        debug!("found 7z file entry with partial match: {file_name}");
+                        possible_matches.push(entry.name());
+                    }
+                }
+            }
+        }
+
+        possible_matches.first().map(|n| (*n).to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn extract_executable_from_dmg(&self, downloaded_file: &Path) -> Result<StagedExe> {
+        debug!(
+            "extracting executable from dmg file at {}",
+            downloaded_file.display()
+        );
+
+        // The image is detached when `dmg` is dropped, even if copying the executable fails.
+        let dmg = MountedDmg::attach(downloaded_file)?;
+        let files = dmg.files()?;
+
+        let mut possible_matches: Vec<&Path> = vec![];
+        for file in &files {
+            let Some(file_name) = file.file_name().and_then(OsStr::to_str) else {
+                continue;
+            };
+            debug!("found dmg file entry with path {}", file.display());
+            if !self.archive_member_is_in_exe_dir(file.strip_prefix(dmg.path()).unwrap_or(file)) {
+                continue;
+            }
+            if self.archive_member_is_exact_match(file_name) {
+                debug!("found dmg file entry with exact match: {file_name}");
+                return self.copy_executable_from_dmg(file);
+            } else if self.archive_member_is_partial_match(file_name)
+                && Self::is_executable_file(file)?
+            {
+                debug!("found dmg file entry with partial match: {file_name}");
+                possible_matches.push(file);
+            }
+        }
+
+        if let Some(file) = possible_matches.first() {
+            return self.copy_executable_from_dmg(file);
+        }
+
+        let files = files
+            .iter()
+            .map(|f| {
+                f.strip_prefix(dmg.path())
+                    .unwrap_or(f)
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        self.could_not_find_archive_matches_error(&files)
+    }
+
+    // Files in a dmg keep their Unix mode, so unlike a zip or 7z file, we can check whether a
+    // partial match is executable.
+    #[cfg(target_os = "macos")]
+    fn is_executable_file(path: &Path) -> Result<bool> {
+        Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn copy_executable_from_dmg(&self, file: &Path) -> Result<StagedExe> {
+        let staged = self.copy_executable(file)?;
+        Self::apply_entry_mode(
+            staged.file.path(),
+            Some(fs::metadata(file)?.permissions().mode()),
+        )?;
+        Ok(staged)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn extract_executable_from_dmg(&self, downloaded_file: &Path) -> Result<StagedExe> {
+        Err(dmg_not_supported_error(downloaded_file))
+    }
+
+    fn archive_member_is_in_exe_dir(&self, path: &Path) -> bool {
+        self.exe_dir.as_os_str().is_empty()
+            || path.parent().is_some_and(|p| p.ends_with(&self.exe_dir))
+    }
+
     fn archive_member_is_exact_match(&self, file_name: &str) -> bool {
         if self.extensions.is_empty() {
             return file_name == self.exe_file_stem;
@@ -264,68 +545,102 @@ impl ExeInstaller {
             .any(|&ext| file_name.to_lowercase().ends_with(ext))
     }
 
-    fn could_not_find_archive_matches_error(&self) -> Result<PathBuf> {
+    fn could_not_find_archive_matches_error(&self, files: &[String]) -> Result<StagedExe> {
+        let exe_file_stem = self.exe_dir.join(&self.exe_file_stem);
+        let exe_file_stem = exe_file_stem.to_string_lossy();
         let expect_names = if self.extensions.is_empty() {
-            format!("{}*", self.exe_file_stem)
+            format!("{exe_file_stem}*")
         } else {
             self.extensions
                 .iter()
-                .map(|ext| format!("{}*{}", self.exe_file_stem, ext))
+                .map(|ext| format!("{exe_file_stem}*{ext}"))
                 .collect::<Vec<_>>()
                 .join(" ")
         };
 
         debug!("could not find any entries matching [{expect_names}]");
+        let contents = if files.is_empty() {
+            String::from("it does not contain any files")
+        } else {
+            let mut listed = files[..files.len().min(MAX_LISTED_ARCHIVE_FILES)].join(", ");
+            if files.len() > MAX_LISTED_ARCHIVE_FILES {
+                listed.push_str(&format!(
+                    ", and {} more",
+                    files.len() - MAX_LISTED_ARCHIVE_FILES
+                ));
+            }
+            format!("it contains {listed}")
+        };
         Err(anyhow!(
-            "could not find any files matching [{}] in the downloaded archive file",
-            expect_names,
+            "could not find any files matching [{expect_names}] in the downloaded archive file, {contents}",
         ))
     }
 
-    fn unbzip(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from bzip file");
-        let reader = BzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
-    }
-
-    fn ungzip(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from gzip file");
-        let reader = GzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+    fn uncompress(&self, downloaded_file: &Path) -> Result<StagedExe> {
+        debug!(
+            "uncompressing executable from {}",
+            downloaded_file.display()
+        );
+        let reader = decompressed_reader_for(downloaded_file)?;
+        self.write_to_install_path(reader, self.uncompressed_install_path(downloaded_file))
     }
 
-    fn unxz(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from xz file");
-        let reader = XzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+    // The install path keeps any extension that the file has once its compression suffix is
+    // removed, so `project.pyz.gz` is installed as `project.pyz`.
+    fn uncompressed_install_path(&self, downloaded_file: &Path) -> PathBuf {
+        let Some(stem) = downloaded_file.file_stem() else {
+            return self.install_path.clone();
+        };
+        match Extension::from_path(Path::new(stem)) {
+            Ok(Some(ext)) if ext.should_preserve_extension_on_install() => {
+                debug!("preserving the {} extension on install", ext.extension());
+                add_extension(&self.install_path, ext.extension_without_dot())
+            }
+            _ => self.install_path.clone(),
+        }
     }
 
-    fn write_to_install_path(&self, mut reader: impl Read) -> Result<()> {
-        self.create_install_dir()?;
-        let mut writer = File::create(&self.install_path)
-            .with_context(|| format!("Cannot write to {}", self.install_path.display()))?;
-        std::io::copy(&mut reader, &mut writer)?;
-        Ok(())
+    fn write_to_install_path(
+        &self,
+        mut reader: impl Read,
+        install_path: PathBuf,
+    ) -> Result<StagedExe> {
+        let mut staged = self.stage_exe(install_path)?;
+        std::io::copy(&mut reader, &mut staged.file).with_context(|| {
+            format!(
+                "could not write the executable for {}",
+                staged.install_path.display()
+            )
+        })?;
+        Ok(staged)
     }
 
-    fn copy_executable(&self, exe_file: &Path) -> Result<PathBuf> {
+    fn copy_executable(&self, exe_file: &Path) -> Result<StagedExe> {
         debug!("copying executable to final location");
-        self.create_install_dir()?;
 
         let mut install_path = self.install_path.clone();
         if let Some(ext) = Extension::from_path(exe_file)? {
             if ext.should_preserve_extension_on_install() {
                 debug!("preserving the {} extension on install", ext.extension());
-                install_path.set_extension(ext.extension_without_dot());
+                install_path = add_extension(&install_path, ext.extension_without_dot());
             }
         }
-        std::fs::copy(exe_file, &install_path).context(format!(
+        let mut staged = self.stage_exe(install_path)?;
+        std::io::copy(&mut open_file(exe_file)?, &mut staged.file).context(format!(
             "error copying file from {} to {}",
             exe_file.display(),
-            install_path.display()
+            staged.install_path.display()
         ))?;
 
-        Ok(install_path)
+        Ok(staged)
+    }
+
+    fn stage_exe(&self, install_path: PathBuf) -> Result<StagedExe> {
+        self.create_install_dir()?;
+        Ok(StagedExe {
+            file: staged_file_for(&install_path)?,
+            install_path,
+        })
     }
 
     fn create_install_dir(&self) -> Result<()> {
@@ -341,13 +656,35 @@ impl ExeInstaller {
             .with_context(|| format!("could not create a directory at {}", path.display()))
     }
 
+    #[cfg(target_family = "windows")]
+    fn apply_entry_mode(_path: &Path, _mode: Option<u32>) -> Result<()> {
+        Ok(())
+    }
+
+    // Tarballs and zip files created on Unix store the file's mode in each entry. We only keep the
+    // permission bits, so a setuid bit in a downloaded archive is never applied.
+    #[cfg(target_family = "unix")]
+    fn apply_entry_mode(path: &Path, mode: Option<u32>) -> Result<()> {
+        let Some(mode) = mode.map(|m| m & 0o777).filter(|m| *m != 0) else {
+            return Ok(());
+        };
+        debug!("setting mode of {} to {mode:o}", path.display());
+        set_permissions(path, Permissions::from_mode(mode))
+            .with_context(|| format!("could not set the mode of {}", path.display()))
+    }
+
     #[cfg(target_family = "windows")]
     fn chmod_executable(_exe: &Path) -> Result<()> {
         Ok(())
     }
 
+    // If the executable already has a mode with an executable bit, like one from a tarball or zip
+    // file, then we leave it alone. Otherwise we make it executable.
     #[cfg(target_family = "unix")]
     fn chmod_executable(exe: &Path) -> Result<()> {
+        if fs::metadata(exe)?.permissions().mode() & 0o111 != 0 {
+            return Ok(());
+        }
         match set_permissions(exe, Permissions::from_mode(0o755)) {
             Ok(()) => Ok(()),
             Err(e) => Err(anyhow::Error::new(e)),
@@ -356,36 +693,148 @@ impl ExeInstaller {
 }
 
 impl Installer for ExeInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
-        let exe = self.extract_executable(&download.archive_path)?;
-        let real_exe = exe.as_deref().unwrap_or(&self.install_path);
-        Self::chmod_executable(real_exe)?;
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>> {
+        let StagedExe {
+            file,
+            install_path: mut real_exe,
+        } = self.extract_executable(&download.archive_path)?;
+        // Windows won't run a file without an extension, which we end up with when we decompress a
+        // bare executable or when the user renames the executable to a name without an extension.
+        if self.is_windows && real_exe.extension().is_none() {
+            let with_exe = add_extension(&real_exe, "exe");
+            debug!(
+                "installing to {} instead of {} so that it can be run on Windows",
+                with_exe.display(),
+                real_exe.display(),
+            );
+            real_exe = with_exe;
+        }
+        if let Some(platform) = &self.verify_arch {
+            check_binary_arch(
+                file.path(),
+                &real_exe.file_name().unwrap_or_default().to_string_lossy(),
+                platform,
+            )?;
+        }
+        Self::chmod_executable(file.path())?;
+        move_into_place(file, &real_exe, self.is_windows)?;
         info!("Installed executable into {}", real_exe.display());
 
-        Ok(())
+        let mut installed = vec![real_exe];
+        if let Some(dir) = &self.completions_dir {
+            installed.extend(install_completions(&download.archive_path, dir)?);
+        }
+
+        Ok(installed)
+    }
+
+    fn install_path(&self) -> &Path {
+        &self.install_path
+    }
+
+    fn install_dir(&self) -> &Path {
+        self.install_path
+            .parent()
+            .expect("the install path for an exe always has a parent")
+    }
+
+    // The executable may have been installed with an extension from the downloaded file, like
+    // `.exe` or `.pyz`, so we check for each of those too.
+    fn existing_install(&self) -> Option<PathBuf> {
+        if self.install_path.is_file() {
+            return Some(self.install_path.clone());
+        }
+        Extension::iter()
+            .filter(Extension::should_preserve_extension_on_install)
+            .map(|ext| add_extension(&self.install_path, ext.extension_without_dot()))
+            .find(|path| path.is_file())
     }
 }
 
 impl ArchiveInstaller {
-    pub(crate) fn new(install_path: PathBuf) -> Self {
+    // If `strip_components` is `None`, then a single top-level directory shared by every entry in
+    // the archive is removed. Otherwise, exactly that many leading path components are removed from
+    // each entry, like tar's `--strip-components`.
+    pub(crate) fn new(install_path: PathBuf, strip_components: Option<usize>) -> Self {
         ArchiveInstaller {
             install_root: install_path,
+            strip_components,
         }
     }
 
     fn extract_entire_archive(&self, downloaded_file: &Path) -> Result<()> {
+        let Some(strip_components) = self.strip_components else {
+            Self::unpack_archive(downloaded_file, &self.install_root)?;
+
+            if self.should_move_up_one_dir()? {
+                Self::move_contents_up_one_dir(&self.install_root)?;
+            } else {
+                debug!("extracted archive did not contain a common top-level directory");
+            }
+
+            return Ok(());
+        };
+
+        // We unpack into a temp dir next to the install root so that the stripped entries can be
+        // renamed into place.
+        create_dir_all(&self.install_root).with_context(|| {
+            format!(
+                "could not create a directory at {}",
+                self.install_root.display(),
+            )
+        })?;
+        let td = match self.install_root.parent() {
+            Some(parent) => tempfile::Builder::new()
+                .prefix(".ubi-")
+                .tempdir_in(parent)
+                .with_context(|| format!("could not create a temp dir in {}", parent.display()))?,
+            None => tempdir()?,
+        };
+        Self::unpack_archive(downloaded_file, td.path())?;
+
+        debug!(
+            "stripping {strip_components} leading path component{} from the extracted archive",
+            if strip_components == 1 { "" } else { "s" },
+        );
+        Self::move_stripped_entries(td.path(), td.path(), &self.install_root, strip_components)
+    }
+
+    fn unpack_archive(downloaded_file: &Path, dest: &Path) -> Result<()> {
         match Extension::from_path(downloaded_file)? {
             Some(
                 Extension::Tar
+                | Extension::TarBr
                 | Extension::TarBz
                 | Extension::TarBz2
                 | Extension::TarGz
+                | Extension::TarLzma
                 | Extension::TarXz
+                | Extension::TarZst
                 | Extension::Tbz
+                | Extension::Tbz2
                 | Extension::Tgz
-                | Extension::Txz,
-            ) => self.extract_entire_tarball(downloaded_file)?,
-            Some(Extension::Zip) => self.extract_entire_zip(downloaded_file)?,
+                | Extension::Txz
+                | Extension::Tzst,
+            ) => Self::extract_entire_tarball(downloaded_file, dest)?,
+            Some(Extension::SevenZ) => Self::extract_entire_7z(downloaded_file, dest)?,
+            Some(Extension::Zip) => Self::extract_entire_zip(downloaded_file, dest)?,
+            #[cfg(target_os = "macos")]
+            Some(Extension::Dmg) => {
+                return Err(anyhow!(
+                    "the downloaded release asset, {}, is a dmg file, and extracting all of the contents of a dmg file is not supported",
+                    downloaded_file.display(),
+                ))
+            }
+            #[cfg(not(target_os = "macos"))]
+            Some(Extension::Dmg) => return Err(dmg_not_supported_error(downloaded_file)),
+            // Without an extension, we can still find out what kind of archive this is by looking
+            // at its contents.
+            None if Extension::from_magic_bytes(downloaded_file)? == Some(Extension::SevenZ) => {
+                Self::extract_entire_7z(downloaded_file, dest)?;
+            }
+            None if is_tarball(downloaded_file)? => {
+                Self::extract_entire_tarball(downloaded_file, dest)?;
+            }
             _ => {
                 return Err(anyhow!(
                     concat!(
@@ -397,20 +846,67 @@ impl ArchiveInstaller {
             }
         }
 
-        if self.should_move_up_one_dir()? {
-            Self::move_contents_up_one_dir(&self.install_root)?;
-        } else {
-            debug!("extracted archive did not contain a common top-level directory");
-        }
-
         Ok(())
     }
 
-    fn extract_entire_tarball(&self, downloaded_file: &Path) -> Result<()> {
+    fn extract_entire_tarball(downloaded_file: &Path, dest: &Path) -> Result<()> {
         debug!("extracting entire tarball at {}", downloaded_file.display(),);
 
         let mut arch = tar_reader_for(downloaded_file)?;
-        arch.unpack(&self.install_root)?;
+        arch.unpack(dest)?;
+
+        Ok(())
+    }
+
+    // Entries with no more than `strip_components` path components are skipped, as they would have
+    // nothing left after stripping.
+    fn move_stripped_entries(
+        unpack_root: &Path,
+        dir: &Path,
+        install_root: &Path,
+        strip_components: usize,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let relative = path
+                .strip_prefix(unpack_root)
+                .expect("an unpacked archive entry is always under the unpack root");
+            let is_dir = fs::symlink_metadata(&path)?.is_dir();
+
+            let stripped = relative
+                .components()
+                .skip(strip_components)
+                .collect::<PathBuf>();
+            if stripped.as_os_str().is_empty() {
+                if is_dir {
+                    Self::move_stripped_entries(
+                        unpack_root,
+                        &path,
+                        install_root,
+                        strip_components,
+                    )?;
+                } else {
+                    debug!(
+                        "skipping {} because it has no more than {strip_components} path components",
+                        relative.display(),
+                    );
+                }
+                continue;
+            }
+
+            let target = install_root.join(stripped);
+            if is_dir && target.is_dir() {
+                Self::move_stripped_entries(unpack_root, &path, install_root, strip_components)?;
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent)?;
+            }
+            fs::rename(&path, &target).with_context(|| {
+                format!("could not move {} to {}", path.display(), target.display(),)
+            })?;
+        }
 
         Ok(())
     }
@@ -484,108 +980,682 @@ impl ArchiveInstaller {
         Ok(())
     }
 
-    fn extract_entire_zip(&self, downloaded_file: &Path) -> Result<()> {
+    fn extract_entire_zip(downloaded_file: &Path, dest: &Path) -> Result<()> {
         debug!(
             "extracting entire zip file at {}",
             downloaded_file.display(),
         );
 
         let mut zip = ZipArchive::new(open_file(downloaded_file)?)?;
-        Ok(zip.extract(&self.install_root)?)
+        Ok(zip.extract(dest)?)
+    }
+
+    fn extract_entire_7z(downloaded_file: &Path, dest: &Path) -> Result<()> {
+        debug!("extracting entire 7z file at {}", downloaded_file.display());
+
+        Ok(sevenz_rust::decompress_file(downloaded_file, dest)?)
     }
 }
 
 impl Installer for ArchiveInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>> {
         self.extract_entire_archive(&download.archive_path)?;
         info!(
             "Installed contents of archive file into {}",
             self.install_root.display()
         );
 
-        Ok(())
+        Ok(vec![self.install_root.clone()])
     }
-}
 
-fn tar_reader_for(downloaded_file: &Path) -> Result<Archive<Box<dyn Read>>> {
-    let file = open_file(downloaded_file)?;
+    fn install_path(&self) -> &Path {
+        &self.install_root
+    }
 
-    let ext = downloaded_file.extension();
-    match ext {
-        Some(ext) => match ext.to_str() {
-            Some("tar") => Ok(Archive::new(Box::new(file))),
-            Some("bz" | "tbz" | "bz2" | "tbz2") => Ok(Archive::new(Box::new(BzDecoder::new(file)))),
-            Some("gz" | "tgz") => Ok(Archive::new(Box::new(GzDecoder::new(file)))),
-            Some("xz" | "txz") => Ok(Archive::new(Box::new(XzDecoder::new(file)))),
-            Some(e) => Err(anyhow!(
-                "don't know how to uncompress a tarball with extension = {}",
-                e,
-            )),
-            None => Err(anyhow!(
-                "tarball {:?} has a non-UTF-8 extension",
-                downloaded_file,
-            )),
-        },
-        None => Ok(Archive::new(Box::new(file))),
+    fn install_dir(&self) -> &Path {
+        &self.install_root
     }
 }
 
-fn open_file(path: &Path) -> Result<File> {
-    File::open(path).with_context(|| format!("Failed to open file at {}", path.display()))
-}
+impl AllExesInstaller {
+    pub(crate) fn new(install_dir: PathBuf, glob: Option<Pattern>, is_windows: bool) -> Self {
+        AllExesInstaller {
+            install_dir,
+            glob,
+            is_windows,
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(target_family = "unix")]
-    use std::os::unix::fs::PermissionsExt;
-    use tempfile::tempdir;
-    use test_case::test_case;
-    use test_log::test;
+    fn install_all_exes(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        // We unpack the whole archive into a temp dir and then look for executables there. This
+        // lets us reuse the code for each archive format from `ArchiveInstaller`, and we can check
+        // the mode of the unpacked files instead of looking at each format's headers.
+        let td = tempdir()?;
+        let unpack_root = td.path().join("archive");
+        ArchiveInstaller::new(unpack_root.clone(), None).extract_entire_archive(downloaded_file)?;
 
-    #[test_case("test-data/project.AppImage", Some("AppImage"))]
-    #[test_case("test-data/project.bat", Some("bat"))]
-    #[test_case("test-data/project.bz", None)]
-    #[test_case("test-data/project.bz2", None)]
-    #[test_case("test-data/project.exe", Some("exe"))]
-    #[test_case("test-data/project.gz", None)]
-    #[test_case("test-data/project.jar", Some("jar"))]
-    #[test_case("test-data/project.pyz", Some("pyz"))]
-    #[test_case("test-data/project.tar", None)]
-    #[test_case("test-data/project.tar.bz", None)]
-    #[test_case("test-data/project.tar.bz2", None)]
-    #[test_case("test-data/project.tar.gz", None)]
-    #[test_case("test-data/project.tar.xz", None)]
-    #[test_case("test-data/project.xz", None)]
-    #[test_case("test-data/project.zip", None)]
-    #[test_case("test-data/project", None)]
-    // This tests a bug where zip files with partial matches before an exact match would pick the wrong file.
-    #[test_case("test-data/project-with-partial-before-exact.zip", None)]
-    // These are archive files that just contain a partial match for the expected executable.
-    #[test_case("test-data/project-with-partial-match.tar.gz", None)]
-    #[test_case("test-data/project-with-partial-match.zip", None)]
-    fn exe_installer(archive_path: &str, installed_extension: Option<&str>) -> Result<()> {
-        crate::test_case::init_logging();
+        let mut exes: Vec<PathBuf> = vec![];
+        self.find_exes(&unpack_root, &mut exes)?;
+        if exes.is_empty() {
+            return Err(self.could_not_find_exes_error());
+        }
+        exes.sort();
 
-        let td = tempdir()?;
-        let path_without_subdir = td.path().to_path_buf();
-        test_installer(
-            archive_path,
-            installed_extension,
-            path_without_subdir,
-            false,
-        )?;
+        debug!("creating directory at {}", self.install_dir.display());
+        create_dir_all(&self.install_dir).with_context(|| {
+            format!(
+                "could not create a directory at {}",
+                self.install_dir.display()
+            )
+        })?;
 
-        let td = tempdir()?;
-        let mut path_with_subdir = td.path().to_path_buf();
-        path_with_subdir.push("subdir");
-        test_installer(archive_path, installed_extension, path_with_subdir, false)
-    }
+        let mut names: HashSet<OsString> = HashSet::new();
+        let mut installed: Vec<PathBuf> = vec![];
+        for exe in exes {
+            let file_name = exe
+                .file_name()
+                .expect("path of a file in the unpacked archive somehow has no file name");
+            if !names.insert(file_name.to_os_string()) {
+                return Err(anyhow!(
+                    "the archive contains more than one executable named {}",
+                    file_name.to_string_lossy(),
+                ));
+            }
 
-    // These tests check that we look for project.bat and project.exe in archive files when running
-    // on Windows.
-    #[test_case("test-data/windows-project-bat.tar.gz", "bat")]
-    #[test_case("test-data/windows-project-exe.tar.gz", "exe")]
+            let install_path = self.install_dir.join(file_name);
+            debug!(
+                "copying executable from {} to {}",
+                exe.display(),
+                install_path.display(),
+            );
+            let mut staged = staged_file_for(&install_path)?;
+            std::io::copy(&mut open_file(&exe)?, &mut staged).with_context(|| {
+                format!(
+                    "error copying file from {} to {}",
+                    exe.display(),
+                    install_path.display(),
+                )
+            })?;
+            fs::set_permissions(staged.path(), fs::metadata(&exe)?.permissions())?;
+            ExeInstaller::chmod_executable(staged.path())?;
+            move_into_place(staged, &install_path, self.is_windows)?;
+            installed.push(install_path);
+        }
+
+        Ok(installed)
+    }
+
+    fn find_exes(&self, dir: &Path, exes: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.find_exes(&path, exes)?;
+            } else if path.is_file() && self.is_exe(&path)? {
+                debug!("found executable at {}", path.display());
+                exes.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_exe(&self, path: &Path) -> Result<bool> {
+        if let Some(glob) = &self.glob {
+            return Ok(path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|n| glob.matches(n)));
+        }
+
+        if self.is_windows {
+            return Ok(path
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|e| WINDOWS_EXE_EXTENSIONS.contains(&e.to_lowercase().as_str())));
+        }
+
+        Self::has_exe_bit(path)
+    }
+
+    #[cfg(target_family = "windows")]
+    fn has_exe_bit(_path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[cfg(target_family = "unix")]
+    fn has_exe_bit(path: &Path) -> Result<bool> {
+        Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+    }
+
+    fn could_not_find_exes_error(&self) -> anyhow::Error {
+        let looked_for = if let Some(glob) = &self.glob {
+            format!("files matching {}", glob.as_str())
+        } else if self.is_windows {
+            format!(
+                "files with an extension of {}",
+                WINDOWS_EXE_EXTENSIONS
+                    .iter()
+                    .map(|e| format!(".{e}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        } else {
+            "files with the executable bit set".to_string()
+        };
+
+        anyhow!("could not find any {looked_for} in the downloaded archive file")
+    }
+}
+
+impl Installer for AllExesInstaller {
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>> {
+        let installed = self.install_all_exes(&download.archive_path)?;
+        for exe in &installed {
+            info!("Installed executable into {}", exe.display());
+        }
+
+        Ok(installed)
+    }
+
+    fn install_path(&self) -> &Path {
+        &self.install_dir
+    }
+
+    fn install_dir(&self) -> &Path {
+        &self.install_dir
+    }
+}
+
+impl AsIsInstaller {
+    pub(crate) fn new(install_path: PathBuf) -> Self {
+        AsIsInstaller { install_path }
+    }
+
+    // A temp file is only readable by its owner, so we give the installed file the same mode a file
+    // created by `cp` normally has.
+    #[cfg(target_family = "windows")]
+    fn chmod_file(_path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    fn chmod_file(path: &Path) -> Result<()> {
+        set_permissions(path, Permissions::from_mode(0o644))
+            .with_context(|| format!("could not set the mode of {}", path.display()))
+    }
+}
+
+impl Installer for AsIsInstaller {
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>> {
+        let dir = self.install_dir();
+        debug!("creating directory at {}", dir.display());
+        create_dir_all(dir)
+            .with_context(|| format!("could not create a directory at {}", dir.display()))?;
+
+        let mut staged = staged_file_for(&self.install_path)?;
+        std::io::copy(&mut open_file(&download.archive_path)?, &mut staged).context(format!(
+            "error copying file from {} to {}",
+            download.archive_path.display(),
+            self.install_path.display()
+        ))?;
+        Self::chmod_file(staged.path())?;
+        move_into_place(staged, &self.install_path, false)?;
+        info!("Installed file into {}", self.install_path.display());
+
+        Ok(vec![self.install_path.clone()])
+    }
+
+    fn install_path(&self) -> &Path {
+        &self.install_path
+    }
+
+    fn install_dir(&self) -> &Path {
+        self.install_path
+            .parent()
+            .expect("the install path for a file always has a parent")
+    }
+
+    fn existing_install(&self) -> Option<PathBuf> {
+        self.install_path
+            .is_file()
+            .then(|| self.install_path.clone())
+    }
+
+    fn expects_binary_or_archive(&self) -> bool {
+        false
+    }
+}
+
+// Completion files are found by name. Zsh completion files are conventionally named after the
+// command with a leading `_`, like `_project`, and have no extension.
+fn completion_shell(file_name: &str) -> Option<&'static str> {
+    let lower = file_name.to_lowercase();
+    if lower.ends_with(".bash") {
+        Some("bash")
+    } else if lower.ends_with(".fish") {
+        Some("fish")
+    } else if lower.ends_with(".zsh")
+        || (file_name.len() > 1 && file_name.starts_with('_') && !file_name.contains('.'))
+    {
+        Some("zsh")
+    } else {
+        None
+    }
+}
+
+// Returns the completion files that were installed. A release asset that isn't an archive that we
+// can unpack, like a bare or compressed executable, has no completion files.
+fn install_completions(downloaded_file: &Path, completions_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !can_unpack(downloaded_file)? {
+        debug!(
+            "not looking for completion files in {}, because it is not an archive",
+            downloaded_file.display(),
+        );
+        return Ok(vec![]);
+    }
+
+    let td = tempdir()?;
+    ArchiveInstaller::unpack_archive(downloaded_file, td.path())?;
+    let mut files = vec![];
+    find_completion_files(td.path(), &mut files)?;
+    // Sorting makes the choice between two files with the same name, like `bash/project.bash` and
+    // `contrib/project.bash`, the same each time.
+    files.sort();
+
+    let mut installed = vec![];
+    for (shell, file) in files {
+        let file_name = file
+            .file_name()
+            .expect("a file found in a directory always has a file name");
+        let install_path = completions_dir.join(shell).join(file_name);
+        if installed.contains(&install_path) {
+            debug!(
+                "skipping {} because another completion file was already installed to {}",
+                file.display(),
+                install_path.display(),
+            );
+            continue;
+        }
+
+        let dir = completions_dir.join(shell);
+        create_dir_all(&dir)
+            .with_context(|| format!("could not create a directory at {}", dir.display()))?;
+        let mut staged = staged_file_for(&install_path)?;
+        std::io::copy(&mut open_file(&file)?, &mut staged).with_context(|| {
+            format!(
+                "could not write the completion file for {}",
+                install_path.display()
+            )
+        })?;
+        staged.persist(&install_path).with_context(|| {
+            format!(
+                "could not install the completion file to {}",
+                install_path.display()
+            )
+        })?;
+        info!("Installed completion file into {}", install_path.display());
+        installed.push(install_path);
+    }
+
+    Ok(installed)
+}
+
+// This matches the archives that `ArchiveInstaller::unpack_archive` can unpack.
+fn can_unpack(downloaded_file: &Path) -> Result<bool> {
+    match Extension::from_path(downloaded_file)? {
+        Some(Extension::SevenZ | Extension::Zip) => Ok(true),
+        Some(ext) if ext.is_archive() && ext != Extension::Dmg => is_tarball(downloaded_file),
+        Some(_) => Ok(false),
+        None => Ok(
+            Extension::from_magic_bytes(downloaded_file)? == Some(Extension::SevenZ)
+                || is_tarball(downloaded_file)?,
+        ),
+    }
+}
+
+fn find_completion_files(dir: &Path, files: &mut Vec<(&'static str, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            find_completion_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            if let Some(shell) = entry.file_name().to_str().and_then(completion_shell) {
+                debug!(
+                    "found {shell} completion file at {}",
+                    entry.path().display()
+                );
+                files.push((shell, entry.path()));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn dmg_not_supported_error(downloaded_file: &Path) -> anyhow::Error {
+    anyhow!(
+        "cannot extract the executable from {}, because dmg extraction is only supported on macOS",
+        downloaded_file.display(),
+    )
+}
+
+fn tar_reader_for(downloaded_file: &Path) -> Result<Archive<Box<dyn Read>>> {
+    Ok(Archive::new(decompressed_reader_for(downloaded_file)?))
+}
+
+fn decompressed_reader_for(downloaded_file: &Path) -> Result<Box<dyn Read>> {
+    // If the file doesn't have an extension, then we look at its magic bytes to find out how it's
+    // compressed, if it is.
+    let ext = match Extension::from_path(downloaded_file)? {
+        Some(ext) => Some(ext),
+        None => Extension::from_magic_bytes(downloaded_file)?,
+    };
+    let file = open_file(downloaded_file)?;
+
+    match ext {
+        None | Some(Extension::Tar) => Ok(Box::new(file)),
+        Some(Extension::Br | Extension::TarBr) => {
+            Ok(Box::new(BrotliDecompressor::new(file, BROTLI_BUFFER_SIZE)))
+        }
+        Some(
+            Extension::Bz
+            | Extension::Bz2
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::Tbz
+            | Extension::Tbz2,
+        ) => Ok(Box::new(BzDecoder::new(file))),
+        Some(Extension::Gz | Extension::TarGz | Extension::Tgz) => {
+            Ok(Box::new(GzDecoder::new(file)))
+        }
+        Some(Extension::Lzma | Extension::TarLzma) => Ok(Box::new(XzDecoder::new_stream(
+            file,
+            Stream::new_lzma_decoder(u64::MAX)?,
+        ))),
+        Some(Extension::Xz | Extension::TarXz | Extension::Txz) => {
+            Ok(Box::new(XzDecoder::new(file)))
+        }
+        Some(Extension::Zst | Extension::TarZst | Extension::Tzst) => {
+            Ok(Box::new(ZstdDecoder::new(file)?))
+        }
+        Some(ext) => Err(anyhow!(
+            "don't know how to uncompress a file with extension = {}",
+            ext.extension_without_dot(),
+        )),
+    }
+}
+
+// A tar header is 512 bytes, and POSIX tarballs have the magic string `ustar` at offset 257.
+fn is_tarball(downloaded_file: &Path) -> Result<bool> {
+    let mut header = [0u8; 512];
+    match decompressed_reader_for(downloaded_file)?.read_exact(&mut header) {
+        Ok(()) => Ok(&header[257..262] == b"ustar"),
+        // A file that is smaller than a tar header can't be a tarball.
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => {
+            Err(e).with_context(|| format!("could not uncompress {}", downloaded_file.display()))
+        }
+    }
+}
+
+// Unlike `Path::set_extension`, this doesn't replace an existing extension, so if the executable is
+// renamed to `tool-1.2`, we install `tool-1.2.exe` and not `tool-1.exe`. If the path already has
+// this extension, it is returned as-is.
+fn add_extension(path: &Path, extension: &str) -> PathBuf {
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+    {
+        return path.to_path_buf();
+    }
+
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(extension);
+    path.with_file_name(file_name)
+}
+
+// Renames the installed executable to a name that includes the release's version, like
+// `foo-1.2.3`, and then creates a link with the executable's original name that points to it. A
+// later install of another version only replaces the link, so the older version is still there to
+// roll back to. Returns the paths of the versioned executable and the link.
+pub(crate) fn link_versioned_exe(exe: &Path, tag: &str) -> Result<(PathBuf, PathBuf)> {
+    let versioned = versioned_exe_path(exe, tag);
+    debug!("renaming {} to {}", exe.display(), versioned.display());
+    fs::rename(exe, &versioned).with_context(|| {
+        format!(
+            "could not move the executable at {} to {}",
+            exe.display(),
+            versioned.display(),
+        )
+    })?;
+    let link = link_to_versioned_exe(exe, &versioned)?;
+    info!("Linked {} to {}", link.display(), versioned.display());
+
+    Ok((versioned, link))
+}
+
+// A tag like `v1.2.3` gives us `foo-1.2.3`, but a tag that doesn't look like a version, like
+// `nightly`, is used as is. Any extension, like `.exe`, stays at the end of the name.
+pub(crate) fn versioned_exe_path(exe: &Path, tag: &str) -> PathBuf {
+    let version = tag
+        .strip_prefix('v')
+        .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(tag)
+        .replace(['/', '\\'], "-");
+    let mut file_name = exe.file_stem().unwrap_or_default().to_os_string();
+    file_name.push("-");
+    file_name.push(version);
+    if let Some(ext) = exe.extension() {
+        file_name.push(".");
+        file_name.push(ext);
+    }
+    exe.with_file_name(file_name)
+}
+
+// This is the path of the link that `link_versioned_exe` creates for `exe`.
+pub(crate) fn versioned_link_path(exe: &Path) -> PathBuf {
+    if cfg!(target_family = "windows") {
+        exe.with_extension("cmd")
+    } else {
+        exe.to_path_buf()
+    }
+}
+
+// The link is relative, so it still works if the install dir is moved.
+#[cfg(target_family = "unix")]
+fn link_to_versioned_exe(exe: &Path, versioned: &Path) -> Result<PathBuf> {
+    let target = versioned.file_name().unwrap_or_default();
+    debug!(
+        "creating a symlink from {} to {}",
+        exe.display(),
+        versioned.display()
+    );
+    std::os::unix::fs::symlink(target, exe)
+        .with_context(|| format!("could not create a symlink at {}", exe.display()))?;
+    Ok(exe.to_path_buf())
+}
+
+// Creating a symlink on Windows requires special privileges, so we create a `.cmd` shim that runs
+// the versioned executable instead.
+#[cfg(target_family = "windows")]
+fn link_to_versioned_exe(exe: &Path, versioned: &Path) -> Result<PathBuf> {
+    let shim = versioned_link_path(exe);
+    let target = versioned.file_name().unwrap_or_default().to_string_lossy();
+    debug!(
+        "creating a shim at {} for {}",
+        shim.display(),
+        versioned.display()
+    );
+    fs::write(&shim, format!("@\"%~dp0{target}\" %*\r\n"))
+        .with_context(|| format!("could not create a shim at {}", shim.display()))?;
+    Ok(shim)
+}
+
+// The temp file is created in the same directory as the install path, so that it can be renamed
+// into place.
+fn staged_file_for(install_path: &Path) -> Result<NamedTempFile> {
+    let dir = install_path
+        .parent()
+        .ok_or_else(|| anyhow!("install path at {} has no parent", install_path.display()))?;
+    tempfile::Builder::new()
+        .prefix(".ubi-")
+        .tempfile_in(dir)
+        .with_context(|| format!("could not create a temp file in {}", dir.display()))
+}
+
+// This makes sure that the existing file at `install_path`, if any, is only replaced once the new
+// one is complete. On Windows, a running executable cannot be replaced, so it is renamed first and
+// then deleted if possible.
+fn move_into_place(staged: NamedTempFile, install_path: &Path, is_windows: bool) -> Result<()> {
+    staged.as_file().sync_all()?;
+    // This closes the file, which Windows requires before the file can be renamed.
+    let staged = staged.into_temp_path();
+
+    let old_exe = is_windows.then(|| {
+        let mut file_name = install_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".old");
+        install_path.with_file_name(file_name)
+    });
+    replace_exe(install_path, &staged, old_exe.as_deref())?;
+
+    if let Some(old_exe) = old_exe.filter(|p| p.exists()) {
+        if let Err(e) = fs::remove_file(&old_exe) {
+            debug!(
+                "could not remove the old executable at {}: {e}",
+                old_exe.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Renames `new_exe` to `exe`. If `old_exe` is given and `exe` exists, then `exe` is first renamed to
+// `old_exe`, which is needed on Windows. If the new executable can't be moved into place, the old
+// one is moved back.
+pub(crate) fn replace_exe(exe: &Path, new_exe: &Path, old_exe: Option<&Path>) -> Result<()> {
+    let rename_into_place = || {
+        debug!("renaming {} to {}", new_exe.display(), exe.display());
+        fs::rename(new_exe, exe).with_context(|| {
+            format!(
+                "could not move the new executable at {} to {}",
+                new_exe.display(),
+                exe.display(),
+            )
+        })
+    };
+
+    let Some(old_exe) = old_exe.filter(|_| exe.exists()) else {
+        return rename_into_place();
+    };
+
+    if old_exe.exists() {
+        debug!("removing {} left by a previous install", old_exe.display());
+        fs::remove_file(old_exe)
+            .with_context(|| format!("could not remove {}", old_exe.display()))?;
+    }
+    debug!("renaming {} to {}", exe.display(), old_exe.display());
+    fs::rename(exe, old_exe).with_context(|| {
+        format!(
+            "could not move the current executable at {} to {}",
+            exe.display(),
+            old_exe.display(),
+        )
+    })?;
+
+    if let Err(e) = rename_into_place() {
+        // Put the old executable back so that we don't leave the user without it at all.
+        fs::rename(old_exe, exe).with_context(|| {
+            format!(
+                "{e:#}, and could not move the old executable at {} back to {}",
+                old_exe.display(),
+                exe.display(),
+            )
+        })?;
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+fn open_file(path: &Path) -> Result<File> {
+    File::open(path).with_context(|| format!("Failed to open file at {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    #[cfg(target_family = "unix")]
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case("test-data/project.7z", None)]
+    #[test_case("test-data/project.AppImage", Some("AppImage"))]
+    #[test_case("test-data/project.bat", Some("bat"))]
+    #[test_case("test-data/project.br", None)]
+    #[test_case("test-data/project.bz", None)]
+    #[test_case("test-data/project.bz2", None)]
+    #[test_case("test-data/project.exe", Some("exe"))]
+    #[test_case("test-data/project.gz", None)]
+    #[test_case("test-data/project.jar", Some("jar"))]
+    #[test_case("test-data/project.lzma", None)]
+    #[test_case("test-data/project.pyz", Some("pyz"))]
+    #[test_case("test-data/project.tar", None)]
+    #[test_case("test-data/project.tar.br", None)]
+    #[test_case("test-data/project.tar.bz", None)]
+    #[test_case("test-data/project.tar.bz2", None)]
+    #[test_case("test-data/project.tar.gz", None)]
+    #[test_case("test-data/project.tar.lzma", None)]
+    #[test_case("test-data/project.tar.xz", None)]
+    #[test_case("test-data/project.tar.zst", None)]
+    #[test_case("test-data/project.tbz", None)]
+    #[test_case("test-data/project.tbz2", None)]
+    #[test_case("test-data/project.xz", None)]
+    #[test_case("test-data/project.zip", None)]
+    #[test_case("test-data/project.zst", None)]
+    #[test_case("test-data/project", None)]
+    // This is a zstd-compressed file without an extension, which we detect by its magic bytes.
+    #[test_case("test-data/project-zst-without-extension", None)]
+    // These are compressed tarballs without an extension, which we also detect by their magic
+    // bytes.
+    #[test_case("test-data/project-tarball-bz2-without-extension", None)]
+    #[test_case("test-data/project-tarball-lzma-without-extension", None)]
+    // This tests a bug where zip files with partial matches before an exact match would pick the wrong file.
+    #[test_case("test-data/project-with-partial-before-exact.zip", None)]
+    // These are archive files that just contain a partial match for the expected executable.
+    #[test_case("test-data/project-with-partial-match.tar.gz", None)]
+    #[test_case("test-data/project-with-partial-match.zip", None)]
+    // This is a tarball compressed with xz that doesn't have a `.tar.xz` extension.
+    #[test_case("test-data/project-tarball.xz", None)]
+    // This is a compressed executable that has a tarball extension.
+    #[test_case("test-data/project-bare.tar.gz", None)]
+    fn exe_installer(archive_path: &str, installed_extension: Option<&str>) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let path_without_subdir = td.path().to_path_buf();
+        test_installer(
+            archive_path,
+            installed_extension,
+            path_without_subdir,
+            false,
+        )?;
+
+        let td = tempdir()?;
+        let mut path_with_subdir = td.path().to_path_buf();
+        path_with_subdir.push("subdir");
+        test_installer(archive_path, installed_extension, path_with_subdir, false)
+    }
+
+    // These tests check that we look for project.bat and project.exe in archive files when running
+    // on Windows.
+    #[test_case("test-data/windows-project-bat.tar.gz", "bat")]
+    #[test_case("test-data/windows-project-exe.tar.gz", "exe")]
     #[test_case("test-data/windows-project-bat.zip", "bat")]
     #[test_case("test-data/windows-project-exe.zip", "exe")]
     // And these check that we match project-with-stuff.exe.
@@ -600,6 +1670,89 @@ mod tests {
         test_installer(archive_path, Some(extension), install_dir, true)
     }
 
+    // These archives have `bin/project`, `bin/project-helper`, and `libexec/project`, each with
+    // different contents, so we can tell which one was installed.
+    #[test_case("test-data/project-with-nested-exes.tar.gz", "project", false, "bin" ; "tarball with file name")]
+    #[test_case("test-data/project-with-nested-exes.tar.gz", "project-helper", false, "helper" ; "tarball with other file name")]
+    #[test_case("test-data/project-with-nested-exes.tar.gz", "libexec/project", false, "libexec" ; "tarball with nested path")]
+    #[test_case("test-data/project-with-nested-exes.tar.gz", "project-1.0/bin/project", false, "bin" ; "tarball with full path")]
+    #[test_case("test-data/project-with-nested-exes.zip", "libexec/project", false, "libexec" ; "zip with nested path")]
+    #[test_case("test-data/windows-project-with-nested-exes.zip", "libexec/project", true, "libexec" ; "windows without exe suffix")]
+    #[test_case("test-data/windows-project-with-nested-exes.zip", "libexec/project.exe", true, "libexec" ; "windows with exe suffix")]
+    #[test_case("test-data/windows-project-with-nested-exes.zip", "project.EXE", true, "bin" ; "windows with uppercase exe suffix")]
+    fn exe_installer_with_exe(
+        archive_path: &str,
+        exe: &str,
+        is_windows: bool,
+        expect_contents: &str,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join(exe.rsplit('/').next().unwrap());
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            exe.to_string(),
+            is_windows,
+            None,
+            None,
+        );
+        let installed = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        let expect_install_path = if is_windows {
+            add_extension(&install_path, "exe")
+        } else {
+            install_path
+        };
+        assert_eq!(installed, vec![expect_install_path.clone()]);
+        assert_eq!(fs::read_to_string(&expect_install_path)?, expect_contents);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-with-nested-exes.tar.gz", "share/project", "share/project*" ; "tarball")]
+    #[test_case("test-data/project-with-nested-exes.zip", "other", "other*" ; "zip")]
+    fn exe_installer_with_exe_not_found(
+        archive_path: &str,
+        exe: &str,
+        expect_pattern: &str,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            exe.to_string(),
+            false,
+            None,
+            None,
+        );
+        let err = installer
+            .install(&Download {
+                _temp_dir: tempdir()?,
+                archive_path: PathBuf::from(archive_path),
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "could not find any files matching [{expect_pattern}] in the downloaded archive file, it contains {}",
+                [
+                    "project-1.0/README.md",
+                    "project-1.0/bin/project",
+                    "project-1.0/bin/project-helper",
+                    "project-1.0/libexec/project",
+                ]
+                .join(", "),
+            ),
+        );
+
+        Ok(())
+    }
+
     fn test_installer(
         archive_path: &str,
         installed_extension: Option<&str>,
@@ -611,8 +1764,13 @@ mod tests {
         let mut install_path = install_dir;
         install_path.push("project");
 
-        let installer =
-            ExeInstaller::new(install_path.clone(), exe_file_stem.to_string(), is_windows);
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            exe_file_stem.to_string(),
+            is_windows,
+            None,
+            None,
+        );
         installer.install(&Download {
             // It doesn't matter what we use here. We're not actually going to
             // put anything in this temp dir.
@@ -625,7 +1783,7 @@ mod tests {
             let path = PathBuf::from(format!("foo.{installed_extension}"));
             let ext = Extension::from_path(&path).unwrap().unwrap();
             if ext.should_preserve_extension_on_install() {
-                expect_install_path.set_extension(ext.extension_without_dot());
+                expect_install_path = add_extension(&install_path, ext.extension_without_dot());
             }
         }
 
@@ -649,12 +1807,20 @@ mod tests {
         Ok(())
     }
 
+    #[test_case("test-data/project.7z")]
     #[test_case("test-data/project.tar")]
+    #[test_case("test-data/project.tar.br")]
     #[test_case("test-data/project.tar.bz")]
     #[test_case("test-data/project.tar.bz2")]
     #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.tar.lzma")]
     #[test_case("test-data/project.tar.xz")]
+    #[test_case("test-data/project.tar.zst")]
+    #[test_case("test-data/project.tbz")]
+    #[test_case("test-data/project.tbz2")]
     #[test_case("test-data/project.zip")]
+    #[test_case("test-data/project-tarball-bz2-without-extension")]
+    #[test_case("test-data/project-tarball-lzma-without-extension")]
     fn archive_installer(archive_path: &str) -> Result<()> {
         crate::test_case::init_logging();
 
@@ -665,7 +1831,7 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
+            let installer = ArchiveInstaller::new(install_root.clone(), None);
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -688,10 +1854,368 @@ mod tests {
         Ok(())
     }
 
-    // This tests a bug in the initial implementation where a tarball that just contained files
-    // caused us to try to move its contents up to a directory that didn't exist.
-    #[test]
-    fn archive_installer_one_file_in_archive_root() -> Result<()> {
+    // The fixtures contain `bin/project` along with completion files for bash, fish, and zsh, plus
+    // a README and a `__init__.py`, which are not completion files.
+    #[test_case("test-data/project-with-completions.tar.gz" ; "tarball")]
+    #[test_case("test-data/project-with-completions.zip" ; "zip file")]
+    fn exe_installer_installs_completions(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("bin").join("project");
+        let completions_dir = td.path().join("completions");
+
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            "project".to_string(),
+            false,
+            Some(completions_dir.clone()),
+            None,
+        );
+        let installed = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        let expect = [
+            (install_path.clone(), "#!/bin/sh\necho project\n"),
+            (
+                completions_dir.join("bash").join("project.bash"),
+                "complete -F _project project\n",
+            ),
+            (
+                completions_dir.join("fish").join("project.fish"),
+                "complete -c project\n",
+            ),
+            (
+                completions_dir.join("zsh").join("_project"),
+                "#compdef project\n",
+            ),
+        ];
+        for (path, content) in &expect {
+            assert_eq!(&fs::read_to_string(path)?, content, "{}", path.display());
+        }
+        assert_eq!(
+            installed.iter().sorted().collect::<Vec<_>>(),
+            expect.iter().map(|(p, _)| p).sorted().collect::<Vec<_>>(),
+        );
+        let mut shells = fs::read_dir(&completions_dir)?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        shells.sort();
+        assert_eq!(shells, vec!["bash", "fish", "zsh"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_with_completions_dir_and_bare_executable() -> Result<()> {
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let completions_dir = td.path().join("completions");
+
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            "project".to_string(),
+            false,
+            Some(completions_dir.clone()),
+            None,
+        );
+        let installed = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project.gz"),
+        })?;
+
+        assert_eq!(installed, vec![install_path]);
+        assert!(!completions_dir.exists());
+
+        Ok(())
+    }
+
+    #[test_case("project.bash", Some("bash") ; "bash")]
+    #[test_case("project.BASH", Some("bash") ; "bash with uppercase extension")]
+    #[test_case("project.fish", Some("fish") ; "fish")]
+    #[test_case("project.zsh", Some("zsh") ; "zsh")]
+    #[test_case("_project", Some("zsh") ; "zsh with leading underscore")]
+    #[test_case("_project.py", None ; "leading underscore with extension")]
+    #[test_case("_", None ; "only an underscore")]
+    #[test_case("project", None ; "executable")]
+    #[test_case("project.bash-completion.md", None ; "docs about completion")]
+    fn completion_shell(file_name: &str, expect: Option<&str>) {
+        assert_eq!(super::completion_shell(file_name), expect);
+    }
+
+    // The 7z fixture contains both `project/README.md` and `project/bin/project`. Only the latter
+    // should be installed.
+    #[test]
+    fn exe_installer_only_installs_exe_from_7z() -> Result<()> {
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            "project".to_string(),
+            false,
+            None,
+            None,
+        );
+        installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project.7z"),
+        })?;
+
+        assert_eq!(fs::read(&install_path)?, b"exe");
+        let installed = fs::read_dir(td.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(installed, vec![OsString::from("project")]);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", "renamed", false, "renamed" ; "from archive")]
+    #[test_case("test-data/project", "renamed", false, "renamed" ; "bare binary")]
+    #[test_case("test-data/project.gz", "renamed-1.2", false, "renamed-1.2" ; "compressed binary with dot in new name")]
+    #[test_case("test-data/project.gz", "renamed", true, "renamed.exe" ; "compressed binary on Windows")]
+    #[test_case("test-data/windows-project-exe.zip", "renamed", true, "renamed.exe" ; "from archive on Windows")]
+    #[test_case("test-data/windows-project-exe.zip", "renamed-1.2", true, "renamed-1.2.exe" ; "from archive on Windows with dot in new name")]
+    #[test_case("test-data/windows-project-exe.zip", "renamed.exe", true, "renamed.exe" ; "from archive on Windows with exe in new name")]
+    #[test_case("test-data/windows-project-bat.zip", "renamed", true, "renamed.bat" ; "bat from archive on Windows")]
+    fn renamed_exe_installer(
+        archive_path: &str,
+        rename_to: &str,
+        is_windows: bool,
+        expect_file_name: &str,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let installer = ExeInstaller::new(
+            td.path().join(rename_to),
+            "project".to_string(),
+            is_windows,
+            None,
+            None,
+        );
+        let installed = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        let expect_install_path = td.path().join(expect_file_name);
+        assert_eq!(installed, vec![expect_install_path.clone()]);
+        let files = fs::read_dir(td.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(files, vec![OsString::from(expect_file_name)]);
+        #[cfg(target_family = "unix")]
+        assert!(expect_install_path.metadata()?.permissions().mode() & 0o111 != 0);
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test_case("test-data/project-with-mode-755.zip", 0o755 ; "zip entry with mode 755")]
+    #[test_case("test-data/project-with-mode-750.zip", 0o750 ; "zip entry with mode 750")]
+    #[test_case("test-data/project.zip", 0o755 ; "zip entry without executable bits")]
+    fn exe_installer_applies_mode_from_zip(archive_path: &str, expect_mode: u32) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            "project".to_string(),
+            false,
+            None,
+            None,
+        );
+        installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        assert!(install_path.is_file());
+        assert_eq!(
+            install_path.metadata()?.permissions().mode() & 0o777,
+            expect_mode,
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-elf.xz", "project", b"\x7fELF", 1024 ; "xz-compressed executable")]
+    #[test_case("test-data/project.pyz.gz", "project.pyz", b"PK", 215 ; "gzip-compressed pyz")]
+    fn exe_installer_installs_compressed_executable(
+        archive_path: &str,
+        expect_name: &str,
+        expect_prefix: &[u8],
+        expect_len: usize,
+    ) -> Result<()> {
+        let td = tempdir()?;
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            "project".to_string(),
+            false,
+            None,
+            None,
+        );
+        let installed = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+        assert_eq!(installed, vec![td.path().join(expect_name)]);
+
+        let content = fs::read(&installed[0])?;
+        assert!(content.starts_with(expect_prefix));
+        assert_eq!(content.len(), expect_len);
+
+        Ok(())
+    }
+
+    #[test_case(&[], None ; "nothing installed")]
+    #[test_case(&["project"], Some("project") ; "exe")]
+    #[test_case(&["project.pyz"], Some("project.pyz") ; "exe with preserved extension")]
+    #[test_case(&["project.tar.gz"], None ; "file with other extension")]
+    #[test_case(&["other"], None ; "other exe")]
+    fn exe_installer_existing_install(files: &[&str], expect: Option<&str>) -> Result<()> {
+        let td = tempdir()?;
+        for f in files {
+            fs::write(td.path().join(f), "exe")?;
+        }
+
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            "project".to_string(),
+            false,
+            None,
+            None,
+        );
+        assert_eq!(
+            installer.existing_install(),
+            expect.map(|e| td.path().join(e)),
+        );
+
+        Ok(())
+    }
+
+    #[test_case(false, "project" ; "not Windows")]
+    #[test_case(true, "project.exe" ; "Windows")]
+    fn exe_installer_replaces_existing_exe(is_windows: bool, exe_name: &str) -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join(exe_name);
+        fs::write(&exe, "old executable")?;
+
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            "project".to_string(),
+            is_windows,
+            None,
+            None,
+        );
+        installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project.gz"),
+        })?;
+
+        assert_eq!(fs::read(&exe)?, b"exe");
+        let files = fs::read_dir(td.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            files,
+            vec![OsString::from(exe_name)],
+            "no files were left behind"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_keeps_existing_exe_when_write_fails() -> Result<()> {
+        use flate2::{write::GzEncoder, Compression};
+
+        // A truncated gzip file fails partway through decompressing it, after some of the new
+        // executable has been written.
+        let mut encoder = GzEncoder::new(vec![], Compression::fast());
+        encoder.write_all(
+            &(0..10_000_u32)
+                .flat_map(u32::to_le_bytes)
+                .collect::<Vec<_>>(),
+        )?;
+        let compressed = encoder.finish()?;
+        let archive_td = tempdir()?;
+        let archive_path = archive_td.path().join("project.gz");
+        fs::write(&archive_path, &compressed[..compressed.len() / 2])?;
+
+        let td = tempdir()?;
+        let exe = td.path().join("project");
+        fs::write(&exe, "old executable")?;
+
+        let installer = ExeInstaller::new(exe.clone(), "project".to_string(), false, None, None);
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path,
+        });
+        assert!(res.is_err(), "installing a truncated file fails");
+
+        assert_eq!(fs::read_to_string(&exe)?, "old executable");
+        let files = fs::read_dir(td.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            files,
+            vec![OsString::from("project")],
+            "the temp file was removed",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_exe_restores_exe_on_failure() -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join("project.exe");
+        fs::write(&exe, "old executable")?;
+        let old_exe = td.path().join("project.exe.old");
+
+        let res = replace_exe(&exe, &td.path().join("missing.exe"), Some(&old_exe));
+        assert!(res.is_err());
+        assert_eq!(fs::read_to_string(&exe)?, "old executable");
+        assert!(!old_exe.exists());
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn archive_installer_preserves_mode_from_zstd_tarball() -> Result<()> {
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+
+        let installer = ArchiveInstaller::new(install_root.clone(), None);
+        installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project.tar.zst"),
+        })?;
+
+        let exe = install_root.join("bin").join("project");
+        assert!(exe.is_file());
+        assert_eq!(exe.metadata()?.permissions().mode() & 0o777, 0o755);
+
+        Ok(())
+    }
+
+    // This tests a bug in the initial implementation where a tarball that just contained files
+    // caused us to try to move its contents up to a directory that didn't exist.
+    #[test]
+    fn archive_installer_one_file_in_archive_root() -> Result<()> {
         let td = tempdir()?;
         let mut path_without_subdir = td.path().to_path_buf();
         path_without_subdir.push("project");
@@ -699,7 +2223,7 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
+            let installer = ArchiveInstaller::new(install_root.clone(), None);
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -727,7 +2251,7 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
+            let installer = ArchiveInstaller::new(install_root.clone(), None);
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -753,4 +2277,260 @@ mod tests {
 
         Ok(())
     }
+
+    // The fixture contains `project-1.2.3/README.md`, `project-1.2.3/bin/project`, and
+    // `project-1.2.3/share/doc/project.md`.
+    #[test_case(
+        0,
+        &["project-1.2.3/README.md", "project-1.2.3/bin/project", "project-1.2.3/share/doc/project.md"],
+        &[] ;
+        "strip 0 components"
+    )]
+    #[test_case(
+        1,
+        &["README.md", "bin/project", "share/doc/project.md"],
+        &["project-1.2.3"] ;
+        "strip 1 component"
+    )]
+    #[test_case(
+        2,
+        &["project", "doc/project.md"],
+        &["README.md", "bin", "share", "project-1.2.3"] ;
+        "strip 2 components"
+    )]
+    #[test_case(4, &[], &["project", "README.md", "bin", "share"] ; "strip more components than any entry has")]
+    fn archive_installer_with_strip_components(
+        strip_components: usize,
+        expect_files: &[&str],
+        expect_missing: &[&str],
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+
+        let installer = ArchiveInstaller::new(install_root.clone(), Some(strip_components));
+        installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project-nested.tar.gz"),
+        })?;
+
+        assert!(install_root.is_dir());
+        for file in expect_files {
+            assert!(install_root.join(file).is_file(), "{file} was extracted");
+        }
+        for path in expect_missing {
+            assert!(
+                !install_root.join(path).exists(),
+                "{path} was not extracted"
+            );
+        }
+
+        // The temp dir used to unpack the archive is cleaned up.
+        let leftovers = fs::read_dir(td.path())?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(leftovers, vec![OsString::from("project")]);
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn archive_installer_with_strip_components_preserves_mode() -> Result<()> {
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+
+        let installer = ArchiveInstaller::new(install_root.clone(), Some(2));
+        installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project-nested.tar.gz"),
+        })?;
+
+        let exe = install_root.join("project");
+        assert!(exe.is_file());
+        assert_eq!(exe.metadata()?.permissions().mode() & 0o777, 0o755);
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn all_exes_installer() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_dir = td.path().join("bin");
+
+        let installer = AllExesInstaller::new(install_dir.clone(), None, false);
+        let installed = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/multiple-exes.tar.gz"),
+        })?;
+
+        let expect = vec![
+            install_dir.join("project"),
+            install_dir.join("project-helper"),
+        ];
+        assert_eq!(installed, expect);
+        for exe in &expect {
+            assert_eq!(fs::read(exe)?, b"exe");
+            assert_eq!(exe.metadata()?.permissions().mode() & 0o777, 0o755);
+        }
+        assert!(!install_dir.join("README.md").exists());
+        assert!(!install_dir.join("data.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_exes_installer_with_glob() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_dir = td.path().to_path_buf();
+
+        let installer =
+            AllExesInstaller::new(install_dir.clone(), Some(Pattern::new("project-*")?), false);
+        let installed = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/multiple-exes.tar.gz"),
+        })?;
+
+        assert_eq!(installed, vec![install_dir.join("project-helper")]);
+        assert!(!install_dir.join("project").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_exes_installer_on_windows() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_dir = td.path().to_path_buf();
+
+        let installer = AllExesInstaller::new(install_dir.clone(), None, true);
+        let installed = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/windows-multiple-exes.zip"),
+        })?;
+
+        assert_eq!(
+            installed,
+            vec![
+                install_dir.join("helper.bat"),
+                install_dir.join("project.exe"),
+                install_dir.join("tool.cmd"),
+            ],
+        );
+        assert!(!install_dir.join("README.md").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_exes_installer_with_no_matches() -> Result<()> {
+        let td = tempdir()?;
+
+        let installer =
+            AllExesInstaller::new(td.path().to_path_buf(), Some(Pattern::new("nope*")?), false);
+        let res = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/multiple-exes.tar.gz"),
+        });
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not find any files matching nope* in the downloaded archive file",
+        );
+
+        Ok(())
+    }
+
+    #[test_case("foo", "v1.2.3", "foo-1.2.3")]
+    #[test_case("foo", "1.2.3", "foo-1.2.3")]
+    #[test_case("foo", "nightly", "foo-nightly")]
+    #[test_case("foo", "version-1", "foo-version-1")]
+    #[test_case("foo", "release/1.0", "foo-release-1.0")]
+    #[test_case("foo.exe", "v1.2.3", "foo-1.2.3.exe")]
+    #[test_case("foo.pyz", "v1.2.3", "foo-1.2.3.pyz")]
+    fn versioned_exe_path(exe: &str, tag: &str, expect: &str) {
+        assert_eq!(
+            super::versioned_exe_path(&Path::new("bin").join(exe), tag),
+            Path::new("bin").join(expect),
+        );
+    }
+
+    #[test]
+    fn link_versioned_exe() -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join("foo");
+
+        fs::write(&exe, "1.0.0")?;
+        let (versioned, link) = super::link_versioned_exe(&exe, "v1.0.0")?;
+        assert_eq!(versioned, td.path().join("foo-1.0.0"));
+        assert_eq!(fs::read_to_string(&versioned)?, "1.0.0");
+        #[cfg(target_family = "unix")]
+        {
+            assert_eq!(link, exe);
+            assert_eq!(fs::read_link(&link)?, PathBuf::from("foo-1.0.0"));
+        }
+
+        // The new executable is written over the link, like an install does.
+        fs::remove_file(&exe)?;
+        fs::write(&exe, "1.1.0")?;
+        let (versioned, link) = super::link_versioned_exe(&exe, "v1.1.0")?;
+        assert_eq!(versioned, td.path().join("foo-1.1.0"));
+        assert_eq!(fs::read_to_string(td.path().join("foo-1.0.0"))?, "1.0.0");
+        #[cfg(target_family = "unix")]
+        {
+            assert_eq!(fs::read_link(&link)?, PathBuf::from("foo-1.1.0"));
+            assert_eq!(fs::read_to_string(&link)?, "1.1.0");
+        }
+        #[cfg(target_family = "windows")]
+        assert_eq!(fs::read_to_string(&link)?, "@\"%~dp0foo-1.1.0\" %*\r\n");
+
+        Ok(())
+    }
+
+    // A tarball is installed without being extracted.
+    #[test_case("test-data/module.wasm", "module.wasm")]
+    #[test_case("test-data/project.tar.gz", "project.tar.gz")]
+    fn as_is_installer(archive_path: &str, file_name: &str) -> Result<()> {
+        let td = tempdir()?;
+        let install_path = td.path().join("subdir").join(file_name);
+
+        let installer = AsIsInstaller::new(install_path.clone());
+        assert_eq!(installer.existing_install(), None);
+        let installed = installer.install(&Download {
+            // It doesn't matter what we use here. We're not actually going to
+            // put anything in this temp dir.
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        assert_eq!(installed, vec![install_path.clone()]);
+        assert_eq!(fs::read(&install_path)?, fs::read(archive_path)?);
+        assert_eq!(installer.existing_install(), Some(install_path.clone()));
+        #[cfg(target_family = "unix")]
+        assert_eq!(
+            fs::metadata(&install_path)?.permissions().mode() & 0o777,
+            0o644
+        );
+
+        Ok(())
+    }
 }
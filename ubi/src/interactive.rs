@@ -0,0 +1,178 @@
+use crate::ubi::Asset;
+use anyhow::{anyhow, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+// We only prompt when stdin is a terminal, so that `ubi` never blocks waiting for input in CI or
+// when its input is piped from somewhere else.
+pub(crate) fn stdin_is_terminal() -> bool {
+    io::stdin().is_terminal()
+}
+
+// The prompt goes to stderr so that it doesn't get mixed up with anything a caller is reading from
+// stdout.
+pub(crate) fn choose_asset_from_stdin(candidates: Vec<Asset>, reason: &str) -> Result<Asset> {
+    choose_asset(
+        candidates,
+        reason,
+        &mut io::stdin().lock(),
+        &mut io::stderr(),
+    )
+}
+
+// The candidates are listed sorted by name, so the numbers are the same every time for the same
+// release. If the selection is not valid, we ask again until we get one or hit EOF.
+pub(crate) fn choose_asset<R: BufRead, W: Write>(
+    mut candidates: Vec<Asset>,
+    reason: &str,
+    input: &mut R,
+    output: &mut W,
+) -> Result<Asset> {
+    if candidates.is_empty() {
+        return Err(anyhow!("there are no release assets to choose from"));
+    }
+
+    candidates.sort_by(|a, b| a.name.cmp(&b.name));
+    writeln!(output, "{reason}:")?;
+    for (i, asset) in candidates.iter().enumerate() {
+        writeln!(output, "  {}) {}", i + 1, asset.name)?;
+    }
+
+    loop {
+        write!(
+            output,
+            "Enter the number of the asset to install (1-{}): ",
+            candidates.len(),
+        )?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(anyhow!("no release asset was selected"));
+        }
+        match parse_selection(&line, candidates.len()) {
+            Ok(idx) => return Ok(candidates.swap_remove(idx)),
+            Err(e) => writeln!(output, "{e}")?,
+        }
+    }
+}
+
+// Returns the zero-based index of the selected candidate.
+fn parse_selection(input: &str, count: usize) -> Result<usize> {
+    let input = input.trim();
+    let selection = input
+        .parse::<usize>()
+        .map_err(|_| anyhow!("`{input}` is not a number"))?;
+    if selection == 0 || selection > count {
+        return Err(anyhow!("{selection} is not between 1 and {count}"));
+    }
+    Ok(selection - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use test_case::test_case;
+    use url::Url;
+
+    #[test_case("1", 3, Ok(0) ; "first")]
+    #[test_case("3\n", 3, Ok(2) ; "last with newline")]
+    #[test_case("  2  \r\n", 3, Ok(1) ; "surrounded by whitespace")]
+    #[test_case("0", 3, Err("0 is not between 1 and 3") ; "zero")]
+    #[test_case("4", 3, Err("4 is not between 1 and 3") ; "too large")]
+    #[test_case("two", 3, Err("`two` is not a number") ; "not a number")]
+    #[test_case("-1", 3, Err("`-1` is not a number") ; "negative")]
+    #[test_case("", 3, Err("`` is not a number") ; "empty")]
+    fn parse_selection(input: &str, count: usize, expect: Result<usize, &str>) {
+        let got = super::parse_selection(input, count).map_err(|e| e.to_string());
+        assert_eq!(got, expect.map_err(str::to_string));
+    }
+
+    fn assets(names: &[&str]) -> Vec<Asset> {
+        let url = Url::parse("https://example.com").unwrap();
+        names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn choose_asset_lists_sorted_candidates() -> Result<()> {
+        let mut input = Cursor::new("2\n");
+        let mut output = vec![];
+        let asset = choose_asset(
+            assets(&["project-b.tar.gz", "project-a.tar.gz", "project-c.tar.gz"]),
+            "Found multiple release assets",
+            &mut input,
+            &mut output,
+        )?;
+        assert_eq!(asset.name, "project-b.tar.gz");
+        assert_eq!(
+            String::from_utf8(output)?,
+            concat!(
+                "Found multiple release assets:\n",
+                "  1) project-a.tar.gz\n",
+                "  2) project-b.tar.gz\n",
+                "  3) project-c.tar.gz\n",
+                "Enter the number of the asset to install (1-3): ",
+            ),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn choose_asset_asks_again_after_invalid_selection() -> Result<()> {
+        let mut input = Cursor::new("foo\n7\n1\n");
+        let mut output = vec![];
+        let asset = choose_asset(
+            assets(&["project-b.tar.gz", "project-a.tar.gz"]),
+            "Found multiple release assets",
+            &mut input,
+            &mut output,
+        )?;
+        assert_eq!(asset.name, "project-a.tar.gz");
+
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("`foo` is not a number\n"), "{output}");
+        assert!(output.contains("7 is not between 1 and 2\n"), "{output}");
+        assert_eq!(
+            output
+                .matches("Enter the number of the asset to install (1-2): ")
+                .count(),
+            3,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn choose_asset_at_eof() {
+        let mut input = Cursor::new("5\n");
+        let mut output = vec![];
+        let res = choose_asset(
+            assets(&["project-b.tar.gz", "project-a.tar.gz"]),
+            "Found multiple release assets",
+            &mut input,
+            &mut output,
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "no release asset was selected"
+        );
+    }
+
+    #[test]
+    fn choose_asset_without_candidates() {
+        let mut input = Cursor::new("1\n");
+        let mut output = vec![];
+        let res = choose_asset(vec![], "Found nothing", &mut input, &mut output);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "there are no release assets to choose from"
+        );
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+// We don't link to each platform's keyring APIs. Instead, we run the command line tool that each
+// platform provides for this, `security` on macOS and `secret-tool` from libsecret everywhere else.
+use crate::forge::ForgeType;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    fmt::Debug,
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+// Every entry is stored with this as its account name, alongside a service name like `ubi:github`.
+const ACCOUNT: &str = "ubi";
+
+pub(crate) trait Keyring: Debug + Send + Sync {
+    fn get(&self, service: &str) -> Result<Option<String>>;
+    fn set(&self, service: &str, secret: &str) -> Result<()>;
+    // Returns false if there was no entry to delete.
+    fn delete(&self, service: &str) -> Result<bool>;
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct OsKeyring;
+
+pub(crate) fn service_name(forge: &ForgeType) -> String {
+    format!("ubi:{}", forge.as_ref())
+}
+
+/// Stores a token for the given forge in the OS keyring. It is stored with the service name
+/// `ubi:<forge>`, like `ubi:github`. See [`UbiBuilder::use_keyring`](crate::UbiBuilder::use_keyring)
+/// for details.
+///
+/// # Errors
+///
+/// This returns an error if the OS keyring tool cannot be run or fails to store the token.
+pub fn set_keyring_token(forge: &ForgeType, token: &str) -> Result<()> {
+    OsKeyring.set(&service_name(forge), token)
+}
+
+/// Deletes the token for the given forge from the OS keyring. This returns `false` if there was no
+/// token to delete.
+///
+/// # Errors
+///
+/// This returns an error if the OS keyring tool cannot be run or fails to delete the token.
+pub fn delete_keyring_token(forge: &ForgeType) -> Result<bool> {
+    OsKeyring.delete(&service_name(forge))
+}
+
+#[cfg(target_os = "macos")]
+impl Keyring for OsKeyring {
+    fn get(&self, service: &str) -> Result<Option<String>> {
+        let output = run(
+            Command::new("security").args([
+                "find-generic-password",
+                "-s",
+                service,
+                "-a",
+                ACCOUNT,
+                "-w",
+            ]),
+            None,
+        )?;
+        if output.status.code() == Some(SECURITY_ITEM_NOT_FOUND) {
+            return Ok(None);
+        }
+        check_status("security find-generic-password", &output)?;
+        Ok(secret_from_stdout(&output))
+    }
+
+    // The `security` tool only accepts the password as an argument or by prompting for it on a
+    // terminal, so this passes it as an argument.
+    fn set(&self, service: &str, secret: &str) -> Result<()> {
+        let output = run(
+            Command::new("security").args([
+                "add-generic-password",
+                "-U",
+                "-s",
+                service,
+                "-a",
+                ACCOUNT,
+                "-w",
+                secret,
+            ]),
+            None,
+        )?;
+        check_status("security add-generic-password", &output)
+    }
+
+    fn delete(&self, service: &str) -> Result<bool> {
+        let output = run(
+            Command::new("security").args([
+                "delete-generic-password",
+                "-s",
+                service,
+                "-a",
+                ACCOUNT,
+            ]),
+            None,
+        )?;
+        if output.status.code() == Some(SECURITY_ITEM_NOT_FOUND) {
+            return Ok(false);
+        }
+        check_status("security delete-generic-password", &output)?;
+        Ok(true)
+    }
+}
+
+// This is `errSecItemNotFound`, which `security` uses as its exit code.
+#[cfg(target_os = "macos")]
+const SECURITY_ITEM_NOT_FOUND: i32 = 44;
+
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+impl Keyring for OsKeyring {
+    // `secret-tool lookup` exits with 1 and prints nothing when there is no matching entry.
+    fn get(&self, service: &str) -> Result<Option<String>> {
+        let output = run(
+            Command::new("secret-tool").args(["lookup", "service", service, "account", ACCOUNT]),
+            None,
+        )?;
+        if !output.status.success() && output.stdout.is_empty() && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        check_status("secret-tool lookup", &output)?;
+        Ok(secret_from_stdout(&output))
+    }
+
+    fn set(&self, service: &str, secret: &str) -> Result<()> {
+        let label = format!("ubi token for {service}");
+        let output = run(
+            Command::new("secret-tool").args([
+                "store", "--label", &label, "service", service, "account", ACCOUNT,
+            ]),
+            Some(secret),
+        )?;
+        check_status("secret-tool store", &output)
+    }
+
+    // `secret-tool clear` succeeds whether or not there was an entry, so we look for it first.
+    fn delete(&self, service: &str) -> Result<bool> {
+        if self.get(service)?.is_none() {
+            return Ok(false);
+        }
+        let output = run(
+            Command::new("secret-tool").args(["clear", "service", service, "account", ACCOUNT]),
+            None,
+        )?;
+        check_status("secret-tool clear", &output)?;
+        Ok(true)
+    }
+}
+
+#[cfg(target_family = "windows")]
+impl Keyring for OsKeyring {
+    fn get(&self, _service: &str) -> Result<Option<String>> {
+        Err(windows_not_supported())
+    }
+
+    fn set(&self, _service: &str, _secret: &str) -> Result<()> {
+        Err(windows_not_supported())
+    }
+
+    fn delete(&self, _service: &str) -> Result<bool> {
+        Err(windows_not_supported())
+    }
+}
+
+#[cfg(target_family = "windows")]
+fn windows_not_supported() -> anyhow::Error {
+    anyhow!("storing tokens in the OS keyring is not supported on Windows")
+}
+
+#[cfg_attr(target_family = "windows", allow(dead_code))]
+fn run(cmd: &mut Command, stdin: Option<&str>) -> Result<Output> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    debug!("running {program} to access the OS keyring");
+    let mut child = cmd
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run `{program}` to access the OS keyring"))?;
+    if let Some(input) = stdin {
+        child
+            .stdin
+            .take()
+            .expect("the child process has a stdin handle")
+            .write_all(input.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+#[cfg_attr(target_family = "windows", allow(dead_code))]
+fn check_status(what: &str, output: &Output) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "`{what}` failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim(),
+    ))
+}
+
+#[cfg_attr(target_family = "windows", allow(dead_code))]
+fn secret_from_stdout(output: &Output) -> Option<String> {
+    let secret = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!secret.is_empty()).then_some(secret)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{collections::HashMap, sync::Mutex};
+    use test_case::test_case;
+
+    // An in-memory keyring for tests, since we don't want to touch the real one.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockKeyring {
+        entries: Mutex<HashMap<String, String>>,
+    }
+
+    impl Keyring for MockKeyring {
+        fn get(&self, service: &str) -> Result<Option<String>> {
+            Ok(self.entries.lock().unwrap().get(service).cloned())
+        }
+
+        fn set(&self, service: &str, secret: &str) -> Result<()> {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(service.to_string(), secret.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, service: &str) -> Result<bool> {
+            Ok(self.entries.lock().unwrap().remove(service).is_some())
+        }
+    }
+
+    #[test_case(ForgeType::AzureDevOps, "ubi:azure-devops")]
+    #[test_case(ForgeType::Bitbucket, "ubi:bitbucket")]
+    #[test_case(ForgeType::Gitea, "ubi:gitea")]
+    #[test_case(ForgeType::GitHub, "ubi:github")]
+    #[test_case(ForgeType::GitLab, "ubi:gitlab")]
+    #[test_case(ForgeType::Oci, "ubi:oci")]
+    #[test_case(ForgeType::SourceHut, "ubi:sourcehut")]
+    fn service_name(forge: ForgeType, expect: &str) {
+        assert_eq!(super::service_name(&forge), expect);
+    }
+}
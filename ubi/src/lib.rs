@@ -52,33 +52,60 @@
 //! project and tries to find the "right" asset for the platform it's running on. The matching logic
 //! currently works like this:
 //!
+//! If you set [`UbiBuilder::exact_asset_name`], then it simply picks the asset with that exact name,
+//! and none of the steps below are used.
+//!
+//! If you set [`UbiBuilder::matching_regex`], then any asset whose name doesn't match the regex is
+//! filtered out before any of the steps below.
+//!
 //! First it filters out assets with extensions it doesn't recognize. Right now this is anything that
 //! doesn't match one of the following:
 //!
+//! - `.7z`
 //! - `.AppImage` (Linux only)
 //! - `.bat` (Windows only)
+//! - `.br`
 //! - `.bz`
 //! - `.bz2`
+//! - `.dmg` (macOS only)
 //! - `.exe` (Windows only)
 //! - `.gz`
 //! - `.jar`
+//! - `.lzma`
 //! - `.pyz`
 //! - `.tar`
+//! - `.tar.br`
 //! - `.tar.bz`
 //! - `.tar.bz2`
 //! - `.tar.gz`
+//! - `.tar.lzma`
 //! - `.tar.xz`
+//! - `.tar.zst`
 //! - `.tbz`
+//! - `.tbz2`
 //! - `.tgz`
 //! - `.txz`
+//! - `.tzst`
 //! - `.xz`
 //! - `.zip`
+//! - `.zst`
 //! - No extension
 //!
 //! It tries to be careful about what constitutes an extension. It's common for release filenames to
 //! include a dot (`.`) in the filename before something that's _not_ intended as an extension, for
 //! example `some-tool.linux.amd64`.
 //!
+//! An asset that is split into parts named like `project.tar.gz.001`, `project.tar.gz.002`, and so
+//! on is treated as a single asset with the extension before the part number. Only the first part
+//! is considered when picking an asset, and when it is picked, every part is downloaded and the
+//! parts are joined in order before the asset is extracted. If a part is missing from the release,
+//! `ubi` returns an error.
+//!
+//! Next it filters out source archives. These are GitHub's auto-generated `Source code` archives,
+//! as well as tarballs and zip files with names like `project-1.2.3.tar.gz` or `project-src.zip`
+//! that don't mention any OS or CPU architecture. If the release only contains source archives,
+//! `ubi` will return an error saying that no platform binary was found.
+//!
 //! If, after filtering for extensions, there's only one asset, it will try to install this one, on
 //! the assumption that this project releases assets which are not platform-specific (like a shell
 //! script) _or_ that this project only releases for one platform and you're running `ubi` on that
@@ -91,29 +118,48 @@
 //!   etc. It looks at the asset filenames to see which ones match your OS, using a (hopefully
 //!   complete) regex.
 //! - Next it filters based on your CPU architecture, which is something like x86-64, ARM64, PowerPC,
-//!   etc. Again, this is done with a regex.
-//! - If you are running on a Linux system using musl as its libc, it will also filter out anything
-//!   _not_ compiled against musl. This filter looks to see if the file name contains an indication
-//!   of which libc it was compiled against. Typically, this is something like "-gnu" or "-musl". If
-//!   it does contain this indicator, names that are _not_ musl are filtered out. However, if there
-//!   is no libc indicator, the asset will still be included. You can use the
-//!   [`UbiBuilder::is_musl`] method to explicitly say that the platform is using musl. If this
-//!   isn't set, then it will try to detect if you are using musl by looking at the output of `ldd
-//!   /bin/ls`.
+//!   etc. Again, this is done with a regex. Both the Rust-style and Go-style (`GOOS`/`GOARCH`)
+//!   names are recognized, so `amd64` matches x86-64, `arm64` matches AArch64, `386` and `x86`
+//!   match 32-bit x86, and `darwin` matches macOS.
+//! - On Linux, it then filters based on your libc. This filter looks to see if the file name
+//!   contains an indication of which libc it was compiled against. Typically, this is something like
+//!   "-gnu" or "-musl". If any names match your libc, only those are kept. Otherwise, names with no
+//!   libc indicator are kept. If you are running on a system using musl, names that indicate glibc
+//!   are never kept, but on a glibc system it will fall back to musl assets if there's nothing
+//!   else. You can use the [`UbiBuilder::libc`] or [`UbiBuilder::is_musl`] methods to explicitly
+//!   say which libc the platform is using. If neither is set, then it will try to detect if you are
+//!   using musl by looking at the output of `ldd /bin/ls`.
 //!
 //! At this point, any remaining assets should work on your platform, so if there's more than one
 //! match, it attempts to pick the best one.
 //!
 //! - If it finds both 64-bit and 32-bit assets and you are on a 64-bit platform, it filters out the
 //!   32-bit assets.
+//! - If you are on a 32-bit ARM platform whose target ends with "eabihf" or "eabi", it prefers
+//!   assets for the same float ABI. It looks for names like "armhf" or "gnueabihf" for hard-float,
+//!   and "armel" or "gnueabi" for soft-float. If there are none, it prefers assets with no float ABI
+//!   in their name.
+//! - If some of the assets contain the version from the release's tag, like `1.2.3` for the tag
+//!   `v1.2.3`, it keeps only those. A version that's part of a longer version, like `1.2.30`,
+//!   doesn't count. A tag with only a major version, like `v2`, is ignored here.
 //! - If you've provided a string to [`UbiBuilder::matching`], this is used as a filter at this
 //!   point.
-//! - If your platform is macOS on ARM64 and there are assets for both x86-64 and ARM64, it filters
-//!   out the non-ARM64 assets.
+//! - If you've called [`UbiBuilder::prefer_static`], it keeps only the assets with "static" in
+//!   their name. If there are none, it filters out assets with "dynamic" in their name instead.
+//! - On Windows, it prefers assets built with MSVC, which are those with "msvc" in their name. If
+//!   you've called [`UbiBuilder::prefer_gnu_on_windows`], it prefers assets with "gnu", "gnullvm",
+//!   or "mingw" in their name instead. If there are no such assets, it keeps all of them.
+//! - On macOS, it prefers an asset for your CPU architecture, then a universal binary, which is an
+//!   asset with "universal", "universal2", "fat", or "all" in its name. On ARM64, it will fall back
+//!   to an x86-64 asset after that, since these can run under Rosetta.
 //!
 //! Finally, if there are still multiple assets left, it sorts them by file name and picks the first
 //! one. The sorting is done to make sure it always picks the same one every time it's run .
 //!
+//! If you've called [`UbiBuilder::interactive`] and stdin is a terminal, `ubi` instead prints a
+//! numbered list of the remaining assets and asks the user to choose one. It does the same if none
+//! of the assets match the OS, architecture, and libc, listing every asset with a valid extension.
+//!
 //! ## How `ubi` Finds the Right Executable in an Archive File
 //!
 //! If the selected release artifact is an archive file (a tarball or zip file), then `ubi` will
@@ -142,21 +188,71 @@
 #![doc = document_features::document_features!()]
 
 mod arch;
+mod azure_devops;
+mod batch;
+mod binary_arch;
+mod bitbucket;
 mod builder;
+mod cache;
+mod checksum;
+#[cfg(target_os = "macos")]
+mod dmg;
 mod extension;
 mod forge;
+mod gitea;
 mod github;
 mod gitlab;
+mod gpg;
+mod install_lock;
+mod install_meta;
 mod installer;
+mod interactive;
+mod keyring;
+mod macos_version;
+mod manifest;
+mod netrc;
+mod oci;
 mod os;
+mod package;
 mod picker;
+mod post_install;
+mod progress;
+mod project;
+mod rate_limit;
+mod release_cache;
+mod retry;
+mod self_test;
+mod self_upgrade;
+mod signature;
+mod sourcehut;
+mod split_asset;
+mod style;
 #[cfg(test)]
 mod test;
 #[cfg(test)]
 mod test_case;
+mod tls;
 mod ubi;
+mod version;
 
-pub use crate::{builder::UbiBuilder, forge::ForgeType, ubi::Ubi};
+pub use crate::{
+    batch::{install_many, InstallSpec, InstalledBinary, DEFAULT_INSTALL_MANY_CONCURRENCY},
+    builder::UbiBuilder,
+    forge::{ForgeType, Release},
+    keyring::{delete_keyring_token, set_keyring_token},
+    manifest::{
+        install_from_lockfile, install_from_manifest, LockedTool, Lockfile, Manifest, ManifestTool,
+        DEFAULT_LOCKFILE_NAME, DEFAULT_MANIFEST_FILE_NAME,
+    },
+    picker::{Libc, ScoringWeights},
+    progress::DownloadProgress,
+    project::{parse_project, ProjectRef},
+    self_test::{self_test, SelfTestReport, TokenEnvVar},
+    self_upgrade::{self_upgrade, SelfUpgrade},
+    style::ColorChoice,
+    tls::TlsVersion,
+    ubi::{Asset, InstallPlan, InstallResult, Ubi},
+};
 
 // The version of the `ubi` crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -168,32 +264,20 @@ use fern::{
 };
 
 /// This function initializes logging for the application. It's public for the sake of the `ubi`
-/// binary, but it lives in the library crate so that test code can also enable logging.
+/// binary, but it lives in the library crate so that test code can also enable logging. Log lines
+/// are written to stderr, and the `color` choice is used to decide whether they are colored.
 ///
 /// # Errors
 ///
 /// This can return a `log::SetLoggerError` error.
 #[cfg(feature = "logging")]
-pub fn init_logger(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
-    let line_colors = ColoredLevelConfig::new()
-        .error(Color::Red)
-        .warn(Color::Yellow)
-        .info(Color::BrightBlack)
-        .debug(Color::BrightBlack)
-        .trace(Color::BrightBlack);
-    let level_colors = line_colors.info(Color::Green).debug(Color::Black);
-
+pub fn init_logger(level: log::LevelFilter, color: ColorChoice) -> Result<(), log::SetLoggerError> {
+    let use_color = color.use_color_on_stderr();
     Dispatch::new()
         .format(move |out, message, record| {
             out.finish(format_args!(
-                "{color_line}[{target}][{level}{color_line}] {message}\x1B[0m",
-                color_line = format_args!(
-                    "\x1B[{}m",
-                    line_colors.get_color(&record.level()).to_fg_str()
-                ),
-                target = record.target(),
-                level = level_colors.color(record.level()),
-                message = message,
+                "{}",
+                log_line(record.level(), record.target(), message, use_color),
             ));
         })
         .level(level)
@@ -202,3 +286,50 @@ pub fn init_logger(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
         .chain(std::io::stderr())
         .apply()
 }
+
+#[cfg(feature = "logging")]
+fn log_line(
+    level: log::Level,
+    target: &str,
+    message: &std::fmt::Arguments<'_>,
+    use_color: bool,
+) -> String {
+    if !use_color {
+        return format!("[{target}][{level}] {message}");
+    }
+
+    let line_colors = ColoredLevelConfig::new()
+        .error(Color::Red)
+        .warn(Color::Yellow)
+        .info(Color::BrightBlack)
+        .debug(Color::BrightBlack)
+        .trace(Color::BrightBlack);
+    let level_colors = line_colors.info(Color::Green).debug(Color::Black);
+    format!(
+        "{color_line}[{target}][{level}{color_line}] {message}\x1B[0m",
+        color_line = format_args!("\x1B[{}m", line_colors.get_color(&level).to_fg_str()),
+        level = level_colors.color(level),
+    )
+}
+
+#[cfg(all(test, feature = "logging"))]
+mod tests {
+    use super::log_line;
+    use log::Level;
+
+    #[test]
+    fn log_line_without_color() {
+        assert_eq!(
+            log_line(Level::Error, "ubi", &format_args!("it failed"), false),
+            "[ubi][ERROR] it failed",
+        );
+    }
+
+    #[test]
+    fn log_line_with_color() {
+        assert_eq!(
+            log_line(Level::Error, "ubi", &format_args!("it failed"), true),
+            "\x1B[31m[ubi][\x1B[31mERROR\x1B[0m\x1B[31m] it failed\x1B[0m",
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use lazy_regex::regex_captures;
+use log::debug;
+use std::{fmt, process::Command};
+
+// Some projects build a separate macOS asset for each minimum OS version they support, with names
+// like `project-macos12-arm64.tar.gz`. If we pick one for a newer OS than the one we're running on,
+// the executable fails at runtime, so the picker compares these against the running version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct MacOsVersion {
+    major: u32,
+    minor: u32,
+}
+
+impl MacOsVersion {
+    // `sysctl` is faster, but `kern.osproductversion` only exists on macOS 10.13.4 and later, so we
+    // fall back to `sw_vers` if it fails.
+    pub(crate) fn running() -> Option<Self> {
+        if std::env::consts::OS != "macos" {
+            return None;
+        }
+
+        let version = [
+            ("sysctl", &["-n", "kern.osproductversion"][..]),
+            ("sw_vers", &["-productVersion"][..]),
+        ]
+        .into_iter()
+        .find_map(|(cmd, args)| {
+            let output = Command::new(cmd).args(args).output().ok()?;
+            if !output.status.success() {
+                debug!("running `{cmd}` to get the macOS version failed");
+                return None;
+            }
+            Self::parse(String::from_utf8_lossy(&output.stdout).trim())
+        });
+        match version {
+            Some(v) => debug!("running on macOS {v}"),
+            None => debug!("could not determine the running macOS version"),
+        }
+        version
+    }
+
+    // This parses a version like `13.4.1`. We ignore the patch version, since no one builds assets
+    // for one.
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        let (_, major, minor) = regex_captures!(r"^(\d+)(?:\.(\d+))?(?:\.\d+)*$", version)?;
+        Some(Self {
+            major: major.parse().ok()?,
+            minor: if minor.is_empty() {
+                0
+            } else {
+                minor.parse().ok()?
+            },
+        })
+    }
+
+    // The version must be attached to the OS name, as in `macos12` or `osx10.15`. We don't look at
+    // a version after a separator, like `macos-12`, since that's often the project's own version.
+    pub(crate) fn from_asset_name(name: &str) -> Option<Self> {
+        let (_, version) =
+            regex_captures!(r"(?i)(?:\b|_)(?:macosx?|osx)(\d+(?:\.\d+)?)(?:\b|_)", name)?;
+        Self::parse(version)
+    }
+}
+
+impl fmt::Display for MacOsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("13", Some((13, 0)) ; "major only")]
+    #[test_case("13.4", Some((13, 4)) ; "major and minor")]
+    #[test_case("13.4.1", Some((13, 4)) ; "with patch")]
+    #[test_case("10.15.7", Some((10, 15)) ; "old style")]
+    #[test_case("", None ; "empty")]
+    #[test_case("thirteen", None ; "not a number")]
+    fn parse(version: &str, expect: Option<(u32, u32)>) {
+        assert_eq!(
+            MacOsVersion::parse(version),
+            expect.map(|(major, minor)| MacOsVersion { major, minor }),
+        );
+    }
+
+    #[test_case("project-macos12-arm64.tar.gz", Some((12, 0)) ; "macos")]
+    #[test_case("project_macOS13_x86_64.tar.gz", Some((13, 0)) ; "underscores and mixed case")]
+    #[test_case("project-macosx11.0-arm64.tar.gz", Some((11, 0)) ; "macosx with minor")]
+    #[test_case("project-osx10.15-x86_64.tar.gz", Some((10, 15)) ; "osx")]
+    #[test_case("project-macos-arm64.tar.gz", None ; "no version")]
+    #[test_case("project-macos-12.0.0-arm64.tar.gz", None ; "version after a separator")]
+    #[test_case("project-darwin-arm64.tar.gz", None ; "darwin")]
+    fn from_asset_name(name: &str, expect: Option<(u32, u32)>) {
+        assert_eq!(
+            MacOsVersion::from_asset_name(name),
+            expect.map(|(major, minor)| MacOsVersion { major, minor }),
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let v = |s| MacOsVersion::parse(s).unwrap();
+        assert!(v("10.15") < v("11"));
+        assert!(v("12") < v("12.1"));
+        assert!(v("13.4.1") == v("13.4"));
+    }
+}
@@ -0,0 +1,809 @@
+use crate::{
+    batch::{install_many, InstallSpec, InstalledBinary},
+    forge::ForgeType,
+    version::{Version, VersionReq},
+    UbiBuilder,
+};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+use log::debug;
+use std::{fmt, fs, path::Path, str::FromStr};
+
+/// The default name of the manifest file, `ubi.toml`.
+pub const DEFAULT_MANIFEST_FILE_NAME: &str = "ubi.toml";
+/// The default name of the lockfile, `ubi.lock`.
+pub const DEFAULT_LOCKFILE_NAME: &str = "ubi.lock";
+
+const TARGET: &str = env!("TARGET");
+const TOOLS_TABLE: &str = "tools";
+const LOCKFILE_HEADER: &str = "# This file is generated by ubi. Do not edit it by hand.";
+
+/// `Manifest` is a list of tools to install, usually read from a `ubi.toml` file. Each tool is a
+/// `[[tools]]` table:
+///
+/// ```toml
+/// [[tools]]
+/// project = "houseabsolute/precious"
+/// version = "^0.7"
+///
+/// [[tools]]
+/// project = "BurntSushi/ripgrep"
+/// tag = "14.1.1"
+/// exe = "rg"
+/// ```
+///
+/// See [`ManifestTool`] for all of the keys a table can have. Only the subset of TOML needed for
+/// this is supported: `[[tools]]` headers, comments, and keys with string values on a single line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// The tools to install, in the order they appear in the manifest.
+    pub tools: Vec<ManifestTool>,
+}
+
+/// `ManifestTool` is one tool in a [`Manifest`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManifestTool {
+    /// The project to install, from the `project` key. See [`UbiBuilder::project`] for the
+    /// accepted formats. This is required.
+    pub project: String,
+    /// The tag to install, from the `tag` key. See [`UbiBuilder::tag`].
+    pub tag: Option<String>,
+    /// A version requirement for the release to install, like `^1.2`, from the `version` key. See
+    /// [`UbiBuilder::version_req`]. You cannot set both this and `tag`.
+    pub version: Option<String>,
+    /// The name of the executable, from the `exe` key. See [`UbiBuilder::exe`].
+    pub exe: Option<String>,
+    /// The forge to use, like `gitlab`, from the `forge` key. See [`UbiBuilder::forge`].
+    pub forge: Option<ForgeType>,
+    /// The base URL for the forge's API, from the `api_base_url` key. See
+    /// [`UbiBuilder::api_base_url`].
+    pub api_base_url: Option<String>,
+}
+
+/// `Lockfile` records exactly what was installed from a [`Manifest`], usually in a `ubi.lock`
+/// file. It is written by [`install_from_manifest`] and checked by [`install_from_lockfile`].
+///
+/// Since each platform installs a different release asset, a tool has one entry for each target
+/// platform it has been installed on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lockfile {
+    /// The locked tools, sorted by project and then target.
+    pub tools: Vec<LockedTool>,
+}
+
+/// `LockedTool` is one entry in a [`Lockfile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockedTool {
+    /// The project, as given in the manifest.
+    pub project: String,
+    /// The target triple of the platform it was installed on, like `x86_64-unknown-linux-musl`.
+    pub target: String,
+    /// The tag of the release that was installed.
+    pub tag: String,
+    /// The name of the release asset that was installed.
+    pub asset_name: String,
+    /// The SHA-256 digest of the release asset, as a lowercase hex string.
+    pub sha256: String,
+}
+
+impl Manifest {
+    /// Reads a manifest from the given file.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file cannot be read or is not a valid manifest.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("could not read the manifest at {}", path.display()))?;
+        contents
+            .parse()
+            .with_context(|| format!("could not parse the manifest at {}", path.display()))
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tools = parse_tables(s)?
+            .into_iter()
+            .map(ManifestTool::from_table)
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(project) = tools.iter().map(|t| &t.project).duplicates().next() {
+            return Err(anyhow!(
+                "the project {project} is listed more than once in the manifest"
+            ));
+        }
+        Ok(Self { tools })
+    }
+}
+
+impl ManifestTool {
+    fn from_table(table: Table) -> Result<Self> {
+        let mut tool = ManifestTool::default();
+        let mut project = None;
+        for (key, value, line) in table.pairs {
+            match key.as_str() {
+                "project" => project = Some(value),
+                "tag" => tool.tag = Some(value),
+                "version" => tool.version = Some(value),
+                "exe" => tool.exe = Some(value),
+                "forge" => {
+                    tool.forge = Some(
+                        ForgeType::from_str(&value)
+                            .map_err(|_| anyhow!("line {line}: `{value}` is not a valid forge"))?,
+                    );
+                }
+                "api_base_url" => tool.api_base_url = Some(value),
+                _ => {
+                    return Err(anyhow!(
+                        "line {line}: unknown key `{key}` in a [[tools]] table"
+                    ))
+                }
+            }
+        }
+
+        let Some(project) = project else {
+            return Err(anyhow!(
+                "line {}: the [[tools]] table has no `project` key",
+                table.line,
+            ));
+        };
+        if tool.tag.is_some() && tool.version.is_some() {
+            return Err(anyhow!(
+                "line {}: the [[tools]] table for {project} cannot have both `tag` and `version`",
+                table.line,
+            ));
+        }
+        tool.project = project;
+        Ok(tool)
+    }
+
+    fn builder(&self) -> UbiBuilder<'_> {
+        let mut builder = UbiBuilder::new();
+        if let Some(exe) = &self.exe {
+            builder = builder.exe(exe);
+        }
+        if let Some(forge) = &self.forge {
+            builder = builder.forge(forge.clone());
+        }
+        if let Some(url) = &self.api_base_url {
+            builder = builder.api_base_url(url);
+        }
+        builder
+    }
+
+    // When installing from a lockfile, the manifest may have changed since the lockfile was
+    // written, in which case the locked release may not be what the manifest asks for.
+    fn check_locked_tag(&self, locked: &LockedTool) -> Result<()> {
+        let matches = match (&self.tag, &self.version) {
+            (Some(tag), _) => *tag == locked.tag,
+            (None, Some(version)) => {
+                let req = VersionReq::from_str(version)?;
+                Version::from_tag(&locked.tag).is_some_and(|v| req.matches(&v))
+            }
+            (None, None) => true,
+        };
+        if matches {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "the lockfile has the tag {} for {}, which does not match the manifest, so you need to install without using the lockfile to update it",
+            locked.tag,
+            self.project,
+        ))
+    }
+}
+
+impl Lockfile {
+    /// Reads a lockfile from the given file.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file cannot be read or is not a valid lockfile.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("could not read the lockfile at {}", path.display()))?;
+        contents
+            .parse()
+            .with_context(|| format!("could not parse the lockfile at {}", path.display()))
+    }
+
+    /// Writes the lockfile to the given file, replacing it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file cannot be written.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())
+            .with_context(|| format!("could not write the lockfile at {}", path.display()))
+    }
+
+    fn find(&self, project: &str, target: &str) -> Option<&LockedTool> {
+        self.tools
+            .iter()
+            .find(|t| t.project == project && t.target == target)
+    }
+}
+
+impl FromStr for Lockfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tools = parse_tables(s)?
+            .into_iter()
+            .map(LockedTool::from_table)
+            .collect::<Result<Vec<_>>>()?;
+        tools.sort_by(|a, b| (&a.project, &a.target).cmp(&(&b.project, &b.target)));
+        Ok(Self { tools })
+    }
+}
+
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{LOCKFILE_HEADER}")?;
+        for tool in &self.tools {
+            writeln!(f)?;
+            writeln!(f, "[[{TOOLS_TABLE}]]")?;
+            for (key, value) in [
+                ("project", &tool.project),
+                ("target", &tool.target),
+                ("tag", &tool.tag),
+                ("asset", &tool.asset_name),
+                ("sha256", &tool.sha256),
+            ] {
+                writeln!(f, "{key} = {}", quote(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LockedTool {
+    fn from_table(table: Table) -> Result<Self> {
+        let mut project = None;
+        let mut target = None;
+        let mut tag = None;
+        let mut asset_name = None;
+        let mut sha256 = None;
+        for (key, value, line) in table.pairs {
+            let field = match key.as_str() {
+                "project" => &mut project,
+                "target" => &mut target,
+                "tag" => &mut tag,
+                "asset" => &mut asset_name,
+                "sha256" => &mut sha256,
+                _ => {
+                    return Err(anyhow!(
+                        "line {line}: unknown key `{key}` in a [[tools]] table"
+                    ))
+                }
+            };
+            *field = Some(value);
+        }
+
+        let missing = |key| {
+            anyhow!(
+                "line {}: the [[tools]] table has no `{key}` key",
+                table.line
+            )
+        };
+        Ok(Self {
+            project: project.ok_or_else(|| missing("project"))?,
+            target: target.ok_or_else(|| missing("target"))?,
+            tag: tag.ok_or_else(|| missing("tag"))?,
+            asset_name: asset_name.ok_or_else(|| missing("asset"))?,
+            sha256: sha256.ok_or_else(|| missing("sha256"))?,
+        })
+    }
+}
+
+/// Installs every tool in the manifest and returns a lockfile recording the tag and SHA-256 digest
+/// of each release asset that was installed. The tools are installed with [`install_many`], using
+/// the given `concurrency`.
+///
+/// If `lockfile` is given, then its entries for other platforms are copied to the returned
+/// lockfile, as long as their project is still in the manifest. Entries for this platform are
+/// always replaced.
+///
+/// # Errors
+///
+/// This returns an error if any of the tools cannot be installed. The other tools are still
+/// installed in that case.
+pub async fn install_from_manifest(
+    manifest: &Manifest,
+    lockfile: Option<&Lockfile>,
+    install_dir: Option<&Path>,
+    concurrency: usize,
+) -> Result<Lockfile> {
+    let specs = manifest
+        .tools
+        .iter()
+        .map(|tool| {
+            let mut builder = tool.builder();
+            if let Some(version) = &tool.version {
+                builder = builder.version_req(version);
+            }
+            let mut spec = InstallSpec::new(&tool.project).builder(builder);
+            if let Some(tag) = &tool.tag {
+                spec = spec.tag(tag);
+            }
+            if let Some(dir) = install_dir {
+                spec = spec.install_dir(dir);
+            }
+            spec
+        })
+        .collect();
+    let installed = all_installed(install_many(specs, concurrency).await)?;
+
+    let mut tools = lockfile
+        .map(|l| {
+            l.tools
+                .iter()
+                .filter(|t| {
+                    t.target != TARGET && manifest.tools.iter().any(|m| m.project == t.project)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for binary in installed {
+        let missing = |what| {
+            anyhow!(
+                "could not determine the {what} of the release asset that was installed for {}",
+                binary.project,
+            )
+        };
+        tools.push(LockedTool {
+            target: TARGET.to_string(),
+            tag: binary.tag.clone().ok_or_else(|| missing("tag"))?,
+            sha256: binary
+                .sha256
+                .clone()
+                .ok_or_else(|| missing("SHA-256 digest"))?,
+            asset_name: binary.asset_name,
+            project: binary.project,
+        });
+    }
+    tools.sort_by(|a, b| (&a.project, &a.target).cmp(&(&b.project, &b.target)));
+
+    Ok(Lockfile { tools })
+}
+
+/// Installs every tool in the manifest using the exact release asset recorded in the lockfile for
+/// this platform. Installation of a tool fails if the downloaded asset's SHA-256 digest does not
+/// match the one in the lockfile. The tools are installed with [`install_many`], using the given
+/// `concurrency`.
+///
+/// # Errors
+///
+/// This returns an error without installing anything if the lockfile has no entry for a tool on
+/// this platform, or if a locked tag doesn't match the tag or version in the manifest. Otherwise,
+/// it returns an error if any of the tools cannot be installed, including when a digest does not
+/// match. The other tools are still installed in that case.
+pub async fn install_from_lockfile(
+    manifest: &Manifest,
+    lockfile: &Lockfile,
+    install_dir: Option<&Path>,
+    concurrency: usize,
+) -> Result<()> {
+    let specs = manifest
+        .tools
+        .iter()
+        .map(|tool| {
+            let Some(locked) = lockfile.find(&tool.project, TARGET) else {
+                return Err(anyhow!(
+                    "the lockfile has no entry for {} on {TARGET}, so you need to install without using the lockfile to update it",
+                    tool.project,
+                ));
+            };
+            tool.check_locked_tag(locked)?;
+            debug!(
+                "installing {} from the lockfile with the tag {} and the asset {}",
+                tool.project, locked.tag, locked.asset_name,
+            );
+
+            let builder = tool
+                .builder()
+                .exact_asset_name(&locked.asset_name)
+                .expect_sha256(&locked.sha256);
+            let mut spec = InstallSpec::new(&tool.project)
+                .tag(&locked.tag)
+                .builder(builder);
+            if let Some(dir) = install_dir {
+                spec = spec.install_dir(dir);
+            }
+            Ok(spec)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    all_installed(install_many(specs, concurrency).await)?;
+
+    Ok(())
+}
+
+fn all_installed(results: Vec<Result<InstalledBinary>>) -> Result<Vec<InstalledBinary>> {
+    let (installed, errors): (Vec<_>, Vec<_>) = results.into_iter().partition_result();
+    if errors.is_empty() {
+        return Ok(installed);
+    }
+    Err(anyhow!(
+        "could not install {} of the tools in the manifest:\n{}",
+        errors.len(),
+        errors.iter().map(|e| format!("  {e:#}")).join("\n"),
+    ))
+}
+
+// These are the `key = "value"` pairs from one `[[tools]]` table, along with the line number of
+// the table header and of each pair.
+#[derive(Debug)]
+struct Table {
+    line: usize,
+    pairs: Vec<(String, String, usize)>,
+}
+
+// We don't need all of TOML for these files, so we only parse the subset that they use. Each
+// `[[tools]]` header starts a new table, and every other non-comment line must be a `key = value`
+// pair where the value is a string on one line.
+fn parse_tables(contents: &str) -> Result<Vec<Table>> {
+    let mut tables: Vec<Table> = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line_num = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let header = strip_comment(line, line_num)?;
+            let name = header
+                .strip_prefix("[[")
+                .and_then(|h| h.strip_suffix("]]"))
+                .map(str::trim);
+            if name != Some(TOOLS_TABLE) {
+                return Err(anyhow!(
+                    "line {line_num}: only [[{TOOLS_TABLE}]] tables are supported, but found {header}"
+                ));
+            }
+            tables.push(Table {
+                line: line_num,
+                pairs: vec![],
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(anyhow!(
+                "line {line_num}: expected a `key = value` pair or a [[{TOOLS_TABLE}]] header"
+            ));
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow!("line {line_num}: `{key}` is not a valid key"));
+        }
+        let (value, rest) = parse_string(value.trim(), line_num)?;
+        strip_comment(rest, line_num)?;
+
+        let Some(table) = tables.last_mut() else {
+            return Err(anyhow!(
+                "line {line_num}: the `{key}` key must be in a [[{TOOLS_TABLE}]] table"
+            ));
+        };
+        if table.pairs.iter().any(|(k, _, _)| k == key) {
+            return Err(anyhow!(
+                "line {line_num}: the `{key}` key is set more than once in the same table"
+            ));
+        }
+        table.pairs.push((key.to_string(), value, line_num));
+    }
+    Ok(tables)
+}
+
+// Returns the part of the line before a trailing comment, which is the only thing allowed after a
+// header or value.
+fn strip_comment(s: &str, line_num: usize) -> Result<&str> {
+    let (before, _) = s.split_once('#').unwrap_or((s, ""));
+    let before = before.trim();
+    if before.is_empty() || before.starts_with('[') {
+        return Ok(before);
+    }
+    Err(anyhow!(
+        "line {line_num}: unexpected `{before}` after the value"
+    ))
+}
+
+// Parses a basic (`"..."`) or literal (`'...'`) string at the start of `s`, and returns the string
+// along with the rest of `s`.
+fn parse_string(s: &str, line_num: usize) -> Result<(String, &str)> {
+    if let Some(literal) = s.strip_prefix('\'') {
+        let Some((value, rest)) = literal.split_once('\'') else {
+            return Err(anyhow!("line {line_num}: the string is not terminated"));
+        };
+        return Ok((value.to_string(), rest));
+    }
+    let Some(basic) = s.strip_prefix('"') else {
+        return Err(anyhow!(
+            "line {line_num}: values must be strings, but found `{s}`"
+        ));
+    };
+
+    let mut value = String::new();
+    let mut chars = basic.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &basic[i + 1..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some(e @ ('u' | 'U')) => {
+                        let len = if e == 'u' { 4 } else { 8 };
+                        let hex = chars.by_ref().take(len).map(|(_, c)| c).collect::<String>();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|_| hex.len() == len)
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                anyhow!("line {line_num}: `\\{e}{hex}` is not a valid escape")
+                            })?
+                    }
+                    Some(e) => {
+                        return Err(anyhow!("line {line_num}: `\\{e}` is not a valid escape"));
+                    }
+                    None => break,
+                };
+                value.push(escaped);
+            }
+            _ => value.push(c),
+        }
+    }
+    Err(anyhow!("line {line_num}: the string is not terminated"))
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn parse_manifest() -> Result<()> {
+        let manifest = Manifest::from_str(
+            r#"
+# Tools for this project.
+[[tools]]
+project = "houseabsolute/precious"
+version = "^0.7"
+
+[[ tools ]] # ripgrep
+project = 'BurntSushi/ripgrep'
+tag = "14.1.1"
+exe = "rg"  # the exe isn't named ripgrep
+
+[[tools]]
+project = "https://gitlab.example.com/some-org/some-tool"
+forge = "gitlab"
+api_base_url = "https://gitlab.example.com/api/v4"
+"#,
+        )?;
+        assert_eq!(
+            manifest.tools,
+            vec![
+                ManifestTool {
+                    project: "houseabsolute/precious".to_string(),
+                    version: Some("^0.7".to_string()),
+                    ..Default::default()
+                },
+                ManifestTool {
+                    project: "BurntSushi/ripgrep".to_string(),
+                    tag: Some("14.1.1".to_string()),
+                    exe: Some("rg".to_string()),
+                    ..Default::default()
+                },
+                ManifestTool {
+                    project: "https://gitlab.example.com/some-org/some-tool".to_string(),
+                    forge: Some(ForgeType::GitLab),
+                    api_base_url: Some("https://gitlab.example.com/api/v4".to_string()),
+                    ..Default::default()
+                },
+            ],
+        );
+
+        Ok(())
+    }
+
+    #[test_case("", &[] ; "empty")]
+    #[test_case(r#"[[tools]]
+project = "a\"b\\c\u00e9""#, &["a\"b\\cé"] ; "escapes")]
+    #[test_case("[[tools]]\nproject = 'C:\\tools'", &["C:\\tools"] ; "literal string")]
+    #[test_case("[[tools]]\nproject = \"a#b\" # comment", &["a#b"] ; "hash in string")]
+    fn parse_manifest_projects(manifest: &str, expect: &[&str]) -> Result<()> {
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(
+            manifest
+                .tools
+                .iter()
+                .map(|t| t.project.as_str())
+                .collect::<Vec<_>>(),
+            expect,
+        );
+
+        Ok(())
+    }
+
+    #[test_case(
+        "project = \"a/b\"",
+        "line 1: the `project` key must be in a [[tools]] table" ;
+        "key outside a table"
+    )]
+    #[test_case(
+        "[tools]\nproject = \"a/b\"",
+        "line 1: only [[tools]] tables are supported, but found [tools]" ;
+        "plain table"
+    )]
+    #[test_case(
+        "[[tools]]\nexe = \"b\"",
+        "line 1: the [[tools]] table has no `project` key" ;
+        "missing project"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\"\nbinary = \"b\"",
+        "line 3: unknown key `binary` in a [[tools]] table" ;
+        "unknown key"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\"\ntag = \"v1\"\nversion = \"1\"",
+        "line 1: the [[tools]] table for a/b cannot have both `tag` and `version`" ;
+        "tag and version"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\"\nproject = \"c/d\"",
+        "line 3: the `project` key is set more than once in the same table" ;
+        "duplicate key"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\"\n[[tools]]\nproject = \"a/b\"",
+        "the project a/b is listed more than once in the manifest" ;
+        "duplicate project"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\"\nforge = \"forgejo\"",
+        "line 3: `forgejo` is not a valid forge" ;
+        "bad forge"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = 42",
+        "line 2: values must be strings, but found `42`" ;
+        "not a string"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b",
+        "line 2: the string is not terminated" ;
+        "unterminated string"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a/b\" \"c\"",
+        "line 2: unexpected `\"c\"` after the value" ;
+        "trailing value"
+    )]
+    #[test_case(
+        "[[tools]]\nproject = \"a\\qb\"",
+        "line 2: `\\q` is not a valid escape" ;
+        "bad escape"
+    )]
+    #[test_case(
+        "[[tools]]\nproject",
+        "line 2: expected a `key = value` pair or a [[tools]] header" ;
+        "no value"
+    )]
+    fn parse_manifest_errors(manifest: &str, expect: &str) {
+        let err = Manifest::from_str(manifest).unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[test]
+    fn lockfile_round_trip() -> Result<()> {
+        let lockfile = Lockfile {
+            tools: vec![
+                LockedTool {
+                    project: "houseabsolute/precious".to_string(),
+                    target: "aarch64-apple-darwin".to_string(),
+                    tag: "v0.7.3".to_string(),
+                    asset_name: "precious-macOS-arm64.tar.gz".to_string(),
+                    sha256: "a".repeat(64),
+                },
+                LockedTool {
+                    project: "houseabsolute/precious".to_string(),
+                    target: "x86_64-unknown-linux-musl".to_string(),
+                    tag: "v0.7.3".to_string(),
+                    asset_name: "precious-Linux-x86_64-musl.tar.gz".to_string(),
+                    sha256: "b".repeat(64),
+                },
+            ],
+        };
+        let written = lockfile.to_string();
+        assert_eq!(
+            written,
+            format!(
+                r#"{LOCKFILE_HEADER}
+
+[[tools]]
+project = "houseabsolute/precious"
+target = "aarch64-apple-darwin"
+tag = "v0.7.3"
+asset = "precious-macOS-arm64.tar.gz"
+sha256 = "{}"
+
+[[tools]]
+project = "houseabsolute/precious"
+target = "x86_64-unknown-linux-musl"
+tag = "v0.7.3"
+asset = "precious-Linux-x86_64-musl.tar.gz"
+sha256 = "{}"
+"#,
+                "a".repeat(64),
+                "b".repeat(64),
+            ),
+        );
+        assert_eq!(Lockfile::from_str(&written)?, lockfile);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lockfile_missing_key() {
+        let err = Lockfile::from_str("[[tools]]\nproject = \"a/b\"\ntarget = \"t\"\ntag = \"v1\"")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: the [[tools]] table has no `asset` key"
+        );
+    }
+
+    #[test_case(Some("v1.2.3"), None, "v1.2.3", true ; "same tag")]
+    #[test_case(Some("v1.2.4"), None, "v1.2.3", false ; "different tag")]
+    #[test_case(None, Some("^1.2"), "v1.2.3", true ; "matching version")]
+    #[test_case(None, Some("^1.3"), "v1.2.3", false ; "version does not match")]
+    #[test_case(None, None, "v1.2.3", true ; "latest")]
+    fn check_locked_tag(tag: Option<&str>, version: Option<&str>, locked_tag: &str, ok: bool) {
+        let tool = ManifestTool {
+            project: "a/b".to_string(),
+            tag: tag.map(String::from),
+            version: version.map(String::from),
+            ..Default::default()
+        };
+        let locked = LockedTool {
+            project: "a/b".to_string(),
+            target: TARGET.to_string(),
+            tag: locked_tag.to_string(),
+            asset_name: "b.tar.gz".to_string(),
+            sha256: "a".repeat(64),
+        };
+        assert_eq!(tool.check_locked_tag(&locked).is_ok(), ok);
+    }
+}
@@ -0,0 +1,178 @@
+// When no token was set explicitly or found in the forge's env vars, `ubi` looks in the user's netrc
+// file for an entry for the forge's API host, like `machine api.github.com login x password
+// <token>`. The entry's password is used as the token.
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use netrc::Netrc;
+use std::{
+    env,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct NetrcCredentials {
+    pub(crate) login: String,
+    pub(crate) password: String,
+}
+
+// Returns the first netrc file that exists, if any.
+pub(crate) fn default_netrc_file() -> Option<PathBuf> {
+    netrc_candidates(|name| env::var(name).ok(), cfg!(target_os = "windows"))
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+// This follows curl. If the `NETRC` env var is set, that is the only file we look at. Otherwise we
+// look for `.netrc` in the home dir. On Windows, we also look for `_netrc`, and we fall back to
+// `USERPROFILE` when `HOME` is not set.
+fn netrc_candidates(var: impl Fn(&str) -> Option<String>, is_windows: bool) -> Vec<PathBuf> {
+    let non_empty_var = |name| var(name).filter(|v| !v.is_empty());
+    if let Some(netrc) = non_empty_var("NETRC") {
+        return vec![PathBuf::from(netrc)];
+    }
+
+    let home = non_empty_var("HOME").or_else(|| {
+        if is_windows {
+            non_empty_var("USERPROFILE")
+        } else {
+            None
+        }
+    });
+    let Some(home) = home.map(PathBuf::from) else {
+        return vec![];
+    };
+
+    let mut candidates = vec![home.join(".netrc")];
+    if is_windows {
+        candidates.push(home.join("_netrc"));
+    }
+    candidates
+}
+
+// Returns the credentials for the URL's host. An entry with a port only matches a URL with that
+// port. The `default` entry is ignored, since we don't want to send a token for one forge to every
+// host we talk to.
+pub(crate) fn credentials_for(netrc_file: &Path, url: &Url) -> Result<Option<NetrcCredentials>> {
+    let Some(host) = url.host_str() else {
+        return Ok(None);
+    };
+
+    let file = File::open(netrc_file)
+        .with_context(|| format!("could not open the netrc file at {}", netrc_file.display()))?;
+    let netrc = Netrc::parse(BufReader::new(file)).map_err(|e| match e {
+        netrc::Error::Io(e) => anyhow!(
+            "could not read the netrc file at {}: {e}",
+            netrc_file.display(),
+        ),
+        netrc::Error::Parse(msg, line) => anyhow!(
+            "could not parse the netrc file at {} on line {line}: {msg}",
+            netrc_file.display(),
+        ),
+    })?;
+
+    let port = url.port_or_known_default();
+    let Some(machine) = netrc
+        .hosts
+        .into_iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(host))
+        .map(|(_, machine)| machine)
+        .find(|machine| machine.port.is_none() || machine.port == port)
+    else {
+        debug!(
+            "no entry for {host} in the netrc file at {}",
+            netrc_file.display()
+        );
+        return Ok(None);
+    };
+
+    let Some(password) = machine.password else {
+        debug!(
+            "the entry for {host} in the netrc file at {} has no password",
+            netrc_file.display()
+        );
+        return Ok(None);
+    };
+
+    Ok(Some(NetrcCredentials {
+        login: machine.login,
+        password,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, fs};
+    use tempfile::tempdir;
+    use test_case::test_case;
+
+    const NETRC: &str = r"
+machine api.github.com login github-user password github-token
+machine gitlab.com
+  login gitlab-user
+  password gitlab-token
+machine 127.0.0.1 port 8080 login port-user password port-token
+machine codeberg.org login codeberg-user
+default login default-user password default-token
+";
+
+    #[test_case("https://api.github.com", Some(("github-user", "github-token")) ; "GitHub")]
+    #[test_case("https://API.GitHub.com", Some(("github-user", "github-token")) ; "host is case-insensitive")]
+    #[test_case("https://gitlab.com/api/v4", Some(("gitlab-user", "gitlab-token")) ; "GitLab")]
+    #[test_case("http://127.0.0.1:8080", Some(("port-user", "port-token")) ; "matching port")]
+    #[test_case("http://127.0.0.1:8081", None ; "different port")]
+    #[test_case("https://codeberg.org/api/v1", None ; "entry without a password")]
+    #[test_case("https://example.com", None ; "default entry is ignored")]
+    fn credentials_for(url: &str, expect: Option<(&str, &str)>) -> Result<()> {
+        let td = tempdir()?;
+        let netrc_file = td.path().join(".netrc");
+        fs::write(&netrc_file, NETRC)?;
+
+        let creds = super::credentials_for(&netrc_file, &Url::parse(url)?)?;
+        assert_eq!(
+            creds,
+            expect.map(|(login, password)| NetrcCredentials {
+                login: login.to_string(),
+                password: password.to_string(),
+            }),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn credentials_for_invalid_file() -> Result<()> {
+        let td = tempdir()?;
+        let netrc_file = td.path().join(".netrc");
+        fs::write(&netrc_file, "machine api.github.com bogus x\n")?;
+
+        let err = super::credentials_for(&netrc_file, &Url::parse("https://api.github.com")?)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "could not parse the netrc file at {} on line 1: Unknown entry `bogus'",
+                netrc_file.display(),
+            ),
+        );
+
+        Ok(())
+    }
+
+    #[test_case(&[("NETRC", "/etc/ubi-netrc"), ("HOME", "/home/autarch")], false, &["/etc/ubi-netrc"] ; "NETRC env var")]
+    #[test_case(&[("HOME", "/home/autarch")], false, &["/home/autarch/.netrc"] ; "HOME")]
+    #[test_case(&[("NETRC", ""), ("HOME", "/home/autarch")], false, &["/home/autarch/.netrc"] ; "empty NETRC env var")]
+    #[test_case(&[("USERPROFILE", "/users/autarch")], false, &[] ; "USERPROFILE is ignored on Unix")]
+    #[test_case(&[("USERPROFILE", "/users/autarch")], true, &["/users/autarch/.netrc", "/users/autarch/_netrc"] ; "USERPROFILE on Windows")]
+    #[test_case(&[], false, &[] ; "no env vars")]
+    fn netrc_candidates(vars: &[(&str, &str)], is_windows: bool, expect: &[&str]) {
+        let vars = vars.iter().copied().collect::<HashMap<_, _>>();
+        assert_eq!(
+            super::netrc_candidates(|name| vars.get(name).map(|v| (*v).to_string()), is_windows),
+            expect.iter().map(PathBuf::from).collect::<Vec<_>>(),
+        );
+    }
+}
@@ -0,0 +1,694 @@
+use crate::{
+    forge::{Forge, ReleaseAssets},
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::debug;
+use reqwest::{
+    header::{HeaderValue, ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE},
+    Client, RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
+use url::Url;
+
+pub(crate) const GHCR_DOMAIN: &str = "ghcr.io";
+
+// This is the tag we use when the reference doesn't include one, which is what `docker pull` does
+// too.
+const DEFAULT_TAG: &str = "latest";
+
+// Tools like ORAS that push arbitrary files to a registry put each file's name in this annotation
+// on its layer.
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+const MANIFEST_MEDIA_TYPES: &str = concat!(
+    "application/vnd.oci.image.manifest.v1+json, ",
+    "application/vnd.oci.image.index.v1+json, ",
+    "application/vnd.docker.distribution.manifest.v2+json, ",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+);
+
+// This is a parsed reference like `ghcr.io/owner/image:tag` or `ghcr.io/owner/image@sha256:...`.
+// The registry is `None` if the reference doesn't start with a hostname, in which case we use
+// `ghcr.io`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct OciReference {
+    pub(crate) registry: Option<String>,
+    pub(crate) repository: String,
+    pub(crate) tag: Option<String>,
+}
+
+impl OciReference {
+    pub(crate) fn registry_url(&self) -> String {
+        format!(
+            "https://{}",
+            self.registry.as_deref().unwrap_or(GHCR_DOMAIN)
+        )
+    }
+}
+
+impl FromStr for OciReference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid =
+            || anyhow!("could not parse `{s}` as an OCI reference like ghcr.io/owner/image:tag");
+
+        let rest = s.strip_prefix("oci://").unwrap_or(s);
+        // This is the same rule that Docker uses to decide whether the first part of a reference
+        // is a registry hostname.
+        let (registry, rest) = match rest.split_once('/') {
+            Some((first, rest))
+                if first.contains('.') || first.contains(':') || first == "localhost" =>
+            {
+                (Some(first.to_string()), rest)
+            }
+            _ => (None, rest),
+        };
+
+        let (repository, tag) = if let Some((repository, digest)) = rest.split_once('@') {
+            (repository, Some(digest))
+        } else {
+            match rest.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag)),
+                _ => (rest, None),
+            }
+        };
+        if repository.split('/').any(str::is_empty) || tag == Some("") {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            registry,
+            repository: repository.to_string(),
+            tag: tag.map(String::from),
+        })
+    }
+}
+
+// This treats an image in an OCI registry like a release. The tag is the release, and each layer
+// with a title annotation is an asset. Since the registry is not a forge, the "project name" is the
+// image's repository, like `owner/image`.
+#[derive(Debug)]
+pub(crate) struct Oci {
+    repository: String,
+    tag: Option<String>,
+    registry_url: Url,
+    token: Option<String>,
+    // This is the token we get from the registry's token service when the registry asks us to
+    // authenticate. We need it for the blob downloads too, which happen after `fetch_assets`
+    // returns.
+    registry_token: Mutex<Option<String>>,
+    retry_policy: RetryPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    // This is only set for an image index, which points to one manifest per platform.
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+// This is what we get from a `WWW-Authenticate: Bearer realm="...",service="...",scope="..."`
+// header.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct BearerChallenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+#[async_trait]
+impl Forge for Oci {
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let reference = self.tag.as_deref().unwrap_or(DEFAULT_TAG);
+        let manifest = self.fetch_manifest(client, reference).await?;
+
+        let mut assets = self.layer_assets(manifest.layers);
+        // For an image index, we look at the manifest for each platform, and let the asset picker
+        // choose between all of their layers.
+        for descriptor in manifest.manifests {
+            debug!(
+                "fetching the {} manifest {} from the image index",
+                descriptor.media_type, descriptor.digest,
+            );
+            let manifest = self.fetch_manifest(client, &descriptor.digest).await?;
+            assets.extend(self.layer_assets(manifest.layers));
+        }
+        if assets.is_empty() {
+            return Err(anyhow!(
+                "the OCI image {}:{reference} does not have any layers with an `{TITLE_ANNOTATION}` annotation",
+                self.repository,
+            ));
+        }
+
+        Ok(ReleaseAssets {
+            tag: Some(reference.to_string()),
+            assets,
+        })
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        self.manifest_url(self.tag.as_deref().unwrap_or(DEFAULT_TAG))
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    // GHCR accepts a GitHub token as a bearer token, as long as it's base64-encoded. Without a
+    // token, we use the anonymous token that the registry's token service gave us, if any.
+    fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
+        let auth = if let Some(token) = self.token.as_deref() {
+            debug!("Adding GitHub token to OCI registry request.");
+            Some(format!("Bearer {}", STANDARD.encode(token)))
+        } else if let Some(token) = self.registry_token.lock().unwrap().as_deref() {
+            debug!("Adding registry token to OCI registry request.");
+            Some(format!("Bearer {token}"))
+        } else {
+            debug!("No OCI registry token found.");
+            None
+        };
+        if let Some(auth) = auth {
+            let mut auth_val = HeaderValue::from_str(&auth)?;
+            auth_val.set_sensitive(true);
+            req_builder = req_builder.header(AUTHORIZATION, auth_val);
+        }
+        Ok(req_builder)
+    }
+}
+
+impl Oci {
+    pub(crate) fn new(
+        repository: String,
+        tag: Option<String>,
+        registry_url: Url,
+        token: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            repository,
+            tag,
+            registry_url,
+            token,
+            registry_token: Mutex::new(None),
+            retry_policy,
+        }
+    }
+
+    async fn fetch_manifest(&self, client: &Client, reference: &str) -> Result<Manifest> {
+        let url = self.manifest_url(reference);
+        debug!("Getting OCI manifest from {url}");
+        let resp = self.registry_request(client, url).await?;
+        Ok(resp.json::<Manifest>().await?)
+    }
+
+    // If the registry responds with a `401 Unauthorized` and a bearer challenge, we get a token
+    // from its token service and try again. We only do this without a token of our own, since a
+    // registry that rejects our token is not going to accept an anonymous one.
+    async fn registry_request(&self, client: &Client, url: Url) -> Result<Response> {
+        let resp = self.send_registry_request(client, url.clone()).await?;
+        if resp.status() != StatusCode::UNAUTHORIZED || self.token.is_some() {
+            return check_status(resp);
+        }
+
+        let Some(challenge) = resp
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(parse_bearer_challenge)
+        else {
+            return check_status(resp);
+        };
+        let token = self.fetch_registry_token(client, &challenge).await?;
+        *self.registry_token.lock().unwrap() = Some(token);
+
+        check_status(self.send_registry_request(client, url).await?)
+    }
+
+    async fn send_registry_request(&self, client: &Client, url: Url) -> Result<Response> {
+        let mut req_builder = client
+            .get(url)
+            .header(ACCEPT, HeaderValue::from_static(MANIFEST_MEDIA_TYPES));
+        req_builder = self.maybe_add_token_header(req_builder)?;
+        self.retry_policy
+            .execute(client, req_builder.build()?)
+            .await
+    }
+
+    async fn fetch_registry_token(
+        &self,
+        client: &Client,
+        challenge: &BearerChallenge,
+    ) -> Result<String> {
+        let mut url = Url::parse(&challenge.realm).with_context(|| {
+            format!(
+                "could not parse the OCI registry's token realm `{}`",
+                challenge.realm
+            )
+        })?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(service) = &challenge.service {
+                query.append_pair("service", service);
+            }
+            let scope = challenge
+                .scope
+                .clone()
+                .unwrap_or_else(|| format!("repository:{}:pull", self.repository));
+            query.append_pair("scope", &scope);
+        }
+        debug!("Getting an OCI registry token from {url}");
+
+        let req = client
+            .get(url)
+            .header(ACCEPT, HeaderValue::from_static("application/json"))
+            .build()?;
+        let resp = check_status(self.retry_policy.execute(client, req).await?)?;
+        let token = resp.json::<TokenResponse>().await?;
+        token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| anyhow!("the OCI registry's token service did not return a token"))
+    }
+
+    // Layers without a title are things like a container image's filesystem, rather than a file
+    // that was pushed as an artifact, so we have no name to give them.
+    fn layer_assets(&self, layers: Vec<Descriptor>) -> Vec<Asset> {
+        layers
+            .into_iter()
+            .filter_map(|mut layer| {
+                let Some(name) = layer.annotations.remove(TITLE_ANNOTATION) else {
+                    debug!(
+                        "skipping the {} layer {} because it has no title annotation",
+                        layer.media_type, layer.digest,
+                    );
+                    return None;
+                };
+                Some(Asset {
+                    name,
+                    url: self.blob_url(&layer.digest),
+                    size: layer.size,
+                })
+            })
+            .collect()
+    }
+
+    fn manifest_url(&self, reference: &str) -> Url {
+        self.registry_api_url("manifests", reference)
+    }
+
+    fn blob_url(&self, digest: &str) -> Url {
+        self.registry_api_url("blobs", digest)
+    }
+
+    fn registry_api_url(&self, kind: &str, reference: &str) -> Url {
+        let mut url = self.registry_url.clone();
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .expect("could not get path segments for url");
+            segments.pop_if_empty().push("v2");
+            segments.extend(self.repository.split('/'));
+            segments.push(kind).push(reference);
+        }
+        url
+    }
+}
+
+fn check_status(resp: Response) -> Result<Response> {
+    if let Err(e) = resp.error_for_status_ref() {
+        return Err(anyhow::Error::new(e));
+    }
+    Ok(resp)
+}
+
+fn parse_bearer_challenge(header: &str) -> Option<BearerChallenge> {
+    let (scheme, params) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut challenge = BearerChallenge::default();
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let (value, after_value) = if let Some(quoted) = after_key.strip_prefix('"') {
+            // The scope can contain commas, so we can't just split on those.
+            let end = quoted.find('"')?;
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            after_key.split_once(',').unwrap_or((after_key, ""))
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "realm" => challenge.realm = value.to_string(),
+            "service" => challenge.service = Some(value.to_string()),
+            "scope" => challenge.scope = Some(value.to_string()),
+            _ => (),
+        }
+        rest = after_value.trim_start_matches([',', ' ']);
+    }
+
+    if challenge.realm.is_empty() {
+        return None;
+    }
+    Some(challenge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case(
+        "ghcr.io/owner/image:v1.2.3",
+        Some("ghcr.io"), "owner/image", Some("v1.2.3") ;
+        "ghcr with tag"
+    )]
+    #[test_case("ghcr.io/owner/image", Some("ghcr.io"), "owner/image", None ; "ghcr without tag")]
+    #[test_case(
+        "oci://ghcr.io/owner/image:v1",
+        Some("ghcr.io"), "owner/image", Some("v1") ;
+        "oci scheme"
+    )]
+    #[test_case(
+        "ghcr.io/owner/group/image:v1",
+        Some("ghcr.io"), "owner/group/image", Some("v1") ;
+        "nested repository"
+    )]
+    #[test_case(
+        "ghcr.io/owner/image@sha256:abc123",
+        Some("ghcr.io"), "owner/image", Some("sha256:abc123") ;
+        "digest"
+    )]
+    #[test_case(
+        "localhost:5000/owner/image:v1",
+        Some("localhost:5000"), "owner/image", Some("v1") ;
+        "registry with port"
+    )]
+    #[test_case("owner/image:v1", None, "owner/image", Some("v1") ; "no registry")]
+    fn parse_reference(
+        reference: &str,
+        expect_registry: Option<&str>,
+        expect_repository: &str,
+        expect_tag: Option<&str>,
+    ) -> Result<()> {
+        assert_eq!(
+            OciReference::from_str(reference)?,
+            OciReference {
+                registry: expect_registry.map(String::from),
+                repository: expect_repository.to_string(),
+                tag: expect_tag.map(String::from),
+            },
+        );
+        Ok(())
+    }
+
+    #[test_case("ghcr.io/owner/image:" ; "empty tag")]
+    #[test_case("ghcr.io//image" ; "empty path segment")]
+    #[test_case("ghcr.io/" ; "no repository")]
+    fn parse_invalid_reference(reference: &str) {
+        assert!(OciReference::from_str(reference).is_err());
+    }
+
+    #[test_case(
+        r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:owner/image:pull""#,
+        Some(("https://ghcr.io/token", Some("ghcr.io"), Some("repository:owner/image:pull"))) ;
+        "ghcr"
+    )]
+    #[test_case(
+        r#"Bearer realm="https://auth.example.com/token", scope="repository:owner/image:pull,push""#,
+        Some(("https://auth.example.com/token", None, Some("repository:owner/image:pull,push"))) ;
+        "scope with comma"
+    )]
+    #[test_case(r#"Basic realm="registry""#, None ; "basic auth")]
+    #[test_case(r#"Bearer service="ghcr.io""#, None ; "no realm")]
+    fn parse_bearer_challenge(header: &str, expect: Option<(&str, Option<&str>, Option<&str>)>) {
+        assert_eq!(
+            super::parse_bearer_challenge(header),
+            expect.map(|(realm, service, scope)| BearerChallenge {
+                realm: realm.to_string(),
+                service: service.map(String::from),
+                scope: scope.map(String::from),
+            }),
+        );
+    }
+
+    const MANIFEST_RESPONSE: &str = r#"
+{
+  "schemaVersion": 2,
+  "mediaType": "application/vnd.oci.image.manifest.v1+json",
+  "artifactType": "application/vnd.example.binary",
+  "config": {
+    "mediaType": "application/vnd.oci.empty.v1+json",
+    "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a",
+    "size": 2
+  },
+  "layers": [
+    {
+      "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+      "digest": "sha256:1111111111111111111111111111111111111111111111111111111111111111",
+      "size": 1024,
+      "annotations": {
+        "org.opencontainers.image.title": "ubi-Linux-x86_64-musl.tar.gz"
+      }
+    },
+    {
+      "mediaType": "application/vnd.oci.image.layer.v1.tar",
+      "digest": "sha256:2222222222222222222222222222222222222222222222222222222222222222",
+      "size": 2048
+    }
+  ]
+}
+"#;
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_anonymous_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let url = server.url();
+        let challenge_mock = server
+            .mock("GET", "/v2/houseabsolute/ubi/manifests/v1.0.0")
+            .match_header(AUTHORIZATION.as_str(), Matcher::Missing)
+            .with_status(401)
+            .with_header(
+                WWW_AUTHENTICATE.as_str(),
+                &format!(
+                    r#"Bearer realm="{url}/token",service="ghcr.io",scope="repository:houseabsolute/ubi:pull""#
+                ),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let token_mock = server
+            .mock("GET", "/token")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("service".to_string(), "ghcr.io".to_string()),
+                Matcher::UrlEncoded(
+                    "scope".to_string(),
+                    "repository:houseabsolute/ubi:pull".to_string(),
+                ),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"token":"anonymous-token"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let manifest_mock = server
+            .mock("GET", "/v2/houseabsolute/ubi/manifests/v1.0.0")
+            .match_header(AUTHORIZATION.as_str(), "Bearer anonymous-token")
+            .with_status(200)
+            .with_body(MANIFEST_RESPONSE)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let oci = Oci::new(
+            "houseabsolute/ubi".to_string(),
+            Some("v1.0.0".to_string()),
+            Url::parse(&url)?,
+            None,
+            RetryPolicy::default(),
+        );
+        let got = oci.fetch_assets(&Client::new()).await?;
+        assert_eq!(got.tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            got.assets,
+            vec![Asset {
+                name: "ubi-Linux-x86_64-musl.tar.gz".to_string(),
+                url: Url::parse(&format!(
+                    "{url}/v2/houseabsolute/ubi/blobs/sha256:1111111111111111111111111111111111111111111111111111111111111111"
+                ))?,
+                size: Some(1024),
+            }],
+        );
+
+        // The blob download needs the same token.
+        let req = oci
+            .maybe_add_token_header(Client::new().get(&url))?
+            .build()?;
+        assert_eq!(
+            req.headers().get(AUTHORIZATION).unwrap(),
+            "Bearer anonymous-token"
+        );
+
+        challenge_mock.assert_async().await;
+        token_mock.assert_async().await;
+        manifest_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_github_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let manifest_mock = server
+            .mock("GET", "/v2/houseabsolute/ubi/manifests/latest")
+            .match_header(AUTHORIZATION.as_str(), "Bearer ZmFrZVRva2Vu")
+            .match_header(ACCEPT.as_str(), MANIFEST_MEDIA_TYPES)
+            .with_status(200)
+            .with_body(MANIFEST_RESPONSE)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let oci = Oci::new(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Url::parse(&server.url())?,
+            Some("fakeToken".to_string()),
+            RetryPolicy::default(),
+        );
+        let got = oci.fetch_assets(&Client::new()).await?;
+        assert_eq!(got.tag.as_deref(), Some("latest"));
+        assert_eq!(got.assets.len(), 1);
+
+        manifest_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_from_image_index() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let index_mock = server
+            .mock("GET", "/v2/houseabsolute/ubi/manifests/v1.0.0")
+            .with_status(200)
+            .with_body(
+                r#"
+{
+  "schemaVersion": 2,
+  "mediaType": "application/vnd.oci.image.index.v1+json",
+  "manifests": [
+    {
+      "mediaType": "application/vnd.oci.image.manifest.v1+json",
+      "digest": "sha256:3333333333333333333333333333333333333333333333333333333333333333",
+      "size": 512,
+      "platform": { "os": "linux", "architecture": "amd64" }
+    }
+  ]
+}
+"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let manifest_mock = server
+            .mock(
+                "GET",
+                "/v2/houseabsolute/ubi/manifests/sha256:3333333333333333333333333333333333333333333333333333333333333333",
+            )
+            .with_status(200)
+            .with_body(MANIFEST_RESPONSE)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let oci = Oci::new(
+            "houseabsolute/ubi".to_string(),
+            Some("v1.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let got = oci.fetch_assets(&Client::new()).await?;
+        assert_eq!(
+            got.assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ubi-Linux-x86_64-musl.tar.gz"],
+        );
+
+        index_mock.assert_async().await;
+        manifest_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_without_titled_layers() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let manifest_mock = server
+            .mock("GET", "/v2/houseabsolute/ubi/manifests/v1.0.0")
+            .with_status(200)
+            .with_body(
+                r#"{"layers":[{"mediaType":"application/vnd.oci.image.layer.v1.tar","digest":"sha256:2222","size":1}]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let oci = Oci::new(
+            "houseabsolute/ubi".to_string(),
+            Some("v1.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+        let err = oci.fetch_assets(&Client::new()).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the OCI image houseabsolute/ubi:v1.0.0 does not have any layers with an `org.opencontainers.image.title` annotation",
+        );
+
+        manifest_mock.assert_async().await;
+
+        Ok(())
+    }
+}
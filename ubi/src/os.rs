@@ -24,7 +24,8 @@ pub(crate) fn linux_re() -> &'static Lazy<Regex> {
 }
 
 pub(crate) fn macos_re() -> &'static Lazy<Regex> {
-    regex!(r"(?i:(?:\b|_)(?:darwin|mac(?:osx?)?|osx)(?:\b|_))")
+    // This includes names with a minimum macOS version, like `macos12` or `osx10.15`.
+    regex!(r"(?i:(?:\b|_)(?:darwin|mac|(?:macosx?|osx)(?:\d+(?:\.\d+)?)?)(?:\b|_))")
 }
 
 pub(crate) fn netbsd_re() -> &'static Lazy<Regex> {
@@ -36,7 +37,7 @@ pub(crate) fn solaris_re() -> &'static Lazy<Regex> {
 }
 
 pub(crate) fn windows_re() -> &'static Lazy<Regex> {
-    regex!(r"(?i:(?:\b|_)win(?:32|64|dows)?(?:\b|_))")
+    regex!(r"(?i:(?:\b|_)(?:win(?:32|64|dows)?|pc-windows|msvc|mingw(?:32|64)?)(?:\b|_))")
 }
 
 pub(crate) static ALL_OSES_RE: LazyLock<Regex> = LazyLock::new(|| {
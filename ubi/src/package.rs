@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Context, Result};
+use binstall_tar::Builder;
+use flate2::{write::GzEncoder, Compression};
+use log::{debug, info};
+use std::{
+    fs::{create_dir_all, File},
+    path::{Component, Path, PathBuf},
+};
+use tempfile::{tempdir, TempDir};
+
+pub(crate) const DEFAULT_PACKAGE_PREFIX: &str = "usr/local/bin";
+
+// When packaging, the installer writes into a temp dir that stands in for the root of the
+// filesystem, with the files under the package prefix. Once the install is done, the prefix dir is
+// written to a tarball, so each path in the tarball is relative to the root, like
+// `usr/local/bin/foo`. The temp dir is deleted when this is dropped.
+#[derive(Debug)]
+pub(crate) struct Package {
+    root: TempDir,
+    prefix: PathBuf,
+    output: PathBuf,
+}
+
+impl Package {
+    pub(crate) fn new(output: &Path, prefix: &str) -> Result<Self> {
+        let prefix = package_prefix(prefix)?;
+        let root = tempdir().context("could not create a temp dir to build the package in")?;
+        debug!(
+            "building the package for {} in {}",
+            output.display(),
+            root.path().display(),
+        );
+        Ok(Self {
+            root,
+            prefix,
+            output: output.to_path_buf(),
+        })
+    }
+
+    // This is the directory that the installer writes into.
+    pub(crate) fn install_dir(&self) -> PathBuf {
+        self.root.path().join(&self.prefix)
+    }
+
+    pub(crate) fn output(&self) -> &Path {
+        &self.output
+    }
+
+    pub(crate) fn write(&self) -> Result<()> {
+        if let Some(parent) = self.output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_dir_all(parent)
+                .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+        }
+        let file = File::create(&self.output)
+            .with_context(|| format!("could not create {}", self.output.display()))?;
+
+        let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+        builder.follow_symlinks(false);
+        builder
+            .append_dir_all(&self.prefix, self.install_dir())
+            .with_context(|| {
+                format!(
+                    "could not add the installed files to {}",
+                    self.output.display()
+                )
+            })?;
+        builder
+            .into_inner()
+            .and_then(GzEncoder::finish)
+            .and_then(|f| f.sync_all())
+            .with_context(|| format!("could not write {}", self.output.display()))?;
+
+        info!(
+            "Wrote a package with the installed files in {} to {}",
+            self.prefix.display(),
+            self.output.display(),
+        );
+        Ok(())
+    }
+}
+
+// The prefix is often written as an absolute path, like `/usr/local/bin`, but it's always relative
+// to the root of the tarball.
+fn package_prefix(prefix: &str) -> Result<PathBuf> {
+    let path = Path::new(prefix.trim_start_matches('/'));
+    if path.as_os_str().is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(anyhow!(
+            "The package prefix `{prefix}` must be a path like `usr/local/bin`, without any `.` or `..` components"
+        ));
+    }
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binstall_tar::Archive;
+    use flate2::read::GzDecoder;
+    use std::fs;
+    use test_case::test_case;
+
+    #[test_case("usr/local/bin", "usr/local/bin" ; "relative")]
+    #[test_case("/usr/local/bin", "usr/local/bin" ; "absolute")]
+    #[test_case("opt/project/bin/", "opt/project/bin" ; "trailing slash")]
+    fn package_prefix(prefix: &str, expect: &str) -> Result<()> {
+        assert_eq!(super::package_prefix(prefix)?, PathBuf::from(expect));
+        Ok(())
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("/" ; "root")]
+    #[test_case("usr/../../etc" ; "parent dir")]
+    fn package_prefix_is_invalid(prefix: &str) {
+        let err = super::package_prefix(prefix).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("The package prefix `{prefix}` must be a path like `usr/local/bin`, without any `.` or `..` components"),
+        );
+    }
+
+    #[test]
+    fn write() -> Result<()> {
+        let td = tempfile::tempdir()?;
+        let output = td.path().join("out").join("project.tar.gz");
+        let package = Package::new(&output, "/opt/project/bin")?;
+        create_dir_all(package.install_dir())?;
+        fs::write(package.install_dir().join("project"), "project")?;
+        package.write()?;
+
+        let mut archive = Archive::new(GzDecoder::new(File::open(&output)?));
+        let paths = archive
+            .entries()?
+            .map(|e| Ok(e?.path()?.to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(paths, ["opt/project/bin/", "opt/project/bin/project"]);
+
+        Ok(())
+    }
+}
@@ -1,16 +1,19 @@
-use std::path::Path;
+use std::{borrow::Cow, fmt, path::Path, sync::OnceLock};
 
 use crate::{
     arch::{
-        aarch64_re, arm_re, macos_aarch64_re, mips64_re, mips64le_re, mips_re, mipsle_re, ppc32_re,
-        ppc64_re, ppc64le_re, riscv64_re, s390x_re, sparc64_re, x86_32_re, x86_64_re,
-        ALL_ARCHES_RE,
+        aarch64_re, arm_hard_float_re, arm_re, arm_soft_float_re, loongarch64_re, macos_aarch64_re,
+        macos_universal_re, mips64_re, mips64le_re, mips_re, mipsle_re, ppc32_re, ppc64_re,
+        ppc64le_re, riscv64_re, s390x_re, sparc64_re, x86_32_re, x86_64_re, ALL_ARCHES_RE,
     },
     extension::Extension,
+    interactive,
+    macos_version::MacOsVersion,
     os::{
         android_re, freebsd_re, fuchsia, illumos_re, linux_re, macos_re, netbsd_re, solaris_re,
-        windows_re,
+        windows_re, ALL_OSES_RE,
     },
+    split_asset::split_asset_part,
     ubi::Asset,
 };
 use anyhow::{anyhow, Result};
@@ -19,34 +22,147 @@ use lazy_regex::{regex, Lazy};
 use log::debug;
 use platforms::{Arch, Endian, Platform, OS};
 use regex::Regex;
+use strum::{AsRefStr, EnumString, VariantNames};
+
+/// The libc to pick release assets for on Linux. See
+/// [`UbiBuilder::libc`](crate::UbiBuilder::libc) for details.
+#[derive(AsRefStr, Clone, Copy, Debug, EnumString, PartialEq, Eq, VariantNames)]
+pub enum Libc {
+    #[strum(serialize = "gnu")]
+    Gnu,
+    #[strum(serialize = "musl")]
+    Musl,
+}
+
+/// The points that the asset picker gives a release asset for each way that its name matches this
+/// platform. See [`UbiBuilder::scoring_weights`](crate::UbiBuilder::scoring_weights) for details.
+///
+/// An asset that doesn't name this platform's OS, or that names a different CPU architecture, is
+/// never picked, whatever the weights are, so the OS isn't part of the score. Of the rest, the
+/// picker keeps the ones with the highest score, then filters those by libc and uses its other
+/// preferences to break any ties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoringWeights {
+    /// Points for naming this platform's CPU architecture, rather than not naming any
+    /// architecture. Defaults to 20.
+    pub arch: u32,
+    /// Points on Linux for naming this platform's libc. An asset that does not name any libc gets
+    /// half of these, so it ranks between an asset for our libc and one for the other libc.
+    /// Defaults to 10.
+    pub libc: u32,
+    /// Points for each place that an asset's extension comes before the end of the extension
+    /// preference, so with the default preference a `.tar.zst` asset gets 5 times this and a
+    /// `.zip` asset gets this once. Defaults to 0, which leaves the extension preference as the
+    /// last tiebreaker.
+    pub extension: u32,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            arch: 20,
+            libc: 10,
+            extension: 0,
+        }
+    }
+}
+
+// This wraps the closure passed to `UbiBuilder::asset_selector`. The closures aren't `Debug`, so we
+// need to implement that ourselves.
+pub(crate) struct AssetSelector(Box<SelectorFn>);
+
+type SelectorFn = dyn Fn(&[Asset]) -> Option<Asset> + Send + Sync;
+
+impl AssetSelector {
+    pub(crate) fn new<F>(selector: F) -> Self
+    where
+        F: Fn(&[Asset]) -> Option<Asset> + Send + Sync + 'static,
+    {
+        Self(Box::new(selector))
+    }
+
+    pub(crate) fn select(&self, assets: &[Asset]) -> Option<Asset> {
+        (self.0)(assets)
+    }
+}
+
+impl fmt::Debug for AssetSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AssetSelector")
+    }
+}
+
+// When there are multiple assets left that differ only by extension, we pick the one whose extension
+// comes first in this list.
+pub(crate) const DEFAULT_EXTENSION_PREFERENCE: &[&str] =
+    &["tar.zst", "tar.xz", "tar.gz", "tgz", "zip"];
 
 #[derive(Debug)]
 pub(crate) struct AssetPicker<'a> {
     matching: Option<&'a str>,
+    matching_regex: Option<Regex>,
+    exact_asset_name: Option<&'a str>,
     platform: Platform,
     is_musl: bool,
+    // This is the version of macOS that we're running on, if we're on macOS and could tell. We
+    // only look this up when we need it, since that means running `sysctl` or `sw_vers`.
+    macos_version: OnceLock<Option<MacOsVersion>>,
     archive_only: bool,
+    prefer_static: bool,
+    prefer_gnu_on_windows: bool,
+    // The extensions to prefer, best first, when we're left with assets that differ by extension.
+    extension_preference: Vec<&'a str>,
+    scoring_weights: ScoringWeights,
+    interactive: bool,
+    // This is the tag of the release that the assets came from, once we know it.
+    release_tag: Option<String>,
 }
 
 impl<'a> AssetPicker<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         matching: Option<&'a str>,
+        matching_regex: Option<Regex>,
+        exact_asset_name: Option<&'a str>,
         platform: Platform,
         is_musl: bool,
         archive_only: bool,
+        prefer_static: bool,
+        prefer_gnu_on_windows: bool,
+        extension_preference: Vec<&'a str>,
+        scoring_weights: ScoringWeights,
+        interactive: bool,
     ) -> Self {
         Self {
             matching,
+            matching_regex,
+            exact_asset_name,
             platform,
             is_musl,
+            macos_version: OnceLock::new(),
             archive_only,
+            prefer_static,
+            prefer_gnu_on_windows,
+            extension_preference,
+            scoring_weights,
+            interactive,
+            release_tag: None,
         }
     }
 
+    pub(crate) fn set_release_tag(&mut self, tag: Option<String>) {
+        self.release_tag = tag;
+    }
+
     pub(crate) fn pick_asset(&mut self, assets: Vec<Asset>) -> Result<Asset> {
         let all_names = assets.iter().map(|a| &a.name).join(", ");
 
-        let mut assets = self.filter_by_extension(assets);
+        if let Some(name) = self.exact_asset_name {
+            return Self::pick_asset_by_exact_name(assets, name, &all_names);
+        }
+
+        let assets = self.filter_by_matching_regex(assets, &all_names)?;
+        let assets = self.filter_by_extension(assets);
         if assets.is_empty() {
             let filter = if self.archive_only {
                 "for archive files (tarball or zip)"
@@ -58,37 +174,62 @@ impl<'a> AssetPicker<'a> {
             ));
         }
 
+        let mut assets = Self::filter_source_archives(assets);
+        if assets.is_empty() {
+            return Err(anyhow!(
+                "no platform binary found, only source archives are available from {all_names}",
+            ));
+        }
+
         if assets.len() == 1 {
             debug!("there is only one asset to pick");
             return Ok(assets.remove(0));
         }
 
+        // If none of the assets match our platform, we let the user pick from all of the assets
+        // with a valid extension.
+        let candidates = if self.interactive {
+            assets.clone()
+        } else {
+            vec![]
+        };
+
         let mut matches = self.os_matches(assets);
         if matches.is_empty() {
-            return Err(anyhow!(
-                "could not find a release asset for this OS ({}) from {all_names}",
-                self.platform.target_os,
-            ));
+            return self.choose_or_err(
+                candidates,
+                anyhow!(
+                    "could not find a release asset for this OS ({}) from {all_names}",
+                    self.platform.target_os,
+                ),
+            );
         }
 
         matches = self.arch_matches(matches);
         if matches.is_empty() {
-            return Err(anyhow!(
-                "could not find a release asset for this OS ({}) and architecture ({}) from {all_names}",
-                self.platform.target_os,
-                self.platform.target_arch,
-            ));
+            return self.choose_or_err(
+                candidates,
+                anyhow!(
+                    "could not find a release asset for this OS ({}) and architecture ({}) from {all_names}",
+                    self.platform.target_os,
+                    self.platform.target_arch,
+                ),
+            );
         }
 
+        matches = self.highest_scoring(matches);
         matches = self.libc_matches(matches);
         if matches.is_empty() {
             let libc_name = self.libc_name();
-            return Err(anyhow!(
-                "could not find a release asset for this OS ({}), architecture ({}), and libc ({}) from {all_names}",
-                self.platform.target_os,
-                self.platform.target_arch,
-                libc_name,
-            ));
+            return self.choose_or_err(
+                candidates,
+                anyhow!(
+                    "could not find a release asset for this OS ({}), architecture ({}), and libc ({}) from {all_names}",
+                    self.platform.target_os,
+                    self.platform.target_arch,
+                    libc_name,
+                ),
+            );
         }
 
         let picked = self.pick_asset_from_matches(matches)?;
@@ -96,40 +237,135 @@ impl<'a> AssetPicker<'a> {
         Ok(picked)
     }
 
+    fn choose_or_err(&self, candidates: Vec<Asset>, err: anyhow::Error) -> Result<Asset> {
+        if !self.interactive || candidates.is_empty() {
+            return Err(err);
+        }
+
+        debug!("{err}, asking the user to choose an asset");
+        interactive::choose_asset_from_stdin(
+            candidates,
+            "Could not find a release asset for this platform. These are the assets in the release",
+        )
+    }
+
+    // When the caller asks for an asset by name, we skip all of the heuristics below. The caller
+    // knows better than we do.
+    fn pick_asset_by_exact_name(assets: Vec<Asset>, name: &str, all_names: &str) -> Result<Asset> {
+        debug!(r#"looking for an asset named exactly "{name}""#);
+        if let Some(asset) = assets.into_iter().find(|a| a.name == name) {
+            debug!("found an asset with the exact name");
+            return Ok(asset);
+        }
+
+        Err(anyhow!(
+            r#"could not find a release asset named "{name}" from {all_names}"#,
+        ))
+    }
+
+    // Unlike the `matching` string, the regex is applied before any of the heuristics below, which
+    // are then used to pick between the assets that match it.
+    fn filter_by_matching_regex(&self, assets: Vec<Asset>, all_names: &str) -> Result<Vec<Asset>> {
+        let Some(re) = &self.matching_regex else {
+            return Ok(assets);
+        };
+
+        debug!(
+            "filtering out assets that do not match the regex {}",
+            re.as_str()
+        );
+        let matches = assets
+            .into_iter()
+            .filter(|a| re.is_match(&a.name))
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return Err(anyhow!(
+                "could not find a release asset matching the regex `{}` from {all_names}",
+                re.as_str(),
+            ));
+        }
+
+        Ok(matches)
+    }
+
+    // The first part of a split asset is checked using the extension of the joined asset, and the
+    // other parts are skipped, since they're downloaded along with the first part.
     fn filter_by_extension(&self, assets: Vec<Asset>) -> Vec<Asset> {
         debug!("filtering out assets that do not have a valid extension");
         assets
             .into_iter()
-            .filter(|a| match Extension::from_path(Path::new(&a.name)) {
-                Err(e) => {
-                    debug!("skipping asset with invalid extension: {e}");
-                    false
-                }
-                Ok(Some(ext)) => {
-                    debug!("found valid extension, `{}`", ext.extension());
-                    if self.archive_only {
-                        if ext.is_archive() {
-                            debug!("including this asset because it is an archive file");
-                            return true;
-                        }
-                        debug!("not including this asset because it is not an archive file");
-                        false
-                    } else if ext.matches_platform(&self.platform) {
-                        debug!("including this asset because this extension is valid for this platform");
-                        true
-                    } else {
-                        debug!("skipping asset because this extension is not valid for this platform");
-                        false
+            .filter(|a| {
+                let name = match split_asset_part(&a.name) {
+                    Some((joined_name, 1)) => {
+                        debug!("found the first part of a split asset, `{}`", a.name);
+                        joined_name
                     }
-                }
-                Ok(None) => {
-                    debug!("found asset with no extension, `{}`", a.name);
-                    if self.archive_only {
-                        debug!("not including this asset because it is not an archive file");
+                    Some(_) => {
+                        debug!(
+                            "skipping asset that is a later part of a split asset, `{}`",
+                            a.name
+                        );
                         return false;
                     }
+                    None => &a.name,
+                };
+                self.has_valid_extension(name)
+            })
+            .collect()
+    }
+
+    fn has_valid_extension(&self, name: &str) -> bool {
+        match Extension::from_path(Path::new(name)) {
+            Err(e) => {
+                debug!("skipping asset with invalid extension: {e}");
+                false
+            }
+            Ok(Some(ext)) => {
+                debug!("found valid extension, `{}`", ext.extension());
+                if self.archive_only {
+                    if ext.is_archive() {
+                        debug!("including this asset because it is an archive file");
+                        return true;
+                    }
+                    debug!("not including this asset because it is not an archive file");
+                    false
+                } else if ext.matches_platform(&self.platform) {
+                    debug!(
+                        "including this asset because this extension is valid for this platform"
+                    );
                     true
+                } else {
+                    debug!("skipping asset because this extension is not valid for this platform");
+                    false
+                }
+            }
+            Ok(None) => {
+                debug!("found asset with no extension, `{name}`");
+                if self.archive_only {
+                    debug!("not including this asset because it is not an archive file");
+                    return false;
+                }
+                true
+            }
+        }
+    }
+
+    fn filter_source_archives(assets: Vec<Asset>) -> Vec<Asset> {
+        debug!("filtering out assets that look like source archives");
+        let has_platform_assets = assets
+            .iter()
+            .any(|a| ALL_OSES_RE.is_match(&a.name) || ALL_ARCHES_RE.is_match(&a.name));
+        assets
+            .into_iter()
+            .filter(|a| {
+                if is_source_archive(&a.name, has_platform_assets) {
+                    debug!(
+                        "skipping asset that looks like a source archive, `{}`",
+                        a.name
+                    );
+                    return false;
                 }
+                true
             })
             .collect()
     }
@@ -145,7 +381,7 @@ impl<'a> AssetPicker<'a> {
         for asset in assets {
             debug!("matching OS against asset name = {}", asset.name);
 
-            if os_matcher.is_match(&asset.name) {
+            if name_matches(os_matcher, &asset.name) {
                 if self.platform.target_os != OS::Android && android_re().is_match(&asset.name) {
                     debug!("does not match our OS");
                     continue;
@@ -161,7 +397,7 @@ impl<'a> AssetPicker<'a> {
         matches
     }
 
-    fn arch_matches(&self, mut os_matches: Vec<Asset>) -> Vec<Asset> {
+    fn arch_matches(&self, os_matches: Vec<Asset>) -> Vec<Asset> {
         let arch_matcher = self.arch_matcher();
         debug!(
             "matching assets against CPU architecture using {}",
@@ -169,74 +405,161 @@ impl<'a> AssetPicker<'a> {
         );
 
         let mut matches: Vec<Asset> = vec![];
-        if os_matches.len() == 1 {
-            debug!("there is only one asset that matches our OS");
-            if arch_matcher.is_match(&os_matches[0].name) {
+        for asset in os_matches {
+            debug!(
+                "matching CPU architecture against asset name = {}",
+                asset.name,
+            );
+            if self.matches_our_arch(arch_matcher, &asset.name) {
                 debug!("matches our CPU architecture");
-                matches.push(os_matches.remove(0));
-            } else if ALL_ARCHES_RE.is_match(&os_matches[0].name) {
-                debug!("it matches a CPU architecture which is not ours");
+                matches.push(asset);
+            } else if name_matches(&ALL_ARCHES_RE, &asset.name) {
+                debug!("matches a CPU architecture which is not ours");
             } else {
                 debug!("does not match any CPU architecture, so we will try it");
-                matches.push(os_matches.remove(0));
-            }
-        } else {
-            for asset in &os_matches {
-                debug!(
-                    "matching CPU architecture against asset name = {}",
-                    asset.name,
-                );
-                if arch_matcher.is_match(&asset.name) {
-                    debug!("matches our CPU architecture");
-                    matches.push(asset.clone());
-                } else {
-                    debug!("does not match our CPU architecture");
-                }
+                matches.push(asset);
             }
+        }
 
-            if matches.is_empty() {
-                debug!("no assets matched our CPU architecture, will look for assets without an architecture");
-                for asset in os_matches {
-                    debug!("matching against asset name = {}", asset.name);
-                    if ALL_ARCHES_RE.is_match(&asset.name) {
-                        debug!("matches a CPU architecture which is not ours");
-                    } else {
-                        debug!("does not match any CPU architecture, so we will try it");
-                        matches.push(asset);
-                    }
+        matches
+    }
+
+    // The 32-bit ARM regex matches names like "arm_64", since the regex crate has no lookahead to
+    // reject them, so we check for 64-bit ARM names separately. On macOS, a universal binary
+    // matches any arch.
+    fn matches_our_arch(&self, arch_matcher: &Regex, name: &str) -> bool {
+        if self.platform.target_arch == Arch::Arm && name_matches(aarch64_re(), name) {
+            return false;
+        }
+        if self.platform.target_os == OS::MacOS && name_matches(macos_universal_re(), name) {
+            return true;
+        }
+        name_matches(arch_matcher, name)
+    }
+
+    // On Linux, we prefer assets built for the platform's libc. On a musl platform, glibc assets
+    // will not run, so we never pick them. On a glibc platform, a (usually static) musl binary will
+    // generally work, so we fall back to those if there are no glibc assets or assets that don't
+    // indicate a libc at all.
+    fn libc_matches(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if self.platform.target_os != OS::Linux {
+            return matches;
+        }
+
+        let libc = if self.is_musl { Libc::Musl } else { Libc::Gnu };
+        // Both kinds of assets can run on a glibc platform, so we leave it to the `--matching`
+        // string to pick between them if one was given.
+        if libc == Libc::Gnu && self.matching.is_some() {
+            return matches;
+        }
+        debug!("filtering assets for the {} libc", self.libc_name());
+
+        let mut same_libc: Vec<Asset> = vec![];
+        let mut no_libc: Vec<Asset> = vec![];
+        let mut other_libc: Vec<Asset> = vec![];
+        for asset in matches {
+            debug!("checking for libc in asset name = {}", asset.name);
+            match Self::asset_libc(&asset.name) {
+                Some(l) if l == libc => {
+                    debug!("indicates the same libc as this platform");
+                    same_libc.push(asset);
+                }
+                Some(_) => {
+                    debug!("indicates a different libc than this platform");
+                    other_libc.push(asset);
+                }
+                None => {
+                    debug!("name does not indicate the libc it was compiled against");
+                    no_libc.push(asset);
                 }
             }
         }
 
-        matches
+        if !same_libc.is_empty() {
+            return same_libc;
+        }
+        if !no_libc.is_empty() {
+            return no_libc;
+        }
+        if libc == Libc::Gnu {
+            debug!("no glibc assets found, falling back to musl assets");
+            return other_libc;
+        }
+
+        debug!("glibc assets are not compatible with a musl platform");
+        vec![]
     }
 
-    fn libc_matches(&mut self, matches: Vec<Asset>) -> Vec<Asset> {
-        if !self.is_musl {
+    // The assets that get here all name our OS and don't name a different CPU architecture, so we
+    // keep the ones whose names match our platform most closely, according to the scoring weights.
+    // With the default weights, this prefers assets that name our architecture, then picks between
+    // those by libc in the same way as `libc_matches`.
+    fn highest_scoring(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if matches.len() == 1 {
             return matches;
         }
 
-        debug!("filtering out glibc assets since this is a musl platform");
+        let scores = matches
+            .iter()
+            .map(|a| {
+                let score = self.score(&a.name);
+                debug!("asset {} has a score of {score}", a.name);
+                score
+            })
+            .collect::<Vec<_>>();
+        let Some(best) = scores.iter().max().copied() else {
+            return matches;
+        };
+        debug!("keeping the assets with the highest score, {best}");
+        matches
+            .into_iter()
+            .zip(scores)
+            .filter_map(|(a, s)| (s == best).then_some(a))
+            .collect()
+    }
+
+    // The weights can be set to anything, so we add them up as a `u64`, which can't overflow unless
+    // the extension preference is absurdly long. Even then, the score saturates rather than
+    // wrapping around.
+    fn score(&self, name: &str) -> u64 {
+        let weights = &self.scoring_weights;
+        let mut score = 0_u64;
+        if self.matches_our_arch(self.arch_matcher(), name) {
+            score = score.saturating_add(u64::from(weights.arch));
+        }
+        score = score.saturating_add(u64::from(self.libc_score(name)));
+        let places = self.extension_preference.len() - self.extension_rank(name);
+        score.saturating_add(
+            u64::from(weights.extension).saturating_mul(u64::try_from(places).unwrap_or(u64::MAX)),
+        )
+    }
 
-        let mut libc_matches: Vec<Asset> = vec![];
-        for asset in &matches {
-            debug!("checking for glibc in asset name = {}", asset.name);
-            if asset.name.contains("-gnu") || asset.name.contains("-glibc") {
-                debug!("indicates glibc and is not compatible with a musl platform");
-                continue;
-            } else if asset.name.contains("-musl") {
-                debug!("indicates musl");
-            } else {
-                debug!("name does not indicate the libc it was compiled against");
-            }
+    // Both kinds of assets can run on a glibc platform, so we leave it to the `--matching` string to
+    // pick between them if one was given.
+    fn libc_score(&self, name: &str) -> u32 {
+        if self.platform.target_os != OS::Linux || (!self.is_musl && self.matching.is_some()) {
+            return 0;
+        }
 
-            libc_matches.push(asset.clone());
+        let libc = if self.is_musl { Libc::Musl } else { Libc::Gnu };
+        match Self::asset_libc(name) {
+            Some(l) if l == libc => self.scoring_weights.libc,
+            Some(_) => 0,
+            None => self.scoring_weights.libc / 2,
         }
+    }
 
-        libc_matches
+    fn asset_libc(name: &str) -> Option<Libc> {
+        if name.contains("-musl") {
+            Some(Libc::Musl)
+        } else if name.contains("-gnu") || name.contains("-glibc") {
+            Some(Libc::Gnu)
+        } else {
+            None
+        }
     }
 
-    fn libc_name(&mut self) -> &'static str {
+    fn libc_name(&self) -> &'static str {
         if self.is_musl {
             "musl"
         } else if self.platform.target_os == OS::Linux {
@@ -253,22 +576,42 @@ impl<'a> AssetPicker<'a> {
         }
 
         let filtered = self.maybe_filter_for_64_bit_arch(matches);
+        let filtered = self.maybe_filter_for_arm_float_abi(filtered);
+        let filtered = self.maybe_filter_for_release_version(filtered);
 
-        let (mut filtered, asset) = self.maybe_filter_for_matching_string(filtered)?;
+        let (filtered, asset) = self.maybe_filter_for_matching_string(filtered)?;
         if let Some(asset) = asset {
             return Ok(asset);
         }
 
+        let filtered = self.maybe_filter_for_static(filtered);
+        let filtered = self.maybe_filter_for_windows_toolchain(filtered);
+        let mut filtered = self.maybe_filter_for_macos_version(filtered);
+
         if filtered.len() == 1 {
             debug!("only found one candidate asset after filtering");
             return Ok(filtered.remove(0));
         }
 
-        let (filtered, asset) = self.maybe_pick_asset_for_macos_arm(filtered);
+        let (filtered, asset) = self.maybe_pick_asset_for_macos(filtered);
         if let Some(asset) = asset {
             return Ok(asset);
         }
 
+        let mut filtered = self.maybe_filter_for_extension_preference(filtered);
+        if filtered.len() == 1 {
+            debug!("only found one candidate asset after filtering by extension preference");
+            return Ok(filtered.remove(0));
+        }
+
+        if self.interactive {
+            debug!("cannot disambiguate multiple asset names, asking the user to choose one");
+            return interactive::choose_asset_from_stdin(
+                filtered,
+                "Found multiple release assets that match this platform",
+            );
+        }
+
         debug!(
             "cannot disambiguate multiple asset names, picking the first one after sorting by name"
         );
@@ -298,15 +641,17 @@ impl<'a> AssetPicker<'a> {
         let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
         debug!("found multiple candidate assets, filtering for 64-bit binaries in {asset_names:?}",);
 
-        if !matches.iter().any(|a| a.name.contains("64")) {
+        // A macOS universal binary includes a 64-bit binary.
+        let is_64_bit = |a: &Asset| {
+            a.name.contains("64")
+                || (self.platform.target_os == OS::MacOS && macos_universal_re().is_match(&a.name))
+        };
+        if !matches.iter().any(is_64_bit) {
             debug!("no 64-bit assets found, falling back to all assets");
             return matches;
         }
 
-        let sixty_four_bit = matches
-            .into_iter()
-            .filter(|a| a.name.contains("64"))
-            .collect::<Vec<_>>();
+        let sixty_four_bit = matches.into_iter().filter(is_64_bit).collect::<Vec<_>>();
         debug!(
             "found 64-bit assets: {}",
             sixty_four_bit.iter().map(|a| a.name.as_str()).join(",")
@@ -314,6 +659,72 @@ impl<'a> AssetPicker<'a> {
         sixty_four_bit
     }
 
+    // On 32-bit ARM, we prefer assets built for the platform's float ABI, falling back to assets
+    // that don't indicate an ABI and then to assets for the other ABI. We can only tell which ABI
+    // the platform uses when its target triple ends with one.
+    fn maybe_filter_for_arm_float_abi(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if self.platform.target_arch != Arch::Arm {
+            return matches;
+        }
+
+        let triple = self.platform.target_triple;
+        let (ours, theirs) = if triple.ends_with("eabihf") {
+            (arm_hard_float_re(), arm_soft_float_re())
+        } else if triple.ends_with("eabi") {
+            (arm_soft_float_re(), arm_hard_float_re())
+        } else {
+            return matches;
+        };
+
+        let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        debug!("found multiple candidate assets, filtering for our float ABI in {asset_names:?}");
+
+        let (same_abi, rest): (Vec<_>, Vec<_>) =
+            matches.into_iter().partition(|a| ours.is_match(&a.name));
+        if !same_abi.is_empty() {
+            debug!(
+                "found assets for our float ABI: {}",
+                same_abi.iter().map(|a| a.name.as_str()).join(",")
+            );
+            return same_abi;
+        }
+
+        let (other_abi, no_abi): (Vec<_>, Vec<_>) =
+            rest.into_iter().partition(|a| theirs.is_match(&a.name));
+        if !no_abi.is_empty() {
+            debug!("no assets for our float ABI found, filtering out assets for the other ABI");
+            return no_abi;
+        }
+
+        debug!("only found assets for the other float ABI, falling back to those");
+        other_abi
+    }
+
+    // Some projects put the version in their asset names, and a release can end up with assets for
+    // more than one version, like when assets from an older release are uploaded again. When we
+    // know the release's tag, we prefer the assets that contain its version. If none of them do, we
+    // leave the matches alone.
+    fn maybe_filter_for_release_version(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        let Some(version) = self.release_tag.as_deref().and_then(version_from_tag) else {
+            return matches;
+        };
+
+        debug!("looking for assets containing the release version {version}");
+        let (with_version, without_version): (Vec<_>, Vec<_>) = matches
+            .into_iter()
+            .partition(|a| name_contains_version(&a.name, version));
+        if with_version.is_empty() {
+            debug!("no assets contain the release version");
+            return without_version;
+        }
+
+        debug!(
+            "found {} asset(s) containing the release version",
+            with_version.len()
+        );
+        with_version
+    }
+
     fn maybe_filter_for_matching_string(
         &self,
         matches: Vec<Asset>,
@@ -335,27 +746,197 @@ impl<'a> AssetPicker<'a> {
         ))
     }
 
-    fn maybe_pick_asset_for_macos_arm(
-        &self,
-        mut matches: Vec<Asset>,
-    ) -> (Vec<Asset>, Option<Asset>) {
-        if !self.running_on_macos_arm() {
+    // This only looks at assets that already matched our OS and architecture, so a static build
+    // for the wrong platform is never picked over a dynamic build for the right one.
+    fn maybe_filter_for_static(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if !self.prefer_static {
+            return matches;
+        }
+
+        let static_re = regex!(r"(?i)(?:\b|_)static(?:\b|_)");
+        let dynamic_re = regex!(r"(?i)(?:\b|_)dynamic(?:\b|_)");
+
+        let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        debug!("found multiple candidate assets, filtering for static binaries in {asset_names:?}");
+
+        if matches.iter().any(|a| static_re.is_match(&a.name)) {
+            let static_matches = matches
+                .into_iter()
+                .filter(|a| static_re.is_match(&a.name))
+                .collect::<Vec<_>>();
+            debug!(
+                "found static assets: {}",
+                static_matches.iter().map(|a| a.name.as_str()).join(",")
+            );
+            return static_matches;
+        }
+
+        if matches.iter().all(|a| dynamic_re.is_match(&a.name)) {
+            debug!("all assets are dynamic, falling back to all assets");
+            return matches;
+        }
+
+        debug!("no static assets found, filtering out dynamic assets");
+        matches
+            .into_iter()
+            .filter(|a| !dynamic_re.is_match(&a.name))
+            .collect()
+    }
+
+    // On Windows, projects often release builds for both the MSVC and GNU (MinGW) toolchains. Either
+    // will run, so we prefer MSVC builds, since that is the platform's native toolchain, unless we
+    // were told to prefer GNU builds.
+    fn maybe_filter_for_windows_toolchain(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if self.platform.target_os != OS::Windows {
+            return matches;
+        }
+
+        let (toolchain, toolchain_re) = if self.prefer_gnu_on_windows {
+            (
+                "GNU",
+                regex!(r"(?i)(?:\b|_)(?:gnu(?:llvm)?|mingw(?:32|64)?)(?:\b|_)"),
+            )
+        } else {
+            ("MSVC", regex!(r"(?i)(?:\b|_)msvc(?:\b|_)"))
+        };
+        let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        debug!(
+            "found multiple candidate assets and running on Windows, filtering for {toolchain} builds in {asset_names:?}",
+        );
+
+        if !matches.iter().any(|a| toolchain_re.is_match(&a.name)) {
+            debug!("did not find any {toolchain} builds");
+            return matches;
+        }
+        matches
+            .into_iter()
+            .filter(|a| toolchain_re.is_match(&a.name))
+            .collect()
+    }
+
+    // On macOS, some projects release an asset for each minimum OS version they support, like
+    // `macos12` and `macos13`. We drop the ones for a newer version than we're running on, then keep
+    // the ones for the highest version that's left, since that's the best fit for this OS. Assets
+    // without a version are always kept alongside those. If every asset has a version and all of
+    // them are for a newer version, we keep the ones for the oldest, which are the most likely to
+    // work.
+    fn maybe_filter_for_macos_version(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if self.platform.target_os != OS::MacOS
+            || !matches
+                .iter()
+                .any(|a| MacOsVersion::from_asset_name(&a.name).is_some())
+        {
+            return matches;
+        }
+        let Some(running) = *self.macos_version.get_or_init(MacOsVersion::running) else {
+            return matches;
+        };
+
+        let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        debug!(
+            "found multiple candidate assets and running on macOS {running}, filtering by minimum macOS version in {asset_names:?}",
+        );
+
+        let best = matches
+            .iter()
+            .filter_map(|a| MacOsVersion::from_asset_name(&a.name))
+            .filter(|v| *v <= running)
+            .max();
+        let keep = match best {
+            Some(best) => {
+                debug!("found assets for macOS {best}");
+                Some(best)
+            }
+            None if matches
+                .iter()
+                .all(|a| MacOsVersion::from_asset_name(&a.name).is_some()) =>
+            {
+                debug!("all assets are for a newer version of macOS, keeping the oldest");
+                matches
+                    .iter()
+                    .filter_map(|a| MacOsVersion::from_asset_name(&a.name))
+                    .min()
+            }
+            None => {
+                debug!("all versioned assets are for a newer version of macOS, filtering them out");
+                None
+            }
+        };
+        matches
+            .into_iter()
+            .filter(|a| match MacOsVersion::from_asset_name(&a.name) {
+                Some(v) => Some(v) == keep,
+                None => true,
+            })
+            .collect()
+    }
+
+    // This is the last tiebreaker before we fall back to sorting by name. An asset with an extension
+    // that isn't in the preference list, or with no extension at all, ranks after all of the ones
+    // that are.
+    fn maybe_filter_for_extension_preference(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        debug!(
+            "found multiple candidate assets, filtering by the extension preference {:?} in {asset_names:?}",
+            self.extension_preference,
+        );
+
+        let Some(best) = matches.iter().map(|a| self.extension_rank(&a.name)).min() else {
+            return matches;
+        };
+        matches
+            .into_iter()
+            .filter(|a| self.extension_rank(&a.name) == best)
+            .collect()
+    }
+
+    fn extension_rank(&self, name: &str) -> usize {
+        // The extension of a split asset part is the extension of the joined asset.
+        let name = split_asset_part(name).map_or(name, |(joined, _)| joined);
+        Extension::from_path(Path::new(name))
+            .ok()
+            .flatten()
+            .and_then(|ext| {
+                self.extension_preference
+                    .iter()
+                    .position(|p| p.eq_ignore_ascii_case(ext.extension_without_dot()))
+            })
+            .unwrap_or(self.extension_preference.len())
+    }
+
+    // On macOS, we prefer a binary for our arch, then a universal binary. On ARM, we'll fall back to
+    // an x86-64 binary after that, since it can run under Rosetta.
+    fn maybe_pick_asset_for_macos(&self, mut matches: Vec<Asset>) -> (Vec<Asset>, Option<Asset>) {
+        if self.platform.target_os != OS::MacOS {
             return (matches, None);
         }
 
         let asset_names = matches.iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
         debug!(
-            "found multiple candidate assets and running on macOS ARM, filtering for arm64 binaries in {asset_names:?}",
+            "found multiple candidate assets and running on macOS, filtering for native binaries in {asset_names:?}",
         );
 
-        let arch_matcher = aarch64_re();
+        let native_matcher = if self.running_on_macos_arm() {
+            aarch64_re()
+        } else {
+            self.arch_matcher()
+        };
+        if let Some(idx) = matches.iter().position(|a| {
+            name_matches(native_matcher, &a.name) && !name_matches(macos_universal_re(), &a.name)
+        }) {
+            debug!("found native binary named {}", matches[idx].name);
+            return (vec![], Some(matches.remove(idx)));
+        }
 
-        if let Some(idx) = matches.iter().position(|a| arch_matcher.is_match(&a.name)) {
-            debug!("found ARM binary named {}", matches[idx].name);
+        if let Some(idx) = matches
+            .iter()
+            .position(|a| name_matches(macos_universal_re(), &a.name))
+        {
+            debug!("found universal binary named {}", matches[idx].name);
             return (vec![], Some(matches.remove(idx)));
         }
 
-        debug!("did not find any ARM binaries");
+        debug!("did not find any native or universal binaries");
         (matches, None)
     }
 
@@ -399,6 +980,7 @@ impl<'a> AssetPicker<'a> {
         match (self.platform.target_arch, self.platform.target_endian) {
             (Arch::AArch64, _) => aarch64_re(),
             (Arch::Arm, _) => arm_re(),
+            (Arch::Loongarch64, _) => loongarch64_re(),
             (Arch::Mips, Endian::Little) => mipsle_re(),
             (Arch::Mips, Endian::Big) => mips_re(),
             (Arch::Mips64, Endian::Little) => mips64le_re(),
@@ -426,6 +1008,74 @@ impl<'a> AssetPicker<'a> {
     }
 }
 
+// The OS and arch regexes only match a name with a word boundary or `_` on each side, so they miss
+// names like `fooLinuxX64` or `foo-darwinArm64`. For those, we also try the name with a `-` wherever
+// a lowercase letter or digit is followed by an uppercase letter, which gives us `foo-Linux-X64`. We
+// don't split between letters and digits, since that would turn `ppc64le` into a match for `ppc64`.
+fn name_matches(re: &Regex, name: &str) -> bool {
+    re.is_match(name) || re.is_match(&split_at_case_boundaries(name))
+}
+
+fn split_at_case_boundaries(name: &str) -> Cow<'_, str> {
+    regex!(r"([a-z0-9])([A-Z])").replace_all(name, "$1-$2")
+}
+
+// A tag is usually the version with a prefix, like `v1.2.3` or `project-v1.2.3`, so the version
+// starts at the first digit. We need at least a major and minor version, since a lone number like
+// the `2` in `v2` is too likely to match some other part of an asset's name.
+fn version_from_tag(tag: &str) -> Option<&str> {
+    let version = &tag[tag.find(|c: char| c.is_ascii_digit())?..];
+    regex!(r"^\d+\.\d").is_match(version).then_some(version)
+}
+
+// The version must not be part of a longer version, so `1.2.3` is not in `foo-11.2.3` or
+// `foo-1.2.30`, but it is in `foo-1.2.3-linux.tar.gz` and `foo_v1.2.3.zip`.
+fn name_contains_version(name: &str, version: &str) -> bool {
+    name.match_indices(version).any(|(i, _)| {
+        let before = name[..i].chars().next_back();
+        let mut after = name[i + version.len()..].chars();
+        let starts_ok = before.is_none_or(|c| !c.is_ascii_digit() && c != '.');
+        let ends_ok = match after.next() {
+            None => true,
+            Some('.') => after.next().is_none_or(|c| !c.is_ascii_digit()),
+            Some(c) => !c.is_ascii_digit(),
+        };
+        starts_ok && ends_ok
+    })
+}
+
+// GitHub shows auto-generated "Source code" archives for every tag, and some projects also upload
+// their own source tarball with a name like `project-1.2.3.tar.gz` or `project-src.zip`. None of
+// these contain an executable, so we never want to pick one. We only treat an archive as source if
+// its name doesn't mention any OS or CPU architecture, since a binary release always has one of
+// those when there's more than one platform to choose from. A name with just a version is only
+// treated as source if some other asset does name an OS or architecture. Otherwise it may be the
+// only binary of a release for a single platform.
+fn is_source_archive(name: &str, has_platform_assets: bool) -> bool {
+    if regex!(r"^Source code \((?:tar\.gz|zip)\)$").is_match(name) {
+        return true;
+    }
+
+    let Ok(Some(ext)) = Extension::from_path(Path::new(name)) else {
+        return false;
+    };
+    // A disk image is only used for macOS binaries, even if its name has no OS in it.
+    if !ext.is_archive()
+        || ext == Extension::Dmg
+        || ALL_OSES_RE.is_match(name)
+        || ALL_ARCHES_RE.is_match(name)
+    {
+        return false;
+    }
+    let Some(stem) = name.strip_suffix(ext.extension()) else {
+        return false;
+    };
+
+    regex!(r"(?i)(?:^|[-_.])(?:src|sources?)(?:$|[-_.])").is_match(stem)
+        || (has_platform_assets
+            && regex!(r"(?i)^.+?[-_]v?\d+(?:\.\d+)+(?:[-+][0-9a-z.]+)?$").is_match(stem))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -524,56 +1174,1355 @@ mod test {
         "aarch64-apple-darwin - pick the all asset on macOS ARM if no aarch64 asset is available"
     )]
     #[test_case(
-        "x86_64-unknown-linux-musl",
-        &["project-Linux-x86_64.tar.gz"],
+        "aarch64-apple-darwin",
+        &["project-macos-arm64.tar.gz", "project-macos-universal.tar.gz", "project-macos-x86_64.tar.gz"],
         None,
         0 ;
-        "x86_64-unknown-linux-musl - only one asset"
+        "aarch64-apple-darwin - pick the arm64 asset over the universal asset"
     )]
     #[test_case(
-        "x86_64-unknown-linux-musl",
-        &["project-Linux-x86_64-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        "aarch64-apple-darwin",
+        &["project-macos-x86_64.tar.gz", "project-macos-universal.tar.gz"],
+        None,
+        1 ;
+        "aarch64-apple-darwin - pick the universal asset over the x86-64 asset"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-darwin-x86_64.tar.gz", "project-darwin-universal2.tar.gz", "project-linux-aarch64.tar.gz"],
+        None,
+        1 ;
+        "aarch64-apple-darwin - pick the universal2 asset over the x86-64 asset"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project_darwin_amd64.tar.gz", "project_darwin_fat.tar.gz"],
+        None,
+        1 ;
+        "aarch64-apple-darwin - pick the fat asset over the amd64 asset"
+    )]
+    #[test_case(
+        "x86_64-apple-darwin",
+        &["project-macos-universal.tar.gz", "project-macos-x86_64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-apple-darwin - pick the x86-64 asset over the universal asset"
+    )]
+    #[test_case(
+        "x86_64-apple-darwin",
+        &["project-macos-arm64.tar.gz", "project-macos-universal.tar.gz"],
+        None,
+        1 ;
+        "x86_64-apple-darwin - pick the universal asset over the arm64 asset"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-linux-universal.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - universal does not mean anything on Linux"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-musl",
+        &["project-Linux-x86_64.tar.gz"],
+        None,
+        0 ;
+        "x86_64-unknown-linux-musl - only one asset"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-musl",
+        &["project-Linux-x86_64-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
         None,
         1 ;
         "x86_64-unknown-linux-musl - pick the musl asset over gnu on a musl platform"
     )]
     #[test_case(
-        "x86_64-unknown-linux-musl",
-        &["project-Linux-x86_64-glibc.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        "x86_64-unknown-linux-musl",
+        &["project-Linux-x86_64-glibc.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-musl - pick the musl asset over glibc on a musl platform"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-musl",
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-musl - pick the musl asset over unspecified libc on a musl platform"
+    )]
+    #[test_case(
+        "aarch64-unknown-linux-gnu",
+        &["project-aarch64-linux-android.tar.gz", "project-aarch64-unknown-linux.tar.gz"],
+        None,
+        1 ;
+        "project-aarch64-unknown-linux - pick the non-Android asset when not on Android"
+    )]
+    #[test_case(
+        "riscv64gc-unknown-linux-gnu",
+        &["project-Linux-aarch64.tar.gz", "project-Linux-riscv64.tar.gz", "project-Linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "riscv64gc-unknown-linux-gnu - pick the riscv64 asset"
+    )]
+    #[test_case(
+        "riscv64gc-unknown-linux-gnu",
+        &["project-aarch64-unknown-linux-gnu.tar.gz", "project-riscv64gc-unknown-linux-gnu.tar.gz", "project-x86_64-unknown-linux-gnu.tar.gz"],
+        None,
+        1 ;
+        "riscv64gc-unknown-linux-gnu - pick the riscv64gc asset"
+    )]
+    #[test_case(
+        "riscv64gc-unknown-linux-gnu",
+        &["project_linux_amd64.tar.gz", "project_linux_arm64.tar.gz", "project_linux_rv64.tar.gz"],
+        None,
+        2 ;
+        "riscv64gc-unknown-linux-gnu - pick the rv64 asset"
+    )]
+    #[test_case(
+        "riscv64gc-unknown-linux-gnu",
+        &["project-linux-riscv.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        0 ;
+        "riscv64gc-unknown-linux-gnu - pick the riscv asset"
+    )]
+    #[test_case(
+        "riscv64gc-unknown-linux-gnu",
+        &["project-linux.tar.gz", "project-linux-riscv64.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "riscv64gc-unknown-linux-gnu - pick the riscv64 asset over an asset without an arch"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-linux-riscv64gc.tar.gz", "project-linux-rv64.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        2 ;
+        "x86_64-unknown-linux-gnu - do not pick riscv64 assets"
+    )]
+    #[test_case(
+        "loongarch64-unknown-linux-gnu",
+        &["project-Linux-aarch64.tar.gz", "project-Linux-loongarch64.tar.gz", "project-Linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "loongarch64-unknown-linux-gnu - pick the loongarch64 asset"
+    )]
+    #[test_case(
+        "loongarch64-unknown-linux-gnu",
+        &["project_linux_amd64.tar.gz", "project_linux_arm64.tar.gz", "project_linux_loong64.tar.gz"],
+        None,
+        2 ;
+        "loongarch64-unknown-linux-gnu - pick the loong64 asset"
+    )]
+    #[test_case(
+        "loongarch64-unknown-linux-musl",
+        &["project-la64-linux-musl.tar.gz", "project-x86_64-linux-musl.tar.gz", "project-aarch64-linux-musl.tar.gz"],
+        None,
+        0 ;
+        "loongarch64-unknown-linux-musl - pick the la64 asset"
+    )]
+    #[test_case(
+        "loongarch64-unknown-linux-gnu",
+        &["project-linux.tar.gz", "project-linux-loongarch64.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "loongarch64-unknown-linux-gnu - pick the loongarch64 asset over an asset without an arch"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-linux-loongarch64.tar.gz", "project-linux-loong64.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        2 ;
+        "x86_64-unknown-linux-gnu - do not pick loongarch64 assets"
+    )]
+    #[test_case(
+        "aarch64-unknown-linux-gnu",
+        &["project-linux-la64.tar.gz", "project-linux-aarch64.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "aarch64-unknown-linux-gnu - do not pick loongarch64 assets"
+    )]
+    #[test_case(
+        "armv7-unknown-linux-gnueabihf",
+        &["project-Linux-aarch64.tar.gz", "project-Linux-armv7.tar.gz", "project-Linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "armv7-unknown-linux-gnueabihf - pick the armv7 asset"
+    )]
+    #[test_case(
+        "armv7-unknown-linux-gnueabihf",
+        &["project_linux_amd64.tar.gz", "project_linux_arm64.tar.gz", "project_linux_armhf.tar.gz"],
+        None,
+        2 ;
+        "armv7-unknown-linux-gnueabihf - pick the armhf asset"
+    )]
+    #[test_case(
+        "arm-unknown-linux-gnueabihf",
+        &["project-aarch64-unknown-linux-gnu.tar.gz", "project-arm-unknown-linux-gnueabihf.tar.gz", "project-i686-unknown-linux-gnu.tar.gz", "project-x86_64-unknown-linux-gnu.tar.gz"],
+        None,
+        1 ;
+        "arm-unknown-linux-gnueabihf - pick the arm-unknown-linux-gnueabihf asset"
+    )]
+    #[test_case(
+        "armv7-unknown-linux-gnueabihf",
+        &["project-linux-arm_64.tar.gz", "project-linux-armv6.tar.gz"],
+        None,
+        1 ;
+        "armv7-unknown-linux-gnueabihf - do not pick an arm_64 asset"
+    )]
+    #[test_case(
+        "armv7-unknown-linux-gnueabihf",
+        &["project-linux-gnueabi.tar.gz", "project-linux-gnueabihf.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "armv7-unknown-linux-gnueabihf - pick the eabihf asset over the eabi asset"
+    )]
+    #[test_case(
+        "arm-unknown-linux-gnueabi",
+        &["project-arm-unknown-linux-gnueabi.tar.gz", "project-arm-unknown-linux-gnueabihf.tar.gz"],
+        None,
+        0 ;
+        "arm-unknown-linux-gnueabi - pick the eabi asset over the eabihf asset"
+    )]
+    #[test_case(
+        "arm-unknown-linux-gnueabi",
+        &["project-linux-armhf.tar.gz", "project-linux-armv6.tar.gz"],
+        None,
+        1 ;
+        "arm-unknown-linux-gnueabi - pick the asset with no float ABI over the hard-float asset"
+    )]
+    #[test_case(
+        "arm-unknown-linux-gnueabi",
+        &["project-linux-armel.tar.gz", "project-linux-armhf.tar.gz"],
+        None,
+        0 ;
+        "arm-unknown-linux-gnueabi - pick the armel asset over the armhf asset"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-linux-armv7.tar.gz", "project-linux-gnueabihf.tar.gz", "project-linux-x86_64.tar.gz"],
+        None,
+        2 ;
+        "x86_64-unknown-linux-gnu - do not pick 32-bit ARM assets"
+    )]
+    #[test_case(
+        "aarch64-unknown-linux-gnu",
+        &["project-linux-aarch64.tar.gz", "project-linux-armv7.tar.gz"],
+        None,
+        0 ;
+        "aarch64-unknown-linux-gnu - do not pick armv7 assets"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-win64.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the win64 asset"
+    )]
+    #[test_case(
+        "i686-pc-windows-msvc",
+        &["project-Linux-i686.tar.gz", "project-win32.zip"],
+        None,
+        1 ;
+        "i686-pc-windows-msvc - pick the win32 asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-x86_64-windows.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the windows asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-x86_64-win.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the win asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-x86_64-unknown-linux-gnu.tar.gz", "project-x86_64-pc-windows-msvc.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the pc-windows-msvc asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-x86_64-unknown-linux-gnu.tar.gz", "project-x86_64-pc-windows-gnu.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the pc-windows-gnu asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-x86_64-msvc.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the msvc asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-x86_64-mingw.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the mingw asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-Linux-x86_64.tar.gz", "project-x86_64-w64-mingw32.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the mingw32 asset"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-x86_64-pc-windows-gnu.zip", "project-x86_64-pc-windows-msvc.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the msvc asset over the gnu asset"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-1.2.3.tar.gz", "project-Linux-x86_64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - skips the source tarball when there is one binary"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-src.zip", "project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - skips the source zip file"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["tool-1.2.3.tar.gz"],
+        None,
+        0 ;
+        "x86_64-unknown-linux-gnu - picks the only asset of a single platform release"
+    )]
+    fn pick_asset(
+        platform_name: &str,
+        asset_names: &[&str],
+        matching: Option<&str>,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .clone();
+        let mut picker = AssetPicker {
+            matching,
+            matching_regex: None,
+            exact_asset_name: None,
+            platform,
+            is_musl: platform_name.contains("musl"),
+            macos_version: OnceLock::new(),
+            archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-x86_64-static.tar.gz"],
+        false,
+        0 ;
+        "static and dynamic - prefer_static off"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-x86_64-static.tar.gz"],
+        true,
+        1 ;
+        "static and dynamic - prefer_static on"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-x86_64.tar.gz"],
+        false,
+        0 ;
+        "dynamic and unmarked - prefer_static off"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-x86_64.tar.gz"],
+        true,
+        1 ;
+        "dynamic and unmarked - prefer_static on"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-aarch64-static.tar.gz"],
+        false,
+        0 ;
+        "static wrong arch and dynamic right arch - prefer_static off"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-dynamic.tar.gz", "project-Linux-aarch64-static.tar.gz"],
+        true,
+        0 ;
+        "static wrong arch and dynamic right arch - prefer_static on"
+    )]
+    #[test_case(
+        &["project-Windows-x86_64-static.zip", "project-Linux-x86_64-static.tar.gz", "project-Linux-x86_64_dynamic.tar.gz"],
+        true,
+        1 ;
+        "static wrong OS is ignored - prefer_static on"
+    )]
+    #[test_case(
+        &["project_static_Linux_x86_64.tar.gz", "project_dynamic_Linux_x86_64.tar.gz"],
+        true,
+        0 ;
+        "underscore separators - prefer_static on"
+    )]
+    #[test_case(
+        &["project-staticky-Linux-x86_64.tar.gz", "project-Linux-x86_64.tar.gz"],
+        true,
+        1 ;
+        "static must be a whole word - prefer_static on"
+    )]
+    fn pick_asset_prefer_static(
+        asset_names: &[&str],
+        prefer_static: bool,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform_name = "x86_64-unknown-linux-gnu";
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            prefer_static,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        &["project-x86_64-pc-windows-gnu.zip", "project-x86_64-pc-windows-msvc.zip"],
+        false,
+        1 ;
+        "gnu and msvc - prefer_gnu_on_windows off"
+    )]
+    #[test_case(
+        &["project-x86_64-pc-windows-gnu.zip", "project-x86_64-pc-windows-msvc.zip"],
+        true,
+        0 ;
+        "gnu and msvc - prefer_gnu_on_windows on"
+    )]
+    #[test_case(
+        &["project-x86_64-pc-windows-msvc.zip", "project-x86_64-w64-mingw32.zip"],
+        true,
+        1 ;
+        "mingw and msvc - prefer_gnu_on_windows on"
+    )]
+    #[test_case(
+        &["project-x86_64-pc-windows-gnullvm.zip", "project-x86_64-pc-windows-msvc.zip"],
+        true,
+        0 ;
+        "gnullvm and msvc - prefer_gnu_on_windows on"
+    )]
+    #[test_case(
+        &["project-x86_64-pc-windows-msvc.zip", "project-x86_64-windows.zip"],
+        true,
+        0 ;
+        "no gnu asset - prefer_gnu_on_windows on"
+    )]
+    #[test_case(
+        &["project-x86_64-pc-windows-gnu.zip", "project-x86_64-windows.zip"],
+        false,
+        0 ;
+        "no msvc asset - prefer_gnu_on_windows off"
+    )]
+    #[test_case(
+        &["project-x86_64-windows-gnu.zip", "project-x86_64-windows-msvcish.zip"],
+        false,
+        0 ;
+        "msvc must be a whole word - prefer_gnu_on_windows off"
+    )]
+    fn pick_asset_windows_toolchain(
+        asset_names: &[&str],
+        prefer_gnu_on_windows: bool,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform_name = "x86_64-pc-windows-msvc";
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            prefer_gnu_on_windows,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.zip"],
+        DEFAULT_EXTENSION_PREFERENCE,
+        0 ;
+        "tar.gz and zip - default preference"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.tar.xz", "project-Linux-x86_64.tar.zst"],
+        DEFAULT_EXTENSION_PREFERENCE,
+        2 ;
+        "compressed tarballs - default preference"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.zip"],
+        &["zip", "tar.gz"],
+        1 ;
+        "tar.gz and zip - prefer zip"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64.tar.bz2", "project-Linux-x86_64.tar.gz"],
+        &["tar.bz2"],
+        0 ;
+        "tar.bz2 and tar.gz - only tar.bz2 in the list"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64.tar.bz2", "project-Linux-x86_64.tar.gz"],
+        &["zip"],
+        0 ;
+        "neither extension in the list - sorted by name"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64.tar.gz.001", "project-Linux-x86_64.zip"],
+        DEFAULT_EXTENSION_PREFERENCE,
+        0 ;
+        "split asset - default preference"
+    )]
+    fn pick_asset_extension_preference(
+        asset_names: &[&str],
+        extension_preference: &[&str],
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform name"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            extension_preference.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos11-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos13-arm64.tar.gz"],
+        Some("12.6"),
+        1 ;
+        "arm64 - host between versions"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos11-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos13-arm64.tar.gz"],
+        Some("13.0"),
+        2 ;
+        "arm64 - host at the highest version"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos11-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos13-arm64.tar.gz"],
+        Some("14.2.1"),
+        2 ;
+        "arm64 - host newer than every version"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos13-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos11-arm64.tar.gz"],
+        Some("11.7"),
+        2 ;
+        "arm64 - host at the lowest version"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos13-arm64.tar.gz", "project-macos12-arm64.tar.gz"],
+        Some("11.7"),
+        1 ;
+        "arm64 - host older than every version"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos13-arm64.tar.gz", "project-macos-arm64.tar.gz"],
+        Some("12.6"),
+        1 ;
+        "arm64 - unversioned asset when versioned asset is too new"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos-arm64.tar.gz", "project-macos12-arm64.tar.gz"],
+        Some("12.6"),
+        0 ;
+        "arm64 - unversioned asset is kept with versioned asset"
+    )]
+    #[test_case(
+        "aarch64-apple-darwin",
+        &["project-macos11-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos13-arm64.tar.gz"],
+        None,
+        0 ;
+        "arm64 - unknown host version"
+    )]
+    #[test_case(
+        "x86_64-apple-darwin",
+        &["project-macos12-arm64.tar.gz", "project-macos12-x86_64.tar.gz", "project-macos13-x86_64.tar.gz", "project-macos14-x86_64.tar.gz"],
+        Some("13.4"),
+        2 ;
+        "x86-64 - host between versions"
+    )]
+    #[test_case(
+        "x86_64-apple-darwin",
+        &["project-osx10.15-x86_64.tar.gz", "project-osx10.13-x86_64.tar.gz", "project-macos11-x86_64.tar.gz"],
+        Some("10.15.7"),
+        0 ;
+        "x86-64 - minor versions"
+    )]
+    fn pick_asset_for_macos_version(
+        platform_name: &str,
+        asset_names: &[&str],
+        macos_version: Option<&str>,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+        picker.macos_version = OnceLock::from(macos_version.and_then(MacOsVersion::parse));
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        &["project-macos-arm64.tar.gz", "project-macos11-arm64.tar.gz", "project-macos12-arm64.tar.gz", "project-macos13-arm64.tar.gz"],
+        Some("12.6"),
+        &["project-macos-arm64.tar.gz", "project-macos12-arm64.tar.gz"] ;
+        "unversioned asset is kept with best versioned asset"
+    )]
+    #[test_case(
+        &["project-macos13-arm64.tar.gz", "project-macos-arm64.tar.gz", "project-macos14-arm64.tar.gz"],
+        Some("12.6"),
+        &["project-macos-arm64.tar.gz"] ;
+        "only unversioned asset when versioned assets are too new"
+    )]
+    #[test_case(
+        &["project-macos13-arm64.tar.gz", "project-macos14-arm64.tar.gz"],
+        Some("12.6"),
+        &["project-macos13-arm64.tar.gz"] ;
+        "oldest versioned asset when all assets are too new"
+    )]
+    #[test_case(
+        &["project-macos-arm64.tar.gz", "project-macos12-arm64.tar.gz"],
+        None,
+        &["project-macos-arm64.tar.gz", "project-macos12-arm64.tar.gz"] ;
+        "unknown host version"
+    )]
+    fn filter_for_macos_version(
+        asset_names: &[&str],
+        macos_version: Option<&str>,
+        expect: &[&str],
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("aarch64-apple-darwin")
+            .ok_or(anyhow!("invalid platform name"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+        picker.macos_version = OnceLock::from(macos_version.and_then(MacOsVersion::parse));
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let filtered = picker.maybe_filter_for_macos_version(assets);
+        assert_eq!(
+            filtered.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(),
+            expect,
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn macos_version_is_not_detected_without_versioned_assets() -> Result<()> {
+        let platform = Platform::find("aarch64-apple-darwin")
+            .ok_or(anyhow!("invalid platform name"))?
+            .clone();
+        let picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+
+        let url = Url::parse("https://example.com")?;
+        let assets = vec![Asset {
+            name: "project-macos-arm64.tar.gz".to_string(),
+            url,
+            size: None,
+        }];
+        picker.maybe_filter_for_macos_version(assets);
+        assert!(picker.macos_version.get().is_none());
+
+        Ok(())
+    }
+
+    fn linux_gnu_picker(scoring_weights: ScoringWeights) -> Result<AssetPicker<'static>> {
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform name"))?
+            .clone();
+        Ok(AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights,
+            false,
+        ))
+    }
+
+    #[test_case("project-Linux-x86_64-gnu.tar.gz", 30 ; "arch and our libc")]
+    #[test_case("project-Linux-x86_64.tar.gz", 25 ; "arch with no libc")]
+    #[test_case("project-Linux-x86_64-musl.tar.gz", 20 ; "arch with the other libc")]
+    #[test_case("project-Linux-gnu.tar.gz", 10 ; "our libc with no arch")]
+    #[test_case("project-Linux.tar.gz", 5 ; "no arch or libc")]
+    fn score(name: &str, expect: u64) -> Result<()> {
+        let picker = linux_gnu_picker(ScoringWeights::default())?;
+        assert_eq!(picker.score(name), expect);
+        Ok(())
+    }
+
+    #[test_case("project-Linux-x86_64.tar.zst", 5 ; "first extension")]
+    #[test_case("project-Linux-x86_64.zip", 1 ; "last extension")]
+    #[test_case("project-Linux-x86_64.tar.bz2", 0 ; "extension not in the preference")]
+    fn score_for_extension(name: &str, expect: u64) -> Result<()> {
+        let picker = linux_gnu_picker(ScoringWeights {
+            arch: 0,
+            libc: 0,
+            extension: 1,
+        })?;
+        assert_eq!(picker.score(name), expect);
+        Ok(())
+    }
+
+    const MAX_WEIGHTS: ScoringWeights = ScoringWeights {
+        arch: u32::MAX,
+        libc: u32::MAX,
+        extension: u32::MAX,
+    };
+
+    #[test_case("project-Linux-x86_64-gnu.tar.zst", 7 * u64::from(u32::MAX) ; "everything matches")]
+    #[test_case("project-Linux-x86_64-musl.tar.zst", 6 * u64::from(u32::MAX) ; "the other libc")]
+    #[test_case("project-Linux-x86_64.zip", 2 * u64::from(u32::MAX) + u64::from(u32::MAX / 2) ; "no libc")]
+    fn score_with_max_weights(name: &str, expect: u64) -> Result<()> {
+        let picker = linux_gnu_picker(MAX_WEIGHTS)?;
+        assert_eq!(picker.score(name), expect);
+        Ok(())
+    }
+
+    #[test]
+    fn pick_asset_with_max_weights() -> Result<()> {
+        let mut picker = linux_gnu_picker(MAX_WEIGHTS)?;
+        let url = Url::parse("https://example.com")?;
+        let asset_names = [
+            "project-Linux-x86_64-musl.tar.zst",
+            "project-Linux-x86_64-gnu.tar.zst",
+            "project-Linux-x86_64-gnu.zip",
+        ];
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, "project-Linux-x86_64-gnu.tar.zst");
+
+        Ok(())
+    }
+
+    #[test_case(
+        &["project-Linux.tar.gz", "project-Linux-x86_64.zip"],
+        ScoringWeights::default(),
+        1 ;
+        "arch beats extension by default"
+    )]
+    #[test_case(
+        &["project-Linux.tar.gz", "project-Linux-x86_64.zip"],
+        ScoringWeights { extension: 100, ..ScoringWeights::default() },
+        0 ;
+        "extension beats arch with a high extension weight"
+    )]
+    #[test_case(
+        &["project-Linux-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        ScoringWeights::default(),
+        1 ;
+        "arch beats libc by default"
+    )]
+    #[test_case(
+        &["project-Linux-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        ScoringWeights { libc: 50, ..ScoringWeights::default() },
+        0 ;
+        "libc beats arch with a high libc weight"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-gnu.zip", "project-Linux-x86_64-musl.tar.gz"],
+        ScoringWeights::default(),
+        0 ;
+        "libc beats extension by default"
+    )]
+    #[test_case(
+        &["project-Linux-x86_64-gnu.zip", "project-Linux-x86_64-musl.tar.gz"],
+        ScoringWeights { extension: 100, ..ScoringWeights::default() },
+        1 ;
+        "extension beats libc with a high extension weight"
+    )]
+    #[test_case(
+        &["project-x86_64.tar.gz", "project-Linux.zip"],
+        ScoringWeights { arch: 1000, ..ScoringWeights::default() },
+        1 ;
+        "an asset that does not name our OS is never picked"
+    )]
+    #[test_case(
+        &["project-Linux-aarch64.tar.gz", "project-Linux.zip"],
+        ScoringWeights { arch: 1000, ..ScoringWeights::default() },
+        1 ;
+        "an asset for another arch is never picked"
+    )]
+    fn pick_asset_with_scoring_weights(
+        asset_names: &[&str],
+        scoring_weights: ScoringWeights,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let mut picker = linux_gnu_picker(scoring_weights)?;
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    // These are named like the release assets that GoReleaser creates, using Go's `GOOS` and
+    // `GOARCH` names.
+    const GO_STYLE_ASSET_NAMES: &[&str] = &[
+        "project_1.2.3_darwin_amd64.tar.gz",
+        "project_1.2.3_darwin_arm64.tar.gz",
+        "project_1.2.3_freebsd_amd64.tar.gz",
+        "project_1.2.3_linux_386.tar.gz",
+        "project_1.2.3_linux_amd64.tar.gz",
+        "project_1.2.3_linux_arm64.tar.gz",
+        "project_1.2.3_linux_armv6.tar.gz",
+        "project_1.2.3_windows_386.zip",
+        "project_1.2.3_windows_amd64.zip",
+        "project_1.2.3_windows_arm64.zip",
+    ];
+
+    #[test_case("x86_64-apple-darwin", "project_1.2.3_darwin_amd64.tar.gz" ; "x86_64-apple-darwin")]
+    #[test_case("aarch64-apple-darwin", "project_1.2.3_darwin_arm64.tar.gz" ; "aarch64-apple-darwin")]
+    #[test_case("x86_64-unknown-freebsd", "project_1.2.3_freebsd_amd64.tar.gz" ; "x86_64-unknown-freebsd")]
+    #[test_case("i686-unknown-linux-gnu", "project_1.2.3_linux_386.tar.gz" ; "i686-unknown-linux-gnu")]
+    #[test_case("x86_64-unknown-linux-gnu", "project_1.2.3_linux_amd64.tar.gz" ; "x86_64-unknown-linux-gnu")]
+    #[test_case("aarch64-unknown-linux-gnu", "project_1.2.3_linux_arm64.tar.gz" ; "aarch64-unknown-linux-gnu")]
+    #[test_case("i686-pc-windows-msvc", "project_1.2.3_windows_386.zip" ; "i686-pc-windows-msvc")]
+    #[test_case("x86_64-pc-windows-msvc", "project_1.2.3_windows_amd64.zip" ; "x86_64-pc-windows-msvc")]
+    #[test_case("aarch64-pc-windows-msvc", "project_1.2.3_windows_arm64.zip" ; "aarch64-pc-windows-msvc")]
+    fn pick_asset_with_go_style_names(platform_name: &str, expect: &str) -> Result<()> {
+        let picked_asset = pick_asset_for_platform(platform_name, GO_STYLE_ASSET_NAMES)?;
+        assert_eq!(picked_asset.name, expect);
+        Ok(())
+    }
+
+    // Each case has one asset using Go's names and one using other common names, so we check that
+    // each naming convention is recognized for a platform and rejected for the other.
+    #[test_case("i686-unknown-linux-gnu", &["project-linux-x86_64.tar.gz", "project_linux_386.tar.gz"], 1 ; "386 over x86_64 on i686")]
+    #[test_case("i686-unknown-linux-gnu", &["project_linux_amd64.tar.gz", "project-linux-i686.tar.gz"], 1 ; "i686 over amd64 on i686")]
+    #[test_case("i686-unknown-linux-gnu", &["project_linux_amd64.tar.gz", "project-linux-i386.tar.gz"], 1 ; "i386 over amd64 on i686")]
+    #[test_case("i686-unknown-linux-gnu", &["project-linux-x86-64.tar.gz", "project-linux-x86.tar.gz"], 1 ; "x86 over x86-64 on i686")]
+    #[test_case("x86_64-unknown-linux-gnu", &["project-linux-i686.tar.gz", "project_linux_amd64.tar.gz"], 1 ; "amd64 over i686 on x86_64")]
+    #[test_case("x86_64-unknown-linux-gnu", &["project_linux_386.tar.gz", "project-linux-x86_64.tar.gz"], 1 ; "x86_64 over 386 on x86_64")]
+    #[test_case("aarch64-unknown-linux-gnu", &["project_linux_amd64.tar.gz", "project-linux-aarch64.tar.gz"], 1 ; "aarch64 over amd64 on aarch64")]
+    #[test_case("aarch64-unknown-linux-gnu", &["project-linux-x86_64.tar.gz", "project_linux_arm64.tar.gz"], 1 ; "arm64 over x86_64 on aarch64")]
+    #[test_case("x86_64-apple-darwin", &["project_linux_amd64.tar.gz", "project-macos-x86_64.tar.gz"], 1 ; "macos on x86_64 macOS")]
+    #[test_case("x86_64-apple-darwin", &["project-linux-x86_64.tar.gz", "project-osx-amd64.tar.gz"], 1 ; "osx on x86_64 macOS")]
+    #[test_case("aarch64-apple-darwin", &["project-macos-aarch64.tar.gz", "project_linux_arm64.tar.gz"], 0 ; "macos over linux on aarch64 macOS")]
+    #[test_case("i686-pc-windows-msvc", &["project-windows-x64.zip", "project-windows-x86.zip"], 1 ; "x86 over x64 on i686 Windows")]
+    #[test_case("x86_64-pc-windows-msvc", &["project-windows-x86.zip", "project-windows-x64.zip"], 1 ; "x64 over x86 on x86_64 Windows")]
+    fn pick_asset_with_mixed_naming_conventions(
+        platform_name: &str,
+        asset_names: &[&str],
+        expect_idx: usize,
+    ) -> Result<()> {
+        let picked_asset = pick_asset_for_platform(platform_name, asset_names)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+        Ok(())
+    }
+
+    #[test_case("x86_64-unknown-linux-gnu", &["fooDarwinX64.tar.gz", "fooLinuxX64.tar.gz", "fooWindowsX64.zip"], 1 ; "camelCase on x86_64 Linux")]
+    #[test_case("x86_64-unknown-linux-gnu", &["fooLinuxArm64.tar.gz", "fooLinuxX64.tar.gz"], 1 ; "camelCase arch on x86_64 Linux")]
+    #[test_case("x86_64-unknown-linux-gnu", &["foo-linuxArm64.tar.gz", "foo-linuxAmd64.tar.gz"], 1 ; "camelCase after a separator on x86_64 Linux")]
+    #[test_case("aarch64-apple-darwin", &["foo-darwinX64.tar.gz", "foo-darwinArm64.tar.gz", "foo-linuxArm64.tar.gz"], 1 ; "camelCase on aarch64 macOS")]
+    #[test_case("aarch64-apple-darwin", &["fooLinuxArm64.tar.gz", "fooMacOSArm64.tar.gz"], 1 ; "camelCase macOS on aarch64 macOS")]
+    #[test_case("x86_64-unknown-freebsd", &["foo-FreeBSD-x86_64.tar.gz", "foo-NetBSD-x86_64.tar.gz"], 0 ; "acronyms are still matched")]
+    fn pick_asset_with_camel_case_names(
+        platform_name: &str,
+        asset_names: &[&str],
+        expect_idx: usize,
+    ) -> Result<()> {
+        let picked_asset = pick_asset_for_platform(platform_name, asset_names)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+        Ok(())
+    }
+
+    #[test_case("fooLinuxX64", "foo-Linux-X64")]
+    #[test_case("foo-darwinArm64", "foo-darwin-Arm64")]
+    #[test_case("foo64Linux", "foo64-Linux")]
+    #[test_case("foo-linux-x86_64", "foo-linux-x86_64")]
+    #[test_case("foo-FreeBSD", "foo-Free-BSD")]
+    fn split_at_case_boundaries(name: &str, expect: &str) {
+        assert_eq!(super::split_at_case_boundaries(name), expect);
+    }
+
+    fn pick_asset_for_platform(platform_name: &str, asset_names: &[&str]) -> Result<Asset> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        picker.pick_asset(assets)
+    }
+
+    #[test_case(
+        Some("v1.2.3"),
+        &["project-1.2.2-linux-amd64.tar.gz", "project-1.2.3-linux-amd64.tar.gz"],
+        1 ;
+        "v-prefixed tag picks the asset with its version"
+    )]
+    #[test_case(
+        Some("1.2.2"),
+        &["project-1.2.2-linux-amd64.tar.gz", "project-1.2.3-linux-amd64.tar.gz"],
+        0 ;
+        "bare version tag picks the asset with its version"
+    )]
+    #[test_case(
+        Some("project-v1.2.3"),
+        &["project_v1.2.2_linux_amd64.tar.gz", "project_v1.2.3_linux_amd64.tar.gz"],
+        1 ;
+        "prefixed tag picks the asset with its version"
+    )]
+    #[test_case(
+        Some("v1.2.3"),
+        &["project-11.2.3-linux-amd64.tar.gz", "project-1.2.30-linux-amd64.tar.gz", "project-1.2.3-linux-amd64.tar.gz"],
+        2 ;
+        "version must not be part of a longer version"
+    )]
+    #[test_case(
+        Some("v1.2.3"),
+        &["project-1.2.3-linux-amd64.tar.gz", "project-1.2.2-linux-amd64-musl.tar.gz"],
+        0 ;
+        "version is preferred even when the other asset would win without it"
+    )]
+    #[test_case(
+        Some("v2.0.0"),
+        &["project-1.2.3-linux-amd64.tar.gz", "project-1.2.2-linux-amd64.tar.gz"],
+        1 ;
+        "no asset contains the version, so the first by name is picked"
+    )]
+    #[test_case(
         None,
+        &["project-1.2.3-linux-amd64.tar.gz", "project-1.2.2-linux-amd64.tar.gz"],
         1 ;
-        "x86_64-unknown-linux-musl - pick the musl asset over glibc on a musl platform"
+        "no tag, so the first by name is picked"
     )]
     #[test_case(
-        "x86_64-unknown-linux-musl",
-        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        Some("v2"),
+        &["project-linux-arm64_v2.tar.gz", "project-linux-x86_64_v2.tar.gz", "project-linux-x86_64.tar.gz"],
+        2 ;
+        "tag with only a major version is ignored"
+    )]
+    fn pick_asset_with_release_tag(
+        release_tag: Option<&str>,
+        asset_names: &[&str],
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform name"))?
+            .clone();
+        let mut picker = AssetPicker::new(
+            None,
+            None,
+            None,
+            platform,
+            false,
+            false,
+            false,
+            false,
+            DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            ScoringWeights::default(),
+            false,
+        );
+        picker.set_release_tag(release_tag.map(String::from));
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.zip", "project-Windows-x86_64.zip"],
         None,
+        "project-Linux-x86_64.zip",
         1 ;
-        "x86_64-unknown-linux-musl - pick the musl asset over unspecified libc on a musl platform"
+        "picks the asset with the exact name over the heuristic choice"
     )]
     #[test_case(
-        "aarch64-unknown-linux-gnu",
-        &["project-aarch64-linux-android.tar.gz", "project-aarch64-unknown-linux.tar.gz"],
+        "x86_64-unknown-linux-gnu",
+        &["project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"],
         None,
+        "project-Windows-x86_64.zip",
         1 ;
-        "project-aarch64-unknown-linux - pick the non-Android asset when not on Android"
+        "picks the asset with the exact name even if it is not for this platform"
     )]
-    fn pick_asset(
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-Linux-x86_64-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        Some("musl"),
+        "project-Linux-x86_64-gnu.tar.gz",
+        0 ;
+        "exact name takes precedence over matching"
+    )]
+    fn pick_asset_by_exact_name(
         platform_name: &str,
         asset_names: &[&str],
         matching: Option<&str>,
+        exact_asset_name: &str,
         expect_idx: usize,
     ) -> Result<()> {
         crate::test_case::init_logging();
 
         let platform = Platform::find(platform_name)
-            .ok_or(anyhow!("invalid platform name - {platform_name}"))?
+            .ok_or(anyhow!("invalid platform"))?
             .clone();
         let mut picker = AssetPicker {
             matching,
+            matching_regex: None,
+            exact_asset_name: Some(exact_asset_name),
             platform,
             is_musl: platform_name.contains("musl"),
+            macos_version: OnceLock::new(),
+            archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pick_asset_by_exact_name_with_no_match() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform"))?
+            .clone();
+        let mut picker = AssetPicker {
+            matching: None,
+            matching_regex: None,
+            exact_asset_name: Some("project-Linux-x86_64"),
+            platform,
+            is_musl: false,
+            macos_version: OnceLock::new(),
+            archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = ["project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"]
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets);
+        assert!(picked_asset.is_err());
+        assert_eq!(
+            picked_asset.unwrap_err().to_string(),
+            r#"could not find a release asset named "project-Linux-x86_64" from project-Linux-x86_64.tar.gz, project-Windows-x86_64.zip"#,
+        );
+
+        Ok(())
+    }
+
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.zip", "project-Windows-x86_64.zip"],
+        r"-Linux-.*\.zip$",
+        1 ;
+        "regex narrows to one asset"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["tool-Linux-x86_64.tar.gz", "project-Linux-i686.tar.gz", "project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"],
+        r"^project-(Linux|Windows)-",
+        2 ;
+        "regex narrows to several assets and the picker breaks the tie"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"],
+        r"Windows",
+        1 ;
+        "regex can pick an asset that is not for this platform"
+    )]
+    fn pick_asset_by_matching_regex(
+        platform_name: &str,
+        asset_names: &[&str],
+        matching_regex: &str,
+        expect_idx: usize,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find(platform_name)
+            .ok_or(anyhow!("invalid platform"))?
+            .clone();
+        let mut picker = AssetPicker {
+            matching: None,
+            matching_regex: Some(Regex::new(matching_regex)?),
+            exact_asset_name: None,
+            platform,
+            is_musl: false,
+            macos_version: OnceLock::new(),
             archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
         };
 
         let url = Url::parse("https://example.com")?;
@@ -582,6 +2531,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
             })
             .collect::<Vec<_>>();
 
@@ -591,6 +2541,48 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn pick_asset_by_matching_regex_with_no_match() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform"))?
+            .clone();
+        let mut picker = AssetPicker {
+            matching: None,
+            matching_regex: Some(Regex::new(r"-(amd64|arm64)\.tar\.gz$")?),
+            exact_asset_name: None,
+            platform,
+            is_musl: false,
+            macos_version: OnceLock::new(),
+            archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = ["project-Linux-x86_64.tar.gz", "project-Windows-x86_64.zip"]
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets);
+        assert_eq!(
+            picked_asset.unwrap_err().to_string(),
+            r"could not find a release asset matching the regex `-(amd64|arm64)\.tar\.gz$` from project-Linux-x86_64.tar.gz, project-Windows-x86_64.zip",
+        );
+
+        Ok(())
+    }
+
     #[test_case(
         "x86_64-unknown-linux-gnu",
         &["project-Linux-x86_64.tar.gz", "project-Linux-x86_64.gz"],
@@ -632,9 +2624,97 @@ mod test {
             .clone();
         let mut picker = AssetPicker {
             matching,
+            matching_regex: None,
+            exact_asset_name: None,
             platform,
             is_musl: platform_name.contains("musl"),
+            macos_version: OnceLock::new(),
             archive_only: true,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = asset_names
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                size: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, asset_names[expect_idx]);
+
+        Ok(())
+    }
+
+    #[test_case(
+        Libc::Musl,
+        &["project-Linux-x86_64-gnu.tar.gz", "project-Linux-x86_64-musl.tar.gz"],
+        1 ;
+        "musl - pick the musl asset"
+    )]
+    #[test_case(
+        Libc::Musl,
+        &["project-Linux-x86_64-musl.tar.gz", "project-Linux-x86_64-glibc.tar.gz"],
+        0 ;
+        "musl - pick the musl asset over glibc"
+    )]
+    #[test_case(
+        Libc::Musl,
+        &["project-Linux-x86_64-gnu.tar.gz", "project-Linux-x86_64.tar.gz"],
+        1 ;
+        "musl - fall back to an asset with no libc"
+    )]
+    #[test_case(
+        Libc::Gnu,
+        &["project-Linux-x86_64-musl.tar.gz", "project-Linux-x86_64-gnu.tar.gz"],
+        1 ;
+        "gnu - pick the gnu asset"
+    )]
+    #[test_case(
+        Libc::Gnu,
+        &["project-Linux-x86_64-musl.tar.gz", "project-Linux-x86_64-glibc.tar.gz"],
+        1 ;
+        "gnu - pick the glibc asset"
+    )]
+    #[test_case(
+        Libc::Gnu,
+        &["project-Linux-x86_64-musl.tar.gz", "project-Linux-x86_64.tar.gz"],
+        1 ;
+        "gnu - pick an asset with no libc over musl"
+    )]
+    #[test_case(
+        Libc::Gnu,
+        &["project-Linux-x86_64-musl.tar.gz", "project-Windows-x86_64-gnu.zip"],
+        0 ;
+        "gnu - fall back to a musl asset"
+    )]
+    fn pick_asset_for_libc(libc: Libc, asset_names: &[&str], expect_idx: usize) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let mut picker = AssetPicker {
+            matching: None,
+            matching_regex: None,
+            exact_asset_name: None,
+            platform: Platform::find("x86_64-unknown-linux-gnu")
+                .ok_or(anyhow!("invalid platform"))?
+                .clone(),
+            is_musl: libc == Libc::Musl,
+            macos_version: OnceLock::new(),
+            archive_only: false,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
         };
 
         let url = Url::parse("https://example.com")?;
@@ -643,6 +2723,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
             })
             .collect::<Vec<_>>();
 
@@ -716,6 +2797,22 @@ mod test {
         "could not find a release asset after filtering for valid extensions" ;
         "aarch64-apple-darwin - does not pick .AppImage files"
     )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        false,
+        &["project-src.tar.gz"],
+        None,
+        "no platform binary found, only source archives are available from project-src.tar.gz" ;
+        "x86_64-unknown-linux-gnu - does not pick the only asset if it is a source tarball"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        false,
+        &["Source code (tar.gz)", "Source code (zip)", "project-v1.2.3-src.tar.gz"],
+        None,
+        "no platform binary found, only source archives are available from" ;
+        "x86_64-unknown-linux-gnu - does not pick any source archives"
+    )]
     fn pick_asset_errors(
         platform_name: &str,
         archive_only: bool,
@@ -730,9 +2827,18 @@ mod test {
             .clone();
         let mut picker = AssetPicker {
             matching,
+            matching_regex: None,
+            exact_asset_name: None,
             platform,
             is_musl: platform_name.contains("musl"),
+            macos_version: OnceLock::new(),
             archive_only,
+            prefer_static: false,
+            prefer_gnu_on_windows: false,
+            extension_preference: DEFAULT_EXTENSION_PREFERENCE.to_vec(),
+            scoring_weights: ScoringWeights::default(),
+            interactive: false,
+            release_tag: None,
         };
 
         let url = Url::parse("https://example.com")?;
@@ -741,6 +2847,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
             })
             .collect::<Vec<_>>();
 
@@ -754,4 +2861,47 @@ mod test {
 
         Ok(())
     }
+
+    #[test_case("project-1.2.3.tar.gz", true, true ; "name and version tarball")]
+    #[test_case("project-v1.2.3.zip", true, true ; "name and v-prefixed version zip file")]
+    #[test_case("project_1.2.3-rc.1.tar.xz", true, true ; "name and prerelease version")]
+    #[test_case("project-src.tar.gz", true, true ; "src suffix")]
+    #[test_case("project-1.2.3-source.tgz", true, true ; "version and source suffix")]
+    #[test_case("Source code (tar.gz)", true, true ; "GitHub source code tarball")]
+    #[test_case("Source code (zip)", true, true ; "GitHub source code zip file")]
+    #[test_case("project-1.2.3-linux-amd64.tar.gz", true, false ; "has OS and arch")]
+    #[test_case("project-1.2.3-x86_64.tar.gz", true, false ; "has arch")]
+    #[test_case("project-1.2.3-darwin.zip", true, false ; "has OS")]
+    #[test_case("project-1.2.3.gz", true, false ; "not an archive")]
+    #[test_case("project.tar.gz", true, false ; "no version")]
+    #[test_case("sourcegraph.tar.gz", true, false ; "source is part of a word")]
+    #[test_case("project-1.2.3.tar.gz", false, false ; "version only with no platform assets")]
+    #[test_case("project-src.tar.gz", false, true ; "src suffix with no platform assets")]
+    fn is_source_archive(name: &str, has_platform_assets: bool, expect: bool) {
+        assert_eq!(super::is_source_archive(name, has_platform_assets), expect);
+    }
+
+    #[test_case("v1.2.3", Some("1.2.3") ; "v prefix")]
+    #[test_case("1.2.3", Some("1.2.3") ; "no prefix")]
+    #[test_case("project-v1.2.3", Some("1.2.3") ; "project prefix")]
+    #[test_case("cli/v1.2.3-rc.1", Some("1.2.3-rc.1") ; "path prefix and prerelease")]
+    #[test_case("nightly", None ; "no version")]
+    #[test_case("v2", None ; "major version only")]
+    #[test_case("release-1", None ; "prefixed major version only")]
+    fn version_from_tag(tag: &str, expect: Option<&str>) {
+        assert_eq!(super::version_from_tag(tag), expect);
+    }
+
+    #[test_case("project-1.2.3-linux.tar.gz", true ; "dash delimited")]
+    #[test_case("project_v1.2.3_linux.tar.gz", true ; "v prefix")]
+    #[test_case("project-1.2.3.tar.gz", true ; "followed by an extension")]
+    #[test_case("1.2.3", true ; "whole name")]
+    #[test_case("project-11.2.3-linux.tar.gz", false ; "longer major version")]
+    #[test_case("project-1.2.30-linux.tar.gz", false ; "longer patch version")]
+    #[test_case("project-0.1.2.3-linux.tar.gz", false ; "more components before")]
+    #[test_case("project-1.2.3.4-linux.tar.gz", false ; "more components after")]
+    #[test_case("project-1.2.2-linux.tar.gz", false ; "different version")]
+    fn name_contains_version(name: &str, expect: bool) {
+        assert_eq!(super::name_contains_version(name, "1.2.3"), expect);
+    }
 }
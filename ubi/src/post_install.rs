@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use tempfile::{tempdir, TempDir};
+
+// This wraps the closure passed to `UbiBuilder::post_install`. The closures aren't `Debug`, so we
+// need to implement that ourselves.
+pub(crate) struct PostInstallHook(Box<HookFn>);
+
+type HookFn = dyn Fn(&Path) -> Result<()> + Send + Sync;
+
+impl PostInstallHook {
+    pub(crate) fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
+    {
+        Self(Box::new(hook))
+    }
+
+    pub(crate) fn run(&self, path: &Path) -> Result<()> {
+        (self.0)(path)
+    }
+}
+
+impl fmt::Debug for PostInstallHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PostInstallHook")
+    }
+}
+
+// This is a copy of the files that an install may replace, taken before installing, so that the
+// install can be rolled back if the post-install hook fails. Paths that don't exist yet aren't
+// saved, so rolling back just removes whatever was installed there. A symlink is saved as its
+// target rather than as a copy of the file it points to, which matters for a versioned install's
+// link. The copies are deleted when this is dropped.
+#[derive(Debug)]
+pub(crate) struct InstallBackup {
+    _dir: TempDir,
+    saved: Vec<(PathBuf, Saved)>,
+}
+
+#[derive(Debug)]
+enum Saved {
+    Copy(PathBuf),
+    Link(PathBuf),
+}
+
+impl InstallBackup {
+    pub(crate) fn new(paths: &[PathBuf]) -> Result<Self> {
+        let dir = tempdir().context("could not create a temp dir for a backup of the install")?;
+        let mut saved = vec![];
+        for (i, path) in paths.iter().enumerate() {
+            if saved.iter().any(|(p, _)| p == path) {
+                continue;
+            }
+            let Ok(meta) = fs::symlink_metadata(path) else {
+                continue;
+            };
+            if meta.file_type().is_symlink() {
+                let target = fs::read_link(path)
+                    .with_context(|| format!("could not read the link at {}", path.display()))?;
+                saved.push((path.clone(), Saved::Link(target)));
+            } else if meta.is_file() {
+                let copy = dir.path().join(i.to_string());
+                debug!("backing up {} to {}", path.display(), copy.display());
+                fs::copy(path, &copy).with_context(|| {
+                    format!("could not back up {} to {}", path.display(), copy.display())
+                })?;
+                saved.push((path.clone(), Saved::Copy(copy)));
+            }
+        }
+        Ok(Self { _dir: dir, saved })
+    }
+
+    // Removes each of the `installed` paths, then puts back the files that were there before the
+    // install.
+    pub(crate) fn restore(&self, installed: &[PathBuf]) -> Result<()> {
+        for path in installed {
+            remove_if_exists(path)?;
+        }
+        for (path, saved) in &self.saved {
+            remove_if_exists(path)?;
+            match saved {
+                Saved::Copy(copy) => {
+                    debug!("restoring {} from {}", path.display(), copy.display());
+                    fs::copy(copy, path)
+                        .with_context(|| format!("could not restore {}", path.display()))?;
+                }
+                Saved::Link(target) => {
+                    debug!(
+                        "restoring the link from {} to {}",
+                        path.display(),
+                        target.display()
+                    );
+                    symlink(target, path).with_context(|| {
+                        format!("could not restore the link at {}", path.display())
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    if fs::symlink_metadata(path).is_err() {
+        return Ok(());
+    }
+    debug!("removing {}", path.display());
+    fs::remove_file(path).with_context(|| format!("could not remove {}", path.display()))
+}
+
+#[cfg(target_family = "unix")]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(target_family = "windows")]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_backup_restores_replaced_files() -> Result<()> {
+        let td = tempdir()?;
+        let existing = td.path().join("project");
+        let new = td.path().join("project-helper");
+        fs::write(&existing, "old")?;
+
+        let backup = InstallBackup::new(&[existing.clone(), new.clone()])?;
+        fs::write(&existing, "new")?;
+        fs::write(&new, "new")?;
+        backup.restore(&[existing.clone(), new.clone()])?;
+
+        assert_eq!(fs::read_to_string(&existing)?, "old");
+        assert!(!new.exists(), "a file that did not exist before is removed");
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn install_backup_restores_links() -> Result<()> {
+        let td = tempdir()?;
+        let link = td.path().join("project");
+        let old = td.path().join("project-1.0.0");
+        let new = td.path().join("project-1.1.0");
+        fs::write(&old, "old")?;
+        symlink(Path::new("project-1.0.0"), &link)?;
+
+        let backup = InstallBackup::new(&[link.clone(), new.clone()])?;
+        fs::write(&new, "new")?;
+        fs::remove_file(&link)?;
+        symlink(Path::new("project-1.1.0"), &link)?;
+        backup.restore(&[new.clone(), link.clone()])?;
+
+        assert_eq!(fs::read_link(&link)?, PathBuf::from("project-1.0.0"));
+        assert_eq!(fs::read_to_string(&link)?, "old");
+        assert!(!new.exists(), "the new versioned exe is removed");
+
+        Ok(())
+    }
+}
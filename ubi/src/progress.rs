@@ -0,0 +1,103 @@
+use std::fmt::Debug;
+
+/// `DownloadProgress` is used to report the progress of a release asset download. Pass an
+/// implementation of this trait to
+/// [`UbiBuilder::download_progress`](crate::UbiBuilder::download_progress) to be notified as the
+/// download proceeds.
+pub trait DownloadProgress: Debug + Send + Sync {
+    /// Called once before any data is downloaded. The total is taken from the response's
+    /// `Content-Length` header, and is `None` if the server did not send one.
+    fn start(&self, total: Option<u64>);
+    /// Called after each chunk of data is downloaded with the number of bytes downloaded so far.
+    fn advance(&self, downloaded: u64);
+    /// Called once the download is complete.
+    fn finish(&self);
+}
+
+#[cfg(feature = "progress")]
+pub(crate) use bar::ProgressBarReporter;
+
+#[cfg(feature = "progress")]
+mod bar {
+    use super::DownloadProgress;
+    use indicatif::{ProgressBar, ProgressStyle};
+    use std::sync::Mutex;
+
+    // This draws a progress bar on stderr. If we don't know the size of the download, it shows a
+    // spinner with the number of bytes downloaded instead.
+    #[derive(Debug)]
+    pub(crate) struct ProgressBarReporter {
+        bar: Mutex<Option<ProgressBar>>,
+        use_color: bool,
+    }
+
+    impl ProgressBarReporter {
+        pub(crate) fn new(use_color: bool) -> Self {
+            Self {
+                bar: Mutex::new(None),
+                use_color,
+            }
+        }
+    }
+
+    fn bar_template(use_color: bool) -> &'static str {
+        if use_color {
+            "{wide_bar:.cyan/blue} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})"
+        } else {
+            "{wide_bar} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})"
+        }
+    }
+
+    fn spinner_template(use_color: bool) -> &'static str {
+        if use_color {
+            "{spinner:.green} {bytes} ({bytes_per_sec})"
+        } else {
+            "{spinner} {bytes} ({bytes_per_sec})"
+        }
+    }
+
+    impl DownloadProgress for ProgressBarReporter {
+        fn start(&self, total: Option<u64>) {
+            let bar = if let Some(total) = total {
+                ProgressBar::new(total).with_style(
+                    ProgressStyle::with_template(bar_template(self.use_color))
+                        .expect("progress bar template is valid"),
+                )
+            } else {
+                ProgressBar::new_spinner().with_style(
+                    ProgressStyle::with_template(spinner_template(self.use_color))
+                        .expect("progress spinner template is valid"),
+                )
+            };
+            *self.bar.lock().unwrap() = Some(bar);
+        }
+
+        fn advance(&self, downloaded: u64) {
+            if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+                bar.set_position(downloaded);
+            }
+        }
+
+        fn finish(&self) {
+            if let Some(bar) = self.bar.lock().unwrap().take() {
+                bar.finish_and_clear();
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use test_case::test_case;
+
+        #[test_case(true ; "with color")]
+        #[test_case(false ; "without color")]
+        fn templates_are_valid(use_color: bool) {
+            for template in [bar_template(use_color), spinner_template(use_color)] {
+                assert!(ProgressStyle::with_template(template).is_ok(), "{template}");
+                // Styles are given after a `:` inside the braces, like `{spinner:.green}`.
+                assert_eq!(template.contains(":."), use_color, "{template}");
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use crate::forge::ForgeType;
+use anyhow::{anyhow, Context, Result};
+use url::Url;
+
+/// `ProjectRef` is a project reference parsed by [`parse_project`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectRef {
+    /// The forge that hosts the project.
+    pub forge: ForgeType,
+    /// The project's owner, like `houseabsolute`. For SourceHut, this includes the leading `~`. For
+    /// Azure DevOps, this is the organization and project, like `some-org/some-project`.
+    pub owner: String,
+    /// The project's repo name, like `precious`.
+    pub repo: String,
+    /// The release tag, if the input ended with `@<tag>` or was the URL of a release or a release
+    /// asset.
+    pub tag: Option<String>,
+    /// The host from the project's URL, like `codeberg.org`, including the port if the URL has one.
+    /// This is `None` if the input was not a URL.
+    pub host: Option<String>,
+}
+
+impl ProjectRef {
+    /// Returns the project name as `owner/repo`, or `org/project/repo` for Azure DevOps, which can be
+    /// passed to
+    /// [`UbiBuilder::project`](crate::UbiBuilder::project).
+    #[must_use]
+    pub fn project_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+}
+
+/// Parses a project reference like `owner/repo`, `https://github.com/owner/repo`, or the URL of a
+/// release or release asset, like `https://github.com/owner/repo/releases/tag/v1.2.3`. This only
+/// looks at the string, so it never makes a network request.
+///
+/// A project or project URL can be followed by `@<tag>`, like `owner/repo@v1.2.3` or
+/// `https://github.com/owner/repo@v1.2.3`, to pick a release by its tag.
+///
+/// A bare `owner/repo` is a GitHub project, unless the owner starts with `~`, in which case it is a
+/// SourceHut project. For a URL, the forge is determined from the host, which must be one of
+/// `bitbucket.org`, `codeberg.org`, `dev.azure.com`, `gitea.com`, `github.com`, `gitlab.com`,
+/// `ghcr.io`, or `git.sr.ht`. For a
+/// project on a self-hosted forge, pass its URL to [`UbiBuilder::project`](crate::UbiBuilder::project)
+/// along with [`UbiBuilder::forge`](crate::UbiBuilder::forge) instead.
+///
+/// # Errors
+///
+/// This returns an error if the input is empty, if it is not a valid URL or `owner/repo` pair, if
+/// the owner or repo is missing, if the URL's host is not a known forge host, or if the tag after an
+/// `@` is empty or the URL also has a tag in its path.
+pub fn parse_project(input: &str) -> Result<ProjectRef> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(anyhow!("the project reference is empty"));
+    }
+
+    if input.starts_with("http://") || input.starts_with("https://") {
+        return parse_project_url(input);
+    }
+
+    let (project, tag) = split_tag(input, input)?;
+    let Some((owner, repo)) = owner_and_repo_from_path(project) else {
+        return Err(malformed_project_error(input));
+    };
+    if project.split('/').count() != 2 {
+        return Err(anyhow!(
+            "could not parse `{input}` as a project: expected `owner/repo` or a URL"
+        ));
+    }
+
+    let forge = if owner.starts_with('~') {
+        ForgeType::SourceHut
+    } else {
+        ForgeType::GitHub
+    };
+    Ok(ProjectRef {
+        forge,
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        tag: tag.map(String::from),
+        host: None,
+    })
+}
+
+fn parse_project_url(input: &str) -> Result<ProjectRef> {
+    let url =
+        Url::parse(input).with_context(|| format!("could not parse `{input}` as a project URL"))?;
+    let Some(host) = url.host_str() else {
+        return Err(anyhow!(
+            "could not parse `{input}` as a project: the URL has no host"
+        ));
+    };
+    let Some(forge) = ForgeType::from_known_host(host) else {
+        return Err(anyhow!(
+            "could not parse `{input}` as a project: `{host}` is not a known forge host"
+        ));
+    };
+    // An OCI reference can include a digest after an `@`, so we don't look for a tag in it.
+    let (url, at_tag) = if forge == ForgeType::Oci {
+        (url.clone(), None)
+    } else {
+        split_url_tag(input, &url)?
+    };
+    if forge == ForgeType::AzureDevOps {
+        let Some((org, project, repo)) = azure_devops_project_from_path(url.path()) else {
+            return Err(anyhow!(
+                "could not parse `{input}` as a project: it must include both an organization and a project name"
+            ));
+        };
+        return Ok(ProjectRef {
+            forge,
+            owner: format!("{org}/{project}"),
+            repo: repo.to_string(),
+            tag: at_tag,
+            host: Some(host_with_port(&url)),
+        });
+    }
+    let Some((owner, repo)) = owner_and_repo_from_path(url.path()) else {
+        return Err(malformed_project_error(input));
+    };
+
+    let tag = combine_url_tags(input, at_tag, tag_from_url(&forge, &url))?;
+    Ok(ProjectRef {
+        tag,
+        forge,
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        host: Some(host_with_port(&url)),
+    })
+}
+
+pub(crate) fn host_with_port(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    }
+}
+
+// Owner and repo names can't contain an `@`, so everything after the first one is the tag. `input`
+// is only used in the error.
+pub(crate) fn split_tag<'a>(input: &str, project: &'a str) -> Result<(&'a str, Option<&'a str>)> {
+    match project.split_once('@') {
+        None => Ok((project, None)),
+        Some((_, "")) => Err(anyhow!(
+            "could not parse `{input}` as a project: the tag after the `@` is empty"
+        )),
+        Some((project, tag)) => Ok((project, Some(tag))),
+    }
+}
+
+// For a URL, we only look for the tag in the path, since an `@` before the host separates the user
+// info. The returned URL has the tag removed from its path.
+pub(crate) fn split_url_tag(input: &str, url: &Url) -> Result<(Url, Option<String>)> {
+    let (path, tag) = split_tag(input, url.path())?;
+    let Some(tag) = tag else {
+        return Ok((url.clone(), None));
+    };
+    let mut without_tag = url.clone();
+    without_tag.set_path(path);
+    Ok((without_tag, Some(tag.to_string())))
+}
+
+fn malformed_project_error(input: &str) -> anyhow::Error {
+    anyhow!("could not parse `{input}` as a project: it must include both an owner and a repo name")
+}
+
+// Returns the first two segments of the path, which are the owner and repo for every forge except
+// an OCI registry. A leading `/` is ignored.
+pub(crate) fn owner_and_repo_from_path(path: &str) -> Option<(&str, &str)> {
+    let mut parts = path.strip_prefix('/').unwrap_or(path).split('/');
+    let owner = parts.next().filter(|p| !p.is_empty())?;
+    let repo = parts.next().filter(|p| !p.is_empty())?;
+    Some((owner, repo))
+}
+
+// Every forge except an OCI registry needs a project name with exactly one `/`. The builder checks
+// this before making a forge so that building the forge's URLs can't fail.
+pub(crate) fn split_project_name(name: &str) -> Result<(&str, &str)> {
+    if name.is_empty() {
+        return Err(anyhow!(
+            "the project name is empty, but it must be in the form `owner/repo`"
+        ));
+    }
+    match name.split_once('/') {
+        Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+            Ok((owner, repo))
+        }
+        _ => Err(anyhow!(
+            "`{name}` is not a valid project name, it must be in the form `owner/repo`"
+        )),
+    }
+}
+
+// An Azure DevOps repo URL looks like `<org>/<project>/_git/<repo>`. Any other page in the project
+// doesn't name a repo, so we use the project's default repo, which has the same name as the project.
+pub(crate) fn azure_devops_project_from_path(path: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = path.strip_prefix('/').unwrap_or(path).split('/');
+    let org = parts.next().filter(|p| !p.is_empty())?;
+    let project = parts.next().filter(|p| !p.is_empty())?;
+    let repo = match (parts.next(), parts.next()) {
+        (Some("_git"), repo) => repo.filter(|r| !r.is_empty())?,
+        _ => project,
+    };
+    Some((org, project, repo))
+}
+
+// An Azure DevOps repo belongs to a project in an organization, so its project name must have
+// exactly two `/`s.
+pub(crate) fn split_azure_devops_project_name(name: &str) -> Result<(&str, &str, &str)> {
+    if name.is_empty() {
+        return Err(anyhow!(
+            "the project name is empty, but it must be in the form `org/project/repo`"
+        ));
+    }
+    match name.split('/').collect::<Vec<_>>()[..] {
+        [org, project, repo] if !org.is_empty() && !project.is_empty() && !repo.is_empty() => {
+            Ok((org, project, repo))
+        }
+        _ => Err(anyhow!(
+            "`{name}` is not a valid project name, it must be in the form `org/project/repo`"
+        )),
+    }
+}
+
+// Returns the tag from the URL of a release or release asset, like
+// `https://github.com/owner/repo/releases/tag/v1.2.3`.
+pub(crate) fn tag_from_url<'a>(forge: &ForgeType, url: &'a Url) -> Option<&'a str> {
+    let rest = url.path_segments()?.skip(2).collect::<Vec<_>>();
+    tag_from_path(forge, &rest)
+}
+
+// A project URL can have a tag after an `@` or a tag in its path, but not both. `input` is only used
+// in the error.
+pub(crate) fn combine_url_tags(
+    input: &str,
+    at_tag: Option<String>,
+    path_tag: Option<&str>,
+) -> Result<Option<String>> {
+    if at_tag.is_some() && path_tag.is_some() {
+        return Err(anyhow!(
+            "could not parse `{input}` as a project: it has a tag after an `@` and a tag in the URL path"
+        ));
+    }
+    Ok(at_tag.or(path_tag.map(String::from)))
+}
+
+// Returns true if the URL is for a release asset, like
+// `https://github.com/owner/repo/releases/download/v1.2.3/asset.tar.gz`, rather than for a project
+// or release page. An asset URL can be downloaded directly, without asking the forge for the
+// release's assets.
+pub(crate) fn is_release_asset_url(forge: &ForgeType, url: &Url) -> bool {
+    let Some(rest) = url.path_segments().map(|s| s.skip(2).collect::<Vec<_>>()) else {
+        return false;
+    };
+    matches!(
+        (forge, &rest[..]),
+        (ForgeType::GitHub | ForgeType::Gitea, ["releases", "download", tag, asset, ..])
+        // GitLab's permanent links to release assets look like `-/releases/<tag>/downloads/<path>`.
+        | (ForgeType::GitLab, ["-", "releases", tag, "downloads", asset, ..])
+        | (ForgeType::SourceHut, ["refs", "download", tag, asset, ..])
+            if !tag.is_empty() && !asset.is_empty()
+    )
+}
+
+// These are the path segments after the owner and repo in the URL of a release or release asset.
+fn tag_from_path<'a>(forge: &ForgeType, rest: &[&'a str]) -> Option<&'a str> {
+    let tag = match (forge, rest) {
+        // `releases/tag/<tag>` is a release page, and `releases/download/<tag>/<asset>` is an
+        // asset. Gitea and Forgejo use the same URLs as GitHub.
+        (ForgeType::GitHub | ForgeType::Gitea, ["releases", "tag" | "download", tag, ..])
+        // `-/releases/<tag>` and `-/tags/<tag>`.
+        | (ForgeType::GitLab, ["-", "releases" | "tags", tag, ..])
+        // `refs/download/<tag>/<asset>` is an asset, and `refs/<tag>` is a release page.
+        | (ForgeType::SourceHut, ["refs", "download", tag, ..] | ["refs", tag, ..]) => *tag,
+        _ => return None,
+    };
+    (!tag.is_empty()).then_some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(
+        "houseabsolute/precious",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "bare owner and repo"
+    )]
+    #[test_case(
+        "  houseabsolute/precious\n",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "bare owner and repo with whitespace"
+    )]
+    #[test_case(
+        "~some-owner/some-repo",
+        ForgeType::SourceHut, "~some-owner", "some-repo", None ;
+        "bare SourceHut owner and repo"
+    )]
+    #[test_case(
+        "houseabsolute/ubi@v0.2.0",
+        ForgeType::GitHub, "houseabsolute", "ubi", Some("v0.2.0") ;
+        "bare owner and repo with tag"
+    )]
+    #[test_case(
+        "houseabsolute/ubi@release/1.0",
+        ForgeType::GitHub, "houseabsolute", "ubi", Some("release/1.0") ;
+        "bare owner and repo with tag containing a slash"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "GitHub URL"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious/",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "GitHub URL with trailing slash"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious/actions/runs/4275745616",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "GitHub URL for another page"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious/releases/tag/v0.7.0",
+        ForgeType::GitHub, "houseabsolute", "precious", Some("v0.7.0") ;
+        "GitHub release URL"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious/releases/download/v0.1.7/precious-Linux-x86_64-musl.tar.gz",
+        ForgeType::GitHub, "houseabsolute", "precious", Some("v0.1.7") ;
+        "GitHub release asset URL"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/precious/releases",
+        ForgeType::GitHub, "houseabsolute", "precious", None ;
+        "GitHub releases URL"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/ubi@v1",
+        ForgeType::GitHub, "houseabsolute", "ubi", Some("v1") ;
+        "GitHub URL with tag"
+    )]
+    #[test_case(
+        "https://user@github.com/houseabsolute/ubi",
+        ForgeType::GitHub, "houseabsolute", "ubi", None ;
+        "GitHub URL with user info"
+    )]
+    #[test_case(
+        "https://gitlab.com/gitlab-org/cli",
+        ForgeType::GitLab, "gitlab-org", "cli", None ;
+        "GitLab URL"
+    )]
+    #[test_case(
+        "https://gitlab.com/gitlab-org/cli/-/releases/v1.36.0",
+        ForgeType::GitLab, "gitlab-org", "cli", Some("v1.36.0") ;
+        "GitLab release URL"
+    )]
+    #[test_case(
+        "https://gitlab.com/gitlab-org/cli/-/tags/v1.36.0",
+        ForgeType::GitLab, "gitlab-org", "cli", Some("v1.36.0") ;
+        "GitLab tag URL"
+    )]
+    #[test_case(
+        "https://gitea.com/some-owner/some-repo",
+        ForgeType::Gitea, "some-owner", "some-repo", None ;
+        "Gitea URL"
+    )]
+    #[test_case(
+        "https://gitea.com/some-owner/some-repo/releases/tag/v1.0.0",
+        ForgeType::Gitea, "some-owner", "some-repo", Some("v1.0.0") ;
+        "Gitea or Forgejo release URL"
+    )]
+    #[test_case(
+        "https://gitea.com/some-owner/some-repo/releases/download/v1.0.0/some-repo-linux-amd64.tar.gz",
+        ForgeType::Gitea, "some-owner", "some-repo", Some("v1.0.0") ;
+        "Gitea or Forgejo release asset URL"
+    )]
+    #[test_case(
+        "https://codeberg.org/some-owner/some-repo/releases/tag/v1.0.0",
+        ForgeType::Gitea, "some-owner", "some-repo", Some("v1.0.0") ;
+        "Codeberg release URL"
+    )]
+    #[test_case(
+        "https://git.sr.ht/~some-owner/some-repo/refs/v1.0.0",
+        ForgeType::SourceHut, "~some-owner", "some-repo", Some("v1.0.0") ;
+        "SourceHut refs URL"
+    )]
+    #[test_case(
+        "https://bitbucket.org/some-owner/some-repo/downloads/",
+        ForgeType::Bitbucket, "some-owner", "some-repo", None ;
+        "Bitbucket downloads URL"
+    )]
+    #[test_case(
+        "https://dev.azure.com/some-org/some-project/_git/some-repo",
+        ForgeType::AzureDevOps, "some-org/some-project", "some-repo", None ;
+        "Azure DevOps repo URL"
+    )]
+    #[test_case(
+        "https://dev.azure.com/some-org/some-project/_build?definitionId=1",
+        ForgeType::AzureDevOps, "some-org/some-project", "some-project", None ;
+        "Azure DevOps project page URL"
+    )]
+    fn parse_project(
+        input: &str,
+        forge: ForgeType,
+        owner: &str,
+        repo: &str,
+        tag: Option<&str>,
+    ) -> Result<()> {
+        assert_eq!(
+            super::parse_project(input)?,
+            ProjectRef {
+                forge,
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                tag: tag.map(String::from),
+                // A bare `owner/repo` can't be parsed as a URL, so this is only set for a URL.
+                host: Url::parse(input.trim()).ok().map(|u| host_with_port(&u)),
+            },
+        );
+        Ok(())
+    }
+
+    #[test_case("", "the project reference is empty" ; "empty")]
+    #[test_case("   ", "the project reference is empty" ; "only whitespace")]
+    #[test_case(
+        "justrepo",
+        "could not parse `justrepo` as a project: it must include both an owner and a repo name" ;
+        "no slash"
+    )]
+    #[test_case(
+        "owner/",
+        "could not parse `owner/` as a project: it must include both an owner and a repo name" ;
+        "missing repo"
+    )]
+    #[test_case(
+        "/repo",
+        "could not parse `/repo` as a project: it must include both an owner and a repo name" ;
+        "missing owner"
+    )]
+    #[test_case(
+        "a/b/c",
+        "could not parse `a/b/c` as a project: expected `owner/repo` or a URL" ;
+        "too many segments"
+    )]
+    #[test_case(
+        "github.com/houseabsolute/precious",
+        "could not parse `github.com/houseabsolute/precious` as a project: expected `owner/repo` or a URL" ;
+        "URL without a scheme"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute",
+        "could not parse `https://github.com/houseabsolute` as a project: it must include both an owner and a repo name" ;
+        "URL missing repo"
+    )]
+    #[test_case(
+        "https://github.com/",
+        "could not parse `https://github.com/` as a project: it must include both an owner and a repo name" ;
+        "URL with no path"
+    )]
+    #[test_case(
+        "https://example.com/owner/repo",
+        "could not parse `https://example.com/owner/repo` as a project: `example.com` is not a known forge host" ;
+        "unknown host"
+    )]
+    #[test_case(
+        "https://dev.azure.com/some-org",
+        "could not parse `https://dev.azure.com/some-org` as a project: it must include both an organization and a project name" ;
+        "Azure DevOps URL missing project"
+    )]
+    #[test_case(
+        "https://",
+        "could not parse `https://` as a project URL" ;
+        "invalid URL"
+    )]
+    #[test_case(
+        "houseabsolute/ubi@",
+        "could not parse `houseabsolute/ubi@` as a project: the tag after the `@` is empty" ;
+        "empty tag"
+    )]
+    #[test_case(
+        "@v1",
+        "could not parse `@v1` as a project: it must include both an owner and a repo name" ;
+        "only a tag"
+    )]
+    #[test_case(
+        "https://github.com/houseabsolute/ubi/releases/tag/v1@v2",
+        "could not parse `https://github.com/houseabsolute/ubi/releases/tag/v1@v2` as a project: it has a tag after an `@` and a tag in the URL path" ;
+        "URL with a tag after an @ and in its path"
+    )]
+    fn parse_project_errors(input: &str, expect: &str) {
+        let err = super::parse_project(input).unwrap_err();
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[test_case("some-org/some-project/some-repo", Ok(("some-org", "some-project", "some-repo")) ; "valid")]
+    #[test_case("some-org/some-repo", Err("`some-org/some-repo` is not a valid project name, it must be in the form `org/project/repo`") ; "too few slashes")]
+    #[test_case("a/b/c/d", Err("`a/b/c/d` is not a valid project name, it must be in the form `org/project/repo`") ; "too many slashes")]
+    #[test_case("a//c", Err("`a//c` is not a valid project name, it must be in the form `org/project/repo`") ; "empty project")]
+    #[test_case("", Err("the project name is empty, but it must be in the form `org/project/repo`") ; "empty")]
+    fn split_azure_devops_project_name(name: &str, expect: Result<(&str, &str, &str), &str>) {
+        assert_eq!(
+            super::split_azure_devops_project_name(name).map_err(|e| e.to_string()),
+            expect.map_err(String::from),
+        );
+    }
+
+    #[test_case("https://github.com/houseabsolute/precious/releases/download/v0.1.7/precious-Linux-x86_64-musl.tar.gz", ForgeType::GitHub, true ; "GitHub asset")]
+    #[test_case("https://codeberg.org/some-owner/some-repo/releases/download/v1.0.0/some-repo.tar.gz", ForgeType::Gitea, true ; "Gitea asset")]
+    #[test_case("https://gitlab.com/gitlab-org/cli/-/releases/v1.36.0/downloads/glab.tar.gz", ForgeType::GitLab, true ; "GitLab asset")]
+    #[test_case("https://git.sr.ht/~some-owner/some-repo/refs/download/v1.0.0/some-repo.tar.gz", ForgeType::SourceHut, true ; "SourceHut asset")]
+    #[test_case("https://github.com/houseabsolute/precious/releases/tag/v0.1.7", ForgeType::GitHub, false ; "GitHub release page")]
+    #[test_case("https://github.com/houseabsolute/precious/releases/download/v0.1.7/", ForgeType::GitHub, false ; "GitHub download URL without an asset")]
+    #[test_case("https://github.com/houseabsolute/precious", ForgeType::GitHub, false ; "GitHub project")]
+    #[test_case("https://gitlab.com/gitlab-org/cli/-/releases/v1.36.0", ForgeType::GitLab, false ; "GitLab release page")]
+    fn is_release_asset_url(url: &str, forge: ForgeType, expect: bool) -> Result<()> {
+        assert_eq!(
+            super::is_release_asset_url(&forge, &Url::parse(url)?),
+            expect
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn project_name() -> Result<()> {
+        let project = super::parse_project("https://git.sr.ht/~some-owner/some-repo")?;
+        assert_eq!(project.project_name(), "~some-owner/some-repo");
+
+        let project =
+            super::parse_project("https://dev.azure.com/some-org/some-project/_git/some-repo")?;
+        assert_eq!(project.project_name(), "some-org/some-project/some-repo");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use log::debug;
+use std::{num::NonZeroU64, time::Duration};
+use tokio::time::{sleep, Instant};
+
+// This is a token bucket that holds up to one second's worth of bytes. It starts out full, so a
+// download that is slower than the limit never waits. When a chunk takes more tokens than are in
+// the bucket, the bucket goes into debt, and we sleep until that debt would be paid off at the
+// limit rate.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    bytes_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(bytes_per_second: NonZeroU64) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let bytes_per_second = bytes_per_second.get() as f64;
+        Self {
+            bytes_per_second,
+            tokens: bytes_per_second,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub(crate) async fn consume(&mut self, bytes: usize) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * self.bytes_per_second).min(self.bytes_per_second);
+
+        #[allow(clippy::cast_precision_loss)]
+        let bytes = bytes as f64;
+        self.tokens -= bytes;
+        if self.tokens >= 0.0 {
+            return;
+        }
+
+        let wait = Duration::from_secs_f64(-self.tokens / self.bytes_per_second);
+        debug!("waiting {wait:?} to stay under the download rate limit");
+        sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn consume_under_the_limit_does_not_wait() {
+        let mut limiter = RateLimiter::new(NonZeroU64::new(1_000_000).unwrap());
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.consume(10_000).await;
+        }
+        assert!(
+            start.elapsed() < Duration::from_millis(100),
+            "took {:?}",
+            start.elapsed(),
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn consume_over_the_limit_waits() {
+        let mut limiter = RateLimiter::new(NonZeroU64::new(1_000).unwrap());
+        let start = Instant::now();
+        // The first 1,000 bytes are already in the bucket, so the next 500 take half a second.
+        limiter.consume(1_000).await;
+        limiter.consume(500).await;
+        assert!(
+            start.elapsed() >= Duration::from_millis(500),
+            "took {:?}",
+            start.elapsed(),
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{forge::ReleaseAssets, ubi::Asset};
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, create_dir_all},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+pub(crate) const DEFAULT_RELEASE_INFO_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+// The release info cache stores the assets for a release, so that installing the same release again
+// within the TTL doesn't make any requests to the forge. Each release is stored in its own file,
+// named for the URL of the forge's release info, which includes the project and the tag, or
+// `latest` if there's no tag.
+#[derive(Debug)]
+pub(crate) struct ReleaseInfoCache {
+    dir: PathBuf,
+    ttl: Duration,
+    // If this is set, we never read from the cache, but we still write the release info we fetch.
+    refresh: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedRelease {
+    url: Url,
+    // This is in seconds since the epoch.
+    fetched_at: u64,
+    tag: Option<String>,
+    assets: Vec<Asset>,
+}
+
+impl ReleaseInfoCache {
+    pub(crate) fn new(dir: PathBuf, ttl: Duration, refresh: bool) -> Self {
+        Self { dir, ttl, refresh }
+    }
+
+    pub(crate) fn default_dir() -> Result<PathBuf> {
+        let Some(cache_dir) = dirs::cache_dir() else {
+            return Err(anyhow!(
+                "could not find the cache directory for this platform, so you must set a release info cache directory"
+            ));
+        };
+        Ok(cache_dir.join("ubi").join("releases"))
+    }
+
+    pub(crate) fn get(&self, url: &Url) -> Result<Option<ReleaseAssets>> {
+        if self.refresh {
+            debug!("not reading the release info cache for {url} because it is being refreshed");
+            return Ok(None);
+        }
+        self.get_at(url, SystemTime::now())
+    }
+
+    fn get_at(&self, url: &Url, now: SystemTime) -> Result<Option<ReleaseAssets>> {
+        let path = self.path_for(url);
+        if !path.exists() {
+            debug!("did not find release info for {url} in {}", path.display());
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "could not read the release info cache from {}",
+                path.display()
+            )
+        })?;
+        // A cache file we can't parse is not worth failing over. We'll just overwrite it.
+        let cached = match serde_json::from_str::<CachedRelease>(&contents) {
+            Ok(c) if c.url == *url => c,
+            Ok(c) => {
+                debug!(
+                    "the release info cache in {} is for {}, not {url}",
+                    path.display(),
+                    c.url,
+                );
+                return Ok(None);
+            }
+            Err(e) => {
+                debug!(
+                    "could not parse the release info cache in {}: {e}",
+                    path.display()
+                );
+                return Ok(None);
+            }
+        };
+
+        // If the file was written in the future, the clock changed, so we can't tell how old it is.
+        let Ok(age) = now.duration_since(UNIX_EPOCH + Duration::from_secs(cached.fetched_at))
+        else {
+            debug!("the release info for {url} was cached in the future, so it is ignored");
+            return Ok(None);
+        };
+        if age >= self.ttl {
+            debug!(
+                "the release info for {url} was cached {}s ago, which is longer than the TTL of {}s",
+                age.as_secs(),
+                self.ttl.as_secs(),
+            );
+            return Ok(None);
+        }
+
+        debug!(
+            "using release info for {url} that was cached {}s ago in {}",
+            age.as_secs(),
+            path.display(),
+        );
+        Ok(Some(ReleaseAssets {
+            tag: cached.tag,
+            assets: cached.assets,
+        }))
+    }
+
+    pub(crate) fn store(&self, url: &Url, release: &ReleaseAssets) -> Result<()> {
+        self.store_at(url, release, SystemTime::now())
+    }
+
+    fn store_at(&self, url: &Url, release: &ReleaseAssets, now: SystemTime) -> Result<()> {
+        create_dir_all(&self.dir)
+            .with_context(|| format!("could not create a directory at {}", self.dir.display()))?;
+
+        let cached = CachedRelease {
+            url: url.clone(),
+            fetched_at: now.duration_since(UNIX_EPOCH)?.as_secs(),
+            tag: release.tag.clone(),
+            assets: release.assets.clone(),
+        };
+        let path = self.path_for(url);
+        debug!("writing release info for {url} to {}", path.display());
+        fs::write(&path, serde_json::to_string_pretty(&cached)?).with_context(|| {
+            format!(
+                "could not write the release info cache to {}",
+                path.display()
+            )
+        })
+    }
+
+    // A URL can contain characters that aren't allowed in file names, so we use its hash instead.
+    fn path_for(&self, url: &Url) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(url.as_str()));
+        self.dir.join(format!("{hash}.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use test_case::test_case;
+
+    fn release() -> ReleaseAssets {
+        ReleaseAssets {
+            tag: Some("v1.0.0".to_string()),
+            assets: vec![Asset {
+                name: "project-Linux-x86_64.tar.gz".to_string(),
+                url: Url::parse("https://example.com/project-Linux-x86_64.tar.gz").unwrap(),
+                size: None,
+            }],
+        }
+    }
+
+    #[test_case(0, true ; "just cached")]
+    #[test_case(299, true ; "within the TTL")]
+    #[test_case(300, false ; "at the TTL")]
+    #[test_case(3_600, false ; "after the TTL")]
+    fn get(age_secs: u64, expect_hit: bool) -> Result<()> {
+        let td = tempdir()?;
+        let cache = ReleaseInfoCache::new(td.path().to_path_buf(), Duration::from_secs(300), false);
+        let url = Url::parse("https://api.github.com/repos/owner/project/releases/latest")?;
+        let fetched_at = UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+        cache.store_at(&url, &release(), fetched_at)?;
+
+        let got = cache.get_at(&url, fetched_at + Duration::from_secs(age_secs))?;
+        assert_eq!(got, expect_hit.then(release));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_other_release() -> Result<()> {
+        let td = tempdir()?;
+        let cache = ReleaseInfoCache::new(
+            td.path().to_path_buf(),
+            DEFAULT_RELEASE_INFO_CACHE_TTL,
+            false,
+        );
+        cache.store(
+            &Url::parse("https://api.github.com/repos/owner/project/releases/latest")?,
+            &release(),
+        )?;
+
+        let other = Url::parse("https://api.github.com/repos/owner/project/releases/tags/v0.9.0")?;
+        assert_eq!(cache.get(&other)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_with_refresh() -> Result<()> {
+        let td = tempdir()?;
+        let url = Url::parse("https://api.github.com/repos/owner/project/releases/latest")?;
+        ReleaseInfoCache::new(
+            td.path().to_path_buf(),
+            DEFAULT_RELEASE_INFO_CACHE_TTL,
+            false,
+        )
+        .store(&url, &release())?;
+
+        let cache = ReleaseInfoCache::new(
+            td.path().to_path_buf(),
+            DEFAULT_RELEASE_INFO_CACHE_TTL,
+            true,
+        );
+        assert_eq!(cache.get(&url)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_with_unparseable_file() -> Result<()> {
+        let td = tempdir()?;
+        let cache = ReleaseInfoCache::new(
+            td.path().to_path_buf(),
+            DEFAULT_RELEASE_INFO_CACHE_TTL,
+            false,
+        );
+        let url = Url::parse("https://api.github.com/repos/owner/project/releases/latest")?;
+        fs::write(cache.path_for(&url), "not json")?;
+        assert_eq!(cache.get(&url)?, None);
+
+        cache.store(&url, &release())?;
+        assert_eq!(cache.get(&url)?, Some(release()));
+
+        Ok(())
+    }
+}
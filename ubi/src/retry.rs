@@ -0,0 +1,597 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use reqwest::{
+    header::{HeaderMap, HeaderName, AUTHORIZATION, LOCATION, RETRY_AFTER},
+    redirect, Client, Method, Request, RequestBuilder, Response, StatusCode,
+};
+use std::time::Duration;
+use tokio::time::error::Elapsed;
+use url::{Origin, Url};
+
+pub(crate) const DEFAULT_MAX_RETRIES: u32 = 3;
+pub(crate) const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// This is the same limit as reqwest's default redirect policy.
+const MAX_REDIRECTS: usize = 10;
+
+// These are the headers that a forge token or other credentials can be sent in. None of them are
+// sent to a different host when following a redirect.
+const CREDENTIAL_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "job-token",
+    "private-token",
+    "proxy-authorization",
+];
+
+// This controls how we retry requests that fail in ways that are likely to be transient, like a
+// `503 Service Unavailable` response or a connection error. Since every request goes through this,
+// it also applies the request timeout, the HTTP Basic auth credentials, and the extra headers, and
+// it follows redirects to other hosts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    request_timeout: Option<Duration>,
+    basic_auth: Option<(String, String)>,
+    // This is the origin of the forge's API, which is the only place we send the HTTP Basic auth
+    // credentials.
+    api_origin: Option<Origin>,
+    extra_headers: HeaderMap,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+            request_timeout: None,
+            basic_auth: None,
+            api_origin: None,
+            extra_headers: HeaderMap::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn new(
+        max_retries: u32,
+        base_delay: Duration,
+        request_timeout: Option<Duration>,
+        basic_auth: Option<(String, String)>,
+    ) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            request_timeout,
+            basic_auth,
+            api_origin: None,
+            extra_headers: HeaderMap::new(),
+        }
+    }
+
+    // These are the headers from `UbiBuilder::header`. A header that's set on the request itself,
+    // like the forge token's header, takes priority over one of these with the same name.
+    #[must_use]
+    pub(crate) fn with_extra_headers(mut self, extra_headers: HeaderMap) -> Self {
+        self.extra_headers = extra_headers;
+        self
+    }
+
+    // The forge calls this once it knows its API's URL. Until then, the HTTP Basic auth
+    // credentials are not sent anywhere.
+    #[must_use]
+    pub(crate) fn for_api_base_url(mut self, api_base_url: &Url) -> Self {
+        self.api_origin = Some(api_base_url.origin());
+        self
+    }
+
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    // The client's redirect policy stops at a redirect to a different host, so we follow that here,
+    // without the forge token or the extra headers. reqwest would only remove a few of the headers
+    // that a token can be sent in, like `Authorization`, and none of the extra headers.
+    pub(crate) async fn execute(&self, client: &Client, req: Request) -> Result<Response> {
+        let mut req = self.add_basic_auth(client, req)?;
+        self.add_extra_headers(&mut req);
+
+        let mut resp = self.execute_with_retries(client, &req).await?;
+        let mut redirects = 0;
+        while let Some(next) = self.cross_host_redirect(&req, &resp)? {
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(anyhow!(
+                    "too many redirects when making a request to {}",
+                    req.url()
+                ));
+            }
+            resp = self.execute_with_retries(client, &next).await?;
+            req = next;
+        }
+        Ok(resp)
+    }
+
+    // If all of the retries fail with a retryable status, this returns the last response, so the
+    // caller can report the status however it normally would.
+    async fn execute_with_retries(&self, client: &Client, req: &Request) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let this_req = req
+                .try_clone()
+                .expect("we only make requests without a streaming body, which can be cloned");
+            let res = self.send(client, this_req).await;
+            if attempt >= self.max_retries {
+                return res;
+            }
+
+            let delay = match &res {
+                Ok(resp) if is_retryable_status(resp.status()) => {
+                    debug!(
+                        "request to {} failed with {}, will retry",
+                        req.url(),
+                        resp.status()
+                    );
+                    retry_after(resp).unwrap_or_else(|| self.backoff(attempt))
+                }
+                Err(e) if is_retryable_error(e) => {
+                    debug!("request to {} failed: {e:#}, will retry", req.url());
+                    self.backoff(attempt)
+                }
+                _ => return res,
+            };
+
+            attempt += 1;
+            debug!(
+                "waiting {delay:?} before retry {attempt} of {}",
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // The credentials are for a gateway in front of the forge's API, so they're only sent to the
+    // API's origin, and not to a download host like a CDN. `UbiBuilder::build` doesn't allow them
+    // with a forge token that's sent in the `Authorization` header, but if a request has one anyway,
+    // like the anonymous token from an OCI registry's token service, we keep it.
+    fn add_basic_auth(&self, client: &Client, req: Request) -> Result<Request> {
+        let (Some((username, password)), Some(api_origin)) = (&self.basic_auth, &self.api_origin)
+        else {
+            return Ok(req);
+        };
+        if req.url().origin() != *api_origin {
+            debug!(
+                "not sending HTTP Basic auth to {}, which is not the forge's API host",
+                req.url()
+            );
+            return Ok(req);
+        }
+        if req.headers().contains_key(AUTHORIZATION) {
+            debug!("not sending HTTP Basic auth, since the request already has an Authorization header");
+            return Ok(req);
+        }
+
+        debug!("adding HTTP Basic auth for {username} to the request");
+        Ok(RequestBuilder::from_parts(client.clone(), req)
+            .basic_auth(username, Some(password))
+            .build()?)
+    }
+
+    fn add_extra_headers(&self, req: &mut Request) {
+        for (name, value) in &self.extra_headers {
+            if !req.headers().contains_key(name) {
+                req.headers_mut().insert(name, value.clone());
+            }
+        }
+    }
+
+    // This returns the request to make for a redirect to a different host, if that's what the
+    // response is. The request keeps the original request's headers, like `Accept` and `Range`,
+    // except for any credentials and the extra headers.
+    fn cross_host_redirect(&self, req: &Request, resp: &Response) -> Result<Option<Request>> {
+        if !resp.status().is_redirection() {
+            return Ok(None);
+        }
+        let Some(location) = resp.headers().get(LOCATION) else {
+            return Ok(None);
+        };
+        let next_url = resp.url().join(location.to_str()?)?;
+        if !is_cross_host(resp.url(), &next_url) {
+            return Ok(None);
+        }
+
+        debug!(
+            "following the redirect from {} to {next_url} without any credentials or extra headers",
+            resp.url(),
+        );
+        let method = if resp.status() == StatusCode::SEE_OTHER
+            || (matches!(
+                resp.status(),
+                StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND
+            ) && req.method() == Method::POST)
+        {
+            Method::GET
+        } else {
+            req.method().clone()
+        };
+        let mut next = Request::new(method, next_url);
+        for (name, value) in req.headers() {
+            if !is_credential_header(name) && !self.extra_headers.contains_key(name) {
+                next.headers_mut().append(name, value.clone());
+            }
+        }
+        Ok(Some(next))
+    }
+
+    // We can't use reqwest's own timeout for this, because that also covers reading the response
+    // body, and downloading a big release asset over a slow connection can take a long time. This
+    // only limits how long we wait for the response to start.
+    async fn send(&self, client: &Client, req: Request) -> Result<Response> {
+        let Some(timeout) = self.request_timeout else {
+            return Ok(client.execute(req).await?);
+        };
+
+        let url = req.url().clone();
+        match tokio::time::timeout(timeout, client.execute(req)).await {
+            Ok(res) => Ok(res?),
+            Err(elapsed) => Err(anyhow::Error::new(elapsed).context(format!(
+                "timed out after {timeout:?} waiting for a response from {url}"
+            ))),
+        }
+    }
+
+    // This is exponential backoff with jitter. The delay doubles with each attempt, and then a
+    // random amount up to half of the delay is subtracted so that many clients retrying at once
+    // don't all hit the server at the same time.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt));
+        delay.mul_f64(1.0 - fastrand::f64() / 2.0)
+    }
+}
+
+// This follows redirects to the same host, like reqwest's default policy, but stops at a redirect to
+// a different host, which `RetryPolicy::execute` follows instead.
+pub(crate) fn redirect_policy() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        match attempt.previous().last() {
+            Some(prev) if is_cross_host(prev, attempt.url()) => attempt.stop(),
+            _ => attempt.follow(),
+        }
+    })
+}
+
+// reqwest uses the same check to decide when to remove the `Authorization` header.
+fn is_cross_host(from: &Url, to: &Url) -> bool {
+    from.host_str() != to.host_str() || from.port_or_known_default() != to.port_or_known_default()
+}
+
+fn is_credential_header(name: &HeaderName) -> bool {
+    CREDENTIAL_HEADERS.contains(&name.as_str())
+}
+
+fn is_retryable_error(e: &anyhow::Error) -> bool {
+    if e.is::<Elapsed>() {
+        return true;
+    }
+    e.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// We only look at `Retry-After` for a `429 Too Many Requests` response, and we only support the
+// form of the header that gives a number of seconds, not the one that gives a date.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    if resp.status() != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let secs = resp
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    debug!("the server sent a Retry-After header of {secs} seconds");
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use reqwest::header::{HeaderValue, ACCEPT};
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case(0, 100 ; "first attempt")]
+    #[test_case(1, 200 ; "second attempt")]
+    #[test_case(3, 800 ; "fourth attempt")]
+    fn backoff(attempt: u32, max_millis: u64) {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), None, None);
+        for _ in 0..20 {
+            let delay = policy.backoff(attempt);
+            assert!(delay <= Duration::from_millis(max_millis), "{delay:?}");
+            assert!(delay >= Duration::from_millis(max_millis / 2), "{delay:?}");
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn retry_after_on_429() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m1 = server
+            .mock("GET", "/")
+            .with_status(StatusCode::TOO_MANY_REQUESTS.as_u16() as usize)
+            .with_header(RETRY_AFTER.as_str(), "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let m2 = server
+            .mock("GET", "/")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // The base delay is long enough that this test would time out if we didn't use the
+        // `Retry-After` value.
+        let policy = RetryPolicy::new(1, Duration::from_secs(600), None, None);
+        let client = Client::new();
+        let req = client.get(server.url()).build()?;
+        let resp =
+            tokio::time::timeout(Duration::from_secs(10), policy.execute(&client, req)).await??;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn does_not_retry_404() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/")
+            .with_status(StatusCode::NOT_FOUND.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = RetryPolicy::new(3, Duration::from_millis(1), None, None);
+        let client = Client::new();
+        let req = client.get(server.url()).build()?;
+        let resp = policy.execute(&client, req).await?;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn basic_auth_is_sent_to_api_host() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/download/project.tar.gz")
+            // This is "user:pass" encoded as base64.
+            .match_header(AUTHORIZATION.as_str(), "Basic dXNlcjpwYXNz")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = basic_auth_policy(&server.url())?;
+        let client = Client::new();
+        let req = client
+            .get(format!("{}/download/project.tar.gz", server.url()))
+            .build()?;
+        policy.execute(&client, req).await?;
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn basic_auth_is_not_sent_to_other_hosts() -> Result<()> {
+        let api_server = Server::new_async().await;
+        let mut cdn_server = Server::new_async().await;
+        let m = cdn_server
+            .mock("GET", "/")
+            .match_header(AUTHORIZATION.as_str(), Matcher::Missing)
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = basic_auth_policy(&api_server.url())?;
+        let client = Client::new();
+        let req = client.get(cdn_server.url()).build()?;
+        policy.execute(&client, req).await?;
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn basic_auth_does_not_replace_authorization_header() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/")
+            .match_header(AUTHORIZATION.as_str(), "Bearer registry-token")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = basic_auth_policy(&server.url())?;
+        let client = Client::new();
+        let req = client
+            .get(server.url())
+            .header(AUTHORIZATION, "Bearer registry-token")
+            .build()?;
+        policy.execute(&client, req).await?;
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    fn basic_auth_policy(api_base_url: &str) -> Result<RetryPolicy> {
+        Ok(RetryPolicy::new(
+            0,
+            Duration::from_millis(1),
+            None,
+            Some(("user".to_string(), "pass".to_string())),
+        )
+        .for_api_base_url(&Url::parse(api_base_url)?))
+    }
+
+    #[test(tokio::test)]
+    async fn cross_host_redirect() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mut other_server = Server::new_async().await;
+        let redirect_mock = server
+            .mock("GET", "/download")
+            .match_header("JOB-TOKEN", "job-token")
+            .match_header("X-Internal-Auth", "secret")
+            .with_status(StatusCode::FOUND.as_u16() as usize)
+            .with_header("location", &format!("{}/storage", other_server.url()))
+            .expect(1)
+            .create_async()
+            .await;
+        let storage_mock = other_server
+            .mock("GET", "/storage")
+            .match_header("JOB-TOKEN", Matcher::Missing)
+            .match_header("X-Internal-Auth", Matcher::Missing)
+            .match_header(ACCEPT.as_str(), "application/octet-stream")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .with_body("body")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut extra_headers = HeaderMap::new();
+        extra_headers.insert("X-Internal-Auth", HeaderValue::from_static("secret"));
+        let policy = RetryPolicy::new(0, Duration::from_millis(1), None, None)
+            .with_extra_headers(extra_headers);
+        let client = Client::builder().redirect(redirect_policy()).build()?;
+        let req = client
+            .get(format!("{}/download", server.url()))
+            .header("JOB-TOKEN", "job-token")
+            .header(ACCEPT, "application/octet-stream")
+            .build()?;
+        let resp = policy.execute(&client, req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await?, "body");
+
+        redirect_mock.assert_async().await;
+        storage_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn same_host_redirect_keeps_headers() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let redirect_mock = server
+            .mock("GET", "/download")
+            .with_status(StatusCode::FOUND.as_u16() as usize)
+            .with_header("location", "/storage")
+            .expect(1)
+            .create_async()
+            .await;
+        let storage_mock = server
+            .mock("GET", "/storage")
+            .match_header("JOB-TOKEN", "job-token")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = RetryPolicy::new(0, Duration::from_millis(1), None, None);
+        let client = Client::builder().redirect(redirect_policy()).build()?;
+        let req = client
+            .get(format!("{}/download", server.url()))
+            .header("JOB-TOKEN", "job-token")
+            .build()?;
+        let resp = policy.execute(&client, req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        redirect_mock.assert_async().await;
+        storage_mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn request_timeout() -> Result<()> {
+        let addr = crate::test_case::stalled_server()?;
+
+        let policy = RetryPolicy::new(
+            1,
+            Duration::from_millis(1),
+            Some(Duration::from_millis(100)),
+            None,
+        );
+        let client = Client::new();
+        let req = client.get(format!("http://{addr}/")).build()?;
+        let err = policy.execute(&client, req).await.unwrap_err();
+        assert!(err.is::<Elapsed>(), "{err:#}");
+        assert_eq!(
+            err.to_string(),
+            format!("timed out after 100ms waiting for a response from http://{addr}/"),
+        );
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn request_timeout_does_not_apply_to_body() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/")
+            .with_status(StatusCode::OK.as_u16() as usize)
+            .with_chunked_body(|w| {
+                w.write_all(b"slow ")?;
+                std::thread::sleep(Duration::from_millis(300));
+                w.write_all(b"body")
+            })
+            .expect(1)
+            .create_async()
+            .await;
+
+        let policy = RetryPolicy::new(
+            0,
+            Duration::from_millis(1),
+            Some(Duration::from_millis(100)),
+            None,
+        );
+        let client = Client::new();
+        let req = client.get(server.url()).build()?;
+        let resp = policy.execute(&client, req).await?;
+        assert_eq!(resp.text().await?, "slow body");
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+}
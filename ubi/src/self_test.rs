@@ -0,0 +1,220 @@
+use crate::{
+    builder::{default_install_dir, platform_is_musl},
+    forge::ForgeType,
+    picker::Libc,
+    UbiBuilder, VERSION,
+};
+use anyhow::Result;
+use platforms::{Platform, OS};
+use serde::Serialize;
+use std::{env, ffi::OsString, path::PathBuf};
+use strum::IntoEnumIterator;
+
+/// `SelfTestReport` describes how `ubi` sees the environment it's running in. It is returned by
+/// [`self_test`]. It serializes to a JSON object with a key for each field.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SelfTestReport {
+    /// The version of the `ubi` crate.
+    pub version: String,
+    /// The platform that `ubi` picks release assets for, as a target triple like
+    /// `x86_64-unknown-linux-gnu`.
+    pub target: String,
+    /// The OS that `ubi` picks release assets for, like `linux` or `macos`.
+    pub os: String,
+    /// The CPU architecture that `ubi` picks release assets for, like `x86_64` or `aarch64`.
+    pub arch: String,
+    /// The libc that `ubi` detected. This is only set on Linux.
+    pub libc: Option<String>,
+    /// The forge token env vars that are set, in the order that the forges are listed in
+    /// [`ForgeType`].
+    pub tokens: Vec<TokenEnvVar>,
+    /// The directory that `ubi` installs into when no install directory is given. This is `None`
+    /// if it could not be found, in which case `install_dir_error` says why.
+    pub install_dir: Option<PathBuf>,
+    /// The error from looking for the default install directory, if there was one.
+    pub install_dir_error: Option<String>,
+}
+
+/// `TokenEnvVar` describes a forge token that was found in an env var.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TokenEnvVar {
+    /// The name of the env var.
+    pub env_var: String,
+    /// The forges that read this env var, like `github`. More than one forge can use the same env
+    /// var.
+    pub forges: Vec<String>,
+    /// The token with all but its last four characters masked, like `****abcd`. Short tokens are
+    /// masked entirely.
+    pub masked_token: String,
+}
+
+/// Returns a report on how `ubi` sees the current environment, which is useful for debugging
+/// problems like picking the wrong release asset or not using a token. This does not make any
+/// network requests.
+///
+/// # Errors
+///
+/// This returns an error if `ubi` cannot find a platform matching the target it was built for.
+pub fn self_test() -> Result<SelfTestReport> {
+    let platform = UbiBuilder::new().determine_platform()?;
+    let is_musl = platform_is_musl(&platform);
+    Ok(self_test_report(&platform, is_musl, |name| {
+        env::var_os(name)
+    }))
+}
+
+fn self_test_report(
+    platform: &Platform,
+    is_musl: bool,
+    var: impl Fn(&str) -> Option<OsString>,
+) -> SelfTestReport {
+    let libc = (platform.target_os == OS::Linux).then(|| {
+        let libc = if is_musl { Libc::Musl } else { Libc::Gnu };
+        libc.as_ref().to_string()
+    });
+    let (install_dir, install_dir_error) =
+        match default_install_dir(platform.target_os == OS::Windows, &var) {
+            Ok(dir) => (Some(dir), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+    SelfTestReport {
+        version: VERSION.to_string(),
+        target: platform.target_triple.to_string(),
+        os: platform.target_os.as_str().to_string(),
+        arch: platform.target_arch.as_str().to_string(),
+        libc,
+        tokens: token_env_vars(&var),
+        install_dir,
+        install_dir_error,
+    }
+}
+
+fn token_env_vars(var: impl Fn(&str) -> Option<OsString>) -> Vec<TokenEnvVar> {
+    let mut tokens: Vec<TokenEnvVar> = vec![];
+    for forge in ForgeType::iter() {
+        for name in forge.env_var_names() {
+            if let Some(existing) = tokens.iter_mut().find(|t| t.env_var == *name) {
+                existing.forges.push(forge.as_ref().to_string());
+                continue;
+            }
+            let Some(token) = var(name).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            tokens.push(TokenEnvVar {
+                env_var: (*name).to_string(),
+                forges: vec![forge.as_ref().to_string()],
+                masked_token: mask_token(&token.to_string_lossy()),
+            });
+        }
+    }
+    tokens
+}
+
+// Showing the end of a long token is enough to tell which one is set without making it usable.
+fn mask_token(token: &str) -> String {
+    let chars = token.chars().collect::<Vec<_>>();
+    if chars.len() < 16 {
+        return "****".to_string();
+    }
+    format!(
+        "****{}",
+        chars[chars.len() - 4..].iter().collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, str::FromStr};
+    use test_case::test_case;
+
+    fn platform(target: &str) -> Platform {
+        Platform::find(target).unwrap().clone()
+    }
+
+    #[test]
+    fn self_test_report() {
+        let vars = HashMap::from([
+            ("HOME", "/home/autarch"),
+            ("GITHUB_TOKEN", "ghp_0123456789abcdef"),
+            ("GITLAB_TOKEN", "short"),
+            ("GITEA_TOKEN", ""),
+        ]);
+        let report =
+            super::self_test_report(&platform("x86_64-unknown-linux-musl"), true, |name| {
+                vars.get(name).map(OsString::from)
+            });
+        assert_eq!(
+            report,
+            SelfTestReport {
+                version: VERSION.to_string(),
+                target: "x86_64-unknown-linux-musl".to_string(),
+                os: "linux".to_string(),
+                arch: "x86_64".to_string(),
+                libc: Some("musl".to_string()),
+                tokens: vec![
+                    TokenEnvVar {
+                        env_var: "GITHUB_TOKEN".to_string(),
+                        forges: vec!["github".to_string(), "oci".to_string()],
+                        masked_token: "****cdef".to_string(),
+                    },
+                    TokenEnvVar {
+                        env_var: "GITLAB_TOKEN".to_string(),
+                        forges: vec!["gitlab".to_string()],
+                        masked_token: "****".to_string(),
+                    },
+                ],
+                install_dir: Some(PathBuf::from("/home/autarch/.local/bin")),
+                install_dir_error: None,
+            },
+        );
+    }
+
+    #[test]
+    fn self_test_report_on_windows_without_local_app_data() {
+        let report = super::self_test_report(&platform("x86_64-pc-windows-msvc"), false, |_| {
+            None::<OsString>
+        });
+        assert_eq!(report.os, "windows");
+        assert_eq!(report.libc, None);
+        assert!(report.tokens.is_empty());
+        assert_eq!(report.install_dir, None);
+        assert_eq!(
+            report.install_dir_error.as_deref(),
+            Some(
+                "could not find the default install directory because the LOCALAPPDATA env var is not set"
+            ),
+        );
+    }
+
+    #[test]
+    fn self_test_report_json() -> Result<()> {
+        let report = super::self_test_report(&platform("aarch64-apple-darwin"), false, |name| {
+            (name == "HOME").then(|| OsString::from("/Users/autarch"))
+        });
+        assert_eq!(
+            serde_json::to_value(&report)?,
+            serde_json::Value::from_str(&format!(
+                r#"{{
+                    "version": "{VERSION}",
+                    "target": "aarch64-apple-darwin",
+                    "os": "macos",
+                    "arch": "aarch64",
+                    "libc": null,
+                    "tokens": [],
+                    "install_dir": "/Users/autarch/.local/bin",
+                    "install_dir_error": null
+                }}"#
+            ))?,
+        );
+        Ok(())
+    }
+
+    #[test_case("", "****" ; "empty")]
+    #[test_case("0123456789abcde", "****" ; "short")]
+    #[test_case("0123456789abcdef", "****cdef" ; "long")]
+    fn mask_token(token: &str, expect: &str) {
+        assert_eq!(super::mask_token(token), expect);
+    }
+}
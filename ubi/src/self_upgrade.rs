@@ -0,0 +1,232 @@
+use crate::{installer, UbiBuilder, VERSION};
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const UBI_PROJECT: &str = "houseabsolute/ubi";
+const OLD_WINDOWS_EXE_NAME: &str = "ubi-old.exe";
+
+/// `SelfUpgrade` describes what [`self_upgrade`] did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfUpgrade {
+    /// The path of the executable that was replaced.
+    pub path: PathBuf,
+    /// The version of `ubi` before the upgrade.
+    pub old_version: String,
+    /// The version of `ubi` after the upgrade, as reported by the new executable's `--version`
+    /// output.
+    pub new_version: String,
+    /// On Windows, the running executable cannot be deleted, so it is renamed to `ubi-old.exe`
+    /// instead. This is the path to that file, which must be deleted manually.
+    pub old_exe: Option<PathBuf>,
+}
+
+/// Upgrades the currently running `ubi` executable to the latest release of `ubi` for the current
+/// platform. This is meant to be called from the `ubi` executable itself.
+///
+/// The `builder` can be used to set options like a token or proxy. Its project and install
+/// directory are always overridden.
+///
+/// The new executable is downloaded into a temporary directory next to the current one and then
+/// renamed into place, so the current executable is never left half-written. On Windows, the
+/// running executable is first renamed to `ubi-old.exe`, since Windows does not allow it to be
+/// replaced or deleted.
+///
+/// # Errors
+///
+/// This returns an error if the current executable cannot be found, if it or the directory
+/// containing it is not writable, if installing the new release fails, or if the new executable
+/// cannot be run to get its version.
+pub async fn self_upgrade(builder: UbiBuilder<'_>) -> Result<SelfUpgrade> {
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("could not find the path of the current executable")?;
+    debug!("upgrading the executable at {}", exe.display());
+    check_exe_is_writable(&exe)?;
+
+    let exe_dir = exe
+        .parent()
+        .ok_or_else(|| anyhow!("executable path `{}` has no parent", exe.display()))?;
+    // This needs to be on the same filesystem as the current executable so that we can rename the
+    // new one into place.
+    let staging_dir = tempfile::Builder::new()
+        .prefix(".ubi-self-upgrade-")
+        .tempdir_in(exe_dir)
+        .with_context(|| {
+            format!(
+                "cannot write to the directory containing the current executable, {}",
+                exe_dir.display(),
+            )
+        })?;
+
+    let is_windows = cfg!(target_os = "windows");
+    builder
+        .project(UBI_PROJECT)
+        .install_dir(staging_dir.path())
+        .build()?
+        .install_binary()
+        .await?;
+    let new_exe = staging_dir
+        .path()
+        .join(if is_windows { "ubi.exe" } else { "ubi" });
+    let new_version = exe_version(&new_exe)?;
+
+    let old_exe = replace_exe(&exe, &new_exe, is_windows)?;
+
+    Ok(SelfUpgrade {
+        path: exe,
+        old_version: VERSION.to_string(),
+        new_version,
+        old_exe,
+    })
+}
+
+fn check_exe_is_writable(exe: &Path) -> Result<()> {
+    let meta = fs::metadata(exe).with_context(|| {
+        format!(
+            "could not get metadata for the current executable at {}",
+            exe.display()
+        )
+    })?;
+    if meta.permissions().readonly() {
+        return Err(anyhow!(
+            "the current executable at {} is not writable",
+            exe.display()
+        ));
+    }
+    Ok(())
+}
+
+// Running the new executable makes sure that we don't replace a working `ubi` with one that can't
+// run at all.
+fn exe_version(exe: &Path) -> Result<String> {
+    let output = Command::new(exe)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("could not run the new executable at {}", exe.display()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "running `{} --version` failed with {}",
+            exe.display(),
+            output.status,
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.trim();
+    Ok(version.strip_prefix("ubi ").unwrap_or(version).to_string())
+}
+
+// Returns the path to the old executable if it had to be left behind.
+fn replace_exe(exe: &Path, new_exe: &Path, is_windows: bool) -> Result<Option<PathBuf>> {
+    let old_exe = is_windows.then(|| exe.with_file_name(OLD_WINDOWS_EXE_NAME));
+    installer::replace_exe(exe, new_exe, old_exe.as_deref())?;
+    Ok(old_exe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case(false, "ubi", None ; "not Windows")]
+    #[test_case(true, "ubi.exe", Some(OLD_WINDOWS_EXE_NAME) ; "Windows")]
+    fn replace_exe(is_windows: bool, exe_name: &str, expect_old_exe: Option<&str>) -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join(exe_name);
+        fs::write(&exe, "old")?;
+        let staging_dir = td.path().join(".ubi-self-upgrade-test");
+        fs::create_dir(&staging_dir)?;
+        let new_exe = staging_dir.join(exe_name);
+        fs::write(&new_exe, "new")?;
+
+        let old_exe = super::replace_exe(&exe, &new_exe, is_windows)?;
+
+        assert_eq!(fs::read_to_string(&exe)?, "new");
+        assert!(!new_exe.exists(), "the new executable was moved");
+        assert_eq!(old_exe, expect_old_exe.map(|n| td.path().join(n)));
+        if let Some(old_exe) = old_exe {
+            assert_eq!(fs::read_to_string(old_exe)?, "old");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_exe_on_windows_removes_previous_old_exe() -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join("ubi.exe");
+        fs::write(&exe, "old")?;
+        fs::write(td.path().join(OLD_WINDOWS_EXE_NAME), "older")?;
+        let new_exe = td.path().join("new-ubi.exe");
+        fs::write(&new_exe, "new")?;
+
+        let old_exe = super::replace_exe(&exe, &new_exe, true)?;
+
+        assert_eq!(fs::read_to_string(&exe)?, "new");
+        assert_eq!(fs::read_to_string(old_exe.unwrap())?, "old");
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_exe_on_windows_restores_exe_on_failure() -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join("ubi.exe");
+        fs::write(&exe, "old")?;
+        let new_exe = td.path().join("does-not-exist.exe");
+
+        assert!(super::replace_exe(&exe, &new_exe, true).is_err());
+        assert_eq!(fs::read_to_string(&exe)?, "old");
+        assert!(!td.path().join(OLD_WINDOWS_EXE_NAME).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_exe_is_writable() -> Result<()> {
+        let td = tempdir()?;
+        let exe = td.path().join("ubi");
+        fs::write(&exe, "old")?;
+        super::check_exe_is_writable(&exe)?;
+
+        let mut perms = fs::metadata(&exe)?.permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&exe, perms)?;
+        let err = super::check_exe_is_writable(&exe).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "the current executable at {} is not writable",
+                exe.display()
+            ),
+        );
+
+        assert!(super::check_exe_is_writable(&td.path().join("missing")).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn exe_version() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let td = tempdir()?;
+        let exe = td.path().join("ubi");
+        fs::write(&exe, "#!/bin/sh\necho 'ubi 9.8.7'\n")?;
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755))?;
+        assert_eq!(super::exe_version(&exe)?, "9.8.7");
+
+        fs::write(&exe, "#!/bin/sh\nexit 1\n")?;
+        assert!(super::exe_version(&exe).is_err());
+
+        Ok(())
+    }
+}
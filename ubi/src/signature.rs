@@ -0,0 +1,208 @@
+use crate::ubi::Asset;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use minisign_verify::{PublicKey, Signature};
+use std::{fs, path::Path};
+
+const MINISIG_EXTENSION: &str = ".minisig";
+
+#[derive(Debug)]
+pub(crate) struct SignatureVerifier {
+    public_key: PublicKey,
+}
+
+impl SignatureVerifier {
+    // The public key can either be given as the base64-encoded key itself, or as a path to a `.pub`
+    // file like the one created by `minisign -G`.
+    pub(crate) fn new(public_key: &str) -> Result<Self> {
+        let path = Path::new(public_key);
+        let public_key = if path.is_file() {
+            debug!("reading minisign public key from {}", path.display());
+            PublicKey::from_file(path).with_context(|| {
+                format!(
+                    "could not read a minisign public key from {}",
+                    path.display()
+                )
+            })?
+        } else {
+            PublicKey::from_base64(public_key).with_context(|| {
+                format!("could not parse the minisign public key `{public_key}`")
+            })?
+        };
+
+        Ok(Self { public_key })
+    }
+
+    // Signatures are expected to be released alongside the asset with the same name plus a
+    // `.minisig` extension, which is what `minisign -S` creates by default.
+    pub(crate) fn signature_asset_for(asset: &Asset, assets: &[Asset]) -> Result<Asset> {
+        let name = format!("{}{MINISIG_EXTENSION}", asset.name);
+        debug!("looking for a signature asset named {name}");
+        assets
+            .iter()
+            .find(|a| a.name == name)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "could not find a minisign signature for {} (expected an asset named {name})",
+                    asset.name,
+                )
+            })
+    }
+
+    // When we're given a URL instead of a project, there's no list of release assets to look in,
+    // so we assume the signature is next to the asset.
+    pub(crate) fn signature_asset_for_url(asset: &Asset) -> Asset {
+        let mut url = asset.url.clone();
+        url.set_path(&format!("{}{MINISIG_EXTENSION}", asset.url.path()));
+        Asset {
+            name: format!("{}{MINISIG_EXTENSION}", asset.name),
+            url,
+            size: None,
+        }
+    }
+
+    pub(crate) fn verify(&self, file: &Path, signature: &str) -> Result<()> {
+        let bin = fs::read(file)
+            .with_context(|| format!("could not read {} to verify it", file.display()))?;
+        self.verify_bytes(&file.display().to_string(), &bin, signature)
+    }
+
+    // The name is only used in messages, so it can be a path or an asset name.
+    pub(crate) fn verify_bytes(&self, name: &str, bin: &[u8], signature: &str) -> Result<()> {
+        debug!("verifying minisign signature for {name}");
+
+        let signature =
+            Signature::decode(signature).context("could not parse the minisign signature")?;
+        // Legacy signatures are made by signing the file contents directly rather than a hash of
+        // them. Older versions of minisign created these by default, and they are still secure.
+        self.public_key
+            .verify(bin, &signature, true)
+            .map_err(|e| anyhow!("minisign signature verification failed for {name}: {e}"))?;
+        debug!("minisign signature is valid");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_case::minisign_sign as sign;
+    use minisign::KeyPair;
+    use tempfile::tempdir;
+    use test_log::test;
+    use url::Url;
+
+    #[test]
+    fn verify_valid_signature() -> Result<()> {
+        let data = fs::read("test-data/project.tar.gz")?;
+        let (public_key, signature) = sign(&data)?;
+
+        let verifier = SignatureVerifier::new(&public_key)?;
+        verifier.verify(Path::new("test-data/project.tar.gz"), &signature)
+    }
+
+    #[test]
+    fn verify_tampered_file() -> Result<()> {
+        let data = fs::read("test-data/project.tar.gz")?;
+        let (public_key, signature) = sign(&data)?;
+
+        let td = tempdir()?;
+        let tampered = td.path().join("project.tar.gz");
+        let mut tampered_data = data.clone();
+        tampered_data.push(0);
+        fs::write(&tampered, tampered_data)?;
+
+        let verifier = SignatureVerifier::new(&public_key)?;
+        let res = verifier.verify(&tampered, &signature);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .starts_with("minisign signature verification failed for"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_signature_from_other_key() -> Result<()> {
+        let data = fs::read("test-data/project.tar.gz")?;
+        let (_, signature) = sign(&data)?;
+        let (other_public_key, _) = sign(&data)?;
+
+        let verifier = SignatureVerifier::new(&other_public_key)?;
+        assert!(verifier
+            .verify(Path::new("test-data/project.tar.gz"), &signature)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn public_key_from_file() -> Result<()> {
+        let KeyPair { pk, .. } = KeyPair::generate_unencrypted_keypair()?;
+        let td = tempdir()?;
+        let pub_file = td.path().join("minisign.pub");
+        fs::write(&pub_file, pk.to_box()?.into_string())?;
+
+        SignatureVerifier::new(pub_file.to_str().unwrap())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_public_key() {
+        let res = SignatureVerifier::new("not a key");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not parse the minisign public key `not a key`",
+        );
+    }
+
+    #[test]
+    fn signature_asset_for() -> Result<()> {
+        let url = Url::parse("https://example.com")?;
+        let asset = |name: &str| Asset {
+            name: name.to_string(),
+            url: url.clone(),
+            size: None,
+        };
+        let assets = vec![
+            asset("project.tar.gz"),
+            asset("project.tar.gz.minisig"),
+            asset("project.zip"),
+        ];
+
+        assert_eq!(
+            SignatureVerifier::signature_asset_for(&assets[0], &assets)?.name,
+            "project.tar.gz.minisig",
+        );
+        let res = SignatureVerifier::signature_asset_for(&assets[2], &assets);
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not find a minisign signature for project.zip (expected an asset named project.zip.minisig)",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn signature_asset_for_url() -> Result<()> {
+        let asset = Asset {
+            name: "project.tar.gz".to_string(),
+            url: Url::parse("https://example.com/download/project.tar.gz?foo=bar")?,
+            size: None,
+        };
+        let sig = SignatureVerifier::signature_asset_for_url(&asset);
+        assert_eq!(sig.name, "project.tar.gz.minisig");
+        assert_eq!(
+            sig.url.as_str(),
+            "https://example.com/download/project.tar.gz.minisig?foo=bar",
+        );
+
+        Ok(())
+    }
+}
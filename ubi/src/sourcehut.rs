@@ -0,0 +1,402 @@
+use crate::{
+    forge::{Forge, ReleaseAssets},
+    project,
+    retry::RetryPolicy,
+    ubi::Asset,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use itertools::Itertools;
+use log::debug;
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    Client, RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug)]
+pub(crate) struct SourceHut {
+    project_name: String,
+    tag: Option<String>,
+    api_base_url: Url,
+    token: Option<String>,
+    retry_policy: RetryPolicy,
+}
+
+unsafe impl Send for SourceHut {}
+unsafe impl Sync for SourceHut {}
+
+// SourceHut doesn't have releases. Instead, artifacts are attached to git refs, which in practice
+// means tags. The list of refs is paginated, with each page containing a `next` cursor to pass as
+// the `start` query param for the next page, if there is one.
+#[derive(Debug, Deserialize, Serialize)]
+struct RefsPage {
+    results: Vec<Ref>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Ref {
+    name: String,
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Artifact {
+    filename: String,
+    url: Url,
+    // This is an RFC 3339 timestamp in UTC, so these can be compared as strings.
+    created: String,
+}
+
+impl From<Artifact> for Asset {
+    fn from(artifact: Artifact) -> Self {
+        Asset {
+            name: artifact.filename,
+            url: artifact.url,
+            size: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for SourceHut {
+    async fn fetch_assets(&self, client: &Client) -> Result<ReleaseAssets> {
+        let mut refs: Vec<Ref> = vec![];
+
+        let mut page = self
+            .make_release_info_request(client)
+            .await?
+            .json::<RefsPage>()
+            .await?;
+        loop {
+            refs.extend(page.results);
+            let Some(next) = page.next else {
+                break;
+            };
+            let mut url = self.release_info_url();
+            url.query_pairs_mut().append_pair("start", &next);
+            debug!("Getting next page of refs from {url}");
+            page = self
+                .make_api_request(client, url)
+                .await?
+                .json::<RefsPage>()
+                .await?;
+        }
+
+        let tag_ref = if let Some(tag) = &self.tag {
+            let name = format!("refs/tags/{tag}");
+            let all_tags = refs.iter().filter_map(|r| tag_name(&r.name)).join(", ");
+            refs.into_iter().find(|r| r.name == name).ok_or_else(|| {
+                anyhow!(
+                    "could not find the tag {tag} for {} in the list of tags: {all_tags}",
+                    self.project_name,
+                )
+            })?
+        } else {
+            // The latest release is the tag with the most recently uploaded artifact.
+            refs.into_iter()
+                .filter(|r| tag_name(&r.name).is_some())
+                .filter_map(|r| {
+                    let newest = r.artifacts.iter().map(|a| a.created.clone()).max()?;
+                    Some((newest, r))
+                })
+                .max_by(|a, b| a.0.cmp(&b.0))
+                .map(|(_, r)| r)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "could not find any tags with artifacts for {}",
+                        self.project_name
+                    )
+                })?
+        };
+        debug!("using artifacts from {}", tag_ref.name);
+
+        Ok(ReleaseAssets {
+            tag: tag_name(&tag_ref.name).map(String::from),
+            assets: tag_ref.artifacts.into_iter().map(Asset::from).collect(),
+        })
+    }
+
+    fn set_tag(&mut self, tag: String) {
+        self.tag = Some(tag);
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn release_info_url(&self) -> Url {
+        let (owner, repo) = project::split_project_name(&self.project_name)
+            .expect("the project name is checked by UbiBuilder::build");
+
+        // SourceHut owners are always prefixed with a `~` in URLs, but we allow the project name to
+        // be given without it.
+        let owner = format!("~{}", owner.strip_prefix('~').unwrap_or(owner));
+
+        let mut url = self.api_base_url.clone();
+        url.path_segments_mut()
+            .expect("could not get path segments for url")
+            .pop_if_empty()
+            .push(&owner)
+            .push("repos")
+            .push(repo)
+            .push("refs");
+
+        url
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn maybe_add_token_header(&self, mut req_builder: RequestBuilder) -> Result<RequestBuilder> {
+        if let Some(token) = self.token.as_deref() {
+            debug!("Adding SourceHut token to SourceHut request.");
+            let mut auth_val = HeaderValue::from_str(&format!("Bearer {token}"))?;
+            auth_val.set_sensitive(true);
+            req_builder = req_builder.header(AUTHORIZATION, auth_val);
+        } else {
+            debug!("No SourceHut token found.");
+        }
+        Ok(req_builder)
+    }
+}
+
+impl SourceHut {
+    pub(crate) fn new(
+        project_name: String,
+        tag: Option<String>,
+        api_base_url: Url,
+        token: Option<String>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            project_name,
+            tag,
+            api_base_url,
+            token,
+            retry_policy,
+        }
+    }
+}
+
+fn tag_name(ref_name: &str) -> Option<&str> {
+    ref_name.strip_prefix("refs/tags/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use reqwest::Client;
+    use serial_test::serial;
+    use std::env;
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_without_token() -> Result<()> {
+        fetch_assets(None, None, "v1.1.0").await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_with_token() -> Result<()> {
+        fetch_assets(None, Some("fakeToken"), "v1.1.0").await
+    }
+
+    #[test(tokio::test)]
+    #[serial]
+    async fn fetch_assets_with_tag() -> Result<()> {
+        fetch_assets(Some("v1.0.0"), None, "v1.0.0").await
+    }
+
+    async fn fetch_assets(tag: Option<&str>, token: Option<&str>, expect_tag: &str) -> Result<()> {
+        let vars = env::vars();
+        env::remove_var("SRHT_TOKEN");
+
+        let authorization_header_matcher = if let Some(token) = token {
+            Matcher::Exact(format!("Bearer {token}"))
+        } else {
+            Matcher::Missing
+        };
+        let mut server = Server::new_async().await;
+        let m1 = server
+            .mock("GET", "/~houseabsolute/repos/ubi/refs")
+            .match_query(Matcher::Missing)
+            .match_header("Authorization", authorization_header_matcher.clone())
+            .with_status(200)
+            .with_body(SOURCEHUT_REFS_RESPONSE_PAGE1)
+            .create_async()
+            .await;
+        let m2 = server
+            .mock("GET", "/~houseabsolute/repos/ubi/refs")
+            .match_query(Matcher::UrlEncoded("start".into(), "2".into()))
+            .match_header("Authorization", authorization_header_matcher)
+            .with_status(200)
+            .with_body(SOURCEHUT_REFS_RESPONSE_PAGE2)
+            .create_async()
+            .await;
+
+        let sourcehut = SourceHut::new(
+            "~houseabsolute/ubi".to_string(),
+            tag.map(String::from),
+            Url::parse(&server.url())?,
+            token.map(String::from),
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let got = sourcehut.fetch_assets(&client).await?;
+        assert_eq!(got.tag.as_deref(), Some(expect_tag));
+        assert_eq!(
+            got.assets,
+            [
+                "ubi-Linux-x86_64-musl.tar.gz",
+                "ubi-Windows-msvc-x86_64.zip"
+            ]
+            .into_iter()
+            .map(|name| Ok(Asset {
+                name: name.to_string(),
+                url: Url::parse(&format!(
+                    "https://git.sr.ht/~houseabsolute/ubi/refs/download/{expect_tag}/{name}"
+                ))?,
+                size: None,
+            }))
+            .collect::<Result<Vec<_>>>()?,
+        );
+
+        m1.assert_async().await;
+        m2.assert_async().await;
+
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn fetch_assets_with_missing_tag() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/~houseabsolute/repos/ubi/refs")
+            .with_status(200)
+            .with_body(SOURCEHUT_REFS_RESPONSE_PAGE2)
+            .create_async()
+            .await;
+
+        let sourcehut = SourceHut::new(
+            "~houseabsolute/ubi".to_string(),
+            Some("v2.0.0".to_string()),
+            Url::parse(&server.url())?,
+            None,
+            RetryPolicy::default(),
+        );
+
+        let client = Client::new();
+        let err = sourcehut.fetch_assets(&client).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "could not find the tag v2.0.0 for ~houseabsolute/ubi in the list of tags: v1.1.0",
+        );
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    // These are trimmed down from a real SourceHut response. The first page has the tag for the
+    // older release and a tag without any artifacts, and the second has the newer release.
+    const SOURCEHUT_REFS_RESPONSE_PAGE1: &str = r#"
+{
+  "next": "2",
+  "results": [
+    {
+      "name": "refs/heads/master",
+      "target": "d4b3f1a2c5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0",
+      "artifacts": []
+    },
+    {
+      "name": "refs/tags/v1.0.0",
+      "target": "a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0",
+      "artifacts": [
+        {
+          "created": "2025-03-01T00:00:00+00:00",
+          "checksum": "sha256:5f1e8a1c7c3e4b0f2d6a9e8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d",
+          "filename": "ubi-Linux-x86_64-musl.tar.gz",
+          "url": "https://git.sr.ht/~houseabsolute/ubi/refs/download/v1.0.0/ubi-Linux-x86_64-musl.tar.gz"
+        },
+        {
+          "created": "2025-03-01T00:01:00+00:00",
+          "checksum": "sha256:0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b",
+          "filename": "ubi-Windows-msvc-x86_64.zip",
+          "url": "https://git.sr.ht/~houseabsolute/ubi/refs/download/v1.0.0/ubi-Windows-msvc-x86_64.zip"
+        }
+      ]
+    },
+    {
+      "name": "refs/tags/v1.0.1-no-artifacts",
+      "target": "b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0",
+      "artifacts": []
+    }
+  ],
+  "total": 4,
+  "results_per_page": 3
+}
+"#;
+
+    const SOURCEHUT_REFS_RESPONSE_PAGE2: &str = r#"
+{
+  "next": null,
+  "results": [
+    {
+      "name": "refs/tags/v1.1.0",
+      "target": "c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0",
+      "artifacts": [
+        {
+          "created": "2025-04-01T00:00:00+00:00",
+          "checksum": "sha256:1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c",
+          "filename": "ubi-Linux-x86_64-musl.tar.gz",
+          "url": "https://git.sr.ht/~houseabsolute/ubi/refs/download/v1.1.0/ubi-Linux-x86_64-musl.tar.gz"
+        },
+        {
+          "created": "2025-04-01T00:01:00+00:00",
+          "checksum": "sha256:2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d",
+          "filename": "ubi-Windows-msvc-x86_64.zip",
+          "url": "https://git.sr.ht/~houseabsolute/ubi/refs/download/v1.1.0/ubi-Windows-msvc-x86_64.zip"
+        }
+      ]
+    }
+  ],
+  "total": 4,
+  "results_per_page": 3
+}
+"#;
+
+    #[test_case("~houseabsolute/ubi", "https://git.sr.ht/api" ; "with tilde")]
+    #[test_case("houseabsolute/ubi", "https://git.sr.ht/api" ; "without tilde")]
+    #[test_case("~houseabsolute/ubi", "https://git.example.com/api/" ; "trailing slash")]
+    fn release_info_url(project_name: &str, api_base: &str) {
+        let sourcehut = SourceHut::new(
+            project_name.to_string(),
+            None,
+            Url::parse(api_base).unwrap(),
+            None,
+            RetryPolicy::default(),
+        );
+        let url = sourcehut.release_info_url();
+        assert_eq!(
+            url.as_str(),
+            format!(
+                "{}/~houseabsolute/repos/ubi/refs",
+                api_base.trim_end_matches('/')
+            ),
+        );
+    }
+}
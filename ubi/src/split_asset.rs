@@ -0,0 +1,107 @@
+// Some projects split a large release asset into parts named like `project.tar.gz.001`,
+// `project.tar.gz.002`, and so on. The parts have to be joined back together, in order, before the
+// asset can be extracted. We only treat a name as a part if the name without the `.NNN` suffix has
+// an extension we know about, since a suffix like `.100` can also be the end of a version number.
+use crate::{extension::Extension, ubi::Asset};
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use lazy_regex::regex_captures;
+use std::path::Path;
+
+// Returns the name of the joined asset and the part number, which starts at 1.
+pub(crate) fn split_asset_part(name: &str) -> Option<(&str, u32)> {
+    let (_, joined_name, part) = regex_captures!(r"^(.+)\.([0-9]{3})$", name)?;
+    if !matches!(Extension::from_path(Path::new(joined_name)), Ok(Some(_))) {
+        return None;
+    }
+    part.parse().ok().map(|p| (joined_name, p))
+}
+
+// Returns every part of the split asset that `first` is the first part of, in order. The first part
+// is always included, even if it's not in `assets`, which is empty when the asset came from a URL.
+pub(crate) fn split_asset_parts(first: &Asset, assets: &[Asset]) -> Result<Vec<Asset>> {
+    let Some((joined_name, 1)) = split_asset_part(&first.name) else {
+        return Err(anyhow!(
+            "{} is not the first part of a split asset",
+            first.name
+        ));
+    };
+
+    let mut parts = assets
+        .iter()
+        .filter_map(|a| match split_asset_part(&a.name) {
+            Some((name, part)) if name == joined_name && part > 1 => Some((part, a.clone())),
+            _ => None,
+        })
+        .sorted_by_key(|(part, _)| *part)
+        .collect::<Vec<_>>();
+    parts.insert(0, (1, first.clone()));
+
+    // The parts are sorted, so if one is missing, the part at that position has a higher number.
+    if let Some(missing) = parts
+        .iter()
+        .enumerate()
+        .map(|(i, (part, _))| (i as u32 + 1, *part))
+        .find(|(expect, part)| expect != part)
+        .map(|(expect, _)| expect)
+    {
+        return Err(anyhow!(
+            "the release is missing part {missing:03} of the split asset {joined_name}, it has {}",
+            parts.iter().map(|(_, a)| &a.name).join(", "),
+        ));
+    }
+
+    Ok(parts.into_iter().map(|(_, a)| a).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use url::Url;
+
+    fn assets(names: &[&str]) -> Vec<Asset> {
+        names
+            .iter()
+            .map(|n| Asset {
+                name: (*n).to_string(),
+                url: Url::parse(&format!("https://example.com/{n}")).unwrap(),
+                size: None,
+            })
+            .collect()
+    }
+
+    #[test_case("project.tar.gz.001", Some(("project.tar.gz", 1)) ; "first part")]
+    #[test_case("project.zip.012", Some(("project.zip", 12)) ; "later part")]
+    #[test_case("project.tar.gz", None ; "not split")]
+    #[test_case("project-1.2.100", None ; "version number")]
+    #[test_case("project.001", None ; "no extension before the part")]
+    #[test_case("project.tar.gz.01", None ; "two digit suffix")]
+    fn split_asset_part(name: &str, expect: Option<(&str, u32)>) {
+        assert_eq!(super::split_asset_part(name), expect);
+    }
+
+    #[test_case(
+        &["project.tar.gz.002", "project.tar.gz.001", "project.tar.gz.003", "other.tar.gz.002"],
+        Ok(&["project.tar.gz.001", "project.tar.gz.002", "project.tar.gz.003"]) ;
+        "all parts"
+    )]
+    #[test_case(&[], Ok(&["project.tar.gz.001"]) ; "only the first part")]
+    #[test_case(
+        &["project.tar.gz.001", "project.tar.gz.003"],
+        Err("the release is missing part 002 of the split asset project.tar.gz, it has project.tar.gz.001, project.tar.gz.003") ;
+        "missing part"
+    )]
+    fn split_asset_parts(names: &[&str], expect: Result<&[&str], &str>) {
+        let first = assets(&["project.tar.gz.001"]).remove(0);
+        let parts = super::split_asset_parts(&first, &assets(names));
+        assert_eq!(
+            parts
+                .map(|p| p.into_iter().map(|a| a.name).collect::<Vec<_>>())
+                .map_err(|e| e.to_string()),
+            expect
+                .map(|e| e.iter().map(|n| (*n).to_string()).collect())
+                .map_err(String::from),
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal},
+};
+use strum::{AsRefStr, EnumString, VariantNames};
+
+/// Whether to use color in terminal output. This is used by [`init_logger`](crate::init_logger)
+/// and by the progress bar enabled with [`UbiBuilder::progress`](crate::UbiBuilder::progress).
+#[derive(AsRefStr, Clone, Copy, Debug, Default, EnumString, PartialEq, Eq, VariantNames)]
+pub enum ColorChoice {
+    /// Use color if the output is a terminal and the `NO_COLOR` env var is not set to a non-empty
+    /// value. See <https://no-color.org> for details on `NO_COLOR`.
+    #[strum(serialize = "auto")]
+    #[default]
+    Auto,
+    /// Always use color, even if the output is not a terminal or `NO_COLOR` is set.
+    #[strum(serialize = "always")]
+    Always,
+    /// Never use color.
+    #[strum(serialize = "never")]
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns true if output written to stderr should use color.
+    #[must_use]
+    pub fn use_color_on_stderr(self) -> bool {
+        self.use_color(io::stderr().is_terminal(), env::var_os("NO_COLOR"))
+    }
+
+    fn use_color(self, is_terminal: bool, no_color: Option<OsString>) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && no_color.is_none_or(|v| v.is_empty()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case(ColorChoice::Auto, true, None, true ; "auto with a terminal")]
+    #[test_case(ColorChoice::Auto, false, None, false ; "auto without a terminal")]
+    #[test_case(ColorChoice::Auto, true, Some("1"), false ; "auto with NO_COLOR")]
+    #[test_case(ColorChoice::Auto, true, Some(""), true ; "auto with empty NO_COLOR")]
+    #[test_case(ColorChoice::Always, false, None, true ; "always without a terminal")]
+    #[test_case(ColorChoice::Always, true, Some("1"), true ; "always with NO_COLOR")]
+    #[test_case(ColorChoice::Never, true, None, false ; "never with a terminal")]
+    fn use_color(choice: ColorChoice, is_terminal: bool, no_color: Option<&str>, expect: bool) {
+        assert_eq!(
+            choice.use_color(is_terminal, no_color.map(OsString::from)),
+            expect,
+        );
+    }
+
+    #[test_case("auto", Ok(ColorChoice::Auto) ; "auto")]
+    #[test_case("always", Ok(ColorChoice::Always) ; "always")]
+    #[test_case("never", Ok(ColorChoice::Never) ; "never")]
+    #[test_case("sometimes", Err(()) ; "invalid")]
+    fn from_str(s: &str, expect: Result<ColorChoice, ()>) {
+        assert_eq!(ColorChoice::from_str(s).map_err(|_| ()), expect);
+    }
+}
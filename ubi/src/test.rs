@@ -1,9 +1,28 @@
-use crate::UbiBuilder;
-use anyhow::Result;
-use mockito::Server;
-use platforms::PlatformReq;
-use reqwest::header::ACCEPT;
-use std::str::FromStr;
+use crate::{
+    install_from_lockfile, install_from_manifest, install_many, Asset, DownloadProgress, ForgeType,
+    InstallResult, InstallSpec, Lockfile, Manifest, UbiBuilder,
+};
+use anyhow::{anyhow, Result};
+use mockito::{Matcher, Server};
+use platforms::{Platform, PlatformReq};
+use reqwest::header::{
+    HeaderName, ACCEPT, ACCEPT_RANGES, AUTHORIZATION, CONTENT_RANGE, ETAG, IF_RANGE, RANGE,
+    USER_AGENT,
+};
+use serial_test::serial;
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::tempdir;
+use test_case::test_case;
 use test_log::test;
 use url::Url;
 
@@ -823,7 +842,7 @@ async fn multiple_matches() -> Result<()> {
             .api_base_url(&url)
             .build()?;
         let asset = ubi.asset().await?;
-        let expect = "mm-i686-pc-windows-gnu.zip";
+        let expect = "mm-i686-pc-windows-msvc.zip";
         assert_eq!(asset.name, expect, "picked {expect} as protobuf asset name");
     }
 
@@ -1033,3 +1052,4007 @@ const OS_WITHOUT_ARCH_RESPONSE2: &str = r#"
     }
   ]
 }"#;
+
+#[test(tokio::test)]
+async fn install_with_valid_signature() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let (public_key, signature) = crate::test_case::minisign_sign(&data)?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_signed_release(&mut server, &data, &signature).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_signature(&public_key)
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert!(td.path().join("project").is_file());
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_invalid_signature() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let (public_key, signature) = crate::test_case::minisign_sign(&data)?;
+    let mut tampered = data.clone();
+    tampered.push(0);
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_signed_release(&mut server, &tampered, &signature).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_signature(&public_key)
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert!(res.is_err());
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .starts_with("minisign signature verification failed for"));
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_missing_signature() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let (public_key, _) = crate::test_case::minisign_sign(&data)?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let m1 = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(
+            SIGNED_RELEASE_RESPONSE
+                .replace("{url}", &url)
+                .replace("project-Linux-x86_64.tar.gz.minisig", "unrelated.minisig"),
+        )
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_signature(&public_key)
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert!(res.is_err());
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .starts_with("could not find a minisign signature for"));
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    m1.assert_async().await;
+
+    Ok(())
+}
+
+async fn mock_signed_release(
+    server: &mut Server,
+    data: &[u8],
+    signature: &str,
+) -> Vec<mockito::Mock> {
+    let release = SIGNED_RELEASE_RESPONSE.replace("{url}", &server.url());
+    vec![
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(data)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz.minisig")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(signature)
+            .create_async()
+            .await,
+    ]
+}
+
+// We only include one binary asset so that the test doesn't depend on the platform it runs on.
+const SIGNED_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz",
+      "name": "project-Linux-x86_64.tar.gz"
+    },
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz.minisig",
+      "name": "project-Linux-x86_64.tar.gz.minisig"
+    }
+  ]
+}"#;
+
+// The GPG fixtures in `test-data/gpg` were made with `gpg --detach-sign` using freshly generated
+// keys, since there's no crate available here that can create OpenPGP signatures.
+#[test_case("ed25519.asc", "project.tar.gz.ed25519.asc", ".asc", false, None ; "valid armored Ed25519 signature")]
+#[test_case("rsa.asc", "project.tar.gz.rsa.sig", ".sig", false, None ; "valid binary RSA signature")]
+#[test_case(
+    "other.asc",
+    "project.tar.gz.ed25519.asc",
+    ".asc",
+    false,
+    Some("the signature was not made by any of the given public keys") ;
+    "signature from the wrong key"
+)]
+#[test_case(
+    "ed25519.asc",
+    "project.tar.gz.ed25519.asc",
+    ".asc",
+    true,
+    Some("the signature does not match the file") ;
+    "tampered payload"
+)]
+#[tokio::test]
+async fn install_with_gpg_signature(
+    key: &str,
+    signature: &str,
+    signature_ext: &str,
+    tamper: bool,
+    expect_err: Option<&str>,
+) -> Result<()> {
+    crate::test_case::init_logging();
+
+    let mut data = fs::read("test-data/project.tar.gz")?;
+    if tamper {
+        data.push(0);
+    }
+    let signature = fs::read(format!("test-data/gpg/{signature}"))?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let signature_name = format!("project-Linux-x86_64.tar.gz{signature_ext}");
+    let release = SIGNED_RELEASE_RESPONSE
+        .replace("{url}", &url)
+        .replace("project-Linux-x86_64.tar.gz.minisig", &signature_name);
+    let mocks = vec![
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(&data)
+            .create_async()
+            .await,
+        server
+            .mock("GET", format!("/download/{signature_name}").as_str())
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(&signature)
+            .create_async()
+            .await,
+    ];
+
+    let key = format!("test-data/gpg/{key}");
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_gpg(&key)
+        .build()?;
+    let res = ubi.install_binary().await;
+    match expect_err {
+        None => {
+            res?;
+            assert!(td.path().join("project").is_file());
+        }
+        Some(expect) => {
+            let err = res.unwrap_err().to_string();
+            assert!(
+                err.starts_with("GPG signature verification failed for") && err.ends_with(expect),
+                "{err}",
+            );
+            assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+        }
+    }
+
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_missing_gpg_signature() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let m1 = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(SIGNED_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let m2 = server
+        .mock("GET", Matcher::Regex("^/download/".to_string()))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_gpg("test-data/gpg/ed25519.asc")
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "could not find a GPG signature for project-Linux-x86_64.tar.gz (expected an asset named project-Linux-x86_64.tar.gz.asc or project-Linux-x86_64.tar.gz.sig)",
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    m1.assert_async().await;
+    m2.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_correct_checksum() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &data, &data).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_checksum()
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert!(td.path().join("project").is_file());
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_corrupted_download() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let mut corrupted = data.clone();
+    corrupted.push(0);
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &corrupted, &data).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .verify_checksum()
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert!(res.is_err());
+    let err = res.unwrap_err().to_string();
+    assert!(err.starts_with("the SHA-256 checksum for"), "{err}");
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test_case(None, "the release v1.2.3 has no downloadable assets" ; "latest release")]
+#[test_case(Some("v1.2.3"), "the release v1.2.3 has no downloadable assets" ; "tagged release")]
+#[tokio::test]
+async fn install_when_release_has_no_assets(tag: Option<&str>, expect: &str) -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let path = match tag {
+        Some(tag) => format!("/repos/test/project/releases/tags/{tag}"),
+        None => "/repos/test/project/releases/latest".to_string(),
+    };
+    let release_mock = server
+        .mock("GET", path.as_str())
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(r#"{"tag_name": "v1.2.3", "assets": []}"#)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url);
+    if let Some(tag) = tag {
+        builder = builder.tag(tag);
+    }
+    let err = builder.build()?.install_binary().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("{expect}; check that the tag is correct, or if the release was just created, wait for its CI to finish uploading the assets"),
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_when_download_is_an_error_page() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("content-type", "text/html")
+        .with_body("<!DOCTYPE html>\n<html><body><h1>Too Many Requests</h1></body></html>\n")
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let err = ubi.install_binary().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the downloaded content for project-Linux-x86_64.tar.gz doesn't look like a binary or archive, it looks like an HTML page, so the server may have returned an error page instead of the file",
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// The `SHA256SUMS` file contains the checksum of `checksummed_data`, while the asset download
+// returns `data`.
+async fn mock_release_with_checksums(
+    server: &mut Server,
+    data: &[u8],
+    checksummed_data: &[u8],
+) -> Vec<mockito::Mock> {
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &server.url());
+    let checksums = format!(
+        "{:x}  project-Linux-x86_64.tar.gz\n",
+        Sha256::digest(checksummed_data),
+    );
+    vec![
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(data)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/SHA256SUMS")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(checksums)
+            .create_async()
+            .await,
+    ]
+}
+
+const CHECKSUMS_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz",
+      "name": "project-Linux-x86_64.tar.gz"
+    },
+    {
+      "url": "{url}/download/SHA256SUMS",
+      "name": "SHA256SUMS"
+    }
+  ]
+}"#;
+
+#[test(tokio::test)]
+async fn install_plan() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .api_base_url(&url)
+        .build()?;
+    let plan = ubi.install_plan().await?;
+
+    assert_eq!(plan.asset_name, "project-Linux-x86_64.tar.gz");
+    assert_eq!(
+        plan.asset_url,
+        Url::parse(&format!("{url}/download/project-Linux-x86_64.tar.gz"))?,
+    );
+    assert_eq!(plan.install_path, install_dir.join("project"));
+    assert!(!install_dir.exists(), "nothing was installed");
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn asset_download_url() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .api_base_url(&url)
+        .build()?;
+    assert_eq!(
+        ubi.asset_download_url().await?,
+        Url::parse(&format!("{url}/download/project-Linux-x86_64.tar.gz"))?,
+    );
+    assert!(!install_dir.exists(), "nothing was installed");
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+// The second plan uses the cached release info, unless the cache expired or is being refreshed.
+#[test_case(None, false, 1 ; "within the TTL")]
+#[test_case(Some(Duration::ZERO), false, 2 ; "after the TTL")]
+#[test_case(None, true, 2 ; "refresh")]
+#[tokio::test]
+async fn install_plan_with_release_info_cache(
+    ttl: Option<Duration>,
+    refresh: bool,
+    expect_requests: usize,
+) -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(expect_requests)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let cache_dir = td.path().join("cache");
+    for i in 0..2 {
+        let mut builder = UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path().join("bin"))
+            .api_base_url(&url)
+            .release_info_cache_dir(&cache_dir);
+        if let Some(ttl) = ttl {
+            builder = builder.release_info_cache_ttl(ttl);
+        }
+        // The first plan always has to fetch the release info.
+        if refresh && i == 1 {
+            builder = builder.refresh_release_info();
+        }
+        let plan = builder.build()?.install_plan().await?;
+        assert_eq!(plan.asset_name, "project-Linux-x86_64.tar.gz");
+    }
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn download_only() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &data, &data).await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .api_base_url(&url)
+        .verify_checksum()
+        .build()?;
+    let (asset, contents) = ubi.download_only().await?;
+
+    assert_eq!(asset.name, "project-Linux-x86_64.tar.gz");
+    assert_eq!(
+        asset.url,
+        Url::parse(&format!("{url}/download/project-Linux-x86_64.tar.gz"))?,
+    );
+    assert_eq!(contents.as_ref(), data.as_slice());
+    assert!(!install_dir.exists(), "nothing was written");
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn download_only_with_corrupted_download() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let mut corrupted = data.clone();
+    corrupted.push(0);
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &corrupted, &data).await;
+
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .api_base_url(&url)
+        .verify_checksum()
+        .build()?;
+    let err = ubi.download_only().await.unwrap_err().to_string();
+    assert!(
+        err.starts_with("the SHA-256 checksum for project-Linux-x86_64.tar.gz is "),
+        "{err}"
+    );
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn available_assets() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .api_base_url(&url)
+        .build()?;
+    let assets = ubi.available_assets().await?;
+
+    assert_eq!(
+        assets,
+        vec![
+            Asset {
+                name: "project-Linux-x86_64.tar.gz".to_string(),
+                url: Url::parse(&format!("{url}/download/project-Linux-x86_64.tar.gz"))?,
+                size: None,
+            },
+            Asset {
+                name: "SHA256SUMS".to_string(),
+                url: Url::parse(&format!("{url}/download/SHA256SUMS"))?,
+                size: None,
+            },
+        ],
+    );
+    assert!(!install_dir.exists(), "nothing was installed");
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn available_assets_with_url() -> Result<()> {
+    let url = "https://example.com/download/project-Linux-x86_64.tar.gz";
+    let mut ubi = UbiBuilder::new().url(url).build()?;
+    assert_eq!(
+        ubi.available_assets().await?,
+        vec![Asset {
+            name: "project-Linux-x86_64.tar.gz".to_string(),
+            url: Url::parse(url)?,
+            size: None,
+        }],
+    );
+
+    Ok(())
+}
+
+// The asset's name doesn't match the platform, and there's no mock for the release info, so this
+// would fail if we asked the forge for the release and picked an asset from it.
+#[test(tokio::test)]
+async fn install_from_release_asset_url_as_project() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let asset_path = "/test/project/releases/download/v1.0.0/project-Windows-x86_64.tar.gz";
+    let m = server
+        .mock("GET", asset_path)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(fs::read("test-data/project.tar.gz")?)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let project = format!("{}{asset_path}", server.url());
+    let platform = Platform::find("x86_64-unknown-linux-gnu").unwrap();
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project(&project)
+        .forge(ForgeType::GitHub)
+        .platform(platform)
+        .install_dir(td.path())
+        .build()?;
+
+    let plan = ubi.install_plan().await?;
+    assert_eq!(plan.asset_name, "project-Windows-x86_64.tar.gz");
+    assert_eq!(plan.asset_url.as_str(), project);
+
+    ubi.install_binary().await?;
+    assert_local_asset_installed(td.path())?;
+    m.assert_async().await;
+
+    Ok(())
+}
+
+// None of these tests start a mock server, so they would fail if installing from a local file made
+// any network requests.
+#[test(tokio::test)]
+async fn install_from_local_tarball() -> Result<()> {
+    install_from_local_asset("test-data/project.tar.gz", None).await
+}
+
+#[test(tokio::test)]
+async fn install_from_local_bare_binary() -> Result<()> {
+    install_from_local_asset("test-data/project", None).await
+}
+
+#[test(tokio::test)]
+async fn install_from_local_zstd_binary_without_extension() -> Result<()> {
+    install_from_local_asset("test-data/project-zst-without-extension", None).await
+}
+
+#[test(tokio::test)]
+async fn install_from_local_zip_with_exe() -> Result<()> {
+    install_from_local_asset("test-data/project.zip", Some("project")).await
+}
+
+// This archive also has a `libexec/project` and a `bin/project-helper`, but `bin/project` is the one
+// with 3 bytes of content. It's installed without the `bin` directory.
+#[test(tokio::test)]
+async fn install_from_local_tarball_with_nested_exe() -> Result<()> {
+    install_from_local_asset(
+        if cfg!(windows) {
+            "test-data/windows-project-with-nested-exes.zip"
+        } else {
+            "test-data/project-with-nested-exes.tar.gz"
+        },
+        Some("bin/project"),
+    )
+    .await
+}
+
+// This is a gzipped executable that isn't a tarball, so it's installed with the asset's name minus
+// its `.gz` extension.
+#[test(tokio::test)]
+async fn install_from_local_gzipped_binary() -> Result<()> {
+    let td = tempdir()?;
+    let installed = UbiBuilder::new()
+        .local_asset("test-data/foo.gz")
+        .install_dir(td.path())
+        .build()?
+        .install_binary()
+        .await?;
+
+    let exe = td
+        .path()
+        .join(if cfg!(windows) { "foo.exe" } else { "foo" });
+    assert_eq!(installed.install_path, exe);
+    assert_eq!(fs::read(&exe)?, fs::read("test-data/project")?);
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert!(exe.metadata()?.permissions().mode() & 0o111 != 0);
+    }
+
+    Ok(())
+}
+
+async fn install_from_local_asset(path: &str, exe: Option<&str>) -> Result<()> {
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new().local_asset(path).install_dir(td.path());
+    if let Some(exe) = exe {
+        builder = builder.exe(exe);
+    }
+    builder.build()?.install_binary().await?;
+
+    assert_local_asset_installed(td.path())
+}
+
+#[test(tokio::test)]
+async fn install_from_file_url() -> Result<()> {
+    let url = Url::from_file_path(fs::canonicalize("test-data/project.tar.gz")?).unwrap();
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .url(url.as_str())
+        .install_dir(td.path())
+        .build()?;
+
+    let plan = ubi.install_plan().await?;
+    assert_eq!(plan.asset_name, "project.tar.gz");
+    assert_eq!(plan.asset_url, url);
+
+    ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())
+}
+
+#[test(tokio::test)]
+async fn install_as_is_from_local_wasm() -> Result<()> {
+    let td = tempdir()?;
+    let installed = UbiBuilder::new()
+        .local_asset("test-data/module.wasm")
+        .install_dir(td.path())
+        .install_as_is()
+        .build()?
+        .install_binary()
+        .await?;
+
+    let expect_path = td.path().join("module.wasm");
+    assert_eq!(installed.install_path, expect_path);
+    assert_eq!(fs::read(&expect_path)?, fs::read("test-data/module.wasm")?);
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&expect_path)?.permissions().mode();
+        assert_eq!(mode & 0o111, 0, "the installed file is not executable");
+    }
+    assert_eq!(
+        fs::read_dir(td.path())?.count(),
+        1,
+        "only one file was installed"
+    );
+
+    Ok(())
+}
+
+// A JSON file looks like an error page, but that check is skipped for a file installed as is.
+#[test(tokio::test)]
+async fn install_as_is_with_exact_asset_name() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(
+            r#"{"assets": [
+                {"url": "{url}/download/schema.json", "name": "schema.json"},
+                {"url": "{url}/download/project-Linux-x86_64.tar.gz", "name": "project-Linux-x86_64.tar.gz"}
+            ]}"#
+            .replace("{url}", &url),
+        )
+        .create_async()
+        .await;
+    let schema = r#"{"$schema": "https://json-schema.org/draft/2020-12/schema"}"#;
+    let download_mock = server
+        .mock("GET", "/download/schema.json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(schema)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .exact_asset_name("schema.json")
+        .rename_exe_to("project-schema.json")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .install_as_is()
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert_eq!(
+        fs::read_to_string(td.path().join("project-schema.json"))?,
+        schema,
+    );
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_from_local_asset_with_checksum() -> Result<()> {
+    let td = tempdir()?;
+    let asset_dir = td.path().join("downloads");
+    fs::create_dir(&asset_dir)?;
+    let asset = asset_dir.join("project.tar.gz");
+    fs::copy("test-data/project.tar.gz", &asset)?;
+    let checksum = format!("{:x}", Sha256::digest(fs::read(&asset)?));
+    fs::write(
+        asset_dir.join("project.tar.gz.sha256"),
+        format!("{checksum}  project.tar.gz\n"),
+    )?;
+
+    let install_dir = td.path().join("bin");
+    UbiBuilder::new()
+        .local_asset(&asset)
+        .install_dir(&install_dir)
+        .verify_checksum()
+        .build()?
+        .install_binary()
+        .await?;
+    assert_local_asset_installed(&install_dir)?;
+
+    fs::write(
+        asset_dir.join("project.tar.gz.sha256"),
+        format!("{}  project.tar.gz\n", "0".repeat(64)),
+    )?;
+    let other_install_dir = td.path().join("other-bin");
+    let res = UbiBuilder::new()
+        .local_asset(&asset)
+        .install_dir(&other_install_dir)
+        .verify_checksum()
+        .build()?
+        .install_binary()
+        .await;
+    assert!(res.is_err(), "checksum mismatch is an error");
+    assert!(!other_install_dir.exists(), "nothing was installed");
+
+    Ok(())
+}
+
+#[test]
+fn local_asset_that_does_not_exist() {
+    let err = UbiBuilder::new()
+        .local_asset("test-data/does-not-exist.tar.gz")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not find the local asset at test-data/does-not-exist.tar.gz",
+    );
+}
+
+// The project fixtures all contain an executable named `project` with 3 bytes of content.
+fn assert_local_asset_installed(install_dir: &std::path::Path) -> Result<()> {
+    let exe = install_dir.join(if cfg!(windows) {
+        "project.exe"
+    } else {
+        "project"
+    });
+    assert!(exe.is_file(), "{} was installed", exe.display());
+    let meta = exe.metadata()?;
+    assert_eq!(meta.len(), 3);
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert!(meta.permissions().mode() & 0o111 != 0);
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_plan_with_asset_selector() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let selector_seen = seen.clone();
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .asset_selector(move |assets| {
+            selector_seen
+                .lock()
+                .unwrap()
+                .extend(assets.iter().map(|a| a.name.clone()));
+            assets.iter().find(|a| a.name == "SHA256SUMS").cloned()
+        })
+        .build()?;
+    let plan = ubi.install_plan().await?;
+
+    assert_eq!(plan.asset_name, "SHA256SUMS");
+    assert_eq!(
+        plan.asset_url,
+        Url::parse(&format!("{url}/download/SHA256SUMS"))?,
+    );
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["project-Linux-x86_64.tar.gz", "SHA256SUMS"],
+        "the selector was called with every asset in the release",
+    );
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_plan_with_asset_selector_that_returns_none() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let called = Arc::new(Mutex::new(false));
+    let selector_called = called.clone();
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .asset_selector(move |_| {
+            *selector_called.lock().unwrap() = true;
+            None
+        })
+        .build()?;
+    let plan = ubi.install_plan().await?;
+
+    assert!(*called.lock().unwrap(), "the selector was called");
+    assert_eq!(
+        plan.asset_name, "project-Linux-x86_64.tar.gz",
+        "fell back to the default asset picking",
+    );
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_dry_run() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_for_dry_run(&mut server).await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .api_base_url(&url)
+        .dry_run()
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert!(!install_dir.exists(), "nothing was installed");
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+// The release info is fetched for a dry run, but the asset is never downloaded.
+async fn mock_release_for_dry_run(server: &mut Server) -> Vec<mockito::Mock> {
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &server.url());
+    vec![
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+            .expect(0)
+            .create_async()
+            .await,
+    ]
+}
+
+#[test(tokio::test)]
+async fn install_with_cache_not_modified() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("etag", r#""abc""#)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+    let not_modified_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("if-none-match", r#""abc""#)
+        .with_status(reqwest::StatusCode::NOT_MODIFIED.as_u16() as usize)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let exe = td.path().join("project");
+    for _ in 0..2 {
+        let mut ubi = UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path())
+            .api_base_url(&url)
+            .cache_downloads()
+            .build()?;
+        ubi.install_binary().await?;
+
+        assert!(td.path().join(".ubi-cache.json").is_file());
+        // This lets us check that the second install doesn't touch the file.
+        if fs::read(&exe)? == b"exe" {
+            fs::write(&exe, "old")?;
+        }
+    }
+
+    assert_eq!(fs::read(&exe)?, b"old", "the file was not reinstalled");
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+    not_modified_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_skip_if_exists() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = TAGGED_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let exe = td.path().join("project");
+    fs::write(&exe, "old")?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .skip_if_exists()
+        .build()?;
+    let result = ubi.install_binary().await?;
+
+    assert_eq!(fs::read(&exe)?, b"old", "the file was not reinstalled");
+    assert_eq!(result.tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(result.install_path, exe);
+    assert_eq!(result.sha256, None);
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+async fn mock_tagged_release(server: &mut Server, tag: &str) -> mockito::Mock {
+    let url = server.url();
+    server
+        .mock(
+            "GET",
+            format!("/repos/test/project/releases/tags/{tag}").as_str(),
+        )
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(
+            TAGGED_RELEASE_RESPONSE
+                .replace("{url}", &url)
+                .replace("v1.0.0", tag),
+        )
+        .expect(1)
+        .create_async()
+        .await
+}
+
+async fn ensure_install(
+    url: &str,
+    install_dir: &std::path::Path,
+    tag: &str,
+) -> Result<InstallResult> {
+    UbiBuilder::new()
+        .project(&format!("test/project@{tag}"))
+        .install_dir(install_dir)
+        .api_base_url(url)
+        .ensure()
+        .build()?
+        .install_binary()
+        .await
+}
+
+fn installed_tag(install_dir: &std::path::Path) -> Result<String> {
+    let meta: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(install_dir.join(".ubi-meta"))?)?;
+    Ok(meta["project"]["tag"]
+        .as_str()
+        .ok_or_else(|| anyhow!("no tag for project in {meta}"))?
+        .to_string())
+}
+
+#[test(tokio::test)]
+async fn install_with_ensure_records_the_tag() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = mock_tagged_release(&mut server, "v1.0.0").await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    let result = ensure_install(&url, td.path(), "v1.0.0").await?;
+
+    assert!(result.install_path.is_file());
+    assert_eq!(installed_tag(td.path())?, "v1.0.0");
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_ensure_at_the_installed_tag() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = mock_tagged_release(&mut server, "v1.0.0").await.expect(2);
+    // The asset is only downloaded by the first install.
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    ensure_install(&url, td.path(), "v1.0.0").await?;
+    let result = ensure_install(&url, td.path(), "v1.0.0").await?;
+
+    assert_eq!(result.tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(result.sha256, None, "nothing was downloaded");
+    assert_eq!(installed_tag(td.path())?, "v1.0.0");
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_ensure_at_a_new_tag() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let old_release_mock = mock_tagged_release(&mut server, "v1.0.0").await;
+    let new_release_mock = mock_tagged_release(&mut server, "v1.1.0").await;
+    let download_mock = mock_install_many_download(&mut server, &data)
+        .await
+        .expect(2);
+
+    let td = tempdir()?;
+    ensure_install(&url, td.path(), "v1.0.0").await?;
+    let result = ensure_install(&url, td.path(), "v1.1.0").await?;
+
+    assert_eq!(result.tag.as_deref(), Some("v1.1.0"));
+    assert!(result.sha256.is_some(), "the new release was downloaded");
+    assert_eq!(installed_tag(td.path())?, "v1.1.0");
+    old_release_mock.assert_async().await;
+    new_release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// Once the metadata file exists, an install without `ensure` keeps it up to date, so that a later
+// `ensure` doesn't think the old release is still installed.
+#[test(tokio::test)]
+async fn install_without_ensure_updates_the_install_meta() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let old_release_mock = mock_tagged_release(&mut server, "v1.0.0").await;
+    let new_release_mock = mock_tagged_release(&mut server, "v1.1.0").await;
+    let download_mock = mock_install_many_download(&mut server, &data)
+        .await
+        .expect(2);
+
+    let td = tempdir()?;
+    ensure_install(&url, td.path(), "v1.0.0").await?;
+    UbiBuilder::new()
+        .project("test/project@v1.1.0")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?
+        .install_binary()
+        .await?;
+
+    assert_eq!(installed_tag(td.path())?, "v1.1.0");
+    old_release_mock.assert_async().await;
+    new_release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_skip_if_exists_when_exe_is_missing() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &data, &data).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .skip_if_exists()
+        .build()?;
+    let result = ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())?;
+    assert!(result.sha256.is_some());
+    mocks[0].assert_async().await;
+    mocks[1].assert_async().await;
+
+    Ok(())
+}
+
+// For an archive, we only know that it's installed because of the download cache.
+#[test(tokio::test)]
+async fn install_with_skip_if_exists_and_extract_all() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("etag", r#""abc""#)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("project");
+    for _ in 0..2 {
+        let mut ubi = UbiBuilder::new()
+            .project("test/project")
+            .install_dir(&install_dir)
+            .api_base_url(&url)
+            .extract_all()
+            .cache_downloads()
+            .cache_file(td.path().join("cache.json"))
+            .skip_if_exists()
+            .build()?;
+        ubi.install_binary().await?;
+    }
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// With `force`, we don't send the cached `ETag`, so the server sends the asset again and it
+// overwrites the existing file.
+#[test(tokio::test)]
+async fn install_with_force() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("etag", r#""abc""#)
+        .with_body(&data)
+        .expect(2)
+        .create_async()
+        .await;
+    let not_modified_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("if-none-match", r#""abc""#)
+        .with_status(reqwest::StatusCode::NOT_MODIFIED.as_u16() as usize)
+        .expect(0)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let exe = td.path().join("project");
+    fs::write(&exe, "old")?;
+    for _ in 0..2 {
+        let mut ubi = UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path())
+            .api_base_url(&url)
+            .cache_downloads()
+            .force()
+            .build()?;
+        ubi.install_binary().await?;
+
+        assert_local_asset_installed(td.path())?;
+        fs::write(&exe, "old")?;
+    }
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+    not_modified_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn install_with_force_and_skip_if_exists() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .force()
+        .skip_if_exists()
+        .build();
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "You cannot set both force and skip_if_exists"
+    );
+}
+
+#[test(tokio::test)]
+async fn install_with_stage_dir() -> Result<()> {
+    install_with_stage_dir_for(false).await
+}
+
+#[test(tokio::test)]
+async fn install_with_stage_dir_and_extract_all() -> Result<()> {
+    install_with_stage_dir_for(true).await
+}
+
+async fn install_with_stage_dir_for(extract_all: bool) -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let stage_dir = td.path().join("stage");
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .stage_dir(&stage_dir)
+        .api_base_url(&url);
+    if extract_all {
+        builder = builder.extract_all();
+    }
+    let mut ubi = builder.build()?;
+    assert_eq!(ubi.install_dir(), stage_dir);
+    let res = ubi.install_binary().await?;
+
+    // With `extract_all`, the archive's top-level directory is stripped, so the executable is
+    // staged as `bin/project`.
+    let (expect_install_path, expect_staged_path, staged_exe) = if extract_all {
+        (
+            install_dir.clone(),
+            stage_dir.clone(),
+            stage_dir.join("bin").join("project"),
+        )
+    } else {
+        (
+            install_dir.join("project"),
+            stage_dir.join("project"),
+            stage_dir.join("project"),
+        )
+    };
+    assert_eq!(res.install_path, expect_install_path);
+    assert_eq!(res.staged_path, Some(expect_staged_path));
+    assert!(res.sha256.is_some());
+    assert!(staged_exe.is_file());
+    assert!(!install_dir.exists(), "nothing was installed");
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test_case(|b| b.dry_run() ; "dry_run")]
+#[test_case(|b| b.skip_if_exists() ; "skip_if_exists")]
+#[test_case(|b| b.cache_downloads() ; "cache_downloads")]
+#[test_case(|b| b.cache_file("ubi.json") ; "cache_file")]
+fn install_with_stage_dir_and_incompatible_option(f: fn(UbiBuilder) -> UbiBuilder) {
+    let res = f(UbiBuilder::new().project("test/project").stage_dir("stage")).build();
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "You cannot set a stage_dir with dry_run, skip_if_exists, cache_downloads, or cache_file",
+    );
+}
+
+#[test_case(
+    1,
+    &["README.md", "bin/project", "share/doc/project.md"],
+    &["project-1.2.3"] ;
+    "strip 1 component"
+)]
+#[test_case(
+    2,
+    &["project", "doc/project.md"],
+    &["README.md", "bin", "share"] ;
+    "strip 2 components"
+)]
+#[tokio::test]
+async fn install_with_extract_all_and_strip_components(
+    strip_components: usize,
+    expect_files: &[&str],
+    expect_missing: &[&str],
+) -> Result<()> {
+    let data = fs::read("test-data/project-nested.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("project");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .extract_all()
+        .strip_components(strip_components)
+        .api_base_url(&url)
+        .build()?;
+    let res = ubi.install_binary().await?;
+    assert_eq!(res.install_path, install_dir);
+    for file in expect_files {
+        assert!(install_dir.join(file).is_file(), "{file} was extracted");
+    }
+    for path in expect_missing {
+        assert!(!install_dir.join(path).exists(), "{path} was not extracted");
+    }
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn strip_components_without_extract_all() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .strip_components(1)
+        .build();
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "You cannot set strip_components without enabling extract_all",
+    );
+}
+
+#[test(tokio::test)]
+async fn install_with_completions_dir() -> Result<()> {
+    let data = fs::read("test-data/project-with-completions.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let completions_dir = td.path().join("share").join("completions");
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(&install_dir)
+        .completions_dir(&completions_dir)
+        .api_base_url(&url)
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert!(install_dir.join("project").is_file());
+    for path in ["bash/project.bash", "fish/project.fish", "zsh/_project"] {
+        assert!(completions_dir.join(path).is_file(), "{path} was installed");
+    }
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test_case(|b| b.extract_all() ; "extract_all")]
+#[test_case(|b| b.all_exes() ; "all_exes")]
+#[test_case(|b| b.stage_dir("stage") ; "stage_dir")]
+fn install_with_completions_dir_and_incompatible_option(f: fn(UbiBuilder) -> UbiBuilder) {
+    let res = f(UbiBuilder::new()
+        .project("test/project")
+        .completions_dir("completions"))
+    .build();
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "You cannot set a completions_dir with extract_all, all_exes, or stage_dir",
+    );
+}
+
+const BARE_EXE_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-linux-amd64",
+      "name": "project-linux-amd64"
+    }
+  ]
+}"#;
+
+#[test_case("elf-x86_64", None ; "matching architecture")]
+#[test_case(
+    "elf-aarch64",
+    Some("the executable project is built for aarch64, but the target platform is x86_64") ;
+    "different architecture"
+)]
+#[tokio::test]
+async fn install_with_verify_binary_arch(fixture: &str, expect_err: Option<&str>) -> Result<()> {
+    let data = fs::read(format!("test-data/binary-arch/{fixture}"))?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(BARE_EXE_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-linux-amd64")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .create_async()
+        .await;
+
+    let platform = Platform::find("x86_64-unknown-linux-gnu").unwrap();
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .platform(platform)
+        .verify_binary_arch()
+        .api_base_url(&url)
+        .build()?;
+    let res = ubi.install_binary().await;
+    match expect_err {
+        None => {
+            res?;
+            assert!(td.path().join("project").is_file());
+        }
+        Some(expect_err) => {
+            assert_eq!(res.unwrap_err().to_string(), expect_err);
+            assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+        }
+    }
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test_case(|b| b.extract_all() ; "extract_all")]
+#[test_case(|b| b.all_exes() ; "all_exes")]
+fn verify_binary_arch_with_incompatible_option(f: fn(UbiBuilder) -> UbiBuilder) {
+    let res = f(UbiBuilder::new()
+        .project("test/project")
+        .verify_binary_arch())
+    .build();
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "You cannot set verify_binary_arch with extract_all or all_exes",
+    );
+}
+
+const SPLIT_ASSET_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz.002",
+      "name": "project-Linux-x86_64.tar.gz.002"
+    },
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz.001",
+      "name": "project-Linux-x86_64.tar.gz.001"
+    },
+    {
+      "url": "{url}/download/project-Windows-x86_64.zip.001",
+      "name": "project-Windows-x86_64.zip.001"
+    }
+  ]
+}"#;
+
+#[test(tokio::test)]
+async fn install_split_asset() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let (first, second) = data.split_at(data.len() / 2);
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(SPLIT_ASSET_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let mut part_mocks = vec![];
+    for (part, body) in [("001", first), ("002", second)] {
+        part_mocks.push(
+            server
+                .mock(
+                    "GET",
+                    format!("/download/project-Linux-x86_64.tar.gz.{part}").as_str(),
+                )
+                .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+                .with_body(body)
+                .expect(1)
+                .create_async()
+                .await,
+        );
+    }
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .platform(Platform::find("x86_64-unknown-linux-gnu").unwrap())
+        .api_base_url(&url)
+        .build()?;
+    let res = ubi.install_binary().await?;
+    assert_eq!(res.asset_name, "project-Linux-x86_64.tar.gz.001");
+    assert_eq!(res.sha256, Some(format!("{:x}", Sha256::digest(&data))));
+    assert!(td.path().join("project").is_file());
+
+    release_mock.assert_async().await;
+    for m in part_mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_split_asset_with_missing_part() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(
+            SPLIT_ASSET_RELEASE_RESPONSE
+                .replace("{url}", &url)
+                .replace(".tar.gz.002", ".tar.gz.003"),
+        )
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", Matcher::Regex("^/download/".to_string()))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .platform(Platform::find("x86_64-unknown-linux-gnu").unwrap())
+        .api_base_url(&url)
+        .build()?;
+    let err = ubi.install_binary().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the release is missing part 002 of the split asset project-Linux-x86_64.tar.gz, it has project-Linux-x86_64.tar.gz.001, project-Linux-x86_64.tar.gz.003",
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+const SOURCE_ARCHIVES_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-1.2.3-src.tar.gz",
+      "name": "project-1.2.3-src.tar.gz"
+    },
+    {
+      "url": "{url}/download/project-1.2.3-src.zip",
+      "name": "project-1.2.3-src.zip"
+    }
+  ]
+}"#;
+
+#[test(tokio::test)]
+async fn install_from_release_with_only_source_archives() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(SOURCE_ARCHIVES_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", Matcher::Regex("^/download/".to_string()))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "no platform binary found, only source archives are available from project-1.2.3-src.tar.gz, project-1.2.3-src.zip",
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_cache_when_installed_file_is_missing() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("etag", r#""abc""#)
+        .with_body(&data)
+        .expect(2)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let cache_file = td.path().join("cache").join("ubi.json");
+    let exe = td.path().join("bin").join("project");
+    for _ in 0..2 {
+        let mut ubi = UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path().join("bin"))
+            .api_base_url(&url)
+            .cache_file(&cache_file)
+            .build()?;
+        ubi.install_binary().await?;
+
+        assert!(cache_file.is_file());
+        assert!(exe.is_file());
+        fs::remove_file(&exe)?;
+    }
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_retries() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let (release_mock, unavailable_mock) = mock_unavailable_download(&mut server, 2).await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .retry_base_delay(Duration::from_millis(1))
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert!(td.path().join("project").is_file());
+    release_mock.assert_async().await;
+    unavailable_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_retries_exhausted() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    // We make the initial request and then retry it twice.
+    let (release_mock, unavailable_mock) = mock_unavailable_download(&mut server, 3).await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .max_retries(2)
+        .retry_base_delay(Duration::from_millis(1))
+        .build()?;
+    let res = ubi.install_binary().await;
+    assert!(res.is_err());
+    let err = res.unwrap_err().to_string();
+    assert!(err.contains("503 Service Unavailable"), "{err}");
+
+    release_mock.assert_async().await;
+    unavailable_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_from_oci_registry() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let digest = format!("sha256:{:x}", Sha256::digest(&data));
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let manifest = OCI_MANIFEST_RESPONSE.replace("{digest}", &digest);
+    let manifest_mock = server
+        .mock("GET", "/v2/test/project/manifests/v1.0.0")
+        .match_header(
+            ACCEPT.as_str(),
+            Matcher::Regex("application/vnd.oci.image.manifest.v1\\+json".to_string()),
+        )
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .with_body(manifest)
+        .expect(1)
+        .create_async()
+        .await;
+    let blob_mock = server
+        .mock("GET", format!("/v2/test/project/blobs/{digest}").as_str())
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("ghcr.io/test/project:v1.0.0")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let result = ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())?;
+    assert_eq!(result.tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(result.asset_name, "project-Linux-x86_64.tar.gz");
+    manifest_mock.assert_async().await;
+    blob_mock.assert_async().await;
+
+    Ok(())
+}
+
+const OCI_MANIFEST_RESPONSE: &str = r#"
+{
+  "schemaVersion": 2,
+  "mediaType": "application/vnd.oci.image.manifest.v1+json",
+  "artifactType": "application/vnd.example.binary",
+  "config": {
+    "mediaType": "application/vnd.oci.empty.v1+json",
+    "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a",
+    "size": 2
+  },
+  "layers": [
+    {
+      "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+      "digest": "{digest}",
+      "size": 181,
+      "annotations": {
+        "org.opencontainers.image.title": "project-Linux-x86_64.tar.gz"
+      }
+    }
+  ]
+}
+"#;
+
+#[test(tokio::test)]
+async fn install_with_max_download_rate() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    // We don't check the checksums mock, since we don't verify the checksum.
+    let _mocks = mock_release_with_checksums(&mut server, &data, &data).await;
+
+    // The rate limiter lets through one second's worth of bytes right away, so the rest of the
+    // download should take at least this long.
+    let rate = 100;
+    let floor = Duration::from_secs_f64((data.len() as f64 - rate as f64) / rate as f64);
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .max_download_rate(rate)
+        .build()?;
+    let start = Instant::now();
+    ubi.install_binary().await?;
+    let elapsed = start.elapsed();
+
+    assert_local_asset_installed(td.path())?;
+    assert!(
+        elapsed >= floor,
+        "downloading {} bytes at {rate} bytes per second took {elapsed:?}, which is less than {floor:?}",
+        data.len(),
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_resumed_download() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let cut = data.len() / 2;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let (release_mock, truncated_mock) = mock_truncated_download(&mut server, &data, true).await;
+    let range_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(RANGE.as_str(), format!("bytes={cut}-").as_str())
+        .match_header(IF_RANGE.as_str(), r#""v1""#)
+        .with_status(reqwest::StatusCode::PARTIAL_CONTENT.as_u16() as usize)
+        .with_header(
+            CONTENT_RANGE.as_str(),
+            &format!("bytes {cut}-{}/{}", data.len() - 1, data.len()),
+        )
+        .with_body(&data[cut..])
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .retry_base_delay(Duration::from_millis(1))
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())?;
+    release_mock.assert_async().await;
+    truncated_mock.assert_async().await;
+    range_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_download_restarted_when_server_ignores_range() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let cut = data.len() / 2;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let (release_mock, truncated_mock) = mock_truncated_download(&mut server, &data, true).await;
+    let full_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(RANGE.as_str(), format!("bytes={cut}-").as_str())
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .retry_base_delay(Duration::from_millis(1))
+        .keep_archive()
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())?;
+    assert_eq!(
+        fs::read(td.path().join("project-Linux-x86_64.tar.gz"))?,
+        data,
+        "the partial download was replaced by the full download",
+    );
+    release_mock.assert_async().await;
+    truncated_mock.assert_async().await;
+    full_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_download_restarted_without_accept_ranges() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let (release_mock, truncated_mock) = mock_truncated_download(&mut server, &data, false).await;
+    let full_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(RANGE.as_str(), Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .retry_base_delay(Duration::from_millis(1))
+        .build()?;
+    ubi.install_binary().await?;
+
+    assert_local_asset_installed(td.path())?;
+    release_mock.assert_async().await;
+    truncated_mock.assert_async().await;
+    full_mock.assert_async().await;
+
+    Ok(())
+}
+
+// The download mock sends the first half of the file and then aborts the connection. It only
+// matches a request without a `Range` header, so that the test can add a mock for the next request.
+async fn mock_truncated_download(
+    server: &mut Server,
+    data: &[u8],
+    accept_ranges: bool,
+) -> (mockito::Mock, mockito::Mock) {
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &server.url());
+    let first_half = data[..data.len() / 2].to_vec();
+    let mut download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(RANGE.as_str(), Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_header(ETAG.as_str(), r#""v1""#)
+        .with_chunked_body(move |w| {
+            w.write_all(&first_half)?;
+            w.flush()?;
+            // If the connection is reset before the client reads the first half, the client's OS
+            // may throw that data away, and the client would have nothing to resume from.
+            thread::sleep(Duration::from_millis(100));
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset",
+            ))
+        })
+        .expect(1);
+    if accept_ranges {
+        download_mock = download_mock.with_header(ACCEPT_RANGES.as_str(), "bytes");
+    }
+    (
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        download_mock.create_async().await,
+    )
+}
+
+async fn mock_unavailable_download(
+    server: &mut Server,
+    times: usize,
+) -> (mockito::Mock, mockito::Mock) {
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &server.url());
+    (
+        server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+            .with_status(reqwest::StatusCode::SERVICE_UNAVAILABLE.as_u16() as usize)
+            .expect(times)
+            .create_async()
+            .await,
+    )
+}
+
+#[derive(Debug, Default)]
+struct RecordedProgress {
+    total: Option<Option<u64>>,
+    downloaded: Vec<u64>,
+    finished: bool,
+}
+
+#[derive(Debug, Default)]
+struct RecordingProgress(Arc<Mutex<RecordedProgress>>);
+
+impl DownloadProgress for RecordingProgress {
+    fn start(&self, total: Option<u64>) {
+        self.0.lock().unwrap().total = Some(total);
+    }
+
+    fn advance(&self, downloaded: u64) {
+        self.0.lock().unwrap().downloaded.push(downloaded);
+    }
+
+    fn finish(&self) {
+        self.0.lock().unwrap().finished = true;
+    }
+}
+
+#[test(tokio::test)]
+async fn install_with_download_progress() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_release_with_checksums(&mut server, &data, &data).await;
+
+    let recorded = Arc::new(Mutex::new(RecordedProgress::default()));
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .download_progress(Box::new(RecordingProgress(recorded.clone())))
+        .build()?;
+    ubi.install_binary().await?;
+
+    {
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.total, Some(Some(data.len() as u64)));
+        assert_eq!(recorded.downloaded.last(), Some(&(data.len() as u64)));
+        assert!(recorded.finished);
+    }
+    // The checksums file is in the release, but we don't download it unless we're verifying
+    // checksums.
+    mocks[0].assert_async().await;
+    mocks[1].assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_download_progress_without_content_length() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = CHECKSUMS_RELEASE_RESPONSE.replace("{url}", &url);
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(ACCEPT.as_str(), "application/json")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .create_async()
+        .await;
+    let chunked = data.clone();
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_chunked_body(move |w| w.write_all(&chunked))
+        .create_async()
+        .await;
+
+    let recorded = Arc::new(Mutex::new(RecordedProgress::default()));
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .download_progress(Box::new(RecordingProgress(recorded.clone())))
+        .build()?;
+    ubi.install_binary().await?;
+
+    {
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.total, Some(None));
+        assert_eq!(recorded.downloaded.last(), Some(&(data.len() as u64)));
+        assert!(recorded.finished);
+    }
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// When the server doesn't send a `Content-Length`, the progress bar's total comes from the asset's
+// size in the release info. The size is also included in the install result.
+#[test(tokio::test)]
+async fn install_with_download_progress_from_asset_size() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release = format!(
+        r#"{{"assets": [{{"url": "{url}/download/project-Linux-x86_64.tar.gz", "name": "project-Linux-x86_64.tar.gz", "size": {}}}]}}"#,
+        data.len(),
+    );
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(release)
+        .create_async()
+        .await;
+    let chunked = data.clone();
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_chunked_body(move |w| w.write_all(&chunked))
+        .create_async()
+        .await;
+
+    let recorded = Arc::new(Mutex::new(RecordedProgress::default()));
+    let td = tempdir()?;
+    let result = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .download_progress(Box::new(RecordingProgress(recorded.clone())))
+        .build()?
+        .install_binary()
+        .await?;
+
+    assert_eq!(result.asset_size, Some(data.len() as u64));
+    assert_eq!(
+        recorded.lock().unwrap().total,
+        Some(Some(data.len() as u64))
+    );
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_many_bounds_concurrency() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+    // Each mockito server runs on its own thread, so sleeping in one server's body callback does
+    // not block the others. This lets us count how many release info requests overlap.
+    let mut servers = vec![];
+    let mut mocks = vec![];
+    for _ in 0..4 {
+        let mut server = Server::new_async().await;
+        let release = INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &server.url());
+        let in_flight = in_flight.clone();
+        let max_in_flight = max_in_flight.clone();
+        mocks.push(
+            server
+                .mock("GET", "/repos/test/project/releases/latest")
+                .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+                .with_body_from_request(move |_| {
+                    let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(n, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(200));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    release.clone().into_bytes()
+                })
+                .expect(1)
+                .create_async()
+                .await,
+        );
+        mocks.push(mock_install_many_download(&mut server, &data).await);
+        servers.push(server);
+    }
+
+    let urls = servers.iter().map(|s| s.url()).collect::<Vec<_>>();
+    let tds = (0..4).map(|_| tempdir()).collect::<Result<Vec<_>, _>>()?;
+    let specs = urls
+        .iter()
+        .zip(&tds)
+        .map(|(url, td)| {
+            InstallSpec::new("test/project")
+                .install_dir(td.path())
+                .builder(UbiBuilder::new().api_base_url(url))
+        })
+        .collect::<Vec<_>>();
+
+    let results = install_many(specs, 2).await;
+
+    assert_eq!(results.len(), 4);
+    for (res, td) in results.into_iter().zip(&tds) {
+        let installed = res?;
+        assert_eq!(installed.project, "test/project");
+        assert_eq!(installed.asset_name, "project-Linux-x86_64.tar.gz");
+        assert!(td.path().join("project").is_file());
+    }
+    assert_eq!(
+        max_in_flight.load(Ordering::SeqCst),
+        2,
+        "release info requests were made concurrently, but no more than 2 at a time",
+    );
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_many_isolates_failures() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut good_server1 = Server::new_async().await;
+    let good_mocks1 = mock_install_many_release(&mut good_server1, &data).await;
+    let mut bad_server = Server::new_async().await;
+    let bad_mock = bad_server
+        .mock("GET", "/repos/test/missing/releases/latest")
+        .with_status(reqwest::StatusCode::NOT_FOUND.as_u16() as usize)
+        .create_async()
+        .await;
+    let mut good_server2 = Server::new_async().await;
+    let good_mocks2 = mock_install_many_release(&mut good_server2, &data).await;
+
+    let (good_url1, bad_url, good_url2) =
+        (good_server1.url(), bad_server.url(), good_server2.url());
+    let (td1, bad_td, td2) = (tempdir()?, tempdir()?, tempdir()?);
+    let specs = vec![
+        InstallSpec::new("test/project")
+            .install_dir(td1.path())
+            .builder(UbiBuilder::new().api_base_url(&good_url1)),
+        InstallSpec::new("test/missing")
+            .install_dir(bad_td.path())
+            .builder(UbiBuilder::new().api_base_url(&bad_url)),
+        InstallSpec::new("test/project")
+            .tag("v1.0.0")
+            .install_dir(td2.path())
+            .builder(UbiBuilder::new().api_base_url(&good_url2)),
+    ];
+
+    let mut results = install_many(specs, 0).await.into_iter();
+
+    assert_eq!(
+        results.next().unwrap()?.install_path,
+        td1.path().join("project")
+    );
+    let err = results.next().unwrap().unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("could not install test/missing"),
+        "{err}",
+    );
+    assert_eq!(
+        fs::read_dir(bad_td.path())?.count(),
+        0,
+        "nothing was installed"
+    );
+    assert_eq!(
+        results.next().unwrap()?.install_path,
+        td2.path().join("project")
+    );
+    assert!(results.next().is_none());
+
+    for m in good_mocks1.into_iter().chain(good_mocks2).chain([bad_mock]) {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+async fn mock_install_many_release(server: &mut Server, data: &[u8]) -> Vec<mockito::Mock> {
+    let release = INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &server.url());
+    vec![
+        server
+            .mock(
+                "GET",
+                Matcher::Regex("^/repos/test/project/releases/(latest|tags/v1.0.0)$".to_string()),
+            )
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(release)
+            .expect(1)
+            .create_async()
+            .await,
+        mock_install_many_download(server, data).await,
+    ]
+}
+
+async fn mock_install_many_download(server: &mut Server, data: &[u8]) -> mockito::Mock {
+    server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await
+}
+
+#[test(tokio::test)]
+async fn install_with_custom_headers() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header("X-Internal-Auth", "secret")
+        .match_header(AUTHORIZATION.as_str(), "Bearer token")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("X-Internal-Auth", "secret")
+        .match_header(AUTHORIZATION.as_str(), "Bearer token")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .token("token")
+        .header("X-Internal-Auth", "secret")
+        // The forge token's header takes priority over this one.
+        .header("Authorization", "Bearer not-the-token")
+        .allow_reserved_headers()
+        .build()?;
+    ubi.install_binary().await?;
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// A GitLab CI job token is sent in its own header, so it can be used with HTTP Basic auth, which is
+// sent in the `Authorization` header.
+#[test(tokio::test)]
+#[serial]
+async fn install_with_basic_auth_and_gitlab_job_token() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let vars = env::vars();
+    env::remove_var("GITLAB_TOKEN");
+    env::set_var("CI_JOB_TOKEN", "job-token");
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mut cdn_server = Server::new_async().await;
+    let release_mock = server
+        .mock("GET", "/projects/test%2Fproject/releases/permalink/latest")
+        .match_request(|req| {
+            has_one_header(req, HeaderName::from_static("job-token"), "job-token")
+                // This is "user:pass" encoded as base64.
+                && has_one_header(req, AUTHORIZATION, "Basic dXNlcjpwYXNz")
+        })
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(format!(
+            r#"{{"tag_name": "v1.0.0", "assets": {{"links": [{{"name": "project-Linux-x86_64.tar.gz", "url": "{}/download/project-Linux-x86_64.tar.gz"}}]}}}}"#,
+            cdn_server.url(),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = cdn_server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(AUTHORIZATION.as_str(), Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .forge(ForgeType::GitLab)
+        .api_base_url(&url)
+        .basic_auth("user", "pass")
+        .build();
+
+    env::remove_var("CI_JOB_TOKEN");
+    for (k, v) in vars {
+        env::set_var(k, v);
+    }
+
+    res?.install_binary().await?;
+    assert!(td.path().join("project").is_file());
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn basic_auth_with_token_in_authorization_header() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .token("token")
+        .basic_auth("user", "pass")
+        .build();
+    assert_eq!(
+        res.err().map(|e| e.to_string()),
+        Some(
+            "You cannot set basic_auth with a forge token that is sent in the Authorization header"
+                .to_string()
+        ),
+    );
+}
+
+// `match_header` checks that every value of a header matches, so it can't tell us whether a header
+// was sent more than once.
+fn has_one_header(req: &mockito::Request, name: HeaderName, value: &str) -> bool {
+    let values = req.header(name);
+    values.len() == 1 && values[0] == value
+}
+
+// GitHub redirects asset downloads to a storage host, which rejects requests that include the
+// token for the forge.
+#[test(tokio::test)]
+async fn install_with_redirect_to_another_host() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mut storage_server = Server::new_async().await;
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(AUTHORIZATION.as_str(), "Bearer token")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let redirect_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(AUTHORIZATION.as_str(), "Bearer token")
+        .with_status(reqwest::StatusCode::FOUND.as_u16() as usize)
+        .with_header(
+            "location",
+            &format!(
+                "{}/storage/project-Linux-x86_64.tar.gz",
+                storage_server.url()
+            ),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = storage_server
+        .mock("GET", "/storage/project-Linux-x86_64.tar.gz")
+        .match_header(AUTHORIZATION.as_str(), Matcher::Missing)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .token("token")
+        .build()?;
+    ubi.install_binary().await?;
+
+    release_mock.assert_async().await;
+    redirect_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// reqwest only removes a few headers on a redirect to a different host, so we make sure that the
+// headers it doesn't know about, like GitLab's `JOB-TOKEN` and the extra headers, aren't sent there.
+#[test(tokio::test)]
+#[serial]
+async fn install_with_gitlab_job_token_and_redirect_to_another_host() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let vars = env::vars();
+    env::remove_var("GITLAB_TOKEN");
+    env::set_var("CI_JOB_TOKEN", "job-token");
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mut storage_server = Server::new_async().await;
+    let release_mock = server
+        .mock("GET", "/projects/test%2Fproject/releases/permalink/latest")
+        .match_header("JOB-TOKEN", "job-token")
+        .match_header("X-Internal-Auth", "secret")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(format!(
+            r#"{{"tag_name": "v1.0.0", "assets": {{"links": [{{"name": "project-Linux-x86_64.tar.gz", "url": "{url}/download/project-Linux-x86_64.tar.gz"}}]}}}}"#
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let redirect_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header("JOB-TOKEN", "job-token")
+        .match_header("X-Internal-Auth", "secret")
+        .with_status(reqwest::StatusCode::FOUND.as_u16() as usize)
+        .with_header(
+            "location",
+            &format!(
+                "{}/storage/project-Linux-x86_64.tar.gz",
+                storage_server.url()
+            ),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = storage_server
+        .mock("GET", "/storage/project-Linux-x86_64.tar.gz")
+        .match_header("JOB-TOKEN", Matcher::Missing)
+        .match_header("X-Internal-Auth", Matcher::Missing)
+        .match_header(ACCEPT.as_str(), "application/octet-stream")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .forge(ForgeType::GitLab)
+        .api_base_url(&url)
+        .header("X-Internal-Auth", "secret")
+        .build();
+
+    env::remove_var("CI_JOB_TOKEN");
+    for (k, v) in vars {
+        env::set_var(k, v);
+    }
+
+    res?.install_binary().await?;
+    assert!(td.path().join("project").is_file());
+
+    release_mock.assert_async().await;
+    redirect_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn concurrent_installs_into_the_same_dir() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data)
+        .await
+        .expect(2);
+
+    let td = tempdir()?;
+    let new_ubi = || {
+        UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path())
+            .api_base_url(&url)
+            .build()
+    };
+    let (mut first, mut second) = (new_ubi()?, new_ubi()?);
+    let (first_res, second_res) = tokio::join!(first.install_binary(), second.install_binary());
+    first_res?;
+    second_res?;
+
+    assert_local_asset_installed(td.path())?;
+    assert_eq!(
+        fs::read_dir(td.path())?.count(),
+        1,
+        "only the exe was installed"
+    );
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// Whichever install takes the lock first holds it for longer than the other install will wait.
+#[test(tokio::test)]
+async fn concurrent_installs_are_serialized_by_the_lock() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(2)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_chunked_body(move |w| {
+            thread::sleep(Duration::from_secs(2));
+            w.write_all(&data)
+        })
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let new_ubi = || {
+        UbiBuilder::new()
+            .project("test/project")
+            .install_dir(td.path())
+            .api_base_url(&url)
+            .install_lock_timeout(Duration::from_secs(1))
+            .build()
+    };
+    let (mut first, mut second) = (new_ubi()?, new_ubi()?);
+    let (first_res, second_res) = tokio::join!(first.install_binary(), second.install_binary());
+    let err = match (first_res, second_res) {
+        (Ok(_), Err(e)) | (Err(e), Ok(_)) => e,
+        (first_res, second_res) => {
+            panic!("expected exactly one install to time out, got {first_res:?} and {second_res:?}")
+        }
+    };
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "timed out after 1s waiting for another ubi process to finish installing into {}",
+            td.path().display(),
+        ),
+    );
+
+    assert_local_asset_installed(td.path())?;
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn custom_header_with_reserved_name() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .header("Authorization", "Bearer token")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You cannot set the reserved `Authorization` header unless you call allow_reserved_headers",
+    );
+}
+
+#[test_case(None, &format!("ubi/{} (+https://github.com/houseabsolute/ubi)", crate::VERSION) ; "default user agent")]
+#[test_case(Some("my-tool/1.0"), "my-tool/1.0" ; "set with user_agent")]
+#[tokio::test]
+async fn install_with_user_agent(user_agent: Option<&str>, expect: &str) -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .match_header(USER_AGENT.as_str(), expect)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .match_header(USER_AGENT.as_str(), expect)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url);
+    if let Some(user_agent) = user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    builder.build()?.install_binary().await?;
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn invalid_user_agent() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .user_agent("my-tool\n1.0")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "invalid user agent `my-tool\n1.0`");
+}
+
+#[test(tokio::test)]
+async fn install_with_keep_archive() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = server
+        .mock("GET", "/download/project-Linux-x86_64.tar.gz")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(&data)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .keep_archive()
+        .build()?;
+    ubi.install_binary().await?;
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    assert_local_asset_installed(td.path())?;
+    let kept = td.path().join("project-Linux-x86_64.tar.gz");
+    assert!(kept.is_file(), "{} was kept", kept.display());
+    assert_eq!(fs::read(&kept)?, data);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_keep_archive_dir() -> Result<()> {
+    let td = tempdir()?;
+    let install_dir = td.path().join("bin");
+    let archive_dir = td.path().join("archives");
+    UbiBuilder::new()
+        .local_asset("test-data/project.tar.gz")
+        .install_dir(&install_dir)
+        .keep_archive_dir(&archive_dir)
+        .build()?
+        .install_binary()
+        .await?;
+
+    assert_local_asset_installed(&install_dir)?;
+    assert!(!install_dir.join("project.tar.gz").exists());
+    let kept = archive_dir.join("project.tar.gz");
+    assert!(kept.is_file(), "{} was kept", kept.display());
+    assert_eq!(fs::read(&kept)?, fs::read("test-data/project.tar.gz")?);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_bare_exe_with_keep_archive() -> Result<()> {
+    let td = tempdir()?;
+    let asset = td.path().join("project");
+    fs::copy("test-data/project", &asset)?;
+    let install_dir = td.path().join("bin");
+    UbiBuilder::new()
+        .local_asset(&asset)
+        .install_dir(&install_dir)
+        .keep_archive()
+        .build()?
+        .install_binary()
+        .await?;
+
+    assert_local_asset_installed(&install_dir)?;
+    assert_eq!(
+        fs::read_dir(&install_dir)?.count(),
+        1,
+        "only the executable is in the install dir",
+    );
+
+    Ok(())
+}
+
+const INSTALL_MANY_RELEASE_RESPONSE: &str = r#"
+{
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz",
+      "name": "project-Linux-x86_64.tar.gz"
+    }
+  ]
+}"#;
+
+const TAGGED_RELEASE_RESPONSE: &str = r#"
+{
+  "tag_name": "v1.0.0",
+  "assets": [
+    {
+      "url": "{url}/download/project-Linux-x86_64.tar.gz",
+      "name": "project-Linux-x86_64.tar.gz"
+    }
+  ]
+}"#;
+
+#[test(tokio::test)]
+async fn install_result_for_github_tagged_release() -> Result<()> {
+    install_result(ForgeType::GitHub, Some("v1.0.0")).await
+}
+
+#[test(tokio::test)]
+async fn install_result_for_github_latest_release() -> Result<()> {
+    install_result(ForgeType::GitHub, None).await
+}
+
+#[test(tokio::test)]
+async fn install_result_for_gitlab_latest_release() -> Result<()> {
+    install_result(ForgeType::GitLab, None).await
+}
+
+#[test(tokio::test)]
+async fn install_result_for_gitea_latest_release() -> Result<()> {
+    install_result(ForgeType::Gitea, None).await
+}
+
+// Each forge's release info includes the tag, so we know which tag the latest release has without
+// asking for it.
+async fn install_result(forge: ForgeType, tag: Option<&str>) -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let asset_url = format!("{url}/download/project-Linux-x86_64.tar.gz");
+    let (path, body) = match forge {
+        ForgeType::GitHub | ForgeType::Gitea => (
+            tag.map_or_else(
+                || "/repos/test/project/releases/latest".to_string(),
+                |t| format!("/repos/test/project/releases/tags/{t}"),
+            ),
+            if forge == ForgeType::GitHub {
+                TAGGED_RELEASE_RESPONSE.replace("{url}", &url)
+            } else {
+                format!(
+                    r#"{{"tag_name": "v1.0.0", "assets": [{{"name": "project-Linux-x86_64.tar.gz", "browser_download_url": "{asset_url}"}}]}}"#
+                )
+            },
+        ),
+        ForgeType::GitLab => (
+            tag.map_or_else(
+                || "/projects/test%2Fproject/releases/permalink/latest".to_string(),
+                |t| format!("/projects/test%2Fproject/releases/{t}"),
+            ),
+            format!(
+                r#"{{"tag_name": "v1.0.0", "assets": {{"links": [{{"name": "project-Linux-x86_64.tar.gz", "url": "{asset_url}"}}]}}}}"#
+            ),
+        ),
+        _ => unreachable!("this test only covers forges with releases"),
+    };
+    let release_mock = server
+        .mock("GET", path.as_str())
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(body)
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .forge(forge)
+        .api_base_url(&url);
+    if let Some(tag) = tag {
+        builder = builder.tag(tag);
+    }
+    let installed = builder.build()?.install_binary().await?;
+
+    assert_eq!(installed.tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(installed.asset_name, "project-Linux-x86_64.tar.gz");
+    assert_eq!(installed.asset_url, Url::parse(&asset_url)?);
+    assert_eq!(installed.install_path, td.path().join("project"));
+    assert_eq!(
+        installed.sha256,
+        Some(format!("{:x}", Sha256::digest(&data)))
+    );
+    assert!(installed.install_path.is_file());
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test_case("test/project@v1.0.0" ; "project name with tag")]
+#[test_case("https://github.com/test/project@v1.0.0" ; "project URL with tag")]
+#[tokio::test]
+async fn install_plan_with_tag_in_project(project: &str) -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/tags/v1.0.0")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(TAGGED_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project(project)
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let plan = ubi.install_plan().await?;
+    assert_eq!(plan.asset_name, "project-Linux-x86_64.tar.gz");
+
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_plan_with_version_req() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let list_mock = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(
+            r#"[
+                {"tag_name": "v2.0.0"},
+                {"tag_name": "v1.10.0-rc.1"},
+                {"tag_name": "v1.9.2"},
+                {"tag_name": "v1.10.0", "draft": true},
+                {"tag_name": "v1.2.3"},
+                {"tag_name": "nightly"}
+            ]"#,
+        )
+        .create_async()
+        .await;
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/tags/v1.9.2")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .version_req("^1.2")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let plan = ubi.install_plan().await?;
+    assert_eq!(plan.asset_name, "project-Linux-x86_64.tar.gz");
+
+    list_mock.assert_async().await;
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_plan_with_version_req_that_matches_nothing() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let list_mock = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(r#"[{"tag_name": "v2.0.0"}, {"tag_name": "v1.2.3"}]"#)
+        .create_async()
+        .await;
+
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .version_req(">=0.4, <0.6")
+        .api_base_url(&url)
+        .build()?;
+    let err = ubi.install_plan().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not find a release matching the version requirement `>=0.4, <0.6` in the list of tags: v2.0.0, v1.2.3",
+    );
+
+    list_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn version_req_conflicts() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .tag("v1.0.0")
+        .version_req("^1")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You cannot set a version_req with a tag, url, or local_asset",
+    );
+
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .version_req("not a version")
+        .build()
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("invalid version requirement `not a version`"),
+        "{err}",
+    );
+}
+
+// These are not in date order, so picking the first matching tag or the highest sorting tag would
+// give the wrong answer.
+const DATE_TAGGED_LIST_RESPONSE: &str = r#"[
+    {"tag_name": "nightly-2024-07-02", "published_at": "2024-07-02T03:00:00Z"},
+    {"tag_name": "release-2024-05", "published_at": "2024-05-30T12:00:00Z"},
+    {"tag_name": "release-2024-06", "published_at": "2024-06-28T12:00:00Z"},
+    {"tag_name": "release-2024-07-rc", "prerelease": true, "published_at": "2024-07-01T12:00:00Z"},
+    {"tag_name": "release-2024-04-hotfix", "published_at": "2024-06-29T12:00:00Z"},
+    {"tag_name": "release-2024-08", "draft": true, "published_at": null},
+    {"tag_name": "v1.0.0", "published_at": "2023-01-01T00:00:00Z"}
+]"#;
+
+#[test_case(r"^release-\d{4}-\d{2}$", false, "release-2024-06" ; "narrowed to monthly releases")]
+#[test_case(r"^release-", false, "release-2024-04-hotfix" ; "newest by date rather than by name")]
+#[test_case(r"^release-", true, "release-2024-07-rc" ; "with prereleases")]
+#[test_case(r"2024", false, "nightly-2024-07-02" ; "unanchored pattern")]
+#[tokio::test]
+async fn install_plan_with_tag_pattern(
+    tag_pattern: &str,
+    include_prereleases: bool,
+    expect_tag: &str,
+) -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let list_mock = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(DATE_TAGGED_LIST_RESPONSE)
+        .create_async()
+        .await;
+    let release_mock = server
+        .mock(
+            "GET",
+            format!("/repos/test/project/releases/tags/{expect_tag}").as_str(),
+        )
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .tag_pattern(tag_pattern)
+        .install_dir(td.path())
+        .api_base_url(&url);
+    if include_prereleases {
+        builder = builder.include_prereleases();
+    }
+    let plan = builder.build()?.install_plan().await?;
+    assert_eq!(plan.asset_name, "project-Linux-x86_64.tar.gz");
+
+    list_mock.assert_async().await;
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_plan_with_tag_pattern_that_matches_nothing() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let list_mock = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(r#"[{"tag_name": "release-2024-06"}, {"tag_name": "v1.2.3"}]"#)
+        .create_async()
+        .await;
+
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .tag_pattern("^release-2023-")
+        .api_base_url(&url)
+        .build()?;
+    let err = ubi.install_plan().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not find a release with a tag matching the pattern `^release-2023-` in the list of tags: release-2024-06, v1.2.3",
+    );
+
+    list_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test_case(
+    |b| b.tag("v1.0.0"),
+    "You cannot set a tag_pattern with a tag, version_req, url, or local_asset" ;
+    "with a tag"
+)]
+#[test_case(
+    |b| b.version_req("^1"),
+    "You cannot set a tag_pattern with a tag, version_req, url, or local_asset" ;
+    "with a version_req"
+)]
+#[test_case(
+    |b| b.tag_pattern("release-("),
+    "could not parse the tag pattern `release-(`" ;
+    "invalid regex"
+)]
+fn tag_pattern_conflicts(f: fn(UbiBuilder<'static>) -> UbiBuilder<'static>, expect: &str) {
+    let err = f(UbiBuilder::new()
+        .project("test/project")
+        .tag_pattern("^release-"))
+    .build()
+    .unwrap_err();
+    assert_eq!(err.to_string(), expect);
+}
+
+const PRERELEASE_LIST_RESPONSE: &str = r#"[
+    {"tag_name": "v2.0.0", "draft": true},
+    {"tag_name": "v1.4.0-rc.1", "prerelease": true},
+    {"tag_name": "v1.3.0", "prerelease": true},
+    {"tag_name": "v1.2.0"},
+    {"tag_name": "v1.1.0"}
+]"#;
+
+#[test(tokio::test)]
+async fn version_req_excludes_prereleases_by_default() -> Result<()> {
+    install_plan_from_release_list(Some("^1"), false, "v1.2.0").await
+}
+
+#[test(tokio::test)]
+async fn version_req_with_include_prereleases() -> Result<()> {
+    install_plan_from_release_list(Some("^1"), true, "v1.4.0-rc.1").await
+}
+
+#[test(tokio::test)]
+async fn version_req_with_include_prereleases_respects_range() -> Result<()> {
+    install_plan_from_release_list(Some("~1.3"), true, "v1.3.0").await
+}
+
+#[test(tokio::test)]
+async fn include_prereleases_picks_newest_release() -> Result<()> {
+    install_plan_from_release_list(None, true, "v1.4.0-rc.1").await
+}
+
+async fn install_plan_from_release_list(
+    version_req: Option<&str>,
+    include_prereleases: bool,
+    expect_tag: &str,
+) -> Result<()> {
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let list_mock = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(PRERELEASE_LIST_RESPONSE)
+        .create_async()
+        .await;
+    let release_mock = server
+        .mock(
+            "GET",
+            format!("/repos/test/project/releases/tags/{expect_tag}").as_str(),
+        )
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url);
+    if let Some(req) = version_req {
+        builder = builder.version_req(req);
+    }
+    if include_prereleases {
+        builder = builder.include_prereleases();
+    }
+    let mut ubi = builder.build()?;
+    ubi.install_plan().await?;
+
+    list_mock.assert_async().await;
+    release_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn list_releases() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let m = server
+        .mock("GET", "/repos/test/project/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(PRERELEASE_LIST_RESPONSE)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let url = server.url();
+    let ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .build()?;
+    let releases = ubi.list_releases().await?;
+    assert_eq!(
+        releases
+            .iter()
+            .map(|r| (r.tag.as_str(), r.prerelease, r.draft))
+            .collect::<Vec<_>>(),
+        vec![
+            ("v2.0.0", false, true),
+            ("v1.4.0-rc.1", true, false),
+            ("v1.3.0", true, false),
+            ("v1.2.0", false, false),
+            ("v1.1.0", false, false),
+        ],
+    );
+
+    m.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn list_releases_with_url() -> Result<()> {
+    let td = tempdir()?;
+    let ubi = UbiBuilder::new()
+        .url("https://github.com/houseabsolute/precious/releases/download/v0.1.7/precious-Linux-x86_64-musl.tar.gz")
+        .install_dir(td.path())
+        .build()?;
+    let err = ubi.list_releases().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot list releases for a Ubi instance that was built with a url or local_asset",
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn list_releases_with_gitlab_url() -> Result<()> {
+    let mut server = Server::new_async().await;
+    let m = server
+        .mock("GET", "/api/v4/projects/test%2Fproject/releases")
+        .match_query(Matcher::Any)
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(r#"[{"tag_name": "v1.1.0"}, {"tag_name": "v1.0.0"}]"#)
+        .create_async()
+        .await;
+
+    let td = tempdir()?;
+    let url = server.url();
+    let ubi = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .gitlab_url(&url)
+        .build()?;
+    let releases = ubi.list_releases().await?;
+    assert_eq!(
+        releases.iter().map(|r| r.tag.as_str()).collect::<Vec<_>>(),
+        vec!["v1.1.0", "v1.0.0"],
+    );
+
+    m.assert_async().await;
+
+    Ok(())
+}
+
+#[test]
+fn gitlab_url_conflicts_with_other_forge() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .gitlab_url("https://gitlab.example.com")
+        .forge(ForgeType::GitHub)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You cannot set gitlab_url when the forge is GitHub",
+    );
+}
+
+#[test]
+fn include_prereleases_conflicts_with_tag() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .tag("v1.0.0")
+        .include_prereleases()
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You cannot set include_prereleases with a tag, url, or local_asset",
+    );
+}
+
+#[test]
+fn matching_regex_errors() {
+    let err = UbiBuilder::new()
+        .project("test/project")
+        .matching_regex("project-(")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not parse the matching regex `project-(`"
+    );
+
+    let err = UbiBuilder::new()
+        .url("https://example.com/project.tar.gz")
+        .matching_regex("project")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You cannot set a matching_regex with a url or local_asset",
+    );
+}
+
+#[test(tokio::test)]
+async fn install_from_manifest_writes_lockfile() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(TAGGED_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let manifest = Manifest::from_str(&format!(
+        "[[tools]]\nproject = \"test/project\"\napi_base_url = \"{url}\"\n"
+    ))?;
+    let td = tempdir()?;
+    let lockfile = install_from_manifest(&manifest, None, Some(td.path()), 0).await?;
+
+    assert!(td.path().join("project").is_file());
+    assert_eq!(lockfile.tools.len(), 1);
+    let locked = &lockfile.tools[0];
+    assert_eq!(locked.project, "test/project");
+    assert_eq!(locked.target, env!("TARGET"));
+    assert_eq!(locked.tag, "v1.0.0");
+    assert_eq!(locked.asset_name, "project-Linux-x86_64.tar.gz");
+    assert_eq!(locked.sha256, format!("{:x}", Sha256::digest(&data)));
+
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_from_lockfile_checks_digest() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_install_many_release(&mut server, &data).await;
+
+    let manifest = Manifest::from_str(&format!(
+        "[[tools]]\nproject = \"test/project\"\ntag = \"v1.0.0\"\napi_base_url = \"{url}\"\n"
+    ))?;
+    let lockfile = Lockfile::from_str(&format!(
+        r#"[[tools]]
+project = "test/project"
+target = "{}"
+tag = "v1.0.0"
+asset = "project-Linux-x86_64.tar.gz"
+sha256 = "{}"
+"#,
+        env!("TARGET"),
+        "0".repeat(64),
+    ))?;
+    let td = tempdir()?;
+    let err = install_from_lockfile(&manifest, &lockfile, Some(td.path()), 0)
+        .await
+        .unwrap_err();
+
+    let sha256 = format!("{:x}", Sha256::digest(&data));
+    assert!(
+        err.to_string().contains(&format!(
+            "the SHA-256 checksum for project-Linux-x86_64.tar.gz is {sha256}, but it should be {}",
+            "0".repeat(64),
+        )),
+        "{err}",
+    );
+    assert_eq!(fs::read_dir(td.path())?.count(), 0, "nothing was installed");
+
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_from_lockfile_with_matching_digest() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let mocks = mock_install_many_release(&mut server, &data).await;
+
+    // The manifest asks for the latest release, but the lockfile pins it to v1.0.0.
+    let manifest = Manifest::from_str(&format!(
+        "[[tools]]\nproject = \"test/project\"\napi_base_url = \"{url}\"\n"
+    ))?;
+    let lockfile = Lockfile::from_str(&format!(
+        r#"[[tools]]
+project = "test/project"
+target = "{}"
+tag = "v1.0.0"
+asset = "project-Linux-x86_64.tar.gz"
+sha256 = "{:x}"
+"#,
+        env!("TARGET"),
+        Sha256::digest(&data),
+    ))?;
+    let td = tempdir()?;
+    install_from_lockfile(&manifest, &lockfile, Some(td.path()), 0).await?;
+
+    assert!(td.path().join("project").is_file());
+    for m in mocks {
+        m.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_from_lockfile_without_entry() -> Result<()> {
+    let manifest = Manifest::from_str("[[tools]]\nproject = \"test/project\"\n")?;
+    let td = tempdir()?;
+    let err = install_from_lockfile(&manifest, &Lockfile::default(), Some(td.path()), 0)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "the lockfile has no entry for test/project on {}, so you need to install without using the lockfile to update it",
+            env!("TARGET"),
+        ),
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn install_with_post_install_hook() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    let called_with = Arc::new(Mutex::new(None));
+    let hook_called_with = called_with.clone();
+    let result = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .post_install(move |path| {
+            assert!(path.is_file(), "the hook is called after the install");
+            *hook_called_with.lock().unwrap() = Some(path.to_path_buf());
+            Ok(())
+        })
+        .build()?
+        .install_binary()
+        .await?;
+
+    assert_eq!(
+        called_with.lock().unwrap().as_deref(),
+        Some(result.install_path.as_path()),
+    );
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// When packaging, the executable is written into a tarball under the prefix, with its execute bit
+// kept, instead of being installed.
+#[cfg(target_family = "unix")]
+#[test_case(None, "usr/local/bin/project" ; "default prefix")]
+#[test_case(Some("/opt/project/bin"), "opt/project/bin/project" ; "custom prefix")]
+#[tokio::test]
+async fn install_with_package_output(prefix: Option<&str>, expect_path: &str) -> Result<()> {
+    use binstall_tar::Archive;
+    use flate2::read::GzDecoder;
+
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    let output = td.path().join("dist").join("project.tar.gz");
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .api_base_url(&url)
+        .package_output(&output);
+    if let Some(prefix) = prefix {
+        builder = builder.package_prefix(prefix);
+    }
+    let result = builder.build()?.install_binary().await?;
+
+    assert_eq!(result.install_path, output);
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    let mut archive = Archive::new(GzDecoder::new(fs::File::open(&output)?));
+    let mut found = false;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.path()?.to_string_lossy() != expect_path {
+            continue;
+        }
+        found = true;
+        assert!(entry.header().entry_type().is_file());
+        assert_ne!(
+            entry.header().mode()? & 0o111,
+            0,
+            "the executable in the package has its execute bit set",
+        );
+    }
+    assert!(found, "the package contains {expect_path}");
+
+    Ok(())
+}
+
+// When the hook fails, the install is rolled back to the executable that was there before, unless
+// we asked to keep it.
+#[test_case(false ; "roll back")]
+#[test_case(true ; "keep")]
+#[tokio::test]
+async fn install_with_failing_post_install_hook(keep: bool) -> Result<()> {
+    crate::test_case::init_logging();
+
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    let exe = td.path().join("project");
+    fs::write(&exe, "old")?;
+    let mut builder = UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .post_install(|_| Err(anyhow!("project --version exited with 1")));
+    if keep {
+        builder = builder.keep_install_on_post_install_failure();
+    }
+    let err = builder.build()?.install_binary().await.unwrap_err();
+
+    let expect = if keep {
+        "and the installed files were kept"
+    } else {
+        "so the install was rolled back"
+    };
+    assert_eq!(
+        format!("{err:#}"),
+        format!(
+            "the post-install hook failed for {}, {expect}: project --version exited with 1",
+            exe.display(),
+        ),
+    );
+    if keep {
+        assert_ne!(fs::read(&exe)?, b"old", "the new executable was kept");
+    } else {
+        assert_eq!(fs::read(&exe)?, b"old", "the old executable was restored");
+    }
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// Rolling back a fresh install removes the executable, since there was nothing there before.
+#[test(tokio::test)]
+async fn install_with_failing_post_install_hook_and_no_existing_install() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let release_mock = server
+        .mock("GET", "/repos/test/project/releases/latest")
+        .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+        .with_body(INSTALL_MANY_RELEASE_RESPONSE.replace("{url}", &url))
+        .expect(1)
+        .create_async()
+        .await;
+    let download_mock = mock_install_many_download(&mut server, &data).await;
+
+    let td = tempdir()?;
+    UbiBuilder::new()
+        .project("test/project")
+        .install_dir(td.path())
+        .api_base_url(&url)
+        .post_install(|_| Err(anyhow!("it failed")))
+        .build()?
+        .install_binary()
+        .await
+        .unwrap_err();
+
+    assert!(
+        fs::read_dir(td.path())?.next().is_none(),
+        "nothing is left in the install dir",
+    );
+    release_mock.assert_async().await;
+    download_mock.assert_async().await;
+
+    Ok(())
+}
+
+// The second install writes a new versioned executable and repoints the link, but leaves the first
+// version in place.
+#[cfg(target_family = "unix")]
+#[test(tokio::test)]
+async fn versioned_install() -> Result<()> {
+    let data = fs::read("test-data/project.tar.gz")?;
+
+    let mut server = Server::new_async().await;
+    let url = server.url();
+    let td = tempdir()?;
+    for tag in ["v1.0.0", "v1.1.0"] {
+        let release_mock = server
+            .mock(
+                "GET",
+                format!("/repos/test/project/releases/tags/{tag}").as_str(),
+            )
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(
+                TAGGED_RELEASE_RESPONSE
+                    .replace("{url}", &url)
+                    .replace("v1.0.0", tag),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let download_mock = mock_install_many_download(&mut server, &data).await;
+
+        let result = UbiBuilder::new()
+            .project("test/project")
+            .tag(tag)
+            .install_dir(td.path())
+            .api_base_url(&url)
+            .versioned_install()
+            .build()?
+            .install_binary()
+            .await?;
+
+        let version = tag.trim_start_matches('v');
+        let versioned = td.path().join(format!("project-{version}"));
+        assert!(versioned.is_file(), "{} was installed", versioned.display());
+        assert_eq!(result.install_path, td.path().join("project"));
+        assert_eq!(
+            fs::read_link(&result.install_path)?,
+            std::path::PathBuf::from(format!("project-{version}")),
+        );
+        release_mock.assert_async().await;
+        download_mock.assert_async().await;
+    }
+    assert!(td.path().join("project-1.0.0").is_file());
+
+    Ok(())
+}
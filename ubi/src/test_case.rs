@@ -1,4 +1,15 @@
-use std::sync::Once;
+use anyhow::Result;
+use minisign::KeyPair;
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    ServerConfig, ServerConnection, StreamOwned, SupportedProtocolVersion,
+};
+use std::{
+    io::{Cursor, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Once},
+    thread,
+};
 
 // Once https://github.com/d-e-s-o/test-log/issues/35 is fixed we can remove this code.
 pub(crate) fn init_logging() {
@@ -8,3 +19,66 @@ pub(crate) fn init_logging() {
         let _ = env_logger::builder().is_test(true).try_init();
     });
 }
+
+// Returns a base64-encoded minisign public key from a new key pair along with that key pair's
+// signature for `data`.
+pub(crate) fn minisign_sign(data: &[u8]) -> Result<(String, String)> {
+    let KeyPair { pk, sk } = KeyPair::generate_unencrypted_keypair()?;
+    let signature = minisign::sign(Some(&pk), &sk, Cursor::new(data), None, None)?;
+    Ok((pk.to_base64(), signature.into_string()))
+}
+
+// Returns the address of a server that accepts connections but never sends anything back. The
+// connections are held open until the test process exits.
+pub(crate) fn stalled_server() -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    thread::spawn(move || {
+        let mut streams = vec![];
+        for stream in listener.incoming() {
+            streams.push(stream);
+        }
+    });
+    Ok(addr)
+}
+
+// Returns the address of an HTTPS server that responds to every request with `ok`. Its certificate
+// is for `localhost` and `127.0.0.1`, and it is signed by the CA in `test-data/tls/ca.pem`. A
+// connection where the TLS handshake fails is dropped, and the server keeps accepting connections.
+pub(crate) fn tls_server() -> Result<SocketAddr> {
+    tls_server_with_versions(rustls::DEFAULT_VERSIONS)
+}
+
+// This is like `tls_server`, but the server only speaks the given TLS versions.
+pub(crate) fn tls_server_with_versions(
+    versions: &[&'static SupportedProtocolVersion],
+) -> Result<SocketAddr> {
+    let certs = CertificateDer::pem_file_iter("test-data/tls/server.pem")?
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_file("test-data/tls/server.key")?;
+    let config = Arc::new(
+        ServerConfig::builder_with_protocol_versions(versions)
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?,
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Ok(conn) = ServerConnection::new(config.clone()) else {
+                continue;
+            };
+            let mut tls = StreamOwned::new(conn, stream);
+            let mut buf = [0; 4096];
+            if tls.read(&mut buf).is_err() {
+                continue;
+            }
+            let _ = tls
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok");
+            tls.conn.send_close_notify();
+            let _ = tls.flush();
+        }
+    });
+    Ok(addr)
+}
@@ -0,0 +1,46 @@
+use reqwest::tls::Version;
+use strum::{AsRefStr, EnumString, VariantNames};
+
+/// The minimum TLS version to allow for connections. See
+/// [`UbiBuilder::min_tls_version`](crate::UbiBuilder::min_tls_version) for details.
+#[derive(AsRefStr, Clone, Copy, Debug, Default, EnumString, PartialEq, Eq, VariantNames)]
+pub enum TlsVersion {
+    /// TLS 1.2. This is the default.
+    #[strum(serialize = "1.2")]
+    #[default]
+    Tls1_2,
+    /// TLS 1.3.
+    #[strum(serialize = "1.3")]
+    Tls1_3,
+}
+
+impl TlsVersion {
+    pub(crate) fn reqwest_version(self) -> Version {
+        match self {
+            TlsVersion::Tls1_2 => Version::TLS_1_2,
+            TlsVersion::Tls1_3 => Version::TLS_1_3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case("1.2", TlsVersion::Tls1_2, Version::TLS_1_2)]
+    #[test_case("1.3", TlsVersion::Tls1_3, Version::TLS_1_3)]
+    fn tls_version(name: &str, expect: TlsVersion, expect_reqwest: Version) {
+        let version = TlsVersion::from_str(name).unwrap();
+        assert_eq!(version, expect);
+        assert_eq!(version.as_ref(), name);
+        assert_eq!(version.reqwest_version(), expect_reqwest);
+    }
+
+    #[test]
+    fn tls_version_default() {
+        assert_eq!(TlsVersion::default(), TlsVersion::Tls1_2);
+        assert!(TlsVersion::from_str("1.1").is_err());
+    }
+}
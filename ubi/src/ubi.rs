@@ -1,12 +1,39 @@
-use crate::{forge::Forge, installer::Installer, picker::AssetPicker};
-use anyhow::{anyhow, Result};
-use log::debug;
+use crate::{
+    cache::{CacheEntry, DownloadCache},
+    checksum,
+    forge::{Forge, Release, ReleaseAssets},
+    gpg::GpgVerifier,
+    install_lock::InstallLock,
+    install_meta::{InstallMeta, InstalledRelease},
+    installer::{self, Installer},
+    package::Package,
+    picker::{AssetPicker, AssetSelector},
+    post_install::{InstallBackup, PostInstallHook},
+    progress::DownloadProgress,
+    rate_limit::RateLimiter,
+    release_cache::ReleaseInfoCache,
+    retry::RetryPolicy,
+    signature::SignatureVerifier,
+    split_asset::{split_asset_part, split_asset_parts},
+    version::{self, VersionReq},
+};
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use itertools::Itertools;
+use log::{debug, info, warn};
+use regex::Regex;
 use reqwest::{
-    header::{HeaderValue, ACCEPT},
-    Client, StatusCode,
+    header::{HeaderValue, ACCEPT, ACCEPT_RANGES, CONTENT_RANGE, ETAG, IF_RANGE, RANGE},
+    Client, Response, StatusCode,
 };
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tempfile::{tempdir, TempDir};
 use url::Url;
 
@@ -16,15 +43,143 @@ use url::Url;
 pub struct Ubi<'a> {
     forge: Box<dyn Forge + Send + Sync>,
     asset_url: Option<Url>,
+    version_req: Option<VersionReq>,
+    tag_pattern: Option<Regex>,
+    include_prereleases: bool,
     asset_picker: AssetPicker<'a>,
+    asset_selector: Option<AssetSelector>,
     installer: Box<dyn Installer>,
+    // This is only set when staging. It's the path that the installer would use if it were
+    // installing into the install dir instead of the stage dir.
+    final_install_path: Option<PathBuf>,
+    package: Option<Package>,
+    signature_verifier: Option<SignatureVerifier>,
+    gpg_verifier: Option<GpgVerifier>,
+    verify_checksum: bool,
+    expect_sha256: Option<String>,
+    dry_run: bool,
+    force: bool,
+    skip_if_exists: bool,
+    ensure: bool,
+    download_cache: Option<DownloadCache>,
+    install_meta: Option<InstallMeta>,
+    release_info_cache: Option<ReleaseInfoCache>,
+    keep_archive_dir: Option<PathBuf>,
+    versioned_install: bool,
+    post_install: Option<PostInstallHook>,
+    keep_install_on_post_install_failure: bool,
+    install_lock_timeout: Duration,
+    retry_policy: RetryPolicy,
+    max_download_rate: Option<NonZeroU64>,
+    download_progress: Option<Box<dyn DownloadProgress>>,
     reqwest_client: Client,
+    release_assets: Option<ReleaseAssets>,
+    // This is the tag of the release that the picked asset came from, once we know it.
+    release_tag: Option<String>,
+}
+
+/// `InstallPlan` describes what [`Ubi::install_binary`] would do, without doing it. It is returned
+/// by [`Ubi::install_plan`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InstallPlan {
+    /// The name of the release asset that would be downloaded.
+    pub asset_name: String,
+    /// The URL that the release asset would be downloaded from.
+    pub asset_url: Url,
+    /// The size of the release asset in bytes, if the forge reports it. See [`Asset::size`].
+    pub asset_size: Option<u64>,
+    /// The path that would be installed. This is the path to the executable, unless
+    /// [`UbiBuilder::extract_all`](crate::UbiBuilder::extract_all) or
+    /// [`UbiBuilder::all_exes`](crate::UbiBuilder::all_exes) were called, in which case it is the
+    /// directory that files would be installed into. Note that if the asset has an extension such
+    /// as `.exe` or `.pyz` that is preserved on install, that extension is not included here.
+    pub install_path: PathBuf,
 }
 
+/// `InstallResult` describes what [`Ubi::install_binary`] installed. It is also returned for a dry
+/// run or when the release asset is already up to date, in which case it describes what is already
+/// installed. It serializes to a JSON object with a key for each field, which is what the `ubi` CLI
+/// prints with `--output json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InstallResult {
+    /// The tag of the release that was installed. When installing the latest release or a release
+    /// matching a version requirement, this is the tag that `ubi` resolved it to. This is `None` if
+    /// the release asset came from [`UbiBuilder::url`](crate::UbiBuilder::url) or
+    /// [`UbiBuilder::local_asset`](crate::UbiBuilder::local_asset), or if the forge does not have
+    /// releases, like Bitbucket, and no tag was given.
+    pub tag: Option<String>,
+    /// The name of the release asset that was installed.
+    pub asset_name: String,
+    /// The URL that the release asset was downloaded from.
+    pub asset_url: Url,
+    /// The size of the release asset in bytes, if the forge reports it. See [`Asset::size`].
+    pub asset_size: Option<u64>,
+    /// The path that was installed. See [`InstallPlan::install_path`] for details. If
+    /// [`UbiBuilder::stage_dir`](crate::UbiBuilder::stage_dir) was called, then nothing is
+    /// installed here. Instead, this is the path that the staged files should be moved to. If
+    /// [`UbiBuilder::package_output`](crate::UbiBuilder::package_output) was called, then this is
+    /// the path of the tarball that was written.
+    pub install_path: PathBuf,
+    /// The path that was staged, if [`UbiBuilder::stage_dir`](crate::UbiBuilder::stage_dir) was
+    /// called. This is the same path as `install_path`, but under the stage directory instead of
+    /// the install directory.
+    pub staged_path: Option<PathBuf>,
+    /// The SHA-256 digest of the downloaded release asset, as a lowercase hex string. This is
+    /// `None` if nothing was downloaded, because this was a dry run or the asset was up to date.
+    pub sha256: Option<String>,
+}
+
+/// `Asset` is a single downloadable file attached to a release. These are returned by
+/// [`Ubi::available_assets`].
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
-pub(crate) struct Asset {
-    pub(crate) name: String,
-    pub(crate) url: Url,
+pub struct Asset {
+    /// The name of the asset, like `precious-Linux-x86_64-musl.tar.gz`.
+    pub name: String,
+    /// The URL that the asset is downloaded from.
+    pub url: Url,
+    /// The size of the asset in bytes. This is `None` if the forge does not say how big its assets
+    /// are, which is the case for GitLab, SourceHut, and Azure DevOps, or if the asset came from
+    /// [`UbiBuilder::url`](crate::UbiBuilder::url).
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+// This is what we need to ask the server to continue a download that failed partway through.
+#[derive(Debug)]
+struct Resume {
+    offset: u64,
+    // If the server sent an `ETag`, we send it back in an `If-Range` header, so that the server
+    // sends the whole file instead of a range if the file has changed since the first request.
+    etag: Option<HeaderValue>,
+}
+
+// The contents of a downloaded asset, which are in a file when installing, and in memory for
+// `Ubi::download_only`.
+#[derive(Clone, Copy)]
+enum Contents<'a> {
+    File(&'a Path),
+    Bytes(&'a [u8]),
+}
+
+// A download is written to a file when installing, and to memory otherwise. When a retried download
+// has to start over, everything written so far is thrown away.
+trait DownloadSink: Write {
+    fn reset(&mut self) -> io::Result<()>;
+}
+
+impl DownloadSink for File {
+    fn reset(&mut self) -> io::Result<()> {
+        self.set_len(0)?;
+        self.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+}
+
+impl DownloadSink for Vec<u8> {
+    fn reset(&mut self) -> io::Result<()> {
+        self.clear();
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -37,19 +192,72 @@ pub(crate) struct Download {
 
 impl<'a> Ubi<'a> {
     /// Create a new Ubi instance.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         forge: Box<dyn Forge + Send + Sync>,
         asset_url: Option<Url>,
+        version_req: Option<VersionReq>,
+        tag_pattern: Option<Regex>,
+        include_prereleases: bool,
         asset_picker: AssetPicker<'a>,
+        asset_selector: Option<AssetSelector>,
         installer: Box<dyn Installer>,
+        final_install_path: Option<PathBuf>,
+        package: Option<Package>,
+        signature_verifier: Option<SignatureVerifier>,
+        gpg_verifier: Option<GpgVerifier>,
+        verify_checksum: bool,
+        expect_sha256: Option<String>,
+        dry_run: bool,
+        force: bool,
+        skip_if_exists: bool,
+        ensure: bool,
+        download_cache: Option<DownloadCache>,
+        install_meta: Option<InstallMeta>,
+        release_info_cache: Option<ReleaseInfoCache>,
+        keep_archive_dir: Option<PathBuf>,
+        versioned_install: bool,
+        post_install: Option<PostInstallHook>,
+        keep_install_on_post_install_failure: bool,
+        install_lock_timeout: Duration,
+        retry_policy: RetryPolicy,
+        max_download_rate: Option<NonZeroU64>,
+        download_progress: Option<Box<dyn DownloadProgress>>,
         reqwest_client: Client,
     ) -> Ubi<'a> {
         Ubi {
             forge,
             asset_url,
+            version_req,
+            tag_pattern,
+            include_prereleases,
             asset_picker,
+            asset_selector,
             installer,
+            final_install_path,
+            package,
+            signature_verifier,
+            gpg_verifier,
+            verify_checksum,
+            expect_sha256,
+            dry_run,
+            force,
+            skip_if_exists,
+            ensure,
+            download_cache,
+            install_meta,
+            release_info_cache,
+            keep_archive_dir,
+            versioned_install,
+            post_install,
+            keep_install_on_post_install_failure,
+            install_lock_timeout,
+            retry_policy,
+            max_download_rate,
+            download_progress,
             reqwest_client,
+            release_assets: None,
+            release_tag: None,
         }
     }
 
@@ -58,6 +266,12 @@ impl<'a> Ubi<'a> {
     /// executable name) in the unpacked archive and write it to the install directory. It will also
     /// set the executable bit on the installed binary on platforms where this is necessary.
     ///
+    /// If [`UbiBuilder::dry_run`](crate::UbiBuilder::dry_run) was called, then this stops after
+    /// picking the release asset and logs what it would have installed instead. The same goes for
+    /// [`UbiBuilder::skip_if_exists`](crate::UbiBuilder::skip_if_exists) when the executable is
+    /// already installed, and for [`UbiBuilder::ensure`](crate::UbiBuilder::ensure) when it is
+    /// already at the release's tag.
+    ///
     /// # Errors
     ///
     /// There are a number of cases where an error can be returned:
@@ -67,40 +281,905 @@ impl<'a> Ubi<'a> {
     ///   to increase these).
     /// * Unable to find the requested project.
     /// * Unable to find a match for the platform on which the code is running.
+    /// * Unable to find or verify the minisign or GPG signature for the downloaded file, if
+    ///   signature verification is enabled.
+    /// * Unable to find the checksum for the downloaded file, or the checksum does not match, if
+    ///   checksum verification is enabled.
     /// * Unable to unpack/uncompress the downloaded release file.
     /// * Unable to find an executable with the right name in a downloaded archive.
     /// * Unable to write the executable to the specified directory.
     /// * Unable to set executable permissions on the installed binary.
-    pub async fn install_binary(&mut self) -> Result<()> {
-        let asset = self.asset().await?;
-        let download = self.download_asset(&self.reqwest_client, asset).await?;
-        self.installer.install(&download)
+    pub async fn install_binary(&mut self) -> Result<InstallResult> {
+        if self.dry_run {
+            let plan = self.install_plan().await?;
+            info!(
+                "Would download {} from {} and install it into {}",
+                plan.asset_name,
+                plan.asset_url,
+                plan.install_path.display(),
+            );
+            return Ok(self.install_result(plan, None));
+        }
+
+        let (asset, all_assets) = self.asset_and_all_assets().await?;
+        let (checksum_asset, signature_asset, gpg_signature_asset) =
+            self.verification_assets(&asset, &all_assets)?;
+        // This is held until we return, so that another process installing into the same directory
+        // waits until we're done. That includes the check for an existing install.
+        let _lock =
+            InstallLock::acquire(self.installer.install_dir(), self.install_lock_timeout).await?;
+        let plan = InstallPlan {
+            asset_name: asset.name.clone(),
+            asset_url: asset.url.clone(),
+            asset_size: asset.size,
+            install_path: self.install_path(),
+        };
+
+        if self.skip_if_exists {
+            if let Some(path) = self.existing_install(&asset)? {
+                info!(
+                    "{} is already installed, so {} was not downloaded",
+                    path.display(),
+                    asset.name,
+                );
+                return Ok(self.install_result(
+                    InstallPlan {
+                        install_path: path,
+                        ..plan
+                    },
+                    None,
+                ));
+            }
+        }
+
+        if let Some(tag) = self.ensured_tag()? {
+            info!(
+                "{} is already at {tag}, so {} was not downloaded",
+                plan.install_path.display(),
+                asset.name,
+            );
+            return Ok(self.install_result(plan, None));
+        }
+
+        let asset_name = asset.name.clone();
+        let split_parts = match split_asset_part(&asset.name) {
+            Some((_, 1)) => Some(split_asset_parts(&asset, &all_assets)?),
+            _ => None,
+        };
+        let download = if let Some(parts) = split_parts {
+            Some((
+                self.download_split_asset(&self.reqwest_client, parts)
+                    .await?,
+                None,
+            ))
+        } else {
+            let cache_entry = if self.force {
+                debug!("not sending cached headers because this install is forced");
+                None
+            } else {
+                self.installed_cache_entry(&asset)?
+            };
+            self.download_asset(&self.reqwest_client, asset, cache_entry.as_ref())
+                .await?
+        };
+        let Some((download, new_cache_entry)) = download else {
+            info!("{asset_name} has not changed since it was last installed, so it is up to date");
+            return Ok(self.install_result(plan, None));
+        };
+        if plan.asset_url.scheme() != "file" && self.installer.expects_binary_or_archive() {
+            check_for_error_page(&asset_name, Contents::File(&download.archive_path))?;
+        }
+        // If verification fails, we return before installing anything. The downloaded file is in a
+        // temp dir which is deleted when `download` is dropped.
+        let sha256 = self
+            .verify_download(
+                &asset_name,
+                Contents::File(&download.archive_path),
+                checksum_asset,
+                signature_asset,
+                gpg_signature_asset,
+            )
+            .await?;
+
+        let backup = self.post_install_backup()?;
+        let mut installed = self.installer.install(&download)?;
+        let plan = if self.versioned_install {
+            let link = self.link_versioned_exe(&asset_name, &mut installed)?;
+            InstallPlan {
+                install_path: link,
+                ..plan
+            }
+        } else {
+            plan
+        };
+        self.run_post_install_hook(&installed, backup)?;
+        let plan = if let Some(package) = &self.package {
+            package.write()?;
+            InstallPlan {
+                install_path: package.output().to_path_buf(),
+                ..plan
+            }
+        } else {
+            plan
+        };
+        if let Some(dir) = &self.keep_archive_dir {
+            keep_archive(&download.archive_path, dir, &asset_name, &installed)?;
+        }
+        if let (Some(cache), Some(mut entry)) = (&self.download_cache, new_cache_entry) {
+            entry.installed = installed;
+            cache.store(&asset_name, entry)?;
+        }
+        self.store_install_meta(&asset_name)?;
+
+        let staged_path = self.final_install_path.is_some().then(|| {
+            let staged_path = self.installer.install_path().to_path_buf();
+            info!(
+                "Staged {} in {}, move it to {} to install it",
+                asset_name,
+                staged_path.display(),
+                plan.install_path.display(),
+            );
+            staged_path
+        });
+
+        Ok(InstallResult {
+            staged_path,
+            ..self.install_result(plan, Some(sha256))
+        })
+    }
+
+    /// Picks a release asset and downloads it into memory, without extracting or installing
+    /// anything. This returns the picked asset along with its contents. If checksum or signature
+    /// verification is enabled, the contents are verified before they are returned.
+    ///
+    /// Nothing is written to the filesystem, so the install dir, the download cache, and options
+    /// like [`UbiBuilder::dry_run`](crate::UbiBuilder::dry_run) and
+    /// [`UbiBuilder::skip_if_exists`](crate::UbiBuilder::skip_if_exists) are ignored.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Ubi::install_binary`], except for the errors from
+    /// extracting and installing the release asset.
+    pub async fn download_only(&mut self) -> Result<(Asset, Bytes)> {
+        let (asset, all_assets) = self.asset_and_all_assets().await?;
+        let (checksum_asset, signature_asset, gpg_signature_asset) =
+            self.verification_assets(&asset, &all_assets)?;
+
+        let contents = Bytes::from(
+            self.download_to_memory(&self.reqwest_client, &asset)
+                .await?,
+        );
+        if asset.url.scheme() != "file" && self.installer.expects_binary_or_archive() {
+            check_for_error_page(&asset.name, Contents::Bytes(&contents))?;
+        }
+        self.verify_download(
+            &asset.name,
+            Contents::Bytes(&contents),
+            checksum_asset,
+            signature_asset,
+            gpg_signature_asset,
+        )
+        .await?;
+
+        Ok((asset, contents))
+    }
+
+    // Returns the SHA-256 digest of the downloaded asset once all of the verification passes.
+    async fn verify_download(
+        &self,
+        asset_name: &str,
+        contents: Contents<'_>,
+        checksum_asset: Option<Asset>,
+        signature_asset: Option<Asset>,
+        gpg_signature_asset: Option<Asset>,
+    ) -> Result<String> {
+        let sha256 = match contents {
+            Contents::File(path) => checksum::sha256_hex(path)?,
+            Contents::Bytes(data) => checksum::sha256_hex_bytes(data),
+        };
+        if let Some(expect) = &self.expect_sha256 {
+            if sha256 != *expect {
+                return Err(anyhow!(
+                    "the SHA-256 checksum for {asset_name} is {sha256}, but it should be {expect}"
+                ));
+            }
+            debug!("the SHA-256 checksum for {asset_name} matches the expected checksum");
+        }
+        if let Some(checksum_asset) = checksum_asset {
+            let text = self
+                .download_text(&self.reqwest_client, &checksum_asset)
+                .await?;
+            let expect = checksum::expected_checksum(&text, asset_name, &checksum_asset.name)?;
+            match contents {
+                Contents::File(path) => checksum::verify(path, &expect, &checksum_asset.name)?,
+                Contents::Bytes(data) => {
+                    checksum::verify_bytes(asset_name, data, &expect, &checksum_asset.name)?;
+                }
+            }
+        }
+        if let (Some(verifier), Some(signature_asset)) = (&self.signature_verifier, signature_asset)
+        {
+            let signature = self
+                .download_text(&self.reqwest_client, &signature_asset)
+                .await?;
+            match contents {
+                Contents::File(path) => verifier.verify(path, &signature)?,
+                Contents::Bytes(data) => verifier.verify_bytes(asset_name, data, &signature)?,
+            }
+        }
+        if let (Some(verifier), Some(signature_asset)) = (&self.gpg_verifier, gpg_signature_asset) {
+            let signature = self
+                .download_bytes(&self.reqwest_client, &signature_asset)
+                .await?;
+            match contents {
+                Contents::File(path) => verifier.verify(path, &signature)?,
+                Contents::Bytes(data) => verifier.verify_bytes(asset_name, data, &signature)?,
+            }
+        }
+
+        Ok(sha256)
+    }
+
+    // The executable is the first installed path. It's replaced by the versioned executable and the
+    // link to it, so both are recorded in the download cache. Returns the path of the link.
+    fn link_versioned_exe(
+        &self,
+        asset_name: &str,
+        installed: &mut Vec<PathBuf>,
+    ) -> Result<PathBuf> {
+        let Some(tag) = &self.release_tag else {
+            return Err(anyhow!(
+                "could not do a versioned install of {asset_name} because its release has no tag"
+            ));
+        };
+        let exe = installed.remove(0);
+        let (versioned, link) = installer::link_versioned_exe(&exe, tag)?;
+        installed.splice(0..0, [versioned, link.clone()]);
+        Ok(link)
+    }
+
+    // If the post-install hook fails, we roll back the executable and, for a versioned install, the
+    // link to it. These are the paths they will be installed to, which we back up before
+    // installing. Other files installed along with the executable, like shell completions, are
+    // left alone.
+    fn post_install_backup(&self) -> Result<Option<InstallBackup>> {
+        if self.post_install.is_none() || self.keep_install_on_post_install_failure {
+            return Ok(None);
+        }
+        let exe = self
+            .installer
+            .existing_install()
+            .unwrap_or_else(|| self.installer.install_path().to_path_buf());
+        let mut paths = vec![exe.clone()];
+        if let (true, Some(tag)) = (self.versioned_install, &self.release_tag) {
+            paths.push(installer::versioned_exe_path(&exe, tag));
+            paths.push(installer::versioned_link_path(&exe));
+        }
+        Ok(Some(InstallBackup::new(&paths)?))
+    }
+
+    fn run_post_install_hook(
+        &self,
+        installed: &[PathBuf],
+        backup: Option<InstallBackup>,
+    ) -> Result<()> {
+        let Some(hook) = &self.post_install else {
+            return Ok(());
+        };
+        // For a versioned install, the first path is the versioned executable and the second is
+        // the link to it, which is what the hook is called with.
+        let exes = &installed[..if self.versioned_install { 2 } else { 1 }];
+        let path = exes
+            .last()
+            .expect("an install always installs an executable");
+        debug!("running the post-install hook for {}", path.display());
+        let Err(e) = hook.run(path) else {
+            return Ok(());
+        };
+
+        let Some(backup) = backup else {
+            return Err(e.context(format!(
+                "the post-install hook failed for {}, and the installed files were kept",
+                path.display(),
+            )));
+        };
+        backup.restore(exes).with_context(|| {
+            format!(
+                "the post-install hook failed for {} with `{e:#}`, and the install could not be rolled back",
+                path.display(),
+            )
+        })?;
+        info!(
+            "Rolled back the install of {} because the post-install hook failed",
+            path.display(),
+        );
+        Err(e.context(format!(
+            "the post-install hook failed for {}, so the install was rolled back",
+            path.display(),
+        )))
+    }
+
+    fn install_result(&self, plan: InstallPlan, sha256: Option<String>) -> InstallResult {
+        InstallResult {
+            tag: self.release_tag.clone(),
+            asset_name: plan.asset_name,
+            asset_url: plan.asset_url,
+            asset_size: plan.asset_size,
+            install_path: plan.install_path,
+            staged_path: None,
+            sha256,
+        }
+    }
+
+    // When staging, this is where the staged files would be installed, not where the installer
+    // writes them.
+    fn install_path(&self) -> PathBuf {
+        self.final_install_path
+            .clone()
+            .unwrap_or_else(|| self.installer.install_path().to_path_buf())
+    }
+
+    // If the installer can't tell us whether the executable is already there, we fall back to the
+    // download cache, which knows what we installed from this asset last time.
+    fn existing_install(&self, asset: &Asset) -> Result<Option<PathBuf>> {
+        if let Some(path) = self.installer.existing_install() {
+            return Ok(Some(path));
+        }
+        Ok(self
+            .installed_cache_entry(asset)?
+            .map(|_| self.installer.install_path().to_path_buf()))
+    }
+
+    // Returns the release's tag if we're ensuring that the executable is at that tag, and the install
+    // metadata says it already is.
+    fn ensured_tag(&self) -> Result<Option<&str>> {
+        let (true, Some(meta), Some(tag)) = (self.ensure, &self.install_meta, &self.release_tag)
+        else {
+            return Ok(None);
+        };
+        if self.installer.existing_install().is_none() {
+            debug!("the executable is not installed");
+            return Ok(None);
+        }
+        let Some(installed) = meta.get(self.installer.install_path())? else {
+            return Ok(None);
+        };
+        if installed.tag != *tag {
+            debug!(
+                "the installed executable is at {}, not {tag}",
+                installed.tag
+            );
+            return Ok(None);
+        }
+        Ok(Some(tag))
     }
 
+    // We always record the release when ensuring. Otherwise, we only update an existing metadata
+    // file, so that a later `ensure` doesn't trust a tag that's out of date.
+    fn store_install_meta(&self, asset_name: &str) -> Result<()> {
+        let Some(meta) = &self.install_meta else {
+            return Ok(());
+        };
+        if !self.ensure && !meta.exists() {
+            return Ok(());
+        }
+        let release = self.release_tag.as_ref().map(|tag| InstalledRelease {
+            tag: tag.clone(),
+            asset_name: asset_name.to_string(),
+        });
+        meta.store(self.installer.install_path(), release)
+    }
+
+    // We only use a cache entry if everything we installed from the asset last time is still
+    // there. Otherwise, a `304 Not Modified` response would leave the user with nothing installed.
+    fn installed_cache_entry(&self, asset: &Asset) -> Result<Option<CacheEntry>> {
+        let Some(cache) = &self.download_cache else {
+            return Ok(None);
+        };
+        let entry = cache.get(&asset.name)?.filter(|e| {
+            let is_installed = e.is_installed();
+            if !is_installed {
+                debug!("the files installed from {} are missing", asset.name);
+            }
+            is_installed
+        });
+
+        Ok(entry)
+    }
+
+    /// Returns the release asset that [`Ubi::install_binary`] would download and the path it would
+    /// install to, without downloading or writing anything. This still makes requests to the forge
+    /// site's API, so it will catch errors like a missing release or a bad token.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Ubi::install_binary`] up to the point where it would
+    /// download the release asset. This includes failing to find a signature or checksum file when
+    /// verification is enabled.
+    pub async fn install_plan(&mut self) -> Result<InstallPlan> {
+        let (asset, all_assets) = self.asset_and_all_assets().await?;
+        self.verification_assets(&asset, &all_assets)?;
+
+        Ok(InstallPlan {
+            asset_name: asset.name,
+            asset_url: asset.url,
+            asset_size: asset.size,
+            install_path: self.install_path(),
+        })
+    }
+
+    /// Returns the download URL of the release asset that [`Ubi::install_binary`] would download,
+    /// without downloading or writing anything. This is a lighter version of [`Ubi::install_plan`]
+    /// for when you only want to hand the URL to another tool.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the request for the release info fails, if the response cannot be
+    /// parsed, or if no matching asset can be found.
+    pub async fn asset_download_url(&mut self) -> Result<Url> {
+        let (asset, _) = self.asset_and_all_assets().await?;
+        Ok(asset.url)
+    }
+
+    /// Returns the directory that files are installed into. If
+    /// [`UbiBuilder::install_dir`](crate::UbiBuilder::install_dir) was not called, this is the
+    /// default install directory for the platform. You can use this to tell the user where the
+    /// files were installed, or to check whether this directory is in their `PATH`. If
+    /// [`UbiBuilder::stage_dir`](crate::UbiBuilder::stage_dir) was called, then this is the stage
+    /// directory.
+    #[must_use]
+    pub fn install_dir(&self) -> &Path {
+        self.installer.install_dir()
+    }
+
+    /// Returns all of the assets for the release, in the order the forge site returned them. This
+    /// fetches the release info but does not pick an asset or download anything, so you can use it
+    /// to present your own list of assets to choose from.
+    ///
+    /// If the `Ubi` instance was built with a
+    /// [`UbiBuilder::url`](crate::UbiBuilder::url), then this returns a single asset for that URL
+    /// without making any requests.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the request for the release info fails or if the response cannot
+    /// be parsed.
+    pub async fn available_assets(&mut self) -> Result<Vec<Asset>> {
+        if let Some(url) = &self.asset_url {
+            return Ok(vec![Self::asset_for_url(url)]);
+        }
+
+        Ok(self.fetch_release_assets().await?.assets)
+    }
+
+    /// Returns all of the project's releases, newest first. This includes prereleases and any draft
+    /// releases that are visible with the token you are using. This does not pick a release or
+    /// download anything.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the `Ubi` instance was built with a
+    /// [`UbiBuilder::url`](crate::UbiBuilder::url) or
+    /// [`UbiBuilder::local_asset`](crate::UbiBuilder::local_asset), if the forge does not support
+    /// listing releases, or if a request to the forge site's API fails.
+    pub async fn list_releases(&self) -> Result<Vec<Release>> {
+        if self.asset_url.is_some() {
+            return Err(anyhow!(
+                "cannot list releases for a Ubi instance that was built with a url or local_asset"
+            ));
+        }
+
+        self.forge.list_releases(&self.reqwest_client).await
+    }
+
+    // We look for these before downloading anything so that we fail early if they're missing.
+    fn verification_assets(
+        &self,
+        asset: &Asset,
+        all_assets: &[Asset],
+    ) -> Result<(Option<Asset>, Option<Asset>, Option<Asset>)> {
+        let checksum_asset = if !self.verify_checksum {
+            None
+        } else if self.asset_url.is_some() {
+            Some(checksum::checksum_asset_for_url(asset))
+        } else {
+            Some(checksum::checksum_asset_for(asset, all_assets)?)
+        };
+        let signature_asset = match self.signature_verifier {
+            Some(_) if self.asset_url.is_some() => {
+                Some(SignatureVerifier::signature_asset_for_url(asset))
+            }
+            Some(_) => Some(SignatureVerifier::signature_asset_for(asset, all_assets)?),
+            None => None,
+        };
+        let gpg_signature_asset = match self.gpg_verifier {
+            Some(_) if self.asset_url.is_some() => {
+                Some(GpgVerifier::signature_asset_for_url(asset))
+            }
+            Some(_) => Some(GpgVerifier::signature_asset_for(asset, all_assets)?),
+            None => None,
+        };
+
+        Ok((checksum_asset, signature_asset, gpg_signature_asset))
+    }
+
+    #[cfg(test)]
     pub(crate) async fn asset(&mut self) -> Result<Asset> {
+        Ok(self.asset_and_all_assets().await?.0)
+    }
+
+    // We return all of the release assets along with the one we picked so that we can find other
+    // related assets, like signatures, in the same release.
+    async fn asset_and_all_assets(&mut self) -> Result<(Asset, Vec<Asset>)> {
         if let Some(url) = &self.asset_url {
-            return Ok(Asset {
-                name: url.path().split('/').next_back().unwrap().to_string(),
-                url: url.clone(),
-            });
+            return Ok((Self::asset_for_url(url), vec![]));
+        }
+
+        let ReleaseAssets { tag, assets } = self.release_assets().await?;
+        self.release_tag = tag.or_else(|| self.forge.tag().map(String::from));
+        self.asset_picker.set_release_tag(self.release_tag.clone());
+        if let Some(asset) = self.asset_selector.as_ref().and_then(|s| s.select(&assets)) {
+            debug!("the asset selector picked the asset named {}", asset.name);
+            return Ok((asset, assets));
         }
 
-        let assets = self.forge.fetch_assets(&self.reqwest_client).await?;
-        let asset = self.asset_picker.pick_asset(assets)?;
+        let asset = self.asset_picker.pick_asset(assets.clone())?;
         debug!("picked asset named {}", asset.name);
-        Ok(asset)
+        Ok((asset, assets))
     }
 
-    async fn download_asset(&self, client: &Client, asset: Asset) -> Result<Download> {
+    // Fetches the release info now, so that the next call to `install_binary` or `install_plan`
+    // does not need to make that request. This does nothing if we were given an asset URL.
+    pub(crate) async fn prefetch_release_assets(&mut self) -> Result<()> {
+        if self.asset_url.is_none() {
+            self.release_assets = Some(self.fetch_release_assets().await?);
+        }
+        Ok(())
+    }
+
+    // Prefetched release info is only used once, so later calls see any new releases.
+    async fn release_assets(&mut self) -> Result<ReleaseAssets> {
+        if let Some(release) = self.release_assets.take() {
+            return Ok(release);
+        }
+
+        self.fetch_release_assets().await
+    }
+
+    async fn fetch_release_assets(&mut self) -> Result<ReleaseAssets> {
+        if self.version_req.is_some() || self.tag_pattern.is_some() || self.include_prereleases {
+            let tag = self.pick_release_tag().await?;
+            self.forge.set_tag(tag);
+        }
+
+        let url = self.forge.release_info_url();
+        if let Some(cache) = &self.release_info_cache {
+            if let Some(release) = cache.get(&url)? {
+                return Ok(release);
+            }
+        }
+        let release = self.forge.fetch_assets(&self.reqwest_client).await?;
+        // We check this before caching so that we don't keep using a release whose assets haven't
+        // been uploaded yet.
+        if release.assets.is_empty() {
+            let release_name = match release.tag.as_deref().or_else(|| self.forge.tag()) {
+                Some(tag) => format!("release {tag}"),
+                None => "latest release".to_string(),
+            };
+            return Err(anyhow!(
+                "the {release_name} has no downloadable assets; check that the tag is correct, or if the release was just created, wait for its CI to finish uploading the assets"
+            ));
+        }
+        if let Some(cache) = &self.release_info_cache {
+            cache.store(&url, &release)?;
+        }
+
+        Ok(release)
+    }
+
+    // Picks the tag of the release to install from the list of the project's releases. Without a
+    // version requirement or tag pattern, this is the newest release.
+    async fn pick_release_tag(&self) -> Result<String> {
+        let releases = self
+            .forge
+            .list_releases(&self.reqwest_client)
+            .await?
+            .into_iter()
+            // We can't look up a draft release by its tag, so these are always skipped.
+            .filter(|r| !r.draft && (self.include_prereleases || !r.prerelease))
+            .collect::<Vec<_>>();
+
+        if let Some(pattern) = &self.tag_pattern {
+            return newest_release_matching_pattern(releases, pattern);
+        }
+
+        let tags = releases.into_iter().map(|r| r.tag).collect::<Vec<_>>();
+
+        let Some(req) = &self.version_req else {
+            debug!(
+                "picking the newest release{}",
+                if self.include_prereleases {
+                    ", including prereleases"
+                } else {
+                    ""
+                },
+            );
+            return tags
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("could not find any releases for this project"));
+        };
+
+        let Some(tag) = version::highest_matching_tag(&tags, req, self.include_prereleases) else {
+            return Err(anyhow!(
+                "could not find a release matching the version requirement {req} in the list of tags: {}",
+                tags.join(", "),
+            ));
+        };
+        debug!("picked the tag {tag} for the version requirement {req}");
+        Ok(tag.to_string())
+    }
+
+    fn asset_for_url(url: &Url) -> Asset {
+        Asset {
+            name: url.path().split('/').next_back().unwrap().to_string(),
+            url: url.clone(),
+            size: None,
+        }
+    }
+
+    // If a cache entry is given, we make a conditional request. This returns `None` if the server
+    // says the asset has not been modified. Otherwise, it also returns a new cache entry based on the
+    // response headers, if the server sent any headers we can cache.
+    async fn download_asset(
+        &self,
+        client: &Client,
+        asset: Asset,
+        cache_entry: Option<&CacheEntry>,
+    ) -> Result<Option<(Download, Option<CacheEntry>)>> {
+        if asset.url.scheme() == "file" {
+            return Ok(Some((Self::local_download(&asset)?, None)));
+        }
+
         debug!("downloading asset from {}", asset.url);
 
+        let resp = self
+            .request_asset(client, &asset, cache_entry, None)
+            .await?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            debug!("the server says {} has not been modified", asset.name);
+            return Ok(None);
+        }
+        let new_cache_entry = CacheEntry::from_headers(resp.headers());
+
+        let td = tempdir()?;
+        let mut archive_path = td.path().to_path_buf();
+        archive_path.push(&asset.name);
+        debug!("archive path is {}", archive_path.to_string_lossy());
+
+        // We only move the file to its final name once the download is complete.
+        let part_path = td.path().join(format!("{}.part", asset.name));
+        let mut file = File::create(&part_path)?;
+        self.download_body(client, &asset, resp, &mut file).await?;
+        // The file must be closed before it's renamed on Windows.
+        drop(file);
+        fs::rename(&part_path, &archive_path)?;
+
+        Ok(Some((
+            Download {
+                _temp_dir: td,
+                archive_path,
+            },
+            new_cache_entry,
+        )))
+    }
+
+    // Each part is downloaded on its own, and then they're joined into a file named like the asset
+    // without the part suffix, so the installer sees the joined asset's extension. We don't make
+    // conditional requests for a split asset, since we'd have to check every part.
+    async fn download_split_asset(&self, client: &Client, parts: Vec<Asset>) -> Result<Download> {
+        let joined_name = split_asset_part(&parts[0].name)
+            .map(|(name, _)| name.to_string())
+            .expect("the first part of a split asset has a part suffix");
+        debug!(
+            "downloading {} parts of the split asset {joined_name}",
+            parts.len()
+        );
+
+        let td = tempdir()?;
+        let archive_path = td.path().join(&joined_name);
+        let mut joined = File::create(&archive_path)?;
+        for part in parts {
+            let part_name = part.name.clone();
+            let Some((download, _)) = self.download_asset(client, part, None).await? else {
+                return Err(anyhow!(
+                    "the server said {part_name} was not modified, but the request was not a conditional request",
+                ));
+            };
+            io::copy(&mut File::open(&download.archive_path)?, &mut joined)
+                .with_context(|| format!("could not add {part_name} to {joined_name}"))?;
+        }
+        drop(joined);
+
+        Ok(Download {
+            _temp_dir: td,
+            archive_path,
+        })
+    }
+
+    // If reading the response body fails partway through, we make a new request, up to the number of
+    // retries in the retry policy. If the server said it accepts ranges, then the new request asks
+    // for the rest of the file. If the server responds with `206 Partial Content`, we append that to
+    // what we already have. Otherwise we start over with whatever it sends.
+    async fn download_body(
+        &self,
+        client: &Client,
+        asset: &Asset,
+        mut resp: Response,
+        sink: &mut impl DownloadSink,
+    ) -> Result<()> {
+        let progress = self.download_progress.as_deref();
+        if let Some(p) = progress {
+            p.start(expected_download_size(asset, resp.content_length()));
+        }
+
+        let mut rate_limiter = self.max_download_rate.map(RateLimiter::new);
+        let mut can_resume = accepts_ranges(&resp);
+        let mut etag = resp.headers().get(ETAG).cloned();
+        let mut downloaded: u64 = 0;
+        let mut attempt = 0;
+        loop {
+            let err = match resp.chunk().await {
+                Ok(Some(c)) => {
+                    sink.write_all(c.as_ref())?;
+                    downloaded += c.len() as u64;
+                    if let Some(p) = progress {
+                        p.advance(downloaded);
+                    }
+                    if let Some(l) = rate_limiter.as_mut() {
+                        l.consume(c.len()).await;
+                    }
+                    continue;
+                }
+                Ok(None) => break,
+                Err(e) => e,
+            };
+            if attempt >= self.retry_policy.max_retries() {
+                return Err(anyhow::Error::new(err).context(format!(
+                    "error downloading {} from {}",
+                    asset.name, asset.url
+                )));
+            }
+
+            let delay = self.retry_policy.backoff(attempt);
+            attempt += 1;
+            debug!(
+                "downloading {} failed after {downloaded} bytes: {err}, will retry in {delay:?}",
+                asset.name,
+            );
+            tokio::time::sleep(delay).await;
+
+            let resume = (can_resume && downloaded > 0).then(|| Resume {
+                offset: downloaded,
+                etag: etag.clone(),
+            });
+            resp = self
+                .request_asset(client, asset, None, resume.as_ref())
+                .await?;
+            if resp.status() == StatusCode::PARTIAL_CONTENT {
+                check_content_range(&resp, downloaded)?;
+                debug!(
+                    "resuming the download of {} at byte {downloaded}",
+                    asset.name
+                );
+                continue;
+            }
+
+            debug!(
+                "restarting the download of {} from the beginning",
+                asset.name
+            );
+            sink.reset()?;
+            downloaded = 0;
+            can_resume = accepts_ranges(&resp);
+            etag = resp.headers().get(ETAG).cloned();
+            if let Some(p) = progress {
+                p.advance(downloaded);
+            }
+        }
+        if let Some(p) = progress {
+            p.finish();
+        }
+
+        Ok(())
+    }
+
+    // A local file is installed from where it is, so nothing is ever written to the temp dir.
+    fn local_download(asset: &Asset) -> Result<Download> {
+        let archive_path = local_file_path(&asset.url)?;
+        debug!("using the local file at {}", archive_path.display());
+        Ok(Download {
+            _temp_dir: tempdir()?,
+            archive_path,
+        })
+    }
+
+    async fn download_text(&self, client: &Client, asset: &Asset) -> Result<String> {
+        if asset.url.scheme() == "file" {
+            let path = local_file_path(&asset.url)?;
+            debug!("reading {} from {}", asset.name, path.display());
+            return fs::read_to_string(&path)
+                .with_context(|| format!("could not read {}", path.display()));
+        }
+
+        debug!("downloading {} from {}", asset.name, asset.url);
+
+        let resp = self.request_asset(client, asset, None, None).await?;
+        Ok(resp.text().await?)
+    }
+
+    // GPG signatures may be binary, so unlike checksums and minisign signatures we can't read
+    // them as text.
+    async fn download_bytes(&self, client: &Client, asset: &Asset) -> Result<Vec<u8>> {
+        if asset.url.scheme() == "file" {
+            let path = local_file_path(&asset.url)?;
+            debug!("reading {} from {}", asset.name, path.display());
+            return fs::read(&path).with_context(|| format!("could not read {}", path.display()));
+        }
+
+        debug!("downloading {} from {}", asset.name, asset.url);
+
+        let resp = self.request_asset(client, asset, None, None).await?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    // Unlike `download_bytes`, this is for the release asset itself, so it has the same progress
+    // bar, rate limit, and retries as a download to a file.
+    async fn download_to_memory(&self, client: &Client, asset: &Asset) -> Result<Vec<u8>> {
+        if asset.url.scheme() == "file" {
+            let path = local_file_path(&asset.url)?;
+            debug!("reading {} from {}", asset.name, path.display());
+            return fs::read(&path).with_context(|| format!("could not read {}", path.display()));
+        }
+
+        debug!("downloading asset from {} into memory", asset.url);
+
+        let resp = self.request_asset(client, asset, None, None).await?;
+        let mut contents = vec![];
+        self.download_body(client, asset, resp, &mut contents)
+            .await?;
+        Ok(contents)
+    }
+
+    async fn request_asset(
+        &self,
+        client: &Client,
+        asset: &Asset,
+        cache_entry: Option<&CacheEntry>,
+        resume: Option<&Resume>,
+    ) -> Result<Response> {
         let mut req_builder = client
             .get(asset.url.clone())
             .header(ACCEPT, HeaderValue::from_str("application/octet-stream")?);
         req_builder = self.forge.maybe_add_token_header(req_builder)?;
+        if let Some(cache_entry) = cache_entry {
+            req_builder = cache_entry.add_conditional_headers(req_builder);
+        }
+        if let Some(resume) = resume {
+            req_builder = req_builder.header(RANGE, format!("bytes={}-", resume.offset));
+            if let Some(etag) = &resume.etag {
+                req_builder = req_builder.header(IF_RANGE, etag.clone());
+            }
+        }
         let req = req_builder.build()?;
 
-        let mut resp = self.reqwest_client.execute(req).await?;
+        let resp = self.retry_policy.execute(&self.reqwest_client, req).await?;
+        if resp.status() == StatusCode::NOT_MODIFIED && cache_entry.is_some() {
+            return Ok(resp);
+        }
+        if resp.status() == StatusCode::PARTIAL_CONTENT && resume.is_some() {
+            return Ok(resp);
+        }
         if resp.status() != StatusCode::OK {
             let mut msg = format!("error requesting {}: {}", asset.url, resp.status());
             if let Ok(t) = resp.text().await {
@@ -110,21 +1189,187 @@ impl<'a> Ubi<'a> {
             return Err(anyhow!(msg));
         }
 
-        let td = tempdir()?;
-        let mut archive_path = td.path().to_path_buf();
-        archive_path.push(&asset.name);
-        debug!("archive path is {}", archive_path.to_string_lossy());
+        Ok(resp)
+    }
+}
 
-        {
-            let mut downloaded_file = File::create(&archive_path)?;
-            while let Some(c) = resp.chunk().await? {
-                downloaded_file.write_all(c.as_ref())?;
-            }
+// Tags that don't follow semver can't be ordered by their names, so we pick the matching release
+// that was published most recently. The releases are newest first, so they are reversed to make
+// `max_by_key`, which returns the last of several equal elements, prefer the first one on a tie.
+fn newest_release_matching_pattern(releases: Vec<Release>, pattern: &Regex) -> Result<String> {
+    let all_tags = releases.iter().map(|r| r.tag.as_str()).join(", ");
+    let Some(release) = releases
+        .into_iter()
+        .rev()
+        .filter(|r| pattern.is_match(&r.tag))
+        .max_by_key(|r| r.published_at.clone())
+    else {
+        return Err(anyhow!(
+            "could not find a release with a tag matching the pattern `{pattern}` in the list of tags: {all_tags}",
+        ));
+    };
+    debug!(
+        "picked the tag {} for the tag pattern `{pattern}`",
+        release.tag
+    );
+    Ok(release.tag)
+}
+
+// The copy is written to a temp file in the same dir and then renamed, so a partial copy never
+// replaces one kept by a previous install.
+fn keep_archive(
+    archive_path: &Path,
+    dir: &Path,
+    asset_name: &str,
+    installed: &[PathBuf],
+) -> Result<()> {
+    let file_name = Path::new(asset_name)
+        .file_name()
+        .ok_or_else(|| anyhow!("could not get a file name from the asset name {asset_name}"))?;
+    let kept_path = dir.join(file_name);
+    if installed.contains(&kept_path) || kept_path == archive_path {
+        debug!(
+            "not keeping a copy of {asset_name} because it is already at {}",
+            kept_path.display(),
+        );
+        return Ok(());
+    }
+
+    debug!("keeping a copy of {asset_name} at {}", kept_path.display());
+    fs::create_dir_all(dir).with_context(|| format!("could not create {}", dir.display()))?;
+    let mut staged = tempfile::Builder::new()
+        .prefix(".ubi-")
+        .tempfile_in(dir)
+        .with_context(|| format!("could not create a temp file in {}", dir.display()))?;
+    io::copy(&mut File::open(archive_path)?, staged.as_file_mut())?;
+    staged.persist(&kept_path).with_context(|| {
+        format!(
+            "could not keep a copy of {asset_name} at {}",
+            kept_path.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+// Some servers and proxies send an error page with a `200 OK` status. Trying to extract one of those
+// fails with a confusing error, so we look for the most common kinds of error pages first.
+fn check_for_error_page(asset_name: &str, contents: Contents<'_>) -> Result<()> {
+    let mut head = [0u8; 512];
+    let head = match contents {
+        Contents::File(path) => {
+            let n = File::open(path)?.read(&mut head)?;
+            &head[..n]
         }
+        Contents::Bytes(data) => &data[..data.len().min(head.len())],
+    };
+    let Some(kind) = error_page_kind(head) else {
+        return Ok(());
+    };
 
-        Ok(Download {
-            _temp_dir: td,
-            archive_path,
-        })
+    Err(anyhow!(
+        "the downloaded content for {asset_name} doesn't look like a binary or archive, it looks like {kind}, so the server may have returned an error page instead of the file"
+    ))
+}
+
+fn error_page_kind(head: &[u8]) -> Option<&'static str> {
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = head.iter().position(|b| !b.is_ascii_whitespace())?;
+    let head = head[start..].to_ascii_lowercase();
+    if head.starts_with(b"<!doctype html") || head.starts_with(b"<html") {
+        Some("an HTML page")
+    } else if head.starts_with(b"<?xml") {
+        Some("an XML document")
+    } else if head.starts_with(b"{") || head.starts_with(b"[") {
+        Some("a JSON document")
+    } else {
+        None
+    }
+}
+
+// The forge's size for the asset is a cross-check on the `Content-Length` header, and it's used for
+// the progress bar's total when the server doesn't send one. If they differ, we trust the header,
+// since that's what the server is actually sending.
+fn expected_download_size(asset: &Asset, content_length: Option<u64>) -> Option<u64> {
+    match (content_length, asset.size) {
+        (Some(len), Some(size)) if len != size => {
+            warn!(
+                "the forge says that {} is {size} bytes, but the server sent a Content-Length of {len}",
+                asset.name,
+            );
+            Some(len)
+        }
+        (Some(len), _) => Some(len),
+        (None, size) => size,
+    }
+}
+
+fn accepts_ranges(resp: &Response) -> bool {
+    resp.headers()
+        .get(ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("bytes"))
+}
+
+// A `206 Partial Content` response has a `Content-Range` header like `bytes 100-999/1000`. If it
+// doesn't start where we asked it to, appending it would corrupt the file.
+fn check_content_range(resp: &Response, offset: u64) -> Result<()> {
+    let start = resp
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes "))
+        .and_then(|v| v.split_once('-'))
+        .and_then(|(start, _)| start.parse::<u64>().ok());
+    if start == Some(offset) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "the server sent a partial response from {} that does not start at byte {offset}",
+        resp.url(),
+    ))
+}
+
+fn local_file_path(url: &Url) -> Result<PathBuf> {
+    url.to_file_path()
+        .map_err(|()| anyhow!("could not get a local path from the URL {url}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(b"<!DOCTYPE html>\n<html><body>Rate limited</body></html>", Some("an HTML page") ; "HTML with doctype")]
+    #[test_case(b"\n  <html><head></head></html>", Some("an HTML page") ; "HTML with leading whitespace")]
+    #[test_case(b"\xEF\xBB\xBF<!doctype html>", Some("an HTML page") ; "HTML with a BOM")]
+    #[test_case(br#"<?xml version="1.0"?><Error><Code>AccessDenied</Code></Error>"#, Some("an XML document") ; "XML")]
+    #[test_case(br#"{"message": "API rate limit exceeded"}"#, Some("a JSON document") ; "JSON object")]
+    #[test_case(br#"[{"message": "not found"}]"#, Some("a JSON document") ; "JSON array")]
+    #[test_case(b"#!/bin/sh\necho project\n", None ; "shell script")]
+    #[test_case(b"\x7fELF\x02\x01\x01", None ; "ELF executable")]
+    #[test_case(b"\x1f\x8b\x08\x00", None ; "gzip")]
+    #[test_case(b"PK\x03\x04", None ; "zip")]
+    #[test_case(b"", None ; "empty")]
+    #[test_case(b"   \n", None ; "only whitespace")]
+    fn error_page_kind(head: &[u8], expect: Option<&str>) {
+        assert_eq!(super::error_page_kind(head), expect);
+    }
+
+    #[test_case(Some(100), Some(100), Some(100) ; "both match")]
+    #[test_case(Some(100), Some(200), Some(100) ; "Content-Length wins when they differ")]
+    #[test_case(Some(100), None, Some(100) ; "only Content-Length")]
+    #[test_case(None, Some(200), Some(200) ; "only the forge's size")]
+    #[test_case(None, None, None ; "neither")]
+    fn expected_download_size(content_length: Option<u64>, size: Option<u64>, expect: Option<u64>) {
+        let asset = Asset {
+            name: "project-Linux-x86_64.tar.gz".to_string(),
+            url: Url::parse("https://example.com/project-Linux-x86_64.tar.gz").unwrap(),
+            size,
+        };
+        assert_eq!(
+            super::expected_download_size(&asset, content_length),
+            expect
+        );
     }
 }
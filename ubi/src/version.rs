@@ -0,0 +1,442 @@
+// This implements the subset of semver that we need to pick a release by version requirement. The
+// requirement syntax follows Cargo's, so `1.2` means `^1.2`, and a prerelease version only matches
+// a requirement that names a prerelease of the same version.
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Identifier>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    // Numeric identifiers always sort before alphanumeric ones, which derive gives us, since this
+    // variant comes first.
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VersionReq {
+    source: String,
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<Identifier>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl Version {
+    // Release tags are often the version with a `v` prefix, like `v1.2.3`.
+    pub(crate) fn from_tag(tag: &str) -> Option<Self> {
+        let version = tag
+            .strip_prefix('v')
+            .or_else(|| tag.strip_prefix('V'))
+            .unwrap_or(tag);
+        match Self::from_str(version) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                debug!("ignoring the tag {tag}: {e}");
+                None
+            }
+        }
+    }
+
+    fn triple(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Build metadata is ignored when comparing versions.
+        let without_build = s.split_once('+').map_or(s, |(v, _)| v);
+        let (core, pre) = match without_build.split_once('-') {
+            Some((core, pre)) => (core, parse_prerelease(pre)?),
+            None => (without_build, vec![]),
+        };
+
+        let parts = core.split('.').collect::<Vec<_>>();
+        let [major, minor, patch] = parts.as_slice() else {
+            return Err(anyhow!(
+                "`{s}` is not a version with a major, minor, and patch number"
+            ));
+        };
+
+        Ok(Version {
+            major: parse_number(major)?,
+            minor: parse_number(minor)?,
+            patch: parse_number(patch)?,
+            pre,
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.triple().cmp(&other.triple()).then_with(|| {
+            // A version without a prerelease sorts after all of its prereleases.
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            }
+        })
+    }
+}
+
+impl VersionReq {
+    pub(crate) fn matches(&self, version: &Version) -> bool {
+        self.matches_including_prereleases(version)
+            && (version.pre.is_empty()
+                || self
+                    .comparators
+                    .iter()
+                    .any(|c| c.allows_prerelease_of(version)))
+    }
+
+    // This is like `matches`, except that a prerelease version matches whenever it satisfies every
+    // comparator, so `^1.2` matches `1.3.0-beta.1`.
+    pub(crate) fn matches_including_prereleases(&self, version: &Version) -> bool {
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().is_empty() {
+            return Err(anyhow!("the version requirement is empty"));
+        }
+
+        let mut comparators = vec![];
+        for c in s.split(',') {
+            if let Some(c) = Comparator::parse(c.trim())
+                .map_err(|e| anyhow!("invalid version requirement `{s}`: {e}"))?
+            {
+                comparators.push(c);
+            }
+        }
+        Ok(VersionReq {
+            source: s.to_string(),
+            comparators,
+        })
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.source)
+    }
+}
+
+impl Comparator {
+    // Returns `None` for `*`, which matches any version.
+    fn parse(s: &str) -> Result<Option<Self>> {
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (Some(op), rest.trim())))
+        .unwrap_or((None, s));
+        if rest.is_empty() {
+            return Err(anyhow!("`{s}` does not contain a version"));
+        }
+
+        let (core, pre) = match rest.split_once('-') {
+            Some((core, pre)) => (core, parse_prerelease(pre)?),
+            None => (rest, vec![]),
+        };
+        let mut parts = core.split('.').map(parse_partial);
+        let Some(major) = parts.next().transpose()? else {
+            return Err(anyhow!("`{s}` does not contain a version"));
+        };
+        let minor = parts.next().transpose()?.flatten();
+        let patch = parts.next().transpose()?.flatten();
+        if parts.next().is_some() {
+            return Err(anyhow!("`{s}` has too many version numbers"));
+        }
+        if (major.is_none() && minor.is_some()) || (minor.is_none() && patch.is_some()) {
+            return Err(anyhow!("`{s}` has a version number after a wildcard"));
+        }
+        let Some(major) = major else {
+            return Ok(None);
+        };
+        if !pre.is_empty() && patch.is_none() {
+            return Err(anyhow!(
+                "`{s}` has a prerelease without a major, minor, and patch number"
+            ));
+        }
+
+        // A bare version like `1.2` means `^1.2`, but one with a wildcard like `1.*` means `=1`.
+        let has_wildcard = core.split('.').any(is_wildcard);
+        let op = op.unwrap_or(if has_wildcard { Op::Exact } else { Op::Caret });
+
+        Ok(Some(Comparator {
+            op,
+            major,
+            minor,
+            patch,
+            pre,
+        }))
+    }
+
+    fn matches(&self, v: &Version) -> bool {
+        let Some(minor) = self.minor else {
+            return match self.op {
+                Op::Exact | Op::Tilde | Op::Caret => v.major == self.major,
+                Op::Greater => v.major > self.major,
+                Op::GreaterEq => v.major >= self.major,
+                Op::Less => v.major < self.major,
+                Op::LessEq => v.major <= self.major,
+            };
+        };
+        let Some(patch) = self.patch else {
+            let v_major_minor = (v.major, v.minor);
+            let major_minor = (self.major, minor);
+            return match self.op {
+                Op::Exact | Op::Tilde => v_major_minor == major_minor,
+                Op::Caret if self.major > 0 => v.major == self.major && v.minor >= minor,
+                Op::Caret => v_major_minor == major_minor,
+                Op::Greater => v_major_minor > major_minor,
+                Op::GreaterEq => v_major_minor >= major_minor,
+                Op::Less => v_major_minor < major_minor,
+                Op::LessEq => v_major_minor <= major_minor,
+            };
+        };
+
+        let bound = Version {
+            major: self.major,
+            minor,
+            patch,
+            pre: self.pre.clone(),
+        };
+        match self.op {
+            Op::Exact => *v == bound,
+            Op::Greater => *v > bound,
+            Op::GreaterEq => *v >= bound,
+            Op::Less => *v < bound,
+            Op::LessEq => *v <= bound,
+            Op::Tilde => v.major == self.major && v.minor == minor && *v >= bound,
+            Op::Caret if self.major > 0 => v.major == self.major && *v >= bound,
+            Op::Caret if minor > 0 => v.major == 0 && v.minor == minor && *v >= bound,
+            Op::Caret => v.triple() == (0, 0, patch) && *v >= bound,
+        }
+    }
+
+    fn allows_prerelease_of(&self, v: &Version) -> bool {
+        !self.pre.is_empty()
+            && (self.major, self.minor, self.patch) == (v.major, Some(v.minor), Some(v.patch))
+    }
+}
+
+// Returns the tag with the highest version that matches the requirement. Tags that are not valid
+// versions are ignored.
+pub(crate) fn highest_matching_tag<'a>(
+    tags: &'a [String],
+    req: &VersionReq,
+    include_prereleases: bool,
+) -> Option<&'a str> {
+    tags.iter()
+        .filter_map(|t| Version::from_tag(t).map(|v| (v, t)))
+        .filter(|(v, _)| {
+            if include_prereleases {
+                req.matches_including_prereleases(v)
+            } else {
+                req.matches(v)
+            }
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, t)| t.as_str())
+}
+
+fn parse_number(s: &str) -> Result<u64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("`{s}` is not a version number"));
+    }
+    Ok(s.parse()?)
+}
+
+// Returns `None` for a wildcard like `*` or `x`.
+fn parse_partial(s: &str) -> Result<Option<u64>> {
+    if is_wildcard(s) {
+        return Ok(None);
+    }
+    parse_number(s).map(Some)
+}
+
+fn is_wildcard(s: &str) -> bool {
+    matches!(s, "*" | "x" | "X")
+}
+
+fn parse_prerelease(s: &str) -> Result<Vec<Identifier>> {
+    s.split('.')
+        .map(|i| {
+            if i.is_empty() || !i.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                Err(anyhow!("`{s}` is not a valid prerelease"))
+            } else if i.chars().all(|c| c.is_ascii_digit()) {
+                Ok(Identifier::Numeric(i.parse()?))
+            } else {
+                Ok(Identifier::Alphanumeric(i.to_string()))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("v1.2.3", Some((1, 2, 3)) ; "v prefix")]
+    #[test_case("V1.2.3", Some((1, 2, 3)) ; "uppercase V prefix")]
+    #[test_case("1.2.3", Some((1, 2, 3)) ; "no prefix")]
+    #[test_case("v10.20.30+build.5", Some((10, 20, 30)) ; "with build metadata")]
+    #[test_case("v1.2.3-beta.1", Some((1, 2, 3)) ; "with prerelease")]
+    #[test_case("v1.2", None ; "missing patch")]
+    #[test_case("release-1.2.3", None ; "non-version prefix")]
+    #[test_case("v1.2.3.4", None ; "too many parts")]
+    #[test_case("v1.2.3-", None ; "empty prerelease")]
+    #[test_case("nightly", None ; "not a version")]
+    fn version_from_tag(tag: &str, expect: Option<(u64, u64, u64)>) {
+        assert_eq!(Version::from_tag(tag).map(|v| v.triple()), expect);
+    }
+
+    #[test]
+    fn version_ordering() -> Result<()> {
+        // This is the example ordering from the semver spec.
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.1.0",
+            "2.0.0",
+        ]
+        .iter()
+        .map(|v| Version::from_str(v))
+        .collect::<Result<Vec<_>>>()?;
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+
+        Ok(())
+    }
+
+    #[test_case("^1.2", "1.2.0", true)]
+    #[test_case("^1.2", "1.9.9", true)]
+    #[test_case("^1.2", "1.1.9", false)]
+    #[test_case("^1.2", "2.0.0", false)]
+    #[test_case("1.2", "1.3.0", true ; "bare version is caret")]
+    #[test_case("^0.2.3", "0.2.9", true)]
+    #[test_case("^0.2.3", "0.3.0", false)]
+    #[test_case("^0.0.3", "0.0.3", true)]
+    #[test_case("^0.0.3", "0.0.4", false)]
+    #[test_case("^0", "0.9.0", true)]
+    #[test_case("~1.2.3", "1.2.9", true)]
+    #[test_case("~1.2.3", "1.3.0", false)]
+    #[test_case("~1", "1.9.0", true)]
+    #[test_case("=1.2.3", "1.2.3", true)]
+    #[test_case("=1.2.3", "1.2.4", false)]
+    #[test_case("=1.2", "1.2.7", true)]
+    #[test_case("1.*", "1.7.0", true)]
+    #[test_case("1.x", "2.0.0", false)]
+    #[test_case("*", "3.4.5", true)]
+    #[test_case(">1.2", "1.2.9", false)]
+    #[test_case(">1.2", "1.3.0", true)]
+    #[test_case(">=0.4, <0.6", "0.4.0", true)]
+    #[test_case(">=0.4, <0.6", "0.5.9", true)]
+    #[test_case(">=0.4, <0.6", "0.6.0", false)]
+    #[test_case(">=0.4, <0.6", "0.3.9", false)]
+    #[test_case("<=1.2", "1.2.9", true)]
+    #[test_case("<=1.2", "1.3.0", false)]
+    #[test_case(">= 1.0.0", "1.0.0", true ; "space after operator")]
+    #[test_case("^1.2", "1.3.0-beta.1", false ; "prerelease is excluded by default")]
+    #[test_case("*", "1.0.0-rc.1", false ; "prerelease is excluded by wildcard")]
+    #[test_case(">=1.2.0-beta", "1.2.0-beta.2", true ; "prerelease of the same version")]
+    #[test_case(">=1.2.0-beta", "1.2.0", true ; "release after prerelease")]
+    #[test_case(">=1.2.0-beta", "1.3.0-alpha", false ; "prerelease of a different version")]
+    fn version_req_matches(req: &str, version: &str, expect: bool) -> Result<()> {
+        let req = VersionReq::from_str(req)?;
+        let version = Version::from_str(version)?;
+        assert_eq!(req.matches(&version), expect);
+
+        Ok(())
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case(">=" ; "operator without version")]
+    #[test_case("^1.2.3.4" ; "too many parts")]
+    #[test_case("^a.b" ; "not numbers")]
+    #[test_case("1.*.3" ; "number after wildcard")]
+    #[test_case("^1.2-beta" ; "prerelease without patch")]
+    #[test_case(">=0.4,, <0.6" ; "empty comparator")]
+    fn invalid_version_req(req: &str) {
+        assert!(VersionReq::from_str(req).is_err());
+    }
+
+    #[test_case(&["v1.0.0", "v1.2.3", "v1.10.0", "v2.0.0"], "^1", false, Some("v1.10.0") ; "highest in major")]
+    #[test_case(&["v1.0.0", "v1.2.3", "v2.0.0-rc.1"], "*", false, Some("v1.2.3") ; "skips prerelease")]
+    #[test_case(&["v1.0.0", "v1.2.3", "v2.0.0-rc.1"], "*", true, Some("v2.0.0-rc.1") ; "includes prerelease")]
+    #[test_case(&["v1.2.3", "v1.3.0-beta.1", "v2.0.0-rc.1"], "^1.2", true, Some("v1.3.0-beta.1") ; "includes prerelease in range")]
+    #[test_case(&["v1.3.0-beta.1", "v1.3.0"], "^1.2", true, Some("v1.3.0") ; "release beats its prerelease")]
+    #[test_case(&["v0.4.1", "v0.5.2", "v0.6.0", "0.5.10"], ">=0.4, <0.6", false, Some("0.5.10") ; "range")]
+    #[test_case(&["nightly", "v1.2.3", "latest"], "^1", false, Some("v1.2.3") ; "ignores non-version tags")]
+    #[test_case(&["v1.0.0", "v2.0.0"], "^3", false, None ; "no match")]
+    fn highest_matching_tag(
+        tags: &[&str],
+        req: &str,
+        include_prereleases: bool,
+        expect: Option<&str>,
+    ) -> Result<()> {
+        let tags = tags.iter().map(|t| (*t).to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            super::highest_matching_tag(&tags, &VersionReq::from_str(req)?, include_prereleases),
+            expect,
+        );
+
+        Ok(())
+    }
+}